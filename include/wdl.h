@@ -0,0 +1,66 @@
+#ifndef WDL_H
+#define WDL_H
+
+/* Generated by cbindgen from src/capi.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * An opaque handle to a parsed document.
+ */
+typedef struct WdlDocument WdlDocument;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Parses the WDL source `text`. On success, returns a document that must be freed with
+ * `wdl_free`. On failure, returns null and, if `error` is not null, sets `*error` to an error
+ * message that must be freed with `wdl_string_free`.
+ *
+ * # Safety
+ *
+ * `text` must be a valid pointer to a NUL-terminated string, and `error` must be null or a valid
+ * pointer to a `char *`.
+ */
+WdlDocument *wdl_parse(const char *text, char **error);
+
+/**
+ * Serializes `document` to JSON. On success, returns a string that must be freed with
+ * `wdl_string_free`. On failure, returns null and, if `error` is not null, sets `*error` to an
+ * error message that must be freed with `wdl_string_free`.
+ *
+ * # Safety
+ *
+ * `document` must be a pointer returned by `wdl_parse` that has not been freed, and `error` must
+ * be null or a valid pointer to a `char *`.
+ */
+char *wdl_document_to_json(const WdlDocument *document, char **error);
+
+/**
+ * Frees a document returned by `wdl_parse`. Does nothing if `document` is null.
+ *
+ * # Safety
+ *
+ * `document` must be null or a pointer returned by `wdl_parse` that has not been freed.
+ */
+void wdl_free(WdlDocument *document);
+
+/**
+ * Frees a string returned by this API. Does nothing if `s` is null.
+ *
+ * # Safety
+ *
+ * `s` must be null or a string returned by this API that has not been freed.
+ */
+void wdl_string_free(char *s);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* WDL_H */
@@ -0,0 +1,301 @@
+use crate::{
+    analysis::resources::task_environment,
+    eval::Environment,
+    model::{Document, DocumentElement, RuntimeKey, Span},
+    values::WdlValue,
+};
+use error_stack::{ensure, Report};
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    str::FromStr,
+};
+use thiserror::Error;
+
+pub const DEFAULT_REGISTRY: &str = "docker.io";
+pub const DEFAULT_TAG: &str = "latest";
+
+#[derive(Error, Debug)]
+#[error("Invalid container image reference {0}")]
+pub struct ImageReferenceError(pub String);
+
+/// A container image reference, normalized so that references to the same image compare equal,
+/// e.g. `ubuntu` and `docker.io/library/ubuntu:latest`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ImageReference {
+    /// The registry host (and optional port), which defaults to `docker.io`.
+    pub registry: String,
+    /// The repository path. Single-component repositories on Docker Hub are prefixed with
+    /// `library/`.
+    pub repository: String,
+    /// The tag, which defaults to `latest` unless the reference has a digest.
+    pub tag: Option<String>,
+    /// The digest, e.g. `sha256:...`.
+    pub digest: Option<String>,
+}
+
+impl ImageReference {
+    /// Returns whether the reference pins the image to a specific digest.
+    pub fn is_pinned(&self) -> bool {
+        self.digest.is_some()
+    }
+}
+
+impl FromStr for ImageReference {
+    type Err = Report<ImageReferenceError>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ImageReferenceError(s.to_owned());
+        let reference = s.trim();
+        ensure!(
+            !reference.is_empty() && !reference.contains(char::is_whitespace),
+            invalid()
+        );
+        let (name, digest) = match reference.split_once('@') {
+            Some((name, digest)) => {
+                ensure!(
+                    matches!(digest.split_once(':'), Some((algorithm, hex))
+                        if !algorithm.is_empty()
+                            && !hex.is_empty()
+                            && hex.chars().all(|c| c.is_ascii_hexdigit())),
+                    invalid()
+                );
+                (name, Some(digest.to_owned()))
+            }
+            None => (reference, None),
+        };
+        // a tag follows the last colon, unless that colon separates a registry host and port
+        let (name, tag) = match name.rsplit_once(':') {
+            Some((rest, tag)) if !tag.contains('/') => (rest, Some(tag.to_owned())),
+            _ => (name, None),
+        };
+        let (registry, repository) = match name.split_once('/') {
+            Some((host, path))
+                if host.contains('.') || host.contains(':') || host == "localhost" =>
+            {
+                (host.to_owned(), path.to_owned())
+            }
+            _ => (DEFAULT_REGISTRY.to_owned(), name.to_owned()),
+        };
+        ensure!(
+            !repository.is_empty()
+                && repository.split('/').all(|part| !part.is_empty())
+                && tag.as_deref() != Some(""),
+            invalid()
+        );
+        let repository = if registry == DEFAULT_REGISTRY && !repository.contains('/') {
+            format!("library/{}", repository)
+        } else {
+            repository
+        };
+        let tag = match (tag, &digest) {
+            (None, None) => Some(DEFAULT_TAG.to_owned()),
+            (tag, _) => tag,
+        };
+        Ok(Self {
+            registry,
+            repository,
+            tag,
+            digest,
+        })
+    }
+}
+
+impl Display for ImageReference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}/{}", self.registry, self.repository)?;
+        if let Some(tag) = &self.tag {
+            write!(f, ":{}", tag)?;
+        }
+        if let Some(digest) = &self.digest {
+            write!(f, "@{}", digest)?;
+        }
+        Ok(())
+    }
+}
+
+/// A container image that is referenced by the runtime section of a task.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContainerImage {
+    pub task: String,
+    /// The span of the `container` (or `docker`) runtime attribute.
+    pub span: Span,
+    /// The image as given in the document, or computed from the defaults of the task's inputs.
+    pub image: String,
+    /// The normalized reference, or `None` if the image is not a valid reference.
+    pub reference: Option<ImageReference>,
+}
+
+/// The container images that are used by the tasks in a document.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ContainerInventory {
+    /// The images, in the order in which they appear. A task that specifies multiple images
+    /// contributes one entry for each.
+    pub images: Vec<ContainerImage>,
+    /// The tasks whose container cannot be determined statically, with the spans of their
+    /// container attributes.
+    pub dynamic: Vec<(String, Span)>,
+    /// The tasks that do not specify a container.
+    pub missing: Vec<String>,
+}
+
+impl ContainerInventory {
+    /// Returns the distinct normalized references, sorted.
+    pub fn references(&self) -> Vec<&ImageReference> {
+        let mut references: Vec<&ImageReference> = self
+            .images
+            .iter()
+            .filter_map(|image| image.reference.as_ref())
+            .collect();
+        references.sort_by_key(|reference| reference.to_string());
+        references.dedup();
+        references
+    }
+}
+
+/// Returns the container images that are referenced by the tasks in `document`. An image that
+/// is computed from an expression (e.g. `"ubuntu:~{release}"`) is included if the expression can
+/// be evaluated with the task's inputs bound to their defaults.
+pub fn container_inventory(document: &Document) -> ContainerInventory {
+    let mut inventory = ContainerInventory::default();
+    for element in document.body_iter() {
+        let task = match element {
+            DocumentElement::Task(task) => task,
+            _ => continue,
+        };
+        let name = task.name.as_str().to_owned();
+        let (runtime, attribute) = match task
+            .runtime()
+            .and_then(|runtime| Some((runtime, runtime.get(&RuntimeKey::Container)?)))
+        {
+            Some(found) => found,
+            None => {
+                inventory.missing.push(name);
+                continue;
+            }
+        };
+        let env = task_environment(task, &Environment::new());
+        let images = match runtime.value_in(&RuntimeKey::Container, &env) {
+            Some(WdlValue::String(image)) => Some(vec![image]),
+            Some(WdlValue::Array(images)) => images
+                .into_iter()
+                .map(|image| match image {
+                    WdlValue::String(image) => Some(image),
+                    _ => None,
+                })
+                .collect(),
+            _ => None,
+        };
+        match images {
+            Some(images) => {
+                inventory
+                    .images
+                    .extend(images.into_iter().map(|image| ContainerImage {
+                        task: name.clone(),
+                        span: attribute.span.clone(),
+                        reference: image.parse().ok(),
+                        image,
+                    }))
+            }
+            None => inventory.dynamic.push((name, attribute.span.clone())),
+        }
+    }
+    inventory
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_image_reference() {
+        let normalized = |s: &str| s.parse::<ImageReference>().unwrap().to_string();
+        assert_eq!(normalized("ubuntu"), "docker.io/library/ubuntu:latest");
+        assert_eq!(
+            normalized("broadinstitute/gatk:4.2.0.0"),
+            "docker.io/broadinstitute/gatk:4.2.0.0"
+        );
+        assert_eq!(
+            normalized("localhost:5000/tools/bwa"),
+            "localhost:5000/tools/bwa:latest"
+        );
+        assert_eq!(
+            normalized("quay.io/biocontainers/samtools:1.15--h1170115_1"),
+            "quay.io/biocontainers/samtools:1.15--h1170115_1"
+        );
+        let pinned: ImageReference = "ubuntu@sha256:abc123".parse().unwrap();
+        assert_eq!(pinned.tag, None);
+        assert!(pinned.is_pinned());
+        assert!("ubuntu@sha256:xyz".parse::<ImageReference>().is_err());
+        assert!("ubuntu:".parse::<ImageReference>().is_err());
+        assert!("".parse::<ImageReference>().is_err());
+    }
+
+    #[cfg(feature = "pest")]
+    #[test]
+    fn test_container_inventory() {
+        use crate::{
+            model::DocumentSource,
+            parsers::{PestParser, WdlParser},
+        };
+
+        let text = r#"version 1.1
+
+task a {
+    input {
+        String release = "22.04"
+    }
+    command <<< >>>
+    runtime {
+        container: "ubuntu:~{release}"
+    }
+}
+
+task b {
+    input {
+        String image
+    }
+    command <<< >>>
+    runtime {
+        docker: image
+    }
+}
+
+task c {
+    command <<< >>>
+    runtime {
+        container: ["ubuntu:22.04", "docker.io/library/ubuntu:22.04"]
+    }
+}
+
+task d {
+    command <<< >>>
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let inventory = container_inventory(&doc);
+        let images: Vec<(&str, &str)> = inventory
+            .images
+            .iter()
+            .map(|image| (image.task.as_str(), image.image.as_str()))
+            .collect();
+        assert_eq!(
+            images,
+            vec![
+                ("a", "ubuntu:22.04"),
+                ("c", "ubuntu:22.04"),
+                ("c", "docker.io/library/ubuntu:22.04")
+            ]
+        );
+        assert_eq!(inventory.references().len(), 1);
+        let dynamic: Vec<&str> = inventory
+            .dynamic
+            .iter()
+            .map(|(task, _)| task.as_str())
+            .collect();
+        assert_eq!(dynamic, vec!["b"]);
+        assert_eq!(inventory.missing, vec!["d".to_owned()]);
+    }
+}
@@ -0,0 +1,26 @@
+//! Static analyses of WDL documents.
+mod containers;
+mod nesting;
+mod outputs;
+mod references;
+mod resources;
+mod scatters;
+mod scopes;
+mod versions;
+mod wiring;
+
+pub use containers::{
+    container_inventory, ContainerImage, ContainerInventory, ImageReference, ImageReferenceError,
+};
+pub use nesting::{effective_output_types, Block, EffectiveType};
+pub use outputs::{output_wiring, OutputDerivation, OutputSource, OutputWiring};
+pub use references::{identifiers, referenced_identifiers, Identifier, IdentifierKind};
+pub(crate) use references::{walk_document, walk_expression};
+pub use resources::{
+    resources, task_resources, task_resources_in, CallResources, ResourceSummary, TaskResources,
+};
+pub use scatters::{estimate_jobs, CallJobs, JobEstimate, ScatterWidth};
+pub use scopes::{resolve_scopes, Reference, Scopes, Symbol, SymbolKind};
+pub use versions::{version_requirements, VersionFeature, VersionRequirement};
+pub(crate) use wiring::{call_scopes, callee_inputs, is_coercible};
+pub use wiring::{suggest_input_wiring, InputBinding, WiringSuggestion};
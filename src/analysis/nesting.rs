@@ -0,0 +1,245 @@
+use crate::{
+    fqn::{self, FqnKind, FullyQualifiedName},
+    model::{
+        Anchor, Call, Document, TaskElement, Type, Workflow, WorkflowElement, WorkflowNestedElement,
+    },
+};
+
+/// A block that encloses an element of a workflow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Block {
+    Scatter,
+    Conditional,
+}
+
+/// The type of a call output or declaration that is nested within one or more scatter and/or
+/// conditional blocks, as seen from outside its enclosing blocks.
+#[derive(Debug, PartialEq)]
+pub struct EffectiveType {
+    /// The fully-qualified name of the element.
+    pub name: FullyQualifiedName,
+    /// The enclosing blocks, from outermost to innermost.
+    pub blocks: Vec<Block>,
+    /// The type with which the element was declared.
+    pub declared: Anchor<Type>,
+    /// The type of the element when referenced from the top level of the workflow.
+    pub effective: Type,
+}
+
+impl EffectiveType {
+    /// Returns the name by which the element is referenced from outside its enclosing blocks,
+    /// i.e. `call.output` for a call output or the declaration name otherwise.
+    pub fn reference(&self) -> String {
+        let n = match self.name.kind {
+            FqnKind::CallOutput => 2,
+            _ => 1,
+        };
+        self.name.parts[self.name.parts.len() - n..].join(".")
+    }
+}
+
+/// Computes the effective types of the call outputs and declarations that are nested within
+/// scatter and/or conditional blocks in `workflow`. Per the WDL spec, a value of type `T` that is
+/// declared within a scatter has type `Array[T]` outside the scatter, and a value declared within
+/// a conditional has type `T?` outside the conditional (or `T` if `T` is already optional).
+/// Blocks are applied from innermost to outermost, so e.g. a call output of type `Int` in a
+/// conditional nested within a scatter has type `Array[Int?]`.
+///
+/// Only the outputs of calls to tasks that are defined in `document` are included. Elements
+/// declared at the top level of the workflow are not included, since their types are unchanged.
+pub fn effective_output_types(
+    document: &Document,
+    workflow: &Workflow,
+) -> Vec<Anchor<EffectiveType>> {
+    let mut analyzer = Analyzer {
+        document,
+        scatters: 0,
+        conditionals: 0,
+        blocks: Vec::new(),
+        types: Vec::new(),
+    };
+    let root = FullyQualifiedName::workflow(workflow.name.as_str());
+    for element in workflow.body.iter() {
+        match &**element {
+            WorkflowElement::Scatter(scatter) => {
+                analyzer.add_block(&root, Block::Scatter, &scatter.body)
+            }
+            WorkflowElement::Conditional(conditional) => {
+                analyzer.add_block(&root, Block::Conditional, &conditional.body)
+            }
+            _ => (),
+        }
+    }
+    analyzer.types
+}
+
+/// Wraps `type_` in the types implied by `blocks`, from innermost to outermost.
+pub(crate) fn promote(type_: &Anchor<Type>, blocks: &[Block]) -> Type {
+    let span = type_.span.clone();
+    blocks
+        .iter()
+        .rev()
+        .fold((**type_).clone(), |inner, block| match (block, inner) {
+            (Block::Scatter, inner) => Type::Array {
+                item: Box::new(Anchor::new(inner, span.clone())),
+                non_empty: false,
+            },
+            (Block::Conditional, Type::Optional(inner)) => Type::Optional(inner),
+            (Block::Conditional, inner) => {
+                Type::Optional(Box::new(Anchor::new(inner, span.clone())))
+            }
+        })
+}
+
+struct Analyzer<'a> {
+    document: &'a Document,
+    scatters: usize,
+    conditionals: usize,
+    blocks: Vec<Block>,
+    types: Vec<Anchor<EffectiveType>>,
+}
+
+impl<'a> Analyzer<'a> {
+    fn add(&mut self, name: FullyQualifiedName, declared: &Anchor<Type>) {
+        let effective = promote(declared, &self.blocks);
+        let span = declared.span.clone();
+        self.types.push(Anchor::new(
+            EffectiveType {
+                name,
+                blocks: self.blocks.clone(),
+                declared: declared.clone(),
+                effective,
+            },
+            span,
+        ))
+    }
+
+    fn add_call(&mut self, parent: &FullyQualifiedName, call: &Call) {
+        if let Some(task) = fqn::find_local_task(self.document, call) {
+            let call_fqn = parent.child(FqnKind::Call, fqn::call_name(call));
+            for element in task.body.iter() {
+                if let TaskElement::Output(output) = &**element {
+                    for decl in output.declarations.iter() {
+                        self.add(
+                            call_fqn.child(FqnKind::CallOutput, decl.name.as_str()),
+                            &decl.type_,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn add_block(
+        &mut self,
+        parent: &FullyQualifiedName,
+        block: Block,
+        body: &[Anchor<WorkflowNestedElement>],
+    ) {
+        let block_fqn = match block {
+            Block::Scatter => {
+                self.scatters += 1;
+                parent.child(FqnKind::Scatter, fqn::scatter_name(self.scatters - 1))
+            }
+            Block::Conditional => {
+                self.conditionals += 1;
+                parent.child(
+                    FqnKind::Conditional,
+                    fqn::conditional_name(self.conditionals - 1),
+                )
+            }
+        };
+        self.blocks.push(block);
+        for element in body.iter() {
+            match &**element {
+                WorkflowNestedElement::Declaration(decl) => self.add(
+                    block_fqn.child(FqnKind::Declaration, decl.name.as_str()),
+                    &decl.type_,
+                ),
+                WorkflowNestedElement::Call(call) => self.add_call(&block_fqn, call),
+                WorkflowNestedElement::Scatter(scatter) => {
+                    self.add_block(&block_fqn, Block::Scatter, &scatter.body)
+                }
+                WorkflowNestedElement::Conditional(conditional) => {
+                    self.add_block(&block_fqn, Block::Conditional, &conditional.body)
+                }
+            }
+        }
+        self.blocks.pop();
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::{DocumentElement, DocumentSource},
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_effective_output_types() {
+        let text = r#"
+version 1.1
+
+task t {
+    command <<< >>>
+    output {
+        Int y = 1
+        String? z = "z"
+    }
+}
+
+workflow wf {
+    call t as top
+    scatter (i in range(3)) {
+        if (i > 0) {
+            call t as x
+        }
+        Int j = i
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let workflow = match doc.get_primary_element() {
+            Some(DocumentElement::Workflow(workflow)) => workflow,
+            _ => panic!("expected a workflow"),
+        };
+        fn render(type_: &Type) -> String {
+            match type_ {
+                Type::Int => "Int".to_owned(),
+                Type::String => "String".to_owned(),
+                Type::Array { item, .. } => format!("Array[{}]", render(item)),
+                Type::Optional(inner) => format!("{}?", render(inner)),
+                _ => panic!("unexpected type"),
+            }
+        }
+        let types: Vec<(String, String, Vec<Block>)> = effective_output_types(&doc, workflow)
+            .iter()
+            .map(|t| (t.reference(), render(&t.effective), t.blocks.clone()))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                (
+                    "x.y".to_owned(),
+                    "Array[Int?]".to_owned(),
+                    vec![Block::Scatter, Block::Conditional]
+                ),
+                (
+                    "x.z".to_owned(),
+                    "Array[String?]".to_owned(),
+                    vec![Block::Scatter, Block::Conditional]
+                ),
+                (
+                    "j".to_owned(),
+                    "Array[Int]".to_owned(),
+                    vec![Block::Scatter]
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,364 @@
+use crate::{
+    fqn,
+    model::{
+        AccessOperation, Anchor, BoundDeclaration, Call, Expression, Span, StringPart, Type,
+        Workflow, WorkflowElement, WorkflowNestedElement,
+    },
+};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Deref,
+};
+
+/// How the value of a workflow output is derived from a call output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OutputDerivation {
+    /// The workflow output is the call output (or a field of it), e.g. `File bam = align.bam`.
+    Direct,
+    /// The call output is one of the values selected by `select_first` or `select_all`, e.g.
+    /// `File bam = select_first([dedup.bam, align.bam])`.
+    Selected,
+    /// The call output is transformed by an operator or another function, or is part of a
+    /// larger value, e.g. `Int total = a.count + b.count`.
+    Computed,
+}
+
+/// A call output from which a workflow output draws.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputSource {
+    /// The name by which the call is referenced in the workflow.
+    pub call: String,
+    /// The target of the call, i.e. the (possibly namespaced) name of a task or workflow.
+    pub target: String,
+    /// The name of the output of the target.
+    pub output: String,
+    /// The fields of the output that are accessed, e.g. `["left"]` for `call.output.left`.
+    pub fields: Vec<String>,
+    pub derivation: OutputDerivation,
+    /// The span of the reference to the call output, which is within the declaration of the
+    /// workflow output or of a declaration that it references.
+    pub span: Span,
+}
+
+/// The provenance of an output of a workflow.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputWiring {
+    pub name: String,
+    pub type_: Type,
+    /// The span of the declaration of the output.
+    pub span: Span,
+    /// The call outputs from which the output draws, in the order in which they are referenced.
+    pub sources: Vec<OutputSource>,
+    /// The workflow inputs from which the output draws.
+    pub inputs: Vec<String>,
+}
+
+impl OutputWiring {
+    /// Returns the least direct derivation of the output from its sources, or `None` if it has
+    /// no sources.
+    pub fn derivation(&self) -> Option<OutputDerivation> {
+        self.sources.iter().map(|source| source.derivation).max()
+    }
+}
+
+struct Resolver<'a> {
+    calls: HashMap<&'a str, &'a Call>,
+    declarations: HashMap<&'a str, &'a BoundDeclaration>,
+    inputs: HashSet<&'a str>,
+}
+
+impl<'a> Resolver<'a> {
+    fn nested(&mut self, body: &'a [Anchor<WorkflowNestedElement>]) {
+        for element in body.iter() {
+            match element.deref() {
+                WorkflowNestedElement::Declaration(decl) => {
+                    self.declarations.insert(decl.name.as_str(), decl);
+                }
+                WorkflowNestedElement::Call(call) => {
+                    self.calls.insert(fqn::call_name(call), call);
+                }
+                WorkflowNestedElement::Scatter(scatter) => self.nested(&scatter.body),
+                WorkflowNestedElement::Conditional(conditional) => self.nested(&conditional.body),
+            }
+        }
+    }
+
+    fn anchor(
+        &self,
+        expression: &'a Anchor<Expression>,
+        derivation: OutputDerivation,
+        visited: &mut Vec<&'a str>,
+        wiring: &mut OutputWiring,
+    ) {
+        self.expression(expression, &expression.span, derivation, visited, wiring)
+    }
+
+    /// Adds the sources of `expression` to `wiring`, where `derivation` is how the value of
+    /// `expression` is used by the output. Declarations are resolved to their expressions, except
+    /// for those in `visited`.
+    fn expression(
+        &self,
+        expression: &'a Expression,
+        span: &'a Span,
+        derivation: OutputDerivation,
+        visited: &mut Vec<&'a str>,
+        wiring: &mut OutputWiring,
+    ) {
+        let computed = OutputDerivation::Computed;
+        match expression {
+            Expression::Identifier(name) => {
+                if let Some(decl) = self.declarations.get(name.as_str()) {
+                    if !visited.contains(&name.as_str()) {
+                        visited.push(name.as_str());
+                        self.anchor(&decl.expression, derivation, visited, wiring);
+                        visited.pop();
+                    }
+                } else if self.inputs.contains(name.as_str())
+                    && !wiring.inputs.iter().any(|input| input == name)
+                {
+                    wiring.inputs.push(name.clone())
+                }
+            }
+            Expression::Access(access) => {
+                let call = match &**access.collection {
+                    Expression::Identifier(name) => self.calls.get(name.as_str()),
+                    _ => None,
+                };
+                let fields: Vec<&str> = access
+                    .accesses
+                    .iter()
+                    .map_while(|operation| match operation.deref() {
+                        AccessOperation::Field(field) => Some(field.as_str()),
+                        AccessOperation::Index(_) => None,
+                    })
+                    .collect();
+                match (call, fields.split_first()) {
+                    (Some(call), Some((output, fields))) => {
+                        // an index of the output (e.g. `call.out[0]`) is a computation
+                        let derivation = if fields.len() + 1 < access.accesses.len() {
+                            computed
+                        } else {
+                            derivation
+                        };
+                        wiring.sources.push(OutputSource {
+                            call: fqn::call_name(call).to_owned(),
+                            target: call
+                                .target
+                                .parts
+                                .iter()
+                                .map(|part| part.as_str())
+                                .collect::<Vec<_>>()
+                                .join("."),
+                            output: (*output).to_owned(),
+                            fields: fields.iter().map(|field| (*field).to_owned()).collect(),
+                            derivation,
+                            span: span.clone(),
+                        })
+                    }
+                    _ => self.anchor(&access.collection, computed, visited, wiring),
+                }
+                for operation in access.accesses.iter() {
+                    if let AccessOperation::Index(index) = operation.deref() {
+                        self.expression(index, &operation.span, computed, visited, wiring)
+                    }
+                }
+            }
+            Expression::Apply(apply) => {
+                let derivation = match apply.name.as_str() {
+                    "select_first" | "select_all" => derivation.max(OutputDerivation::Selected),
+                    _ => computed,
+                };
+                for argument in apply.arguments.iter() {
+                    match argument.deref() {
+                        // the values that are selected are the elements of an array literal
+                        Expression::Array(array) if derivation == OutputDerivation::Selected => {
+                            for element in array.elements.iter() {
+                                self.anchor(element, derivation, visited, wiring)
+                            }
+                        }
+                        _ => self.anchor(argument, derivation, visited, wiring),
+                    }
+                }
+            }
+            Expression::Group(group) => self.anchor(group, derivation, visited, wiring),
+            // any other sub-expression is part of a computed value
+            Expression::String(string) => {
+                for part in string.parts.iter() {
+                    if let StringPart::Placeholder(placeholder, _) = part.deref() {
+                        self.expression(placeholder, &part.span, computed, visited, wiring)
+                    }
+                }
+            }
+            Expression::Array(array) => {
+                for element in array.elements.iter() {
+                    self.anchor(element, computed, visited, wiring)
+                }
+            }
+            Expression::Map(map) => {
+                for entry in map.entries.iter() {
+                    self.anchor(&entry.key, computed, visited, wiring);
+                    self.anchor(&entry.value, computed, visited, wiring);
+                }
+            }
+            Expression::Pair(pair) => {
+                self.anchor(&pair.left, computed, visited, wiring);
+                self.anchor(&pair.right, computed, visited, wiring);
+            }
+            Expression::Object(object) => {
+                for field in object.fields.iter() {
+                    self.anchor(&field.expression, computed, visited, wiring)
+                }
+            }
+            Expression::Unary(unary) => self.anchor(&unary.expression, computed, visited, wiring),
+            Expression::Binary(binary) => {
+                self.anchor(&binary.left, computed, visited, wiring);
+                self.anchor(&binary.right, computed, visited, wiring);
+            }
+            Expression::Ternary(ternary) => {
+                self.anchor(&ternary.condition, computed, visited, wiring);
+                self.anchor(&ternary.true_branch, computed, visited, wiring);
+                self.anchor(&ternary.false_branch, computed, visited, wiring);
+            }
+            Expression::None
+            | Expression::Boolean(_)
+            | Expression::Int(_)
+            | Expression::Float(_) => (),
+        }
+    }
+}
+
+/// Resolves, for each output of `workflow`, the call outputs from which it draws, i.e. the call
+/// outputs that it references directly or via the private declarations of the workflow that it
+/// references, e.g. for display as the provenance of the output. Each source records how the
+/// output is derived from it, e.g. whether it is one of the values of a `select_first`.
+///
+/// Calls are matched by name, so references to calls are resolved wherever the calls are nested
+/// within scatter or conditional blocks. Returns an empty `Vec` if the workflow has no outputs.
+pub fn output_wiring(workflow: &Workflow) -> Vec<OutputWiring> {
+    let mut resolver = Resolver {
+        calls: HashMap::new(),
+        declarations: HashMap::new(),
+        inputs: HashSet::new(),
+    };
+    for element in workflow.body.iter() {
+        match element.deref() {
+            WorkflowElement::Input(input) => resolver
+                .inputs
+                .extend(input.declarations.iter().map(|decl| decl.name().as_str())),
+            WorkflowElement::Declaration(decl) => {
+                resolver.declarations.insert(decl.name.as_str(), decl);
+            }
+            WorkflowElement::Call(call) => {
+                resolver.calls.insert(fqn::call_name(call), call);
+            }
+            WorkflowElement::Scatter(scatter) => resolver.nested(&scatter.body),
+            WorkflowElement::Conditional(conditional) => resolver.nested(&conditional.body),
+            _ => (),
+        }
+    }
+    workflow
+        .outputs()
+        .into_iter()
+        .flat_map(|output| output.declarations.iter())
+        .map(|decl| {
+            let mut wiring = OutputWiring {
+                name: decl.name.as_str().to_owned(),
+                type_: (*decl.type_).clone(),
+                span: decl.span.clone(),
+                sources: Vec::new(),
+                inputs: Vec::new(),
+            };
+            resolver.expression(
+                &decl.expression,
+                &decl.expression.span,
+                OutputDerivation::Direct,
+                &mut Vec::new(),
+                &mut wiring,
+            );
+            wiring
+        })
+        .collect()
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::{DocumentElement, DocumentSource},
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_output_wiring() {
+        let text = r#"version 1.1
+
+workflow w {
+    input {
+        Array[File] reads
+        Boolean dedup
+    }
+    scatter (r in reads) {
+        call lib.align as align { input: r }
+        if (dedup) {
+            call markdup { input: bam = align.bam }
+        }
+    }
+    Array[File] bams = align.bam
+    output {
+        Array[File] aligned = bams
+        Array[File] final = select_all(markdup.bam)
+        File first = select_first([markdup.bam, align.bam])[0]
+        Int total = length(align.stats.reads) + length(reads)
+        String label = "~{dedup}"
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let workflow = match doc.body_iter().next().unwrap() {
+            DocumentElement::Workflow(workflow) => workflow,
+            _ => unreachable!(),
+        };
+        let wiring = output_wiring(workflow);
+        // each source as `call (target).output.fields derivation`
+        let sources: Vec<Vec<String>> = wiring
+            .iter()
+            .map(|wiring| {
+                wiring
+                    .sources
+                    .iter()
+                    .map(|source| {
+                        let mut path = vec![source.output.as_str()];
+                        path.extend(source.fields.iter().map(|field| field.as_str()));
+                        format!(
+                            "{} ({}).{} {:?}",
+                            source.call,
+                            source.target,
+                            path.join("."),
+                            source.derivation
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            sources,
+            vec![
+                vec!["align (lib.align).bam Direct"],
+                vec!["markdup (markdup).bam Selected"],
+                vec![
+                    "markdup (markdup).bam Computed",
+                    "align (lib.align).bam Computed"
+                ],
+                vec!["align (lib.align).stats.reads Computed"],
+                vec![],
+            ]
+        );
+        assert_eq!(wiring[1].derivation(), Some(OutputDerivation::Selected));
+        assert_eq!(wiring[3].inputs, vec!["reads"]);
+        assert_eq!(wiring[4].inputs, vec!["dedup"]);
+        assert_eq!(wiring[4].derivation(), None);
+    }
+}
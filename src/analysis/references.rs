@@ -0,0 +1,322 @@
+use crate::{
+    analysis::scopes::{resolve_scopes, Symbol, SymbolKind},
+    model::{
+        AccessOperation, Anchor, Document, DocumentElement, Expression, InputDeclaration, Span,
+        StringPart, TaskElement, WorkflowElement, WorkflowNestedElement,
+    },
+};
+use std::{collections::BTreeMap, ops::Deref};
+
+/// Calls `f` with `expression` and each of its sub-expressions, in depth-first, left-to-right
+/// order. Some sub-expressions (string placeholders and index operations) are not anchored, so
+/// `f` is also called with the span of the closest anchored element that contains each
+/// expression.
+pub(crate) fn walk_expression<'a, F>(expression: &'a Expression, span: &'a Span, f: &mut F)
+where
+    F: FnMut(&'a Expression, &'a Span),
+{
+    f(expression, span);
+    let walk_anchor = |anchor: &'a Anchor<Expression>, f: &mut F| {
+        walk_expression(anchor.deref(), &anchor.span, f)
+    };
+    match expression {
+        Expression::String(s) => {
+            for part in s.parts.iter() {
+                if let StringPart::Placeholder(placeholder, _) = part.deref() {
+                    walk_expression(placeholder, &part.span, f)
+                }
+            }
+        }
+        Expression::Array(a) => {
+            for element in a.elements.iter() {
+                walk_anchor(element, f)
+            }
+        }
+        Expression::Map(m) => {
+            for entry in m.entries.iter() {
+                walk_anchor(&entry.key, f);
+                walk_anchor(&entry.value, f);
+            }
+        }
+        Expression::Pair(p) => {
+            walk_anchor(&p.left, f);
+            walk_anchor(&p.right, f);
+        }
+        Expression::Object(o) => {
+            for field in o.fields.iter() {
+                walk_anchor(&field.expression, f)
+            }
+        }
+        Expression::Unary(u) => walk_anchor(&u.expression, f),
+        Expression::Binary(b) => {
+            walk_anchor(&b.left, f);
+            walk_anchor(&b.right, f);
+        }
+        Expression::Apply(a) => {
+            for argument in a.arguments.iter() {
+                walk_anchor(argument, f)
+            }
+        }
+        Expression::Access(a) => {
+            walk_anchor(&a.collection, f);
+            for access in a.accesses.iter() {
+                if let AccessOperation::Index(index) = access.deref() {
+                    walk_expression(index, &access.span, f)
+                }
+            }
+        }
+        Expression::Ternary(t) => {
+            walk_anchor(&t.condition, f);
+            walk_anchor(&t.true_branch, f);
+            walk_anchor(&t.false_branch, f);
+        }
+        Expression::Group(g) => walk_anchor(g, f),
+        _ => (),
+    }
+}
+
+/// Calls `f` with each expression in `document` and each of their sub-expressions, as in
+/// `walk_expression`, in document order.
+pub(crate) fn walk_document<'a, F>(document: &'a Document, f: &mut F)
+where
+    F: FnMut(&'a Expression, &'a Span),
+{
+    let walk_anchor = |anchor: &'a Anchor<Expression>, f: &mut F| {
+        walk_expression(anchor.deref(), &anchor.span, f)
+    };
+    for element in document.body_iter() {
+        match element {
+            DocumentElement::Task(task) => {
+                for element in task.body.iter() {
+                    match element.deref() {
+                        TaskElement::Input(input) => {
+                            for decl in input.declarations.iter() {
+                                if let InputDeclaration::Bound(decl) = decl.deref() {
+                                    walk_anchor(&decl.expression, f)
+                                }
+                            }
+                        }
+                        TaskElement::Declaration(decl) => walk_anchor(&decl.expression, f),
+                        TaskElement::Output(output) => {
+                            for decl in output.declarations.iter() {
+                                walk_anchor(&decl.expression, f)
+                            }
+                        }
+                        TaskElement::Command(command) => {
+                            for part in command.parts.iter() {
+                                if let StringPart::Placeholder(expression, _) = part.deref() {
+                                    walk_expression(expression, &part.span, f)
+                                }
+                            }
+                        }
+                        TaskElement::Runtime(runtime) => {
+                            for attribute in runtime.attributes.iter() {
+                                walk_anchor(&attribute.expression, f)
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            DocumentElement::Workflow(workflow) => {
+                for element in workflow.body.iter() {
+                    match element.deref() {
+                        WorkflowElement::Input(input) => {
+                            for decl in input.declarations.iter() {
+                                if let InputDeclaration::Bound(decl) = decl.deref() {
+                                    walk_anchor(&decl.expression, f)
+                                }
+                            }
+                        }
+                        WorkflowElement::Output(output) => {
+                            for decl in output.declarations.iter() {
+                                walk_anchor(&decl.expression, f)
+                            }
+                        }
+                        WorkflowElement::Declaration(decl) => walk_anchor(&decl.expression, f),
+                        WorkflowElement::Call(call) => {
+                            for input in call.inputs.iter().flatten() {
+                                if let Some(expression) = &input.expression {
+                                    walk_anchor(expression, f)
+                                }
+                            }
+                        }
+                        WorkflowElement::Scatter(scatter) => {
+                            walk_anchor(&scatter.expression, f);
+                            walk_nested(&scatter.body, f);
+                        }
+                        WorkflowElement::Conditional(conditional) => {
+                            walk_anchor(&conditional.expression, f);
+                            walk_nested(&conditional.body, f);
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn walk_nested<'a, F>(body: &'a [Anchor<WorkflowNestedElement>], f: &mut F)
+where
+    F: FnMut(&'a Expression, &'a Span),
+{
+    for element in body.iter() {
+        match element.deref() {
+            WorkflowNestedElement::Declaration(decl) => {
+                walk_expression(&decl.expression, &decl.expression.span, f)
+            }
+            WorkflowNestedElement::Call(call) => {
+                for input in call.inputs.iter().flatten() {
+                    if let Some(expression) = &input.expression {
+                        walk_expression(expression, &expression.span, f)
+                    }
+                }
+            }
+            WorkflowNestedElement::Scatter(scatter) => {
+                walk_expression(&scatter.expression, &scatter.expression.span, f);
+                walk_nested(&scatter.body, f);
+            }
+            WorkflowNestedElement::Conditional(conditional) => {
+                walk_expression(&conditional.expression, &conditional.expression.span, f);
+                walk_nested(&conditional.body, f);
+            }
+        }
+    }
+}
+
+/// Returns all the identifiers referenced by `expression`, in the order in which they appear. For
+/// member access expressions (e.g. `call.output`), only the identifier of the collection (e.g.
+/// `call`) is returned.
+pub fn referenced_identifiers(expression: &Anchor<Expression>) -> Vec<Anchor<String>> {
+    let mut identifiers = Vec::new();
+    walk_expression(expression.deref(), &expression.span, &mut |e, span| {
+        if let Expression::Identifier(name) = e {
+            identifiers.push(Anchor::new(name.clone(), span.clone()))
+        }
+    });
+    identifiers
+}
+
+/// The kind of element to which an identifier refers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IdentifierKind {
+    /// An input of the enclosing task or workflow.
+    Input,
+    /// A private declaration, output, or scatter variable.
+    Declaration,
+    /// A call, i.e. the base of an access of one of its outputs (e.g. `call` in `call.output`).
+    CallOutput,
+    /// A name that is not defined in any enclosing scope.
+    Unknown,
+}
+
+/// An identifier referenced by an expression of a document, with the element it refers to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Identifier {
+    pub name: String,
+    pub span: Span,
+    pub kind: IdentifierKind,
+    /// If the identifier is the base of a member access (e.g. `call` in `call.output`), the name
+    /// of the member that is accessed.
+    pub member: Option<String>,
+    /// The symbol to which the identifier refers, if it is defined.
+    pub symbol: Option<Symbol>,
+}
+
+/// Returns each identifier referenced by the expressions of `document`'s tasks and workflows,
+/// in document order, classified by the kind of element to which it refers. Identifiers are
+/// resolved as in `resolve_scopes`.
+pub fn identifiers(document: &Document) -> Vec<Identifier> {
+    // the first member accessed from each identifier that is the base of an access, by span
+    let mut members: BTreeMap<&Span, &str> = BTreeMap::new();
+    walk_document(document, &mut |e, _| {
+        if let Expression::Access(access) = e {
+            if let (Expression::Identifier(_), Some(AccessOperation::Field(field))) = (
+                access.collection.deref().deref(),
+                access.accesses.first().map(|a| a.deref()),
+            ) {
+                members.insert(&access.collection.span, field);
+            }
+        }
+    });
+    resolve_scopes(document)
+        .references
+        .into_iter()
+        .map(|reference| {
+            let kind = match reference.symbol.as_ref().map(|symbol| symbol.kind) {
+                Some(SymbolKind::Input) => IdentifierKind::Input,
+                Some(SymbolKind::Call) => IdentifierKind::CallOutput,
+                Some(_) => IdentifierKind::Declaration,
+                None => IdentifierKind::Unknown,
+            };
+            Identifier {
+                member: members.get(&reference.span).map(|m| (*m).to_owned()),
+                name: reference.name,
+                span: reference.span,
+                kind,
+                symbol: reference.symbol,
+            }
+        })
+        .collect()
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_identifiers() {
+        let text = r#"version 1.1
+task t {
+    input {
+        Int x
+    }
+    Int y = x + 1
+    command <<< echo ~{y} >>>
+    output {
+        Int z = y
+    }
+}
+workflow w {
+    input {
+        Int x
+    }
+    call t { input: x }
+    Int n = t.z + missing
+}
+"#;
+        let document = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let identifiers = document.identifiers();
+        let identifiers: Vec<(&str, IdentifierKind, Option<&str>, usize)> = identifiers
+            .iter()
+            .map(|identifier| {
+                (
+                    identifier.name.as_str(),
+                    identifier.kind,
+                    identifier.member.as_deref(),
+                    identifier.span.start.line,
+                )
+            })
+            .collect();
+        assert_eq!(
+            identifiers,
+            vec![
+                ("x", IdentifierKind::Input, None, 5),
+                ("y", IdentifierKind::Declaration, None, 6),
+                ("y", IdentifierKind::Declaration, None, 8),
+                ("x", IdentifierKind::Input, None, 15),
+                ("t", IdentifierKind::CallOutput, Some("z"), 16),
+                ("missing", IdentifierKind::Unknown, None, 16),
+            ]
+        );
+    }
+}
@@ -0,0 +1,488 @@
+use crate::{
+    analysis::nesting::Block,
+    eval::{self, Environment},
+    fqn,
+    model::{
+        Anchor, Call, Document, DocumentElement, Expression, InputDeclaration, RuntimeKey, Span,
+        Task, TaskElement, Workflow, WorkflowElement, WorkflowNestedElement,
+    },
+    units::{self, Quantity, Unit},
+    values::WdlValue,
+};
+use std::ops::Deref;
+
+/// The resources that one instance of a task requires. A resource is `None` if the task does not
+/// specify it or its value cannot be determined statically.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TaskResources {
+    pub cpu: Option<f64>,
+    pub memory: Option<Quantity>,
+    /// The total size of the task's disks.
+    pub disk: Option<Quantity>,
+    /// The value of `gpuCount` if specified, otherwise 1 or 0 depending on the value of `gpu`.
+    pub gpus: Option<i64>,
+}
+
+/// The resources required by a call in a workflow.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallResources {
+    /// The name by which the call is referenced in its workflow.
+    pub call: String,
+    /// The name of the called task, or `None` if the target is not a task in the same document.
+    pub task: Option<String>,
+    pub span: Span,
+    /// The enclosing blocks, from outermost to innermost.
+    pub blocks: Vec<Block>,
+    /// The number of times the call is executed, i.e. the product of the widths of its enclosing
+    /// scatters, or `None` if the width of a scatter cannot be determined or the product does not
+    /// fit in a `u64`. Calls within conditionals are assumed to be executed.
+    pub multiplicity: Option<u64>,
+    /// The resources required by each execution of the call.
+    pub resources: TaskResources,
+}
+
+/// A summary of the resources required by the workflow of a document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResourceSummary {
+    pub calls: Vec<CallResources>,
+    /// The total number of CPUs, i.e. the sum over calls of the CPUs multiplied by the
+    /// multiplicity of the call.
+    pub total_cpu: f64,
+    /// The total memory, in GiB.
+    pub total_memory: Quantity,
+    /// The total disk size, in GiB.
+    pub total_disk: Quantity,
+    pub total_gpus: i64,
+    /// Whether all calls have known multiplicities and resources, and the totals do not overflow.
+    /// If not, the totals only include the known values and are lower bounds.
+    pub complete: bool,
+}
+
+/// Returns the resources required by one instance of `task` when its inputs are bound to their
+/// default values.
+pub fn task_resources(task: &Task) -> TaskResources {
+    task_resources_in(task, &Environment::new())
+}
+
+/// Returns the resources required by one instance of `task` when its inputs are bound to the
+/// values in `inputs`. Inputs that are not bound in `inputs` take their default values.
+pub fn task_resources_in(task: &Task, inputs: &Environment) -> TaskResources {
+    let env = task_environment(task, inputs);
+    let runtime = match task.runtime() {
+        Some(runtime) => runtime,
+        None => return TaskResources::default(),
+    };
+    let cpu = match runtime.value_in(&RuntimeKey::Cpu, &env) {
+        Some(WdlValue::Int(i)) => Some(i as f64),
+        Some(WdlValue::Float(f)) => Some(f),
+        _ => None,
+    };
+    let gpus = match (
+        runtime.value_in(&RuntimeKey::GpuCount, &env),
+        runtime.value_in(&RuntimeKey::Gpu, &env),
+    ) {
+        (Some(WdlValue::Int(count)), _) => Some(count),
+        (_, Some(WdlValue::Boolean(gpu))) => Some(gpu as i64),
+        _ => None,
+    };
+    TaskResources {
+        cpu,
+        memory: runtime
+            .value_in(&RuntimeKey::Memory, &env)
+            .and_then(|value| units::memory(&value)),
+        disk: runtime
+            .value_in(&RuntimeKey::Disks, &env)
+            .and_then(|value| units::disks(&value))
+            .map(|disks| {
+                let bytes: f64 = disks.iter().map(|disk| disk.size.bytes()).sum();
+                Quantity::new(bytes / Unit::GiB.bytes(), Unit::GiB)
+            }),
+        gpus,
+    }
+}
+
+/// Returns the values of `task`'s inputs and private declarations that can be determined
+/// statically, with its inputs bound to the values in `inputs` and otherwise to their defaults.
+pub(crate) fn task_environment(task: &Task, inputs: &Environment) -> Environment {
+    let mut env = inputs.clone();
+    for element in task.body.iter() {
+        match element.deref() {
+            TaskElement::Input(input) => {
+                for decl in input.declarations.iter() {
+                    let decl = match decl.deref() {
+                        InputDeclaration::Bound(decl) if !env.contains(decl.name.as_str()) => decl,
+                        _ => continue,
+                    };
+                    // defaults may refer to other inputs, so they are evaluated in `env`
+                    if let Some(value) = eval::evaluate(&decl.expression, &env)
+                        .ok()
+                        .and_then(|value| value.coerce(&decl.type_).ok())
+                    {
+                        env.bind(decl.name.as_str(), value)
+                    }
+                }
+            }
+            TaskElement::Declaration(decl) => {
+                if let Ok(value) = eval::evaluate(&decl.expression, &env) {
+                    env.bind(decl.name.as_str(), value)
+                }
+            }
+            _ => (),
+        }
+    }
+    env
+}
+
+/// Aggregates the resources required by the calls in the workflow of `document`. The resources
+/// of a call are determined by evaluating the runtime attributes of the called task with its
+/// inputs bound to the call's inputs, if they are constant, and otherwise to their defaults.
+/// The width of a scatter is determined by evaluating the scatter expression, which may refer to
+/// workflow inputs with constant defaults and to top-level declarations with constant values.
+///
+/// Calls to tasks in imported documents and to sub-workflows are included with unknown
+/// resources.
+pub fn resources(document: &Document) -> ResourceSummary {
+    let mut summary = ResourceSummary {
+        calls: Vec::new(),
+        total_cpu: 0.0,
+        total_memory: Quantity::new(0.0, Unit::GiB),
+        total_disk: Quantity::new(0.0, Unit::GiB),
+        total_gpus: 0,
+        complete: true,
+    };
+    let workflow = match document.body_iter().find_map(|element| match element {
+        DocumentElement::Workflow(workflow) => Some(workflow),
+        _ => None,
+    }) {
+        Some(workflow) => workflow,
+        None => return summary,
+    };
+    let mut collector = Collector {
+        document,
+        env: workflow_environment(workflow),
+        blocks: Vec::new(),
+        multiplicity: Some(1),
+        calls: Vec::new(),
+    };
+    for element in workflow.body.iter() {
+        match element.deref() {
+            WorkflowElement::Call(call) => collector.call(call, &element.span),
+            WorkflowElement::Scatter(scatter) => {
+                let width = collector.scatter_width(&scatter.expression);
+                collector.block(Block::Scatter, width, &scatter.body)
+            }
+            WorkflowElement::Conditional(conditional) => {
+                collector.block(Block::Conditional, Some(1), &conditional.body)
+            }
+            _ => (),
+        }
+    }
+    let gib = Unit::GiB.bytes();
+    for call in collector.calls.iter() {
+        let resources = &call.resources;
+        let (multiplicity, count) = match call.multiplicity {
+            Some(multiplicity) => (multiplicity as f64, multiplicity),
+            None => {
+                summary.complete = false;
+                continue;
+            }
+        };
+        if call.task.is_none() {
+            summary.complete = false;
+        }
+        match resources.cpu {
+            Some(cpu) => summary.total_cpu += cpu * multiplicity,
+            None => summary.complete = false,
+        }
+        match resources.memory {
+            Some(memory) => summary.total_memory.value += memory.bytes() / gib * multiplicity,
+            None => summary.complete = false,
+        }
+        match resources.disk {
+            Some(disk) => summary.total_disk.value += disk.bytes() / gib * multiplicity,
+            None => summary.complete = false,
+        }
+        if let Some(gpus) = resources.gpus {
+            match i64::try_from(count)
+                .ok()
+                .and_then(|count| gpus.checked_mul(count))
+                .and_then(|gpus| summary.total_gpus.checked_add(gpus))
+            {
+                Some(total) => summary.total_gpus = total,
+                None => summary.complete = false,
+            }
+        }
+    }
+    summary.calls = collector.calls;
+    summary
+}
+
+/// Returns an environment with the workflow's inputs bound to their constant defaults and its
+/// top-level declarations bound to their values, where these can be evaluated.
+fn workflow_environment(workflow: &Workflow) -> Environment {
+    let mut env = Environment::new();
+    for element in workflow.body.iter() {
+        match element.deref() {
+            WorkflowElement::Input(input) => {
+                for decl in input.declarations.iter() {
+                    if let Some(value) = decl.default_value() {
+                        env.bind(decl.name().as_str(), value)
+                    }
+                }
+            }
+            WorkflowElement::Declaration(decl) => {
+                if let Ok(value) = eval::evaluate(&decl.expression, &env) {
+                    env.bind(decl.name.as_str(), value)
+                }
+            }
+            _ => (),
+        }
+    }
+    env
+}
+
+struct Collector<'a> {
+    document: &'a Document,
+    env: Environment,
+    blocks: Vec<Block>,
+    multiplicity: Option<u64>,
+    calls: Vec<CallResources>,
+}
+
+impl<'a> Collector<'a> {
+    fn scatter_width(&self, expression: &Anchor<Expression>) -> Option<u64> {
+        match eval::evaluate(expression, &self.env).ok()? {
+            WdlValue::Array(values) => Some(values.len() as u64),
+            _ => None,
+        }
+    }
+
+    fn call(&mut self, call: &Call, span: &Span) {
+        let task = fqn::find_local_task(self.document, call);
+        let resources = match task {
+            Some(task) => {
+                let mut inputs = Environment::new();
+                let declared: Vec<&InputDeclaration> = task
+                    .body
+                    .iter()
+                    .filter_map(|element| match element.deref() {
+                        TaskElement::Input(input) => Some(input),
+                        _ => None,
+                    })
+                    .flat_map(|input| input.declarations.iter().map(|decl| decl.deref()))
+                    .collect();
+                for input in call.inputs.iter().flatten() {
+                    let value = match &input.expression {
+                        Some(expression) => eval::evaluate(expression, &self.env).ok(),
+                        None => self.env.get(input.name.as_str()).cloned(),
+                    };
+                    let type_ = declared
+                        .iter()
+                        .find(|decl| decl.name().as_str() == input.name.as_str())
+                        .map(|decl| decl.type_());
+                    match (value, type_) {
+                        (Some(value), Some(type_)) => {
+                            if let Ok(value) = value.coerce(type_) {
+                                inputs.bind(input.name.as_str(), value)
+                            }
+                        }
+                        // the value is dynamic, so the task's default must not be used
+                        _ => inputs.bind(input.name.as_str(), WdlValue::None),
+                    }
+                }
+                task_resources_in(task, &inputs)
+            }
+            None => TaskResources::default(),
+        };
+        self.calls.push(CallResources {
+            call: fqn::call_name(call).to_owned(),
+            task: task.map(|task| task.name.as_str().to_owned()),
+            span: span.clone(),
+            blocks: self.blocks.clone(),
+            multiplicity: self.multiplicity,
+            resources,
+        })
+    }
+
+    fn block(
+        &mut self,
+        block: Block,
+        width: Option<u64>,
+        body: &'a [Anchor<WorkflowNestedElement>],
+    ) {
+        let multiplicity = self.multiplicity;
+        self.multiplicity = multiplicity.zip(width).and_then(|(m, w)| m.checked_mul(w));
+        self.blocks.push(block);
+        for element in body.iter() {
+            match element.deref() {
+                WorkflowNestedElement::Call(call) => self.call(call, &element.span),
+                WorkflowNestedElement::Scatter(scatter) => {
+                    let width = self.scatter_width(&scatter.expression);
+                    self.block(Block::Scatter, width, &scatter.body)
+                }
+                WorkflowNestedElement::Conditional(conditional) => {
+                    self.block(Block::Conditional, Some(1), &conditional.body)
+                }
+                WorkflowNestedElement::Declaration(_) => (),
+            }
+        }
+        self.blocks.pop();
+        self.multiplicity = multiplicity;
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_resources() {
+        let text = r#"version 1.1
+
+task align {
+    input {
+        Int threads = 4
+        Int mem_gb = threads * 2
+    }
+    command <<< >>>
+    runtime {
+        cpu: threads
+        memory: "~{mem_gb} GiB"
+        disks: "local-disk 10 HDD, /mnt/ref 20 SSD"
+        gpu: true
+    }
+}
+
+task merge {
+    input {
+        Array[File] bams
+    }
+    command <<< >>>
+    runtime {
+        cpu: 1
+        memory: "~{length(bams)} GB"
+    }
+}
+
+workflow wf {
+    input {
+        Array[String] samples = ["a", "b", "c"]
+        Array[File] lanes
+    }
+    scatter (sample in samples) {
+        call align { input: threads = 8 }
+        scatter (lane in lanes) {
+            call align as per_lane
+        }
+    }
+    call merge { input: bams = align.threads }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let summary = resources(&doc);
+        let calls: Vec<(&str, Option<u64>, &TaskResources)> = summary
+            .calls
+            .iter()
+            .map(|call| (call.call.as_str(), call.multiplicity, &call.resources))
+            .collect();
+        assert_eq!(
+            calls,
+            vec![
+                (
+                    "align",
+                    Some(3),
+                    &TaskResources {
+                        cpu: Some(8.0),
+                        memory: Some(Quantity::new(16.0, Unit::GiB)),
+                        disk: Some(Quantity::new(30.0, Unit::GiB)),
+                        gpus: Some(1),
+                    }
+                ),
+                (
+                    "per_lane",
+                    None,
+                    &TaskResources {
+                        cpu: Some(4.0),
+                        memory: Some(Quantity::new(8.0, Unit::GiB)),
+                        disk: Some(Quantity::new(30.0, Unit::GiB)),
+                        gpus: Some(1),
+                    }
+                ),
+                (
+                    "merge",
+                    Some(1),
+                    &TaskResources {
+                        cpu: Some(1.0),
+                        ..Default::default()
+                    }
+                ),
+            ]
+        );
+        assert_eq!(summary.total_cpu, 25.0);
+        assert_eq!(summary.total_memory, Quantity::new(48.0, Unit::GiB));
+        assert_eq!(summary.total_gpus, 3);
+        assert!(!summary.complete);
+    }
+
+    #[test]
+    fn test_resources_overflow() {
+        let text = r#"version 1.1
+
+task t {
+    command <<< >>>
+    runtime {
+        cpu: 1
+        memory: "1 GiB"
+        disks: "local-disk 1 HDD"
+        gpuCount: 100
+    }
+}
+
+workflow wf {
+    input {
+        Int n = 1000000
+    }
+    call t as single
+    scatter (i in range(n)) {
+        scatter (j in range(n)) {
+            scatter (k in range(n)) {
+                call t as wide
+                scatter (l in range(n)) {
+                    call t as wider
+                }
+            }
+        }
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let summary = resources(&doc);
+        let multiplicities: Vec<(&str, Option<u64>)> = summary
+            .calls
+            .iter()
+            .map(|call| (call.call.as_str(), call.multiplicity))
+            .collect();
+        // the multiplicity of `wider` (10^24) does not fit in a u64, and the number of GPUs
+        // required by `wide` (10^20) does not fit in an i64
+        assert_eq!(
+            multiplicities,
+            vec![
+                ("single", Some(1)),
+                ("wide", Some(1_000_000_000_000_000_000)),
+                ("wider", None),
+            ]
+        );
+        assert_eq!(summary.total_gpus, 100);
+        assert_eq!(summary.total_cpu, 1e18 + 1.0);
+        assert!(!summary.complete);
+    }
+}
@@ -0,0 +1,437 @@
+use crate::{
+    eval::{self, Environment},
+    fqn::{self, FqnKind, FullyQualifiedName},
+    model::{
+        Anchor, Call, Document, DocumentElement, Expression, InputDeclaration, Span, Type,
+        Workflow, WorkflowElement, WorkflowNestedElement,
+    },
+    values::WdlValue,
+};
+use std::{collections::HashMap, ops::Deref};
+
+/// The maximum number of block instances (i.e. scatter shards) that are evaluated individually.
+/// Beyond this, the widths of nested scatters and the numbers of jobs are reported as unknown, and
+/// the collection of a scatter is not evaluated for the remaining instances of its enclosing
+/// scatters.
+const MAX_INSTANCES: usize = 100_000;
+
+/// The number of shards of a scatter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScatterWidth {
+    pub name: FullyQualifiedName,
+    pub variable: String,
+    pub span: Span,
+    /// The number of elements in the scatter collection, or `None` if it cannot be determined
+    /// statically or differs between the instances of the scatter (e.g. a nested scatter whose
+    /// collection depends on the variable of the enclosing scatter).
+    pub width: Option<usize>,
+    /// The total number of shards across all instances of the scatter, or `None` if it cannot be
+    /// determined statically.
+    pub shards: Option<usize>,
+}
+
+/// The number of jobs that a call generates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallJobs {
+    pub name: FullyQualifiedName,
+    pub span: Span,
+    /// The number of times the call is executed, or `None` if it cannot be determined statically.
+    pub jobs: Option<usize>,
+}
+
+/// Estimates of the scatter widths and job counts of a workflow.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JobEstimate {
+    /// The scatters of the workflow, in source order.
+    pub scatters: Vec<ScatterWidth>,
+    /// The calls of the workflow, in source order.
+    pub calls: Vec<CallJobs>,
+    /// Whether every estimate is exact. If the value of a condition cannot be determined, the
+    /// calls in its body are assumed to be executed, so the estimates are upper bounds.
+    pub exact: bool,
+}
+
+impl JobEstimate {
+    /// Returns the total number of jobs in the workflow, or `None` if the number of jobs of any
+    /// call cannot be determined. A call to a sub-workflow counts as a single job.
+    pub fn total_jobs(&self) -> Option<usize> {
+        self.calls.iter().map(|call| call.jobs).sum()
+    }
+
+    /// Returns the total number of jobs of the calls for which it can be determined, which is a
+    /// lower bound on the total if the number of jobs of some calls is unknown.
+    pub fn known_jobs(&self) -> usize {
+        self.calls.iter().filter_map(|call| call.jobs).sum()
+    }
+}
+
+/// Estimates the number of shards of each scatter and the number of jobs of each call in the
+/// workflow of `document`, for capacity planning. Scatter collections are evaluated with the
+/// workflow inputs bound to the values in `inputs`, which may be keyed by unqualified (`x`) or
+/// fully-qualified (`wf.x`) names, and otherwise to their defaults. Widths can thus be
+/// determined for scatters over literal arrays, `range(n)` with constant `n`, and arrays that are
+/// provided as inputs (e.g. by converting an inputs JSON file with `WdlValue::from_json`).
+///
+/// Each instance of a block is evaluated separately, so nested scatters whose collections depend
+/// on the variables of enclosing scatters are supported. Values that depend on call outputs are
+/// unknown. Inputs whose values cannot be coerced to their declared types are ignored.
+pub fn estimate_jobs(document: &Document, inputs: &HashMap<String, WdlValue>) -> JobEstimate {
+    let mut estimate = JobEstimate {
+        scatters: Vec::new(),
+        calls: Vec::new(),
+        exact: true,
+    };
+    let workflow = match document.body_iter().find_map(|element| match element {
+        DocumentElement::Workflow(workflow) => Some(workflow),
+        _ => None,
+    }) {
+        Some(workflow) => workflow,
+        None => return estimate,
+    };
+    let mut estimator = Estimator {
+        estimate: &mut estimate,
+        scatters: 0,
+        conditionals: 0,
+    };
+    let root = FullyQualifiedName::workflow(workflow.name.as_str());
+    let mut instances = Some(vec![input_environment(workflow, inputs)]);
+    for element in workflow.body.iter() {
+        match element.deref() {
+            WorkflowElement::Declaration(decl) => {
+                bind_all(&mut instances, decl.name.as_str(), &decl.expression)
+            }
+            WorkflowElement::Call(call) => estimator.call(&root, call, &element.span, &instances),
+            WorkflowElement::Scatter(scatter) => estimator.scatter(
+                &root,
+                scatter.name.as_str(),
+                &scatter.expression,
+                &scatter.body,
+                &element.span,
+                &instances,
+            ),
+            WorkflowElement::Conditional(conditional) => estimator.conditional(
+                &root,
+                &conditional.expression,
+                &conditional.body,
+                &instances,
+            ),
+            _ => (),
+        }
+    }
+    estimate
+}
+
+/// Returns an environment with the workflow inputs bound to the values in `inputs`, or otherwise
+/// to their defaults, where these can be evaluated.
+fn input_environment(workflow: &Workflow, inputs: &HashMap<String, WdlValue>) -> Environment {
+    let mut env = Environment::new();
+    for element in workflow.body.iter() {
+        if let WorkflowElement::Input(input) = element.deref() {
+            for decl in input.declarations.iter() {
+                let (name, type_) = (decl.name().as_str(), decl.type_());
+                let qualified = format!("{}.{}", workflow.name.as_str(), name);
+                let value = match inputs.get(&qualified).or_else(|| inputs.get(name)) {
+                    Some(value) => value.clone().coerce(type_).ok(),
+                    None => match decl.deref() {
+                        InputDeclaration::Bound(decl) => eval::evaluate(&decl.expression, &env)
+                            .ok()
+                            .and_then(|value| value.coerce(type_).ok()),
+                        _ if matches!(type_.deref(), Type::Optional(_)) => Some(WdlValue::None),
+                        _ => None,
+                    },
+                };
+                if let Some(value) = value {
+                    env.bind(name, value)
+                }
+            }
+        }
+    }
+    env
+}
+
+/// Binds `name` to the value of `expression` in each instance in which it can be evaluated.
+fn bind_all(instances: &mut Option<Vec<Environment>>, name: &str, expression: &Expression) {
+    for env in instances.iter_mut().flatten() {
+        if let Ok(value) = eval::evaluate(expression, env) {
+            env.bind(name, value)
+        }
+    }
+}
+
+struct Estimator<'a> {
+    estimate: &'a mut JobEstimate,
+    scatters: usize,
+    conditionals: usize,
+}
+
+impl<'a> Estimator<'a> {
+    fn body(
+        &mut self,
+        parent: &FullyQualifiedName,
+        body: &[Anchor<WorkflowNestedElement>],
+        mut instances: Option<Vec<Environment>>,
+    ) {
+        for element in body.iter() {
+            match element.deref() {
+                WorkflowNestedElement::Declaration(decl) => {
+                    bind_all(&mut instances, decl.name.as_str(), &decl.expression)
+                }
+                WorkflowNestedElement::Call(call) => {
+                    self.call(parent, call, &element.span, &instances)
+                }
+                WorkflowNestedElement::Scatter(scatter) => self.scatter(
+                    parent,
+                    scatter.name.as_str(),
+                    &scatter.expression,
+                    &scatter.body,
+                    &element.span,
+                    &instances,
+                ),
+                WorkflowNestedElement::Conditional(conditional) => self.conditional(
+                    parent,
+                    &conditional.expression,
+                    &conditional.body,
+                    &instances,
+                ),
+            }
+        }
+    }
+
+    fn call(
+        &mut self,
+        parent: &FullyQualifiedName,
+        call: &Call,
+        span: &Span,
+        instances: &Option<Vec<Environment>>,
+    ) {
+        self.estimate.calls.push(CallJobs {
+            name: parent.child(FqnKind::Call, fqn::call_name(call)),
+            span: span.clone(),
+            jobs: instances.as_ref().map(|instances| instances.len()),
+        })
+    }
+
+    fn scatter(
+        &mut self,
+        parent: &FullyQualifiedName,
+        variable: &str,
+        expression: &Expression,
+        body: &[Anchor<WorkflowNestedElement>],
+        span: &Span,
+        instances: &Option<Vec<Environment>>,
+    ) {
+        self.scatters += 1;
+        let name = parent.child(FqnKind::Scatter, fqn::scatter_name(self.scatters - 1));
+        // the collections of all instances are kept until the shards are created, so evaluation
+        // stops once their total length exceeds the maximum, unless no instances remain
+        let evaluated: Option<Vec<(&Environment, Vec<WdlValue>)>> =
+            instances.as_ref().and_then(|instances| {
+                let mut evaluated = Vec::new();
+                let mut total: usize = 0;
+                for env in instances.iter() {
+                    if total > MAX_INSTANCES {
+                        return None;
+                    }
+                    match eval::evaluate(expression, env) {
+                        Ok(WdlValue::Array(items)) => {
+                            total = total.saturating_add(items.len());
+                            evaluated.push((env, items))
+                        }
+                        _ => return None,
+                    }
+                }
+                Some(evaluated)
+            });
+        let width = evaluated.as_ref().and_then(|evaluated| {
+            let first = evaluated.first()?.1.len();
+            if evaluated.iter().all(|(_, items)| items.len() == first) {
+                Some(first)
+            } else {
+                None
+            }
+        });
+        let total = evaluated
+            .as_ref()
+            .map(|evaluated| evaluated.iter().map(|(_, items)| items.len()).sum());
+        self.estimate.scatters.push(ScatterWidth {
+            name: name.clone(),
+            variable: variable.to_owned(),
+            span: span.clone(),
+            width,
+            shards: total,
+        });
+        let shards = match (evaluated, total) {
+            (Some(evaluated), Some(total)) if total <= MAX_INSTANCES => Some(
+                evaluated
+                    .into_iter()
+                    .flat_map(|(env, items)| {
+                        items.into_iter().map(move |item| {
+                            let mut shard = env.clone();
+                            shard.bind(variable, item);
+                            shard
+                        })
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        };
+        self.body(&name, body, shards)
+    }
+
+    fn conditional(
+        &mut self,
+        parent: &FullyQualifiedName,
+        expression: &Expression,
+        body: &[Anchor<WorkflowNestedElement>],
+        instances: &Option<Vec<Environment>>,
+    ) {
+        self.conditionals += 1;
+        let name = parent.child(
+            FqnKind::Conditional,
+            fqn::conditional_name(self.conditionals - 1),
+        );
+        let instances = instances.as_ref().map(|instances| {
+            instances
+                .iter()
+                .filter(|env| match eval::evaluate(expression, env) {
+                    Ok(WdlValue::Boolean(condition)) => condition,
+                    _ => {
+                        self.estimate.exact = false;
+                        true
+                    }
+                })
+                .cloned()
+                .collect()
+        });
+        self.body(&name, body, instances)
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_estimate_jobs() {
+        let text = r#"version 1.1
+
+workflow wf {
+    input {
+        Array[String] samples
+        Int chunks = 4
+        Boolean qc = false
+    }
+    scatter (sample in samples) {
+        scatter (chunk in range(chunks)) {
+            call align
+        }
+        scatter (i in range(length(sample))) {
+            call count
+        }
+    }
+    scatter (x in [1, 2, 3]) {
+        if (qc) {
+            call check
+        }
+        if (x > 1) {
+            call merge
+        }
+    }
+    scatter (y in align.bam) {
+        call index
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let inputs = HashMap::from([(
+            "wf.samples".to_owned(),
+            WdlValue::Array(vec![
+                WdlValue::String("a.bam".to_owned()),
+                WdlValue::String("bb.bam".to_owned()),
+            ]),
+        )]);
+        let estimate = estimate_jobs(&doc, &inputs);
+        let scatters: Vec<(String, Option<usize>, Option<usize>)> = estimate
+            .scatters
+            .iter()
+            .map(|s| (s.name.to_string(), s.width, s.shards))
+            .collect();
+        assert_eq!(
+            scatters,
+            vec![
+                ("wf.scatter_0".to_owned(), Some(2), Some(2)),
+                ("wf.scatter_0.scatter_1".to_owned(), Some(4), Some(8)),
+                ("wf.scatter_0.scatter_2".to_owned(), None, Some(11)),
+                ("wf.scatter_3".to_owned(), Some(3), Some(3)),
+                ("wf.scatter_4".to_owned(), None, None),
+            ]
+        );
+        let calls: Vec<(&str, Option<usize>)> = estimate
+            .calls
+            .iter()
+            .map(|c| (c.name.local_name(), c.jobs))
+            .collect();
+        assert_eq!(
+            calls,
+            vec![
+                ("align", Some(8)),
+                ("count", Some(11)),
+                ("check", Some(0)),
+                ("merge", Some(2)),
+                ("index", None),
+            ]
+        );
+        assert!(estimate.exact);
+        assert_eq!(estimate.total_jobs(), None);
+        assert_eq!(estimate.known_jobs(), 21);
+
+        let estimate = estimate_jobs(&doc, &HashMap::new());
+        assert_eq!(estimate.scatters[0].width, None);
+        assert_eq!(estimate.calls[0].jobs, None);
+        assert_eq!(estimate.calls[3].jobs, Some(2));
+    }
+
+    #[test]
+    fn test_estimate_jobs_limit() {
+        let text = r#"version 1.1
+
+workflow wf {
+    scatter (i in range(200000)) {
+        call a
+    }
+    scatter (j in range(1000)) {
+        scatter (k in range(200)) {
+            call b
+        }
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let estimate = estimate_jobs(&doc, &HashMap::new());
+        let scatters: Vec<(String, Option<usize>, Option<usize>)> = estimate
+            .scatters
+            .iter()
+            .map(|s| (s.name.to_string(), s.width, s.shards))
+            .collect();
+        // the shards of a scatter with too many instances are not evaluated, and evaluation of
+        // the nested scatter stops after the first 501 of its 1000 instances
+        assert_eq!(
+            scatters,
+            vec![
+                ("wf.scatter_0".to_owned(), Some(200000), Some(200000)),
+                ("wf.scatter_1".to_owned(), Some(1000), Some(1000)),
+                ("wf.scatter_1.scatter_2".to_owned(), None, None),
+            ]
+        );
+        assert!(estimate.calls.iter().all(|call| call.jobs.is_none()));
+    }
+}
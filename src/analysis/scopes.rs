@@ -0,0 +1,410 @@
+use crate::{
+    analysis::references::walk_expression,
+    model::{
+        Anchor, BoundDeclaration, Call, Document, DocumentElement, Expression, InputDeclaration,
+        Span, StringPart, Task, TaskElement, Workflow, WorkflowElement, WorkflowNestedElement,
+    },
+};
+use std::ops::Deref;
+
+/// The kind of element that defines a name within a task or workflow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    Input,
+    Declaration,
+    Output,
+    Call,
+    ScatterVariable,
+}
+
+/// A name defined within a task or workflow.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// The span of the name where it is defined. For calls, this is the span of the alias if there
+    /// is one, otherwise the span of the last part of the call target.
+    pub span: Span,
+}
+
+/// An identifier referenced by an expression.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reference {
+    pub name: String,
+    pub span: Span,
+    /// The symbol to which the identifier refers, or `None` if it is not defined in any enclosing
+    /// scope.
+    pub symbol: Option<Symbol>,
+}
+
+/// The symbols defined in a document's tasks and workflows, and the references to them.
+#[derive(Debug, Default, PartialEq)]
+pub struct Scopes {
+    /// All symbols, ordered by position in the document.
+    pub symbols: Vec<Symbol>,
+    /// All references, ordered by position in the document.
+    pub references: Vec<Reference>,
+}
+
+impl Scopes {
+    /// Returns the reference whose span contains the byte `offset`, if any.
+    pub fn reference_at(&self, offset: usize) -> Option<&Reference> {
+        self.references.iter().find(|reference| {
+            reference.span.start.offset <= offset && offset < reference.span.end.offset
+        })
+    }
+
+    /// Returns all references to `symbol`.
+    pub fn references_to<'a>(&'a self, symbol: &'a Symbol) -> impl Iterator<Item = &'a Reference> {
+        self.references
+            .iter()
+            .filter(move |reference| reference.symbol.as_ref() == Some(symbol))
+    }
+}
+
+/// Resolves the identifiers referenced by the expressions in `document`'s tasks and workflows.
+///
+/// Within a task, inputs and private declarations are visible to all expressions, and outputs
+/// are also visible within the output section. Within a workflow, inputs, declarations and calls
+/// are visible everywhere, including those nested within scatter and conditional blocks; a
+/// scatter variable is only visible within the body of its scatter. Outputs are visible within
+/// the output section. Names in inner scopes shadow names in outer scopes.
+pub fn resolve_scopes(document: &Document) -> Scopes {
+    let mut resolver = Resolver::default();
+    for element in document.body_iter() {
+        match element {
+            DocumentElement::Task(task) => resolver.resolve_task(task),
+            DocumentElement::Workflow(workflow) => resolver.resolve_workflow(workflow),
+            _ => (),
+        }
+    }
+    let mut scopes = resolver.scopes;
+    scopes.symbols.sort_by(|a, b| a.span.cmp(&b.span));
+    scopes.references.sort_by(|a, b| a.span.cmp(&b.span));
+    scopes
+}
+
+#[derive(Default)]
+struct Resolver {
+    /// The symbols of the enclosing scopes, innermost last.
+    frames: Vec<Vec<Symbol>>,
+    scopes: Scopes,
+}
+
+impl Resolver {
+    fn define(&mut self, name: &Anchor<String>, kind: SymbolKind) -> Symbol {
+        let symbol = Symbol {
+            name: (**name).clone(),
+            kind,
+            span: name.span.clone(),
+        };
+        self.scopes.symbols.push(symbol.clone());
+        symbol
+    }
+
+    /// Defines the name of `call`, i.e. its alias or the last part of its target, which is the
+    /// same as `fqn::call_name`.
+    fn define_call(&mut self, call: &Call) -> Option<Symbol> {
+        let name = call.alias.as_ref().or_else(|| call.target.parts.last())?;
+        Some(self.define(name, SymbolKind::Call))
+    }
+
+    fn resolve_identifier(&mut self, name: &str, span: &Span) {
+        let symbol = self
+            .frames
+            .iter()
+            .rev()
+            .find_map(|frame| frame.iter().find(|symbol| symbol.name == name))
+            .cloned();
+        self.scopes.references.push(Reference {
+            name: name.to_owned(),
+            span: span.clone(),
+            symbol,
+        })
+    }
+
+    fn resolve_expression(&mut self, expression: &Anchor<Expression>) {
+        self.resolve_unanchored(expression.deref(), &expression.span)
+    }
+
+    fn resolve_unanchored(&mut self, expression: &Expression, span: &Span) {
+        let mut identifiers = Vec::new();
+        walk_expression(expression, span, &mut |e, span| {
+            if let Expression::Identifier(name) = e {
+                identifiers.push((name, span))
+            }
+        });
+        for (name, span) in identifiers {
+            self.resolve_identifier(name, span)
+        }
+    }
+
+    fn resolve_outputs(&mut self, outputs: Vec<Symbol>, declarations: &[Anchor<BoundDeclaration>]) {
+        self.frames.push(outputs);
+        for decl in declarations.iter() {
+            self.resolve_expression(&decl.expression)
+        }
+        self.frames.pop();
+    }
+
+    fn resolve_task(&mut self, task: &Task) {
+        let mut symbols = Vec::new();
+        let mut outputs = Vec::new();
+        for element in task.body.iter() {
+            match element.deref() {
+                TaskElement::Input(input) => {
+                    for decl in input.declarations.iter() {
+                        symbols.push(self.define(decl.name(), SymbolKind::Input))
+                    }
+                }
+                TaskElement::Declaration(decl) => {
+                    symbols.push(self.define(&decl.name, SymbolKind::Declaration))
+                }
+                TaskElement::Output(output) => {
+                    for decl in output.declarations.iter() {
+                        outputs.push(self.define(&decl.name, SymbolKind::Output))
+                    }
+                }
+                _ => (),
+            }
+        }
+        self.frames.push(symbols);
+        for element in task.body.iter() {
+            match element.deref() {
+                TaskElement::Input(input) => {
+                    for decl in input.declarations.iter() {
+                        if let InputDeclaration::Bound(decl) = decl.deref() {
+                            self.resolve_expression(&decl.expression)
+                        }
+                    }
+                }
+                TaskElement::Declaration(decl) => self.resolve_expression(&decl.expression),
+                TaskElement::Command(command) => {
+                    for part in command.parts.iter() {
+                        if let StringPart::Placeholder(expression, _) = part.deref() {
+                            self.resolve_unanchored(expression, &part.span)
+                        }
+                    }
+                }
+                TaskElement::Runtime(runtime) => {
+                    for attribute in runtime.attributes.iter() {
+                        self.resolve_expression(&attribute.expression)
+                    }
+                }
+                TaskElement::Output(output) => {
+                    self.resolve_outputs(outputs.clone(), &output.declarations)
+                }
+                _ => (),
+            }
+        }
+        self.frames.pop();
+    }
+
+    fn define_nested(&mut self, body: &[Anchor<WorkflowNestedElement>], symbols: &mut Vec<Symbol>) {
+        for element in body.iter() {
+            match element.deref() {
+                WorkflowNestedElement::Declaration(decl) => {
+                    symbols.push(self.define(&decl.name, SymbolKind::Declaration))
+                }
+                WorkflowNestedElement::Call(call) => symbols.extend(self.define_call(call)),
+                WorkflowNestedElement::Scatter(scatter) => {
+                    self.define_nested(&scatter.body, symbols)
+                }
+                WorkflowNestedElement::Conditional(conditional) => {
+                    self.define_nested(&conditional.body, symbols)
+                }
+            }
+        }
+    }
+
+    fn resolve_call(&mut self, call: &Call) {
+        for input in call.inputs.iter().flatten() {
+            match &input.expression {
+                Some(expression) => self.resolve_expression(expression),
+                // `call x { input: y }` is shorthand for `call x { input: y = y }`
+                None => self.resolve_identifier(&input.name, &input.name.span),
+            }
+        }
+    }
+
+    fn resolve_nested(&mut self, body: &[Anchor<WorkflowNestedElement>]) {
+        for element in body.iter() {
+            match element.deref() {
+                WorkflowNestedElement::Declaration(decl) => {
+                    self.resolve_expression(&decl.expression)
+                }
+                WorkflowNestedElement::Call(call) => self.resolve_call(call),
+                WorkflowNestedElement::Scatter(scatter) => {
+                    self.resolve_expression(&scatter.expression);
+                    let variable = self.define(&scatter.name, SymbolKind::ScatterVariable);
+                    self.frames.push(vec![variable]);
+                    self.resolve_nested(&scatter.body);
+                    self.frames.pop();
+                }
+                WorkflowNestedElement::Conditional(conditional) => {
+                    self.resolve_expression(&conditional.expression);
+                    self.resolve_nested(&conditional.body);
+                }
+            }
+        }
+    }
+
+    fn resolve_workflow(&mut self, workflow: &Workflow) {
+        let mut symbols = Vec::new();
+        let mut outputs = Vec::new();
+        for element in workflow.body.iter() {
+            match element.deref() {
+                WorkflowElement::Input(input) => {
+                    for decl in input.declarations.iter() {
+                        symbols.push(self.define(decl.name(), SymbolKind::Input))
+                    }
+                }
+                WorkflowElement::Declaration(decl) => {
+                    symbols.push(self.define(&decl.name, SymbolKind::Declaration))
+                }
+                WorkflowElement::Call(call) => symbols.extend(self.define_call(call)),
+                WorkflowElement::Scatter(scatter) => {
+                    self.define_nested(&scatter.body, &mut symbols)
+                }
+                WorkflowElement::Conditional(conditional) => {
+                    self.define_nested(&conditional.body, &mut symbols)
+                }
+                WorkflowElement::Output(output) => {
+                    for decl in output.declarations.iter() {
+                        outputs.push(self.define(&decl.name, SymbolKind::Output))
+                    }
+                }
+                _ => (),
+            }
+        }
+        self.frames.push(symbols);
+        for element in workflow.body.iter() {
+            match element.deref() {
+                WorkflowElement::Input(input) => {
+                    for decl in input.declarations.iter() {
+                        if let InputDeclaration::Bound(decl) = decl.deref() {
+                            self.resolve_expression(&decl.expression)
+                        }
+                    }
+                }
+                WorkflowElement::Declaration(decl) => self.resolve_expression(&decl.expression),
+                WorkflowElement::Call(call) => self.resolve_call(call),
+                WorkflowElement::Scatter(scatter) => {
+                    self.resolve_expression(&scatter.expression);
+                    let variable = self.define(&scatter.name, SymbolKind::ScatterVariable);
+                    self.frames.push(vec![variable]);
+                    self.resolve_nested(&scatter.body);
+                    self.frames.pop();
+                }
+                WorkflowElement::Conditional(conditional) => {
+                    self.resolve_expression(&conditional.expression);
+                    self.resolve_nested(&conditional.body);
+                }
+                WorkflowElement::Output(output) => {
+                    self.resolve_outputs(outputs.clone(), &output.declarations)
+                }
+                _ => (),
+            }
+        }
+        self.frames.pop();
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::model::DocumentSource;
+    use crate::parsers::{PestParser, WdlParser};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_resolve() {
+        let text = r#"
+version 1.1
+
+task t {
+    input {
+        Int i
+    }
+    Int j = i + 1
+    command <<<
+        echo ~{j}
+    >>>
+    output {
+        Int k = j
+        Int l = k + undefined
+    }
+}
+
+workflow wf {
+    input {
+        Array[Int] xs
+    }
+    scatter (x in xs) {
+        call t as c { input: i = x }
+    }
+    Int n = 1
+    call t { input: i = n }
+    output {
+        Array[Int] ks = c.k
+        Int y = n
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let scopes = resolve_scopes(&doc);
+        let symbols: Vec<_> = scopes
+            .symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.kind, s.span.start.line))
+            .collect();
+        assert_eq!(
+            symbols,
+            vec![
+                ("i", SymbolKind::Input, 5),
+                ("j", SymbolKind::Declaration, 7),
+                ("k", SymbolKind::Output, 12),
+                ("l", SymbolKind::Output, 13),
+                ("xs", SymbolKind::Input, 19),
+                ("x", SymbolKind::ScatterVariable, 21),
+                ("c", SymbolKind::Call, 22),
+                ("n", SymbolKind::Declaration, 24),
+                ("t", SymbolKind::Call, 25),
+                ("ks", SymbolKind::Output, 27),
+                ("y", SymbolKind::Output, 28),
+            ]
+        );
+        let references: Vec<_> = scopes
+            .references
+            .iter()
+            .map(|r| {
+                (
+                    r.name.as_str(),
+                    r.span.start.line,
+                    r.symbol.as_ref().map(|s| (s.kind, s.span.start.line)),
+                )
+            })
+            .collect();
+        assert_eq!(
+            references,
+            vec![
+                ("i", 7, Some((SymbolKind::Input, 5))),
+                ("j", 9, Some((SymbolKind::Declaration, 7))),
+                ("j", 12, Some((SymbolKind::Declaration, 7))),
+                ("k", 13, Some((SymbolKind::Output, 12))),
+                ("undefined", 13, None),
+                ("xs", 21, Some((SymbolKind::Input, 19))),
+                ("x", 22, Some((SymbolKind::ScatterVariable, 21))),
+                ("n", 25, Some((SymbolKind::Declaration, 24))),
+                ("c", 27, Some((SymbolKind::Call, 22))),
+                ("n", 28, Some((SymbolKind::Declaration, 24))),
+            ]
+        );
+        let c = &scopes.symbols[6];
+        assert_eq!(scopes.references_to(c).count(), 1);
+        let offset = text.find("c.k").unwrap();
+        assert_eq!(scopes.reference_at(offset).unwrap().name, "c");
+    }
+}
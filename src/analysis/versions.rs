@@ -0,0 +1,223 @@
+use crate::{
+    analysis::references::walk_document,
+    model::{
+        Anchor, Call, Document, DocumentElement, Expression, ObjectKind, Span, VersionIdentifier,
+        WorkflowElement, WorkflowNestedElement,
+    },
+};
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    ops::Deref,
+};
+
+/// Standard library functions that were added in WDL 1.1.
+const FUNCTIONS_1_1: [&str; 11] = [
+    "as_map",
+    "as_pairs",
+    "collect_by_key",
+    "keys",
+    "max",
+    "min",
+    "quote",
+    "sep",
+    "squote",
+    "suffix",
+    "unzip",
+];
+
+/// A language feature that is not available in all WDL versions. Integer literals (including
+/// octal and hexadecimal literals) are available in all supported versions.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum VersionFeature {
+    /// The `None` literal.
+    NoneLiteral,
+    /// A struct literal, e.g. `Sample { name: "x" }`.
+    StructLiteral,
+    /// A call input without an expression, e.g. `call t { input: x }`.
+    ImplicitCallInput,
+    /// A standard library function.
+    Function(String),
+}
+
+impl VersionFeature {
+    /// Returns the earliest version in which this feature is available.
+    pub fn min_version(&self) -> VersionIdentifier {
+        match self {
+            Self::NoneLiteral | Self::StructLiteral | Self::ImplicitCallInput => {
+                VersionIdentifier::V1_1
+            }
+            Self::Function(name) if FUNCTIONS_1_1.contains(&name.as_str()) => {
+                VersionIdentifier::V1_1
+            }
+            Self::Function(_) => VersionIdentifier::V1_0,
+        }
+    }
+}
+
+impl Display for VersionFeature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::NoneLiteral => write!(f, "the None literal"),
+            Self::StructLiteral => write!(f, "struct literals"),
+            Self::ImplicitCallInput => write!(f, "call inputs without expressions"),
+            Self::Function(name) => write!(f, "the {} function", name),
+        }
+    }
+}
+
+/// A use of a feature that requires a WDL version later than 1.0.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VersionRequirement {
+    pub feature: VersionFeature,
+    pub span: Span,
+}
+
+impl VersionRequirement {
+    pub fn min_version(&self) -> VersionIdentifier {
+        self.feature.min_version()
+    }
+}
+
+/// Returns the uses of features in `document` that require a WDL version later than 1.0, ordered
+/// by position, regardless of the version that the document declares. The minimum version that
+/// `document` requires is the maximum of the requirements' minimum versions.
+pub fn version_requirements(document: &Document) -> Vec<VersionRequirement> {
+    let mut requirements = Vec::new();
+    walk_document(document, &mut |expression, span| {
+        let feature = match expression {
+            Expression::None => VersionFeature::NoneLiteral,
+            Expression::Object(object) if object.kind == ObjectKind::Struct => {
+                VersionFeature::StructLiteral
+            }
+            Expression::Apply(apply) => VersionFeature::Function((*apply.name).clone()),
+            _ => return,
+        };
+        if feature.min_version() > VersionIdentifier::V1_0 {
+            requirements.push(VersionRequirement {
+                feature,
+                span: span.clone(),
+            })
+        }
+    });
+    for element in document.body_iter() {
+        if let DocumentElement::Workflow(workflow) = element {
+            for element in workflow.body.iter() {
+                match element.deref() {
+                    WorkflowElement::Call(call) => call_requirements(call, &mut requirements),
+                    WorkflowElement::Scatter(scatter) => {
+                        nested_requirements(&scatter.body, &mut requirements)
+                    }
+                    WorkflowElement::Conditional(conditional) => {
+                        nested_requirements(&conditional.body, &mut requirements)
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+    requirements.sort_by(|a, b| a.span.cmp(&b.span));
+    requirements
+}
+
+fn call_requirements(call: &Call, requirements: &mut Vec<VersionRequirement>) {
+    for input in call.inputs.iter().flatten() {
+        if input.expression.is_none() {
+            requirements.push(VersionRequirement {
+                feature: VersionFeature::ImplicitCallInput,
+                span: input.span.clone(),
+            })
+        }
+    }
+}
+
+fn nested_requirements(
+    body: &[Anchor<WorkflowNestedElement>],
+    requirements: &mut Vec<VersionRequirement>,
+) {
+    for element in body.iter() {
+        match element.deref() {
+            WorkflowNestedElement::Call(call) => call_requirements(call, requirements),
+            WorkflowNestedElement::Scatter(scatter) => {
+                nested_requirements(&scatter.body, requirements)
+            }
+            WorkflowNestedElement::Conditional(conditional) => {
+                nested_requirements(&conditional.body, requirements)
+            }
+            WorkflowNestedElement::Declaration(_) => (),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::{DocumentSource, ModelError, ValidationLevel, ValidationOptions},
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    const TEXT: &str = r#"version 1.0
+
+struct Sample {
+    String name
+}
+
+task t {
+    input {
+        Array[String] xs
+        Int? n = None
+    }
+    command <<<
+        echo ~{sep(",", xs)} ~{length(xs)}
+    >>>
+}
+
+workflow wf {
+    Sample s = Sample { name: "x" }
+    Array[String] xs = ["a"]
+    call t { input: xs }
+}
+"#;
+
+    #[test]
+    fn test_version_requirements() {
+        let doc = PestParser::new()
+            .with_validation(ValidationOptions {
+                version_features: ValidationLevel::Ignore,
+                ..ValidationOptions::default()
+            })
+            .parse_text(TEXT, DocumentSource::Unknown)
+            .unwrap();
+        let features: Vec<(String, &str)> = version_requirements(&doc)
+            .into_iter()
+            .map(|r| {
+                (
+                    r.feature.to_string(),
+                    &TEXT[r.span.start.offset..r.span.end.offset],
+                )
+            })
+            .collect();
+        assert_eq!(
+            features,
+            vec![
+                ("the None literal".to_owned(), "None"),
+                // placeholders are not anchored, so the span includes the delimiters
+                ("the sep function".to_owned(), "~{sep(\",\", xs)}"),
+                ("struct literals".to_owned(), "Sample { name: \"x\" }"),
+                ("call inputs without expressions".to_owned(), "xs"),
+            ]
+        );
+        let report = PestParser::new()
+            .parse_text(TEXT, DocumentSource::Unknown)
+            .unwrap_err();
+        assert!(report.frames().any(|frame| matches!(
+            frame.downcast_ref::<ModelError>(),
+            Some(ModelError::VersionFeature { .. })
+        )));
+        let text = TEXT.replace("version 1.0", "version 1.1");
+        assert!(PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .is_ok());
+    }
+}
@@ -0,0 +1,404 @@
+use crate::{
+    analysis::nesting::{promote, Block},
+    edit::TextEdit,
+    fqn,
+    model::{
+        Anchor, Call, Document, DocumentElement, Expression, InputDeclaration, Scatter, Span,
+        TaskElement, Type, Workflow, WorkflowElement, WorkflowNestedElement,
+    },
+    workspace::Workspace,
+};
+use std::{collections::HashMap, ops::Deref};
+
+/// A binding of a call input to the in-scope value with the same name, i.e. `input: x = x`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputBinding {
+    /// The name of the input and of the value that is bound to it.
+    pub name: String,
+    /// The declared type of the input.
+    pub input_type: Type,
+    /// The type of the value as seen from the call, which may differ from its declared type if
+    /// it is declared within a scatter or conditional block that does not enclose the call.
+    pub value_type: Type,
+}
+
+/// Suggested bindings for the missing required inputs of a call.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WiringSuggestion {
+    /// The name by which the call is referenced in its workflow.
+    pub call: String,
+    /// The span of the call.
+    pub span: Span,
+    pub bindings: Vec<InputBinding>,
+    /// The missing required inputs for which no matching value is in scope.
+    pub unmatched: Vec<String>,
+    /// An edit that adds all the bindings to the call.
+    pub edit: TextEdit,
+}
+
+/// Suggests bindings for the required inputs that are missing from the calls in the workflow of
+/// `document`. An input is bound to an in-scope workflow input, declaration, or scatter variable
+/// with the same name, if the value's type (taking into account any enclosing scatter or
+/// conditional blocks) is the same as or coercible to the input's type.
+///
+/// The targets of calls are resolved via `workspace`; calls to tasks or workflows that cannot be
+/// resolved are ignored, as are calls that have no missing inputs with matching values. The type
+/// of a scatter variable is only known if the scatter expression is an identifier.
+pub fn suggest_input_wiring(workspace: &Workspace, document: &Document) -> Vec<WiringSuggestion> {
+    let workflow = match document.body_iter().find_map(|element| match element {
+        DocumentElement::Workflow(workflow) => Some(workflow),
+        _ => None,
+    }) {
+        Some(workflow) => workflow,
+        None => return Vec::new(),
+    };
+    let mut collector = Collector::default();
+    collector.workflow(workflow);
+    collector
+        .calls
+        .iter()
+        .filter_map(|(call, span, path)| {
+            let inputs = callee_inputs(workspace, document, call)?;
+            let scope = collector.scope(path);
+            let mut bindings = Vec::new();
+            let mut unmatched = Vec::new();
+            for decl in inputs {
+                let decl = match decl {
+                    InputDeclaration::Unbound(decl) => decl,
+                    InputDeclaration::Bound(_) => continue,
+                };
+                let name = decl.name.as_str();
+                if matches!(decl.type_.deref(), Type::Optional(_))
+                    || call
+                        .inputs
+                        .iter()
+                        .flatten()
+                        .any(|input| input.name.as_str() == name)
+                {
+                    continue;
+                }
+                match scope.get(name) {
+                    Some(value_type) if is_coercible(value_type, &decl.type_) => {
+                        bindings.push(InputBinding {
+                            name: name.to_owned(),
+                            input_type: (*decl.type_).clone(),
+                            value_type: value_type.clone(),
+                        })
+                    }
+                    _ => unmatched.push(name.to_owned()),
+                }
+            }
+            if bindings.is_empty() {
+                return None;
+            }
+            Some(WiringSuggestion {
+                call: fqn::call_name(call).to_owned(),
+                span: (*span).clone(),
+                edit: wiring_edit(call, span, &bindings),
+                bindings,
+                unmatched,
+            })
+        })
+        .collect()
+}
+
+/// Returns each call in `workflow`, its span, and the names and types of the values that are
+/// visible from it (as in `suggest_input_wiring`).
+pub(crate) fn call_scopes(workflow: &Workflow) -> Vec<(&Call, &Span, HashMap<&str, Type>)> {
+    let mut collector = Collector::default();
+    collector.workflow(workflow);
+    collector
+        .calls
+        .iter()
+        .map(|(call, span, path)| (*call, *span, collector.scope(path)))
+        .collect()
+}
+
+/// Returns the input declarations of the task or workflow that is the target of `call`.
+pub(crate) fn callee_inputs<'a>(
+    workspace: &'a Workspace,
+    document: &'a Document,
+    call: &Call,
+) -> Option<Vec<&'a InputDeclaration>> {
+    let (name, namespaces) = call.target.parts.split_last()?;
+    let mut current = document;
+    for namespace in namespaces {
+        current = workspace
+            .resolve_namespace(current, namespace.as_str())?
+            .1?;
+    }
+    current.body_iter().find_map(|element| match element {
+        DocumentElement::Task(task) if task.name.as_str() == name.as_str() => Some(
+            task.body
+                .iter()
+                .filter_map(|element| match element.deref() {
+                    TaskElement::Input(input) => Some(input),
+                    _ => None,
+                })
+                .flat_map(|input| input.declarations.iter().map(|decl| decl.deref()))
+                .collect(),
+        ),
+        DocumentElement::Workflow(workflow) if workflow.name.as_str() == name.as_str() => Some(
+            workflow
+                .body
+                .iter()
+                .filter_map(|element| match element.deref() {
+                    WorkflowElement::Input(input) => Some(input),
+                    _ => None,
+                })
+                .flat_map(|input| input.declarations.iter().map(|decl| decl.deref()))
+                .collect(),
+        ),
+        _ => None,
+    })
+}
+
+/// Returns whether a value of type `from` can be bound to an input of type `to`. Besides
+/// identical types, allows the coercions `Int` to `Float`, `String` to/from `File`, and `Object`
+/// to a struct (whose members can only be checked at runtime).
+pub(crate) fn is_coercible(from: &Type, to: &Type) -> bool {
+    matches!(
+        (from, to),
+        (Type::Int, Type::Float)
+            | (Type::String, Type::File)
+            | (Type::File, Type::String)
+            | (Type::Object, Type::User(_))
+    ) || from.to_string() == to.to_string()
+}
+
+/// Returns an edit that adds `bindings` to the inputs of `call`, whose span is `span`.
+fn wiring_edit(call: &Call, span: &Span, bindings: &[InputBinding]) -> TextEdit {
+    let bindings = bindings
+        .iter()
+        .map(|binding| format!("{} = {}", binding.name, binding.name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    match &call.inputs {
+        Some(inputs) if !inputs.is_empty() => TextEdit::insert(
+            inputs.last().unwrap().span.end.clone(),
+            format!(", {}", bindings),
+        ),
+        Some(_) => {
+            // the call has an empty input section, which we replace along with the rest of the
+            // call since its position is not part of the model
+            let target = call
+                .target
+                .parts
+                .iter()
+                .map(|part| part.as_str())
+                .collect::<Vec<_>>()
+                .join(".");
+            let alias = call
+                .alias
+                .as_ref()
+                .map(|alias| format!(" as {}", alias.as_str()))
+                .unwrap_or_default();
+            TextEdit::replace(
+                span.clone(),
+                format!("call {}{} {{ input: {} }}", target, alias, bindings),
+            )
+        }
+        None => {
+            let end = match &call.alias {
+                Some(alias) => &alias.span.end,
+                None => &call.target.span.end,
+            };
+            TextEdit::insert(end.clone(), format!(" {{ input: {} }}", bindings))
+        }
+    }
+}
+
+/// A scatter or conditional block on the path from the top level of the workflow to an element.
+#[derive(Clone)]
+struct Frame<'a> {
+    id: usize,
+    block: Block,
+    scatter: Option<&'a Scatter>,
+}
+
+/// A workflow input or declaration, and the blocks that enclose it.
+struct Value<'a> {
+    name: &'a str,
+    type_: &'a Anchor<Type>,
+    path: Vec<Frame<'a>>,
+}
+
+#[derive(Default)]
+struct Collector<'a> {
+    blocks: usize,
+    path: Vec<Frame<'a>>,
+    values: Vec<Value<'a>>,
+    calls: Vec<(&'a Call, &'a Span, Vec<Frame<'a>>)>,
+}
+
+impl<'a> Collector<'a> {
+    fn workflow(&mut self, workflow: &'a Workflow) {
+        for element in workflow.body.iter() {
+            match element.deref() {
+                WorkflowElement::Input(input) => {
+                    for decl in input.declarations.iter() {
+                        self.value(decl.name(), decl.type_())
+                    }
+                }
+                WorkflowElement::Declaration(decl) => self.value(&decl.name, &decl.type_),
+                WorkflowElement::Call(call) => self.call(call, &element.span),
+                WorkflowElement::Scatter(scatter) => {
+                    self.block(Block::Scatter, Some(scatter), &scatter.body)
+                }
+                WorkflowElement::Conditional(conditional) => {
+                    self.block(Block::Conditional, None, &conditional.body)
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn value(&mut self, name: &'a Anchor<String>, type_: &'a Anchor<Type>) {
+        self.values.push(Value {
+            name: name.as_str(),
+            type_,
+            path: self.path.clone(),
+        })
+    }
+
+    fn call(&mut self, call: &'a Call, span: &'a Span) {
+        self.calls.push((call, span, self.path.clone()))
+    }
+
+    fn block(
+        &mut self,
+        block: Block,
+        scatter: Option<&'a Scatter>,
+        body: &'a [Anchor<WorkflowNestedElement>],
+    ) {
+        self.blocks += 1;
+        self.path.push(Frame {
+            id: self.blocks,
+            block,
+            scatter,
+        });
+        for element in body.iter() {
+            match element.deref() {
+                WorkflowNestedElement::Declaration(decl) => self.value(&decl.name, &decl.type_),
+                WorkflowNestedElement::Call(call) => self.call(call, &element.span),
+                WorkflowNestedElement::Scatter(scatter) => {
+                    self.block(Block::Scatter, Some(scatter), &scatter.body)
+                }
+                WorkflowNestedElement::Conditional(conditional) => {
+                    self.block(Block::Conditional, None, &conditional.body)
+                }
+            }
+        }
+        self.path.pop();
+    }
+
+    /// Returns the names and types of the values that are visible from an element enclosed by
+    /// the blocks in `path`. A value declared in a block that does not enclose the element is
+    /// visible with the type promoted by the blocks that enclose the value but not the element.
+    fn scope(&self, path: &[Frame<'a>]) -> HashMap<&'a str, Type> {
+        let mut scope = HashMap::new();
+        for value in self.values.iter() {
+            let common = value
+                .path
+                .iter()
+                .zip(path.iter())
+                .take_while(|(a, b)| a.id == b.id)
+                .count();
+            let blocks: Vec<Block> = value.path[common..]
+                .iter()
+                .map(|frame| frame.block)
+                .collect();
+            scope.insert(value.name, promote(value.type_, &blocks));
+        }
+        // scatter variables shadow other values, and inner scatters shadow outer ones
+        for scatter in path.iter().filter_map(|frame| frame.scatter) {
+            let item = match scatter.expression.deref() {
+                Expression::Identifier(name) => match scope.get(name.as_str()) {
+                    Some(Type::Array { item, .. }) => Some((***item).clone()),
+                    _ => None,
+                },
+                _ => None,
+            };
+            match item {
+                Some(item) => scope.insert(scatter.name.as_str(), item),
+                None => scope.remove(scatter.name.as_str()),
+            };
+        }
+        scope
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        edit::apply_edits,
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_suggest_input_wiring() {
+        let text = r#"version 1.1
+
+task align {
+    input {
+        File reads
+        Int threads
+        Float ratio
+        String? sample
+        Int memory = 4
+    }
+    command <<< >>>
+}
+
+workflow wf {
+    input {
+        Array[File] samples
+        Int threads
+    }
+    String ratio = "1"
+    scatter (reads in samples) {
+        call align
+    }
+    call align as again { input: reads = "x" }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let mut workspace = Workspace::new();
+        workspace.insert(doc);
+        let doc = workspace.get(&DocumentSource::Unknown).unwrap();
+        let suggestions = suggest_input_wiring(&workspace, doc);
+        let summary: Vec<_> = suggestions
+            .iter()
+            .map(|suggestion| {
+                (
+                    suggestion.call.as_str(),
+                    suggestion
+                        .bindings
+                        .iter()
+                        .map(|binding| binding.name.as_str())
+                        .collect::<Vec<_>>(),
+                    suggestion.unmatched.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("align", vec!["reads", "threads"], vec!["ratio".to_owned()]),
+                ("again", vec!["threads"], vec!["ratio".to_owned()]),
+            ]
+        );
+        let edits: Vec<TextEdit> = suggestions
+            .into_iter()
+            .map(|suggestion| suggestion.edit)
+            .collect();
+        let wired = apply_edits(text, &edits).unwrap();
+        assert!(wired.contains("call align { input: reads = reads, threads = threads }"));
+        assert!(wired.contains("call align as again { input: reads = \"x\", threads = threads }"));
+    }
+}
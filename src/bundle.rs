@@ -0,0 +1,258 @@
+//! Packaging of a workflow and the documents that it imports for submission to an engine, either
+//! as a zip archive in the layout that Cromwell expects for `workflowDependencies` (i.e.
+//! "imports.zip"), or as a single flattened document.
+use crate::{
+    edit::{apply_edits, TextEdit},
+    model::{DocumentElement, DocumentSource},
+    parsers::WdlParser,
+    transform::{flatten_with, FlattenOptions, Flattened},
+    workspace::{ResolverOptions, Workspace},
+};
+use error_stack::{IntoReport, Result, ResultExt};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Cursor, Write},
+    ops::Deref,
+    path::{Component, Path, PathBuf},
+};
+use thiserror::Error;
+use zip::{write::FileOptions, CompressionMethod, DateTime, ZipWriter};
+
+#[derive(Error, Debug)]
+pub enum BundleError {
+    #[error("error loading document {0}")]
+    Load(PathBuf),
+    #[error("error reading document {0}")]
+    Read(DocumentSource),
+    #[error("error rewriting the imports of document {0}")]
+    Rewrite(DocumentSource),
+    #[error("error flattening document {0}")]
+    Flatten(PathBuf),
+    #[error("error writing zip archive")]
+    Zip,
+}
+
+/// The form of a bundle.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BundleFormat {
+    /// The entrypoint document and a zip archive of the documents that it imports.
+    #[default]
+    Zip,
+    /// A single document with no imports.
+    Flattened,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BundleOptions {
+    pub format: BundleFormat,
+    /// The options with which imports are resolved.
+    pub resolver: ResolverOptions,
+    /// The options with which a flattened bundle is flattened.
+    pub flatten: FlattenOptions,
+}
+
+/// A packaged workflow.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Bundle {
+    /// The text of the entrypoint document (i.e. the `workflowSource`) and a zip archive of the
+    /// documents it imports (i.e. the `workflowDependencies`).
+    Zip { entry: String, imports: Vec<u8> },
+    /// The flattened document, with a source map from it to the original documents.
+    Flattened(Flattened),
+}
+
+/// Returns the deepest directory that contains all of `paths`.
+fn common_root<'a, I: IntoIterator<Item = &'a PathBuf>>(paths: I) -> PathBuf {
+    let mut root: Option<Vec<Component>> = None;
+    for path in paths {
+        let dir: Vec<Component> = path.parent().unwrap_or(path).components().collect();
+        root = Some(match root {
+            None => dir,
+            Some(root) => root
+                .into_iter()
+                .zip(dir)
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+    root.unwrap_or_default().into_iter().collect()
+}
+
+/// Returns `path` relative to `root` with `/` separators, which is how the path is referenced
+/// within the archive.
+fn archive_path(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let parts: Vec<String> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    parts.join("/")
+}
+
+/// Packages the document at `entry` and all the local documents that it imports, directly or
+/// indirectly, into a `Bundle`. The bundle is deterministic, i.e. it depends only on the contents
+/// of the documents and not on, e.g., file modification times.
+///
+/// In a zip bundle, each imported document is stored at its path relative to the deepest
+/// directory that contains all the documents, and the URI of each import of a local document
+/// (including those of the entrypoint) is rewritten to that path, so that imports resolve
+/// relative to the root of the archive. Imports of remote documents are left as-is.
+pub fn pack<P: WdlParser, F: AsRef<Path>>(
+    parser: &mut P,
+    entry: F,
+    options: &BundleOptions,
+) -> Result<Bundle, BundleError> {
+    let entry = entry.as_ref();
+    let mut workspace = Workspace::with_options(options.resolver.clone());
+    let entry_source = workspace
+        .load(parser, entry)
+        .change_context_lazy(|| BundleError::Load(entry.to_owned()))?;
+    let mut texts = HashMap::new();
+    for document in workspace.documents() {
+        if let DocumentSource::File(path) = &document.source {
+            let text = fs::read_to_string(path)
+                .into_report()
+                .change_context_lazy(|| BundleError::Read(document.source.clone()))?;
+            texts.insert(document.source.clone(), text);
+        }
+    }
+    if options.format == BundleFormat::Flattened {
+        return flatten_with(&workspace, &texts, &entry_source, &options.flatten)
+            .change_context_lazy(|| BundleError::Flatten(entry.to_owned()))
+            .map(Bundle::Flattened);
+    }
+
+    let root = common_root(texts.keys().filter_map(|source| match source {
+        DocumentSource::File(path) => Some(path),
+        _ => None,
+    }));
+    let mut files = Vec::new();
+    let mut entry_text = String::new();
+    for (source, text) in texts.iter() {
+        let document = workspace.get(source).unwrap();
+        let edits: Vec<TextEdit> = document
+            .body
+            .iter()
+            .filter_map(|element| match element.deref() {
+                DocumentElement::Import(import) => {
+                    match workspace.resolve_source(source, import.uri.as_str()) {
+                        DocumentSource::File(path) => Some(TextEdit::replace(
+                            import.uri.span.clone(),
+                            format!("\"{}\"", archive_path(&root, &path)),
+                        )),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            })
+            .collect();
+        let rewritten = apply_edits(text, &edits)
+            .change_context_lazy(|| BundleError::Rewrite(source.clone()))?;
+        match source {
+            _ if *source == entry_source => entry_text = rewritten,
+            DocumentSource::File(path) => files.push((archive_path(&root, path), rewritten)),
+            _ => (),
+        }
+    }
+    files.sort();
+
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let file_options = FileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(DateTime::default())
+        .unix_permissions(0o644);
+    for (path, text) in files {
+        writer
+            .start_file(path, file_options)
+            .into_report()
+            .change_context(BundleError::Zip)?;
+        writer
+            .write_all(text.as_bytes())
+            .into_report()
+            .change_context(BundleError::Zip)?;
+    }
+    let imports = writer
+        .finish()
+        .into_report()
+        .change_context(BundleError::Zip)?
+        .into_inner();
+    Ok(Bundle::Zip {
+        entry: entry_text,
+        imports,
+    })
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::parsers::PestParser;
+    use pretty_assertions::assert_eq;
+    use std::io::Read;
+    use zip::ZipArchive;
+
+    #[test]
+    fn test_pack() {
+        let root = std::env::temp_dir().join(format!("wdl-bundle-{}", std::process::id()));
+        let workflows = root.join("workflows");
+        let lib = root.join("lib");
+        fs::create_dir_all(&workflows).unwrap();
+        fs::create_dir_all(&lib).unwrap();
+        fs::write(
+            workflows.join("main.wdl"),
+            "version 1.1\nimport \"../lib/align.wdl\"\nworkflow main {\n    call align.bwa\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            lib.join("align.wdl"),
+            "version 1.1\nimport \"common.wdl\"\ntask bwa {\n    command <<< >>>\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            lib.join("common.wdl"),
+            "version 1.1\nstruct Reads {\n    File fastq\n}\n",
+        )
+        .unwrap();
+
+        let mut parser = PestParser::new();
+        let options = BundleOptions::default();
+        let bundle = pack(&mut parser, workflows.join("main.wdl"), &options).unwrap();
+        let (entry, imports) = match &bundle {
+            Bundle::Zip { entry, imports } => (entry, imports),
+            _ => panic!("expected a zip bundle"),
+        };
+        assert_eq!(
+            entry,
+            "version 1.1\nimport \"lib/align.wdl\"\nworkflow main {\n    call align.bwa\n}\n"
+        );
+        let mut archive = ZipArchive::new(Cursor::new(imports.clone())).unwrap();
+        let mut names: Vec<&str> = archive.file_names().collect();
+        names.sort();
+        assert_eq!(names, vec!["lib/align.wdl", "lib/common.wdl"]);
+        let mut align = String::new();
+        archive
+            .by_name("lib/align.wdl")
+            .unwrap()
+            .read_to_string(&mut align)
+            .unwrap();
+        assert!(align.starts_with("version 1.1\nimport \"lib/common.wdl\"\n"));
+        assert_eq!(
+            pack(&mut parser, workflows.join("main.wdl"), &options).unwrap(),
+            bundle
+        );
+
+        let options = BundleOptions {
+            format: BundleFormat::Flattened,
+            ..Default::default()
+        };
+        match pack(&mut parser, workflows.join("main.wdl"), &options).unwrap() {
+            Bundle::Flattened(flattened) => {
+                assert!(flattened.text.contains("task bwa") && flattened.text.contains("call bwa"));
+                assert!(!flattened.source_map.is_empty());
+            }
+            _ => panic!("expected a flattened bundle"),
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
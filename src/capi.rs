@@ -0,0 +1,155 @@
+//! C API for parsing WDL documents from other languages. Parsed documents are returned as opaque
+//! handles that can be serialized to JSON. All strings are UTF-8 and NUL-terminated.
+//!
+//! The header `include/wdl.h` is generated from this module with
+//! `cbindgen --config cbindgen.toml --output include/wdl.h`. To build a shared library, run
+//! `cargo rustc --release --features capi --crate-type cdylib`.
+use crate::{
+    model::{Document, DocumentSource},
+    parsers::{PestParser, WdlParser},
+};
+use std::{
+    ffi::{CStr, CString},
+    os::raw::c_char,
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+/// An opaque handle to a parsed document.
+pub struct WdlDocument(Document);
+
+/// Parses the WDL source `text`. On success, returns a document that must be freed with
+/// `wdl_free`. On failure, returns null and, if `error` is not null, sets `*error` to an error
+/// message that must be freed with `wdl_string_free`.
+///
+/// # Safety
+///
+/// `text` must be a valid pointer to a NUL-terminated string, and `error` must be null or a valid
+/// pointer to a `char *`.
+#[no_mangle]
+pub unsafe extern "C" fn wdl_parse(
+    text: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut WdlDocument {
+    let result = catch(|| {
+        if text.is_null() {
+            return Err("text is null".to_owned());
+        }
+        let text = CStr::from_ptr(text).to_str().map_err(|e| e.to_string())?;
+        PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .map_err(|report| format!("{:?}", report))
+    });
+    match result {
+        Ok(document) => Box::into_raw(Box::new(WdlDocument(document))),
+        Err(message) => {
+            set_error(error, message);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Serializes `document` to JSON. On success, returns a string that must be freed with
+/// `wdl_string_free`. On failure, returns null and, if `error` is not null, sets `*error` to an
+/// error message that must be freed with `wdl_string_free`.
+///
+/// # Safety
+///
+/// `document` must be a pointer returned by `wdl_parse` that has not been freed, and `error` must
+/// be null or a valid pointer to a `char *`.
+#[no_mangle]
+pub unsafe extern "C" fn wdl_document_to_json(
+    document: *const WdlDocument,
+    error: *mut *mut c_char,
+) -> *mut c_char {
+    let result = catch(|| {
+        if document.is_null() {
+            return Err("document is null".to_owned());
+        }
+        serde_json::to_string(&(*document).0).map_err(|e| e.to_string())
+    });
+    match result {
+        Ok(json) => into_c_string(json),
+        Err(message) => {
+            set_error(error, message);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Frees a document returned by `wdl_parse`. Does nothing if `document` is null.
+///
+/// # Safety
+///
+/// `document` must be null or a pointer returned by `wdl_parse` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn wdl_free(document: *mut WdlDocument) {
+    if !document.is_null() {
+        drop(Box::from_raw(document))
+    }
+}
+
+/// Frees a string returned by this API. Does nothing if `s` is null.
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by this API that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn wdl_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s))
+    }
+}
+
+/// Calls `f`, converting a panic into an error so that it does not unwind across the FFI boundary.
+fn catch<T, F: FnOnce() -> Result<T, String>>(f: F) -> Result<T, String> {
+    panic::catch_unwind(AssertUnwindSafe(f))
+        .unwrap_or_else(|_| Err("unexpected panic in wdl library".to_owned()))
+}
+
+fn into_c_string(s: String) -> *mut c_char {
+    // strings cannot contain interior NUL bytes
+    CString::new(s.replace('\0', ""))
+        .unwrap_or_default()
+        .into_raw()
+}
+
+unsafe fn set_error(error: *mut *mut c_char, message: String) {
+    if !error.is_null() {
+        *error = into_c_string(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::Value as JsonValue;
+
+    #[test]
+    fn test_capi() {
+        let text = CString::new("version 1.1\n\nworkflow foo {}\n").unwrap();
+        let mut error: *mut c_char = ptr::null_mut();
+        unsafe {
+            let document = wdl_parse(text.as_ptr(), &mut error);
+            assert!(!document.is_null());
+            assert!(error.is_null());
+            let json = wdl_document_to_json(document, &mut error);
+            assert!(!json.is_null());
+            let value: JsonValue =
+                serde_json::from_str(CStr::from_ptr(json).to_str().unwrap()).unwrap();
+            assert_eq!(
+                value["version"]["element"]["identifier"]["element"],
+                JsonValue::String("V1_1".to_owned())
+            );
+            wdl_string_free(json);
+            wdl_free(document);
+
+            let text = CString::new("version 1.1\n\nworkflow {}\n").unwrap();
+            let document = wdl_parse(text.as_ptr(), &mut error);
+            assert!(document.is_null());
+            assert!(!error.is_null());
+            wdl_string_free(error);
+        }
+    }
+}
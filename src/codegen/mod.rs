@@ -0,0 +1,2 @@
+//! Generation of code in other languages from WDL documents.
+pub mod rust;
@@ -0,0 +1,299 @@
+//! Generation of Rust types from WDL struct definitions, so that programs written in Rust (e.g.
+//! task wrappers) can deserialize workflow inputs and outputs in a type-safe way. The generated
+//! types use serde derives that match the JSON representation of WDL values.
+//!
+//! `generate_file` is intended for build scripts:
+//!
+//! ```no_run
+//! # #[cfg(feature = "pest")]
+//! # fn main() {
+//! use wdl::{codegen::rust::{generate_file, RustOptions}, parsers::PestParser};
+//!
+//! let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("structs.rs");
+//! generate_file(&mut PestParser::new(), "wdl/structs.wdl", out, &RustOptions::default()).unwrap();
+//! # }
+//! # #[cfg(not(feature = "pest"))]
+//! # fn main() {}
+//! ```
+use crate::{
+    docs::{doc_comment, DocCommentStyle},
+    model::{Document, DocumentElement, Type},
+    parsers::WdlParser,
+};
+use error_stack::{IntoReport, Result, ResultExt};
+use std::{fmt::Write, fs, ops::Deref, path::Path, path::PathBuf};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum CodegenError {
+    #[error("error parsing {0}")]
+    Parse(PathBuf),
+    #[error("error writing {0}")]
+    Write(PathBuf),
+}
+
+/// The keywords that cannot be used as Rust identifiers without the `r#` prefix.
+const KEYWORDS: [&str; 38] = [
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RustOptions {
+    /// The traits that are derived by each generated type, in addition to serde's `Serialize`
+    /// and `Deserialize` if `serde` is set.
+    pub derives: Vec<String>,
+    /// Whether to derive serde's `Serialize` and `Deserialize`.
+    pub serde: bool,
+    /// The Rust type of `File` values.
+    pub file_type: String,
+}
+
+impl Default for RustOptions {
+    fn default() -> Self {
+        Self {
+            derives: vec![
+                "Clone".to_owned(),
+                "Debug".to_owned(),
+                "PartialEq".to_owned(),
+            ],
+            serde: true,
+            file_type: "std::path::PathBuf".to_owned(),
+        }
+    }
+}
+
+/// Returns `name` in upper camel case, e.g. `sample_info` -> `SampleInfo`.
+fn type_name(name: &str) -> String {
+    name.split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().unwrap().to_ascii_uppercase();
+            std::iter::once(first).chain(chars).collect::<String>()
+        })
+        .collect()
+}
+
+/// Returns `name` in snake case, e.g. `readGroup` -> `read_group`.
+fn field_name(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len());
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 && !snake.ends_with('_') {
+                snake.push('_')
+            }
+            snake.push(c.to_ascii_lowercase())
+        } else {
+            snake.push(c)
+        }
+    }
+    snake
+}
+
+fn write_docs(code: &mut String, indent: &str, docs: &str) {
+    for line in docs.lines() {
+        let space = if line.is_empty() { "" } else { " " };
+        writeln!(code, "{}///{}{}", indent, space, line).unwrap();
+    }
+}
+
+struct Generator<'a> {
+    options: &'a RustOptions,
+    /// Whether any of the generated types uses the `Pair` type.
+    uses_pair: bool,
+}
+
+impl<'a> Generator<'a> {
+    fn rust_type(&mut self, type_: &Type) -> String {
+        match type_ {
+            Type::Boolean => "bool".to_owned(),
+            Type::Int => "i64".to_owned(),
+            Type::Float => "f64".to_owned(),
+            Type::String => "String".to_owned(),
+            Type::File => self.options.file_type.clone(),
+            Type::Array { item, .. } => format!("Vec<{}>", self.rust_type(item)),
+            Type::Map { key, value } => {
+                // the keys of maps are serialized as JSON object keys
+                let key = match key.deref().deref() {
+                    Type::Int | Type::Boolean | Type::File | Type::String => self.rust_type(key),
+                    _ => "String".to_owned(),
+                };
+                format!(
+                    "std::collections::HashMap<{}, {}>",
+                    key,
+                    self.rust_type(value)
+                )
+            }
+            Type::Pair { left, right } => {
+                self.uses_pair = true;
+                format!("Pair<{}, {}>", self.rust_type(left), self.rust_type(right))
+            }
+            Type::Object => "serde_json::Value".to_owned(),
+            Type::User(name) => type_name(name),
+            Type::Optional(inner) => format!("Option<{}>", self.rust_type(inner)),
+        }
+    }
+
+    fn derives(&self) -> String {
+        let mut derives = self.options.derives.clone();
+        if self.options.serde {
+            derives.extend([
+                "serde::Serialize".to_owned(),
+                "serde::Deserialize".to_owned(),
+            ])
+        }
+        format!("#[derive({})]\n", derives.join(", "))
+    }
+}
+
+/// Returns the source of a Rust module that defines a type for each struct of `document`. Doc
+/// comments of the structs and their fields (in the default `DocCommentStyle`) become Rust doc
+/// comments.
+///
+/// Types are mapped as follows: `Boolean`, `Int`, `Float`, and `String` become `bool`, `i64`,
+/// `f64`, and `String`; `File` becomes `options.file_type`; `Array[T]` becomes `Vec<T>`;
+/// `Map[K, V]` becomes `HashMap<K, V>`; `Pair[L, R]` becomes a generated `Pair<L, R>` type with
+/// `left` and `right` fields; `Object` becomes `serde_json::Value`; and `T?` becomes `Option<T>`.
+/// Struct names are converted to upper camel case and field names to snake case (with serde
+/// `rename` attributes if they change).
+pub fn generate(document: &Document, options: &RustOptions) -> String {
+    let mut generator = Generator {
+        options,
+        uses_pair: false,
+    };
+    let mut types = Vec::new();
+    for element in document.body.iter() {
+        let struct_ = match element.deref() {
+            DocumentElement::Struct(struct_) => struct_,
+            _ => continue,
+        };
+        let mut code = String::new();
+        let docs = |line: usize| doc_comment(&document.comments, line, DocCommentStyle::default());
+        if let Some(docs) = docs(element.span.start.line) {
+            write_docs(&mut code, "", &docs)
+        }
+        code.push_str(&generator.derives());
+        writeln!(code, "pub struct {} {{", type_name(&struct_.name)).unwrap();
+        for field in struct_.fields.iter() {
+            if let Some(docs) = docs(field.span.start.line) {
+                write_docs(&mut code, "    ", &docs)
+            }
+            let wdl_name = field.name.as_str();
+            let mut name = field_name(wdl_name);
+            if options.serde {
+                let mut attributes = Vec::new();
+                if name != wdl_name {
+                    attributes.push(format!("rename = \"{}\"", wdl_name))
+                }
+                if matches!(field.type_.deref(), Type::Optional(_)) {
+                    attributes.push("default".to_owned());
+                    attributes.push("skip_serializing_if = \"Option::is_none\"".to_owned());
+                }
+                if !attributes.is_empty() {
+                    writeln!(code, "    #[serde({})]", attributes.join(", ")).unwrap();
+                }
+            }
+            if KEYWORDS.contains(&name.as_str()) {
+                name.insert_str(0, "r#")
+            }
+            let type_ = generator.rust_type(&field.type_);
+            writeln!(code, "    pub {}: {},", name, type_).unwrap();
+        }
+        code.push_str("}\n");
+        types.push(code)
+    }
+    if generator.uses_pair {
+        let mut pair = String::from("/// A WDL `Pair`.\n");
+        pair.push_str(&generator.derives());
+        pair.push_str("pub struct Pair<L, R> {\n    pub left: L,\n    pub right: R,\n}\n");
+        types.push(pair)
+    }
+    let mut module = String::from("// Generated from WDL struct definitions. Do not edit.\n\n");
+    module.push_str(&types.join("\n"));
+    module
+}
+
+/// Parses the WDL document at `input` and writes the Rust types generated for its structs to
+/// `output`. When called from a build script, Cargo is instructed to run the script again if
+/// `input` changes.
+pub fn generate_file<P: WdlParser, I: AsRef<Path>, O: AsRef<Path>>(
+    parser: &mut P,
+    input: I,
+    output: O,
+    options: &RustOptions,
+) -> Result<(), CodegenError> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    let document = parser
+        .parse_file(input)
+        .change_context_lazy(|| CodegenError::Parse(input.to_owned()))?;
+    fs::write(output, generate(&document, options))
+        .into_report()
+        .change_context_lazy(|| CodegenError::Write(output.to_owned()))?;
+    if std::env::var_os("CARGO_MANIFEST_DIR").is_some() && std::env::var_os("OUT_DIR").is_some() {
+        println!("cargo:rerun-if-changed={}", input.display());
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{model::DocumentSource, parsers::PestParser};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_generate() {
+        let text = r#"version 1.1
+
+## A sequencing sample.
+struct sample_info {
+    ## The sample ID.
+    String id
+    Array[File]+ reads
+    Map[String, Float]? qualities
+    Pair[Int, library]? readGroup
+    Object type
+}
+
+struct library {
+    Boolean paired
+}
+"#;
+        let document = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        assert_eq!(
+            generate(&document, &RustOptions::default()),
+            r#"// Generated from WDL struct definitions. Do not edit.
+
+/// A sequencing sample.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SampleInfo {
+    /// The sample ID.
+    pub id: String,
+    pub reads: Vec<std::path::PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qualities: Option<std::collections::HashMap<String, f64>>,
+    #[serde(rename = "readGroup", default, skip_serializing_if = "Option::is_none")]
+    pub read_group: Option<Pair<i64, Library>>,
+    pub r#type: serde_json::Value,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Library {
+    pub paired: bool,
+}
+
+/// A WDL `Pair`.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Pair<L, R> {
+    pub left: L,
+    pub right: R,
+}
+"#
+        );
+    }
+}
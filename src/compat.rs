@@ -0,0 +1,614 @@
+//! Compatibility of documents with workflow engines. Engines differ in the WDL versions and
+//! features that they support, and some interpret parts of the language (mostly runtime
+//! attributes) in their own ways. An `EngineProfile` lists the constructs that an engine does not
+//! support or interprets differently, and a `ProfileDatabase` holds the profiles of the target
+//! engines by name. The database has built-in profiles for common engines, to which users can
+//! add their own (or replace the built-in ones).
+//!
+//! `report` lists the uses of incompatible constructs in a document for a single profile, and
+//! `CompatibilityLint` reports them as warnings for each of a set of target profiles.
+//!
+//! The built-in profiles are approximations of the documented limitations of the engines at the
+//! given releases and are not exhaustive.
+use crate::{
+    diagnostics::Diagnostic,
+    lint::Lint,
+    model::{
+        Call, Document, Expression, ObjectKind, RuntimeKey, Span, Type, VersionIdentifier,
+        WorkflowElement, WorkflowNestedElement,
+    },
+    tree::{walk, Node},
+};
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter, Result as FmtResult},
+    ops::Deref,
+};
+
+pub const ENGINE_COMPATIBILITY: &str = "engine_compatibility";
+
+/// A language construct whose support differs between engines.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Construct {
+    /// A document that declares the given WDL version.
+    Version(VersionIdentifier),
+    /// The `Object` type.
+    ObjectType,
+    /// An `object { ... }` literal.
+    ObjectLiteral,
+    /// A struct literal, e.g. `Sample { name: "x" }`.
+    StructLiteral,
+    /// The `None` literal.
+    NoneLiteral,
+    /// A call of the standard library function with the given name.
+    Function(String),
+    /// A runtime attribute with the given key, by its canonical name (e.g. `container` for both
+    /// `container` and `docker`).
+    RuntimeKey(String),
+    /// A call input without an expression, e.g. `call t { input: x }`.
+    ImplicitCallInput,
+}
+
+impl Display for Construct {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Version(version) => write!(f, "WDL version {}", version),
+            Self::ObjectType => write!(f, "the Object type"),
+            Self::ObjectLiteral => write!(f, "`object` literal syntax"),
+            Self::StructLiteral => write!(f, "struct literal syntax"),
+            Self::NoneLiteral => write!(f, "the None literal"),
+            Self::Function(name) => write!(f, "the {} function", name),
+            Self::RuntimeKey(key) => write!(f, "the {} runtime attribute", key),
+            Self::ImplicitCallInput => write!(f, "the call input shorthand (e.g. `input: x`)"),
+        }
+    }
+}
+
+/// A use of a construct in a document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConstructUse {
+    pub construct: Construct,
+    pub span: Span,
+}
+
+/// Returns the uses of the constructs in `Construct` in `document`, ordered by position.
+pub fn construct_uses(document: &Document) -> Vec<ConstructUse> {
+    let mut uses = Vec::new();
+    let mut add = |construct: Construct, span: &Span| {
+        uses.push(ConstructUse {
+            construct,
+            span: span.clone(),
+        })
+    };
+    let calls = |call: &Call, add: &mut dyn FnMut(Construct, &Span)| {
+        for input in call.inputs.iter().flatten() {
+            if input.expression.is_none() {
+                add(Construct::ImplicitCallInput, &input.span)
+            }
+        }
+    };
+    walk(document, &mut |node, _| match node {
+        Node::VersionIdentifier(version) => {
+            add(Construct::Version(version.deref().clone()), &version.span)
+        }
+        Node::Type(type_) if matches!(type_.deref(), Type::Object) => {
+            add(Construct::ObjectType, &type_.span)
+        }
+        Node::Expression(expression) => match expression.deref() {
+            Expression::Object(object) if object.kind == ObjectKind::Object => {
+                add(Construct::ObjectLiteral, &expression.span)
+            }
+            Expression::Object(_) => add(Construct::StructLiteral, &expression.span),
+            Expression::None => add(Construct::NoneLiteral, &expression.span),
+            Expression::Apply(apply) => {
+                add(Construct::Function((*apply.name).clone()), &apply.name.span)
+            }
+            _ => (),
+        },
+        Node::RuntimeAttribute(attribute) => add(
+            Construct::RuntimeKey(
+                RuntimeKey::from_name(attribute.name.as_str())
+                    .name()
+                    .to_owned(),
+            ),
+            &attribute.name.span,
+        ),
+        Node::WorkflowElement(element) => {
+            if let WorkflowElement::Call(call) = element.deref() {
+                calls(call, &mut add)
+            }
+        }
+        Node::WorkflowNestedElement(element) => {
+            if let WorkflowNestedElement::Call(call) = element.deref() {
+                calls(call, &mut add)
+            }
+        }
+        _ => (),
+    });
+    uses.sort_by(|a, b| a.span.cmp(&b.span));
+    uses
+}
+
+/// How an engine deviates from the spec for a construct.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IncompatibilityKind {
+    /// Documents that use the construct are rejected, or the construct does not work.
+    Unsupported,
+    /// The construct is accepted but interpreted differently than the spec requires (e.g. a
+    /// runtime attribute that is ignored or that expects a different format).
+    Different,
+}
+
+/// A construct that an engine does not support or interprets differently.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Incompatibility {
+    pub construct: Construct,
+    pub kind: IncompatibilityKind,
+    /// An explanation of how the engine deviates, if it is not evident from the kind.
+    pub note: Option<String>,
+    /// A suggested alternative to the construct that the engine supports.
+    pub alternative: Option<String>,
+}
+
+impl Incompatibility {
+    pub fn unsupported(construct: Construct) -> Self {
+        Self {
+            construct,
+            kind: IncompatibilityKind::Unsupported,
+            note: None,
+            alternative: None,
+        }
+    }
+
+    pub fn different<N: Into<String>>(construct: Construct, note: N) -> Self {
+        Self {
+            construct,
+            kind: IncompatibilityKind::Different,
+            note: Some(note.into()),
+            alternative: None,
+        }
+    }
+
+    pub fn with_note<N: Into<String>>(mut self, note: N) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+
+    pub fn with_alternative<A: Into<String>>(mut self, alternative: A) -> Self {
+        self.alternative = Some(alternative.into());
+        self
+    }
+}
+
+/// The constructs that an engine (at a given release) does not support or interprets
+/// differently.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EngineProfile {
+    /// The name by which the profile is identified, e.g. `cromwell-86`.
+    pub name: String,
+    /// The display name of the engine, e.g. `Cromwell 86`.
+    pub engine: String,
+    incompatibilities: BTreeMap<Construct, Incompatibility>,
+}
+
+impl EngineProfile {
+    pub fn new<N: Into<String>, E: Into<String>>(name: N, engine: E) -> Self {
+        Self {
+            name: name.into(),
+            engine: engine.into(),
+            incompatibilities: BTreeMap::new(),
+        }
+    }
+
+    /// Adds `incompatibility`, replacing any other incompatibility of the same construct.
+    pub fn with(mut self, incompatibility: Incompatibility) -> Self {
+        self.incompatibilities
+            .insert(incompatibility.construct.clone(), incompatibility);
+        self
+    }
+
+    /// Returns how the engine deviates for `construct`, or `None` if it supports the construct
+    /// as specified.
+    pub fn incompatibility(&self, construct: &Construct) -> Option<&Incompatibility> {
+        self.incompatibilities.get(construct)
+    }
+
+    pub fn incompatibilities(&self) -> impl Iterator<Item = &Incompatibility> {
+        self.incompatibilities.values()
+    }
+
+    /// Cromwell 86, which supports WDL 1.0 (and draft-2) but not WDL 1.1.
+    pub fn cromwell_86() -> Self {
+        Self::new("cromwell-86", "Cromwell 86")
+            .with(
+                Incompatibility::unsupported(Construct::Version(VersionIdentifier::V1_1))
+                    .with_alternative("version 1.0"),
+            )
+            .with(Incompatibility::unsupported(Construct::Version(
+                VersionIdentifier::Development,
+            )))
+            .with(
+                Incompatibility::different(
+                    Construct::RuntimeKey("gpu".to_owned()),
+                    "GPUs are requested with the gpuCount and gpuType attributes",
+                )
+                .with_alternative("gpuCount: 1"),
+            )
+            .with(Incompatibility::different(
+                Construct::RuntimeKey("disks".to_owned()),
+                "on cloud backends, disks must have the form `local-disk <size> <type>` (e.g. \
+                 `local-disk 10 SSD`)",
+            ))
+    }
+
+    /// miniwdl, which supports WDL 1.0, 1.1, and development but runs tasks on a single host.
+    pub fn miniwdl() -> Self {
+        let ignored = |key: &str| {
+            Incompatibility::different(
+                Construct::RuntimeKey(key.to_owned()),
+                "the attribute is ignored by the local backend",
+            )
+        };
+        Self::new("miniwdl", "miniwdl")
+            .with(ignored("disks"))
+            .with(ignored("preemptible"))
+            .with(ignored("zones"))
+            .with(ignored("bootDiskSizeGb"))
+    }
+
+    /// dxWDL, which supports WDL 1.0 (and draft-2) on DNAnexus.
+    pub fn dxwdl() -> Self {
+        Self::new("dxwdl", "dxWDL")
+            .with(
+                Incompatibility::unsupported(Construct::Version(VersionIdentifier::V1_1))
+                    .with_alternative("version 1.0"),
+            )
+            .with(Incompatibility::unsupported(Construct::Version(
+                VersionIdentifier::Development,
+            )))
+            .with(Incompatibility::unsupported(Construct::ObjectType).with_alternative("a struct"))
+            .with(
+                Incompatibility::unsupported(Construct::ObjectLiteral)
+                    .with_alternative("a struct literal or a Map"),
+            )
+            .with(Incompatibility::different(
+                Construct::RuntimeKey("disks".to_owned()),
+                "instance types are selected from the memory, cpu, and disks attributes, or \
+                 with the dx_instance_type attribute",
+            ))
+    }
+}
+
+/// A set of engine profiles, by name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProfileDatabase {
+    profiles: BTreeMap<String, EngineProfile>,
+}
+
+impl Default for ProfileDatabase {
+    /// Returns a database with the built-in profiles.
+    fn default() -> Self {
+        let mut database = Self::empty();
+        for profile in [
+            EngineProfile::cromwell_86(),
+            EngineProfile::miniwdl(),
+            EngineProfile::dxwdl(),
+        ] {
+            database.register(profile)
+        }
+        database
+    }
+}
+
+impl ProfileDatabase {
+    /// Returns a database with the built-in profiles.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a database without any profiles.
+    pub fn empty() -> Self {
+        Self {
+            profiles: BTreeMap::new(),
+        }
+    }
+
+    /// Adds `profile`, replacing any profile with the same name.
+    pub fn register(&mut self, profile: EngineProfile) {
+        self.profiles.insert(profile.name.clone(), profile);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&EngineProfile> {
+        self.profiles.get(name)
+    }
+
+    /// Returns the profiles, ordered by name.
+    pub fn profiles(&self) -> impl Iterator<Item = &EngineProfile> {
+        self.profiles.values()
+    }
+}
+
+/// A use of a construct in a document that an engine does not support or interprets
+/// differently.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Finding {
+    pub span: Span,
+    pub incompatibility: Incompatibility,
+}
+
+impl Finding {
+    pub fn construct(&self) -> &Construct {
+        &self.incompatibility.construct
+    }
+
+    pub fn kind(&self) -> IncompatibilityKind {
+        self.incompatibility.kind
+    }
+
+    /// Returns a description of how `engine` deviates for this use, with the suggested
+    /// alternative, if any.
+    pub fn message(&self, engine: &str) -> String {
+        let incompatibility = &self.incompatibility;
+        let mut message = match incompatibility.kind {
+            IncompatibilityKind::Unsupported => {
+                format!(
+                    "{} is not supported by {}",
+                    incompatibility.construct, engine
+                )
+            }
+            IncompatibilityKind::Different => format!(
+                "{} interprets {} differently",
+                engine, incompatibility.construct
+            ),
+        };
+        if let Some(note) = &incompatibility.note {
+            message.push_str(": ");
+            message.push_str(note);
+        }
+        if let Some(alternative) = &incompatibility.alternative {
+            message.push_str(&format!(" (use {} instead)", alternative));
+        }
+        message
+    }
+}
+
+/// The uses of constructs in a document that an engine does not support or interprets
+/// differently, ordered by position.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompatibilityReport {
+    /// The display name of the engine.
+    pub engine: String,
+    pub findings: Vec<Finding>,
+}
+
+impl CompatibilityReport {
+    /// Whether the engine supports all of the constructs in the document, although it may
+    /// interpret some of them differently.
+    pub fn is_supported(&self) -> bool {
+        self.unsupported().next().is_none()
+    }
+
+    /// Returns the findings of constructs that the engine does not support.
+    pub fn unsupported(&self) -> impl Iterator<Item = &Finding> {
+        self.findings
+            .iter()
+            .filter(|finding| finding.kind() == IncompatibilityKind::Unsupported)
+    }
+
+    /// Returns the findings of constructs that the engine interprets differently.
+    pub fn different(&self) -> impl Iterator<Item = &Finding> {
+        self.findings
+            .iter()
+            .filter(|finding| finding.kind() == IncompatibilityKind::Different)
+    }
+
+    /// Returns a warning for each finding.
+    pub fn to_diagnostics(&self) -> Vec<Diagnostic> {
+        self.findings
+            .iter()
+            .map(|finding| {
+                Diagnostic::warning(
+                    ENGINE_COMPATIBILITY,
+                    finding.message(&self.engine),
+                    finding.span.clone(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Displays one finding per line, with the (one-based) line and column of its start.
+impl Display for CompatibilityReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        for finding in self.findings.iter() {
+            writeln!(
+                f,
+                "{}:{}: {}",
+                finding.span.start.line + 1,
+                finding.span.start.column + 1,
+                finding.message(&self.engine)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns a report of every construct, standard library function, and runtime attribute in
+/// `document` that the engine of `profile` does not support or interprets differently.
+pub fn report(document: &Document, profile: &EngineProfile) -> CompatibilityReport {
+    let findings = construct_uses(document)
+        .into_iter()
+        .filter_map(|use_| {
+            profile
+                .incompatibility(&use_.construct)
+                .map(|incompatibility| Finding {
+                    span: use_.span,
+                    incompatibility: incompatibility.clone(),
+                })
+        })
+        .collect();
+    CompatibilityReport {
+        engine: profile.engine.clone(),
+        findings,
+    }
+}
+
+/// Reports the uses of constructs that any of the target engines does not support or interprets
+/// differently as warnings, one per use and engine.
+#[derive(Clone, Debug, Default)]
+pub struct CompatibilityLint {
+    targets: Vec<EngineProfile>,
+}
+
+impl CompatibilityLint {
+    pub fn new(targets: Vec<EngineProfile>) -> Self {
+        Self { targets }
+    }
+
+    /// Returns a lint that targets the profiles in `database` with the given names. Names that
+    /// are not in the database are ignored.
+    pub fn from_database(database: &ProfileDatabase, names: &[&str]) -> Self {
+        Self::new(
+            names
+                .iter()
+                .filter_map(|name| database.get(name).cloned())
+                .collect(),
+        )
+    }
+}
+
+impl Lint for CompatibilityLint {
+    fn name(&self) -> &'static str {
+        ENGINE_COMPATIBILITY
+    }
+
+    fn check(&self, document: &Document) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = self
+            .targets
+            .iter()
+            .flat_map(|profile| report(document, profile).to_diagnostics())
+            .collect();
+        // stable, so the diagnostics of each use remain in the order of the targets
+        diagnostics.sort_by(|a, b| a.span.cmp(&b.span));
+        diagnostics
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    const TEXT: &str = r#"version 1.1
+
+task t {
+    input {
+        Object o
+    }
+    String s = sub("a", "a", "b")
+    command <<< >>>
+    runtime {
+        docker: "ubuntu:22.04"
+        disks: "10 GB"
+    }
+}
+
+workflow w {
+    call t as a
+}
+"#;
+
+    #[test]
+    fn test_compatibility() {
+        let doc = PestParser::new()
+            .parse_text(TEXT, DocumentSource::Unknown)
+            .unwrap();
+        let constructs: Vec<Construct> = construct_uses(&doc)
+            .into_iter()
+            .map(|use_| use_.construct)
+            .collect();
+        assert_eq!(
+            constructs,
+            vec![
+                Construct::Version(VersionIdentifier::V1_1),
+                Construct::ObjectType,
+                Construct::Function("sub".to_owned()),
+                Construct::RuntimeKey("container".to_owned()),
+                Construct::RuntimeKey("disks".to_owned()),
+            ]
+        );
+
+        let mut database = ProfileDatabase::new();
+        database.register(EngineProfile::new("old", "Old Engine").with(
+            Incompatibility::unsupported(Construct::Function("sub".to_owned())),
+        ));
+        let lint = CompatibilityLint::from_database(&database, &["dxwdl", "old", "unknown"]);
+        let messages: Vec<(usize, String)> = lint
+            .check(&doc)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.span.start.line, diagnostic.message))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    0,
+                    "WDL version 1.1 is not supported by dxWDL (use version 1.0 instead)"
+                        .to_owned()
+                ),
+                (
+                    4,
+                    "the Object type is not supported by dxWDL (use a struct instead)".to_owned()
+                ),
+                (
+                    6,
+                    "the sub function is not supported by Old Engine".to_owned()
+                ),
+                (
+                    10,
+                    "dxWDL interprets the disks runtime attribute differently: instance types \
+                     are selected from the memory, cpu, and disks attributes, or with the \
+                     dx_instance_type attribute"
+                        .to_owned()
+                ),
+            ]
+        );
+        assert!(CompatibilityLint::new(vec![EngineProfile::miniwdl()])
+            .check(&doc)
+            .iter()
+            .all(|diagnostic| diagnostic.span.start.line == 10));
+    }
+
+    #[test]
+    fn test_report() {
+        let doc = PestParser::new()
+            .parse_text(TEXT, DocumentSource::Unknown)
+            .unwrap();
+        let report = report(&doc, &EngineProfile::cromwell_86());
+        assert_eq!(report.engine, "Cromwell 86");
+        assert!(!report.is_supported());
+        assert_eq!(
+            report
+                .unsupported()
+                .map(|finding| finding.construct().clone())
+                .collect::<Vec<Construct>>(),
+            vec![Construct::Version(VersionIdentifier::V1_1)]
+        );
+        assert_eq!(report.different().count(), 1);
+        assert_eq!(
+            report.to_string(),
+            "1:9: WDL version 1.1 is not supported by Cromwell 86 (use version 1.0 instead)\n\
+             11:9: Cromwell 86 interprets the disks runtime attribute differently: on cloud \
+             backends, disks must have the form `local-disk <size> <type>` (e.g. `local-disk 10 \
+             SSD`)\n"
+        );
+        assert_eq!(report.to_diagnostics().len(), 2);
+        assert!(super::report(&doc, &EngineProfile::new("any", "Any"))
+            .findings
+            .is_empty());
+    }
+}
@@ -0,0 +1,390 @@
+//! Project configuration for linting, formatting, and validation, loaded from a `.wdl.toml` file
+//! (or a `wdlconfig.yml` file, if the `yaml` feature is enabled). An example `.wdl.toml`:
+//!
+//! ```toml
+//! [lint]
+//! # levels are "error", "warning", "note", or "off", keyed by lint name or diagnostic code
+//! shell = "off"
+//! parameter_meta_unknown = "warning"
+//!
+//! [format]
+//! indent_width = 2
+//! max_line_length = 120
+//! placeholder_style = "tilde"
+//!
+//! [validation]
+//! # "strict" or "permissive", optionally followed by the levels of individual rules
+//! level = "permissive"
+//! missing_command = "error"
+//! development_version = "ignore"
+//! ```
+use crate::{
+    diagnostics::{Diagnostic, Severity},
+    model::{ValidationLevel, ValidationOptions},
+};
+use error_stack::{bail, IntoReport, Result, ResultExt};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// The names of configuration files, in order of precedence.
+pub const CONFIG_FILE_NAMES: [&str; 3] = [".wdl.toml", "wdlconfig.yml", "wdlconfig.yaml"];
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read config file {0}")]
+    Read(PathBuf),
+    #[error("Invalid config syntax")]
+    Syntax,
+    #[error("Invalid value for config key {0}")]
+    Value(String),
+    #[error("Unknown config key {0}")]
+    UnknownKey(String),
+    #[error("Unsupported config file format {0}")]
+    Format(PathBuf),
+}
+
+/// The level at which the diagnostics of a lint rule are reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuleLevel {
+    Error,
+    Warning,
+    Note,
+    /// The rule is disabled.
+    Off,
+}
+
+impl RuleLevel {
+    fn parse(key: &str, value: &JsonValue) -> Result<Self, ConfigError> {
+        let level = match value.as_str() {
+            Some("error") => Self::Error,
+            Some("warning") => Self::Warning,
+            Some("note") => Self::Note,
+            Some("off") => Self::Off,
+            _ => bail!(ConfigError::Value(key.to_owned())),
+        };
+        Ok(level)
+    }
+
+    /// Returns the severity of diagnostics at this level, or `None` if the rule is disabled.
+    pub fn severity(&self) -> Option<Severity> {
+        match self {
+            Self::Error => Some(Severity::Error),
+            Self::Warning => Some(Severity::Warning),
+            Self::Note => Some(Severity::Note),
+            Self::Off => None,
+        }
+    }
+}
+
+/// Lint rule levels, keyed by either lint name (e.g. `shell`), which applies to all of the lint's
+/// diagnostics, or diagnostic code (e.g. `shell_unquoted_placeholder`), which takes precedence.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LintConfig {
+    pub levels: HashMap<String, RuleLevel>,
+}
+
+impl LintConfig {
+    /// Returns whether the lint named `name` is disabled.
+    pub fn is_disabled(&self, name: &str) -> bool {
+        self.levels.get(name) == Some(&RuleLevel::Off)
+    }
+
+    /// Applies the configured levels to `diagnostics` produced by the lint named `lint`, changing
+    /// their severities and removing the diagnostics of disabled rules.
+    pub fn apply(&self, lint: &str, diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        diagnostics
+            .into_iter()
+            .filter_map(|mut diagnostic| {
+                let level = self
+                    .levels
+                    .get(&diagnostic.code)
+                    .or_else(|| self.levels.get(lint));
+                if let Some(level) = level {
+                    diagnostic.severity = level.severity()?;
+                }
+                Some(diagnostic)
+            })
+            .collect()
+    }
+}
+
+/// The syntax that is used for placeholders in commands and strings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaceholderStyle {
+    /// `~{expr}`
+    Tilde,
+    /// `${expr}`
+    Dollar,
+}
+
+/// Formatting style for tools that generate or rewrite WDL source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FormatConfig {
+    /// The number of spaces per indentation level.
+    pub indent_width: usize,
+    pub max_line_length: usize,
+    pub placeholder_style: PlaceholderStyle,
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        Self {
+            indent_width: 4,
+            max_line_length: 100,
+            placeholder_style: PlaceholderStyle::Tilde,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    /// The file from which the configuration was loaded, or `None` for the default configuration.
+    pub path: Option<PathBuf>,
+    pub lint: LintConfig,
+    pub format: FormatConfig,
+    pub validation: ValidationOptions,
+}
+
+impl Config {
+    /// Returns the configuration for the document at `path`, which is loaded from the first
+    /// configuration file found in the directory containing `path` or any of its ancestors. If a
+    /// directory contains more than one configuration file, the first in `CONFIG_FILE_NAMES` is
+    /// used. Returns the default configuration if there is no configuration file.
+    pub fn discover<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let start = if path.is_dir() {
+            Some(path)
+        } else {
+            path.parent()
+        };
+        for dir in start.into_iter().flat_map(|dir| dir.ancestors()) {
+            for name in CONFIG_FILE_NAMES.iter() {
+                let candidate = dir.join(name);
+                if candidate.is_file() {
+                    return Self::load(candidate);
+                }
+            }
+        }
+        Ok(Self::default())
+    }
+
+    /// Loads the configuration file at `path`, whose format is determined by its extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .into_report()
+            .change_context_lazy(|| ConfigError::Read(path.to_owned()))?;
+        let mut config = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&text),
+            #[cfg(feature = "yaml")]
+            Some("yml") | Some("yaml") => Self::from_yaml(&text),
+            _ => bail!(ConfigError::Format(path.to_owned())),
+        }
+        .attach_printable_lazy(|| path.display().to_string())?;
+        config.path = Some(path.to_owned());
+        Ok(config)
+    }
+
+    /// Parses a configuration in TOML format.
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        let value: toml::Value = toml::from_str(text)
+            .into_report()
+            .change_context(ConfigError::Syntax)?;
+        let json = serde_json::to_value(value)
+            .into_report()
+            .change_context(ConfigError::Syntax)?;
+        Self::from_json(&json)
+    }
+
+    /// Parses a configuration in YAML format.
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(text: &str) -> Result<Self, ConfigError> {
+        let value: serde_yaml::Value = serde_yaml::from_str(text)
+            .into_report()
+            .change_context(ConfigError::Syntax)?;
+        let json = serde_json::to_value(value)
+            .into_report()
+            .change_context(ConfigError::Syntax)?;
+        Self::from_json(&json)
+    }
+
+    fn from_json(json: &JsonValue) -> Result<Self, ConfigError> {
+        let mut config = Self::default();
+        let root = match json {
+            JsonValue::Object(root) => root,
+            // an empty YAML document
+            JsonValue::Null => return Ok(config),
+            _ => bail!(ConfigError::Syntax),
+        };
+        for (key, value) in root.iter() {
+            match key.as_str() {
+                "lint" => {
+                    for (name, level) in table(key, value)?.iter() {
+                        let key = format!("lint.{}", name);
+                        config
+                            .lint
+                            .levels
+                            .insert(name.clone(), RuleLevel::parse(&key, level)?);
+                    }
+                }
+                "format" => config.format = parse_format(table(key, value)?)?,
+                "validation" => config.validation = parse_validation(table(key, value)?)?,
+                _ => bail!(ConfigError::UnknownKey(key.clone())),
+            }
+        }
+        Ok(config)
+    }
+}
+
+fn table<'a>(
+    key: &str,
+    value: &'a JsonValue,
+) -> Result<&'a JsonMap<String, JsonValue>, ConfigError> {
+    match value {
+        JsonValue::Object(table) => Ok(table),
+        _ => bail!(ConfigError::Value(key.to_owned())),
+    }
+}
+
+fn parse_format(table: &JsonMap<String, JsonValue>) -> Result<FormatConfig, ConfigError> {
+    let mut format = FormatConfig::default();
+    for (key, value) in table.iter() {
+        let invalid = || ConfigError::Value(format!("format.{}", key));
+        match key.as_str() {
+            "indent_width" => {
+                format.indent_width = value.as_u64().ok_or_else(invalid)? as usize;
+            }
+            "max_line_length" => {
+                format.max_line_length = value.as_u64().ok_or_else(invalid)? as usize;
+            }
+            "placeholder_style" => {
+                format.placeholder_style = match value.as_str() {
+                    Some("tilde") => PlaceholderStyle::Tilde,
+                    Some("dollar") => PlaceholderStyle::Dollar,
+                    _ => bail!(invalid()),
+                }
+            }
+            _ => bail!(ConfigError::UnknownKey(format!("format.{}", key))),
+        }
+    }
+    Ok(format)
+}
+
+fn parse_validation(table: &JsonMap<String, JsonValue>) -> Result<ValidationOptions, ConfigError> {
+    let mut options = match table.get("level").map(|level| level.as_str()) {
+        None | Some(Some("strict")) => ValidationOptions::strict(),
+        Some(Some("permissive")) => ValidationOptions::permissive(),
+        Some(_) => bail!(ConfigError::Value("validation.level".to_owned())),
+    };
+    for (key, value) in table.iter() {
+        let full_key = format!("validation.{}", key);
+        let rule = match key.as_str() {
+            "level" => continue,
+            "incomplete_document" => &mut options.incomplete_document,
+            "multiple_workflows" => &mut options.multiple_workflows,
+            "repeated_sections" => &mut options.repeated_sections,
+            "missing_command" => &mut options.missing_command,
+            "version_features" => &mut options.version_features,
+            "development_version" => &mut options.development_version,
+            "invalid_names" => &mut options.invalid_names,
+            "dropped_comments" => &mut options.dropped_comments,
+            _ => bail!(ConfigError::UnknownKey(full_key)),
+        };
+        *rule = match value.as_str() {
+            Some("error") => ValidationLevel::Error,
+            Some("warning") => ValidationLevel::Warning,
+            Some("ignore") => ValidationLevel::Ignore,
+            _ => bail!(ConfigError::Value(full_key)),
+        };
+    }
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Span;
+    use pretty_assertions::assert_eq;
+
+    const TOML: &str = r#"
+[lint]
+shell = "off"
+parameter_meta_unknown = "warning"
+
+[format]
+indent_width = 2
+placeholder_style = "dollar"
+
+[validation]
+level = "permissive"
+missing_command = "error"
+"#;
+
+    #[test]
+    fn test_from_toml() {
+        let config = Config::from_toml(TOML).unwrap();
+        assert!(config.lint.is_disabled("shell"));
+        assert_eq!(config.format.indent_width, 2);
+        assert_eq!(config.format.max_line_length, 100);
+        assert_eq!(config.format.placeholder_style, PlaceholderStyle::Dollar);
+        assert_eq!(config.validation.missing_command, ValidationLevel::Error);
+        assert_eq!(
+            config.validation.multiple_workflows,
+            ValidationLevel::Warning
+        );
+        let span = Span::from_components(0, 0, 0, 0, 1, 1);
+        let diagnostics = vec![
+            Diagnostic::error("parameter_meta_unknown", "unknown", span.clone()),
+            Diagnostic::warning("parameter_meta_missing", "missing", span),
+        ];
+        let applied = config.lint.apply("parameter_meta", diagnostics.clone());
+        assert_eq!(applied[0].severity, Severity::Warning);
+        assert_eq!(applied[1], diagnostics[1]);
+        // levels for codes take precedence over levels for lints
+        let applied = config.lint.apply("shell", diagnostics);
+        assert_eq!(applied.len(), 1);
+        assert_eq!(applied[0].code, "parameter_meta_unknown");
+
+        assert!(matches!(
+            Config::from_toml("[lint]\nshell = \"loud\"")
+                .unwrap_err()
+                .current_context(),
+            ConfigError::Value(key) if key == "lint.shell"
+        ));
+        assert!(matches!(
+            Config::from_toml("[formatting]")
+                .unwrap_err()
+                .current_context(),
+            ConfigError::UnknownKey(_)
+        ));
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_from_yaml() {
+        let config =
+            Config::from_yaml("lint:\n  shell: off\nformat:\n  indent_width: 2\n").unwrap();
+        assert!(config.lint.is_disabled("shell"));
+        assert_eq!(config.format.indent_width, 2);
+    }
+
+    #[test]
+    fn test_discover() {
+        let root = std::env::temp_dir().join(format!("wdl-config-{}", std::process::id()));
+        let nested = root.join("a").join("b");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join(".wdl.toml"), TOML).unwrap();
+        let config = Config::discover(nested.join("main.wdl")).unwrap();
+        assert_eq!(config.path, Some(root.join(".wdl.toml")));
+        assert_eq!(config.format.indent_width, 2);
+        fs::write(nested.join(".wdl.toml"), "[format]\nindent_width = 8\n").unwrap();
+        let config = Config::discover(nested.join("main.wdl")).unwrap();
+        assert_eq!(config.format.indent_width, 8);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
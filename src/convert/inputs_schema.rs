@@ -0,0 +1,353 @@
+//! Generation of JSON Schemas that describe the valid inputs JSON of a workflow, e.g. to generate
+//! submission forms or to validate inputs files in CI. The schema is an object with a property
+//! for each input, keyed by its fully-qualified name (e.g. `wf.x`). Types are converted as
+//! follows:
+//!
+//! * `Boolean`, `Int`, `Float`, and `String` become `boolean`, `integer`, `number`, and `string`
+//! * `File` becomes a `string` with the format `uri-reference`
+//! * `Array[T]` becomes an `array` (with `minItems` 1 if it is non-empty)
+//! * `Map[K, V]` becomes an `object` with `additionalProperties`
+//! * `Pair[L, R]` becomes an `object` with the properties `left` and `right`
+//! * `Object` becomes an `object` with any properties
+//! * structs of the document become `$ref`s to `#/definitions/<name>`, and other structs (i.e.
+//!   imported ones) become objects with any properties
+//! * `T?` allows `null`
+//!
+//! Inputs without defaults and with non-optional types are required. Constant defaults are the
+//! `default`s of their properties, and the `description`, `label` (as the `title`), and `choices`
+//! (as the `enum`) of inputs are taken from `parameter_meta`. The schema does not allow
+//! properties that are not inputs.
+//!
+//! This is the inverse of `json_schema`, which generates structs from JSON Schemas.
+use crate::{
+    inputs::Callable,
+    model::{Document, DocumentElement, InputDeclaration, ParameterDescription, Struct, Type},
+};
+use error_stack::{bail, Result};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use std::ops::Deref;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum InputsSchemaError {
+    #[error("the document does not have a workflow or a single task")]
+    NoTarget,
+}
+
+/// Generates the schemas of the types of a document's declarations.
+pub(super) struct Generator<'a> {
+    structs: Vec<&'a Struct>,
+    /// The names of the structs that are referenced by the schema.
+    definitions: Vec<&'a str>,
+}
+
+impl<'a> Generator<'a> {
+    pub(super) fn new(document: &'a Document) -> Self {
+        Self {
+            structs: document
+                .body_iter()
+                .filter_map(|element| match element {
+                    DocumentElement::Struct(struct_) => Some(struct_),
+                    _ => None,
+                })
+                .collect(),
+            definitions: Vec::new(),
+        }
+    }
+
+    pub(super) fn schema(&mut self, type_: &'a Type) -> JsonValue {
+        match type_ {
+            Type::Boolean => json!({ "type": "boolean" }),
+            Type::Int => json!({ "type": "integer" }),
+            Type::Float => json!({ "type": "number" }),
+            Type::String => json!({ "type": "string" }),
+            Type::File => json!({ "type": "string", "format": "uri-reference" }),
+            Type::Array { item, non_empty } => {
+                let mut schema = json!({ "type": "array", "items": self.schema(item) });
+                if *non_empty {
+                    schema["minItems"] = json!(1);
+                }
+                schema
+            }
+            Type::Map { value, .. } => {
+                json!({ "type": "object", "additionalProperties": self.schema(value) })
+            }
+            Type::Pair { left, right } => json!({
+                "type": "object",
+                "properties": { "left": self.schema(left), "right": self.schema(right) },
+                "required": ["left", "right"],
+                "additionalProperties": false,
+            }),
+            Type::Object => json!({ "type": "object" }),
+            Type::User(name) => match self
+                .structs
+                .iter()
+                .find(|struct_| struct_.name.as_str() == name)
+            {
+                Some(struct_) => {
+                    let name = struct_.name.as_str();
+                    if !self.definitions.contains(&name) {
+                        self.definitions.push(name)
+                    }
+                    json!({ "$ref": format!("#/definitions/{}", name) })
+                }
+                None => json!({ "type": "object" }),
+            },
+            Type::Optional(inner) => nullable(self.schema(inner)),
+        }
+    }
+
+    /// Returns the schema of the struct `name`, which is one of the structs of the document.
+    fn definition(&mut self, name: &str) -> JsonValue {
+        let struct_ = *self
+            .structs
+            .iter()
+            .find(|struct_| struct_.name.as_str() == name)
+            .unwrap();
+        let mut properties = JsonMap::new();
+        let mut required = Vec::new();
+        for field in struct_.fields.iter() {
+            if !matches!(field.type_.deref(), Type::Optional(_)) {
+                required.push(json!(field.name.as_str()))
+            }
+            properties.insert(field.name.as_str().to_owned(), self.schema(&field.type_));
+        }
+        json!({
+            "title": name,
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "additionalProperties": false,
+        })
+    }
+
+    /// Returns the root schema of an object with the given properties, with the definitions of
+    /// the structs that they reference.
+    pub(super) fn root(
+        mut self,
+        title: &str,
+        properties: JsonMap<String, JsonValue>,
+        required: Vec<JsonValue>,
+    ) -> JsonValue {
+        let mut schema = json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": title,
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "additionalProperties": false,
+        });
+        // definitions may reference further structs, which are added to the end
+        let mut definitions = JsonMap::new();
+        let mut i = 0;
+        while i < self.definitions.len() {
+            let name = self.definitions[i];
+            let definition = self.definition(name);
+            definitions.insert(name.to_owned(), definition);
+            i += 1;
+        }
+        if !definitions.is_empty() {
+            schema["definitions"] = JsonValue::Object(definitions)
+        }
+        schema
+    }
+}
+
+/// Returns `schema` extended to allow `null`.
+fn nullable(mut schema: JsonValue) -> JsonValue {
+    match schema.get("type").cloned() {
+        Some(JsonValue::String(type_)) => {
+            schema["type"] = json!([type_, "null"]);
+            schema
+        }
+        _ => json!({ "oneOf": [schema, { "type": "null" }] }),
+    }
+}
+
+/// Converts `choice`, the text of an element of `choices` in `parameter_meta`, to a value of
+/// `type_`, if it is a primitive type.
+fn choice(choice: &str, type_: &Type) -> JsonValue {
+    let parsed = match type_ {
+        Type::Boolean => choice.parse::<bool>().ok().map(JsonValue::from),
+        Type::Int => choice.parse::<i64>().ok().map(JsonValue::from),
+        Type::Float => choice.parse::<f64>().ok().map(JsonValue::from),
+        Type::Optional(inner) => return self::choice(choice, inner),
+        _ => None,
+    };
+    parsed.unwrap_or_else(|| JsonValue::from(choice))
+}
+
+/// Adds the documentation of `description` to `schema`, the schema of a parameter of type
+/// `type_`.
+pub(super) fn describe(schema: &mut JsonValue, description: ParameterDescription, type_: &Type) {
+    if let Some(text) = description.description {
+        schema["description"] = json!(text)
+    }
+    if let Some(label) = description.label {
+        schema["title"] = json!(label)
+    }
+    if !description.choices.is_empty() {
+        let mut choices: Vec<JsonValue> = description
+            .choices
+            .iter()
+            .map(|c| choice(c, type_))
+            .collect();
+        if matches!(type_, Type::Optional(_)) {
+            choices.push(JsonValue::Null)
+        }
+        schema["enum"] = json!(choices)
+    }
+}
+
+/// Returns a JSON Schema (draft 7) that describes the inputs JSON of the workflow of `document`,
+/// or of its task if it has no workflow and a single task.
+pub fn inputs_schema(document: &Document) -> Result<JsonValue, InputsSchemaError> {
+    let callable = match Callable::of(document) {
+        Some(callable) => callable,
+        None => bail!(InputsSchemaError::NoTarget),
+    };
+    let name = callable.name();
+    let mut generator = Generator::new(document);
+    let mut properties = JsonMap::new();
+    let mut required = Vec::new();
+    for input in callable.input_declarations() {
+        let type_ = input.type_();
+        let key = format!("{}.{}", name, input.name().as_str());
+        let mut schema = generator.schema(type_);
+        if let Some(value) = input.default_value().and_then(|value| value.to_json().ok()) {
+            schema["default"] = value
+        } else if matches!(input, InputDeclaration::Unbound(_))
+            && !matches!(type_.deref(), Type::Optional(_))
+        {
+            required.push(json!(key))
+        }
+        if let Some(description) = callable.parameter_description(input.name()) {
+            describe(&mut schema, description, type_)
+        }
+        properties.insert(key, schema);
+    }
+    Ok(generator.root(name, properties, required))
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        convert::json_schema::structs_from_schema,
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_inputs_schema() {
+        let text = r#"version 1.1
+
+struct Sample {
+    String id
+    Array[File]+ reads
+    Pair[Int, Int]? range
+}
+
+workflow align {
+    input {
+        Sample sample
+        String mode = "local"
+        Int? threads
+        Map[String, Float] weights = {"a": 1}
+        Int shards = length(sample.reads)
+    }
+    parameter_meta {
+        sample: "The sample to align."
+        mode: {
+            label: "Mode",
+            choices: ["local", "global"]
+        }
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let schema = inputs_schema(&doc).unwrap();
+        assert_eq!(
+            schema,
+            json!({
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "title": "align",
+                "type": "object",
+                "properties": {
+                    "align.sample": {
+                        "$ref": "#/definitions/Sample",
+                        "description": "The sample to align.",
+                    },
+                    "align.mode": {
+                        "type": "string",
+                        "default": "local",
+                        "title": "Mode",
+                        "enum": ["local", "global"],
+                    },
+                    "align.threads": { "type": ["integer", "null"] },
+                    "align.weights": {
+                        "type": "object",
+                        "additionalProperties": { "type": "number" },
+                        "default": { "a": 1.0 },
+                    },
+                    "align.shards": { "type": "integer" },
+                },
+                "required": ["align.sample"],
+                "additionalProperties": false,
+                "definitions": {
+                    "Sample": {
+                        "title": "Sample",
+                        "type": "object",
+                        "properties": {
+                            "id": { "type": "string" },
+                            "reads": {
+                                "type": "array",
+                                "items": { "type": "string", "format": "uri-reference" },
+                                "minItems": 1,
+                            },
+                            "range": {
+                                "type": ["object", "null"],
+                                "properties": {
+                                    "left": { "type": "integer" },
+                                    "right": { "type": "integer" },
+                                },
+                                "required": ["left", "right"],
+                                "additionalProperties": false,
+                            },
+                        },
+                        "required": ["id", "reads"],
+                        "additionalProperties": false,
+                    }
+                }
+            })
+        );
+        // the definitions can be converted back to structs
+        let structs = structs_from_schema(&schema["definitions"]["Sample"], "Sample").unwrap();
+        let types: Vec<(&str, &str)> = structs
+            .last()
+            .unwrap()
+            .fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.type_.as_str()))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                ("id", "String"),
+                ("range", "Range?"),
+                ("reads", "Array[File]+")
+            ]
+        );
+
+        let tasks = PestParser::new()
+            .parse_text(
+                "version 1.1\n\ntask a {\n    command <<< >>>\n}\n\ntask b {\n    command <<< >>>\n}\n",
+                DocumentSource::Unknown,
+            )
+            .unwrap();
+        assert!(inputs_schema(&tasks).is_err());
+    }
+}
@@ -0,0 +1,468 @@
+//! Generation of WDL struct definitions from JSON Schemas or from example JSON documents, e.g.
+//! to model the rows of a sample sheet.
+//!
+//! JSON Schema types are converted as follows:
+//!
+//! * `string` becomes `String`, or `File` if its `format` is `uri` or `uri-reference`
+//! * `integer`, `number`, and `boolean` become `Int`, `Float`, and `Boolean`
+//! * `array` becomes `Array[T]` (or `Array[T]+` if `minItems` is at least 1)
+//! * `object` with `properties` becomes a struct named by its `title` or its property name
+//! * `object` with only `additionalProperties` becomes `Map[String, T]`
+//! * `$ref`s to `#/definitions/X` or `#/$defs/X` become the struct `X`
+//!
+//! Properties that are not `required`, and schemas whose type includes `null` (or that are a
+//! `oneOf`/`anyOf` of `null` and a single other schema), are optional.
+use error_stack::{bail, Result};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use std::fmt::Write;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum JsonSchemaError {
+    #[error("unsupported schema at {0}")]
+    Unsupported(String),
+    #[error("unresolved reference {0}")]
+    Reference(String),
+    #[error("the root of the schema must be an object with properties")]
+    Root,
+}
+
+/// A field of a generated struct.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GeneratedField {
+    pub name: String,
+    /// The WDL type of the field, e.g. `Array[File]?`.
+    pub type_: String,
+    /// The `description` of the field's schema.
+    pub description: Option<String>,
+}
+
+/// A generated struct definition.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GeneratedStruct {
+    pub name: String,
+    pub fields: Vec<GeneratedField>,
+}
+
+impl GeneratedStruct {
+    /// Returns the WDL source of this struct, indented by `indent_width` spaces. Field
+    /// descriptions are rendered as comments.
+    pub fn to_wdl(&self, indent_width: usize) -> String {
+        let indent = " ".repeat(indent_width);
+        let mut wdl = format!("struct {} {{\n", self.name);
+        for field in self.fields.iter() {
+            if let Some(description) = &field.description {
+                for line in description.lines() {
+                    writeln!(wdl, "{}# {}", indent, line).unwrap();
+                }
+            }
+            writeln!(wdl, "{}{} {}", indent, field.type_, field.name).unwrap();
+        }
+        wdl.push_str("}\n");
+        wdl
+    }
+}
+
+/// Returns the WDL source of `structs`, separated by blank lines.
+pub fn to_wdl(structs: &[GeneratedStruct], indent_width: usize) -> String {
+    let structs: Vec<String> = structs
+        .iter()
+        .map(|struct_| struct_.to_wdl(indent_width))
+        .collect();
+    structs.join("\n")
+}
+
+/// Returns `name` as a valid WDL identifier, replacing invalid characters with `_`.
+fn identifier(name: &str) -> String {
+    let mut identifier: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !identifier.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        identifier.insert(0, 'x')
+    }
+    identifier
+}
+
+/// Returns `name` as a WDL identifier in upper camel case, e.g. `sample_files` -> `SampleFiles`.
+fn struct_name(name: &str) -> String {
+    let name: String = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().unwrap().to_ascii_uppercase();
+            std::iter::once(first).chain(chars).collect::<String>()
+        })
+        .collect();
+    identifier(&name)
+}
+
+struct Generator<'a> {
+    root: &'a JsonValue,
+    structs: Vec<GeneratedStruct>,
+    /// The names of the structs generated for `$ref`s, by reference.
+    references: Vec<(String, String)>,
+}
+
+impl<'a> Generator<'a> {
+    /// Adds `struct_` unless an identical struct exists, renaming it if its name is taken, and
+    /// returns its name.
+    fn add(&mut self, mut struct_: GeneratedStruct) -> String {
+        if let Some(existing) = self
+            .structs
+            .iter()
+            .find(|s| s.name == struct_.name && s.fields == struct_.fields)
+        {
+            return existing.name.clone();
+        }
+        let base = struct_.name.clone();
+        let mut suffix = 2;
+        while self.structs.iter().any(|s| s.name == struct_.name) {
+            struct_.name = format!("{}{}", base, suffix);
+            suffix += 1;
+        }
+        let name = struct_.name.clone();
+        self.structs.push(struct_);
+        name
+    }
+
+    fn resolve(&mut self, reference: &str) -> Result<String, JsonSchemaError> {
+        if let Some((_, name)) = self.references.iter().find(|(r, _)| r == reference) {
+            return Ok(name.clone());
+        }
+        let definition = ["#/definitions/", "#/$defs/"].iter().find_map(|prefix| {
+            let name = reference.strip_prefix(prefix)?;
+            let container = &prefix[2..prefix.len() - 1];
+            Some((name, self.root.get(container)?.get(name)?))
+        });
+        let (name, schema) = match definition {
+            Some(definition) => definition,
+            None => bail!(JsonSchemaError::Reference(reference.to_owned())),
+        };
+        // reserve the name first, so that recursive references resolve
+        let name = struct_name(name);
+        self.references.push((reference.to_owned(), name.clone()));
+        let type_ = self.type_(schema, &name, reference)?;
+        self.references.last_mut().unwrap().1 = type_.trim_end_matches('?').to_owned();
+        Ok(type_.trim_end_matches('?').to_owned())
+    }
+
+    fn object(
+        &mut self,
+        schema: &JsonMap<String, JsonValue>,
+        name: &str,
+        path: &str,
+    ) -> Result<String, JsonSchemaError> {
+        let properties = match schema.get("properties").and_then(|p| p.as_object()) {
+            Some(properties) => properties,
+            None => {
+                let value = match schema.get("additionalProperties") {
+                    Some(value @ JsonValue::Object(_)) => {
+                        self.type_(value, name, &format!("{}/additionalProperties", path))?
+                    }
+                    _ => "String".to_owned(),
+                };
+                return Ok(format!("Map[String, {}]", value));
+            }
+        };
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(|required| required.as_array())
+            .map(|required| required.iter().filter_map(|r| r.as_str()).collect())
+            .unwrap_or_default();
+        let name = schema
+            .get("title")
+            .and_then(|title| title.as_str())
+            .map(struct_name)
+            .unwrap_or_else(|| struct_name(name));
+        let mut fields = Vec::new();
+        for (property, value) in properties.iter() {
+            let mut type_ = self.type_(
+                value,
+                property,
+                &format!("{}/properties/{}", path, property),
+            )?;
+            if !required.contains(&property.as_str()) && !type_.ends_with('?') {
+                type_.push('?')
+            }
+            fields.push(GeneratedField {
+                name: identifier(property),
+                type_,
+                description: value
+                    .get("description")
+                    .and_then(|description| description.as_str())
+                    .map(|description| description.to_owned()),
+            })
+        }
+        Ok(self.add(GeneratedStruct { name, fields }))
+    }
+
+    /// Returns the WDL type of `schema`, generating structs for any objects it contains. `name`
+    /// is the name of the property whose schema it is, and `path` is its JSON pointer.
+    fn type_(
+        &mut self,
+        schema: &JsonValue,
+        name: &str,
+        path: &str,
+    ) -> Result<String, JsonSchemaError> {
+        let unsupported = || JsonSchemaError::Unsupported(path.to_owned());
+        let schema = match schema {
+            JsonValue::Object(schema) => schema,
+            _ => bail!(unsupported()),
+        };
+        if let Some(reference) = schema.get("$ref").and_then(|r| r.as_str()) {
+            return self.resolve(reference);
+        }
+        for key in ["oneOf", "anyOf"] {
+            if let Some(JsonValue::Array(options)) = schema.get(key) {
+                let is_null =
+                    |option: &&JsonValue| option.get("type") == Some(&JsonValue::from("null"));
+                let others: Vec<&JsonValue> =
+                    options.iter().filter(|option| !is_null(option)).collect();
+                if others.len() != 1 {
+                    bail!(unsupported())
+                }
+                let mut type_ = self.type_(others[0], name, &format!("{}/{}", path, key))?;
+                if others.len() < options.len() && !type_.ends_with('?') {
+                    type_.push('?')
+                }
+                return Ok(type_);
+            }
+        }
+        let (type_name, nullable) = match schema.get("type") {
+            Some(JsonValue::String(type_name)) => (type_name.as_str(), false),
+            Some(JsonValue::Array(types)) => {
+                let types: Vec<&str> = types.iter().filter_map(|t| t.as_str()).collect();
+                let others: Vec<&str> = types.iter().copied().filter(|t| *t != "null").collect();
+                if others.len() != 1 {
+                    bail!(unsupported())
+                }
+                (others[0], others.len() < types.len())
+            }
+            None if schema.contains_key("properties") => ("object", false),
+            None if schema.contains_key("enum") => ("string", false),
+            _ => bail!(unsupported()),
+        };
+        let mut type_ = match type_name {
+            "string" => match schema.get("format").and_then(|format| format.as_str()) {
+                Some("uri") | Some("uri-reference") => "File".to_owned(),
+                _ => "String".to_owned(),
+            },
+            "integer" => "Int".to_owned(),
+            "number" => "Float".to_owned(),
+            "boolean" => "Boolean".to_owned(),
+            "array" => {
+                let items = schema.get("items").ok_or_else(unsupported)?;
+                let item = self.type_(items, name, &format!("{}/items", path))?;
+                let non_empty = schema
+                    .get("minItems")
+                    .and_then(|min| min.as_u64())
+                    .unwrap_or(0)
+                    > 0;
+                format!("Array[{}]{}", item, if non_empty { "+" } else { "" })
+            }
+            "object" => self.object(schema, name, path)?,
+            _ => bail!(unsupported()),
+        };
+        if nullable {
+            type_.push('?')
+        }
+        Ok(type_)
+    }
+}
+
+/// Generates struct definitions from `schema`, a JSON Schema whose root is an object with
+/// `properties`. The root struct is named by the schema's `title`, or `name` if it has none.
+/// Structs are returned in dependency order, i.e. the root struct is last.
+pub fn structs_from_schema(
+    schema: &JsonValue,
+    name: &str,
+) -> Result<Vec<GeneratedStruct>, JsonSchemaError> {
+    if schema.get("properties").is_none() {
+        bail!(JsonSchemaError::Root)
+    }
+    let mut generator = Generator {
+        root: schema,
+        structs: Vec::new(),
+        references: Vec::new(),
+    };
+    generator.type_(schema, name, "#")?;
+    Ok(generator.structs)
+}
+
+/// Infers a JSON Schema from `example`. Fields of objects in arrays that are missing or `null`
+/// in some of the objects are not required, and arrays with both integers and floats are arrays
+/// of numbers.
+fn infer_schema(example: &JsonValue) -> JsonValue {
+    match example {
+        JsonValue::Null => serde_json::json!({ "type": ["string", "null"] }),
+        JsonValue::Bool(_) => serde_json::json!({ "type": "boolean" }),
+        JsonValue::Number(number) if number.is_f64() => serde_json::json!({ "type": "number" }),
+        JsonValue::Number(_) => serde_json::json!({ "type": "integer" }),
+        JsonValue::String(_) => serde_json::json!({ "type": "string" }),
+        JsonValue::Array(elements) => {
+            let items = elements
+                .iter()
+                .map(infer_schema)
+                .reduce(merge_schemas)
+                .unwrap_or_else(|| serde_json::json!({ "type": "string" }));
+            serde_json::json!({ "type": "array", "items": items })
+        }
+        JsonValue::Object(object) => {
+            let properties: JsonMap<String, JsonValue> = object
+                .iter()
+                .map(|(key, value)| (key.clone(), infer_schema(value)))
+                .collect();
+            let required: Vec<&String> = object
+                .iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, _)| key)
+                .collect();
+            serde_json::json!({ "type": "object", "properties": properties, "required": required })
+        }
+    }
+}
+
+/// Merges two inferred schemas of elements of the same array.
+fn merge_schemas(a: JsonValue, b: JsonValue) -> JsonValue {
+    let type_of = |schema: &JsonValue| schema.get("type").cloned().unwrap_or(JsonValue::Null);
+    match (type_of(&a), type_of(&b)) {
+        (x, y) if x == y && x == "object" => {
+            let empty = JsonMap::new();
+            let properties = |schema: &JsonValue| {
+                schema
+                    .get("properties")
+                    .and_then(|p| p.as_object())
+                    .cloned()
+                    .unwrap_or_else(|| empty.clone())
+            };
+            let required = |schema: &JsonValue| -> Vec<JsonValue> {
+                schema
+                    .get("required")
+                    .and_then(|r| r.as_array())
+                    .cloned()
+                    .unwrap_or_default()
+            };
+            let mut merged = properties(&a);
+            for (key, value) in properties(&b) {
+                let value = match merged.remove(&key) {
+                    Some(existing) => merge_schemas(existing, value),
+                    None => value,
+                };
+                merged.insert(key, value);
+            }
+            let (required_a, required_b) = (required(&a), required(&b));
+            let required: Vec<&JsonValue> = required_a
+                .iter()
+                .filter(|key| required_b.contains(key))
+                .collect();
+            serde_json::json!({ "type": "object", "properties": merged, "required": required })
+        }
+        (x, y) if x == y && x == "array" => {
+            let items = merge_schemas(a["items"].clone(), b["items"].clone());
+            serde_json::json!({ "type": "array", "items": items })
+        }
+        (x, y) if x == y => a,
+        (x, y) if (x == "integer" && y == "number") || (x == "number" && y == "integer") => {
+            serde_json::json!({ "type": "number" })
+        }
+        (x, _) if x == serde_json::json!(["string", "null"]) => b,
+        (_, y) if y == serde_json::json!(["string", "null"]) => a,
+        _ => serde_json::json!({ "type": "string" }),
+    }
+}
+
+/// Generates struct definitions from `example`, an example JSON object (e.g. a row of a sample
+/// sheet) or an array of such objects. The root struct is named `name`. Fields that are `null`,
+/// or that are missing from some of the objects of an array, are optional.
+pub fn structs_from_example(
+    example: &JsonValue,
+    name: &str,
+) -> Result<Vec<GeneratedStruct>, JsonSchemaError> {
+    let mut schema = infer_schema(example);
+    if schema["type"] == "array" {
+        schema = schema["items"].take();
+    }
+    structs_from_schema(&schema, name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    #[test]
+    fn test_structs_from_schema() {
+        let schema = json!({
+            "title": "sample",
+            "type": "object",
+            "required": ["id", "reads"],
+            "properties": {
+                "id": { "type": "string", "description": "The sample ID." },
+                "reads": {
+                    "type": "array",
+                    "minItems": 1,
+                    "items": { "type": "string", "format": "uri" }
+                },
+                "library": { "$ref": "#/$defs/library" },
+                "tags": { "type": "object", "additionalProperties": { "type": "integer" } },
+                "coverage": { "type": ["number", "null"] }
+            },
+            "$defs": {
+                "library": {
+                    "type": "object",
+                    "required": ["paired"],
+                    "properties": { "paired": { "type": "boolean" } }
+                }
+            }
+        });
+        let structs = structs_from_schema(&schema, "Row").unwrap();
+        assert_eq!(
+            to_wdl(&structs, 4),
+            r#"struct Library {
+    Boolean paired
+}
+
+struct Sample {
+    Float? coverage
+    # The sample ID.
+    String id
+    Library? library
+    Array[File]+ reads
+    Map[String, Int]? tags
+}
+"#
+        );
+        assert!(matches!(
+            structs_from_schema(&json!({ "properties": { "x": { "$ref": "#/nope" } } }), "A")
+                .unwrap_err()
+                .current_context(),
+            JsonSchemaError::Reference(_)
+        ));
+    }
+
+    #[test]
+    fn test_structs_from_example() {
+        let example = json!([
+            { "sample id": "a", "depth": 1, "files": { "bam": "a.bam" } },
+            { "sample id": "b", "depth": 2.5, "files": { "bam": "b.bam", "bai": "b.bai" } }
+        ]);
+        let structs = structs_from_example(&example, "sample_row").unwrap();
+        assert_eq!(
+            to_wdl(&structs, 2),
+            r#"struct Files {
+  String? bai
+  String bam
+}
+
+struct SampleRow {
+  Float depth
+  Files files
+  String sample_id
+}
+"#
+        );
+    }
+}
@@ -0,0 +1,5 @@
+//! Conversions between WDL and other formats.
+pub mod inputs_schema;
+pub mod json_schema;
+pub mod nextflow;
+pub mod outputs_schema;
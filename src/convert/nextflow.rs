@@ -0,0 +1,594 @@
+//! Experimental, best-effort translation of WDL documents to Nextflow DSL2, to assist with
+//! migrating workflows between engines. Each task becomes a `process` and each workflow becomes
+//! a named `workflow` with `take`, `main`, and `emit` sections.
+//!
+//! Only a subset of WDL can be translated: identifiers, literals, and member accesses in
+//! expressions; outputs that are files named by string literals or `glob`s, or `stdout()`; and
+//! calls of tasks of the same document. Any other construct is reported with a diagnostic and,
+//! where possible, left in the output in its WDL form (e.g. untranslated placeholders are kept as
+//! `~{...}`) so that it can be translated by hand.
+use crate::{
+    diagnostics::Diagnostic,
+    fqn::call_name,
+    model::{
+        AccessOperation, Call, Document, DocumentElement, Expression, InputDeclaration, RuntimeKey,
+        Span, StringPart, Task, TaskElement, Type, Workflow, WorkflowElement,
+    },
+};
+use std::{collections::HashMap, fmt::Write, ops::Deref};
+
+/// The code of the diagnostics of constructs that cannot be translated.
+pub const UNTRANSLATED: &str = "nextflow_untranslated";
+
+/// The result of translating a document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Translation {
+    /// The Nextflow script.
+    pub text: String,
+    /// Diagnostics about the constructs of the document that were not translated, or that were
+    /// translated approximately.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Returns `s` as a single-quoted Groovy string.
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Escapes `s` for inclusion in a triple-double-quoted Groovy string, in which `$` and `\` are
+/// special.
+fn escape_script(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('$', "\\$")
+}
+
+/// Removes the common indentation and leading and trailing blank lines of `text`.
+fn dedent(text: &str) -> Vec<&str> {
+    let lines: Vec<&str> = text.lines().collect();
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let mut lines: Vec<&str> = lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end())
+        .skip_while(|line| line.is_empty())
+        .collect();
+    while lines.last() == Some(&"") {
+        lines.pop();
+    }
+    lines
+}
+
+/// How identifiers are translated in a workflow, where the outputs of a call `c` are accessed as
+/// `c.out.<name>` and calls are referred to by the names of their processes.
+#[derive(Default)]
+struct Scope<'a> {
+    /// The process called by each call, by call name.
+    calls: HashMap<&'a str, &'a str>,
+}
+
+struct Translator<'a> {
+    document: &'a Document,
+    text: String,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Translator<'a> {
+    fn untranslated<S: Into<String>>(&mut self, message: S, span: &Span) {
+        self.diagnostics
+            .push(Diagnostic::warning(UNTRANSLATED, message, span.clone()))
+    }
+
+    fn note<S: Into<String>>(&mut self, message: S, span: &Span) {
+        self.diagnostics
+            .push(Diagnostic::note(UNTRANSLATED, message, span.clone()))
+    }
+
+    fn task(&self, name: &str) -> Option<&'a Task> {
+        self.document
+            .body
+            .iter()
+            .find_map(|element| match element.deref() {
+                DocumentElement::Task(task) if task.name.as_str() == name => Some(task),
+                _ => None,
+            })
+    }
+
+    /// Returns the Groovy translation of `expression`, whose span is `span`, or `None` (after
+    /// adding a diagnostic) if it cannot be translated.
+    fn expression(
+        &mut self,
+        expression: &Expression,
+        span: &Span,
+        scope: &Scope,
+    ) -> Option<String> {
+        match expression {
+            Expression::None => Some("null".to_owned()),
+            Expression::Boolean(_) | Expression::Int(_) | Expression::Float(_) => {
+                Some(expression.to_string())
+            }
+            Expression::String(s) => {
+                let mut groovy = String::from("\"");
+                for part in s.parts.iter() {
+                    match part.deref() {
+                        StringPart::Content(text) | StringPart::Escape(text) => {
+                            groovy.push_str(&text.replace('$', "\\$").replace('"', "\\\""))
+                        }
+                        StringPart::Placeholder(inner, _) => {
+                            let inner = self.expression(inner, &part.span, scope)?;
+                            write!(groovy, "${{{}}}", inner).unwrap()
+                        }
+                    }
+                }
+                groovy.push('"');
+                Some(groovy)
+            }
+            Expression::Identifier(name) => Some(name.clone()),
+            Expression::Group(inner) => self.expression(inner, &inner.span, scope),
+            Expression::Access(access) => {
+                let mut groovy = match access.collection.deref().deref() {
+                    Expression::Identifier(call) if scope.calls.contains_key(call.as_str()) => {
+                        format!("{}.out", scope.calls[call.as_str()])
+                    }
+                    collection => self.expression(collection, &access.collection.span, scope)?,
+                };
+                for operation in access.accesses.iter() {
+                    match operation.deref() {
+                        AccessOperation::Field(field) => write!(groovy, ".{}", field).unwrap(),
+                        AccessOperation::Index(_) => {
+                            self.untranslated(
+                                "index expressions are not translated",
+                                &operation.span,
+                            );
+                            return None;
+                        }
+                    }
+                }
+                Some(groovy)
+            }
+            _ => {
+                self.untranslated(
+                    format!("expression `{}` is not translated", expression),
+                    span,
+                );
+                None
+            }
+        }
+    }
+
+    fn process(&mut self, task: &'a Task) {
+        let scope = Scope::default();
+        let mut directives = Vec::new();
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+        let mut script = String::new();
+        if let Some(runtime) = task.runtime() {
+            for attribute in runtime.attributes.iter() {
+                let key = RuntimeKey::from_name(attribute.name.as_str());
+                let directive = match &key {
+                    RuntimeKey::Container => match runtime.container().as_deref() {
+                        Some([image, ..]) => Some(format!("container {}", quote(image))),
+                        _ => None,
+                    },
+                    RuntimeKey::Cpu => runtime.cpu().map(|cpu| format!("cpus {}", cpu.ceil())),
+                    RuntimeKey::Memory => runtime.memory().map(|memory| {
+                        // Nextflow's units are binary, and are written without the `i`
+                        let unit = memory.unit.to_string().replace('i', "");
+                        format!("memory {}", quote(&format!("{} {}", memory.value, unit)))
+                    }),
+                    RuntimeKey::MaxRetries => runtime.max_retries().map(|retries| {
+                        format!("maxRetries {}\n    errorStrategy 'retry'", retries)
+                    }),
+                    _ => {
+                        self.untranslated(
+                            format!(
+                                "runtime attribute `{}` is not translated",
+                                attribute.name.as_str()
+                            ),
+                            &attribute.span,
+                        );
+                        continue;
+                    }
+                };
+                match directive {
+                    Some(directive) => directives.push(directive),
+                    None => self.untranslated(
+                        format!(
+                            "runtime attribute `{}` is not a constant and is not translated",
+                            attribute.name.as_str()
+                        ),
+                        &attribute.span,
+                    ),
+                }
+            }
+        }
+        for element in task.body.iter() {
+            match element.deref() {
+                TaskElement::Input(input) => {
+                    for decl in input.declarations.iter() {
+                        let name = decl.name().as_str();
+                        let qualifier = match decl.type_().deref() {
+                            Type::File => "path",
+                            Type::Array { item, .. }
+                                if matches!(item.deref().deref(), Type::File) =>
+                            {
+                                "path"
+                            }
+                            _ => "val",
+                        };
+                        inputs.push(format!("{} {}", qualifier, name));
+                        if let InputDeclaration::Bound(_) = decl.deref() {
+                            self.note(
+                                format!(
+                                    "the default value of input `{}` is not translated and must be provided by the caller",
+                                    name
+                                ),
+                                &decl.span,
+                            )
+                        }
+                    }
+                }
+                TaskElement::Output(output) => {
+                    for decl in output.declarations.iter() {
+                        let name = decl.name.as_str();
+                        let translated = match decl.expression.deref() {
+                            Expression::Apply(apply)
+                                if apply.name.as_str() == "stdout"
+                                    && apply.arguments.is_empty() =>
+                            {
+                                Some(format!("stdout emit: {}", name))
+                            }
+                            Expression::Apply(apply)
+                                if apply.name.as_str() == "glob" && apply.arguments.len() == 1 =>
+                            {
+                                self.expression(
+                                    &apply.arguments[0],
+                                    &apply.arguments[0].span,
+                                    &scope,
+                                )
+                                .map(|pattern| format!("path {}, emit: {}", pattern, name))
+                            }
+                            Expression::String(_) => self
+                                .expression(&decl.expression, &decl.expression.span, &scope)
+                                .map(|path| format!("path {}, emit: {}", path, name)),
+                            Expression::Identifier(input) => {
+                                Some(format!("val {}, emit: {}", input, name))
+                            }
+                            _ => {
+                                self.untranslated(
+                                    format!("output `{}` is not translated", name),
+                                    &decl.span,
+                                );
+                                None
+                            }
+                        };
+                        if let Some(translated) = translated {
+                            outputs.push(translated)
+                        }
+                    }
+                }
+                TaskElement::Declaration(decl) => self.untranslated(
+                    format!(
+                        "private declaration `{}` is not translated",
+                        decl.name.as_str()
+                    ),
+                    &element.span,
+                ),
+                TaskElement::Command(command) => {
+                    for part in command.parts.iter() {
+                        match part.deref() {
+                            StringPart::Content(text) | StringPart::Escape(text) => {
+                                script.push_str(&escape_script(text))
+                            }
+                            StringPart::Placeholder(expression, _) => {
+                                match self.expression(expression, &part.span, &scope) {
+                                    Some(groovy) => write!(script, "${{{}}}", groovy).unwrap(),
+                                    None => script.push_str(&part.deref().to_string()),
+                                }
+                            }
+                        }
+                    }
+                }
+                TaskElement::Runtime(_) | TaskElement::Meta(_) | TaskElement::ParameterMeta(_) => {}
+            }
+        }
+
+        writeln!(self.text, "process {} {{", task.name.as_str()).unwrap();
+        for directive in directives.iter() {
+            writeln!(self.text, "    {}", directive).unwrap();
+        }
+        for (section, lines) in [("input", &inputs), ("output", &outputs)] {
+            if !lines.is_empty() {
+                writeln!(self.text, "\n    {}:", section).unwrap();
+                for line in lines.iter() {
+                    writeln!(self.text, "    {}", line).unwrap();
+                }
+            }
+        }
+        self.text.push_str("\n    script:\n    \"\"\"\n");
+        for line in dedent(&script) {
+            if line.is_empty() {
+                self.text.push('\n')
+            } else {
+                writeln!(self.text, "    {}", line).unwrap()
+            }
+        }
+        self.text.push_str("    \"\"\"\n}\n");
+    }
+
+    fn call(&mut self, call: &'a Call, scope: &Scope) -> Option<String> {
+        let target = call.target.parts.last().unwrap().as_str();
+        let task = match (call.target.parts.len(), self.task(target)) {
+            (1, Some(task)) => task,
+            _ => {
+                self.untranslated(
+                    format!(
+                        "call of `{}`, which is not a task of this document, is not translated",
+                        target
+                    ),
+                    &call.target.span,
+                );
+                return None;
+            }
+        };
+        if let Some(alias) = &call.alias {
+            self.untranslated(
+                format!(
+                    "call alias `{}` is not translated; a second call of `{}` requires it to be included under another name",
+                    alias.as_str(),
+                    target
+                ),
+                &alias.span,
+            )
+        }
+        let mut arguments = Vec::new();
+        let declarations = task.inputs().map(|input| input.declarations.iter());
+        for decl in declarations.into_iter().flatten() {
+            let name = decl.name().as_str();
+            let input = call
+                .inputs
+                .iter()
+                .flatten()
+                .find(|input| input.name.as_str() == name);
+            let argument = match (input, decl.deref()) {
+                (Some(input), _) => match &input.expression {
+                    Some(expression) => self.expression(expression, &expression.span, scope),
+                    None => Some(name.to_owned()),
+                },
+                (None, InputDeclaration::Bound(bound)) => {
+                    self.expression(&bound.expression, &bound.expression.span, scope)
+                }
+                (None, InputDeclaration::Unbound(unbound)) => {
+                    if !matches!(unbound.type_.deref(), Type::Optional(_)) {
+                        self.untranslated(
+                            format!(
+                                "call of `{}` does not provide required input `{}`",
+                                target, name
+                            ),
+                            &call.target.span,
+                        )
+                    }
+                    None
+                }
+            };
+            arguments.push(argument.unwrap_or_else(|| "null".to_owned()))
+        }
+        Some(format!("{}({})", target, arguments.join(", ")))
+    }
+
+    fn workflow(&mut self, workflow: &'a Workflow) {
+        let mut scope = Scope::default();
+        let mut take = Vec::new();
+        let mut main = Vec::new();
+        let mut emit = Vec::new();
+        for element in workflow.body.iter() {
+            match element.deref() {
+                WorkflowElement::Input(input) => {
+                    for decl in input.declarations.iter() {
+                        take.push(decl.name().as_str().to_owned());
+                        if let InputDeclaration::Bound(_) = decl.deref() {
+                            self.note(
+                                format!(
+                                    "the default value of input `{}` is not translated and must be provided by the caller",
+                                    decl.name().as_str()
+                                ),
+                                &decl.span,
+                            )
+                        }
+                    }
+                }
+                WorkflowElement::Call(call) => {
+                    if let Some(translated) = self.call(call, &scope) {
+                        main.push(translated);
+                        let target = call.target.parts.last().unwrap().as_str();
+                        scope.calls.insert(call_name(call), target);
+                    }
+                }
+                WorkflowElement::Output(output) => {
+                    for decl in output.declarations.iter() {
+                        if let Some(expression) =
+                            self.expression(&decl.expression, &decl.expression.span, &scope)
+                        {
+                            emit.push(format!("{} = {}", decl.name.as_str(), expression))
+                        }
+                    }
+                }
+                WorkflowElement::Meta(_) | WorkflowElement::ParameterMeta(_) => (),
+                other => self.untranslated(
+                    format!("workflow {} is not translated", other.kind()),
+                    &element.span,
+                ),
+            }
+        }
+        writeln!(self.text, "workflow {} {{", workflow.name.as_str()).unwrap();
+        let mut first = true;
+        for (section, lines) in [("take", &take), ("main", &main), ("emit", &emit)] {
+            if lines.is_empty() {
+                continue;
+            }
+            if !first {
+                self.text.push('\n')
+            }
+            first = false;
+            writeln!(self.text, "    {}:", section).unwrap();
+            for line in lines.iter() {
+                writeln!(self.text, "    {}", line).unwrap();
+            }
+        }
+        self.text.push_str("}\n");
+    }
+}
+
+/// Translates the tasks and workflows of `document` to a Nextflow DSL2 script. The translation
+/// is best-effort: constructs that cannot be translated are reported as diagnostics with the
+/// code `UNTRANSLATED` and the span of the construct in `document`.
+pub fn translate(document: &Document) -> Translation {
+    let mut translator = Translator {
+        document,
+        text: String::from("nextflow.enable.dsl = 2\n"),
+        diagnostics: Vec::new(),
+    };
+    for element in document.body.iter() {
+        match element.deref() {
+            DocumentElement::Import(_) => translator.untranslated(
+                "imports are not translated; the imported document must be translated separately",
+                &element.span,
+            ),
+            DocumentElement::Struct(struct_) => translator.note(
+                format!(
+                    "struct `{}` is not translated; its values are Groovy maps",
+                    struct_.name.as_str()
+                ),
+                &element.span,
+            ),
+            DocumentElement::Task(task) => {
+                translator.text.push('\n');
+                translator.process(task)
+            }
+            DocumentElement::Workflow(workflow) => {
+                translator.text.push('\n');
+                translator.workflow(workflow)
+            }
+        }
+    }
+    Translation {
+        text: translator.text,
+        diagnostics: translator.diagnostics,
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_translate() {
+        let text = r#"version 1.1
+
+task align {
+    input {
+        File reads
+        String sample
+        Int threads = 4
+    }
+
+    command <<<
+        bwa mem -t ~{threads} ref.fa ~{reads} > ~{sample}.sam
+        echo "$HOME" ~{if threads > 1 then "multi" else "single"}
+    >>>
+
+    output {
+        File sam = "~{sample}.sam"
+        File log = stdout()
+        Int n = length(read_lines(sam))
+    }
+
+    runtime {
+        container: "biocontainers/bwa:0.7.17"
+        cpu: 4
+        memory: "8 GiB"
+    }
+}
+
+workflow main {
+    input {
+        File reads
+        String sample
+    }
+
+    call align { input: reads, sample = sample }
+
+    scatter (i in [1, 2]) {
+        call align as again { input: reads, sample = sample }
+    }
+
+    output {
+        File sam = align.sam
+    }
+}
+"#;
+        let document = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let translation = translate(&document);
+        assert_eq!(
+            translation.text,
+            r#"nextflow.enable.dsl = 2
+
+process align {
+    container 'biocontainers/bwa:0.7.17'
+    cpus 4
+    memory '8 GB'
+
+    input:
+    path reads
+    val sample
+    val threads
+
+    output:
+    path "${sample}.sam", emit: sam
+    stdout emit: log
+
+    script:
+    """
+    bwa mem -t ${threads} ref.fa ${reads} > ${sample}.sam
+    echo "\$HOME" ~{if threads > 1 then "multi" else "single"}
+    """
+}
+
+workflow main {
+    take:
+    reads
+    sample
+
+    main:
+    align(reads, sample, 4)
+
+    emit:
+    sam = align.out.sam
+}
+"#
+        );
+        let messages: Vec<&str> = translation
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "the default value of input `threads` is not translated and must be provided by the caller",
+                "expression `if threads > 1 then \"multi\" else \"single\"` is not translated",
+                "output `n` is not translated",
+                "workflow scatter is not translated",
+            ]
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! Generation of JSON Schemas that describe the outputs JSON of a workflow, so that consumers of
+//! the outputs can validate their expectations statically. The schema is an object with a required
+//! property for each output, keyed by its fully-qualified name (e.g. `wf.x`), and types are
+//! converted as they are by `inputs_schema`. Outputs with optional types may be `null`. The
+//! `description` and `label` of outputs are taken from `parameter_meta`.
+use crate::{
+    convert::inputs_schema::{describe, Generator},
+    inputs::Callable,
+    model::Document,
+};
+use error_stack::{bail, Result};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum OutputsSchemaError {
+    #[error("the document does not have a workflow or a single task")]
+    NoTarget,
+}
+
+/// Returns a JSON Schema (draft 7) that describes the outputs JSON of the workflow of
+/// `document`, or of its task if it has no workflow and a single task.
+pub fn outputs_schema(document: &Document) -> Result<JsonValue, OutputsSchemaError> {
+    let callable = match Callable::of(document) {
+        Some(callable) => callable,
+        None => bail!(OutputsSchemaError::NoTarget),
+    };
+    let name = callable.name();
+    let mut generator = Generator::new(document);
+    let mut properties = JsonMap::new();
+    let mut required = Vec::new();
+    for output in callable
+        .outputs()
+        .into_iter()
+        .flat_map(|outputs| outputs.declarations.iter())
+    {
+        let key = format!("{}.{}", name, output.name.as_str());
+        let mut schema = generator.schema(&output.type_);
+        if let Some(description) = callable.parameter_description(output.name.as_str()) {
+            describe(&mut schema, description, &output.type_)
+        }
+        required.push(json!(key));
+        properties.insert(key, schema);
+    }
+    Ok(generator.root(name, properties, required))
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_outputs_schema() {
+        let text = r#"version 1.1
+
+struct Stats {
+    Int reads
+    Float? coverage
+}
+
+task count {
+    command <<< >>>
+    output {
+        Array[File] bams = glob("*.bam")
+        Stats stats = Stats { reads: 1, coverage: None }
+        String? log = None
+    }
+    parameter_meta {
+        bams: "The aligned reads."
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        assert_eq!(
+            outputs_schema(&doc).unwrap(),
+            json!({
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "title": "count",
+                "type": "object",
+                "properties": {
+                    "count.bams": {
+                        "type": "array",
+                        "items": { "type": "string", "format": "uri-reference" },
+                        "description": "The aligned reads.",
+                    },
+                    "count.stats": { "$ref": "#/definitions/Stats" },
+                    "count.log": { "type": ["string", "null"] },
+                },
+                "required": ["count.bams", "count.stats", "count.log"],
+                "additionalProperties": false,
+                "definitions": {
+                    "Stats": {
+                        "title": "Stats",
+                        "type": "object",
+                        "properties": {
+                            "reads": { "type": "integer" },
+                            "coverage": { "type": ["number", "null"] },
+                        },
+                        "required": ["reads"],
+                        "additionalProperties": false,
+                    }
+                }
+            })
+        );
+    }
+}
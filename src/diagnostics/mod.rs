@@ -0,0 +1,105 @@
+//! Diagnostics (errors, warnings, and notes) about WDL documents that are produced by lints and
+//! other checks. Unlike `ModelError`s, diagnostics do not prevent a document from being parsed.
+mod output;
+mod report;
+
+pub use output::{to_github_annotations, to_json, to_sarif, OutputFormat, OutputFormatError};
+pub use report::{DiagnosticsReport, FileDiagnostics, SeverityCounts};
+
+use crate::{fix::Fix, model::Span};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Note,
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            Self::Note => "note",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A span that is related to a diagnostic, e.g. the first definition of a name that is defined
+/// more than once.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RelatedSpan {
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// A short, unique identifier for the kind of problem, e.g. `parameter_meta_unknown`.
+    pub code: String,
+    pub message: String,
+    /// The span of the source document to which the diagnostic applies.
+    pub span: Span,
+    /// Other spans of the source document that are relevant to the problem.
+    pub related: Vec<RelatedSpan>,
+    /// Fixes that resolve the problem, in order of preference.
+    pub fixes: Vec<Fix>,
+}
+
+impl Diagnostic {
+    pub fn new<C: Into<String>, M: Into<String>>(
+        severity: Severity,
+        code: C,
+        message: M,
+        span: Span,
+    ) -> Self {
+        Self {
+            severity,
+            code: code.into(),
+            message: message.into(),
+            span,
+            related: Vec::new(),
+            fixes: Vec::new(),
+        }
+    }
+
+    /// Adds a related span to this diagnostic.
+    pub fn with_related<M: Into<String>>(mut self, span: Span, message: M) -> Self {
+        self.related.push(RelatedSpan {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    /// Adds `fix` to the fixes of this diagnostic.
+    pub fn with_fix(mut self, fix: Fix) -> Self {
+        self.fixes.push(fix);
+        self
+    }
+
+    pub fn error<C: Into<String>, M: Into<String>>(code: C, message: M, span: Span) -> Self {
+        Self::new(Severity::Error, code, message, span)
+    }
+
+    pub fn warning<C: Into<String>, M: Into<String>>(code: C, message: M, span: Span) -> Self {
+        Self::new(Severity::Warning, code, message, span)
+    }
+
+    pub fn note<C: Into<String>, M: Into<String>>(code: C, message: M, span: Span) -> Self {
+        Self::new(Severity::Note, code, message, span)
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "{}[{}]: {} ({})",
+            self.severity, self.code, self.message, self.span
+        )
+    }
+}
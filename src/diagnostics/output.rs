@@ -0,0 +1,342 @@
+//! Serialization of diagnostics to formats that are understood by other tools. Each function
+//! takes the diagnostics of one or more documents, as pairs of the document path (or URI) and
+//! its diagnostics. Lines and columns are one-based in all formats except plain JSON, which uses
+//! the same zero-based positions as `Span`.
+use crate::{
+    diagnostics::{Diagnostic, Severity},
+    model::{Position, Span},
+};
+use serde_json::{json, Value as JsonValue};
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    str::FromStr,
+};
+use thiserror::Error;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+#[derive(Error, Debug)]
+#[error("Unknown output format {0}")]
+pub struct OutputFormatError(String);
+
+/// A format in which diagnostics can be written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// One line per diagnostic, prefixed with the document path.
+    Text,
+    /// A JSON array of diagnostics.
+    Json,
+    /// A [SARIF 2.1.0](https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html) log,
+    /// e.g. for upload to GitHub code scanning.
+    Sarif,
+    /// GitHub Actions workflow commands, which create annotations on pull requests.
+    GitHub,
+}
+
+impl FromStr for OutputFormat {
+    type Err = OutputFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "sarif" => Ok(Self::Sarif),
+            "github" => Ok(Self::GitHub),
+            _ => Err(OutputFormatError(s.to_owned())),
+        }
+    }
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            Self::Text => "text",
+            Self::Json => "json",
+            Self::Sarif => "sarif",
+            Self::GitHub => "github",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl OutputFormat {
+    /// Renders the diagnostics of `files` in this format.
+    pub fn render(&self, files: &[(&str, &[Diagnostic])]) -> String {
+        match self {
+            Self::Text => files
+                .iter()
+                .flat_map(|(path, diagnostics)| {
+                    diagnostics.iter().map(move |diagnostic| {
+                        format!(
+                            "{}:{}:{}: {}[{}]: {}\n",
+                            path,
+                            diagnostic.span.start.line + 1,
+                            diagnostic.span.start.column + 1,
+                            diagnostic.severity,
+                            diagnostic.code,
+                            diagnostic.message
+                        )
+                    })
+                })
+                .collect(),
+            Self::Json => serde_json::to_string_pretty(&to_json(files)).unwrap(),
+            Self::Sarif => serde_json::to_string_pretty(&to_sarif(files)).unwrap(),
+            Self::GitHub => to_github_annotations(files),
+        }
+    }
+}
+
+fn position_json(position: &Position) -> JsonValue {
+    json!({
+        "line": position.line,
+        "column": position.column,
+        "offset": position.offset,
+    })
+}
+
+fn span_json(span: &Span) -> JsonValue {
+    json!({
+        "start": position_json(&span.start),
+        "end": position_json(&span.end),
+    })
+}
+
+/// Converts the diagnostics of `files` to a JSON array with one object per diagnostic.
+pub fn to_json(files: &[(&str, &[Diagnostic])]) -> JsonValue {
+    let diagnostics = files
+        .iter()
+        .flat_map(|(path, diagnostics)| {
+            diagnostics.iter().map(move |diagnostic| {
+                json!({
+                    "path": path,
+                    "severity": diagnostic.severity.to_string(),
+                    "code": diagnostic.code,
+                    "message": diagnostic.message,
+                    "span": span_json(&diagnostic.span),
+                    "related": diagnostic.related.iter().map(|related| json!({
+                        "span": span_json(&related.span),
+                        "message": related.message,
+                    })).collect::<Vec<_>>(),
+                    "fixes": diagnostic.fixes.iter().map(|fix| json!({
+                        "title": fix.title,
+                        "edits": fix.edits.iter().map(|edit| json!({
+                            "span": span_json(&edit.span),
+                            "text": edit.text,
+                        })).collect::<Vec<_>>(),
+                    })).collect::<Vec<_>>(),
+                })
+            })
+        })
+        .collect();
+    JsonValue::Array(diagnostics)
+}
+
+fn sarif_region(span: &Span) -> JsonValue {
+    json!({
+        "startLine": span.start.line + 1,
+        "startColumn": span.start.column + 1,
+        "endLine": span.end.line + 1,
+        "endColumn": span.end.column + 1,
+    })
+}
+
+fn sarif_location(path: &str, span: &Span) -> JsonValue {
+    json!({
+        "physicalLocation": {
+            "artifactLocation": { "uri": path },
+            "region": sarif_region(span),
+        }
+    })
+}
+
+fn sarif_level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note => "note",
+    }
+}
+
+/// Converts the diagnostics of `files` to a SARIF 2.1.0 log with a single run. A rule is defined
+/// for each distinct diagnostic code, and fixes are included as SARIF fixes.
+pub fn to_sarif(files: &[(&str, &[Diagnostic])]) -> JsonValue {
+    let mut rules: Vec<&str> = Vec::new();
+    let mut results = Vec::new();
+    for (path, diagnostics) in files.iter() {
+        for diagnostic in diagnostics.iter() {
+            let rule_index = match rules.iter().position(|rule| *rule == diagnostic.code) {
+                Some(index) => index,
+                None => {
+                    rules.push(&diagnostic.code);
+                    rules.len() - 1
+                }
+            };
+            let related: Vec<JsonValue> = diagnostic
+                .related
+                .iter()
+                .enumerate()
+                .map(|(i, related)| {
+                    let mut location = sarif_location(path, &related.span);
+                    location["id"] = json!(i);
+                    location["message"] = json!({ "text": related.message });
+                    location
+                })
+                .collect();
+            let fixes: Vec<JsonValue> = diagnostic
+                .fixes
+                .iter()
+                .map(|fix| {
+                    json!({
+                        "description": { "text": fix.title },
+                        "artifactChanges": [{
+                            "artifactLocation": { "uri": path },
+                            "replacements": fix.edits.iter().map(|edit| json!({
+                                "deletedRegion": sarif_region(&edit.span),
+                                "insertedContent": { "text": edit.text },
+                            })).collect::<Vec<_>>(),
+                        }],
+                    })
+                })
+                .collect();
+            results.push(json!({
+                "ruleId": diagnostic.code,
+                "ruleIndex": rule_index,
+                "level": sarif_level(diagnostic.severity),
+                "message": { "text": diagnostic.message },
+                "locations": [sarif_location(path, &diagnostic.span)],
+                "relatedLocations": related,
+                "fixes": fixes,
+            }))
+        }
+    }
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_HOMEPAGE"),
+                    "rules": rules.iter().map(|rule| json!({ "id": rule })).collect::<Vec<_>>(),
+                }
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+        }]
+    })
+}
+
+/// Escapes the message of a GitHub workflow command.
+fn escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a property value of a GitHub workflow command.
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Converts the diagnostics of `files` to GitHub Actions workflow commands, one per line, e.g.
+/// `::warning file=a.wdl,line=3,col=5,endLine=3,endColumn=9,title=code::message`. Notes are
+/// reported as notices.
+pub fn to_github_annotations(files: &[(&str, &[Diagnostic])]) -> String {
+    let mut output = String::new();
+    for (path, diagnostics) in files.iter() {
+        for diagnostic in diagnostics.iter() {
+            let command = match diagnostic.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Note => "notice",
+            };
+            let span = &diagnostic.span;
+            output.push_str(&format!(
+                "::{} file={},line={},col={},endLine={},endColumn={},title={}::{}\n",
+                command,
+                escape_property(path),
+                span.start.line + 1,
+                span.start.column + 1,
+                span.end.line + 1,
+                span.end.column + 1,
+                escape_property(&diagnostic.code),
+                escape_data(&diagnostic.message)
+            ))
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{edit::TextEdit, fix::Fix};
+    use pretty_assertions::assert_eq;
+
+    fn diagnostics() -> Vec<Diagnostic> {
+        vec![
+            Diagnostic::warning(
+                "shell_unquoted_placeholder",
+                "placeholder is not quoted",
+                Span::from_components(2, 4, 30, 2, 10, 36),
+            )
+            .with_fix(Fix::new(
+                "Quote placeholder",
+                vec![TextEdit::insert(Position::new(2, 4, 30), "\"")],
+            )),
+            Diagnostic::error(
+                "duplicate_name",
+                "name x is defined\nmore than once, 100%",
+                Span::from_components(5, 0, 60, 5, 1, 61),
+            )
+            .with_related(Span::from_components(1, 0, 10, 1, 1, 11), "first"),
+        ]
+    }
+
+    #[test]
+    fn test_sarif() {
+        let diagnostics = diagnostics();
+        let sarif = to_sarif(&[("a.wdl", &diagnostics)]);
+        assert_eq!(sarif["version"], "2.1.0");
+        let run = &sarif["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"][1]["id"], "duplicate_name");
+        let result = &run["results"][1];
+        assert_eq!(result["level"], "error");
+        assert_eq!(result["ruleIndex"], 1);
+        assert_eq!(
+            result["locations"][0]["physicalLocation"]["region"],
+            json!({"startLine": 6, "startColumn": 1, "endLine": 6, "endColumn": 2})
+        );
+        assert_eq!(result["relatedLocations"][0]["message"]["text"], "first");
+        let replacement = &run["results"][0]["fixes"][0]["artifactChanges"][0]["replacements"][0];
+        assert_eq!(replacement["insertedContent"]["text"], "\"");
+    }
+
+    #[test]
+    fn test_json() {
+        let diagnostics = diagnostics();
+        let json = to_json(&[("a.wdl", &diagnostics)]);
+        assert_eq!(json[0]["path"], "a.wdl");
+        assert_eq!(json[0]["severity"], "warning");
+        assert_eq!(json[0]["span"]["start"]["offset"], 30);
+        assert_eq!(json[0]["fixes"][0]["title"], "Quote placeholder");
+    }
+
+    #[test]
+    fn test_github_annotations() {
+        let diagnostics = diagnostics();
+        assert_eq!(
+            OutputFormat::GitHub.render(&[("dir,1/a.wdl", &diagnostics)]),
+            "::warning file=dir%2C1/a.wdl,line=3,col=5,endLine=3,endColumn=11,\
+             title=shell_unquoted_placeholder::placeholder is not quoted\n\
+             ::error file=dir%2C1/a.wdl,line=6,col=1,endLine=6,endColumn=2,\
+             title=duplicate_name::name x is defined%0Amore than once, 100%25\n"
+        );
+        assert_eq!(
+            "SARIF".parse::<OutputFormat>().unwrap(),
+            OutputFormat::Sarif
+        );
+        assert!("xml".parse::<OutputFormat>().is_err());
+    }
+}
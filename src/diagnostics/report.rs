@@ -0,0 +1,206 @@
+use crate::diagnostics::{to_json, Diagnostic, Severity};
+use serde_json::{json, Value as JsonValue};
+
+/// The numbers of diagnostics of each severity.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SeverityCounts {
+    pub errors: usize,
+    pub warnings: usize,
+    pub notes: usize,
+}
+
+impl SeverityCounts {
+    fn add(&mut self, severity: Severity) {
+        match severity {
+            Severity::Error => self.errors += 1,
+            Severity::Warning => self.warnings += 1,
+            Severity::Note => self.notes += 1,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.errors + self.warnings + self.notes
+    }
+}
+
+/// The diagnostics of a single file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileDiagnostics {
+    /// The path (or URI) of the file.
+    pub path: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// The diagnostics of a set of files, e.g. the result of checking a repository in CI, with a
+/// policy that determines whether the check passed: it fails if any diagnostic has a severity at
+/// or above the report's threshold, which is `Error` by default.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiagnosticsReport {
+    files: Vec<FileDiagnostics>,
+    threshold: Option<Severity>,
+}
+
+impl Default for DiagnosticsReport {
+    fn default() -> Self {
+        Self {
+            files: Vec::new(),
+            threshold: Some(Severity::Error),
+        }
+    }
+}
+
+impl DiagnosticsReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the minimum severity of a diagnostic that fails the check, or `None` if the check
+    /// never fails.
+    pub fn with_threshold(mut self, threshold: Option<Severity>) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Adds the diagnostics of the file at `path`. Files without diagnostics should also be added,
+    /// so that they are counted as checked.
+    pub fn add<P: Into<String>>(&mut self, path: P, diagnostics: Vec<Diagnostic>) {
+        self.files.push(FileDiagnostics {
+            path: path.into(),
+            diagnostics,
+        })
+    }
+
+    pub fn files(&self) -> &[FileDiagnostics] {
+        &self.files
+    }
+
+    pub fn threshold(&self) -> Option<Severity> {
+        self.threshold
+    }
+
+    fn diagnostics(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.files.iter().flat_map(|file| file.diagnostics.iter())
+    }
+
+    /// Returns the numbers of diagnostics of each severity, over all files.
+    pub fn counts(&self) -> SeverityCounts {
+        let mut counts = SeverityCounts::default();
+        self.diagnostics()
+            .for_each(|diagnostic| counts.add(diagnostic.severity));
+        counts
+    }
+
+    /// Returns the highest severity of any diagnostic, or `None` if there are no diagnostics.
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.diagnostics()
+            .map(|diagnostic| diagnostic.severity)
+            .max()
+    }
+
+    /// Returns whether the check passed, i.e. whether no diagnostic has a severity at or above
+    /// the threshold.
+    pub fn passed(&self) -> bool {
+        match (self.threshold, self.max_severity()) {
+            (Some(threshold), Some(max)) => max < threshold,
+            _ => true,
+        }
+    }
+
+    /// Returns the exit code of a process that performed the check: 0 if it passed, otherwise 1.
+    pub fn exit_code(&self) -> i32 {
+        if self.passed() {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// Converts this report to JSON: an object with a `files` array, in which each file has the
+    /// diagnostics in the format of `to_json`, and a `summary` object with the counts of files
+    /// and diagnostics, the maximum severity, the threshold, and whether the check passed.
+    pub fn to_json(&self) -> JsonValue {
+        let counts = self.counts();
+        let files: Vec<JsonValue> = self
+            .files
+            .iter()
+            .map(|file| {
+                json!({
+                    "path": file.path,
+                    "diagnostics": to_json(&[(&file.path, &file.diagnostics)]),
+                })
+            })
+            .collect();
+        json!({
+            "files": files,
+            "summary": {
+                "files": self.files.len(),
+                "files_with_diagnostics": self
+                    .files
+                    .iter()
+                    .filter(|file| !file.diagnostics.is_empty())
+                    .count(),
+                "errors": counts.errors,
+                "warnings": counts.warnings,
+                "notes": counts.notes,
+                "max_severity": self.max_severity().map(|severity| severity.to_string()),
+                "threshold": self.threshold.map(|severity| severity.to_string()),
+                "passed": self.passed(),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Position, Span};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_report() {
+        let span = Span {
+            start: Position::new(0, 0, 0),
+            end: Position::new(0, 1, 1),
+        };
+        let mut report = DiagnosticsReport::new();
+        report.add(
+            "a.wdl",
+            vec![
+                Diagnostic::warning("w", "warning", span.clone()),
+                Diagnostic::note("n", "note", span.clone()),
+            ],
+        );
+        report.add("b.wdl", Vec::new());
+        assert_eq!(
+            report.counts(),
+            SeverityCounts {
+                errors: 0,
+                warnings: 1,
+                notes: 1
+            }
+        );
+        assert_eq!(report.max_severity(), Some(Severity::Warning));
+        assert!(report.passed());
+        assert_eq!(report.exit_code(), 0);
+        let json = report.to_json();
+        assert_eq!(
+            json["summary"],
+            json!({
+                "files": 2,
+                "files_with_diagnostics": 1,
+                "errors": 0,
+                "warnings": 1,
+                "notes": 1,
+                "max_severity": "warning",
+                "threshold": "error",
+                "passed": true,
+            })
+        );
+        assert_eq!(json["files"][0]["diagnostics"][1]["code"], "n");
+
+        let report = report.with_threshold(Some(Severity::Warning));
+        assert!(!report.passed());
+        assert_eq!(report.exit_code(), 1);
+        assert!(report.with_threshold(None).passed());
+    }
+}
@@ -0,0 +1,557 @@
+//! Documentation of document elements. Elements may be documented either in their `meta` and
+//! `parameter_meta` sections or with doc comments, which are the full-line comments immediately
+//! preceding the element, e.g.
+//!
+//! ```wdl
+//! ## Aligns reads to the reference genome.
+//! ##
+//! ## Unpaired reads are aligned in single-end mode.
+//! task align {
+//!     input {
+//!         ## The reads to align.
+//!         File reads
+//!     }
+//!     ...
+//! }
+//! ```
+//!
+//! `workflow_metadata` extracts the `meta` and `parameter_meta` conventions that are recognized by
+//! Dockstore and Terra (e.g. `author` and `email`) for publishing workflows to registries.
+use crate::model::{
+    Anchor, Comments, Document, DocumentElement, Meta, MetaAttribute, MetaValue, Span, TaskElement,
+    Workflow, WorkflowElement,
+};
+use std::ops::Deref;
+
+/// The convention by which doc comments are distinguished from other comments.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DocCommentStyle {
+    /// Any full-line comment is a doc comment.
+    Any,
+    /// Doc comments start with `##`.
+    #[default]
+    DoubleHash,
+    /// Doc comments start with `#@`.
+    HashAt,
+}
+
+impl DocCommentStyle {
+    /// Returns the text of `comment` without the comment prefix and a single following space if
+    /// it is a doc comment in this style, otherwise `None`.
+    pub fn strip<'a>(&self, comment: &'a str) -> Option<&'a str> {
+        let text = match self {
+            Self::Any => comment.trim_start_matches('#'),
+            Self::DoubleHash => comment.strip_prefix("##")?,
+            Self::HashAt => comment.strip_prefix("#@")?,
+        };
+        let text = text.trim_end();
+        Some(text.strip_prefix(' ').unwrap_or(text))
+    }
+}
+
+/// Returns the doc comment of an element that starts on `line`, i.e. the consecutive doc
+/// comments in `style` that immediately precede the line, joined by newlines. Returns `None` if
+/// there are no such comments.
+pub fn doc_comment(comments: &Comments, line: usize, style: DocCommentStyle) -> Option<String> {
+    let lines: Vec<&str> = comments
+        .leading(line)
+        .into_iter()
+        .rev()
+        .map_while(|comment| style.strip(comment.as_str()))
+        .collect();
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.into_iter().rev().collect::<Vec<_>>().join("\n"))
+    }
+}
+
+/// The kind of a documented element.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ElementKind {
+    Struct,
+    Task,
+    Workflow,
+    Input,
+    Output,
+}
+
+/// The documentation of a single element.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ElementDocs {
+    pub kind: ElementKind,
+    /// The name of the element. The names of inputs and outputs are qualified by the name of
+    /// their task or workflow, e.g. `align.reads`.
+    pub name: String,
+    pub span: Span,
+    /// The `description` from the element's `meta` section (for tasks and workflows) or its
+    /// `parameter_meta` entry (for inputs and outputs).
+    pub description: Option<String>,
+    /// The element's doc comment.
+    pub docs: Option<String>,
+}
+
+impl ElementDocs {
+    /// Returns the documentation text of this element: the description if there is one,
+    /// otherwise the doc comment.
+    pub fn text(&self) -> Option<&str> {
+        self.description.as_deref().or(self.docs.as_deref())
+    }
+}
+
+fn meta_description(meta: &Meta) -> Option<String> {
+    meta.attributes
+        .iter()
+        .find(|attribute| attribute.name.as_str() == "description")
+        .and_then(|attribute| attribute.value.text())
+}
+
+struct Harvester<'a> {
+    comments: &'a Comments,
+    style: DocCommentStyle,
+    docs: Vec<ElementDocs>,
+}
+
+impl<'a> Harvester<'a> {
+    fn add(&mut self, kind: ElementKind, name: String, span: &Span, description: Option<String>) {
+        self.docs.push(ElementDocs {
+            kind,
+            name,
+            span: span.clone(),
+            description,
+            docs: doc_comment(self.comments, span.start.line, self.style),
+        })
+    }
+}
+
+/// Collects the documentation of the structs, tasks, workflows, and task and workflow inputs and
+/// outputs of `document`, in source order. Elements without any documentation are included, with
+/// both `description` and `docs` set to `None`.
+pub fn harvest(document: &Document, style: DocCommentStyle) -> Vec<ElementDocs> {
+    let mut harvester = Harvester {
+        comments: &document.comments,
+        style,
+        docs: Vec::new(),
+    };
+    for element in document.body.iter() {
+        match element.deref() {
+            DocumentElement::Import(_) => (),
+            DocumentElement::Struct(struct_) => harvester.add(
+                ElementKind::Struct,
+                struct_.name.as_str().to_owned(),
+                &element.span,
+                None,
+            ),
+            DocumentElement::Task(task) => {
+                let description = task.body.iter().find_map(|e| match e.deref() {
+                    TaskElement::Meta(meta) => meta_description(meta),
+                    _ => None,
+                });
+                harvester.add(
+                    ElementKind::Task,
+                    task.name.as_str().to_owned(),
+                    &element.span,
+                    description,
+                );
+                for e in task.body.iter() {
+                    let declarations: Vec<(ElementKind, &str, &Span)> = match e.deref() {
+                        TaskElement::Input(input) => input
+                            .declarations
+                            .iter()
+                            .map(|decl| (ElementKind::Input, decl.name().as_str(), &decl.span))
+                            .collect(),
+                        TaskElement::Output(output) => output
+                            .declarations
+                            .iter()
+                            .map(|decl| (ElementKind::Output, decl.name.as_str(), &decl.span))
+                            .collect(),
+                        _ => continue,
+                    };
+                    for (kind, name, span) in declarations {
+                        let description = task
+                            .parameter_description(name)
+                            .and_then(|desc| desc.description);
+                        harvester.add(
+                            kind,
+                            format!("{}.{}", task.name.as_str(), name),
+                            span,
+                            description,
+                        )
+                    }
+                }
+            }
+            DocumentElement::Workflow(workflow) => {
+                let description = workflow.body.iter().find_map(|e| match e.deref() {
+                    WorkflowElement::Meta(meta) => meta_description(meta),
+                    _ => None,
+                });
+                harvester.add(
+                    ElementKind::Workflow,
+                    workflow.name.as_str().to_owned(),
+                    &element.span,
+                    description,
+                );
+                for e in workflow.body.iter() {
+                    let declarations: Vec<(ElementKind, &str, &Span)> = match e.deref() {
+                        WorkflowElement::Input(input) => input
+                            .declarations
+                            .iter()
+                            .map(|decl| (ElementKind::Input, decl.name().as_str(), &decl.span))
+                            .collect(),
+                        WorkflowElement::Output(output) => output
+                            .declarations
+                            .iter()
+                            .map(|decl| (ElementKind::Output, decl.name.as_str(), &decl.span))
+                            .collect(),
+                        _ => continue,
+                    };
+                    for (kind, name, span) in declarations {
+                        let description = workflow
+                            .parameter_description(name)
+                            .and_then(|desc| desc.description);
+                        harvester.add(
+                            kind,
+                            format!("{}.{}", workflow.name.as_str(), name),
+                            span,
+                            description,
+                        )
+                    }
+                }
+            }
+        }
+    }
+    harvester.docs
+}
+
+/// An author of a workflow or task.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Author {
+    pub name: String,
+    pub email: Option<String>,
+    pub affiliation: Option<String>,
+    pub orcid: Option<String>,
+}
+
+impl Author {
+    fn from_value(value: &MetaValue) -> Option<Self> {
+        match value {
+            MetaValue::Object(_) => {
+                let text = |field| value.get_field(field).and_then(|v| v.text());
+                Some(Self {
+                    name: text("name")?,
+                    email: text("email"),
+                    affiliation: text("affiliation"),
+                    orcid: text("orcid"),
+                })
+            }
+            _ => Some(Self {
+                name: value.text()?,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+/// The metadata of a workflow input or output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParameterMetadata {
+    /// Either `Input` or `Output`.
+    pub kind: ElementKind,
+    pub name: String,
+    /// The name to display in user interfaces: the `label` or `display_name` of the parameter's
+    /// `parameter_meta` entry.
+    pub display_name: Option<String>,
+    pub description: Option<String>,
+    pub help: Option<String>,
+}
+
+/// The metadata of a workflow that is recognized by workflow registries.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WorkflowMetadata {
+    pub name: String,
+    /// The `description` from the workflow's `meta` section, or else its doc comment.
+    pub description: Option<String>,
+    /// The authors of the workflow, followed by the authors of the tasks in the same document
+    /// that are not also authors of the workflow.
+    pub authors: Vec<Author>,
+    /// The workflow's inputs and then its outputs, in source order.
+    pub parameters: Vec<ParameterMetadata>,
+}
+
+impl WorkflowMetadata {
+    /// Returns the metadata of the parameter `name`.
+    pub fn parameter(&self, name: &str) -> Option<&ParameterMetadata> {
+        self.parameters
+            .iter()
+            .find(|parameter| parameter.name == name)
+    }
+}
+
+/// Returns the authors in the `meta` attributes of a workflow or task. Authors may be given as
+/// `author` (a name, or an array of names) with an optional `email` that applies to the first
+/// author, or as `authors`: an array of names or of objects with `name`, `email`, `affiliation`,
+/// and `orcid` fields.
+fn meta_authors(attributes: &[Anchor<MetaAttribute>]) -> Vec<Author> {
+    let attribute = |name| {
+        attributes
+            .iter()
+            .find(|attribute| attribute.name.as_str() == name)
+            .map(|attribute| attribute.value.deref())
+    };
+    let mut authors: Vec<Author> = match attribute("author").or_else(|| attribute("authors")) {
+        Some(MetaValue::Array(array)) => array
+            .elements
+            .iter()
+            .filter_map(|element| Author::from_value(element))
+            .collect(),
+        Some(value) => Author::from_value(value).into_iter().collect(),
+        None => Vec::new(),
+    };
+    if let (Some(author), Some(email)) = (
+        authors.first_mut(),
+        attribute("email").and_then(|value| value.text()),
+    ) {
+        author.email.get_or_insert(email);
+    }
+    authors
+}
+
+fn parameter_metadata(workflow: &Workflow, kind: ElementKind, name: &str) -> ParameterMetadata {
+    let mut parameter = ParameterMetadata {
+        kind,
+        name: name.to_owned(),
+        display_name: None,
+        description: None,
+        help: None,
+    };
+    if let Some(desc) = workflow.parameter_description(name) {
+        parameter.display_name = desc.label;
+        parameter.description = desc.description;
+        parameter.help = desc.help;
+    }
+    if parameter.display_name.is_none() {
+        parameter.display_name = workflow.body.iter().find_map(|e| match e.deref() {
+            WorkflowElement::ParameterMeta(meta) => meta
+                .attributes
+                .iter()
+                .find(|attribute| attribute.name.as_str() == name)?
+                .value
+                .get_field("display_name")?
+                .text(),
+            _ => None,
+        });
+    }
+    parameter
+}
+
+/// Returns the registry metadata of the workflow in `document`, or `None` if the document does
+/// not contain a workflow. Doc comments are recognized in `style`.
+pub fn workflow_metadata(document: &Document, style: DocCommentStyle) -> Option<WorkflowMetadata> {
+    let (workflow, span) = document
+        .body
+        .iter()
+        .find_map(|element| match element.deref() {
+            DocumentElement::Workflow(workflow) => Some((workflow, &element.span)),
+            _ => None,
+        })?;
+    let meta = workflow.body.iter().find_map(|e| match e.deref() {
+        WorkflowElement::Meta(meta) => Some(meta),
+        _ => None,
+    });
+    let mut metadata = WorkflowMetadata {
+        name: workflow.name.as_str().to_owned(),
+        description: meta
+            .and_then(meta_description)
+            .or_else(|| doc_comment(&document.comments, span.start.line, style)),
+        authors: meta
+            .map(|meta| meta_authors(&meta.attributes))
+            .unwrap_or_default(),
+        parameters: Vec::new(),
+    };
+    for task in document.body_iter().filter_map(|element| match element {
+        DocumentElement::Task(task) => Some(task),
+        _ => None,
+    }) {
+        for e in task.body.iter() {
+            if let TaskElement::Meta(meta) = e.deref() {
+                for author in meta_authors(&meta.attributes) {
+                    if !metadata.authors.iter().any(|a| a.name == author.name) {
+                        metadata.authors.push(author)
+                    }
+                }
+            }
+        }
+    }
+    for decl in workflow
+        .inputs()
+        .iter()
+        .flat_map(|input| &input.declarations)
+    {
+        metadata.parameters.push(parameter_metadata(
+            workflow,
+            ElementKind::Input,
+            decl.name().as_str(),
+        ))
+    }
+    for decl in workflow
+        .outputs()
+        .iter()
+        .flat_map(|output| &output.declarations)
+    {
+        metadata.parameters.push(parameter_metadata(
+            workflow,
+            ElementKind::Output,
+            decl.name.as_str(),
+        ))
+    }
+    Some(metadata)
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_harvest() {
+        let text = r#"version 1.1
+
+# Copyright notice
+## Aligns reads.
+##
+## Unpaired reads are aligned in single-end mode.
+task align {
+    input {
+        ## The reads to align.
+        File reads
+        Int threads = 1 # not documentation
+        String sample
+    }
+    command <<< >>>
+    output {
+        #@ not a doc comment in this style
+        File bam = "out.bam"
+    }
+    parameter_meta {
+        sample: "the sample name"
+    }
+}
+
+workflow wf {
+    meta {
+        description: "the workflow"
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let docs = harvest(&doc, DocCommentStyle::default());
+        let docs: Vec<(ElementKind, &str, Option<&str>)> = docs
+            .iter()
+            .map(|d| (d.kind, d.name.as_str(), d.text()))
+            .collect();
+        assert_eq!(
+            docs,
+            vec![
+                (
+                    ElementKind::Task,
+                    "align",
+                    Some("Aligns reads.\n\nUnpaired reads are aligned in single-end mode.")
+                ),
+                (
+                    ElementKind::Input,
+                    "align.reads",
+                    Some("The reads to align.")
+                ),
+                (ElementKind::Input, "align.threads", None),
+                (ElementKind::Input, "align.sample", Some("the sample name")),
+                (ElementKind::Output, "align.bam", None),
+                (ElementKind::Workflow, "wf", Some("the workflow")),
+            ]
+        );
+        assert_eq!(
+            doc_comment(&doc.comments, 16, DocCommentStyle::HashAt).as_deref(),
+            Some("not a doc comment in this style")
+        );
+        assert_eq!(
+            doc_comment(&doc.comments, 6, DocCommentStyle::Any).as_deref(),
+            Some(
+                "Copyright notice\nAligns reads.\n\nUnpaired reads are aligned in single-end mode."
+            )
+        );
+    }
+
+    #[test]
+    fn test_workflow_metadata() {
+        let text = r#"version 1.1
+
+task align {
+    meta {
+        author: ["Jane Doe", "John Roe"]
+    }
+    command <<< >>>
+}
+
+## Aligns all the reads.
+workflow wf {
+    input {
+        File reads
+        String sample
+    }
+    output {
+        File bam = "out.bam"
+    }
+    meta {
+        author: "Jane Doe"
+        email: "jane@example.com"
+    }
+    parameter_meta {
+        reads: { label: "Reads", description: "the reads to align" }
+        sample: { display_name: "Sample name", help: "used to name outputs" }
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let metadata = workflow_metadata(&doc, DocCommentStyle::default()).unwrap();
+        assert_eq!(metadata.name, "wf");
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("Aligns all the reads.")
+        );
+        assert_eq!(
+            metadata.authors,
+            vec![
+                Author {
+                    name: "Jane Doe".to_owned(),
+                    email: Some("jane@example.com".to_owned()),
+                    ..Default::default()
+                },
+                Author {
+                    name: "John Roe".to_owned(),
+                    ..Default::default()
+                },
+            ]
+        );
+        assert_eq!(
+            metadata.parameter("reads"),
+            Some(&ParameterMetadata {
+                kind: ElementKind::Input,
+                name: "reads".to_owned(),
+                display_name: Some("Reads".to_owned()),
+                description: Some("the reads to align".to_owned()),
+                help: None,
+            })
+        );
+        let sample = metadata.parameter("sample").unwrap();
+        assert_eq!(sample.display_name.as_deref(), Some("Sample name"));
+        assert_eq!(sample.help.as_deref(), Some("used to name outputs"));
+        assert_eq!(metadata.parameter("bam").unwrap().kind, ElementKind::Output);
+    }
+}
@@ -0,0 +1,112 @@
+//! Text edits to the source of a document, e.g. for refactorings and quick fixes.
+use crate::model::{Position, Span};
+use error_stack::{ensure, Result};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum EditError {
+    #[error("edit span {0} is outside of the text")]
+    OutOfBounds(Span),
+    #[error("edits at {0} and {1} overlap")]
+    Overlap(Span, Span),
+}
+
+/// Replaces the text in `span` with `text`. An edit with an empty span is an insertion.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextEdit {
+    pub span: Span,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn replace<S: Into<String>>(span: Span, text: S) -> Self {
+        Self {
+            span,
+            text: text.into(),
+        }
+    }
+
+    pub fn insert<S: Into<String>>(position: Position, text: S) -> Self {
+        Self {
+            span: Span {
+                start: position.clone(),
+                end: position,
+            },
+            text: text.into(),
+        }
+    }
+
+    pub fn delete(span: Span) -> Self {
+        Self::replace(span, "")
+    }
+}
+
+/// Applies `edits` to `text`. Edits are located by the byte offsets of their spans, which refer to
+/// the original text. Edits may not overlap, except that multiple insertions at the same position
+/// are applied in the order in which they are given.
+pub fn apply_edits(text: &str, edits: &[TextEdit]) -> Result<String, EditError> {
+    let mut edits: Vec<&TextEdit> = edits.iter().collect();
+    // stable sort, so insertions at the same position keep their order
+    edits.sort_by_key(|edit| (edit.span.start.offset, edit.span.end.offset));
+    let mut result = String::with_capacity(text.len());
+    let mut offset = 0;
+    let mut previous: Option<&TextEdit> = None;
+    for edit in edits {
+        let (start, end) = (edit.span.start.offset, edit.span.end.offset);
+        ensure!(
+            start <= end
+                && end <= text.len()
+                && text.is_char_boundary(start)
+                && text.is_char_boundary(end),
+            EditError::OutOfBounds(edit.span.clone())
+        );
+        if let Some(previous) = previous {
+            ensure!(
+                start >= offset,
+                EditError::Overlap(previous.span.clone(), edit.span.clone())
+            );
+        }
+        result.push_str(&text[offset..start]);
+        result.push_str(&edit.text);
+        offset = end;
+        previous = Some(edit);
+    }
+    result.push_str(&text[offset..]);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_apply_edits() {
+        let span = |start: usize, end: usize| Span::from_components(0, start, start, 0, end, end);
+        let text = "Int x = 1";
+        let edits = vec![
+            TextEdit::replace(span(8, 9), "2"),
+            TextEdit::insert(Position::new(0, 0, 0), "# a\n"),
+            TextEdit::insert(Position::new(0, 0, 0), "# b\n"),
+            TextEdit::delete(span(3, 5)),
+        ];
+        assert_eq!(apply_edits(text, &edits).unwrap(), "# a\n# b\nInt = 2");
+        let overlapping = vec![
+            TextEdit::replace(span(0, 5), "a"),
+            TextEdit::replace(span(4, 6), "b"),
+        ];
+        assert!(matches!(
+            apply_edits(text, &overlapping)
+                .unwrap_err()
+                .current_context(),
+            EditError::Overlap(_, _)
+        ));
+        let out_of_bounds = vec![TextEdit::delete(span(5, 20))];
+        assert!(matches!(
+            apply_edits(text, &out_of_bounds)
+                .unwrap_err()
+                .current_context(),
+            EditError::OutOfBounds(_)
+        ));
+    }
+}
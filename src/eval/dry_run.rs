@@ -0,0 +1,544 @@
+use crate::{
+    eval::{evaluate, Environment},
+    fqn,
+    model::{
+        Anchor, BoundDeclaration, Call, Conditional, Document, DocumentElement, Expression,
+        InputDeclaration, Scatter, TaskElement, Type, Workflow, WorkflowElement,
+        WorkflowNestedElement,
+    },
+    values::WdlValue,
+};
+use error_stack::{bail, Result, ResultExt};
+use std::{collections::HashMap, ops::Deref};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum DryRunError {
+    #[error("Document does not contain a workflow")]
+    MissingWorkflow,
+    #[error("Invalid value for input {0}")]
+    Input(String),
+    #[error("Missing value for required input {0}")]
+    MissingInput(String),
+    #[error("No stub outputs for call {0}")]
+    MissingStub(String),
+    #[error("Error evaluating {0}")]
+    Eval(String),
+    #[error("Collection of scatter over {0} is not an array")]
+    Scatter(String),
+    #[error("Conditional expression is not a Boolean: {0}")]
+    Condition(String),
+    #[error("Invalid value for output {0}")]
+    Output(String),
+}
+
+type StubFn = dyn Fn(&HashMap<String, WdlValue>) -> HashMap<String, WdlValue>;
+
+/// A call that was executed during a dry run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallRecord {
+    /// The name by which the call is referenced in the workflow.
+    pub call: String,
+    /// The indices of the call's shard in each of its enclosing scatters, from outermost to
+    /// innermost.
+    pub shard: Vec<usize>,
+    /// The values of the inputs that are specified in the call.
+    pub inputs: HashMap<String, WdlValue>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DryRunResult {
+    /// The workflow outputs, in declaration order.
+    pub outputs: Vec<(String, WdlValue)>,
+    /// The calls that were executed, in order of execution.
+    pub calls: Vec<CallRecord>,
+}
+
+/// Simulates the execution of a workflow without running any tasks: declarations and conditions
+/// are evaluated, scatters are expanded, and each call produces the stub outputs that are
+/// registered for it. This is intended for smoke-testing workflow logic independently of an
+/// engine.
+///
+/// Elements are executed in the order in which they appear in the workflow, so an element that
+/// refers to a later element fails to evaluate. Expressions may only use the functions that are
+/// supported by `evaluate`.
+pub struct DryRun<'a> {
+    document: &'a Document,
+    stubs: HashMap<String, Box<StubFn>>,
+}
+
+impl<'a> DryRun<'a> {
+    pub fn new(document: &'a Document) -> Self {
+        Self {
+            document,
+            stubs: HashMap::new(),
+        }
+    }
+
+    /// Registers `outputs` as the outputs of the calls named `name`, which may be either the
+    /// name by which a call is referenced or the name of the called task or workflow. Stubs for
+    /// call names take precedence over stubs for targets.
+    pub fn stub<S: Into<String>>(mut self, name: S, outputs: HashMap<String, WdlValue>) -> Self {
+        self.stubs
+            .insert(name.into(), Box::new(move |_| outputs.clone()));
+        self
+    }
+
+    /// Registers a function that computes the outputs of the calls named `name` from the values
+    /// of the call inputs.
+    pub fn stub_with<S, F>(mut self, name: S, f: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn(&HashMap<String, WdlValue>) -> HashMap<String, WdlValue> + 'static,
+    {
+        self.stubs.insert(name.into(), Box::new(f));
+        self
+    }
+
+    /// Runs the workflow in the document with the given inputs and returns its outputs. `inputs`
+    /// may be keyed by either unqualified workflow input names (e.g. `x`) or fully-qualified
+    /// names (e.g. `wf.x`). Input values are coerced to their declared types.
+    pub fn run(&self, inputs: &HashMap<String, WdlValue>) -> Result<DryRunResult, DryRunError> {
+        let workflow = self
+            .document
+            .body_iter()
+            .find_map(|element| match element {
+                DocumentElement::Workflow(workflow) => Some(workflow),
+                _ => None,
+            })
+            .ok_or(DryRunError::MissingWorkflow)?;
+        let mut runner = Runner {
+            dry_run: self,
+            shard: Vec::new(),
+            calls: Vec::new(),
+        };
+        let mut env = Environment::new();
+        for element in workflow.body.iter() {
+            match element.deref() {
+                WorkflowElement::Input(input) => {
+                    for decl in input.declarations.iter() {
+                        bind_input(workflow, decl, inputs, &mut env)?
+                    }
+                }
+                WorkflowElement::Declaration(decl) => runner.declaration(decl, &mut env)?,
+                WorkflowElement::Call(call) => runner.call(call, &mut env)?,
+                WorkflowElement::Scatter(scatter) => runner.scatter(scatter, &mut env)?,
+                WorkflowElement::Conditional(conditional) => {
+                    runner.conditional(conditional, &mut env)?
+                }
+                _ => (),
+            }
+        }
+        let mut outputs = Vec::new();
+        for element in workflow.body.iter() {
+            if let WorkflowElement::Output(output) = element.deref() {
+                for decl in output.declarations.iter() {
+                    let name = decl.name.as_str();
+                    let value = evaluate(&decl.expression, &env)
+                        .change_context(DryRunError::Eval(name.to_owned()))?
+                        .coerce(&decl.type_)
+                        .change_context(DryRunError::Output(name.to_owned()))?;
+                    env.bind(name, value.clone());
+                    outputs.push((name.to_owned(), value));
+                }
+            }
+        }
+        Ok(DryRunResult {
+            outputs,
+            calls: runner.calls,
+        })
+    }
+}
+
+fn bind_input(
+    workflow: &Workflow,
+    decl: &InputDeclaration,
+    inputs: &HashMap<String, WdlValue>,
+    env: &mut Environment,
+) -> Result<(), DryRunError> {
+    let (name, type_) = (decl.name().as_str(), decl.type_());
+    let qualified = format!("{}.{}", workflow.name.as_str(), name);
+    let value = match (inputs.get(&qualified).or_else(|| inputs.get(name)), decl) {
+        (Some(value), _) => value
+            .clone()
+            .coerce(type_)
+            .change_context(DryRunError::Input(name.to_owned()))?,
+        (None, InputDeclaration::Bound(decl)) => evaluate(&decl.expression, env)
+            .change_context(DryRunError::Eval(name.to_owned()))?
+            .coerce(type_)
+            .change_context(DryRunError::Input(name.to_owned()))?,
+        (None, InputDeclaration::Unbound(_)) if matches!(type_.deref(), Type::Optional(_)) => {
+            WdlValue::None
+        }
+        (None, InputDeclaration::Unbound(_)) => {
+            bail!(DryRunError::MissingInput(name.to_owned()))
+        }
+    };
+    env.bind(name, value);
+    Ok(())
+}
+
+/// Adds the names that are declared in `body` and are visible after the enclosing block to
+/// `names`, along with the call for names that refer to calls. This includes the names declared in
+/// nested blocks.
+fn declared_names<'a>(
+    body: &'a [Anchor<WorkflowNestedElement>],
+    names: &mut Vec<(String, Option<&'a Call>)>,
+) {
+    for element in body.iter() {
+        match element.deref() {
+            WorkflowNestedElement::Declaration(decl) => names.push(((*decl.name).clone(), None)),
+            WorkflowNestedElement::Call(call) => {
+                names.push((fqn::call_name(call).to_owned(), Some(call)))
+            }
+            WorkflowNestedElement::Scatter(scatter) => declared_names(&scatter.body, names),
+            WorkflowNestedElement::Conditional(conditional) => {
+                declared_names(&conditional.body, names)
+            }
+        }
+    }
+}
+
+/// Returns the names of the outputs of the task called by `call`, or an empty `Vec` if the task
+/// is not defined in `document`.
+fn output_names(document: &Document, call: &Call) -> Vec<String> {
+    let task = match fqn::find_local_task(document, call) {
+        Some(task) => task,
+        None => return Vec::new(),
+    };
+    task.body
+        .iter()
+        .flat_map(|element| match element.deref() {
+            TaskElement::Output(output) => output
+                .declarations
+                .iter()
+                .map(|decl| (*decl.name).clone())
+                .collect(),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+/// Gathers the values of a name across the shards of a scatter. Calls are represented by objects
+/// with one member per output, so the values of each output are gathered into an array instead,
+/// i.e. `call.output` has type `Array[T]` after the scatter.
+fn gather(document: &Document, call: Option<&Call>, values: Vec<WdlValue>) -> WdlValue {
+    let call = match call {
+        Some(call) => call,
+        None => return WdlValue::Array(values),
+    };
+    let mut members: Vec<(String, Vec<WdlValue>)> = output_names(document, call)
+        .into_iter()
+        .map(|name| (name, Vec::new()))
+        .collect();
+    for value in values.iter() {
+        if let WdlValue::Object(outputs) = value {
+            for (name, _) in outputs.iter() {
+                if !members.iter().any(|(member, _)| member == name) {
+                    members.push((name.clone(), Vec::new()))
+                }
+            }
+        }
+    }
+    for (name, gathered) in members.iter_mut() {
+        for value in values.iter() {
+            let output = match value {
+                WdlValue::Object(outputs) => outputs
+                    .iter()
+                    .find(|(output, _)| output == name)
+                    .map(|(_, value)| value.clone()),
+                _ => None,
+            };
+            gathered.push(output.unwrap_or(WdlValue::None))
+        }
+    }
+    WdlValue::Object(
+        members
+            .into_iter()
+            .map(|(name, values)| (name, WdlValue::Array(values)))
+            .collect(),
+    )
+}
+
+/// Returns the value of a name whose enclosing conditional is false. A call is represented by an
+/// object whose members are all undefined, so that `call.output` evaluates to `None`.
+fn skipped(document: &Document, call: Option<&Call>) -> WdlValue {
+    match call {
+        Some(call) => WdlValue::Object(
+            output_names(document, call)
+                .into_iter()
+                .map(|name| (name, WdlValue::None))
+                .collect(),
+        ),
+        None => WdlValue::None,
+    }
+}
+
+struct Runner<'a, 'b> {
+    dry_run: &'b DryRun<'a>,
+    /// The index of the current shard of each enclosing scatter.
+    shard: Vec<usize>,
+    calls: Vec<CallRecord>,
+}
+
+impl<'a, 'b> Runner<'a, 'b> {
+    fn body(
+        &mut self,
+        body: &[Anchor<WorkflowNestedElement>],
+        env: &mut Environment,
+    ) -> Result<(), DryRunError> {
+        for element in body.iter() {
+            match element.deref() {
+                WorkflowNestedElement::Declaration(decl) => self.declaration(decl, env)?,
+                WorkflowNestedElement::Call(call) => self.call(call, env)?,
+                WorkflowNestedElement::Scatter(scatter) => self.scatter(scatter, env)?,
+                WorkflowNestedElement::Conditional(conditional) => {
+                    self.conditional(conditional, env)?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn declaration(
+        &mut self,
+        decl: &BoundDeclaration,
+        env: &mut Environment,
+    ) -> Result<(), DryRunError> {
+        let name = decl.name.as_str();
+        let value = evaluate(&decl.expression, env)
+            .change_context(DryRunError::Eval(name.to_owned()))?
+            .coerce(&decl.type_)
+            .change_context(DryRunError::Eval(name.to_owned()))?;
+        env.bind(name, value);
+        Ok(())
+    }
+
+    fn call(&mut self, call: &Call, env: &mut Environment) -> Result<(), DryRunError> {
+        let name = fqn::call_name(call);
+        let mut inputs = HashMap::new();
+        for input in call.inputs.iter().flatten() {
+            let input_name = input.name.as_str();
+            let value = match &input.expression {
+                Some(expression) => evaluate(expression, env),
+                None => evaluate(&Expression::Identifier(input_name.to_owned()), env),
+            }
+            .change_context_lazy(|| DryRunError::Eval(format!("{}.{}", name, input_name)))?;
+            inputs.insert(input_name.to_owned(), value);
+        }
+        let target = call
+            .target
+            .parts
+            .iter()
+            .map(|part| part.as_str())
+            .collect::<Vec<&str>>()
+            .join(".");
+        let outputs = match self
+            .dry_run
+            .stubs
+            .get(name)
+            .or_else(|| self.dry_run.stubs.get(&target))
+        {
+            Some(stub) => stub(&inputs),
+            None if fqn::find_local_task(self.dry_run.document, call).is_some()
+                && output_names(self.dry_run.document, call).is_empty() =>
+            {
+                HashMap::new()
+            }
+            None => bail!(DryRunError::MissingStub(name.to_owned())),
+        };
+        let mut members: Vec<(String, WdlValue)> = outputs.into_iter().collect();
+        members.sort_by(|a, b| a.0.cmp(&b.0));
+        env.bind(name, WdlValue::Object(members));
+        self.calls.push(CallRecord {
+            call: name.to_owned(),
+            shard: self.shard.clone(),
+            inputs,
+        });
+        Ok(())
+    }
+
+    fn scatter(&mut self, scatter: &Scatter, env: &mut Environment) -> Result<(), DryRunError> {
+        let name = scatter.name.as_str();
+        let items = match evaluate(&scatter.expression, env)
+            .change_context_lazy(|| DryRunError::Eval(format!("scatter over {}", name)))?
+        {
+            WdlValue::Array(items) => items,
+            _ => bail!(DryRunError::Scatter(name.to_owned())),
+        };
+        let mut names = Vec::new();
+        declared_names(&scatter.body, &mut names);
+        let mut gathered: Vec<Vec<WdlValue>> = vec![Vec::with_capacity(items.len()); names.len()];
+        for (i, item) in items.into_iter().enumerate() {
+            let mut shard_env = env.clone();
+            shard_env.bind(name, item);
+            self.shard.push(i);
+            let result = self.body(&scatter.body, &mut shard_env);
+            self.shard.pop();
+            result?;
+            for ((name, _), values) in names.iter().zip(gathered.iter_mut()) {
+                values.push(shard_env.get(name).cloned().unwrap_or(WdlValue::None))
+            }
+        }
+        let document = self.dry_run.document;
+        for ((name, call), values) in names.into_iter().zip(gathered) {
+            env.bind(name, gather(document, call, values))
+        }
+        Ok(())
+    }
+
+    fn conditional(
+        &mut self,
+        conditional: &Conditional,
+        env: &mut Environment,
+    ) -> Result<(), DryRunError> {
+        let condition = match evaluate(&conditional.expression, env)
+            .change_context_lazy(|| DryRunError::Eval(conditional.expression.deref().to_string()))?
+        {
+            WdlValue::Boolean(b) => b,
+            _ => bail!(DryRunError::Condition(
+                conditional.expression.deref().to_string()
+            )),
+        };
+        let mut names = Vec::new();
+        declared_names(&conditional.body, &mut names);
+        if condition {
+            let mut block_env = env.clone();
+            self.body(&conditional.body, &mut block_env)?;
+            for (name, _) in names {
+                let value = block_env.get(&name).cloned().unwrap_or(WdlValue::None);
+                env.bind(name, value)
+            }
+        } else {
+            for (name, call) in names {
+                env.bind(name, skipped(self.dry_run.document, call))
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    const WDL: &str = r#"version 1.1
+
+task align {
+    input {
+        String sample
+        String prefix
+    }
+    command <<<
+        bwa mem ~{sample} > ~{prefix}.bam
+    >>>
+    output {
+        File bam = "~{prefix}.bam"
+    }
+}
+
+task summarize {
+    input {
+        Array[File] bams
+    }
+    command <<<
+        summarize ~{sep(" ", bams)}
+    >>>
+    output {
+        File report = "report.html"
+    }
+}
+
+workflow wf {
+    input {
+        Array[String] samples
+        Boolean report = false
+        Int? threads
+    }
+    scatter (sample in samples) {
+        String prefix = "out/" + sample
+        call align { input: sample, prefix }
+    }
+    if (report) {
+        call summarize { input: bams = align.bam }
+    }
+    output {
+        Array[File] bams = align.bam
+        File? summary = summarize.report
+    }
+}
+"#;
+
+    #[test]
+    fn test_dry_run() {
+        let doc = PestParser::new()
+            .parse_text(WDL, DocumentSource::Unknown)
+            .unwrap();
+        let dry_run = DryRun::new(&doc)
+            .stub_with("align", |inputs| {
+                let prefix = match inputs.get("prefix") {
+                    Some(WdlValue::String(prefix)) => prefix.clone(),
+                    _ => panic!("missing prefix"),
+                };
+                HashMap::from([("bam".to_owned(), WdlValue::File(prefix + ".bam"))])
+            })
+            .stub(
+                "summarize",
+                HashMap::from([(
+                    "report".to_owned(),
+                    WdlValue::File("report.html".to_owned()),
+                )]),
+            );
+        let samples = WdlValue::Array(vec![
+            WdlValue::String("a".to_owned()),
+            WdlValue::String("b".to_owned()),
+        ]);
+        let inputs = HashMap::from([("wf.samples".to_owned(), samples.clone())]);
+        let result = dry_run.run(&inputs).unwrap();
+        let bams = WdlValue::Array(vec![
+            WdlValue::File("out/a.bam".to_owned()),
+            WdlValue::File("out/b.bam".to_owned()),
+        ]);
+        assert_eq!(
+            result.outputs,
+            vec![
+                ("bams".to_owned(), bams.clone()),
+                ("summary".to_owned(), WdlValue::None)
+            ]
+        );
+        let shards: Vec<(&str, &[usize])> = result
+            .calls
+            .iter()
+            .map(|call| (call.call.as_str(), call.shard.as_slice()))
+            .collect();
+        assert_eq!(shards, vec![("align", &[0][..]), ("align", &[1][..])]);
+
+        let inputs = HashMap::from([
+            ("samples".to_owned(), samples),
+            ("report".to_owned(), WdlValue::Boolean(true)),
+        ]);
+        let result = dry_run.run(&inputs).unwrap();
+        assert_eq!(
+            result.outputs[1],
+            (
+                "summary".to_owned(),
+                WdlValue::File("report.html".to_owned())
+            )
+        );
+        assert_eq!(result.calls[2].inputs.get("bams"), Some(&bams));
+
+        assert!(matches!(
+            dry_run.run(&HashMap::new()).unwrap_err().current_context(),
+            DryRunError::MissingInput(name) if name == "samples"
+        ));
+        assert!(matches!(
+            DryRun::new(&doc).run(&inputs).unwrap_err().current_context(),
+            DryRunError::MissingStub(name) if name == "align"
+        ));
+    }
+}
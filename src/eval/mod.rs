@@ -0,0 +1,719 @@
+//! Evaluation of WDL expressions to values. Only the subset of the standard library that does not
+//! require access to the file system or an execution engine is supported; applying any other
+//! function results in an `EvalError::UnsupportedFunction` error.
+use crate::{
+    model::{
+        unescape, AccessOperation, Anchor, BinaryOperator, Expression, ObjectKind, StringPart,
+        UnaryOperator,
+    },
+    values::WdlValue,
+};
+use error_stack::{bail, ensure, IntoReport, Report, Result, ResultExt};
+use regex::Regex;
+use std::{collections::HashMap, ops::Deref};
+use thiserror::Error;
+
+mod dry_run;
+
+pub use dry_run::{CallRecord, DryRun, DryRunError, DryRunResult};
+
+#[derive(Error, Debug)]
+pub enum EvalError {
+    #[error("Unknown identifier {0}")]
+    UnknownIdentifier(String),
+    #[error("Unsupported function {0}")]
+    UnsupportedFunction(String),
+    #[error("Function {name} expects {expected} argument(s) but got {actual}")]
+    Arguments {
+        name: String,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("Invalid operand(s) for {operation}: {operands}")]
+    Operand { operation: String, operands: String },
+    #[error("Index {index} is out of bounds for collection of length {length}")]
+    IndexOutOfBounds { index: i64, length: usize },
+    #[error("Key {0} not found")]
+    KeyNotFound(String),
+    #[error("Division by zero")]
+    DivisionByZero,
+    #[error("Integer overflow in {0}")]
+    Overflow(String),
+    #[error("{function} would create an array of {length} elements (the maximum is {max})")]
+    ArrayTooLarge {
+        function: String,
+        length: usize,
+        max: usize,
+    },
+    #[error("All arguments to select_first are undefined")]
+    SelectFirst,
+    #[error("Invalid regular expression {0}")]
+    Regex(String),
+    #[error("Invalid escape sequence {0}")]
+    Escape(String),
+}
+
+/// The largest array that a standard library function (e.g. `range` or `cross`) creates.
+/// Documents are evaluated during static analysis (e.g. to determine the widths of scatters), so
+/// larger arrays result in an error rather than exhausting memory.
+pub const MAX_ARRAY_LENGTH: usize = 1_000_000;
+
+/// A mapping of identifiers to values in which expressions are evaluated.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Environment {
+    values: HashMap<String, WdlValue>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds `name` to `value`, replacing any existing binding.
+    pub fn bind<S: Into<String>>(&mut self, name: S, value: WdlValue) {
+        self.values.insert(name.into(), value);
+    }
+
+    /// Returns the value bound to `name`, if any.
+    pub fn get(&self, name: &str) -> Option<&WdlValue> {
+        self.values.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+}
+
+impl From<HashMap<String, WdlValue>> for Environment {
+    fn from(values: HashMap<String, WdlValue>) -> Self {
+        Self { values }
+    }
+}
+
+/// Evaluates `expression` in `env`.
+pub fn evaluate(expression: &Expression, env: &Environment) -> Result<WdlValue, EvalError> {
+    let value = match expression {
+        Expression::None => WdlValue::None,
+        Expression::Boolean(b) => WdlValue::Boolean(*b),
+        Expression::Int(i) => WdlValue::Int(i.value),
+        Expression::Float(f) => WdlValue::Float(f.value),
+        Expression::String(s) => {
+            let mut value = String::new();
+            for part in s.parts.iter() {
+                match part.deref() {
+                    StringPart::Content(content) => value.push_str(content),
+                    StringPart::Escape(escape) => match unescape(escape) {
+                        Some(unescaped) => value.push_str(&unescaped),
+                        None => bail!(Report::new(EvalError::Escape(escape.clone()))
+                            .attach_printable(part.span.clone())),
+                    },
+                    StringPart::Placeholder(expression, _) => match evaluate(expression, env)? {
+                        WdlValue::None => (),
+                        v => value.push_str(&v.to_string()),
+                    },
+                }
+            }
+            WdlValue::String(value)
+        }
+        Expression::Array(a) => WdlValue::Array(evaluate_all(&a.elements, env)?),
+        Expression::Map(m) => WdlValue::Map(
+            m.entries
+                .iter()
+                .map(|entry| Ok((evaluate(&entry.key, env)?, evaluate(&entry.value, env)?)))
+                .collect::<Result<Vec<_>, EvalError>>()?,
+        ),
+        Expression::Pair(p) => WdlValue::Pair(
+            Box::new(evaluate(&p.left, env)?),
+            Box::new(evaluate(&p.right, env)?),
+        ),
+        Expression::Object(o) => {
+            let members = o
+                .fields
+                .iter()
+                .map(|field| Ok(((*field.name).clone(), evaluate(&field.expression, env)?)))
+                .collect::<Result<Vec<_>, EvalError>>()?;
+            match o.kind {
+                ObjectKind::Object => WdlValue::Object(members),
+                ObjectKind::Struct => WdlValue::Struct {
+                    name: (*o.type_name).clone(),
+                    members,
+                },
+            }
+        }
+        Expression::Unary(u) => {
+            let value = evaluate(&u.expression, env)?;
+            match (&u.operator, value) {
+                (UnaryOperator::Pos, v @ (WdlValue::Int(_) | WdlValue::Float(_))) => v,
+                (UnaryOperator::Neg, WdlValue::Int(i)) => match i.checked_neg() {
+                    Some(i) => WdlValue::Int(i),
+                    None => bail!(EvalError::Overflow(format!("{}{}", u.operator, i))),
+                },
+                (UnaryOperator::Neg, WdlValue::Float(f)) => WdlValue::Float(-f),
+                (UnaryOperator::Not, WdlValue::Boolean(b)) => WdlValue::Boolean(!b),
+                (operator, v) => bail!(EvalError::Operand {
+                    operation: operator.to_string(),
+                    operands: v.kind().to_owned()
+                }),
+            }
+        }
+        Expression::Binary(b) => {
+            let left = evaluate(&b.left, env)?;
+            match (&b.operator, left) {
+                (BinaryOperator::And, WdlValue::Boolean(false)) => WdlValue::Boolean(false),
+                (BinaryOperator::Or, WdlValue::Boolean(true)) => WdlValue::Boolean(true),
+                (operator, left) => binary(operator, left, evaluate(&b.right, env)?)?,
+            }
+        }
+        Expression::Apply(a) => {
+            let arguments = evaluate_all(&a.arguments, env)?;
+            apply(a.name.as_str(), arguments)?
+        }
+        Expression::Access(a) => {
+            let mut value = evaluate(&a.collection, env)?;
+            for access in a.accesses.iter() {
+                value = match access.deref() {
+                    AccessOperation::Index(index) => index_value(value, evaluate(index, env)?)?,
+                    AccessOperation::Field(field) => field_value(value, field)?,
+                }
+            }
+            value
+        }
+        Expression::Ternary(t) => match evaluate(&t.condition, env)? {
+            WdlValue::Boolean(true) => evaluate(&t.true_branch, env)?,
+            WdlValue::Boolean(false) => evaluate(&t.false_branch, env)?,
+            v => bail!(EvalError::Operand {
+                operation: "if".to_owned(),
+                operands: v.kind().to_owned()
+            }),
+        },
+        Expression::Group(g) => evaluate(g, env)?,
+        Expression::Identifier(name) => match env.get(name) {
+            Some(value) => value.clone(),
+            None => bail!(EvalError::UnknownIdentifier(name.clone())),
+        },
+    };
+    Ok(value)
+}
+
+fn evaluate_all(
+    expressions: &[Anchor<Expression>],
+    env: &Environment,
+) -> Result<Vec<WdlValue>, EvalError> {
+    expressions
+        .iter()
+        .map(|expression| evaluate(expression, env))
+        .collect()
+}
+
+fn operand_error(operator: &BinaryOperator, left: &WdlValue, right: &WdlValue) -> EvalError {
+    EvalError::Operand {
+        operation: operator.to_string(),
+        operands: format!("{}, {}", left.kind(), right.kind()),
+    }
+}
+
+fn binary(
+    operator: &BinaryOperator,
+    left: WdlValue,
+    right: WdlValue,
+) -> Result<WdlValue, EvalError> {
+    use WdlValue::{Boolean, Float, Int};
+    let value = match (operator, &left, &right) {
+        // Int is promoted to Float for comparison
+        (BinaryOperator::Eq | BinaryOperator::Neq, Int(_), Float(_))
+        | (BinaryOperator::Eq | BinaryOperator::Neq, Float(_), Int(_)) => {
+            let equal = as_float(&left) == as_float(&right);
+            Boolean(equal == matches!(operator, BinaryOperator::Eq))
+        }
+        (BinaryOperator::Eq, l, r) => Boolean(l == r),
+        (BinaryOperator::Neq, l, r) => Boolean(l != r),
+        (BinaryOperator::And, Boolean(l), Boolean(r)) => Boolean(*l && *r),
+        (BinaryOperator::Or, Boolean(l), Boolean(r)) => Boolean(*l || *r),
+        (BinaryOperator::Add, WdlValue::String(l), r)
+        | (BinaryOperator::Add, WdlValue::File(l), r)
+            if r.is_primitive() =>
+        {
+            WdlValue::String(format!("{}{}", l, r))
+        }
+        (BinaryOperator::Add, l, WdlValue::String(r)) if l.is_primitive() => {
+            WdlValue::String(format!("{}{}", l, r))
+        }
+        (BinaryOperator::Div | BinaryOperator::Mod, Int(_), Int(0)) => {
+            bail!(EvalError::DivisionByZero)
+        }
+        (_, Int(l), Int(r)) => match operator {
+            BinaryOperator::Add
+            | BinaryOperator::Sub
+            | BinaryOperator::Mul
+            | BinaryOperator::Div
+            | BinaryOperator::Mod => {
+                let value = match operator {
+                    BinaryOperator::Add => l.checked_add(*r),
+                    BinaryOperator::Sub => l.checked_sub(*r),
+                    BinaryOperator::Mul => l.checked_mul(*r),
+                    BinaryOperator::Div => l.checked_div(*r),
+                    _ => l.checked_rem(*r),
+                };
+                match value {
+                    Some(value) => Int(value),
+                    None => bail!(EvalError::Overflow(format!("{} {} {}", l, operator, r))),
+                }
+            }
+            BinaryOperator::Gt => Boolean(l > r),
+            BinaryOperator::Lt => Boolean(l < r),
+            BinaryOperator::Gte => Boolean(l >= r),
+            BinaryOperator::Lte => Boolean(l <= r),
+            _ => bail!(operand_error(operator, &left, &right)),
+        },
+        (_, Int(_) | Float(_), Int(_) | Float(_)) => {
+            let l = as_float(&left);
+            let r = as_float(&right);
+            match operator {
+                BinaryOperator::Add => Float(l + r),
+                BinaryOperator::Sub => Float(l - r),
+                BinaryOperator::Mul => Float(l * r),
+                BinaryOperator::Div => Float(l / r),
+                BinaryOperator::Mod => Float(l % r),
+                BinaryOperator::Gt => Boolean(l > r),
+                BinaryOperator::Lt => Boolean(l < r),
+                BinaryOperator::Gte => Boolean(l >= r),
+                BinaryOperator::Lte => Boolean(l <= r),
+                _ => bail!(operand_error(operator, &left, &right)),
+            }
+        }
+        (
+            BinaryOperator::Gt | BinaryOperator::Lt | BinaryOperator::Gte | BinaryOperator::Lte,
+            WdlValue::String(l),
+            WdlValue::String(r),
+        ) => match operator {
+            BinaryOperator::Gt => Boolean(l > r),
+            BinaryOperator::Lt => Boolean(l < r),
+            BinaryOperator::Gte => Boolean(l >= r),
+            _ => Boolean(l <= r),
+        },
+        _ => bail!(operand_error(operator, &left, &right)),
+    };
+    Ok(value)
+}
+
+fn as_float(value: &WdlValue) -> f64 {
+    match value {
+        WdlValue::Int(i) => *i as f64,
+        WdlValue::Float(f) => *f,
+        _ => f64::NAN,
+    }
+}
+
+/// Returns an error if `function` would create an array of `length` elements, which is more than
+/// `MAX_ARRAY_LENGTH`.
+fn check_length(function: &str, length: usize) -> Result<(), EvalError> {
+    ensure!(
+        length <= MAX_ARRAY_LENGTH,
+        EvalError::ArrayTooLarge {
+            function: function.to_owned(),
+            length,
+            max: MAX_ARRAY_LENGTH
+        }
+    );
+    Ok(())
+}
+
+/// Converts the result of `floor`, `ceil`, or `round` to an `Int`, or returns an error if it is
+/// out of range.
+fn float_to_int(function: &str, value: f64) -> Result<i64, EvalError> {
+    // i64::MAX is not representable as an f64, and rounds up to 2^63
+    ensure!(
+        value >= i64::MIN as f64 && value < i64::MAX as f64,
+        EvalError::Overflow(format!("{}({})", function, value))
+    );
+    Ok(value as i64)
+}
+
+fn index_value(collection: WdlValue, index: WdlValue) -> Result<WdlValue, EvalError> {
+    match (collection, index) {
+        (WdlValue::Array(mut values), WdlValue::Int(i)) => {
+            let length = values.len();
+            ensure!(
+                i >= 0 && (i as usize) < length,
+                EvalError::IndexOutOfBounds { index: i, length }
+            );
+            Ok(values.swap_remove(i as usize))
+        }
+        (WdlValue::Map(entries), key) => entries
+            .into_iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v)
+            .ok_or_else(|| Report::new(EvalError::KeyNotFound(key.to_string()))),
+        (collection, index) => bail!(EvalError::Operand {
+            operation: "[]".to_owned(),
+            operands: format!("{}, {}", collection.kind(), index.kind())
+        }),
+    }
+}
+
+fn field_value(value: WdlValue, field: &str) -> Result<WdlValue, EvalError> {
+    match (value, field) {
+        (WdlValue::Pair(left, _), "left") => Ok(*left),
+        (WdlValue::Pair(_, right), "right") => Ok(*right),
+        (WdlValue::Object(members), _) | (WdlValue::Struct { members, .. }, _) => members
+            .into_iter()
+            .find(|(name, _)| name == field)
+            .map(|(_, v)| v)
+            .ok_or_else(|| Report::new(EvalError::KeyNotFound(field.to_owned()))),
+        (value, _) => bail!(EvalError::Operand {
+            operation: ".".to_owned(),
+            operands: format!("{}, {}", value.kind(), field)
+        }),
+    }
+}
+
+fn check_arguments(
+    name: &str,
+    arguments: &[WdlValue],
+    min: usize,
+    max: usize,
+) -> Result<(), EvalError> {
+    let expected = if arguments.len() < min { min } else { max };
+    ensure!(
+        arguments.len() >= min && arguments.len() <= max,
+        EvalError::Arguments {
+            name: name.to_owned(),
+            expected,
+            actual: arguments.len(),
+        }
+    );
+    Ok(())
+}
+
+fn invalid_arguments(name: &str, arguments: &[WdlValue]) -> Report<EvalError> {
+    let kinds: Vec<&str> = arguments.iter().map(|a| a.kind()).collect();
+    Report::new(EvalError::Operand {
+        operation: name.to_owned(),
+        operands: kinds.join(", "),
+    })
+}
+
+/// Applies the standard library function `name` to `arguments`.
+pub fn apply(name: &str, arguments: Vec<WdlValue>) -> Result<WdlValue, EvalError> {
+    let (min, max) = match name {
+        "defined" | "length" | "range" | "select_first" | "select_all" | "flatten" | "floor"
+        | "ceil" | "round" | "keys" | "as_pairs" | "as_map" => (1, 1),
+        "basename" => (1, 2),
+        "prefix" | "suffix" | "sep" | "min" | "max" | "zip" | "cross" => (2, 2),
+        "sub" => (3, 3),
+        _ => bail!(EvalError::UnsupportedFunction(name.to_owned())),
+    };
+    check_arguments(name, &arguments, min, max)?;
+    let mut iter = arguments.iter();
+    let first = iter.next().unwrap();
+    let value = match (name, first) {
+        ("defined", WdlValue::None) => WdlValue::Boolean(false),
+        ("defined", _) => WdlValue::Boolean(true),
+        ("length", WdlValue::Array(values)) => WdlValue::Int(values.len() as i64),
+        ("length", WdlValue::Map(entries)) => WdlValue::Int(entries.len() as i64),
+        ("length", WdlValue::String(s)) => WdlValue::Int(s.chars().count() as i64),
+        ("range", WdlValue::Int(n)) if *n >= 0 => {
+            check_length(name, usize::try_from(*n).unwrap_or(usize::MAX))?;
+            WdlValue::Array((0..*n).map(WdlValue::Int).collect())
+        }
+        ("select_first", WdlValue::Array(values)) => values
+            .iter()
+            .find(|v| **v != WdlValue::None)
+            .cloned()
+            .ok_or_else(|| Report::new(EvalError::SelectFirst))?,
+        ("select_all", WdlValue::Array(values)) => {
+            check_length(name, values.len())?;
+            WdlValue::Array(
+                values
+                    .iter()
+                    .filter(|v| **v != WdlValue::None)
+                    .cloned()
+                    .collect(),
+            )
+        }
+        ("flatten", WdlValue::Array(values)) => {
+            let arrays = values
+                .iter()
+                .map(|value| match value {
+                    WdlValue::Array(inner) => Ok(inner),
+                    _ => Err(invalid_arguments(name, &arguments)),
+                })
+                .collect::<Result<Vec<_>, EvalError>>()?;
+            check_length(
+                name,
+                arrays
+                    .iter()
+                    .fold(0, |length: usize, inner| length.saturating_add(inner.len())),
+            )?;
+            WdlValue::Array(arrays.into_iter().flatten().cloned().collect())
+        }
+        ("floor" | "ceil" | "round", WdlValue::Int(i)) => WdlValue::Int(*i),
+        ("floor", WdlValue::Float(f)) => WdlValue::Int(float_to_int(name, f.floor())?),
+        ("ceil", WdlValue::Float(f)) => WdlValue::Int(float_to_int(name, f.ceil())?),
+        ("round", WdlValue::Float(f)) => WdlValue::Int(float_to_int(name, f.round())?),
+        ("keys", WdlValue::Map(entries)) => {
+            check_length(name, entries.len())?;
+            WdlValue::Array(entries.iter().map(|(k, _)| k.clone()).collect())
+        }
+        ("as_pairs", WdlValue::Map(entries)) => {
+            check_length(name, entries.len())?;
+            WdlValue::Array(
+                entries
+                    .iter()
+                    .map(|(k, v)| WdlValue::Pair(Box::new(k.clone()), Box::new(v.clone())))
+                    .collect(),
+            )
+        }
+        ("as_map", WdlValue::Array(values)) => WdlValue::Map(
+            values
+                .iter()
+                .map(|v| match v {
+                    WdlValue::Pair(k, v) => Ok(((**k).clone(), (**v).clone())),
+                    _ => Err(invalid_arguments(name, &arguments)),
+                })
+                .collect::<Result<Vec<_>, EvalError>>()?,
+        ),
+        ("basename", WdlValue::String(path) | WdlValue::File(path)) => {
+            let base = path.rsplit('/').next().unwrap_or("");
+            match iter.next() {
+                Some(WdlValue::String(suffix)) => WdlValue::String(
+                    base.strip_suffix(suffix.as_str())
+                        .unwrap_or(base)
+                        .to_owned(),
+                ),
+                Some(_) => return Err(invalid_arguments(name, &arguments)),
+                None => WdlValue::String(base.to_owned()),
+            }
+        }
+        ("prefix" | "suffix", WdlValue::String(affix)) => match iter.next() {
+            Some(WdlValue::Array(values)) => {
+                check_length(name, values.len())?;
+                WdlValue::Array(
+                    values
+                        .iter()
+                        .map(|v| {
+                            WdlValue::String(if name == "prefix" {
+                                format!("{}{}", affix, v)
+                            } else {
+                                format!("{}{}", v, affix)
+                            })
+                        })
+                        .collect(),
+                )
+            }
+            _ => return Err(invalid_arguments(name, &arguments)),
+        },
+        ("sep", WdlValue::String(separator)) => match iter.next() {
+            Some(WdlValue::Array(values)) => {
+                let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                WdlValue::String(values.join(separator))
+            }
+            _ => return Err(invalid_arguments(name, &arguments)),
+        },
+        ("min" | "max", WdlValue::Int(_) | WdlValue::Float(_)) => {
+            let second = iter.next().unwrap();
+            let operator = if name == "min" {
+                BinaryOperator::Lte
+            } else {
+                BinaryOperator::Gte
+            };
+            match binary(&operator, first.clone(), second.clone())? {
+                WdlValue::Boolean(true) => first.clone(),
+                _ => second.clone(),
+            }
+        }
+        ("zip" | "cross", WdlValue::Array(left)) => match iter.next() {
+            Some(WdlValue::Array(right)) if name == "zip" => {
+                ensure!(
+                    left.len() == right.len(),
+                    invalid_arguments(name, &arguments)
+                );
+                check_length(name, left.len())?;
+                WdlValue::Array(
+                    left.iter()
+                        .zip(right.iter())
+                        .map(|(l, r)| WdlValue::Pair(Box::new(l.clone()), Box::new(r.clone())))
+                        .collect(),
+                )
+            }
+            Some(WdlValue::Array(right)) => {
+                check_length(name, left.len().saturating_mul(right.len()))?;
+                WdlValue::Array(
+                    left.iter()
+                        .flat_map(|l| {
+                            right
+                                .iter()
+                                .map(|r| WdlValue::Pair(Box::new(l.clone()), Box::new(r.clone())))
+                        })
+                        .collect(),
+                )
+            }
+            _ => return Err(invalid_arguments(name, &arguments)),
+        },
+        ("sub", WdlValue::String(input)) => match (iter.next(), iter.next()) {
+            (Some(WdlValue::String(pattern)), Some(WdlValue::String(replacement))) => {
+                let regex = Regex::new(pattern)
+                    .into_report()
+                    .change_context(EvalError::Regex(pattern.clone()))?;
+                WdlValue::String(regex.replace_all(input, replacement.as_str()).into_owned())
+            }
+            _ => return Err(invalid_arguments(name, &arguments)),
+        },
+        _ => return Err(invalid_arguments(name, &arguments)),
+    };
+    Ok(value)
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::{BoundDeclaration, DocumentElement, DocumentSource, TaskElement, WorkflowElement},
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    fn declarations(text: &str) -> Vec<(String, WdlValue)> {
+        let text = format!("version 1.1\ntask t {{\n{}\ncommand <<< >>>\n}}", text);
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let task = match doc.body_iter().next() {
+            Some(DocumentElement::Task(task)) => task,
+            _ => panic!("expected a task"),
+        };
+        let mut env = Environment::new();
+        let mut values = Vec::new();
+        for element in task.body.iter() {
+            if let TaskElement::Declaration(BoundDeclaration {
+                name, expression, ..
+            }) = element.deref()
+            {
+                let value = evaluate(expression, &env).unwrap();
+                env.bind(name.as_str(), value.clone());
+                values.push(((**name).clone(), value));
+            }
+        }
+        values
+    }
+
+    /// Evaluates the expression of the only declaration in `text`.
+    fn evaluate_declaration(text: &str) -> Result<WdlValue, EvalError> {
+        let text = format!("version 1.1\nworkflow w {{\n{}\n}}", text);
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let workflow = match doc.body_iter().next() {
+            Some(DocumentElement::Workflow(workflow)) => workflow,
+            _ => panic!("expected a workflow"),
+        };
+        match workflow.body.first().map(|element| element.deref()) {
+            Some(WorkflowElement::Declaration(decl)) => {
+                evaluate(&decl.expression, &Environment::new())
+            }
+            _ => panic!("expected a declaration"),
+        }
+    }
+
+    #[test]
+    fn test_overflow() {
+        for text in [
+            "Int i = 9223372036854775807 + 1",
+            "Int i = -9223372036854775807 - 2",
+            "Int i = 9223372036854775807 * 2",
+            "Int i = (-9223372036854775807 - 1) / -1",
+            "Int i = (-9223372036854775807 - 1) % -1",
+            "Int i = -(-9223372036854775807 - 1)",
+        ] {
+            let err = evaluate_declaration(text).unwrap_err();
+            assert!(
+                matches!(err.current_context(), EvalError::Overflow(_)),
+                "{}: {:?}",
+                text,
+                err
+            );
+        }
+        assert_eq!(
+            evaluate_declaration("Int i = -9223372036854775807 - 1").unwrap(),
+            WdlValue::Int(i64::MIN)
+        );
+        for text in [
+            "Int i = floor(1e19)",
+            "Int i = ceil(-1e19)",
+            "Int i = round(9223372036854775807.0)",
+        ] {
+            let err = evaluate_declaration(text).unwrap_err();
+            assert!(
+                matches!(err.current_context(), EvalError::Overflow(_)),
+                "{}: {:?}",
+                text,
+                err
+            );
+        }
+        assert_eq!(
+            evaluate_declaration("Int i = floor(9223372036854775807)").unwrap(),
+            WdlValue::Int(i64::MAX)
+        );
+        for text in [
+            "Array[Int] a = range(1000000000)",
+            "Array[Pair[Int, Int]] a = cross(range(1000000), range(1000000))",
+            "Array[Int] a = flatten([range(1000000), range(1)])",
+        ] {
+            let err = evaluate_declaration(text).unwrap_err();
+            assert!(
+                matches!(err.current_context(), EvalError::ArrayTooLarge { .. }),
+                "{}: {:?}",
+                text,
+                err
+            );
+        }
+        assert_eq!(
+            evaluate_declaration("Int n = length(range(1000000))").unwrap(),
+            WdlValue::Int(MAX_ARRAY_LENGTH as i64)
+        );
+        assert_eq!(
+            evaluate_declaration("Int n = length(zip(range(1000000), range(1000000)))").unwrap(),
+            WdlValue::Int(MAX_ARRAY_LENGTH as i64)
+        );
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let values = declarations(
+            r#"
+Int a = 1 + 2 * 3
+Float b = a / 2 + 0.5
+String c = "x~{a}y" + "\t"
+Array[Int] d = range(a)
+Boolean e = length(d) == 7 && !defined(None)
+Int f = select_first([None, d[2], 4])
+Pair[Int, String] g = (f, c)
+Int h = if e then g.left else -1
+Boolean i = 1 == 1.0 && !(2.0 != 2) && 1 != 1.5
+"#,
+        );
+        assert_eq!(
+            values,
+            vec![
+                ("a".to_owned(), WdlValue::Int(7)),
+                ("b".to_owned(), WdlValue::Float(3.5)),
+                ("c".to_owned(), WdlValue::String("x7y\t".to_owned())),
+                (
+                    "d".to_owned(),
+                    WdlValue::Array((0..7).map(WdlValue::Int).collect())
+                ),
+                ("e".to_owned(), WdlValue::Boolean(true)),
+                ("f".to_owned(), WdlValue::Int(2)),
+                (
+                    "g".to_owned(),
+                    WdlValue::Pair(
+                        Box::new(WdlValue::Int(2)),
+                        Box::new(WdlValue::String("x7y\t".to_owned()))
+                    )
+                ),
+                ("h".to_owned(), WdlValue::Int(2)),
+                ("i".to_owned(), WdlValue::Boolean(true)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! Machine-applicable fixes for problems in documents. Lints attach fixes to the `Diagnostic`s
+//! they return, and validation errors attach them to their `Report`s; either way, a fix is a set
+//! of text edits that can be applied to the source of the document with `apply_fixes`.
+use crate::{
+    edit::{self, EditError, TextEdit},
+    model::Span,
+};
+use error_stack::{Report, Result};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fix {
+    /// A short description of the fix, e.g. for display as a code action in an editor.
+    pub title: String,
+    /// The edits that make up the fix, which are applied together.
+    pub edits: Vec<TextEdit>,
+}
+
+impl Fix {
+    pub fn new<S: Into<String>>(title: S, edits: Vec<TextEdit>) -> Self {
+        Self {
+            title: title.into(),
+            edits,
+        }
+    }
+
+    /// Returns whether any edit of this fix overlaps any edit of `other`. Insertions at the same
+    /// position do not overlap.
+    pub fn conflicts_with(&self, other: &Fix) -> bool {
+        self.edits.iter().any(|a| {
+            other.edits.iter().any(|b| {
+                a.span.start.offset < b.span.end.offset && b.span.start.offset < a.span.end.offset
+            })
+        })
+    }
+}
+
+/// Applies `fixes` to `text`, which must be the source of the document to which they refer.
+/// Fixes are applied in order, and a fix that conflicts with an earlier fix is skipped, so the
+/// result may need to be checked and fixed again.
+pub fn apply_fixes<'a, I: IntoIterator<Item = &'a Fix>>(
+    text: &str,
+    fixes: I,
+) -> Result<String, EditError> {
+    let mut applied: Vec<&Fix> = Vec::new();
+    for fix in fixes {
+        if !applied.iter().any(|other| other.conflicts_with(fix)) {
+            applied.push(fix)
+        }
+    }
+    let edits: Vec<TextEdit> = applied
+        .into_iter()
+        .flat_map(|fix| fix.edits.iter().cloned())
+        .collect();
+    edit::apply_edits(text, &edits)
+}
+
+/// Returns the fixes that are attached to `report`, in the order in which they were attached.
+pub fn report_fixes<C>(report: &Report<C>) -> Vec<&Fix> {
+    let mut fixes: Vec<&Fix> = report
+        .frames()
+        .filter_map(|frame| frame.downcast_ref::<Fix>())
+        .collect();
+    fixes.reverse();
+    fixes
+}
+
+/// Returns a fix for a repeated section of kind `kind` in a task or workflow, where `span` is the
+/// span of the repeated section. A repeated `meta` section is likely intended to be the
+/// `parameter_meta` section, so it is renamed if there is no `parameter_meta` section;
+/// otherwise the repeated section is removed.
+pub(crate) fn repeated_section(kind: &str, span: &Span, has_parameter_meta: bool) -> Fix {
+    if kind == "meta" && !has_parameter_meta {
+        let mut keyword = span.clone();
+        keyword.end = keyword.start.clone();
+        keyword.end.column += kind.len();
+        keyword.end.offset += kind.len();
+        Fix::new(
+            "Rename section to parameter_meta",
+            vec![TextEdit::replace(keyword, "parameter_meta")],
+        )
+    } else {
+        Fix::new(
+            format!("Remove repeated {} section", kind),
+            vec![TextEdit::delete(span.clone())],
+        )
+    }
+}
+
+/// Returns a fix for `${...}` at `span` that replaces the `$` with `~`, e.g. to make it a
+/// placeholder within a heredoc command, or to replace a deprecated `$` placeholder.
+pub(crate) fn tilde_placeholder(span: &Span) -> Fix {
+    let mut sigil = span.clone();
+    sigil.end = sigil.start.clone();
+    sigil.end.column += 1;
+    sigil.end.offset += 1;
+    Fix::new("Replace ${ with ~{", vec![TextEdit::replace(sigil, "~")])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Position;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_apply_fixes() {
+        let span = |start: usize, end: usize| Span::from_components(0, start, start, 0, end, end);
+        let text = "Int x = 1";
+        let fixes = vec![
+            Fix::new("rename", vec![TextEdit::replace(span(4, 5), "y")]),
+            Fix::new(
+                "conflicting",
+                vec![TextEdit::replace(span(0, 5), "Float z")],
+            ),
+            Fix::new(
+                "comment",
+                vec![
+                    TextEdit::insert(Position::new(0, 0, 0), "# "),
+                    TextEdit::insert(Position::new(0, 9, 9), " # one"),
+                ],
+            ),
+        ];
+        assert_eq!(apply_fixes(text, &fixes).unwrap(), "# Int y = 1 # one");
+    }
+
+    #[cfg(feature = "pest")]
+    #[test]
+    fn test_validation_fixes() {
+        use crate::{
+            model::DocumentSource,
+            parsers::{PestParser, WdlParser},
+        };
+
+        let text = r#"version 1.1
+
+task t {
+    meta {
+        author: "me"
+    }
+    meta {
+        x: "an input"
+    }
+    command <<< >>>
+}
+
+workflow a {}
+
+workflow b {}
+"#;
+        // fixes are attached to validation errors, which are reported by the parsers
+        let mut parser = PestParser::new();
+        let report = parser
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap_err();
+        let fixes = report_fixes(&report);
+        assert_eq!(fixes.len(), 1);
+        let fixed = apply_fixes(text, fixes).unwrap();
+        assert!(fixed.contains("parameter_meta {\n        x: \"an input\""));
+        let report = parser
+            .parse_text(&fixed, DocumentSource::Unknown)
+            .unwrap_err();
+        let fixed = apply_fixes(&fixed, report_fixes(&report)).unwrap();
+        assert!(fixed.contains("workflow a {}") && !fixed.contains("workflow b"));
+        assert!(parser.parse_text(&fixed, DocumentSource::Unknown).is_ok());
+    }
+}
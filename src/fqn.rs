@@ -0,0 +1,345 @@
+//! Fully-qualified names (FQNs) for the named elements of a document, using the same dotted
+//! naming scheme as Cromwell's workflow object model (WOM), e.g.
+//! `wf.scatter_0.call_x.output_y`. Scatter and conditional blocks do not have names in WDL, so
+//! they are assigned names of the form `scatter_<n>` and `conditional_<n>`, where `n` is the
+//! zero-based index of the block among all blocks of the same kind in the workflow (in source
+//! order).
+use crate::model::{
+    Anchor, Call, Conditional, Document, DocumentElement, Scatter, Span, Task, TaskElement,
+    Workflow, WorkflowElement, WorkflowNestedElement,
+};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// The kind of element to which a fully-qualified name refers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FqnKind {
+    /// A workflow, which is the root of the names of its elements.
+    Workflow,
+    /// A task, which is the root of the names of its elements.
+    Task,
+    Input,
+    Declaration,
+    Output,
+    Call,
+    CallOutput,
+    Scatter,
+    ScatterVariable,
+    Conditional,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FullyQualifiedName {
+    pub kind: FqnKind,
+    pub parts: Vec<String>,
+}
+
+impl FullyQualifiedName {
+    pub(crate) fn workflow<S: Into<String>>(name: S) -> Self {
+        Self {
+            kind: FqnKind::Workflow,
+            parts: vec![name.into()],
+        }
+    }
+
+    pub(crate) fn task<S: Into<String>>(name: S) -> Self {
+        Self {
+            kind: FqnKind::Task,
+            parts: vec![name.into()],
+        }
+    }
+
+    pub(crate) fn child<S: Into<String>>(&self, kind: FqnKind, name: S) -> Self {
+        let mut parts = self.parts.clone();
+        parts.push(name.into());
+        Self { kind, parts }
+    }
+
+    /// Returns the last (unqualified) part of the name.
+    pub fn local_name(&self) -> &str {
+        self.parts.last().map(|s| s.as_str()).unwrap_or("")
+    }
+}
+
+impl Display for FullyQualifiedName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.parts.join("."))
+    }
+}
+
+/// Returns the fully-qualified names of `document`'s workflow and tasks and of all their named
+/// elements, in source order.
+pub fn document_names(document: &Document) -> Vec<Anchor<FullyQualifiedName>> {
+    let mut names = Vec::new();
+    for element in document.body_iter() {
+        match element {
+            DocumentElement::Workflow(workflow) => names.extend(workflow_names(document, workflow)),
+            DocumentElement::Task(task) => names.extend(task_names(task)),
+            _ => (),
+        }
+    }
+    names
+}
+
+/// Returns the fully-qualified names of `task` (the root of the other names, at the span of its
+/// name) and of its inputs, declarations and outputs.
+pub fn task_names(task: &Task) -> Vec<Anchor<FullyQualifiedName>> {
+    let root = FullyQualifiedName::task(task.name.as_str());
+    let mut names = vec![Anchor::new(root.clone(), task.name.span.clone())];
+    for element in task.body.iter() {
+        match &**element {
+            TaskElement::Input(input) => {
+                for decl in input.declarations.iter() {
+                    names.push(Anchor::new(
+                        root.child(FqnKind::Input, decl.name().as_str()),
+                        decl.span.clone(),
+                    ));
+                }
+            }
+            TaskElement::Declaration(decl) => names.push(Anchor::new(
+                root.child(FqnKind::Declaration, decl.name.as_str()),
+                element.span.clone(),
+            )),
+            TaskElement::Output(output) => {
+                for decl in output.declarations.iter() {
+                    names.push(Anchor::new(
+                        root.child(FqnKind::Output, decl.name.as_str()),
+                        decl.span.clone(),
+                    ));
+                }
+            }
+            _ => (),
+        }
+    }
+    names
+}
+
+/// Returns the fully-qualified names of `workflow` (the root of the other names, at the span of its
+/// name) and of all its elements. The outputs of calls are included when the called task is
+/// defined in `document`.
+pub fn workflow_names(document: &Document, workflow: &Workflow) -> Vec<Anchor<FullyQualifiedName>> {
+    let mut namer = Namer {
+        document,
+        scatters: 0,
+        conditionals: 0,
+        names: Vec::new(),
+    };
+    let root = FullyQualifiedName::workflow(workflow.name.as_str());
+    namer.add(root.clone(), &workflow.name.span);
+    for element in workflow.body.iter() {
+        let span = &element.span;
+        match &**element {
+            WorkflowElement::Input(input) => {
+                for decl in input.declarations.iter() {
+                    namer.add(root.child(FqnKind::Input, decl.name().as_str()), &decl.span);
+                }
+            }
+            WorkflowElement::Declaration(decl) => {
+                namer.add(root.child(FqnKind::Declaration, decl.name.as_str()), span)
+            }
+            WorkflowElement::Call(call) => namer.add_call(&root, call, span),
+            WorkflowElement::Scatter(scatter) => namer.add_scatter(&root, scatter, span),
+            WorkflowElement::Conditional(conditional) => {
+                namer.add_conditional(&root, conditional, span)
+            }
+            WorkflowElement::Output(output) => {
+                for decl in output.declarations.iter() {
+                    namer.add(root.child(FqnKind::Output, decl.name.as_str()), &decl.span);
+                }
+            }
+            _ => (),
+        }
+    }
+    namer.names
+}
+
+/// Returns the name by which a call is referenced in its workflow - its alias if it has one,
+/// otherwise the last part of its target.
+pub fn call_name(call: &Call) -> &str {
+    match &call.alias {
+        Some(alias) => alias.as_str(),
+        None => call
+            .target
+            .parts
+            .last()
+            .map(|part| part.as_str())
+            .unwrap_or(""),
+    }
+}
+
+pub(crate) fn scatter_name(index: usize) -> String {
+    format!("scatter_{}", index)
+}
+
+pub(crate) fn conditional_name(index: usize) -> String {
+    format!("conditional_{}", index)
+}
+
+/// Returns the task in `document` that is the target of `call`, or `None` if the call target is
+/// namespaced (i.e. refers to a task in an imported document) or there is no such task.
+pub fn find_local_task<'a>(document: &'a Document, call: &Call) -> Option<&'a Task> {
+    if call.target.parts.len() != 1 {
+        return None;
+    }
+    let target = call.target.parts[0].as_str();
+    document.body_iter().find_map(|element| match element {
+        DocumentElement::Task(task) if task.name.as_str() == target => Some(task),
+        _ => None,
+    })
+}
+
+struct Namer<'a> {
+    document: &'a Document,
+    scatters: usize,
+    conditionals: usize,
+    names: Vec<Anchor<FullyQualifiedName>>,
+}
+
+impl<'a> Namer<'a> {
+    fn add(&mut self, name: FullyQualifiedName, span: &Span) {
+        self.names.push(Anchor::new(name, span.clone()))
+    }
+
+    fn add_call(&mut self, parent: &FullyQualifiedName, call: &Call, span: &Span) {
+        let call_fqn = parent.child(FqnKind::Call, call_name(call));
+        if let Some(task) = find_local_task(self.document, call) {
+            let outputs: Vec<FullyQualifiedName> = task
+                .body
+                .iter()
+                .filter_map(|element| match &**element {
+                    TaskElement::Output(output) => Some(output),
+                    _ => None,
+                })
+                .flat_map(|output| output.declarations.iter())
+                .map(|decl| call_fqn.child(FqnKind::CallOutput, decl.name.as_str()))
+                .collect();
+            self.add(call_fqn, span);
+            for output in outputs {
+                self.add(output, span);
+            }
+        } else {
+            self.add(call_fqn, span);
+        }
+    }
+
+    fn add_scatter(&mut self, parent: &FullyQualifiedName, scatter: &Scatter, span: &Span) {
+        let scatter_fqn = parent.child(FqnKind::Scatter, scatter_name(self.scatters));
+        self.scatters += 1;
+        self.add(scatter_fqn.clone(), span);
+        self.add(
+            scatter_fqn.child(FqnKind::ScatterVariable, scatter.name.as_str()),
+            &scatter.name.span,
+        );
+        self.add_nested(&scatter_fqn, &scatter.body);
+    }
+
+    fn add_conditional(
+        &mut self,
+        parent: &FullyQualifiedName,
+        conditional: &Conditional,
+        span: &Span,
+    ) {
+        let conditional_fqn =
+            parent.child(FqnKind::Conditional, conditional_name(self.conditionals));
+        self.conditionals += 1;
+        self.add(conditional_fqn.clone(), span);
+        self.add_nested(&conditional_fqn, &conditional.body);
+    }
+
+    fn add_nested(&mut self, parent: &FullyQualifiedName, body: &[Anchor<WorkflowNestedElement>]) {
+        for element in body.iter() {
+            let span = &element.span;
+            match &**element {
+                WorkflowNestedElement::Declaration(decl) => {
+                    self.add(parent.child(FqnKind::Declaration, decl.name.as_str()), span)
+                }
+                WorkflowNestedElement::Call(call) => self.add_call(parent, call, span),
+                WorkflowNestedElement::Scatter(scatter) => self.add_scatter(parent, scatter, span),
+                WorkflowNestedElement::Conditional(conditional) => {
+                    self.add_conditional(parent, conditional, span)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_workflow_names() {
+        let text = r#"
+version 1.1
+
+task t {
+    command <<< >>>
+    output {
+        Int y = 1
+    }
+}
+
+workflow wf {
+    input {
+        Int n
+    }
+    scatter (i in range(n)) {
+        if (i > 0) {
+            call t as x
+        }
+    }
+    output {
+        Array[Int?] ys = x.y
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let names: Vec<(FqnKind, String)> = document_names(&doc)
+            .iter()
+            .map(|name| (name.kind, (**name).to_string()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                (FqnKind::Task, "t".to_owned()),
+                (FqnKind::Output, "t.y".to_owned()),
+                (FqnKind::Workflow, "wf".to_owned()),
+                (FqnKind::Input, "wf.n".to_owned()),
+                (FqnKind::Scatter, "wf.scatter_0".to_owned()),
+                (FqnKind::ScatterVariable, "wf.scatter_0.i".to_owned()),
+                (
+                    FqnKind::Conditional,
+                    "wf.scatter_0.conditional_0".to_owned()
+                ),
+                (FqnKind::Call, "wf.scatter_0.conditional_0.x".to_owned()),
+                (
+                    FqnKind::CallOutput,
+                    "wf.scatter_0.conditional_0.x.y".to_owned()
+                ),
+                (FqnKind::Output, "wf.ys".to_owned()),
+            ]
+        );
+
+        // the roots of the names are the workflow and task themselves, not calls
+        let names = document_names(&doc);
+        let roots: Vec<(FqnKind, &str, usize)> = names
+            .iter()
+            .filter(|name| name.parts.len() == 1)
+            .map(|name| (name.kind, name.local_name(), name.span.start.line))
+            .collect();
+        assert_eq!(
+            roots,
+            vec![(FqnKind::Task, "t", 3), (FqnKind::Workflow, "wf", 10)]
+        );
+        assert!(names
+            .iter()
+            .filter(|name| name.parts.len() > 1)
+            .all(|name| !matches!(name.kind, FqnKind::Workflow | FqnKind::Task)));
+    }
+}
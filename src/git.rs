@@ -0,0 +1,245 @@
+//! Loading of the WDL documents in a git repository at a given revision (e.g. a commit, branch,
+//! or tag) into a `Workspace`, e.g. to compare the workflows in two commits. Requires the `git`
+//! executable at runtime.
+//!
+//! Documents are given `File` sources at their paths in the working tree of the repository, so
+//! that relative imports resolve as they do for the checked-out files, but their text is read
+//! from the revision rather than from the working tree.
+use crate::{
+    model::{DocumentSource, SourceDigest},
+    parsers::WdlParser,
+    workspace::Workspace,
+};
+use error_stack::{bail, IntoReport, Result, ResultExt};
+use std::{
+    path::{Path, PathBuf},
+    process::Command as Process,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum GitError {
+    #[error("Failed to run git")]
+    Run,
+    #[error("git failed: {0}")]
+    Failed(String),
+    #[error("Invalid git output")]
+    Output,
+    #[error("error parsing document {0}")]
+    Parse(PathBuf),
+}
+
+/// A git repository.
+#[derive(Clone, Debug)]
+pub struct GitRepository {
+    program: PathBuf,
+    root: PathBuf,
+}
+
+impl GitRepository {
+    /// Opens the repository that contains `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, GitError> {
+        Self::open_with_program("git", path)
+    }
+
+    /// Opens the repository that contains `path` using the `git` executable at `program`.
+    pub fn open_with_program<G: Into<PathBuf>, P: AsRef<Path>>(
+        program: G,
+        path: P,
+    ) -> Result<Self, GitError> {
+        let mut repository = Self {
+            program: program.into(),
+            root: path.as_ref().to_owned(),
+        };
+        let root = repository.git(&["rev-parse", "--show-toplevel"])?;
+        repository.root = PathBuf::from(root.trim_end_matches('\n'));
+        Ok(repository)
+    }
+
+    /// Returns the root of the working tree of the repository.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Runs git in the repository with `args` and returns its standard output.
+    fn git(&self, args: &[&str]) -> Result<String, GitError> {
+        let output = Process::new(&self.program)
+            .arg("-C")
+            .arg(&self.root)
+            .args(args)
+            .output()
+            .into_report()
+            .change_context(GitError::Run)?;
+        if !output.status.success() {
+            bail!(GitError::Failed(
+                String::from_utf8_lossy(&output.stderr).trim().to_owned()
+            ))
+        }
+        String::from_utf8(output.stdout)
+            .into_report()
+            .change_context(GitError::Output)
+    }
+
+    /// Returns the ID of the commit that `revision` refers to.
+    pub fn commit_id(&self, revision: &str) -> Result<String, GitError> {
+        let commit = format!("{}^{{commit}}", revision);
+        Ok(self
+            .git(&["rev-parse", "--verify", "--quiet", &commit])?
+            .trim_end()
+            .to_owned())
+    }
+
+    /// Returns the paths (relative to the root of the repository) of the `.wdl` files in
+    /// `revision`, sorted.
+    pub fn wdl_files(&self, revision: &str) -> Result<Vec<PathBuf>, GitError> {
+        let output = self.git(&["ls-tree", "-r", "-z", "--name-only", revision, "--"])?;
+        let mut paths: Vec<PathBuf> = output
+            .split('\0')
+            .filter(|path| path.ends_with(".wdl"))
+            .map(PathBuf::from)
+            .collect();
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// Returns the text of the file at `path` (relative to the root of the repository) in
+    /// `revision`.
+    pub fn read<P: AsRef<Path>>(&self, revision: &str, path: P) -> Result<String, GitError> {
+        let object = format!("{}:{}", revision, path.as_ref().to_string_lossy());
+        self.git(&["show", &object])
+    }
+
+    /// Parses the `.wdl` files in `revision` with `parser` and returns a workspace that contains
+    /// them.
+    pub fn load<P: WdlParser>(
+        &self,
+        parser: &mut P,
+        revision: &str,
+    ) -> Result<Workspace, GitError> {
+        let mut workspace = Workspace::new();
+        for path in self.wdl_files(revision)? {
+            let text = self.read(revision, &path)?;
+            let path = self.root.join(path);
+            let document = parser
+                .parse_text(&text, DocumentSource::File(path.clone()))
+                .change_context(GitError::Parse(path))?;
+            workspace.insert(document);
+        }
+        Ok(workspace)
+    }
+}
+
+/// A difference between the documents of two workspaces.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DocumentChange {
+    Added(DocumentSource),
+    Removed(DocumentSource),
+    /// The text of the document differs, as determined by the digests of the documents.
+    Modified(DocumentSource),
+}
+
+impl DocumentChange {
+    pub fn source(&self) -> &DocumentSource {
+        match self {
+            Self::Added(source) | Self::Removed(source) | Self::Modified(source) => source,
+        }
+    }
+}
+
+/// Returns the documents that were added, removed, or modified between `before` and `after`
+/// (e.g. the workspaces loaded from two commits), sorted by source.
+pub fn changed_documents(before: &Workspace, after: &Workspace) -> Vec<DocumentChange> {
+    let digest = |workspace: &Workspace, source| -> Option<SourceDigest> {
+        workspace
+            .get(source)
+            .map(|document| *document.source_digest())
+    };
+    let mut changes: Vec<DocumentChange> = before
+        .documents()
+        .filter_map(|document| match digest(after, &document.source) {
+            None => Some(DocumentChange::Removed(document.source.clone())),
+            Some(digest) if digest != *document.source_digest() => {
+                Some(DocumentChange::Modified(document.source.clone()))
+            }
+            Some(_) => None,
+        })
+        .chain(
+            after
+                .documents()
+                .filter(|document| !before.contains(&document.source))
+                .map(|document| DocumentChange::Added(document.source.clone())),
+        )
+        .collect();
+    changes.sort_by(|a, b| a.source().cmp(b.source()));
+    changes
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::parsers::PestParser;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    #[test]
+    fn test_load_revisions() {
+        let dir = std::env::temp_dir().join(format!("wdl-git-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        let git = |args: &[&str]| {
+            let status = Process::new("git")
+                .arg("-C")
+                .arg(&dir)
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?} failed", args);
+        };
+        let task = "version 1.1\n\ntask t {\n    command <<< >>>\n}\n";
+        git(&["init", "-q"]);
+        fs::write(
+            dir.join("main.wdl"),
+            "version 1.1\n\nimport \"lib/t.wdl\"\n",
+        )
+        .unwrap();
+        fs::write(dir.join("lib/t.wdl"), task).unwrap();
+        fs::write(dir.join("README.md"), "not wdl").unwrap();
+        git(&["add", "-A"]);
+        git(&["commit", "-q", "-m", "first"]);
+        fs::write(dir.join("lib/t.wdl"), task.replace("task t", "task u")).unwrap();
+        fs::write(dir.join("other.wdl"), task).unwrap();
+        git(&["add", "-A"]);
+        git(&["commit", "-q", "-m", "second"]);
+        // uncommitted changes are not loaded
+        fs::write(dir.join("main.wdl"), "not wdl").unwrap();
+
+        let repository = GitRepository::open(dir.join("lib")).unwrap();
+        let root = repository.root().to_owned();
+        assert_eq!(
+            repository.wdl_files("HEAD~1").unwrap(),
+            vec![PathBuf::from("lib/t.wdl"), PathBuf::from("main.wdl")]
+        );
+        assert_eq!(repository.commit_id("HEAD").unwrap().len(), 40);
+        let mut parser = PestParser::new();
+        let before = repository.load(&mut parser, "HEAD~1").unwrap();
+        let after = repository.load(&mut parser, "HEAD").unwrap();
+        let main = before
+            .get(&DocumentSource::File(root.join("main.wdl")))
+            .unwrap();
+        assert_eq!(before.documents().count(), 2);
+        assert_eq!(
+            changed_documents(&before, &after),
+            vec![
+                DocumentChange::Modified(DocumentSource::File(root.join("lib/t.wdl"))),
+                DocumentChange::Added(DocumentSource::File(root.join("other.wdl"))),
+            ]
+        );
+        assert!(main.body_iter().any(
+            |element| matches!(element, crate::model::DocumentElement::Import(import)
+                if before.resolve_import(main, import).is_some())
+        ));
+        assert!(repository.load(&mut parser, "no-such-revision").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
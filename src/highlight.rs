@@ -0,0 +1,367 @@
+//! Semantic highlighting of documents. Whereas `tokens::tokenize` classifies tokens by their
+//! syntax, `semantic_tokens` uses the document model and scope analysis to classify names by what
+//! they refer to, e.g. to distinguish the names of tasks from the names of variables, and inputs
+//! from other declarations.
+use crate::{
+    analysis::{resolve_scopes, walk_expression, SymbolKind},
+    model::{
+        AccessOperation, Anchor, BoundDeclaration, Call, Document, DocumentElement, Expression,
+        InputDeclaration, Namespace, Span, StringPart, TaskElement, Type, WorkflowElement,
+        WorkflowNestedElement,
+    },
+};
+use std::ops::Deref;
+
+/// Semantic token types, named as in the Language Server Protocol.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SemanticTokenType {
+    /// An import namespace.
+    Namespace,
+    /// A struct.
+    Type,
+    /// A task or workflow input.
+    Parameter,
+    /// A declaration, output, call, or scatter variable.
+    Variable,
+    /// A struct member, object field, or runtime attribute.
+    Property,
+    /// A task, workflow, or standard library function.
+    Function,
+}
+
+impl SemanticTokenType {
+    /// All token types, in the order in which they should be registered in an LSP semantic tokens
+    /// legend.
+    pub const ALL: [Self; 6] = [
+        Self::Namespace,
+        Self::Type,
+        Self::Parameter,
+        Self::Variable,
+        Self::Property,
+        Self::Function,
+    ];
+
+    /// Returns the name of this token type in the Language Server Protocol.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Namespace => "namespace",
+            Self::Type => "type",
+            Self::Parameter => "parameter",
+            Self::Variable => "variable",
+            Self::Property => "property",
+            Self::Function => "function",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SemanticToken {
+    pub span: Span,
+    pub token_type: SemanticTokenType,
+    /// Whether this token is the definition of a name, rather than a reference to it.
+    pub definition: bool,
+}
+
+/// Returns the semantic tokens of `document`, ordered by position.
+pub fn semantic_tokens(document: &Document) -> Vec<SemanticToken> {
+    let mut highlighter = Highlighter { tokens: Vec::new() };
+    let scopes = resolve_scopes(document);
+    for symbol in scopes.symbols.iter() {
+        // calls are highlighted along with their targets
+        if symbol.kind != SymbolKind::Call {
+            highlighter.add(&symbol.span, symbol_type(symbol.kind), true);
+        }
+    }
+    for reference in scopes.references.iter() {
+        let token_type = reference
+            .symbol
+            .as_ref()
+            .map(|symbol| symbol_type(symbol.kind))
+            .unwrap_or(SemanticTokenType::Variable);
+        highlighter.add(&reference.span, token_type, false);
+    }
+    for element in document.body_iter() {
+        highlighter.document_element(element);
+    }
+    let mut tokens = highlighter.tokens;
+    tokens.sort_by(|a, b| a.span.cmp(&b.span));
+    tokens
+}
+
+fn symbol_type(kind: SymbolKind) -> SemanticTokenType {
+    match kind {
+        SymbolKind::Input => SemanticTokenType::Parameter,
+        _ => SemanticTokenType::Variable,
+    }
+}
+
+struct Highlighter {
+    tokens: Vec<SemanticToken>,
+}
+
+impl Highlighter {
+    fn add(&mut self, span: &Span, token_type: SemanticTokenType, definition: bool) {
+        self.tokens.push(SemanticToken {
+            span: span.clone(),
+            token_type,
+            definition,
+        })
+    }
+
+    fn document_element(&mut self, element: &DocumentElement) {
+        match element {
+            DocumentElement::Import(import) => {
+                if let Namespace::Explicit(namespace) = &import.namespace {
+                    self.add(&namespace.span, SemanticTokenType::Namespace, true)
+                }
+                for alias in import.aliases.iter() {
+                    self.add(&alias.from.span, SemanticTokenType::Type, false);
+                    self.add(&alias.to.span, SemanticTokenType::Type, true);
+                }
+            }
+            DocumentElement::Struct(struct_) => {
+                self.add(&struct_.name.span, SemanticTokenType::Type, true);
+                for field in struct_.fields.iter() {
+                    self.type_(&field.type_);
+                    self.add(&field.name.span, SemanticTokenType::Property, true);
+                }
+            }
+            DocumentElement::Task(task) => {
+                self.add(&task.name.span, SemanticTokenType::Function, true);
+                for element in task.body.iter() {
+                    match element.deref() {
+                        TaskElement::Input(input) => {
+                            for decl in input.declarations.iter() {
+                                self.input_declaration(decl)
+                            }
+                        }
+                        TaskElement::Declaration(decl) => self.bound_declaration(decl),
+                        TaskElement::Output(output) => {
+                            for decl in output.declarations.iter() {
+                                self.bound_declaration(decl)
+                            }
+                        }
+                        TaskElement::Command(command) => {
+                            for part in command.parts.iter() {
+                                if let StringPart::Placeholder(expression, _) = part.deref() {
+                                    self.unanchored_expression(expression, &part.span)
+                                }
+                            }
+                        }
+                        TaskElement::Runtime(runtime) => {
+                            for attribute in runtime.attributes.iter() {
+                                self.add(&attribute.name.span, SemanticTokenType::Property, false);
+                                self.expression(&attribute.expression);
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            DocumentElement::Workflow(workflow) => {
+                self.add(&workflow.name.span, SemanticTokenType::Function, true);
+                for element in workflow.body.iter() {
+                    match element.deref() {
+                        WorkflowElement::Input(input) => {
+                            for decl in input.declarations.iter() {
+                                self.input_declaration(decl)
+                            }
+                        }
+                        WorkflowElement::Output(output) => {
+                            for decl in output.declarations.iter() {
+                                self.bound_declaration(decl)
+                            }
+                        }
+                        WorkflowElement::Declaration(decl) => self.bound_declaration(decl),
+                        WorkflowElement::Call(call) => self.call(call),
+                        WorkflowElement::Scatter(scatter) => {
+                            self.expression(&scatter.expression);
+                            self.nested(&scatter.body);
+                        }
+                        WorkflowElement::Conditional(conditional) => {
+                            self.expression(&conditional.expression);
+                            self.nested(&conditional.body);
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+    }
+
+    fn nested(&mut self, body: &[Anchor<WorkflowNestedElement>]) {
+        for element in body.iter() {
+            match element.deref() {
+                WorkflowNestedElement::Declaration(decl) => self.bound_declaration(decl),
+                WorkflowNestedElement::Call(call) => self.call(call),
+                WorkflowNestedElement::Scatter(scatter) => {
+                    self.expression(&scatter.expression);
+                    self.nested(&scatter.body);
+                }
+                WorkflowNestedElement::Conditional(conditional) => {
+                    self.expression(&conditional.expression);
+                    self.nested(&conditional.body);
+                }
+            }
+        }
+    }
+
+    fn call(&mut self, call: &Call) {
+        if let Some((name, namespaces)) = call.target.parts.split_last() {
+            for namespace in namespaces {
+                self.add(&namespace.span, SemanticTokenType::Namespace, false);
+            }
+            self.add(&name.span, SemanticTokenType::Function, false);
+        }
+        if let Some(alias) = &call.alias {
+            self.add(&alias.span, SemanticTokenType::Variable, true);
+        }
+        for input in call.inputs.iter().flatten() {
+            // if there is no expression, the name is both the input and a reference, which is
+            // highlighted by the scope analysis
+            if let Some(expression) = &input.expression {
+                self.add(&input.name.span, SemanticTokenType::Parameter, false);
+                self.expression(expression);
+            }
+        }
+    }
+
+    fn type_(&mut self, type_: &Anchor<Type>) {
+        match type_.deref() {
+            Type::User(_) => self.add(&type_.span, SemanticTokenType::Type, false),
+            Type::Array { item, .. } => self.type_(item),
+            Type::Map { key, value } => {
+                self.type_(key);
+                self.type_(value);
+            }
+            Type::Pair { left, right } => {
+                self.type_(left);
+                self.type_(right);
+            }
+            Type::Optional(inner) => self.type_(inner),
+            _ => (),
+        }
+    }
+
+    /// Highlights the type and expression of `decl`. Names of declarations are highlighted by the
+    /// scope analysis.
+    fn bound_declaration(&mut self, decl: &BoundDeclaration) {
+        self.type_(&decl.type_);
+        self.expression(&decl.expression);
+    }
+
+    fn input_declaration(&mut self, decl: &InputDeclaration) {
+        match decl {
+            InputDeclaration::Bound(decl) => self.bound_declaration(decl),
+            InputDeclaration::Unbound(decl) => self.type_(&decl.type_),
+        }
+    }
+
+    /// Highlights function names, member accesses, and object literals. Identifiers are
+    /// highlighted by the scope analysis.
+    fn expression(&mut self, expression: &Anchor<Expression>) {
+        self.unanchored_expression(expression.deref(), &expression.span)
+    }
+
+    fn unanchored_expression(&mut self, expression: &Expression, span: &Span) {
+        let mut tokens = Vec::new();
+        walk_expression(expression, span, &mut |e, _| match e {
+            Expression::Apply(apply) => {
+                tokens.push((apply.name.span.clone(), SemanticTokenType::Function))
+            }
+            Expression::Access(access) => {
+                for operation in access.accesses.iter() {
+                    if let AccessOperation::Field(_) = operation.deref() {
+                        tokens.push((operation.span.clone(), SemanticTokenType::Property))
+                    }
+                }
+            }
+            Expression::Object(object) => {
+                if object.type_name.as_str() != "object" {
+                    tokens.push((object.type_name.span.clone(), SemanticTokenType::Type))
+                }
+                for field in object.fields.iter() {
+                    tokens.push((field.name.span.clone(), SemanticTokenType::Property))
+                }
+            }
+            _ => (),
+        });
+        for (span, token_type) in tokens {
+            self.add(&span, token_type, false)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_semantic_tokens() {
+        let text = r#"
+version 1.1
+
+struct Sample {
+    String name
+}
+
+task t {
+    input {
+        Sample s
+    }
+    command <<<
+        echo ~{basename(s.name)}
+    >>>
+    runtime {
+        docker: "ubuntu"
+    }
+}
+
+workflow wf {
+    input {
+        Sample sample
+    }
+    call t as run { input: s = sample }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let tokens: Vec<_> = semantic_tokens(&doc)
+            .into_iter()
+            .map(|token| {
+                (
+                    &text[token.span.start.offset..token.span.end.offset],
+                    token.token_type.as_str(),
+                    token.definition,
+                )
+            })
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                ("Sample", "type", true),
+                ("name", "property", true),
+                ("t", "function", true),
+                ("Sample", "type", false),
+                ("s", "parameter", true),
+                ("basename", "function", false),
+                ("s", "parameter", false),
+                ("name", "property", false),
+                ("docker", "property", false),
+                ("wf", "function", true),
+                ("Sample", "type", false),
+                ("sample", "parameter", true),
+                ("t", "function", false),
+                ("run", "variable", true),
+                ("s", "parameter", false),
+                ("sample", "parameter", false),
+            ]
+        );
+    }
+}
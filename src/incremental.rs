@@ -0,0 +1,343 @@
+//! An incremental computation layer for editors and language servers. A `Database` holds the
+//! text of a set of documents and memoizes the results of queries (e.g. parsing, scope
+//! resolution, and diagnostics) on them. Each result records the revisions of the documents that
+//! it was derived from, and is recomputed only when one of them has changed, so that an edit to
+//! one document does not require re-analyzing the others.
+//!
+//! Queries may use the results of other queries. The dependencies of a query are the union of
+//! the documents whose text it reads directly and the dependencies of the queries it uses.
+//!
+//! A `Database` is used from a single thread, but the results of its queries are reference
+//! counted with `Arc`s, so that they (e.g. parsed documents) can be shared with other threads.
+use crate::{
+    analysis::{resolve_scopes, Scopes},
+    diagnostics::Diagnostic,
+    lint::Linter,
+    model::{Document, DocumentSource, ValidationOptions},
+    parsers::{WdlParser, WdlParserError},
+    trace,
+};
+use error_stack::Report;
+use std::{
+    any::{Any, TypeId},
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    sync::Arc,
+};
+
+/// A revision of the documents of a `Database`. The revision is incremented each time a document
+/// is changed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Revision(u64);
+
+/// A computation on a single document whose result is memoized by a `Database`.
+pub trait Query: 'static {
+    type Output: Send + Sync + 'static;
+
+    /// Computes the result of this query for the document with the given source. Documents must
+    /// be accessed only through `db`, so that they are recorded as dependencies.
+    fn compute<P: WdlParser>(db: &Database<P>, source: &DocumentSource) -> Self::Output;
+}
+
+/// The revisions of the documents on which a memoized result depends. A revision of `None` means
+/// that the document did not exist.
+type Dependencies = Vec<(DocumentSource, Option<Revision>)>;
+
+struct Memo {
+    value: Arc<dyn Any + Send + Sync>,
+    dependencies: Dependencies,
+}
+
+pub struct Database<P: WdlParser> {
+    parser: RefCell<P>,
+    linter: Linter,
+    validation_options: ValidationOptions,
+    revision: Revision,
+    /// The text of each document and the revision in which it last changed.
+    texts: HashMap<DocumentSource, (Arc<str>, Revision)>,
+    memos: RefCell<HashMap<(TypeId, DocumentSource), Memo>>,
+    /// The dependencies recorded by each active query, innermost last.
+    active: RefCell<Vec<((TypeId, DocumentSource), Dependencies)>>,
+    computations: Cell<usize>,
+}
+
+impl<P: WdlParser> Database<P> {
+    /// Creates an empty database. Diagnostics are computed with the default `Linter` and with
+    /// permissive validation options.
+    pub fn new(parser: P) -> Self {
+        Self {
+            parser: RefCell::new(parser),
+            linter: Linter::default(),
+            validation_options: ValidationOptions::permissive(),
+            revision: Revision::default(),
+            texts: HashMap::new(),
+            memos: RefCell::new(HashMap::new()),
+            active: RefCell::new(Vec::new()),
+            computations: Cell::new(0),
+        }
+    }
+
+    pub fn with_linter(mut self, linter: Linter) -> Self {
+        self.linter = linter;
+        self.memos.get_mut().clear();
+        self
+    }
+
+    pub fn with_validation_options(mut self, options: ValidationOptions) -> Self {
+        self.validation_options = options;
+        self.memos.get_mut().clear();
+        self
+    }
+
+    pub fn linter(&self) -> &Linter {
+        &self.linter
+    }
+
+    pub fn validation_options(&self) -> &ValidationOptions {
+        &self.validation_options
+    }
+
+    /// Returns the current revision.
+    pub fn revision(&self) -> Revision {
+        self.revision
+    }
+
+    /// Returns the number of times that a query has been computed (rather than returned from
+    /// the cache) since this database was created.
+    pub fn computations(&self) -> usize {
+        self.computations.get()
+    }
+
+    /// Sets the text of the document with the given source, adding the document if it does not
+    /// exist. Returns the new revision. Setting the text of a document to its current text does
+    /// not invalidate any results.
+    pub fn set_text<S: Into<String>>(&mut self, source: DocumentSource, text: S) -> Revision {
+        let text = text.into();
+        if let Some((current, _)) = self.texts.get(&source) {
+            if current.as_ref() == text {
+                return self.revision;
+            }
+        }
+        self.revision.0 += 1;
+        self.texts.insert(source, (Arc::from(text), self.revision));
+        self.revision
+    }
+
+    /// Removes the document with the given source. Returns the new revision.
+    pub fn remove(&mut self, source: &DocumentSource) -> Revision {
+        if self.texts.remove(source).is_some() {
+            self.revision.0 += 1;
+        }
+        self.revision
+    }
+
+    /// Returns the text of the document with the given source, if it exists, and records it as a
+    /// dependency of the active query.
+    pub fn text(&self, source: &DocumentSource) -> Option<Arc<str>> {
+        let entry = self.texts.get(source);
+        if let Some((_, dependencies)) = self.active.borrow_mut().last_mut() {
+            dependencies.push((source.clone(), entry.map(|(_, revision)| *revision)))
+        }
+        entry.map(|(text, _)| text.clone())
+    }
+
+    fn is_current(&self, dependencies: &Dependencies) -> bool {
+        dependencies.iter().all(|(source, revision)| {
+            self.texts.get(source).map(|(_, revision)| *revision) == *revision
+        })
+    }
+
+    /// Returns the result of query `Q` for the document with the given source, computing it only
+    /// if there is no memoized result or if any of the documents it depends on has changed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the query depends on its own result.
+    pub fn get<Q: Query>(&self, source: &DocumentSource) -> Arc<Q::Output> {
+        let key = (TypeId::of::<Q>(), source.clone());
+        let cached = match self.memos.borrow().get(&key) {
+            Some(memo) if self.is_current(&memo.dependencies) => {
+                Some((memo.value.clone(), memo.dependencies.clone()))
+            }
+            _ => None,
+        };
+        let (value, dependencies) = match cached {
+            Some(cached) => cached,
+            None => {
+                if self
+                    .active
+                    .borrow()
+                    .iter()
+                    .any(|(active, _)| *active == key)
+                {
+                    panic!(
+                        "query {} depends on itself for {}",
+                        std::any::type_name::<Q>(),
+                        source
+                    )
+                }
+                self.active.borrow_mut().push((key.clone(), Vec::new()));
+                let value: Arc<dyn Any + Send + Sync> = Arc::new(Q::compute(self, source));
+                let (_, mut dependencies) = self.active.borrow_mut().pop().unwrap();
+                dependencies.sort_by_key(|(source, _)| source.to_string());
+                dependencies.dedup();
+                self.computations.set(self.computations.get() + 1);
+                self.memos.borrow_mut().insert(
+                    key,
+                    Memo {
+                        value: value.clone(),
+                        dependencies: dependencies.clone(),
+                    },
+                );
+                (value, dependencies)
+            }
+        };
+        if let Some((_, active)) = self.active.borrow_mut().last_mut() {
+            active.extend(dependencies)
+        }
+        value
+            .downcast::<Q::Output>()
+            .unwrap_or_else(|_| panic!("query result has the wrong type"))
+    }
+
+    /// Returns the parsed document with the given source.
+    pub fn parse(&self, source: &DocumentSource) -> Arc<Result<Document, Report<WdlParserError>>> {
+        self.get::<Parse>(source)
+    }
+
+    /// Returns the scopes of the document with the given source, or `None` if it cannot be
+    /// parsed.
+    pub fn scopes(&self, source: &DocumentSource) -> Arc<Option<Scopes>> {
+        self.get::<ResolveScopes>(source)
+    }
+
+    /// Returns the validation warnings and lint diagnostics of the document with the given
+    /// source, or an empty `Vec` if it cannot be parsed.
+    pub fn diagnostics(&self, source: &DocumentSource) -> Arc<Vec<Diagnostic>> {
+        self.get::<Diagnostics>(source)
+    }
+}
+
+/// Parses a document.
+pub struct Parse;
+
+impl Query for Parse {
+    type Output = Result<Document, Report<WdlParserError>>;
+
+    fn compute<P: WdlParser>(db: &Database<P>, source: &DocumentSource) -> Self::Output {
+        match db.text(source) {
+            Some(text) => db.parser.borrow_mut().parse_text(text, source.clone()),
+            None => Err(Report::new(WdlParserError::IO(source.clone()))),
+        }
+    }
+}
+
+/// Resolves the scopes of a document.
+pub struct ResolveScopes;
+
+impl Query for ResolveScopes {
+    type Output = Option<Scopes>;
+
+    fn compute<P: WdlParser>(db: &Database<P>, source: &DocumentSource) -> Self::Output {
+        db.parse(source)
+            .as_ref()
+            .as_ref()
+            .ok()
+            .map(|document| trace::phase("scopes", || resolve_scopes(document)))
+    }
+}
+
+/// Validates and lints a document.
+pub struct Diagnostics;
+
+impl Query for Diagnostics {
+    type Output = Vec<Diagnostic>;
+
+    fn compute<P: WdlParser>(db: &Database<P>, source: &DocumentSource) -> Self::Output {
+        let parsed = db.parse(source);
+        let document = match parsed.as_ref() {
+            Ok(document) => document,
+            Err(_) => return Vec::new(),
+        };
+        let mut diagnostics = document
+            .validate_with(&db.validation_options)
+            .unwrap_or_default();
+        diagnostics.extend(trace::phase("lint", || db.linter.check(document)));
+        diagnostics
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{model::DocumentElement, parsers::PestParser, workspace::Workspace};
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    /// Counts the tasks of a document and of the documents it imports.
+    struct CountTasks;
+
+    impl Query for CountTasks {
+        type Output = usize;
+
+        fn compute<P: WdlParser>(db: &Database<P>, source: &DocumentSource) -> Self::Output {
+            let parsed = db.parse(source);
+            let document = match parsed.as_ref() {
+                Ok(document) => document,
+                Err(_) => return 0,
+            };
+            document
+                .body_iter()
+                .map(|element| match element {
+                    DocumentElement::Task(_) => 1,
+                    DocumentElement::Import(import) => *db
+                        .get::<CountTasks>(&Workspace::import_source(source, import.uri.as_str())),
+                    _ => 0,
+                })
+                .sum()
+        }
+    }
+
+    #[test]
+    fn test_incremental() {
+        let main = DocumentSource::File(PathBuf::from("/w/main.wdl"));
+        let lib = DocumentSource::File(PathBuf::from("/w/lib.wdl"));
+        let other = DocumentSource::File(PathBuf::from("/w/other.wdl"));
+        let mut db = Database::new(PestParser::new());
+        db.set_text(
+            main.clone(),
+            "version 1.1\nimport \"lib.wdl\"\ntask a { command <<< >>> }\n",
+        );
+        db.set_text(lib.clone(), "version 1.1\ntask b { command <<< >>> }\n");
+        db.set_text(other.clone(), "version 1.1\ntask c { command <<< >>> }\n");
+        assert_eq!(*db.get::<CountTasks>(&main), 2);
+        assert_eq!(*db.get::<CountTasks>(&other), 1);
+        // parse and count for each of the three documents
+        assert_eq!(db.computations(), 6);
+        assert_eq!(*db.get::<CountTasks>(&main), 2);
+        assert_eq!(db.computations(), 6);
+
+        // editing the imported document invalidates the results that depend on it, but the
+        // importing document is not parsed again
+        db.set_text(
+            lib.clone(),
+            "version 1.1\ntask b { command <<< >>> }\ntask b2 { command <<< >>> }\n",
+        );
+        assert_eq!(*db.get::<CountTasks>(&main), 3);
+        assert_eq!(db.computations(), 9);
+        assert_eq!(*db.get::<CountTasks>(&other), 1);
+        assert_eq!(db.computations(), 9);
+
+        // setting the same text does not invalidate anything
+        let revision = db.revision();
+        assert_eq!(
+            db.set_text(other.clone(), "version 1.1\ntask c { command <<< >>> }\n"),
+            revision
+        );
+        db.remove(&lib);
+        assert_eq!(*db.get::<CountTasks>(&main), 1);
+        assert!(db.parse(&lib).is_err());
+        assert!(db.scopes(&main).is_some());
+        assert!(db.diagnostics(&other).is_empty());
+    }
+}
@@ -0,0 +1,243 @@
+//! Inputs JSON files, which bind the inputs of a workflow (or of a document's only task) by their
+//! fully-qualified names, e.g. `{"wf.x": 1}`.
+use crate::{
+    eval::{evaluate, Environment},
+    model::{
+        Document, DocumentElement, Input, InputDeclaration, Output, ParameterDescription, Task,
+        Type, Workflow,
+    },
+    values::WdlValue,
+};
+use error_stack::{bail, Result, ResultExt};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use std::ops::Deref;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum InputsError {
+    #[error("the document does not have a workflow or a single task")]
+    NoTarget,
+    #[error("{0} is not an input")]
+    UnknownInput(String),
+    #[error("invalid value for input {0}")]
+    InvalidValue(String),
+    #[error("missing required input(s) {}", .0.join(", "))]
+    Missing(Vec<String>),
+}
+
+/// The workflow or task whose inputs are bound by an inputs JSON file: the workflow of a
+/// document, or its task if it has no workflow and a single task.
+pub(crate) enum Callable<'a> {
+    Workflow(&'a Workflow),
+    Task(&'a Task),
+}
+
+impl<'a> Callable<'a> {
+    pub(crate) fn of(document: &'a Document) -> Option<Self> {
+        let mut tasks = Vec::new();
+        for element in document.body_iter() {
+            match element {
+                DocumentElement::Workflow(workflow) => return Some(Self::Workflow(workflow)),
+                DocumentElement::Task(task) => tasks.push(task),
+                _ => (),
+            }
+        }
+        match tasks.as_slice() {
+            [task] => Some(Self::Task(task)),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn name(&self) -> &'a str {
+        match self {
+            Self::Workflow(workflow) => workflow.name.as_str(),
+            Self::Task(task) => task.name.as_str(),
+        }
+    }
+
+    pub(crate) fn inputs(&self) -> Option<&'a Input> {
+        match self {
+            Self::Workflow(workflow) => workflow.inputs(),
+            Self::Task(task) => task.inputs(),
+        }
+    }
+
+    pub(crate) fn outputs(&self) -> Option<&'a Output> {
+        match self {
+            Self::Workflow(workflow) => workflow.outputs(),
+            Self::Task(task) => task.outputs(),
+        }
+    }
+
+    /// Returns the input declarations, in the order in which they are declared.
+    pub(crate) fn input_declarations(&self) -> impl Iterator<Item = &'a InputDeclaration> {
+        self.inputs()
+            .into_iter()
+            .flat_map(|inputs| inputs.declarations.iter().map(|decl| decl.deref()))
+    }
+
+    pub(crate) fn parameter_description(&self, name: &str) -> Option<ParameterDescription> {
+        match self {
+            Self::Workflow(workflow) => workflow.parameter_description(name),
+            Self::Task(task) => task.parameter_description(name),
+        }
+    }
+}
+
+/// Returns a fully-populated inputs JSON object for `document`, with the value of each input
+/// taken from `overrides` or else from its default. Overrides may be keyed by fully-qualified
+/// (`wf.x`) or unqualified (`x`) names, and are coerced to the types of their inputs. Overrides of
+/// the inputs of calls (e.g. `wf.call.x`) are copied to the result as they are.
+///
+/// Defaults are evaluated with `eval::evaluate`, in which inputs are bound to their values, so a
+/// default may depend on other inputs, e.g. `Int n = length(samples)`. Defaults that cannot be
+/// evaluated (e.g. they call `read_lines`) are omitted, so that they are evaluated by the engine.
+/// Optional inputs without defaults or overrides are `null`.
+///
+/// Returns an error listing all of the required inputs, i.e. those without defaults and with
+/// non-optional types, that are not overridden.
+pub fn materialize(
+    document: &Document,
+    overrides: &JsonMap<String, JsonValue>,
+) -> Result<JsonValue, InputsError> {
+    let callable = match Callable::of(document) {
+        Some(callable) => callable,
+        None => bail!(InputsError::NoTarget),
+    };
+    let prefix = format!("{}.", callable.name());
+    let declarations: Vec<&InputDeclaration> = callable.input_declarations().collect();
+    let mut env = Environment::new();
+    let mut result = JsonMap::new();
+    for (key, json) in overrides {
+        let name = key.strip_prefix(&prefix).unwrap_or(key);
+        match declarations
+            .iter()
+            .find(|decl| decl.name().as_str() == name)
+        {
+            Some(decl) => {
+                let value = WdlValue::from_json_typed(json, decl.type_())
+                    .change_context(InputsError::InvalidValue(key.clone()))?;
+                env.bind(name, value);
+            }
+            None if key.starts_with(&prefix) && name.contains('.') => {
+                result.insert(key.clone(), json.clone());
+            }
+            None => bail!(InputsError::UnknownInput(key.clone())),
+        }
+    }
+    let mut missing = Vec::new();
+    let mut defaults = Vec::new();
+    for decl in declarations.iter() {
+        if env.contains(decl.name()) {
+            continue;
+        }
+        match decl {
+            InputDeclaration::Bound(decl) => defaults.push(decl),
+            InputDeclaration::Unbound(decl) => match decl.type_.deref() {
+                Type::Optional(_) => env.bind(decl.name.as_str(), WdlValue::None),
+                _ => missing.push(format!("{}{}", prefix, decl.name.as_str())),
+            },
+        }
+    }
+    if !missing.is_empty() {
+        bail!(InputsError::Missing(missing))
+    }
+    // evaluate defaults until none of the remaining ones can be evaluated, since they may depend
+    // on each other in any order
+    loop {
+        let pending = defaults.len();
+        defaults.retain(|decl| {
+            match evaluate(&decl.expression, &env)
+                .ok()
+                .and_then(|value| value.coerce(&decl.type_).ok())
+            {
+                Some(value) => {
+                    env.bind(decl.name.as_str(), value);
+                    false
+                }
+                None => true,
+            }
+        });
+        if defaults.is_empty() || defaults.len() == pending {
+            break;
+        }
+    }
+    for decl in declarations.iter() {
+        let name = decl.name().as_str();
+        if let Some(value) = env.get(name) {
+            let key = format!("{}{}", prefix, name);
+            let json = value
+                .to_json()
+                .change_context(InputsError::InvalidValue(key.clone()))?;
+            result.insert(key, json);
+        }
+    }
+    Ok(JsonValue::Object(result))
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    #[test]
+    fn test_materialize() {
+        let text = r#"version 1.1
+
+workflow w {
+    input {
+        Array[String] samples
+        Int shards = length(samples) * scale
+        Int scale = 2
+        Float threshold = 1
+        String? label
+        Array[String] lines = read_lines("lines.txt")
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let overrides = |json: JsonValue| match json {
+            JsonValue::Object(map) => map,
+            _ => unreachable!(),
+        };
+        let inputs = materialize(
+            &doc,
+            &overrides(json!({"w.samples": ["a", "b", "c"], "scale": 3, "w.t.cpu": 4})),
+        )
+        .unwrap();
+        assert_eq!(
+            inputs,
+            json!({
+                "w.samples": ["a", "b", "c"],
+                "w.shards": 9,
+                "w.scale": 3,
+                "w.threshold": 1.0,
+                "w.label": null,
+                "w.t.cpu": 4,
+            })
+        );
+
+        let error = materialize(&doc, &JsonMap::new()).unwrap_err();
+        assert_eq!(
+            error.current_context().to_string(),
+            "missing required input(s) w.samples"
+        );
+        let error = materialize(&doc, &overrides(json!({"samples": [], "x.y": 1}))).unwrap_err();
+        assert!(matches!(
+            error.current_context(),
+            InputsError::UnknownInput(key) if key == "x.y"
+        ));
+        let error = materialize(&doc, &overrides(json!({"samples": 1}))).unwrap_err();
+        assert!(matches!(
+            error.current_context(),
+            InputsError::InvalidValue(key) if key == "samples"
+        ));
+    }
+}
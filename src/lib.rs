@@ -1,2 +1,48 @@
+pub mod analysis;
+#[cfg(feature = "bundle")]
+pub mod bundle;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod codegen;
+pub mod compat;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod convert;
+pub mod diagnostics;
+pub mod docs;
+pub mod edit;
+pub mod eval;
+pub mod fix;
+pub mod fqn;
+#[cfg(feature = "git")]
+pub mod git;
+pub mod highlight;
+pub mod incremental;
+pub mod inputs;
+pub mod lint;
+pub mod matching;
+pub mod metrics;
 pub mod model;
+pub mod navigate;
 pub mod parsers;
+pub mod plan;
+pub mod provenance;
+#[cfg(feature = "registry")]
+pub mod registry;
+pub mod scaffold;
+pub mod shell;
+#[cfg(feature = "shellcheck")]
+pub mod shellcheck;
+pub mod sourcemap;
+#[cfg(feature = "testdata")]
+pub mod testdata;
+pub mod testing;
+pub mod tokens;
+mod trace;
+pub mod transform;
+pub mod tree;
+pub mod units;
+pub mod values;
+#[cfg(feature = "notify")]
+pub mod watch;
+pub mod workspace;
@@ -0,0 +1,233 @@
+use crate::{
+    analysis::{call_scopes, callee_inputs, is_coercible},
+    diagnostics::Diagnostic,
+    fqn,
+    model::{Document, DocumentElement, Expression, InputDeclaration, ObjectKind, Type},
+    workspace::Workspace,
+};
+use std::{collections::HashMap, ops::Deref};
+
+pub const MISSING_INPUT: &str = "call_input_missing";
+pub const UNKNOWN_INPUT: &str = "call_input_unknown";
+pub const INPUT_TYPE: &str = "call_input_type";
+
+/// Returns whether a value of type `from` can be bound to an input of type `to`, ignoring
+/// optionality and non-emptiness, which can only be checked at runtime.
+fn is_compatible(from: &Type, to: &Type) -> bool {
+    match (from, to) {
+        (_, Type::Optional(to)) => is_compatible(from, to),
+        (Type::Optional(from), _) => is_compatible(from, to),
+        (Type::Array { item: from, .. }, Type::Array { item: to, .. }) => is_compatible(from, to),
+        (
+            Type::Map {
+                key: from_key,
+                value: from_value,
+            },
+            Type::Map { key, value },
+        ) => is_compatible(from_key, key) && is_compatible(from_value, value),
+        (
+            Type::Pair {
+                left: from_left,
+                right: from_right,
+            },
+            Type::Pair { left, right },
+        ) => is_compatible(from_left, left) && is_compatible(from_right, right),
+        _ => is_coercible(from, to),
+    }
+}
+
+/// Returns the type of `expression` if it is a literal or an identifier of a value in `scope`,
+/// otherwise `None`.
+fn expression_type(expression: &Expression, scope: &HashMap<&str, Type>) -> Option<Type> {
+    match expression {
+        Expression::Boolean(_) => Some(Type::Boolean),
+        Expression::Int(_) => Some(Type::Int),
+        Expression::Float(_) => Some(Type::Float),
+        Expression::String(_) => Some(Type::String),
+        Expression::Object(object) => match object.kind {
+            ObjectKind::Object => Some(Type::Object),
+            ObjectKind::Struct => Some(Type::User((*object.type_name).clone())),
+        },
+        Expression::Identifier(name) => scope.get(name.as_str()).cloned(),
+        Expression::Group(inner) => expression_type(inner, scope),
+        _ => None,
+    }
+}
+
+/// Checks the inputs of each call in the workflow of `document` against the inputs of its
+/// target, which is resolved via `workspace` (so calls of tasks and workflows in imported
+/// namespaces are checked if the imported documents are in the workspace):
+/// * Each input that is set by the call must be an input of the target.
+/// * The value of each input must have a type that is compatible with the input's declared type.
+///   Only literals and identifiers are checked, since the types of other expressions are not
+///   inferred.
+/// * Each required input of the target should be set by the call. This is reported as a warning
+///   rather than an error, since engines may allow missing inputs to be supplied as (nested)
+///   inputs of the workflow.
+///
+/// Calls whose targets cannot be resolved are not checked. Unlike lints, this check requires a
+/// `Workspace` in which to resolve imports.
+pub fn check_call_inputs(workspace: &Workspace, document: &Document) -> Vec<Diagnostic> {
+    let workflow = match document.body_iter().find_map(|element| match element {
+        DocumentElement::Workflow(workflow) => Some(workflow),
+        _ => None,
+    }) {
+        Some(workflow) => workflow,
+        None => return Vec::new(),
+    };
+    let mut diagnostics = Vec::new();
+    for (call, span, scope) in call_scopes(workflow) {
+        let inputs = match callee_inputs(workspace, document, call) {
+            Some(inputs) => inputs,
+            None => continue,
+        };
+        let name = fqn::call_name(call);
+        for input in call.inputs.iter().flatten() {
+            let decl = match inputs
+                .iter()
+                .find(|decl| decl.name().as_str() == input.name.as_str())
+            {
+                Some(decl) => decl,
+                None => {
+                    diagnostics.push(Diagnostic::error(
+                        UNKNOWN_INPUT,
+                        format!(
+                            "call {} sets input {}, which is not an input of {}",
+                            name,
+                            input.name.as_str(),
+                            call.target.parts.last().unwrap().as_str()
+                        ),
+                        input.name.span.clone(),
+                    ));
+                    continue;
+                }
+            };
+            let (value_type, value_span) = match &input.expression {
+                Some(expression) => (expression_type(expression, &scope), &expression.span),
+                // `input: x` is shorthand for `input: x = x`
+                None => (scope.get(input.name.as_str()).cloned(), &input.name.span),
+            };
+            match value_type {
+                Some(value_type) if !is_compatible(&value_type, decl.type_()) => diagnostics.push(
+                    Diagnostic::error(
+                        INPUT_TYPE,
+                        format!(
+                            "input {} of call {} has type {}, but its value has type {}",
+                            input.name.as_str(),
+                            name,
+                            decl.type_().deref(),
+                            value_type
+                        ),
+                        value_span.clone(),
+                    )
+                    .with_related(decl.type_().span.clone(), "input is declared here"),
+                ),
+                _ => (),
+            }
+        }
+        for decl in inputs.iter() {
+            let unbound = match decl {
+                InputDeclaration::Unbound(unbound) => unbound,
+                InputDeclaration::Bound(_) => continue,
+            };
+            if matches!(unbound.type_.deref(), Type::Optional(_))
+                || call
+                    .inputs
+                    .iter()
+                    .flatten()
+                    .any(|input| input.name.as_str() == unbound.name.as_str())
+            {
+                continue;
+            }
+            diagnostics.push(Diagnostic::warning(
+                MISSING_INPUT,
+                format!(
+                    "call {} does not set required input {}",
+                    name,
+                    unbound.name.as_str()
+                ),
+                span.clone(),
+            ))
+        }
+    }
+    diagnostics.sort_by(|a, b| a.span.cmp(&b.span));
+    diagnostics
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_check_call_inputs() {
+        let tasks = r#"version 1.1
+
+task align {
+    input {
+        File reads
+        Int threads
+        String? tag
+        Array[String] flags = []
+    }
+    command <<< >>>
+}
+"#;
+        let main = r#"version 1.1
+
+import "tasks.wdl" as lib
+
+workflow main {
+    input {
+        File reads
+        String threads
+    }
+    call lib.align { input: reads, threads, flags = ["-a"] }
+    call lib.align as again { input: reads = "r.fq", tags = "x" }
+    call other.sort
+}
+"#;
+        let mut parser = PestParser::new();
+        let mut workspace = Workspace::new();
+        for (path, text) in [("/w/tasks.wdl", tasks), ("/w/main.wdl", main)] {
+            workspace.insert(
+                parser
+                    .parse_text(text, DocumentSource::File(PathBuf::from(path)))
+                    .unwrap(),
+            );
+        }
+        let document = workspace
+            .get(&DocumentSource::File(PathBuf::from("/w/main.wdl")))
+            .unwrap();
+        let diagnostics: Vec<(String, String, usize)> = check_call_inputs(&workspace, document)
+            .into_iter()
+            .map(|d| (d.code, d.message, d.span.start.line))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                (
+                    INPUT_TYPE.to_owned(),
+                    "input threads of call align has type Int, but its value has type String"
+                        .to_owned(),
+                    9
+                ),
+                (
+                    MISSING_INPUT.to_owned(),
+                    "call again does not set required input threads".to_owned(),
+                    10
+                ),
+                (
+                    UNKNOWN_INPUT.to_owned(),
+                    "call again sets input tags, which is not an input of align".to_owned(),
+                    10
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,473 @@
+use crate::{
+    analysis::{resolve_scopes, walk_expression, Scopes, SymbolKind},
+    diagnostics::Diagnostic,
+    eval::{evaluate, Environment},
+    fqn,
+    lint::Lint,
+    model::{
+        Anchor, Document, DocumentElement, Expression, Span, UnaryOperator, Workflow,
+        WorkflowElement, WorkflowNestedElement,
+    },
+    values::WdlValue,
+};
+use std::{collections::HashMap, ops::Deref};
+
+pub const CONSTANT_CONDITION: &str = "conditional_constant";
+pub const SELECT_FIRST_WITHOUT_FALLBACK: &str = "select_first_without_fallback";
+pub const DOWNSTREAM_REFERENCE: &str = "conditional_downstream_reference";
+
+/// Checks the conditionals of workflows for likely mistakes:
+/// * Conditions that are constant, i.e. that evaluate to the same value regardless of the
+///   workflow inputs, so that the body of the conditional is either always or never executed.
+/// * Calls to `select_first` with an array literal whose elements all refer to values that are
+///   declared within conditionals (e.g. `select_first([a.out, b.out])`), which fail at runtime if
+///   none of the conditions is true. Values from two conditionals whose conditions are `c` and
+///   `!c` are assumed to always provide a value.
+/// * References from the condition or body of a conditional to declarations or calls that are
+///   declared after the conditional, which make the order in which the workflow is executed hard
+///   to follow and are not supported by all engines.
+#[derive(Debug)]
+pub struct ConditionalLint {
+    pub constant_conditions: bool,
+    pub select_first_without_fallback: bool,
+    pub downstream_references: bool,
+}
+
+impl Default for ConditionalLint {
+    fn default() -> Self {
+        Self {
+            constant_conditions: true,
+            select_first_without_fallback: true,
+            downstream_references: true,
+        }
+    }
+}
+
+/// Collects the spans of the conditionals nested within `body`, outermost first.
+fn collect_conditionals<'a>(body: &'a [Anchor<WorkflowNestedElement>], spans: &mut Vec<&'a Span>) {
+    for element in body.iter() {
+        match element.deref() {
+            WorkflowNestedElement::Scatter(scatter) => collect_conditionals(&scatter.body, spans),
+            WorkflowNestedElement::Conditional(conditional) => {
+                spans.push(&element.span);
+                collect_conditionals(&conditional.body, spans)
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Removes any enclosing parentheses from `expression`.
+fn ungroup(expression: &Expression) -> &Expression {
+    match expression {
+        Expression::Group(inner) => ungroup(inner),
+        _ => expression,
+    }
+}
+
+/// Returns whether `a` is the negation of `b` or vice versa.
+fn complementary(a: &Expression, b: &Expression) -> bool {
+    let negates = |a: &Expression, b: &Expression| match ungroup(a) {
+        Expression::Unary(unary) if unary.operator == UnaryOperator::Not => {
+            ungroup(&unary.expression).to_string() == ungroup(b).to_string()
+        }
+        _ => false,
+    };
+    negates(a, b) || negates(b, a)
+}
+
+/// Returns the name of the declaration or call referenced by `expression`, if it is a reference
+/// to a declaration or a call output.
+fn referenced_name(expression: &Expression) -> Option<&str> {
+    match ungroup(expression) {
+        Expression::Identifier(name) => Some(name.as_str()),
+        Expression::Access(access) => match ungroup(&access.collection) {
+            Expression::Identifier(name) => Some(name.as_str()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+struct Checker<'a> {
+    lint: &'a ConditionalLint,
+    /// The conditions of the workflow's conditionals, in source order.
+    conditions: Vec<&'a Anchor<Expression>>,
+    /// The indices of the conditionals that enclose each declaration and call.
+    guards: HashMap<&'a str, Vec<usize>>,
+    /// The indices of the conditionals that enclose the current element.
+    enclosing: Vec<usize>,
+    /// The number of conditionals visited so far.
+    visited: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a> {
+    fn collect(&mut self, body: &'a [Anchor<WorkflowNestedElement>]) {
+        for element in body.iter() {
+            match element.deref() {
+                WorkflowNestedElement::Declaration(decl) => {
+                    self.guards
+                        .insert(decl.name.as_str(), self.enclosing.clone());
+                }
+                WorkflowNestedElement::Call(call) => {
+                    self.guards
+                        .insert(fqn::call_name(call), self.enclosing.clone());
+                }
+                WorkflowNestedElement::Scatter(scatter) => self.collect(&scatter.body),
+                WorkflowNestedElement::Conditional(conditional) => {
+                    self.conditions.push(&conditional.expression);
+                    self.enclosing.push(self.conditions.len() - 1);
+                    self.collect(&conditional.body);
+                    self.enclosing.pop();
+                }
+            }
+        }
+    }
+
+    fn check_body(&mut self, body: &'a [Anchor<WorkflowNestedElement>]) {
+        for element in body.iter() {
+            match element.deref() {
+                WorkflowNestedElement::Declaration(decl) => self.check_expression(&decl.expression),
+                WorkflowNestedElement::Call(call) => {
+                    for input in call.inputs.iter().flatten() {
+                        if let Some(expression) = &input.expression {
+                            self.check_expression(expression)
+                        }
+                    }
+                }
+                WorkflowNestedElement::Scatter(scatter) => {
+                    self.check_expression(&scatter.expression);
+                    self.check_body(&scatter.body)
+                }
+                WorkflowNestedElement::Conditional(conditional) => {
+                    self.check_condition(&conditional.expression);
+                    self.check_expression(&conditional.expression);
+                    self.enclosing.push(self.visited);
+                    self.visited += 1;
+                    self.check_body(&conditional.body);
+                    self.enclosing.pop();
+                }
+            }
+        }
+    }
+
+    fn check_condition(&mut self, condition: &Anchor<Expression>) {
+        if !self.lint.constant_conditions {
+            return;
+        }
+        if let Ok(WdlValue::Boolean(value)) = evaluate(condition, &Environment::new()) {
+            self.diagnostics.push(Diagnostic::warning(
+                CONSTANT_CONDITION,
+                format!(
+                    "condition {} is always {}, so the body of the conditional is {} executed",
+                    condition.deref(),
+                    value,
+                    if value { "always" } else { "never" }
+                ),
+                condition.span.clone(),
+            ))
+        }
+    }
+
+    fn check_expression(&mut self, expression: &'a Anchor<Expression>) {
+        if !self.lint.select_first_without_fallback {
+            return;
+        }
+        let mut applications = Vec::new();
+        walk_expression(expression, &expression.span, &mut |e, span| {
+            if let Expression::Apply(apply) = e {
+                if apply.name.as_str() == "select_first" {
+                    applications.push((apply, span))
+                }
+            }
+        });
+        for (apply, span) in applications {
+            let elements = match apply.arguments.first().map(|arg| ungroup(arg)) {
+                Some(Expression::Array(array)) if !array.elements.is_empty() => &array.elements,
+                _ => continue,
+            };
+            // the conditionals that do not enclose this expression and that guard each element
+            let guards: Option<Vec<Vec<usize>>> = elements
+                .iter()
+                .map(|element| {
+                    let guards: Vec<usize> = self
+                        .guards
+                        .get(referenced_name(element)?)?
+                        .iter()
+                        .filter(|guard| !self.enclosing.contains(guard))
+                        .copied()
+                        .collect();
+                    if guards.is_empty() {
+                        None
+                    } else {
+                        Some(guards)
+                    }
+                })
+                .collect();
+            let guards = match guards {
+                Some(guards) => guards,
+                None => continue,
+            };
+            let mut conditions: Vec<usize> = guards.into_iter().flatten().collect();
+            conditions.sort_unstable();
+            conditions.dedup();
+            let exhaustive = conditions.iter().any(|a| {
+                conditions
+                    .iter()
+                    .any(|b| complementary(self.conditions[*a], self.conditions[*b]))
+            });
+            if exhaustive {
+                continue;
+            }
+            let mut diagnostic = Diagnostic::warning(
+                SELECT_FIRST_WITHOUT_FALLBACK,
+                "all the values passed to select_first are declared in conditionals, so it fails \
+                 if none of the conditions is true; add a fallback value as the last element",
+                span.clone(),
+            );
+            for condition in conditions {
+                let condition = self.conditions[condition];
+                diagnostic = diagnostic.with_related(
+                    condition.span.clone(),
+                    format!("value is only defined if {} is true", condition.deref()),
+                )
+            }
+            self.diagnostics.push(diagnostic)
+        }
+    }
+}
+
+impl ConditionalLint {
+    fn check_downstream_references(
+        &self,
+        workflow: &Workflow,
+        span: &Span,
+        scopes: &Scopes,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let mut conditionals = Vec::new();
+        for element in workflow.body.iter() {
+            match element.deref() {
+                WorkflowElement::Scatter(scatter) => {
+                    collect_conditionals(&scatter.body, &mut conditionals)
+                }
+                WorkflowElement::Conditional(conditional) => {
+                    conditionals.push(&element.span);
+                    collect_conditionals(&conditional.body, &mut conditionals)
+                }
+                _ => (),
+            }
+        }
+        for reference in scopes.references.iter() {
+            let symbol = match &reference.symbol {
+                Some(symbol)
+                    if matches!(symbol.kind, SymbolKind::Declaration | SymbolKind::Call)
+                        && span.contains_span(&symbol.span) =>
+                {
+                    symbol
+                }
+                _ => continue,
+            };
+            let downstream = conditionals.iter().any(|conditional| {
+                conditional.contains_span(&reference.span) && symbol.span.start >= conditional.end
+            });
+            if downstream {
+                diagnostics.push(
+                    Diagnostic::warning(
+                        DOWNSTREAM_REFERENCE,
+                        format!(
+                            "{} is referenced by a conditional but is declared after it",
+                            reference.name
+                        ),
+                        reference.span.clone(),
+                    )
+                    .with_related(
+                        symbol.span.clone(),
+                        format!("{} is declared here", reference.name),
+                    ),
+                )
+            }
+        }
+    }
+
+    fn check_workflow(&self, workflow: &Workflow, diagnostics: &mut Vec<Diagnostic>) {
+        let mut checker = Checker {
+            lint: self,
+            conditions: Vec::new(),
+            guards: HashMap::new(),
+            enclosing: Vec::new(),
+            visited: 0,
+            diagnostics: Vec::new(),
+        };
+        for element in workflow.body.iter() {
+            match element.deref() {
+                WorkflowElement::Scatter(scatter) => checker.collect(&scatter.body),
+                WorkflowElement::Conditional(conditional) => {
+                    checker.conditions.push(&conditional.expression);
+                    checker.enclosing.push(checker.conditions.len() - 1);
+                    checker.collect(&conditional.body);
+                    checker.enclosing.pop();
+                }
+                _ => (),
+            }
+        }
+        for element in workflow.body.iter() {
+            match element.deref() {
+                WorkflowElement::Declaration(decl) => checker.check_expression(&decl.expression),
+                WorkflowElement::Call(call) => {
+                    for input in call.inputs.iter().flatten() {
+                        if let Some(expression) = &input.expression {
+                            checker.check_expression(expression)
+                        }
+                    }
+                }
+                WorkflowElement::Scatter(scatter) => {
+                    checker.check_expression(&scatter.expression);
+                    checker.check_body(&scatter.body)
+                }
+                WorkflowElement::Conditional(conditional) => {
+                    checker.check_condition(&conditional.expression);
+                    checker.check_expression(&conditional.expression);
+                    checker.enclosing.push(checker.visited);
+                    checker.visited += 1;
+                    checker.check_body(&conditional.body);
+                    checker.enclosing.pop();
+                }
+                WorkflowElement::Output(output) => {
+                    for decl in output.declarations.iter() {
+                        checker.check_expression(&decl.expression)
+                    }
+                }
+                _ => (),
+            }
+        }
+        diagnostics.extend(checker.diagnostics)
+    }
+}
+
+impl Lint for ConditionalLint {
+    fn name(&self) -> &'static str {
+        "conditionals"
+    }
+
+    fn check(&self, document: &Document) -> Vec<Diagnostic> {
+        let scopes = if self.downstream_references {
+            Some(resolve_scopes(document))
+        } else {
+            None
+        };
+        let mut diagnostics = Vec::new();
+        for element in document.body.iter() {
+            if let DocumentElement::Workflow(workflow) = element.deref() {
+                self.check_workflow(workflow, &mut diagnostics);
+                if let Some(scopes) = &scopes {
+                    self.check_downstream_references(
+                        workflow,
+                        &element.span,
+                        scopes,
+                        &mut diagnostics,
+                    )
+                }
+            }
+        }
+        diagnostics
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_conditional_lint() {
+        let text = r#"version 1.1
+
+workflow wf {
+    input {
+        Boolean paired
+        Boolean trim
+    }
+    if (paired) {
+        call align_paired
+    }
+    if (!(paired)) {
+        call align_single
+    }
+    if (trim) {
+        call trimmer
+        File trimmed = select_first([trimmer.out])
+    }
+    if (1 > 2) {
+        call never
+    }
+    output {
+        File bam = select_first([align_paired.bam, align_single.bam])
+        File fastq = select_first([trimmer.out])
+        File maybe = select_first([trimmed, never.out, "default.fq"])
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let diagnostics = ConditionalLint::default().check(&doc);
+        let found: Vec<(&str, &str)> = diagnostics
+            .iter()
+            .map(|d| {
+                (
+                    d.code.as_str(),
+                    &text[d.span.start.offset..d.span.end.offset],
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (CONSTANT_CONDITION, "1 > 2"),
+                (SELECT_FIRST_WITHOUT_FALLBACK, "select_first([trimmer.out])"),
+            ]
+        );
+        assert_eq!(diagnostics[1].related.len(), 1);
+        assert_eq!(diagnostics[1].related[0].span.start.line, 13);
+    }
+
+    #[test]
+    fn test_downstream_references() {
+        let text = r#"version 1.1
+
+workflow wf {
+    input {
+        Boolean run
+    }
+    if (run && enabled) {
+        scatter (i in range(n)) {
+            call step { input: x = i, y = step_input }
+        }
+    }
+    Boolean enabled = true
+    Int n = 2
+    call prepare
+    String step_input = prepare.out
+    output {
+        Array[Int]? outs = step.out
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let found: Vec<&str> = ConditionalLint::default()
+            .check(&doc)
+            .iter()
+            .filter(|d| d.code == DOWNSTREAM_REFERENCE)
+            .map(|d| &text[d.span.start.offset..d.span.end.offset])
+            .collect();
+        assert_eq!(found, vec!["enabled", "n", "step_input"]);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{diagnostics::Diagnostic, model::Comments};
+use std::collections::HashMap;
+
+/// The prefix of comments that contain lint directives.
+pub const DIRECTIVE_PREFIX: &str = "wdl-lint:";
+
+/// The rule name that matches all rules.
+pub const ALL_RULES: &str = "all";
+
+/// Lint rules that are disabled by directives in comments. Two directives are supported, each
+/// followed by a comma-separated list of lint names or diagnostic codes (or `all`):
+/// * `# wdl-lint: disable=rule` disables the rules on the line of the comment.
+/// * `# wdl-lint: disable-next-line=rule` disables the rules on the line following the comment.
+///
+/// A diagnostic is suppressed if its span starts on a line on which its rule is disabled.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Suppressions {
+    lines: HashMap<usize, Vec<String>>,
+}
+
+impl Suppressions {
+    /// Collects the lint directives in `comments`. Comments that start with `wdl-lint:` but do
+    /// not contain a valid directive are ignored.
+    pub fn from_comments(comments: &Comments) -> Self {
+        let mut suppressions = Self::default();
+        for comment in comments.values() {
+            let text = comment.trim_start_matches('#').trim();
+            let directive = match text.strip_prefix(DIRECTIVE_PREFIX) {
+                Some(directive) => directive.trim(),
+                None => continue,
+            };
+            let (line, rules) = if let Some(rules) = directive.strip_prefix("disable-next-line=") {
+                (comment.span.start.line + 1, rules)
+            } else if let Some(rules) = directive.strip_prefix("disable=") {
+                (comment.span.start.line, rules)
+            } else {
+                continue;
+            };
+            suppressions.lines.entry(line).or_default().extend(
+                rules
+                    .split(',')
+                    .map(|rule| rule.trim())
+                    .filter(|rule| !rule.is_empty())
+                    .map(|rule| rule.to_owned()),
+            )
+        }
+        suppressions
+    }
+
+    /// Returns whether there are no directives.
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Returns whether `diagnostic`, which was produced by the lint named `lint`, is suppressed.
+    pub fn is_suppressed(&self, lint: &str, diagnostic: &Diagnostic) -> bool {
+        match self.lines.get(&diagnostic.span.start.line) {
+            Some(rules) => rules
+                .iter()
+                .any(|rule| rule == ALL_RULES || rule == lint || *rule == diagnostic.code),
+            None => false,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        lint::{Lint, Linter, ParameterMetaLint},
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_suppressions() {
+        let text = r#"version 1.1
+
+task t {
+    input {
+        String a
+        String b
+        String c
+    }
+    command <<< >>>
+    parameter_meta {
+        x: "unknown" # wdl-lint: disable=parameter_meta_unknown
+        # wdl-lint: disable-next-line=parameter_meta
+        y: "unknown"
+        # wdl-lint: disable=all
+        z: "unknown"
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let suppressions = Suppressions::from_comments(&doc.comments);
+        let lint = ParameterMetaLint::default();
+        let lines: Vec<(usize, bool)> = lint
+            .check(&doc)
+            .iter()
+            .map(|d| {
+                (
+                    d.span.start.line,
+                    suppressions.is_suppressed(lint.name(), d),
+                )
+            })
+            .collect();
+        assert_eq!(lines, vec![(10, true), (12, true), (14, false)]);
+        let diagnostics = Linter::new(vec![Box::new(lint)]).check(&doc);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].span.start.line, 14);
+    }
+}
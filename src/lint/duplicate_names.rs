@@ -0,0 +1,260 @@
+use crate::{
+    diagnostics::Diagnostic,
+    lint::Lint,
+    model::{
+        Anchor, Call, Document, DocumentElement, Span, Struct, Task, TaskElement, Workflow,
+        WorkflowElement, WorkflowNestedElement,
+    },
+};
+use std::{collections::HashMap, ops::Deref};
+
+pub const DUPLICATE_NAME: &str = "duplicate_name";
+
+/// Checks that names are unique within their scopes, as required by the WDL spec:
+/// * The names of the structs, tasks, and workflow in a document.
+/// * The names of the fields of a struct.
+/// * The names of the inputs, private declarations, and outputs of a task.
+/// * The names of the inputs, declarations (including those nested within scatter and
+///   conditional blocks), and calls of a workflow, and separately the names of its outputs.
+///
+/// Each duplicate is reported at its second definition, with the first definition as a related
+/// span.
+#[derive(Debug, Default)]
+pub struct DuplicateNameLint;
+
+/// The names defined in a scope, with the kind of element that defines each name and the span of
+/// its first definition.
+struct Scope<'a, 'd> {
+    names: HashMap<&'a str, (&'static str, &'a Span)>,
+    diagnostics: &'d mut Vec<Diagnostic>,
+}
+
+impl<'a, 'd> Scope<'a, 'd> {
+    fn new(diagnostics: &'d mut Vec<Diagnostic>) -> Self {
+        Self {
+            names: HashMap::new(),
+            diagnostics,
+        }
+    }
+
+    fn define(&mut self, kind: &'static str, name: &'a Anchor<String>) {
+        match self.names.get(name.as_str()) {
+            Some((first_kind, first_span)) => {
+                let message = if *first_kind == kind {
+                    format!("{} {} is defined more than once", kind, name.as_str())
+                } else {
+                    format!(
+                        "{} {} has the same name as {} {}",
+                        kind,
+                        name.as_str(),
+                        first_kind,
+                        name.as_str()
+                    )
+                };
+                self.diagnostics.push(
+                    Diagnostic::error(DUPLICATE_NAME, message, name.span.clone()).with_related(
+                        (*first_span).clone(),
+                        format!("{} {} is first defined here", first_kind, name.as_str()),
+                    ),
+                )
+            }
+            None => {
+                self.names.insert(name.as_str(), (kind, &name.span));
+            }
+        }
+    }
+}
+
+impl DuplicateNameLint {
+    fn check_struct(&self, struct_: &Struct, diagnostics: &mut Vec<Diagnostic>) {
+        let mut scope = Scope::new(diagnostics);
+        for field in struct_.fields.iter() {
+            scope.define("field", &field.name);
+        }
+    }
+
+    fn check_task(&self, task: &Task, diagnostics: &mut Vec<Diagnostic>) {
+        let mut scope = Scope::new(diagnostics);
+        for element in task.body.iter() {
+            match element.deref() {
+                TaskElement::Input(input) => {
+                    for decl in input.declarations.iter() {
+                        scope.define("input", decl.name())
+                    }
+                }
+                TaskElement::Declaration(decl) => scope.define("declaration", &decl.name),
+                TaskElement::Output(output) => {
+                    for decl in output.declarations.iter() {
+                        scope.define("output", &decl.name)
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn check_workflow(&self, workflow: &Workflow, diagnostics: &mut Vec<Diagnostic>) {
+        let mut outputs = Vec::new();
+        let mut scope = Scope::new(diagnostics);
+        for element in workflow.body.iter() {
+            match element.deref() {
+                WorkflowElement::Input(input) => {
+                    for decl in input.declarations.iter() {
+                        scope.define("input", decl.name())
+                    }
+                }
+                WorkflowElement::Declaration(decl) => scope.define("declaration", &decl.name),
+                WorkflowElement::Call(call) => define_call(&mut scope, call),
+                WorkflowElement::Scatter(scatter) => define_nested(&mut scope, &scatter.body),
+                WorkflowElement::Conditional(conditional) => {
+                    define_nested(&mut scope, &conditional.body)
+                }
+                WorkflowElement::Output(output) => outputs.push(output),
+                _ => (),
+            }
+        }
+        let mut scope = Scope::new(diagnostics);
+        for output in outputs {
+            for decl in output.declarations.iter() {
+                scope.define("output", &decl.name)
+            }
+        }
+    }
+}
+
+/// Defines the name by which `call` is referenced, i.e. its alias or the last part of its target.
+fn define_call<'a>(scope: &mut Scope<'a, '_>, call: &'a Call) {
+    let name = match &call.alias {
+        Some(alias) => alias,
+        None => match call.target.parts.last() {
+            Some(name) => name,
+            None => return,
+        },
+    };
+    scope.define("call", name)
+}
+
+fn define_nested<'a>(scope: &mut Scope<'a, '_>, body: &'a [Anchor<WorkflowNestedElement>]) {
+    for element in body.iter() {
+        match element.deref() {
+            WorkflowNestedElement::Declaration(decl) => scope.define("declaration", &decl.name),
+            WorkflowNestedElement::Call(call) => define_call(scope, call),
+            WorkflowNestedElement::Scatter(scatter) => define_nested(scope, &scatter.body),
+            WorkflowNestedElement::Conditional(conditional) => {
+                define_nested(scope, &conditional.body)
+            }
+        }
+    }
+}
+
+impl Lint for DuplicateNameLint {
+    fn name(&self) -> &'static str {
+        "duplicate_names"
+    }
+
+    fn check(&self, document: &Document) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut names = Vec::new();
+        for element in document.body_iter() {
+            match element {
+                DocumentElement::Struct(struct_) => {
+                    names.push(("struct", &struct_.name));
+                    self.check_struct(struct_, &mut diagnostics);
+                }
+                DocumentElement::Task(task) => {
+                    names.push(("task", &task.name));
+                    self.check_task(task, &mut diagnostics);
+                }
+                DocumentElement::Workflow(workflow) => {
+                    names.push(("workflow", &workflow.name));
+                    self.check_workflow(workflow, &mut diagnostics);
+                }
+                DocumentElement::Import(_) => (),
+            }
+        }
+        let mut scope = Scope::new(&mut diagnostics);
+        for (kind, name) in names {
+            scope.define(kind, name)
+        }
+        diagnostics
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_duplicate_names() {
+        let text = r#"version 1.1
+
+struct t {
+    Int a
+    String a
+}
+
+task t {
+    input {
+        Int x
+        Int x
+    }
+    Int y = 1
+    command <<< >>>
+    output {
+        Int y = 2
+    }
+}
+
+task u {
+    command <<< >>>
+}
+
+task u {
+    command <<< >>>
+}
+
+workflow wf {
+    input {
+        Int u
+    }
+    if (true) {
+        call t { input: x = 1 }
+    }
+    call u
+    call u as t
+    output {
+        Int u = 1
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let diagnostics: Vec<(String, usize, usize)> = DuplicateNameLint
+            .check(&doc)
+            .into_iter()
+            .map(|d| (d.message, d.span.start.line, d.related[0].span.start.line))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                ("field a is defined more than once".to_owned(), 4, 3),
+                ("input x is defined more than once".to_owned(), 10, 9),
+                (
+                    "output y has the same name as declaration y".to_owned(),
+                    15,
+                    12
+                ),
+                ("call u has the same name as input u".to_owned(), 34, 29),
+                ("call t is defined more than once".to_owned(), 35, 32),
+                ("task t has the same name as struct t".to_owned(), 7, 2),
+                ("task u is defined more than once".to_owned(), 23, 19),
+            ]
+        );
+    }
+}
@@ -0,0 +1,382 @@
+use crate::{
+    analysis::walk_document,
+    diagnostics::Diagnostic,
+    edit::TextEdit,
+    fix::Fix,
+    lint::Lint,
+    model::{
+        Anchor, Document, DocumentElement, Expression, Span, TaskElement, Type, VersionIdentifier,
+        WorkflowBodyElement,
+    },
+};
+use std::{collections::HashMap, ops::Deref};
+
+pub const ARGUMENT_COUNT: &str = "function_argument_count";
+pub const ARGUMENT_TYPE: &str = "function_argument_type";
+pub const FUNCTION_VERSION: &str = "function_version";
+
+/// The kind of a parameter of a standard library function.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Parameter {
+    /// `String` (or a type that coerces to it).
+    String,
+    /// `Array[P]`, where `P` is a primitive type.
+    PrimitiveArray,
+}
+
+impl Parameter {
+    fn description(&self) -> &'static str {
+        match self {
+            Self::String => "String",
+            Self::PrimitiveArray => "an Array of a primitive type",
+        }
+    }
+}
+
+/// The signature of a standard library function that is typically used to format values in
+/// placeholders.
+struct Signature {
+    name: &'static str,
+    parameters: &'static [Parameter],
+    min_version: VersionIdentifier,
+}
+
+const SIGNATURES: [Signature; 5] = [
+    Signature {
+        name: "sep",
+        parameters: &[Parameter::String, Parameter::PrimitiveArray],
+        min_version: VersionIdentifier::V1_1,
+    },
+    Signature {
+        name: "prefix",
+        parameters: &[Parameter::String, Parameter::PrimitiveArray],
+        min_version: VersionIdentifier::V1_0,
+    },
+    Signature {
+        name: "suffix",
+        parameters: &[Parameter::String, Parameter::PrimitiveArray],
+        min_version: VersionIdentifier::V1_1,
+    },
+    Signature {
+        name: "quote",
+        parameters: &[Parameter::PrimitiveArray],
+        min_version: VersionIdentifier::V1_1,
+    },
+    Signature {
+        name: "squote",
+        parameters: &[Parameter::PrimitiveArray],
+        min_version: VersionIdentifier::V1_1,
+    },
+];
+
+/// The type of an argument, to the extent that it can be determined without full type inference.
+#[derive(Debug, PartialEq)]
+enum ArgumentType<'a> {
+    /// A literal of a primitive type, or an array or other compound literal.
+    Literal(&'static str),
+    /// A reference to a declaration with the given type.
+    Declared(&'a Type),
+    Unknown,
+}
+
+fn is_primitive(type_: &Type) -> bool {
+    match type_ {
+        Type::Boolean | Type::Int | Type::Float | Type::String | Type::File => true,
+        Type::Optional(inner) => is_primitive(inner),
+        _ => false,
+    }
+}
+
+/// Returns the type of `expression`, where `scope` contains the declared types of the values
+/// that are in scope.
+fn argument_type<'a>(expression: &Expression, scope: &HashMap<&str, &'a Type>) -> ArgumentType<'a> {
+    match expression {
+        Expression::None => ArgumentType::Literal("None"),
+        Expression::Boolean(_) => ArgumentType::Literal("Boolean"),
+        Expression::Int(_) => ArgumentType::Literal("Int"),
+        Expression::Float(_) => ArgumentType::Literal("Float"),
+        Expression::String(_) => ArgumentType::Literal("String"),
+        Expression::Array(_) => ArgumentType::Literal("Array"),
+        Expression::Map(_) => ArgumentType::Literal("Map"),
+        Expression::Pair(_) => ArgumentType::Literal("Pair"),
+        Expression::Object(_) => ArgumentType::Literal("Object"),
+        Expression::Group(inner) => argument_type(inner, scope),
+        Expression::Identifier(name) => match scope.get(name.as_str()) {
+            Some(type_) => ArgumentType::Declared(type_),
+            None => ArgumentType::Unknown,
+        },
+        _ => ArgumentType::Unknown,
+    }
+}
+
+/// Returns the spans and descriptions of the type errors of `argument`, which is passed for a
+/// parameter of kind `parameter`.
+fn check_argument(
+    parameter: Parameter,
+    argument: &Anchor<Expression>,
+    scope: &HashMap<&str, &Type>,
+) -> Vec<(Span, String)> {
+    let mut errors = Vec::new();
+    let mut error = |span: &Span, actual: String| {
+        errors.push((
+            span.clone(),
+            format!("expected {}, but found {}", parameter.description(), actual),
+        ))
+    };
+    match (parameter, argument_type(argument, scope)) {
+        (_, ArgumentType::Unknown) => (),
+        (Parameter::String, ArgumentType::Literal("String")) => (),
+        (Parameter::String, ArgumentType::Literal(literal)) => {
+            error(&argument.span, literal.into())
+        }
+        (Parameter::String, ArgumentType::Declared(Type::String | Type::File)) => (),
+        (Parameter::String, ArgumentType::Declared(type_)) => {
+            error(&argument.span, type_.to_string())
+        }
+        (Parameter::PrimitiveArray, ArgumentType::Literal("Array")) => {
+            // check the elements of the array literal individually
+            if let Expression::Array(array) = argument.deref() {
+                for element in array.elements.iter() {
+                    match argument_type(element, scope) {
+                        ArgumentType::Literal(
+                            literal @ ("Array" | "Map" | "Pair" | "Object" | "None"),
+                        ) => error(&element.span, format!("an element of type {}", literal)),
+                        ArgumentType::Declared(type_) if !is_primitive(type_) => {
+                            error(&element.span, format!("an element of type {}", type_))
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+        (Parameter::PrimitiveArray, ArgumentType::Literal(literal)) => {
+            error(&argument.span, literal.into())
+        }
+        (Parameter::PrimitiveArray, ArgumentType::Declared(type_)) => match type_ {
+            Type::Array { item, .. } if is_primitive(item) => (),
+            _ => error(&argument.span, type_.to_string()),
+        },
+    }
+    errors
+}
+
+/// Returns the declared types of the values in each task and workflow of `document`, along with
+/// the span of the task or workflow. The names of declarations are unique within a task or
+/// workflow, so a single scope is sufficient for each.
+fn scopes(document: &Document) -> Vec<(&Span, HashMap<&str, &Type>)> {
+    document
+        .body
+        .iter()
+        .filter_map(|element| {
+            let mut scope: HashMap<&str, &Type> = HashMap::new();
+            match element.deref() {
+                DocumentElement::Task(task) => {
+                    for decl in task.inputs().iter().flat_map(|i| i.declarations.iter()) {
+                        scope.insert(decl.name(), decl.type_());
+                    }
+                    for element in task.body.iter() {
+                        if let TaskElement::Declaration(decl) = element.deref() {
+                            scope.insert(&decl.name, &decl.type_);
+                        }
+                    }
+                    for decl in task.outputs().iter().flat_map(|o| o.declarations.iter()) {
+                        scope.insert(&decl.name, &decl.type_);
+                    }
+                }
+                DocumentElement::Workflow(workflow) => {
+                    for decl in workflow.inputs().iter().flat_map(|i| i.declarations.iter()) {
+                        scope.insert(decl.name(), decl.type_());
+                    }
+                    for (element, _) in workflow.body_nested_iter() {
+                        if let WorkflowBodyElement::Declaration(decl) = element {
+                            scope.insert(&decl.name, &decl.type_);
+                        }
+                    }
+                    for decl in workflow
+                        .outputs()
+                        .iter()
+                        .flat_map(|o| o.declarations.iter())
+                    {
+                        scope.insert(&decl.name, &decl.type_);
+                    }
+                }
+                _ => return None,
+            }
+            Some((&element.span, scope))
+        })
+        .collect()
+}
+
+/// Checks applications of the standard library functions that are typically used to format
+/// values in placeholders (`sep`, `prefix`, `suffix`, `quote`, and `squote`), which fail at
+/// runtime if they are given the wrong arguments:
+/// * The function must be available in the document's WDL version (`sep` is a placeholder
+///   option rather than a function in WDL 1.0, and only `prefix` is available in 1.0).
+/// * The number of arguments must match the function's signature.
+/// * The arguments must have the expected types: `String` for separators and affixes, and an
+///   `Array` of primitive values for the values to format. Only the types of literals and of
+///   references to declarations are checked.
+#[derive(Debug, Default)]
+pub struct FunctionArgumentLint;
+
+impl Lint for FunctionArgumentLint {
+    fn name(&self) -> &'static str {
+        "function_arguments"
+    }
+
+    fn check(&self, document: &Document) -> Vec<Diagnostic> {
+        let version = &document.version.identifier;
+        let scopes = scopes(document);
+        let empty = HashMap::new();
+        let mut diagnostics = Vec::new();
+        walk_document(document, &mut |expression, span| {
+            let apply = match expression {
+                Expression::Apply(apply) => apply,
+                _ => return,
+            };
+            let signature = match SIGNATURES.iter().find(|s| s.name == apply.name.as_str()) {
+                Some(signature) => signature,
+                None => return,
+            };
+            if signature.min_version > **version {
+                diagnostics.push(
+                    Diagnostic::error(
+                        FUNCTION_VERSION,
+                        format!(
+                            "the {} function requires WDL version {} or later",
+                            signature.name, signature.min_version
+                        ),
+                        apply.name.span.clone(),
+                    )
+                    .with_fix(Fix::new(
+                        format!("Change version to {}", signature.min_version),
+                        vec![TextEdit::replace(
+                            version.span.clone(),
+                            signature.min_version.to_string(),
+                        )],
+                    )),
+                )
+            }
+            if apply.arguments.len() != signature.parameters.len() {
+                diagnostics.push(Diagnostic::error(
+                    ARGUMENT_COUNT,
+                    format!(
+                        "the {} function takes {} argument(s), but {} were given",
+                        signature.name,
+                        signature.parameters.len(),
+                        apply.arguments.len()
+                    ),
+                    span.clone(),
+                ));
+                return;
+            }
+            let scope = scopes
+                .iter()
+                .find(|(element_span, _)| element_span.contains_span(span))
+                .map(|(_, scope)| scope)
+                .unwrap_or(&empty);
+            for (i, (parameter, argument)) in signature
+                .parameters
+                .iter()
+                .zip(apply.arguments.iter())
+                .enumerate()
+            {
+                for (span, message) in check_argument(*parameter, argument, scope) {
+                    diagnostics.push(Diagnostic::error(
+                        ARGUMENT_TYPE,
+                        format!(
+                            "argument {} of the {} function: {}",
+                            i + 1,
+                            signature.name,
+                            message
+                        ),
+                        span,
+                    ))
+                }
+            }
+        });
+        diagnostics
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::{DocumentSource, ValidationOptions},
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_function_arguments() {
+        let text = r#"version 1.1
+
+task t {
+    input {
+        Array[String] names
+        Array[Array[Int]] matrix
+        Array[File]? files
+        Int n
+    }
+    command <<<
+        echo ~{sep(",", names)} ~{sep(n, names)} ~{quote(matrix)}
+        echo ~{prefix("-f ", files)} ~{squote([1, [2]])} ~{suffix(".txt")}
+    >>>
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let diagnostics: Vec<(String, String)> = FunctionArgumentLint
+            .check(&doc)
+            .into_iter()
+            .map(|d| (d.code, d.message))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                (
+                    ARGUMENT_TYPE.to_owned(),
+                    "argument 1 of the sep function: expected String, but found Int".to_owned()
+                ),
+                (
+                    ARGUMENT_TYPE.to_owned(),
+                    "argument 1 of the quote function: expected an Array of a primitive type, \
+                    but found Array[Array[Int]]"
+                        .to_owned()
+                ),
+                (
+                    ARGUMENT_TYPE.to_owned(),
+                    "argument 2 of the prefix function: expected an Array of a primitive type, \
+                    but found Array[File]?"
+                        .to_owned()
+                ),
+                (
+                    ARGUMENT_TYPE.to_owned(),
+                    "argument 1 of the squote function: expected an Array of a primitive type, \
+                    but found an element of type Array"
+                        .to_owned()
+                ),
+                (
+                    ARGUMENT_COUNT.to_owned(),
+                    "the suffix function takes 2 argument(s), but 1 were given".to_owned()
+                ),
+            ]
+        );
+
+        let text = "version 1.0\n\ntask t {\n    input {\n        Array[String] names\n    }\n    command <<< echo ~{sep(\",\", names)} ~{prefix(\"-\", names)} >>>\n}\n";
+        // the parser is permissive so that the version requirement is not rejected by validation
+        let doc = PestParser::new()
+            .with_validation(ValidationOptions::permissive())
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let diagnostics = FunctionArgumentLint.check(&doc);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, FUNCTION_VERSION);
+        assert_eq!(
+            diagnostics[0].message,
+            "the sep function requires WDL version 1.1 or later"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{
+    diagnostics::Diagnostic,
+    model::{Document, DocumentElement, Span},
+    navigate::find_struct,
+    workspace::Workspace,
+};
+use std::collections::HashMap;
+
+pub const UNKNOWN_ALIAS: &str = "import_alias_unknown";
+pub const ALIAS_COLLISION: &str = "import_alias_collision";
+pub const IMPORT_CYCLE: &str = "import_cycle";
+
+/// Checks the struct aliases of the imports in `document`:
+/// * The struct named by each alias must be defined in, or imported by, the imported document.
+///   Imports of documents that are not in `workspace` are not checked.
+/// * The name given to each struct by an alias must not be the name of a struct that is defined in
+///   `document`, or that is given by another alias.
+///
+/// Unlike lints, this check requires a `Workspace` in which to resolve imports.
+pub fn check_import_aliases(workspace: &Workspace, document: &Document) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut names: HashMap<&str, (&str, &Span)> = document
+        .body_iter()
+        .filter_map(|element| match element {
+            DocumentElement::Struct(struct_) => {
+                Some((struct_.name.as_str(), ("struct", &struct_.name.span)))
+            }
+            _ => None,
+        })
+        .collect();
+    for element in document.body_iter() {
+        let import = match element {
+            DocumentElement::Import(import) => import,
+            _ => continue,
+        };
+        let imported = workspace.resolve_import(document, import);
+        for alias in import.aliases.iter() {
+            if let Some(imported) = imported {
+                if find_struct(workspace, imported, alias.from.as_str(), &mut Vec::new()).is_none()
+                {
+                    diagnostics.push(Diagnostic::error(
+                        UNKNOWN_ALIAS,
+                        format!(
+                            "struct {} is not defined in imported document {}",
+                            alias.from.as_str(),
+                            import.uri.as_str()
+                        ),
+                        alias.from.span.clone(),
+                    ))
+                }
+            }
+            match names.get(alias.to.as_str()) {
+                Some((kind, span)) => diagnostics.push(
+                    Diagnostic::error(
+                        ALIAS_COLLISION,
+                        format!(
+                            "alias {} has the same name as {} {}",
+                            alias.to.as_str(),
+                            kind,
+                            alias.to.as_str()
+                        ),
+                        alias.to.span.clone(),
+                    )
+                    .with_related(
+                        (*span).clone(),
+                        format!("{} {} is defined here", kind, alias.to.as_str()),
+                    ),
+                ),
+                None => {
+                    names.insert(alias.to.as_str(), ("alias", &alias.to.span));
+                }
+            }
+        }
+    }
+    diagnostics.sort_by(|a, b| a.span.cmp(&b.span));
+    diagnostics
+}
+
+/// Checks whether any import of `document` leads back to `document`, directly or through the
+/// imports of other documents in `workspace`. Each cycle is reported at the import statement in
+/// `document`, with the full chain of documents in the message and the other import statements of
+/// the chain as related spans. (Related spans do not identify their documents; the chain in the
+/// message is in the same order.)
+pub fn check_import_cycles(workspace: &Workspace, document: &Document) -> Vec<Diagnostic> {
+    workspace
+        .import_cycles(&document.source)
+        .into_iter()
+        .map(|cycle| {
+            let first = &cycle.imports[0];
+            let mut diagnostic = Diagnostic::error(
+                IMPORT_CYCLE,
+                format!("import of {} creates an import cycle: {}", first.uri, cycle),
+                first.span.clone(),
+            );
+            for import in cycle.imports[1..].iter() {
+                diagnostic = diagnostic.with_related(
+                    import.span.clone(),
+                    format!("{} imports {}", import.source, import.uri),
+                )
+            }
+            diagnostic
+        })
+        .collect()
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_check_import_aliases() {
+        let lib = r#"version 1.1
+
+import "common.wdl"
+
+struct Sample {
+    String name
+}
+"#;
+        let common = r#"version 1.1
+
+struct Reference {
+    File fasta
+}
+"#;
+        let main = r#"version 1.1
+
+import "lib.wdl" alias Sample as Specimen alias Reference as Ref alias Missing as Other
+import "remote/lib.wdl" alias Unknown as Specimen
+
+struct Ref {
+    String id
+}
+"#;
+        let mut parser = PestParser::new();
+        let mut workspace = Workspace::new();
+        for (path, text) in [
+            ("/w/lib.wdl", lib),
+            ("/w/common.wdl", common),
+            ("/w/main.wdl", main),
+        ] {
+            workspace.insert(
+                parser
+                    .parse_text(text, DocumentSource::File(PathBuf::from(path)))
+                    .unwrap(),
+            );
+        }
+        let doc = workspace
+            .get(&DocumentSource::File(PathBuf::from("/w/main.wdl")))
+            .unwrap();
+        let diagnostics: Vec<(&str, String, usize)> = check_import_aliases(&workspace, doc)
+            .into_iter()
+            .map(|d| {
+                let related = d.related.first().map(|r| r.span.start.line).unwrap_or(0);
+                (
+                    &main[d.span.start.offset..d.span.end.offset],
+                    d.code,
+                    related,
+                )
+            })
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                ("Ref", ALIAS_COLLISION.to_owned(), 5),
+                ("Missing", UNKNOWN_ALIAS.to_owned(), 0),
+                ("Specimen", ALIAS_COLLISION.to_owned(), 2),
+            ]
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! Lints are checks for problems in documents that are syntactically valid but that violate
+//! requirements of the WDL spec that are not enforced by the parsers, or that are likely to be
+//! mistakes.
+mod calls;
+mod conditionals;
+mod directives;
+mod duplicate_names;
+mod functions;
+mod imports;
+mod objects;
+mod parameter_meta;
+mod placeholders;
+mod scatters;
+mod shell;
+
+pub use calls::check_call_inputs;
+pub use conditionals::ConditionalLint;
+pub use directives::{Suppressions, ALL_RULES, DIRECTIVE_PREFIX};
+pub use duplicate_names::DuplicateNameLint;
+pub use functions::FunctionArgumentLint;
+pub use imports::{check_import_aliases, check_import_cycles};
+pub use objects::DeprecatedObjectLint;
+pub use parameter_meta::ParameterMetaLint;
+pub use placeholders::DollarPlaceholderLint;
+pub use scatters::ScatterLint;
+pub use shell::ShellLint;
+
+#[cfg(feature = "config")]
+use crate::config::LintConfig;
+use crate::{diagnostics::Diagnostic, model::Document, trace};
+
+pub trait Lint {
+    /// Returns the name of this lint.
+    fn name(&self) -> &'static str;
+
+    /// Checks `document` and returns any problems that are found.
+    fn check(&self, document: &Document) -> Vec<Diagnostic>;
+}
+
+/// Runs a set of lints on a document.
+pub struct Linter {
+    lints: Vec<Box<dyn Lint>>,
+}
+
+impl Linter {
+    pub fn new(lints: Vec<Box<dyn Lint>>) -> Self {
+        Self { lints }
+    }
+
+    /// Adds `lint` to the set of lints that are run by this `Linter`.
+    pub fn add<L: Lint + 'static>(&mut self, lint: L) {
+        self.lints.push(Box::new(lint))
+    }
+
+    /// Runs all lints on `document` and returns the diagnostics, ordered by position in the
+    /// document. Diagnostics that are disabled by directives in comments (see `Suppressions`) are
+    /// not included.
+    pub fn check(&self, document: &Document) -> Vec<Diagnostic> {
+        let suppressions = Suppressions::from_comments(&document.comments);
+        let mut diagnostics: Vec<Diagnostic> = self
+            .lints
+            .iter()
+            .flat_map(|lint| {
+                trace::phase(lint.name(), || lint.check(document))
+                    .into_iter()
+                    .filter(|d| !suppressions.is_suppressed(lint.name(), d))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        diagnostics.sort_by(|a, b| a.span.cmp(&b.span));
+        diagnostics
+    }
+
+    /// Like `check`, but also applies the rule levels in `config` to the diagnostics.
+    #[cfg(feature = "config")]
+    pub fn check_with_config(&self, document: &Document, config: &LintConfig) -> Vec<Diagnostic> {
+        let suppressions = Suppressions::from_comments(&document.comments);
+        let mut diagnostics: Vec<Diagnostic> = self
+            .lints
+            .iter()
+            .flat_map(|lint| {
+                let diagnostics = trace::phase(lint.name(), || lint.check(document))
+                    .into_iter()
+                    .filter(|d| !suppressions.is_suppressed(lint.name(), d))
+                    .collect();
+                config.apply(lint.name(), diagnostics)
+            })
+            .collect();
+        diagnostics.sort_by(|a, b| a.span.cmp(&b.span));
+        diagnostics
+    }
+}
+
+impl Default for Linter {
+    /// Returns a `Linter` with all lints enabled with their default settings.
+    fn default() -> Self {
+        Self::new(vec![
+            Box::new(DuplicateNameLint),
+            Box::new(ParameterMetaLint::default()),
+            Box::new(ShellLint::default()),
+            Box::new(ConditionalLint::default()),
+            Box::new(DeprecatedObjectLint),
+            Box::new(FunctionArgumentLint),
+            Box::new(ScatterLint::default()),
+            Box::new(DollarPlaceholderLint),
+        ])
+    }
+}
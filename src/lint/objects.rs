@@ -0,0 +1,148 @@
+use crate::{
+    diagnostics::Diagnostic,
+    edit::TextEdit,
+    fix::Fix,
+    lint::Lint,
+    model::{
+        BoundDeclaration, Document, Expression, InputDeclaration, ObjectKind, Span, TaskElement,
+        Type, VersionIdentifier, WorkflowElement, WorkflowNestedElement,
+    },
+    tree::{walk, Node},
+};
+use std::{collections::BTreeMap, ops::Deref};
+
+pub const DEPRECATED_OBJECT: &str = "deprecated_object";
+
+/// Checks WDL 1.1 (and later) documents for uses of `Object`, which is deprecated:
+/// * `Object` types of declarations and struct fields.
+/// * `object { ... }` literals. If the literal is the value of a declaration with a struct type,
+///   the diagnostic has a fix that replaces it with a struct literal.
+///
+/// Documents with earlier versions are not checked, since struct literals are not available.
+#[derive(Debug, Default)]
+pub struct DeprecatedObjectLint;
+
+/// Returns the bound declaration of `node`, if it is one.
+fn bound_declaration(node: Node<'_>) -> Option<&BoundDeclaration> {
+    match node {
+        Node::BoundDeclaration(decl) => Some(decl),
+        Node::InputDeclaration(decl) => match decl.deref() {
+            InputDeclaration::Bound(decl) => Some(decl),
+            InputDeclaration::Unbound(_) => None,
+        },
+        Node::TaskElement(element) => match element.deref() {
+            TaskElement::Declaration(decl) => Some(decl),
+            _ => None,
+        },
+        Node::WorkflowElement(element) => match element.deref() {
+            WorkflowElement::Declaration(decl) => Some(decl),
+            _ => None,
+        },
+        Node::WorkflowNestedElement(element) => match element.deref() {
+            WorkflowNestedElement::Declaration(decl) => Some(decl),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl Lint for DeprecatedObjectLint {
+    fn name(&self) -> &'static str {
+        "deprecated_object"
+    }
+
+    fn check(&self, document: &Document) -> Vec<Diagnostic> {
+        if *document.version.identifier < VersionIdentifier::V1_1 {
+            return Vec::new();
+        }
+        let mut diagnostics = Vec::new();
+        // the struct type of each declaration's expression, by the span of the expression
+        let mut struct_types: BTreeMap<&Span, &str> = BTreeMap::new();
+        walk(document, &mut |node, _| {
+            if let Some(decl) = bound_declaration(node) {
+                if let Type::User(name) = decl.type_.deref() {
+                    struct_types.insert(&decl.expression.span, name.as_str());
+                }
+            }
+            match node {
+                Node::Type(type_) if matches!(type_.deref(), Type::Object) => {
+                    diagnostics.push(Diagnostic::warning(
+                        DEPRECATED_OBJECT,
+                        "the Object type is deprecated; use a struct type instead",
+                        type_.span.clone(),
+                    ))
+                }
+                Node::Expression(expression) => {
+                    let object = match expression.deref() {
+                        Expression::Object(object) if object.kind == ObjectKind::Object => object,
+                        _ => return,
+                    };
+                    let mut diagnostic = Diagnostic::warning(
+                        DEPRECATED_OBJECT,
+                        "object literals are deprecated; use a struct literal instead",
+                        expression.span.clone(),
+                    );
+                    if let Some(name) = struct_types.get(&expression.span) {
+                        diagnostic = diagnostic.with_fix(Fix::new(
+                            format!("Replace with {} struct literal", name),
+                            vec![TextEdit::replace(
+                                object.type_name.span.clone(),
+                                (*name).to_owned(),
+                            )],
+                        ))
+                    }
+                    diagnostics.push(diagnostic)
+                }
+                _ => (),
+            }
+        });
+        diagnostics
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        fix::apply_fixes,
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_deprecated_object() {
+        let text = r#"version 1.1
+
+struct Sample {
+    String id
+    Object attributes
+}
+
+workflow wf {
+    Sample s = object { id: "a", attributes: object {} }
+    Sample t = Sample { id: "b", attributes: object {} }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let diagnostics = DeprecatedObjectLint.check(&doc);
+        let locations: Vec<(usize, usize, bool)> = diagnostics
+            .iter()
+            .map(|d| (d.span.start.line, d.span.start.column, !d.fixes.is_empty()))
+            .collect();
+        assert_eq!(
+            locations,
+            vec![(4, 4, false), (8, 15, true), (8, 45, false), (9, 45, false)]
+        );
+        let fixed = apply_fixes(text, diagnostics.iter().flat_map(|d| d.fixes.first())).unwrap();
+        assert!(fixed.contains("Sample s = Sample { id: \"a\", attributes: object {} }"));
+
+        let text = "version 1.0\n\nworkflow wf {\n    Object o = object { a: 1 }\n}\n";
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        assert!(DeprecatedObjectLint.check(&doc).is_empty());
+    }
+}
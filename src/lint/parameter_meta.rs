@@ -0,0 +1,235 @@
+use crate::{
+    diagnostics::Diagnostic,
+    edit::TextEdit,
+    fix::Fix,
+    lint::Lint,
+    model::{
+        Anchor, Document, DocumentElement, Input, MetaAttribute, Output, TaskElement,
+        WorkflowElement,
+    },
+};
+use std::ops::Deref;
+
+pub const UNKNOWN_PARAMETER: &str = "parameter_meta_unknown";
+pub const MISSING_PARAMETER: &str = "parameter_meta_missing";
+
+/// Checks that every key in a task's or workflow's `parameter_meta` section corresponds to an
+/// input or output, as required by the WDL spec. Optionally also checks that every input has a
+/// `parameter_meta` entry.
+#[derive(Debug, Default)]
+pub struct ParameterMetaLint {
+    /// Whether to warn about inputs that do not have a `parameter_meta` entry.
+    pub require_all_inputs: bool,
+}
+
+impl ParameterMetaLint {
+    fn check_element(
+        &self,
+        kind: &str,
+        name: &str,
+        input: Option<&Input>,
+        output: Option<&Output>,
+        attributes: Option<&Vec<Anchor<MetaAttribute>>>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let inputs: Vec<&Anchor<String>> = input
+            .map(|input| input.declarations.iter().map(|decl| decl.name()).collect())
+            .unwrap_or_default();
+        let outputs: Vec<&str> = output
+            .map(|output| {
+                output
+                    .declarations
+                    .iter()
+                    .map(|decl| decl.name.as_str())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let attributes: Vec<&Anchor<MetaAttribute>> = attributes
+            .map(|attributes| attributes.iter().collect())
+            .unwrap_or_default();
+        for attribute in attributes.iter() {
+            let key = attribute.name.as_str();
+            if !inputs.iter().any(|i| i.as_str() == key) && !outputs.contains(&key) {
+                diagnostics.push(
+                    Diagnostic::error(
+                        UNKNOWN_PARAMETER,
+                        format!(
+                            "parameter_meta key {} does not correspond to an input or output of \
+                             {} {}",
+                            key, kind, name
+                        ),
+                        attribute.span.clone(),
+                    )
+                    .with_fix(Fix::new(
+                        format!("Remove parameter_meta key {}", key),
+                        vec![TextEdit::delete(attribute.span.clone())],
+                    )),
+                )
+            }
+        }
+        if self.require_all_inputs {
+            for input in inputs.iter() {
+                if !attributes.iter().any(|a| a.name.as_str() == input.as_str()) {
+                    let mut diagnostic = Diagnostic::warning(
+                        MISSING_PARAMETER,
+                        format!(
+                            "input {} of {} {} does not have a parameter_meta entry",
+                            input.as_str(),
+                            kind,
+                            name
+                        ),
+                        input.span.clone(),
+                    );
+                    // add an empty entry after the last one, with the same indentation
+                    if let Some(last) = attributes.last() {
+                        diagnostic = diagnostic.with_fix(Fix::new(
+                            format!("Add parameter_meta key {}", input.as_str()),
+                            vec![TextEdit::insert(
+                                last.span.end.clone(),
+                                format!(
+                                    "\n{}{}: \"\"",
+                                    " ".repeat(last.span.start.column),
+                                    input.as_str()
+                                ),
+                            )],
+                        ));
+                    }
+                    diagnostics.push(diagnostic)
+                }
+            }
+        }
+    }
+}
+
+impl Lint for ParameterMetaLint {
+    fn name(&self) -> &'static str {
+        "parameter_meta"
+    }
+
+    fn check(&self, document: &Document) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for element in document.body_iter() {
+            match element {
+                DocumentElement::Task(task) => {
+                    let mut input = None;
+                    let mut output = None;
+                    let mut attributes = None;
+                    for element in task.body.iter() {
+                        match element.deref() {
+                            TaskElement::Input(i) => input = Some(i),
+                            TaskElement::Output(o) => output = Some(o),
+                            TaskElement::ParameterMeta(meta) => attributes = Some(&meta.attributes),
+                            _ => (),
+                        }
+                    }
+                    self.check_element(
+                        "task",
+                        &task.name,
+                        input,
+                        output,
+                        attributes,
+                        &mut diagnostics,
+                    );
+                }
+                DocumentElement::Workflow(workflow) => {
+                    let mut input = None;
+                    let mut output = None;
+                    let mut attributes = None;
+                    for element in workflow.body.iter() {
+                        match element.deref() {
+                            WorkflowElement::Input(i) => input = Some(i),
+                            WorkflowElement::Output(o) => output = Some(o),
+                            WorkflowElement::ParameterMeta(meta) => {
+                                attributes = Some(&meta.attributes)
+                            }
+                            _ => (),
+                        }
+                    }
+                    self.check_element(
+                        "workflow",
+                        &workflow.name,
+                        input,
+                        output,
+                        attributes,
+                        &mut diagnostics,
+                    );
+                }
+                _ => (),
+            }
+        }
+        diagnostics
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        fix::apply_fixes,
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser, WdlParserError},
+    };
+    use error_stack::Result;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parameter_meta() -> Result<(), WdlParserError> {
+        let text = r#"
+version 1.1
+
+workflow wf {
+    input {
+        String a
+        Int b
+    }
+    output {
+        String d = a
+    }
+    parameter_meta {
+        a: "documented"
+        c: "not an input"
+        d: "an output"
+    }
+}
+"#;
+        let doc = PestParser::new().parse_text(text, DocumentSource::Unknown)?;
+        let codes = |lint: ParameterMetaLint| -> Vec<(String, usize, String)> {
+            lint.check(&doc)
+                .into_iter()
+                .map(|d| (d.code, d.span.start.line, d.message))
+                .collect()
+        };
+        let unknown = (
+            UNKNOWN_PARAMETER.to_owned(),
+            13,
+            "parameter_meta key c does not correspond to an input or output of workflow wf"
+                .to_owned(),
+        );
+        assert_eq!(codes(ParameterMetaLint::default()), vec![unknown.clone()]);
+        assert_eq!(
+            codes(ParameterMetaLint {
+                require_all_inputs: true
+            }),
+            vec![
+                unknown,
+                (
+                    MISSING_PARAMETER.to_owned(),
+                    6,
+                    "input b of workflow wf does not have a parameter_meta entry".to_owned()
+                )
+            ]
+        );
+        let diagnostics = ParameterMetaLint {
+            require_all_inputs: true,
+        }
+        .check(&doc);
+        let fixed = apply_fixes(text, diagnostics.iter().flat_map(|d| d.fixes.first())).unwrap();
+        // the unknown key is removed, the output is kept, and the missing key is added after
+        // the last key
+        assert!(fixed.contains(
+            "parameter_meta {\n        a: \"documented\"\n        \n        d: \"an output\"\n        \
+             b: \"\"\n    }"
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{
+    diagnostics::Diagnostic,
+    fix,
+    lint::Lint,
+    model::{
+        CommandStyle, Document, PlaceholderStyle, Span, StringPart, TaskElement, VersionIdentifier,
+    },
+    tree::{walk, Node},
+};
+use std::ops::Deref;
+
+pub const DOLLAR_PLACEHOLDER: &str = "dollar_placeholder";
+
+/// Checks WDL 1.1 (and later) documents for `${...}` placeholders, which are deprecated, in
+/// strings and in commands with braces. Each diagnostic has a fix that replaces the placeholder
+/// with the equivalent `~{...}` placeholder.
+///
+/// Only the placeholders of the model are rewritten: within a heredoc command (`<<< >>>`),
+/// `${...}` is passed to the shell unchanged, so it is never reported or rewritten by this lint
+/// (see `ValidationOptions::heredoc_placeholders` for the rule that applies to it instead).
+///
+/// Documents with earlier versions are not checked, since `${...}` is not deprecated.
+#[derive(Debug, Default)]
+pub struct DollarPlaceholderLint;
+
+impl Lint for DollarPlaceholderLint {
+    fn name(&self) -> &'static str {
+        "dollar_placeholder"
+    }
+
+    fn check(&self, document: &Document) -> Vec<Diagnostic> {
+        if *document.version.identifier < VersionIdentifier::V1_1 {
+            return Vec::new();
+        }
+        let mut diagnostics = Vec::new();
+        // the spans of the commands with braces, which are visited before their parts
+        let mut brace_commands: Vec<&Span> = Vec::new();
+        walk(document, &mut |node, _| match node {
+            Node::TaskElement(element) => {
+                if let TaskElement::Command(command) = element.deref() {
+                    if command.style == CommandStyle::Braces {
+                        brace_commands.push(&element.span)
+                    }
+                }
+            }
+            Node::StringPart(part) => {
+                if !matches!(
+                    part.deref(),
+                    StringPart::Placeholder(_, PlaceholderStyle::Dollar)
+                ) {
+                    return;
+                }
+                let in_command = brace_commands
+                    .iter()
+                    .any(|span| span.contains_span(&part.span));
+                let message = if in_command {
+                    "${...} placeholders are deprecated, and are not placeholders if the command \
+                     is converted to a heredoc command (<<< >>>); use ~{...} instead"
+                } else {
+                    "${...} placeholders are deprecated; use ~{...} instead"
+                };
+                diagnostics.push(
+                    Diagnostic::warning(DOLLAR_PLACEHOLDER, message, part.span.clone())
+                        .with_fix(fix::tilde_placeholder(&part.span)),
+                )
+            }
+            _ => (),
+        });
+        diagnostics
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        fix::apply_fixes,
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_dollar_placeholder() {
+        let text = r#"version 1.1
+
+task greet {
+    input {
+        String name
+    }
+    String greeting = "hello ${name}, ~{"${name}"}"
+    command {
+        echo ${greeting} ~{name} $HOME
+    }
+}
+
+task list {
+    input {
+        String dir
+    }
+    command <<<
+        for f in ~{dir}/*; do echo ${f}; done
+    >>>
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let diagnostics = DollarPlaceholderLint.check(&doc);
+        let found: Vec<(&str, bool)> = diagnostics
+            .iter()
+            .map(|d| {
+                (
+                    &text[d.span.start.offset..d.span.end.offset],
+                    d.message.contains("heredoc"),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("${name}", false),
+                ("${name}", false),
+                ("${greeting}", true)
+            ]
+        );
+        let fixed = apply_fixes(text, diagnostics.iter().flat_map(|d| d.fixes.iter())).unwrap();
+        assert_eq!(
+            fixed,
+            text.replace(
+                "hello ${name}, ~{\"${name}\"}",
+                "hello ~{name}, ~{\"~{name}\"}"
+            )
+            .replace("echo ${greeting}", "echo ~{greeting}")
+        );
+        assert!(DollarPlaceholderLint
+            .check(
+                &PestParser::new()
+                    .parse_text(&fixed, DocumentSource::Unknown)
+                    .unwrap()
+            )
+            .is_empty());
+
+        let text = "version 1.0\n\nworkflow w {\n    String s = \"${1}\"\n}\n";
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        assert!(DollarPlaceholderLint.check(&doc).is_empty());
+    }
+}
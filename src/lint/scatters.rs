@@ -0,0 +1,218 @@
+use crate::{
+    analysis::{resolve_scopes, Scopes, SymbolKind},
+    diagnostics::Diagnostic,
+    lint::Lint,
+    model::{
+        Anchor, Document, DocumentElement, Scatter, Span, Workflow, WorkflowElement,
+        WorkflowNestedElement,
+    },
+};
+use std::ops::Deref;
+
+pub const SCATTER_VARIABLE_SHADOWING: &str = "scatter_variable_shadowing";
+pub const SCATTER_VARIABLE_OUT_OF_SCOPE: &str = "scatter_variable_out_of_scope";
+
+/// Checks the scatters of workflows for likely mistakes:
+/// * Scatter variables with the same name as an input, declaration, or call of the workflow
+///   (outside the body of the scatter), or as the variable of an enclosing scatter, which are
+///   hidden within the body of the scatter.
+/// * References to scatter variables outside the bodies of their scatters, where they are not
+///   defined.
+#[derive(Debug)]
+pub struct ScatterLint {
+    pub shadowing: bool,
+    pub out_of_scope_references: bool,
+}
+
+impl Default for ScatterLint {
+    fn default() -> Self {
+        Self {
+            shadowing: true,
+            out_of_scope_references: true,
+        }
+    }
+}
+
+/// Collects the scatters nested within `body`, with the span of each scatter.
+fn collect_scatters<'a>(
+    body: &'a [Anchor<WorkflowNestedElement>],
+    scatters: &mut Vec<(&'a Scatter, &'a Span)>,
+) {
+    for element in body.iter() {
+        match element.deref() {
+            WorkflowNestedElement::Scatter(scatter) => {
+                scatters.push((scatter, &element.span));
+                collect_scatters(&scatter.body, scatters)
+            }
+            WorkflowNestedElement::Conditional(conditional) => {
+                collect_scatters(&conditional.body, scatters)
+            }
+            _ => (),
+        }
+    }
+}
+
+impl ScatterLint {
+    fn check_workflow(
+        &self,
+        workflow: &Workflow,
+        span: &Span,
+        scopes: &Scopes,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let mut scatters = Vec::new();
+        for element in workflow.body.iter() {
+            match element.deref() {
+                WorkflowElement::Scatter(scatter) => {
+                    scatters.push((scatter, &element.span));
+                    collect_scatters(&scatter.body, &mut scatters)
+                }
+                WorkflowElement::Conditional(conditional) => {
+                    collect_scatters(&conditional.body, &mut scatters)
+                }
+                _ => (),
+            }
+        }
+        if self.shadowing {
+            for (scatter, scatter_span) in scatters.iter() {
+                let name = scatter.name.as_str();
+                let shadowed = scopes.symbols.iter().find(|symbol| {
+                    symbol.name == name
+                        && span.contains_span(&symbol.span)
+                        && match symbol.kind {
+                            SymbolKind::Input | SymbolKind::Declaration | SymbolKind::Call => {
+                                !scatter_span.contains_span(&symbol.span)
+                            }
+                            SymbolKind::ScatterVariable => {
+                                symbol.span != scatter.name.span
+                                    && scatters.iter().any(|(outer, outer_span)| {
+                                        outer.name.span == symbol.span
+                                            && outer_span.contains_span(scatter_span)
+                                    })
+                            }
+                            SymbolKind::Output => false,
+                        }
+                });
+                if let Some(shadowed) = shadowed {
+                    diagnostics.push(
+                        Diagnostic::warning(
+                            SCATTER_VARIABLE_SHADOWING,
+                            format!(
+                                "scatter variable {} hides another element with the same name \
+                                 within the body of the scatter",
+                                name
+                            ),
+                            scatter.name.span.clone(),
+                        )
+                        .with_related(
+                            shadowed.span.clone(),
+                            format!("{} is also declared here", name),
+                        ),
+                    )
+                }
+            }
+        }
+        if self.out_of_scope_references {
+            for reference in scopes.references.iter() {
+                if reference.symbol.is_some() || !span.contains_span(&reference.span) {
+                    continue;
+                }
+                let scatter = scatters.iter().find(|(scatter, scatter_span)| {
+                    scatter.name.as_str() == reference.name
+                        && !scatter_span.contains_span(&reference.span)
+                });
+                if let Some((scatter, _)) = scatter {
+                    diagnostics.push(
+                        Diagnostic::error(
+                            SCATTER_VARIABLE_OUT_OF_SCOPE,
+                            format!(
+                                "scatter variable {} is referenced outside the body of its \
+                                 scatter, where it is not defined",
+                                reference.name
+                            ),
+                            reference.span.clone(),
+                        )
+                        .with_related(
+                            scatter.name.span.clone(),
+                            format!("{} is declared here", reference.name),
+                        ),
+                    )
+                }
+            }
+        }
+    }
+}
+
+impl Lint for ScatterLint {
+    fn name(&self) -> &'static str {
+        "scatters"
+    }
+
+    fn check(&self, document: &Document) -> Vec<Diagnostic> {
+        let scopes = resolve_scopes(document);
+        let mut diagnostics = Vec::new();
+        for element in document.body.iter() {
+            if let DocumentElement::Workflow(workflow) = element.deref() {
+                self.check_workflow(workflow, &element.span, &scopes, &mut diagnostics)
+            }
+        }
+        diagnostics
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_scatter_lint() {
+        let text = r#"version 1.1
+
+workflow wf {
+    input {
+        Array[String] samples
+        String sample = "x"
+    }
+    scatter (sample in samples) {
+        scatter (sample in [1, 2]) {
+            call count { input: n = sample }
+        }
+    }
+    scatter (i in range(2)) {
+        Int j = i
+    }
+    Int last = i
+    output {
+        Array[Int] js = j
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let diagnostics = ScatterLint::default().check(&doc);
+        let found: Vec<(&str, &str, usize)> = diagnostics
+            .iter()
+            .map(|d| {
+                (
+                    d.code.as_str(),
+                    &text[d.span.start.offset..d.span.end.offset],
+                    d.related[0].span.start.line,
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (SCATTER_VARIABLE_SHADOWING, "sample", 5),
+                (SCATTER_VARIABLE_SHADOWING, "sample", 5),
+                (SCATTER_VARIABLE_OUT_OF_SCOPE, "i", 12),
+            ]
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use crate::{
+    diagnostics::Diagnostic,
+    edit::TextEdit,
+    fix::Fix,
+    lint::Lint,
+    model::{Command, Document, DocumentElement, Expression, StringPart, Task, TaskElement, Type},
+    shell::ShellTemplate,
+};
+use regex::Regex;
+use std::{collections::HashMap, ops::Deref};
+
+pub const UNQUOTED_PLACEHOLDER: &str = "shell_unquoted_placeholder";
+pub const CD_WITHOUT_ERREXIT: &str = "shell_cd_without_errexit";
+
+/// Checks task commands for common shell scripting problems. Commands are analyzed as bash
+/// scripts with their placeholders replaced by markers (see `Command::shell_template`):
+/// * Placeholders that are not quoted, which break if the value contains whitespace. Only
+///   placeholders that refer to a declaration or member whose type is not numeric or `Boolean`
+///   are checked, since other expressions (e.g. calls to `sep`) are commonly intended to expand
+///   to multiple words.
+/// * Uses of `cd` without `set -e` (or an equivalent) earlier in the command, unless the status
+///   of `cd` is checked with `||` or `&&`, since the rest of the command would run in the wrong
+///   directory if `cd` fails.
+#[derive(Debug)]
+pub struct ShellLint {
+    pub unquoted_placeholders: bool,
+    pub cd_without_errexit: bool,
+}
+
+impl Default for ShellLint {
+    fn default() -> Self {
+        Self {
+            unquoted_placeholders: true,
+            cd_without_errexit: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Quoting {
+    None,
+    Single,
+    Double,
+}
+
+/// Returns the quoting state at the start of each placeholder in `template`, in order.
+fn placeholder_quoting(template: &ShellTemplate) -> Vec<Quoting> {
+    let mut states = Vec::new();
+    let mut state = Quoting::None;
+    let mut chars = template.text.char_indices().peekable();
+    let mut placeholders = template.placeholders().peekable();
+    let mut word_start = true;
+    while let Some((offset, c)) = chars.next() {
+        if let Some(placeholder) = placeholders.peek() {
+            if placeholder.range.start == offset {
+                states.push(state);
+                placeholders.next();
+            }
+        }
+        match (state, c) {
+            (Quoting::None, '\'') => state = Quoting::Single,
+            (Quoting::None, '"') => state = Quoting::Double,
+            (Quoting::Single, '\'') | (Quoting::Double, '"') => state = Quoting::None,
+            (Quoting::None, '\\') | (Quoting::Double, '\\') => {
+                chars.next();
+            }
+            (Quoting::None, '#') if word_start => {
+                // skip the comment, but not any placeholders within it
+                while let Some((offset, c)) = chars.peek() {
+                    if *c == '\n' {
+                        break;
+                    }
+                    if let Some(placeholder) = placeholders.peek() {
+                        if placeholder.range.start == *offset {
+                            states.push(Quoting::Single);
+                            placeholders.next();
+                        }
+                    }
+                    chars.next();
+                }
+            }
+            _ => (),
+        }
+        word_start = state == Quoting::None && (c.is_whitespace() || ";&|()".contains(c));
+    }
+    states
+}
+
+impl ShellLint {
+    fn check_task(&self, task: &Task, diagnostics: &mut Vec<Diagnostic>) {
+        let mut types: HashMap<&str, &Type> = HashMap::new();
+        for element in task.body.iter() {
+            match element.deref() {
+                TaskElement::Input(input) => {
+                    for decl in input.declarations.iter() {
+                        types.insert(decl.name().as_str(), decl.type_());
+                    }
+                }
+                TaskElement::Declaration(decl) => {
+                    types.insert(decl.name.as_str(), &decl.type_);
+                }
+                _ => (),
+            }
+        }
+        for element in task.body.iter() {
+            if let TaskElement::Command(command) = element.deref() {
+                let template = command.shell_template();
+                if self.unquoted_placeholders {
+                    self.check_placeholders(task, command, &template, &types, diagnostics)
+                }
+                if self.cd_without_errexit {
+                    self.check_cd(task, &template, diagnostics)
+                }
+            }
+        }
+    }
+
+    fn check_placeholders(
+        &self,
+        task: &Task,
+        command: &Command,
+        template: &ShellTemplate,
+        types: &HashMap<&str, &Type>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let placeholders = command
+            .parts
+            .iter()
+            .filter(|part| matches!(***part, StringPart::Placeholder(_, _)));
+        for (part, quoting) in placeholders.zip(placeholder_quoting(template)) {
+            let expression = match part.deref() {
+                StringPart::Placeholder(expression, _) => expression,
+                _ => continue,
+            };
+            let name = match expression {
+                Expression::Identifier(name) => name.as_str(),
+                Expression::Access(access) => match &**access.collection {
+                    Expression::Identifier(name) => name.as_str(),
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            if quoting != Quoting::None || is_scalar(types.get(name).copied(), expression) {
+                continue;
+            }
+            diagnostics.push(
+                Diagnostic::warning(
+                    UNQUOTED_PLACEHOLDER,
+                    format!(
+                        "placeholder {} in the command of task {} is not quoted",
+                        expression,
+                        task.name.as_str()
+                    ),
+                    part.span.clone(),
+                )
+                .with_fix(Fix::new(
+                    "Quote placeholder",
+                    vec![
+                        TextEdit::insert(part.span.start.clone(), "\""),
+                        TextEdit::insert(part.span.end.clone(), "\""),
+                    ],
+                )),
+            )
+        }
+    }
+
+    fn check_cd(&self, task: &Task, template: &ShellTemplate, diagnostics: &mut Vec<Diagnostic>) {
+        let errexit = Regex::new(r"^set\s+(-[a-zA-Z]*e|-o\s+errexit)").unwrap();
+        let mut offset = 0;
+        for line in template.text.split_inclusive('\n') {
+            let mut statement_offset = offset;
+            for statement in line.split(';') {
+                let trimmed = statement.trim_start();
+                let start = statement_offset + (statement.len() - trimmed.len());
+                statement_offset += statement.len() + 1;
+                if trimmed.starts_with('#') {
+                    break;
+                }
+                if errexit.is_match(trimmed) {
+                    return;
+                }
+                let trimmed = trimmed.trim_end();
+                if (trimmed == "cd" || trimmed.starts_with("cd "))
+                    && !trimmed.contains("||")
+                    && !trimmed.contains("&&")
+                {
+                    if let Some(span) = template.span(start..start + trimmed.len()) {
+                        diagnostics.push(Diagnostic::warning(
+                            CD_WITHOUT_ERREXIT,
+                            format!(
+                                "the command of task {} uses cd without set -e, so it continues \
+                                 in the wrong directory if cd fails",
+                                task.name.as_str()
+                            ),
+                            span,
+                        ))
+                    }
+                }
+            }
+            offset += line.len();
+        }
+    }
+}
+
+/// Returns whether the value of `expression`, which refers to a declaration of type `type_`, is
+/// a number or `Boolean`. The types of members are not known, so they are assumed not to be.
+fn is_scalar(type_: Option<&Type>, expression: &Expression) -> bool {
+    let type_ = match (type_, expression) {
+        (Some(Type::Optional(inner)), Expression::Identifier(_)) => inner.deref().deref(),
+        (Some(type_), Expression::Identifier(_)) => type_,
+        _ => return false,
+    };
+    matches!(type_, Type::Int | Type::Float | Type::Boolean)
+}
+
+impl Lint for ShellLint {
+    fn name(&self) -> &'static str {
+        "shell"
+    }
+
+    fn check(&self, document: &Document) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for element in document.body_iter() {
+            if let DocumentElement::Task(task) = element {
+                self.check_task(task, &mut diagnostics)
+            }
+        }
+        diagnostics
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        fix::apply_fixes,
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_shell_lint() {
+        let text = r#"version 1.1
+
+task t {
+    input {
+        File reads
+        String sample
+        Int threads
+        Array[String] flags
+    }
+    command <<<
+        # align ~{reads}
+        cd ~{sample} || exit 1
+        cd /tmp
+        bwa mem -t ~{threads} ~{sep(" ", flags)} '~{reads}' > "~{sample}.bam"
+        cat ~{reads}
+        set -euo pipefail
+        cd /
+    >>>
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let diagnostics = ShellLint::default().check(&doc);
+        let found: Vec<(&str, &str)> = diagnostics
+            .iter()
+            .map(|d| {
+                (
+                    d.code.as_str(),
+                    &text[d.span.start.offset..d.span.end.offset],
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (UNQUOTED_PLACEHOLDER, "~{sample}"),
+                (UNQUOTED_PLACEHOLDER, "~{reads}"),
+                (CD_WITHOUT_ERREXIT, "cd /tmp"),
+            ]
+        );
+        let fixed = apply_fixes(text, diagnostics.iter().flat_map(|d| d.fixes.iter())).unwrap();
+        assert!(fixed.contains("cat \"~{reads}\""));
+        let lint = ShellLint {
+            cd_without_errexit: false,
+            ..ShellLint::default()
+        };
+        assert_eq!(lint.check(&doc).len(), 2);
+    }
+}
@@ -0,0 +1,397 @@
+//! Pattern matching of nested expressions. The operands and arguments of an `Expression` are
+//! `Anchor`s (and are often boxed), so matching even a shallow expression such as `x + 1` with
+//! `match` requires a nested `match` and `deref` at each level. `wdl_match!` matches an
+//! expression against patterns that are written as if the expression were a plain enum:
+//!
+//! ```
+//! use wdl::{model::Expression, wdl_match};
+//!
+//! /// Returns the name of the variable that is incremented by `expression`, if it is `x + 1`.
+//! fn incremented(expression: &Expression) -> Option<&str> {
+//!     wdl_match!(expression,
+//!         Binary(Add, Identifier(name), Int(1)) => Some(name),
+//!         Group(Binary(Add, Identifier(name), Int(1))) => Some(name),
+//!         _ => None,
+//!     )
+//! }
+//! ```
+//!
+//! See also the combinators of `Expression` (e.g. `Expression::as_apply`), which are more
+//! convenient for matching a single level.
+
+/// Matches an expression (an `&Expression`, or anything that dereferences to one, such as an
+/// `&Anchor<Expression>`) against a list of arms of the form `pattern => body` or
+/// `pattern if guard => body`, like `match`, and evaluates to the body of the first arm whose
+/// pattern matches and whose guard (if any) is true. The last arm may be `_ => body`; otherwise,
+/// the bodies must evaluate to `()`, which is also the value if no arm matches.
+///
+/// Patterns are written as `Variant(...)`, where `Variant` is a variant of `Expression`, and
+/// match the expression as follows:
+/// * `_` matches any expression, and `name` matches any expression and binds it to `name`. An
+///   operand or argument is bound as an `&Anchor<Expression>`, so its span is available.
+/// * `None` matches the `None` literal.
+/// * `Boolean(p)`, `Int(p)`, `Float(p)`, and `Identifier(p)` match a literal or identifier
+///   whose value (a `bool`, `i64`, `f64`, or `&str`, respectively) matches `p`, which is `_`, a
+///   name to bind to the value, or a literal to compare the value with.
+/// * `String(p)`, `Map(p)`, and `Object(p)` match a literal of the kind, which is bound by `p`
+///   (`_` or a name).
+/// * `Unary(op, p)` and `Binary(op, p, p)` match an operation whose operator is the variant `op`
+///   of `UnaryOperator` or `BinaryOperator` (or any operator if `op` is `_`), and whose operands
+///   match the patterns `p`.
+/// * `Ternary(p, p, p)`, `Pair(p, p)`, and `Group(p)` match the expressions of the kind whose
+///   operands match the patterns `p`.
+/// * `Apply(name, args)` matches a call of a function whose name matches `name` (`_`, a name to
+///   bind, or a string literal), and `Array(args)` matches an array literal. `args` is `_`, a
+///   name to bind to the slice of arguments (or elements), or a list of patterns in brackets
+///   that the arguments must match one-to-one, e.g. `[Identifier(x), _]`. A list may end with
+///   `..` to match any remaining arguments.
+/// * `Access(p, accesses)` matches an access (e.g. `a.b[0]`) of a collection that matches `p`,
+///   where `accesses` is `_` or a name to bind to the slice of access operations.
+///
+/// Unlike `match`, the arms are not checked for exhaustiveness, and a body cannot use `break`
+/// or `continue` to refer to loops outside the macro.
+#[macro_export]
+macro_rules! wdl_match {
+    // arms
+    (@arms $label:lifetime, $e:ident, , $($rest:tt)*) => {
+        $crate::wdl_match!(@arms $label, $e, $($rest)*)
+    };
+    (@arms $label:lifetime, $e:ident $(,)?) => {
+        ()
+    };
+    (@arms $label:lifetime, $e:ident, _ => $body:expr $(,)?) => {
+        $body
+    };
+    (@arms $label:lifetime, $e:ident,
+        $v:ident ( $($a:tt)* ) $(if $guard:expr)? => $body:block $($rest:tt)*
+    ) => {{
+        $crate::wdl_match!(@arm $label, $e, [$v ( $($a)* )], [$($guard)?], $body);
+        $crate::wdl_match!(@arms $label, $e, $($rest)*)
+    }};
+    (@arms $label:lifetime, $e:ident,
+        $v:ident ( $($a:tt)* ) $(if $guard:expr)? => $body:expr $(, $($rest:tt)*)?
+    ) => {{
+        $crate::wdl_match!(@arm $label, $e, [$v ( $($a)* )], [$($guard)?], $body);
+        $crate::wdl_match!(@arms $label, $e, $($($rest)*)?)
+    }};
+    (@arms $label:lifetime, $e:ident,
+        $p:tt $(if $guard:expr)? => $body:block $($rest:tt)*
+    ) => {{
+        $crate::wdl_match!(@arm $label, $e, [$p], [$($guard)?], $body);
+        $crate::wdl_match!(@arms $label, $e, $($rest)*)
+    }};
+    (@arms $label:lifetime, $e:ident,
+        $p:tt $(if $guard:expr)? => $body:expr $(, $($rest:tt)*)?
+    ) => {{
+        $crate::wdl_match!(@arm $label, $e, [$p], [$($guard)?], $body);
+        $crate::wdl_match!(@arms $label, $e, $($($rest)*)?)
+    }};
+    (@arm $label:lifetime, $e:ident, [$($p:tt)+], [$($guard:expr)?], $body:expr) => {
+        'arm: {
+            $crate::wdl_match!(@pat 'arm, $e, $($p)+);
+            $(
+                if !($guard) {
+                    break 'arm;
+                }
+            )?
+            break $label ($body);
+        }
+    };
+
+    // patterns, which are matched against `$bind`, an `&Expression` or `&Anchor<Expression>`
+    (@expr $bind:expr) => {{
+        let expression: &$crate::model::Expression = $bind;
+        expression
+    }};
+    (@pat $label:lifetime, $bind:expr, Boolean($p:tt)) => {
+        let $crate::model::Expression::Boolean(value) = $crate::wdl_match!(@expr $bind) else {
+            break $label;
+        };
+        $crate::wdl_match!(@value $label, *value, $p);
+    };
+    (@pat $label:lifetime, $bind:expr, Int($p:tt)) => {
+        let $crate::model::Expression::Int(value) = $crate::wdl_match!(@expr $bind) else {
+            break $label;
+        };
+        $crate::wdl_match!(@value $label, value.value, $p);
+    };
+    (@pat $label:lifetime, $bind:expr, Float($p:tt)) => {
+        let $crate::model::Expression::Float(value) = $crate::wdl_match!(@expr $bind) else {
+            break $label;
+        };
+        $crate::wdl_match!(@value $label, value.value, $p);
+    };
+    (@pat $label:lifetime, $bind:expr, Identifier($p:tt)) => {
+        let $crate::model::Expression::Identifier(value) = $crate::wdl_match!(@expr $bind) else {
+            break $label;
+        };
+        $crate::wdl_match!(@value $label, value.as_str(), $p);
+    };
+    (@pat $label:lifetime, $bind:expr, String($p:tt)) => {
+        let $crate::model::Expression::String(value) = $crate::wdl_match!(@expr $bind) else {
+            break $label;
+        };
+        $crate::wdl_match!(@bind value, $p);
+    };
+    (@pat $label:lifetime, $bind:expr, Map($p:tt)) => {
+        let $crate::model::Expression::Map(value) = $crate::wdl_match!(@expr $bind) else {
+            break $label;
+        };
+        $crate::wdl_match!(@bind value, $p);
+    };
+    (@pat $label:lifetime, $bind:expr, Object($p:tt)) => {
+        let $crate::model::Expression::Object(value) = $crate::wdl_match!(@expr $bind) else {
+            break $label;
+        };
+        $crate::wdl_match!(@bind value, $p);
+    };
+    (@pat $label:lifetime, $bind:expr, Unary($op:tt, $($p:tt)+)) => {
+        let $crate::model::Expression::Unary(unary) = $crate::wdl_match!(@expr $bind) else {
+            break $label;
+        };
+        $crate::wdl_match!(@op $label, &unary.operator, UnaryOperator, $op);
+        $crate::wdl_match!(@pat $label, &*unary.expression, $($p)+);
+    };
+    (@pat $label:lifetime, $bind:expr, Binary($op:tt, $($p:tt)+)) => {
+        let $crate::model::Expression::Binary(binary) = $crate::wdl_match!(@expr $bind) else {
+            break $label;
+        };
+        $crate::wdl_match!(@op $label, &binary.operator, BinaryOperator, $op);
+        $crate::wdl_match!(@args $label, [&*binary.left, &*binary.right], $($p)+);
+    };
+    (@pat $label:lifetime, $bind:expr, Ternary($($p:tt)+)) => {
+        let $crate::model::Expression::Ternary(ternary) = $crate::wdl_match!(@expr $bind) else {
+            break $label;
+        };
+        $crate::wdl_match!(
+            @args $label,
+            [&*ternary.condition, &*ternary.true_branch, &*ternary.false_branch],
+            $($p)+
+        );
+    };
+    (@pat $label:lifetime, $bind:expr, Pair($($p:tt)+)) => {
+        let $crate::model::Expression::Pair(pair) = $crate::wdl_match!(@expr $bind) else {
+            break $label;
+        };
+        $crate::wdl_match!(@args $label, [&*pair.left, &*pair.right], $($p)+);
+    };
+    (@pat $label:lifetime, $bind:expr, Group($($p:tt)+)) => {
+        let $crate::model::Expression::Group(group) = $crate::wdl_match!(@expr $bind) else {
+            break $label;
+        };
+        $crate::wdl_match!(@pat $label, &**group, $($p)+);
+    };
+    (@pat $label:lifetime, $bind:expr, Apply($name:tt, $args:tt)) => {
+        let $crate::model::Expression::Apply(apply) = $crate::wdl_match!(@expr $bind) else {
+            break $label;
+        };
+        $crate::wdl_match!(@value $label, apply.name.as_str(), $name);
+        $crate::wdl_match!(@slice $label, apply.arguments, $args);
+    };
+    (@pat $label:lifetime, $bind:expr, Array($elements:tt)) => {
+        let $crate::model::Expression::Array(array) = $crate::wdl_match!(@expr $bind) else {
+            break $label;
+        };
+        $crate::wdl_match!(@slice $label, array.elements, $elements);
+    };
+    (@pat $label:lifetime, $bind:expr, Access($v:ident ( $($a:tt)* ), $accesses:tt)) => {
+        let $crate::model::Expression::Access(access) = $crate::wdl_match!(@expr $bind) else {
+            break $label;
+        };
+        $crate::wdl_match!(@pat $label, &*access.collection, $v ( $($a)* ));
+        $crate::wdl_match!(@bind &access.accesses[..], $accesses);
+    };
+    (@pat $label:lifetime, $bind:expr, Access($p:tt, $accesses:tt)) => {
+        let $crate::model::Expression::Access(access) = $crate::wdl_match!(@expr $bind) else {
+            break $label;
+        };
+        $crate::wdl_match!(@pat $label, &*access.collection, $p);
+        $crate::wdl_match!(@bind &access.accesses[..], $accesses);
+    };
+    (@pat $label:lifetime, $bind:expr, None) => {
+        let $crate::model::Expression::None = $crate::wdl_match!(@expr $bind) else {
+            break $label;
+        };
+    };
+    (@pat $label:lifetime, $bind:expr, _) => {};
+    (@pat $label:lifetime, $bind:expr, $name:ident) => {
+        let $name = $bind;
+    };
+
+    // the operands of an expression, which are matched against the expressions `$bind`
+    (@args $label:lifetime, []) => {};
+    (@args $label:lifetime, [$bind:expr $(, $binds:expr)*],
+        $v:ident ( $($a:tt)* ) $(, $($rest:tt)*)?
+    ) => {
+        $crate::wdl_match!(@pat $label, $bind, $v ( $($a)* ));
+        $crate::wdl_match!(@args $label, [$($binds),*] $(, $($rest)*)?);
+    };
+    (@args $label:lifetime, [$bind:expr $(, $binds:expr)*], $p:tt $(, $($rest:tt)*)?) => {
+        $crate::wdl_match!(@pat $label, $bind, $p);
+        $crate::wdl_match!(@args $label, [$($binds),*] $(, $($rest)*)?);
+    };
+
+    // a list of expressions, e.g. the arguments of a function call
+    (@slice $label:lifetime, $slice:expr, [ $($p:tt)* ]) => {
+        $crate::wdl_match!(@list $label, $slice, 0usize, $($p)*);
+    };
+    (@slice $label:lifetime, $slice:expr, $p:tt) => {
+        $crate::wdl_match!(@bind &$slice[..], $p);
+    };
+    (@list $label:lifetime, $slice:expr, $index:expr $(,)?) => {
+        if $slice.len() != $index {
+            break $label;
+        }
+    };
+    (@list $label:lifetime, $slice:expr, $index:expr, .. $(,)?) => {
+        if $slice.len() < $index {
+            break $label;
+        }
+    };
+    (@list $label:lifetime, $slice:expr, $index:expr,
+        $v:ident ( $($a:tt)* ) $(, $($rest:tt)*)?
+    ) => {
+        let Some(element) = $slice.get($index) else {
+            break $label;
+        };
+        $crate::wdl_match!(@pat $label, element, $v ( $($a)* ));
+        $crate::wdl_match!(@list $label, $slice, $index + 1, $($($rest)*)?);
+    };
+    (@list $label:lifetime, $slice:expr, $index:expr, $p:tt $(, $($rest:tt)*)?) => {
+        let Some(element) = $slice.get($index) else {
+            break $label;
+        };
+        $crate::wdl_match!(@pat $label, element, $p);
+        $crate::wdl_match!(@list $label, $slice, $index + 1, $($($rest)*)?);
+    };
+
+    // values, operators, and bindings
+    (@value $label:lifetime, $value:expr, _) => {
+        let _ = $value;
+    };
+    (@value $label:lifetime, $value:expr, $literal:literal) => {
+        if $value != $literal {
+            break $label;
+        }
+    };
+    (@value $label:lifetime, $value:expr, $name:ident) => {
+        let $name = $value;
+    };
+    (@op $label:lifetime, $operator:expr, $kind:ident, _) => {};
+    (@op $label:lifetime, $operator:expr, $kind:ident, $variant:ident) => {
+        let $crate::model::$kind::$variant = $operator else {
+            break $label;
+        };
+    };
+    (@bind $value:expr, _) => {
+        let _ = $value;
+    };
+    (@bind $value:expr, $name:ident) => {
+        let $name = $value;
+    };
+
+    ($expression:expr, $($arms:tt)*) => {{
+        let expression: &$crate::model::Expression = &$expression;
+        'wdl_match: {
+            $crate::wdl_match!(@arms 'wdl_match, expression, $($arms)*)
+        }
+    }};
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use crate::{
+        model::{Anchor, DocumentElement, DocumentSource, Expression, WorkflowElement},
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+    use std::ops::Deref;
+
+    fn expressions(text: &str) -> Vec<Anchor<Expression>> {
+        let text = format!("version 1.1\n\nworkflow w {{\n{}\n}}\n", text);
+        let mut doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let workflow = match doc.body.pop().unwrap().into_element() {
+            DocumentElement::Workflow(workflow) => workflow,
+            _ => unreachable!(),
+        };
+        workflow
+            .body
+            .into_iter()
+            .map(|element| match element.into_element() {
+                WorkflowElement::Declaration(decl) => decl.expression,
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    fn describe(expression: &Expression) -> String {
+        wdl_match!(expression,
+            Binary(Add, Identifier(x), Int(1)) => format!("increment {}", x),
+            Binary(Add, lhs, Unary(Neg, Int(n))) if n > 0 => {
+                format!("subtract {} from {}", n, **lhs)
+            }
+            Binary(_, Group(Binary(Mul, _, _)), _) => "grouped product".to_owned(),
+            Apply("select_first", [Array([first, ..])]) => {
+                format!("select {} first at {}", **first, first.span.start.column)
+            }
+            Apply(name, args) => format!("{} with {} arguments", name, args.len()),
+            Ternary(Identifier(c), Boolean(true), _) if c.starts_with("is_") => {
+                "predicate".to_owned()
+            }
+            Access(Identifier("align"), accesses) => format!("{} accesses", accesses.len()),
+            Unary(Not, Unary(Not, e)) => format!("double negation of {}", **e),
+            Pair(None, _) => "pair of none".to_owned(),
+            String(_) => "string".to_owned(),
+            _ => "other".to_owned(),
+        )
+    }
+
+    #[test]
+    fn test_wdl_match() {
+        let descriptions: Vec<String> = expressions(
+            r#"Int a = i + 1
+Int b = i + -2
+Int c = (2 * 3) - 1
+File d = select_first([x, y])
+Array[Int] e = range(3)
+Boolean f = if is_ok then true else false
+Boolean g = if ok then true else false
+Int h = align.out[0]
+Boolean i = !!ok
+Pair[Int?, Int] j = (None, 1)
+String k = "~{a}"
+Int l = i + 1 + 1
+File m = select_first([])"#,
+        )
+        .iter()
+        .map(|expression| describe(expression))
+        .collect();
+        assert_eq!(
+            descriptions,
+            vec![
+                "increment i",
+                "subtract 2 from i",
+                "grouped product",
+                "select x first at 23",
+                "range with 1 arguments",
+                "predicate",
+                "other",
+                "2 accesses",
+                "double negation of ok",
+                "pair of none",
+                "string",
+                "other",
+                "select_first with 1 arguments",
+            ]
+        );
+
+        // without a wildcard arm, the arms evaluate to `()`
+        let mut names = Vec::new();
+        for expression in expressions("Int a = x\nInt b = 1\nInt c = y").iter() {
+            wdl_match!(expression.deref(), Identifier(name) => names.push(name.to_owned()))
+        }
+        assert_eq!(names, vec!["x", "y"]);
+    }
+}
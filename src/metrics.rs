@@ -0,0 +1,394 @@
+//! Size and complexity metrics for documents, e.g. for enforcing thresholds in code review or for
+//! tracking the growth of a code base over time. Metrics are computed from the model alone, so
+//! imported documents are not taken into account: a call counts once, regardless of the size of
+//! the task or workflow that it calls.
+use crate::{
+    model::{
+        AccessOperation, Anchor, Command, Document, DocumentElement, Expression, Span, StringPart,
+        Task, Workflow, WorkflowElement, WorkflowNestedElement,
+    },
+    tree::{walk, Node},
+};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::ops::Deref;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TaskMetrics {
+    pub name: String,
+    pub inputs: usize,
+    pub outputs: usize,
+    /// The number of non-blank lines of the command, or 0 if the task has no command.
+    pub command_lines: usize,
+    /// The depth of the deepest expression of the task (see `expression_depth`).
+    pub max_expression_depth: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct WorkflowMetrics {
+    pub name: String,
+    pub inputs: usize,
+    pub outputs: usize,
+    /// The number of calls, including those nested within scatter and conditional blocks.
+    pub calls: usize,
+    pub scatters: usize,
+    pub conditionals: usize,
+    /// The greatest number of scatter blocks that enclose any element, i.e. the number of
+    /// dimensions of the most deeply scattered call or declaration.
+    pub max_scatter_depth: usize,
+    /// The greatest number of scatter and conditional blocks that enclose any element.
+    pub max_nesting_depth: usize,
+    /// The depth of the deepest expression of the workflow (see `expression_depth`).
+    pub max_expression_depth: usize,
+    /// A score that increases with the number of calls, blocks, and branches of the workflow,
+    /// with nested blocks scoring higher than blocks at the top level:
+    /// * 1 for each call.
+    /// * 1 plus the nesting depth of the block for each scatter or conditional block, i.e. 1 for
+    ///   a block at the top level, 2 for a block within one other block, etc.
+    /// * 1 for each `if ... then ... else` expression.
+    pub complexity: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DocumentMetrics {
+    pub imports: usize,
+    pub structs: usize,
+    /// The metrics of each task, in the order in which they are defined.
+    pub tasks: Vec<TaskMetrics>,
+    /// The metrics of each workflow, in the order in which they are defined.
+    pub workflows: Vec<WorkflowMetrics>,
+}
+
+impl DocumentMetrics {
+    /// Returns the total number of calls of all workflows.
+    pub fn calls(&self) -> usize {
+        self.workflows.iter().map(|workflow| workflow.calls).sum()
+    }
+
+    /// Returns the greatest number of non-blank command lines of any task.
+    pub fn max_command_lines(&self) -> usize {
+        self.tasks
+            .iter()
+            .map(|task| task.command_lines)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+fn max_depth<'a, I: IntoIterator<Item = &'a Anchor<Expression>>>(expressions: I) -> usize {
+    expressions
+        .into_iter()
+        .map(|expression| expression_depth(expression))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Returns the depth of `expression`, i.e. the number of expressions on the longest path from
+/// `expression` to a literal or identifier that it contains, including both. Literals and
+/// identifiers have depth 1, and `a + b * c` has depth 3. Parentheses do not add to the depth,
+/// and the expressions of the placeholders of a string are one level deeper than the string.
+pub fn expression_depth(expression: &Expression) -> usize {
+    match expression {
+        Expression::None
+        | Expression::Boolean(_)
+        | Expression::Int(_)
+        | Expression::Float(_)
+        | Expression::Identifier(_) => 1,
+        Expression::Group(group) => expression_depth(group),
+        Expression::String(string) => {
+            1 + string
+                .parts
+                .iter()
+                .map(|part| match part.deref() {
+                    StringPart::Placeholder(placeholder, _) => expression_depth(placeholder),
+                    _ => 0,
+                })
+                .max()
+                .unwrap_or(0)
+        }
+        Expression::Array(array) => 1 + max_depth(array.elements.iter()),
+        Expression::Map(map) => {
+            1 + max_depth(
+                map.entries
+                    .iter()
+                    .flat_map(|entry| [&entry.key, &entry.value]),
+            )
+        }
+        Expression::Pair(pair) => 1 + max_depth([&*pair.left, &*pair.right]),
+        Expression::Object(object) => {
+            1 + max_depth(object.fields.iter().map(|field| &field.expression))
+        }
+        Expression::Unary(unary) => 1 + expression_depth(&unary.expression),
+        Expression::Binary(binary) => 1 + max_depth([&*binary.left, &*binary.right]),
+        Expression::Ternary(ternary) => {
+            1 + max_depth([
+                &*ternary.condition,
+                &*ternary.true_branch,
+                &*ternary.false_branch,
+            ])
+        }
+        Expression::Apply(apply) => 1 + max_depth(apply.arguments.iter()),
+        Expression::Access(access) => {
+            let indexes = access
+                .accesses
+                .iter()
+                .filter_map(|operation| match operation.deref() {
+                    AccessOperation::Index(index) => Some(expression_depth(index)),
+                    AccessOperation::Field(_) => None,
+                });
+            1 + indexes
+                .chain([expression_depth(&access.collection)])
+                .max()
+                .unwrap_or(0)
+        }
+    }
+}
+
+/// Returns the number of non-blank lines of `command`, where each placeholder counts as text.
+pub fn command_lines(command: &Command) -> usize {
+    let text: String = command
+        .parts
+        .iter()
+        .map(|part| part.deref().to_string())
+        .collect();
+    text.lines().filter(|line| !line.trim().is_empty()).count()
+}
+
+/// The expressions of a document, with their spans and depths.
+struct Expressions<'a> {
+    expressions: Vec<(&'a Span, usize, bool)>,
+}
+
+impl<'a> Expressions<'a> {
+    fn new(document: &'a Document) -> Self {
+        let mut expressions = Vec::new();
+        walk(document, &mut |node, _| {
+            if let Node::Expression(expression) = node {
+                expressions.push((
+                    &expression.span,
+                    expression_depth(expression),
+                    matches!(expression.deref(), Expression::Ternary(_)),
+                ))
+            }
+        });
+        Self { expressions }
+    }
+
+    fn within(&self, span: &'a Span) -> impl Iterator<Item = &(&'a Span, usize, bool)> + '_ {
+        self.expressions
+            .iter()
+            .filter(move |(expression, _, _)| span.contains_span(expression))
+    }
+
+    fn max_depth(&self, span: &'a Span) -> usize {
+        self.within(span)
+            .map(|(_, depth, _)| *depth)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn ternaries(&self, span: &'a Span) -> usize {
+        self.within(span).filter(|(_, _, ternary)| *ternary).count()
+    }
+}
+
+fn task_metrics(task: &Task, span: &Span, expressions: &Expressions) -> TaskMetrics {
+    TaskMetrics {
+        name: task.name.as_str().to_owned(),
+        inputs: task
+            .inputs()
+            .map(|input| input.declarations.len())
+            .unwrap_or(0),
+        outputs: task
+            .outputs()
+            .map(|output| output.declarations.len())
+            .unwrap_or(0),
+        command_lines: task.command().map(command_lines).unwrap_or(0),
+        max_expression_depth: expressions.max_depth(span),
+    }
+}
+
+/// Adds the metrics of the elements of the block `body`, which is enclosed by `scatters` scatter
+/// blocks and `conditionals` conditional blocks, to `metrics`.
+fn nested_metrics(
+    body: &[Anchor<WorkflowNestedElement>],
+    scatters: usize,
+    conditionals: usize,
+    metrics: &mut WorkflowMetrics,
+) {
+    metrics.max_scatter_depth = metrics.max_scatter_depth.max(scatters);
+    metrics.max_nesting_depth = metrics.max_nesting_depth.max(scatters + conditionals);
+    for element in body.iter() {
+        match element.deref() {
+            WorkflowNestedElement::Call(_) => {
+                metrics.calls += 1;
+                metrics.complexity += 1;
+            }
+            WorkflowNestedElement::Scatter(scatter) => {
+                metrics.scatters += 1;
+                metrics.complexity += 1 + scatters + conditionals;
+                nested_metrics(&scatter.body, scatters + 1, conditionals, metrics)
+            }
+            WorkflowNestedElement::Conditional(conditional) => {
+                metrics.conditionals += 1;
+                metrics.complexity += 1 + scatters + conditionals;
+                nested_metrics(&conditional.body, scatters, conditionals + 1, metrics)
+            }
+            WorkflowNestedElement::Declaration(_) => (),
+        }
+    }
+}
+
+fn workflow_metrics(
+    workflow: &Workflow,
+    span: &Span,
+    expressions: &Expressions,
+) -> WorkflowMetrics {
+    let mut metrics = WorkflowMetrics {
+        name: workflow.name.as_str().to_owned(),
+        inputs: workflow
+            .inputs()
+            .map(|input| input.declarations.len())
+            .unwrap_or(0),
+        outputs: workflow
+            .outputs()
+            .map(|output| output.declarations.len())
+            .unwrap_or(0),
+        max_expression_depth: expressions.max_depth(span),
+        complexity: expressions.ternaries(span),
+        ..Default::default()
+    };
+    for element in workflow.body.iter() {
+        match element.deref() {
+            WorkflowElement::Call(_) => {
+                metrics.calls += 1;
+                metrics.complexity += 1;
+            }
+            WorkflowElement::Scatter(scatter) => {
+                metrics.scatters += 1;
+                metrics.complexity += 1;
+                nested_metrics(&scatter.body, 1, 0, &mut metrics)
+            }
+            WorkflowElement::Conditional(conditional) => {
+                metrics.conditionals += 1;
+                metrics.complexity += 1;
+                nested_metrics(&conditional.body, 0, 1, &mut metrics)
+            }
+            _ => (),
+        }
+    }
+    metrics
+}
+
+/// Returns the metrics of `document` and of each of its tasks and workflows.
+pub fn metrics(document: &Document) -> DocumentMetrics {
+    let expressions = Expressions::new(document);
+    let mut metrics = DocumentMetrics::default();
+    for element in document.body.iter() {
+        match element.deref() {
+            DocumentElement::Import(_) => metrics.imports += 1,
+            DocumentElement::Struct(_) => metrics.structs += 1,
+            DocumentElement::Task(task) => {
+                metrics
+                    .tasks
+                    .push(task_metrics(task, &element.span, &expressions))
+            }
+            DocumentElement::Workflow(workflow) => {
+                metrics
+                    .workflows
+                    .push(workflow_metrics(workflow, &element.span, &expressions))
+            }
+        }
+    }
+    metrics
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_metrics() {
+        let text = r#"version 1.1
+
+import "lib.wdl"
+
+task count {
+    input {
+        File f
+        Int n = 1 + 2 * 3
+    }
+    command <<<
+
+        wc -l ~{f} \
+            | head -n ~{n}
+    >>>
+    output {
+        Int lines = read_int(stdout())
+    }
+}
+
+workflow w {
+    input {
+        Array[Array[File]] files
+        Boolean all
+    }
+    scatter (group in files) {
+        scatter (f in group) {
+            if (all) {
+                call count { input: f }
+            }
+        }
+    }
+    if (!all) {
+        call lib.summarize
+    }
+    Int n = if all then length(files) else 0
+    output {
+        Array[Array[Int?]] counts = count.lines
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let metrics = metrics(&doc);
+        assert_eq!(metrics.imports, 1);
+        assert_eq!(
+            metrics.tasks,
+            vec![TaskMetrics {
+                name: "count".to_owned(),
+                inputs: 2,
+                outputs: 1,
+                command_lines: 2,
+                max_expression_depth: 3,
+            }]
+        );
+        assert_eq!(
+            metrics.workflows,
+            vec![WorkflowMetrics {
+                name: "w".to_owned(),
+                inputs: 2,
+                outputs: 1,
+                calls: 2,
+                scatters: 2,
+                conditionals: 2,
+                max_scatter_depth: 2,
+                max_nesting_depth: 3,
+                max_expression_depth: 3,
+                // calls (2), blocks (1 + 2 + 3 + 1), and the ternary (1)
+                complexity: 10,
+            }]
+        );
+        assert_eq!(metrics.calls(), 2);
+        assert_eq!(metrics.max_command_lines(), 2);
+    }
+}
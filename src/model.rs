@@ -1,13 +1,29 @@
-use error_stack::{bail, ensure, IntoReport, Report, Result, ResultExt};
-use regex::Regex;
+use crate::{
+    analysis,
+    diagnostics::Diagnostic,
+    edit::TextEdit,
+    eval::{self, Environment},
+    fix::{self, Fix},
+    shell::ShellTemplate,
+    units::{self, Disk, Quantity},
+    values::WdlValue,
+};
+use error_stack::{bail, ensure, report, IntoReport, Report, Result, ResultExt};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 use std::{
     cmp::Ordering,
-    collections::{btree_map::Range as CommentIterator, BTreeMap, HashSet},
-    fmt::{Display, Formatter, Result as FmtResult, Write},
+    collections::{btree_map::Range as CommentIterator, BTreeMap, BTreeSet, HashSet},
+    fmt::{Debug, Display, Formatter, Result as FmtResult, Write},
     ops::Deref,
     ops::Range,
     path::PathBuf,
     str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering as AtomicOrdering},
+        Arc,
+    },
 };
 use thiserror::Error;
 
@@ -25,18 +41,41 @@ pub enum ModelError {
     Float(String),
     #[error("Invalid version identifier {0} (only WDL 1.x is supported)")]
     Version(String),
+    #[error(
+        "Version development is not enabled (see ValidationOptions::with_development_version)"
+    )]
+    VersionDevelopment,
+    #[error("{feature} requires WDL version {version} or later")]
+    VersionFeature { feature: String, version: String },
     #[error("Task {kind} contains more than one of the same element type {kind}")]
     TaskRepeatedElement { task: String, kind: String },
     #[error("Task {0} is missing required 'command' element")]
     TaskMissingCommand(String),
     #[error("Workflow {workflow} contains more than one of the same element type {kind}")]
     WorkflowRepeatedElement { workflow: String, kind: String },
-    #[error("Document is missing at least one element of kind Struct, Task, or Workflow")]
+    #[error("Invalid {kind} name {name}: {reason}")]
+    InvalidName {
+        kind: String,
+        name: String,
+        reason: String,
+    },
+    #[error("Document is missing at least one element of kind Import, Struct, Task, or Workflow")]
     DocumentIncomplete,
     #[error("Document has more than one Workflow element")]
     DocumentMultipleWorkflows,
     #[error("Comment already exists for line {0}")]
     CommentRepeatedLine(usize),
+    #[error("Invalid escape sequence {0}")]
+    Escape(String),
+    #[error("Invalid type {0}")]
+    Type(String),
+    #[error("String contains a placeholder and cannot be evaluated without an environment")]
+    StringPlaceholder,
+    #[error(
+        "${{{name}}} in the heredoc command of task {task} is not a placeholder; use ~{{{name}}} to \
+        refer to the declaration {name}"
+    )]
+    HeredocDollarPlaceholder { task: String, name: String },
 }
 
 impl ModelError {
@@ -46,11 +85,157 @@ impl ModelError {
             value: msg,
         }
     }
+
+    /// Returns a short identifier for the kind of error, for use as the code of a `Diagnostic`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Grammar { .. } => "grammar",
+            Self::Integer(_) => "integer",
+            Self::Float(_) => "float",
+            Self::Version(_) => "version",
+            Self::VersionDevelopment => "version_development",
+            Self::VersionFeature { .. } => "version_feature",
+            Self::TaskRepeatedElement { .. } => "task_repeated_element",
+            Self::TaskMissingCommand(_) => "task_missing_command",
+            Self::WorkflowRepeatedElement { .. } => "workflow_repeated_element",
+            Self::InvalidName { .. } => "invalid_name",
+            Self::DocumentIncomplete => "document_incomplete",
+            Self::DocumentMultipleWorkflows => "document_multiple_workflows",
+            Self::CommentRepeatedLine(_) => "comment_repeated_line",
+            Self::Escape(_) => "escape",
+            Self::Type(_) => "type",
+            Self::StringPlaceholder => "string_placeholder",
+            Self::HeredocDollarPlaceholder { .. } => "heredoc_dollar_placeholder",
+        }
+    }
+}
+
+/// How a validation rule is enforced.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidationLevel {
+    /// A violation of the rule is an error, and the document is invalid.
+    Error,
+    /// A violation of the rule is reported as a warning.
+    Warning,
+    /// The rule is not checked.
+    Ignore,
+}
+
+impl ValidationLevel {
+    /// Handles a violation of a rule that is enforced at this level, by returning `error`, adding
+    /// a warning to `warnings`, or doing nothing.
+    fn violation(
+        self,
+        error: ModelError,
+        span: &Span,
+        fixes: Vec<Fix>,
+        warnings: &mut Vec<Diagnostic>,
+    ) -> Result<(), ModelError> {
+        match self {
+            Self::Error => Err(fixes.into_iter().fold(
+                Report::from(error).attach_printable(span.clone()),
+                |report, fix| report.attach(fix),
+            )),
+            Self::Warning => {
+                let mut warning =
+                    Diagnostic::warning(error.code(), error.to_string(), span.clone());
+                warning.fixes = fixes;
+                warnings.push(warning);
+                Ok(())
+            }
+            Self::Ignore => Ok(()),
+        }
+    }
+}
+
+/// The levels at which the structural rules of `Document::validate_with` are enforced. The
+/// default is to enforce all rules as errors.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationOptions {
+    /// A document must contain at least one import, struct, task, or workflow.
+    pub incomplete_document: ValidationLevel,
+    /// A document may contain at most one workflow.
+    pub multiple_workflows: ValidationLevel,
+    /// A task or workflow may contain at most one of each kind of section (e.g. `input`).
+    pub repeated_sections: ValidationLevel,
+    /// A task must contain a `command` section.
+    pub missing_command: ValidationLevel,
+    /// A document may only use features that are available in the WDL version it declares.
+    pub version_features: ValidationLevel,
+    /// A document may not declare `version development`. The development version is unstable,
+    /// so this rule is an error in every preset (including `permissive`), and documents that
+    /// declare it must be opted into with `with_development_version`.
+    pub development_version: ValidationLevel,
+    /// The names of structs and their fields, tasks, workflows, declarations, call aliases,
+    /// scatter variables, and import namespaces must be valid identifiers that are not reserved
+    /// keywords or the names of built-in types.
+    pub invalid_names: ValidationLevel,
+    /// All comments must be added to the model. A comment is dropped if it cannot be added (see
+    /// `Comments::dropped`), which indicates a parser bug rather than a problem with the
+    /// document, so by default this rule is only a warning; dropped comments are omitted from
+    /// the model (e.g. by the formatter) unless it is enforced as an error.
+    pub dropped_comments: ValidationLevel,
+    /// Within a heredoc command (`command <<< >>>`), `${name}` is passed to the shell unchanged
+    /// rather than replaced with the value of `name`, so it may not be used where `name` is an
+    /// input or private declaration of the task. A shell variable with the same name as a
+    /// declaration must be written as `$name`, or this rule set to `Ignore`.
+    pub heredoc_placeholders: ValidationLevel,
+}
+
+impl ValidationOptions {
+    /// Returns options that enforce all rules as errors.
+    pub fn strict() -> Self {
+        Self {
+            incomplete_document: ValidationLevel::Error,
+            multiple_workflows: ValidationLevel::Error,
+            repeated_sections: ValidationLevel::Error,
+            missing_command: ValidationLevel::Error,
+            version_features: ValidationLevel::Error,
+            development_version: ValidationLevel::Error,
+            invalid_names: ValidationLevel::Error,
+            dropped_comments: ValidationLevel::Error,
+            heredoc_placeholders: ValidationLevel::Error,
+        }
+    }
+
+    /// Returns options that report violations of all rules as warnings, so that any document that
+    /// can be parsed is valid, except that `version development` is still an error.
+    pub fn permissive() -> Self {
+        Self {
+            incomplete_document: ValidationLevel::Warning,
+            multiple_workflows: ValidationLevel::Warning,
+            repeated_sections: ValidationLevel::Warning,
+            missing_command: ValidationLevel::Warning,
+            version_features: ValidationLevel::Warning,
+            development_version: ValidationLevel::Error,
+            invalid_names: ValidationLevel::Warning,
+            dropped_comments: ValidationLevel::Warning,
+            heredoc_placeholders: ValidationLevel::Warning,
+        }
+    }
+
+    /// Sets the level of the `development_version` rule, e.g. `Ignore` to accept documents that
+    /// declare `version development`.
+    pub fn with_development_version(mut self, level: ValidationLevel) -> Self {
+        self.development_version = level;
+        self
+    }
+}
+
+/// The default options are strict, except that dropped comments are reported as warnings.
+impl Default for ValidationOptions {
+    fn default() -> Self {
+        Self {
+            dropped_comments: ValidationLevel::Warning,
+            ..Self::strict()
+        }
+    }
 }
 
 /// A position in the source document. Includes both 1D (byte offset) and 2D (line and column)
 /// coordinates. Coordinates are zero-based, end-exclusive.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Position {
     /// Line of the source file
     pub line: usize,
@@ -96,6 +281,7 @@ impl PartialOrd for Position {
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 /// The span (start and end positions) in the source document from which a model element was derived.
 pub struct Span {
     pub start: Position,
@@ -132,8 +318,40 @@ impl Span {
         }
     }
 
+    /// Returns the length of this span in bytes.
     pub fn len(&self) -> usize {
-        self.end.offset - self.start.offset - 1
+        self.end.offset - self.start.offset
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start.offset == self.end.offset
+    }
+
+    /// Returns whether `position` is within this span, i.e. at or after its start and before its
+    /// (exclusive) end. Like all comparisons of positions, this compares their offsets.
+    pub fn contains(&self, position: &Position) -> bool {
+        self.start <= *position && *position < self.end
+    }
+
+    /// Returns whether `other` is entirely within this span. A span contains itself, and an
+    /// empty span at the end of this span.
+    pub fn contains_span(&self, other: &Span) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    /// Returns whether this span and `other` overlap, i.e. whether there is a position that is
+    /// contained by both. Adjacent spans (where one ends where the other starts) do not
+    /// intersect, and neither do empty spans, which contain no positions.
+    pub fn intersects(&self, other: &Span) -> bool {
+        !self.is_empty() && !other.is_empty() && self.start < other.end && other.start < self.end
+    }
+
+    /// Returns the smallest span that contains both this span and `other`.
+    pub fn union(&self, other: &Span) -> Span {
+        Self {
+            start: self.start.clone().min(other.start.clone()),
+            end: self.end.clone().max(other.end.clone()),
+        }
     }
 
     pub fn trim_end(&mut self, n: usize) {
@@ -171,7 +389,7 @@ impl<T> InnerSpan for Vec<Anchor<T>> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct SourceFragment(pub String);
 
 impl Display for SourceFragment {
@@ -180,10 +398,35 @@ impl Display for SourceFragment {
     }
 }
 
+/// Identifier of a model node. Each `Anchor` is assigned an ID when it is created (i.e. during
+/// parsing) or cloned, which is unique among all the `Anchor`s created by the current process, so
+/// a clone of an `Anchor` (and each node within it) has a different ID than the original.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct NodeId(u64);
+
+static NEXT_NODE_ID: AtomicU64 = AtomicU64::new(0);
+
+impl NodeId {
+    fn next() -> Self {
+        Self(NEXT_NODE_ID.fetch_add(1, AtomicOrdering::Relaxed))
+    }
+}
+
+impl Display for NodeId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "#{}", self.0)
+    }
+}
+
 /// Wrapper around a model element of type `T` that also encapsulates source code span information.
-/// `Deref`s to `T`.
-#[derive(Debug, PartialEq)]
+/// `Deref`s to `T`. Two `Anchor`s are equal if their elements and spans are equal, regardless of
+/// their IDs, which are also omitted from the `Debug` representation.
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Anchor<T> {
+    /// The ID of this node.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    id: NodeId,
     /// The model element.
     element: T,
     /// The span of the source document from which the element was derived.    
@@ -192,7 +435,43 @@ pub struct Anchor<T> {
 
 impl<T> Anchor<T> {
     pub fn new(element: T, span: Span) -> Self {
-        Self { element, span }
+        Self {
+            id: NodeId::next(),
+            element,
+            span,
+        }
+    }
+
+    /// Returns the ID of this node.
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    /// Consumes this `Anchor` and returns the wrapped element.
+    pub fn into_element(self) -> T {
+        self.element
+    }
+}
+
+impl<T: Clone> Clone for Anchor<T> {
+    /// Returns a copy of this `Anchor` with a new ID.
+    fn clone(&self) -> Self {
+        Self::new(self.element.clone(), self.span.clone())
+    }
+}
+
+impl<T: Debug> Debug for Anchor<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Anchor")
+            .field("element", &self.element)
+            .field("span", &self.span)
+            .finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for Anchor<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.element == other.element && self.span == other.span
     }
 }
 
@@ -210,80 +489,228 @@ impl<T: Display> Display for Anchor<T> {
     }
 }
 
+/// Whether a comment occupies a line by itself or follows code on the same line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommentKind {
+    /// The comment is the only non-whitespace content of its line.
+    FullLine,
+    /// The comment follows code on the same line.
+    Trailing,
+}
+
+impl CommentKind {
+    /// Returns the kind of a comment that is preceded by `prefix` on its line.
+    pub fn from_prefix(prefix: &str) -> Self {
+        if prefix.trim().is_empty() {
+            Self::FullLine
+        } else {
+            Self::Trailing
+        }
+    }
+}
+
 /// Mapping of source lines to comments. In WDL there are no block comments, so there can be a
 /// maximum of one comment per line.
-#[derive(Debug, PartialEq)]
-pub struct Comments(BTreeMap<usize, Anchor<String>>);
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize), serde(transparent))]
+pub struct Comments {
+    comments: BTreeMap<usize, Anchor<String>>,
+    /// The lines on which comments follow code.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    trailing: BTreeSet<usize>,
+    /// Comments that could not be added while the model was built.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    dropped: Vec<Anchor<String>>,
+}
 
 impl Comments {
-    /// Adds a comment to the map. Returns a `ModelError::DuplicateComment` if a comment has
-    /// already been added at `line`.
+    /// Adds a full-line comment to the map. Returns a `ModelError::DuplicateComment` if a comment
+    /// has already been added at `line`.
     pub fn try_insert(&mut self, line: usize, comment: Anchor<String>) -> Result<(), ModelError> {
+        self.try_insert_with_kind(line, comment, CommentKind::FullLine)
+    }
+
+    /// Adds a comment of the given kind to the map. Returns a `ModelError::DuplicateComment` if a
+    /// comment has already been added at `line`.
+    pub fn try_insert_with_kind(
+        &mut self,
+        line: usize,
+        comment: Anchor<String>,
+        kind: CommentKind,
+    ) -> Result<(), ModelError> {
         ensure!(
-            !self.0.contains_key(&line),
+            !self.comments.contains_key(&line),
             ModelError::CommentRepeatedLine(line)
         );
-        self.0.insert(line, comment);
+        self.comments.insert(line, comment);
+        if kind == CommentKind::Trailing {
+            self.trailing.insert(line);
+        }
         Ok(())
     }
 
     /// Returns the comment on the specified line, or `None` if there is no comment on that line.
     pub fn get(&self, line: usize) -> Option<&Anchor<String>> {
-        self.0.get(&line)
+        self.comments.get(&line)
+    }
+
+    /// Returns the kind of the comment on the specified line, or `None` if there is no comment on
+    /// that line.
+    pub fn kind(&self, line: usize) -> Option<CommentKind> {
+        if !self.comments.contains_key(&line) {
+            None
+        } else if self.trailing.contains(&line) {
+            Some(CommentKind::Trailing)
+        } else {
+            Some(CommentKind::FullLine)
+        }
+    }
+
+    /// Returns the comment that follows code on the specified line, if any.
+    pub fn trailing(&self, line: usize) -> Option<&Anchor<String>> {
+        if self.trailing.contains(&line) {
+            self.comments.get(&line)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the full-line comments that immediately precede the specified line, i.e. the
+    /// comments on consecutive lines ending at `line - 1`, in line order.
+    pub fn leading(&self, line: usize) -> Vec<&Anchor<String>> {
+        let mut leading = Vec::new();
+        for prev in (0..line).rev() {
+            match self.comments.get(&prev) {
+                Some(comment) if !self.trailing.contains(&prev) => leading.push(comment),
+                _ => break,
+            }
+        }
+        leading.reverse();
+        leading
     }
 
-    //// Returns an iterator over all comments in line order.
+    /// Returns an iterator over all comments in line order.
     pub fn values(&self) -> impl Iterator<Item = &Anchor<String>> {
-        self.0.values()
+        self.comments.values()
     }
 
     /// Returns an iterator over comments within the given source line range (start-inclusive,
     /// end-exclusive).
     pub fn range(&self, lines: Range<usize>) -> CommentIterator<usize, Anchor<String>> {
-        self.0.range(lines)
+        self.comments.range(lines)
     }
-}
 
-impl Default for Comments {
-    fn default() -> Self {
-        Self(Default::default())
+    /// Returns an iterator over the comments on the lines of `span` (including its first and
+    /// last lines), e.g. the comments within an element and the comment that trails it.
+    pub fn in_span(&self, span: &Span) -> CommentIterator<'_, usize, Anchor<String>> {
+        self.comments.range(span.start.line..=span.end.line)
+    }
+
+    /// Records a comment that could not be added, e.g. because it was encountered after the
+    /// model of the element that contains it was built, when errors can no longer be returned.
+    pub fn push_dropped(&mut self, comment: Anchor<String>) {
+        self.dropped.push(comment)
+    }
+
+    /// Returns the comments that could not be added while the model was built, in the order in
+    /// which they were encountered. These are not returned by the other methods.
+    pub fn dropped(&self) -> &[Anchor<String>] {
+        &self.dropped
+    }
+
+    /// Returns an error if any comments were dropped. The error is that of the first dropped
+    /// comment, with the spans of all dropped comments attached.
+    pub fn check_dropped(&self) -> Result<(), ModelError> {
+        match self.dropped.first() {
+            None => Ok(()),
+            Some(first) => Err(self.dropped.iter().fold(
+                report!(ModelError::CommentRepeatedLine(first.span.start.line)),
+                |report, comment| report.attach_printable(comment.span.clone()),
+            )),
+        }
     }
 }
 
+/// The base in which an integer literal is written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Radix {
+    Decimal,
+    Octal,
+    Hex,
+}
+
+/// An integer literal. The literal as it is written in the source document is preserved in `raw`,
+/// so that it can be reproduced exactly, e.g. `0777` rather than `511`.
 #[derive(Debug, PartialEq)]
-pub enum Integer {
-    Decimal(i64),
-    Octal(i64),
-    Hex(i64),
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Integer {
+    pub value: i64,
+    pub radix: Radix,
+    pub raw: String,
 }
 
 impl Integer {
+    pub fn decimal(value: i64) -> Self {
+        Self {
+            value,
+            radix: Radix::Decimal,
+            raw: value.to_string(),
+        }
+    }
+
+    pub fn octal(value: i64) -> Self {
+        let sign = if value < 0 { "-" } else { "" };
+        Self {
+            value,
+            radix: Radix::Octal,
+            raw: format!("{}0{:o}", sign, value.unsigned_abs()),
+        }
+    }
+
+    pub fn hex(value: i64) -> Self {
+        let sign = if value < 0 { "-" } else { "" };
+        Self {
+            value,
+            radix: Radix::Hex,
+            raw: format!("{}0x{:X}", sign, value.unsigned_abs()),
+        }
+    }
+
     pub fn negate(&self) -> Self {
-        match self {
-            Self::Decimal(i) => Self::Decimal(i * -1),
-            Self::Octal(i) => Self::Octal(i * -1),
-            Self::Hex(i) => Self::Hex(i * -1),
+        Self {
+            value: -self.value,
+            radix: self.radix,
+            raw: negate_raw(&self.raw),
         }
     }
 }
 
+/// Negates the source text of a numeric literal.
+fn negate_raw(raw: &str) -> String {
+    match raw.strip_prefix('-') {
+        Some(raw) => raw.to_owned(),
+        None => format!("-{}", raw),
+    }
+}
+
 impl FromStr for Integer {
     type Err = Report<ModelError>;
 
     fn from_str(s: &str) -> Result<Self, ModelError> {
-        if s.starts_with("0") && s.len() > 1 {
+        let (value, radix) = if s.starts_with("0") && s.len() > 1 {
             match s.chars().nth(1).unwrap() {
                 'x' | 'X' => {
                     let int_value = i64::from_str_radix(&s[2..], 16)
                         .into_report()
                         .change_context(ModelError::Integer(s.to_owned()))?;
-                    Ok(Self::Hex(int_value))
+                    (int_value, Radix::Hex)
                 }
                 '0'..='7' => {
                     let int_value = i64::from_str_radix(&s[1..], 8)
                         .into_report()
                         .change_context(ModelError::Integer(s.to_owned()))?;
-                    Ok(Self::Octal(int_value))
+                    (int_value, Radix::Octal)
                 }
                 _ => bail!(ModelError::Grammar {
                     kind: String::from("integer"),
@@ -295,32 +722,69 @@ impl FromStr for Integer {
                 .parse::<i64>()
                 .into_report()
                 .change_context(ModelError::Integer(s.to_owned()))?;
-            Ok(Self::Decimal(int_value))
-        }
+            (int_value, Radix::Decimal)
+        };
+        Ok(Self {
+            value,
+            radix,
+            raw: s.to_owned(),
+        })
+    }
+}
+
+impl Display for Integer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(&self.raw)
     }
 }
 
 impl Into<i64> for Integer {
     fn into(self) -> i64 {
-        match self {
-            Integer::Decimal(i) => i,
-            Integer::Octal(i) => i,
-            Integer::Hex(i) => i,
-        }
+        self.value
     }
 }
 
+/// The notation in which a float literal is written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Notation {
+    Decimal,
+    Scientific,
+}
+
+/// A float literal. The literal as it is written in the source document is preserved in `raw`, so
+/// that it can be reproduced exactly, e.g. `1e-3` rather than `0.001`.
 #[derive(Debug, PartialEq)]
-pub enum Float {
-    Decimal(f64),
-    Scientific(f64),
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Float {
+    pub value: f64,
+    pub notation: Notation,
+    pub raw: String,
 }
 
 impl Float {
+    pub fn decimal(value: f64) -> Self {
+        Self {
+            value,
+            notation: Notation::Decimal,
+            // Debug formatting always includes a decimal point
+            raw: format!("{:?}", value),
+        }
+    }
+
+    pub fn scientific(value: f64) -> Self {
+        Self {
+            value,
+            notation: Notation::Scientific,
+            raw: format!("{:e}", value),
+        }
+    }
+
     pub fn negate(&self) -> Self {
-        match self {
-            Self::Decimal(f) => Self::Decimal(f * -1.0),
-            Self::Scientific(f) => Self::Scientific(f * -1.0),
+        Self {
+            value: -self.value,
+            notation: self.notation,
+            raw: negate_raw(&self.raw),
         }
     }
 }
@@ -329,33 +793,61 @@ impl FromStr for Float {
     type Err = Report<ModelError>;
 
     fn from_str(s: &str) -> Result<Self, ModelError> {
-        let float_value = s
+        let value = s
             .parse()
             .into_report()
             .change_context(ModelError::Float(s.to_owned()))?;
-        let f = if s.contains('e') || s.contains('E') {
-            Self::Scientific(float_value)
+        let notation = if s.contains('e') || s.contains('E') {
+            Notation::Scientific
         } else {
-            Self::Decimal(float_value)
+            Notation::Decimal
         };
-        Ok(f)
+        Ok(Self {
+            value,
+            notation,
+            raw: s.to_owned(),
+        })
+    }
+}
+
+impl Display for Float {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(&self.raw)
     }
 }
 
 impl Into<f64> for Float {
     fn into(self) -> f64 {
+        self.value
+    }
+}
+
+/// The opening sigil of a placeholder.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum PlaceholderStyle {
+    /// `~{...}`.
+    Tilde,
+    /// `${...}`, which is deprecated in WDL 1.1 and is not a placeholder within a heredoc
+    /// command.
+    Dollar,
+}
+
+impl PlaceholderStyle {
+    pub fn sigil(&self) -> char {
         match self {
-            Float::Decimal(f) => f,
-            Float::Scientific(f) => f,
+            Self::Tilde => '~',
+            Self::Dollar => '$',
         }
     }
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub enum StringPart {
     Content(String),
     Escape(String),
-    Placeholder(Expression),
+    Placeholder(Expression, PlaceholderStyle),
 }
 
 impl Display for StringPart {
@@ -363,21 +855,95 @@ impl Display for StringPart {
         match self {
             StringPart::Content(s) => write!(f, "{}", s),
             StringPart::Escape(s) => write!(f, "{}", s),
-            StringPart::Placeholder(_) => write!(f, "~{{..}}"),
+            StringPart::Placeholder(e, style) => write!(f, "{}{{{}}}", style.sigil(), e),
         }
     }
 }
 
+/// Resolves an escape sequence (including the leading backslash) to the text it represents, or
+/// returns `None` if the escape sequence is not valid. Supported escape sequences are `\\`,
+/// `\n`, `\t`, `\r`, `\"`, `\'`, `\~`, `\$`, octal (`\NNN`), hex (`\xNN`), and unicode
+/// (`\uNNNN`, `\UNNNNNNNN`, and `\u{N...}`).
+pub fn unescape(escape: &str) -> Option<String> {
+    let body = escape.strip_prefix('\\')?;
+    let mut chars = body.chars();
+    let first = chars.next()?;
+    let rest = chars.as_str();
+    let from_radix = |digits: &str, radix: u32| {
+        u32::from_str_radix(digits, radix)
+            .ok()
+            .and_then(char::from_u32)
+    };
+    let c = match (first, rest) {
+        ('\\', "") => '\\',
+        ('n', "") => '\n',
+        ('t', "") => '\t',
+        ('r', "") => '\r',
+        ('"', "") => '"',
+        ('\'', "") => '\'',
+        ('~', "") => '~',
+        ('$', "") => '$',
+        ('x', hex) if hex.len() == 2 => from_radix(hex, 16)?,
+        ('u', hex) if hex.starts_with('{') && hex.ends_with('}') && hex.len() > 2 => {
+            from_radix(&hex[1..hex.len() - 1], 16)?
+        }
+        ('u', hex) if hex.len() == 4 => from_radix(hex, 16)?,
+        ('U', hex) if hex.len() == 8 => from_radix(hex, 16)?,
+        ('0'..='7', oct) if oct.len() == 2 => from_radix(body, 8)?,
+        _ => return None,
+    };
+    Some(c.to_string())
+}
+
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct StringLiteral {
     pub parts: Vec<Anchor<StringPart>>,
 }
 
+impl StringLiteral {
+    /// Returns `true` if this string does not contain any placeholders.
+    pub fn is_constant(&self) -> bool {
+        !self
+            .parts
+            .iter()
+            .any(|part| matches!(part.deref(), StringPart::Placeholder(_, _)))
+    }
+
+    /// Returns the value of this string with all escape sequences resolved. Returns a
+    /// `ModelError::Escape` error (with the span of the escape sequence attached) if the string
+    /// contains an invalid escape sequence, or a `ModelError::StringPlaceholder` error if it
+    /// contains a placeholder.
+    pub fn cooked(&self) -> Result<String, ModelError> {
+        let mut value = String::new();
+        for part in self.parts.iter() {
+            match part.deref() {
+                StringPart::Content(s) => value.push_str(s),
+                StringPart::Escape(s) => match unescape(s) {
+                    Some(unescaped) => value.push_str(&unescaped),
+                    None => bail!(Report::from(ModelError::Escape(s.clone()))
+                        .attach_printable(part.span.clone())),
+                },
+                StringPart::Placeholder(_, _) => {
+                    bail!(Report::from(ModelError::StringPlaceholder)
+                        .attach_printable(part.span.clone()))
+                }
+            }
+        }
+        Ok(value)
+    }
+}
+
 impl Display for StringLiteral {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         f.write_char('"')?;
         for part in self.parts.iter() {
-            write!(f, "{}", *part)?;
+            match part.deref() {
+                // content that came from a single-quoted string may contain unescaped double
+                // quotes
+                StringPart::Content(s) => write!(f, "{}", s.replace('"', "\\\""))?,
+                part => write!(f, "{}", part)?,
+            }
         }
         f.write_char('"')?;
         Ok(())
@@ -391,6 +957,7 @@ impl InnerSpan for StringLiteral {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct ArrayLiteral {
     pub elements: Vec<Anchor<Expression>>,
 }
@@ -402,12 +969,14 @@ impl InnerSpan for ArrayLiteral {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct MapEntry {
     pub key: Anchor<Expression>,
     pub value: Anchor<Expression>,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct MapLiteral {
     pub entries: Vec<Anchor<MapEntry>>,
 }
@@ -419,6 +988,7 @@ impl InnerSpan for MapLiteral {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct PairLiteral {
     pub left: InnerExpression,
     pub right: InnerExpression,
@@ -431,17 +1001,47 @@ impl InnerSpan for PairLiteral {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct ObjectField {
     pub name: Anchor<String>,
     pub expression: Anchor<Expression>,
 }
 
+/// The kind of an `ObjectLiteral`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum ObjectKind {
+    /// An `object { ... }` literal, which is deprecated in WDL 1.1.
+    Object,
+    /// A struct literal, e.g. `Sample { ... }` (WDL 1.1).
+    Struct,
+}
+
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct ObjectLiteral {
+    /// `object`, or the name of the struct for a struct literal.
     pub type_name: Anchor<String>,
+    pub kind: ObjectKind,
     pub fields: Vec<Anchor<ObjectField>>,
 }
 
+impl ObjectLiteral {
+    /// Creates an `ObjectLiteral` whose kind is determined by `type_name`.
+    pub fn new(type_name: Anchor<String>, fields: Vec<Anchor<ObjectField>>) -> Self {
+        let kind = if type_name.as_str() == "object" {
+            ObjectKind::Object
+        } else {
+            ObjectKind::Struct
+        };
+        Self {
+            type_name,
+            kind,
+            fields,
+        }
+    }
+}
+
 impl InnerSpan for ObjectLiteral {
     fn get_inner_span(&self) -> Option<Span> {
         let field_span = self.fields.get_inner_span().unwrap();
@@ -454,6 +1054,7 @@ const NEG: &str = "-";
 const NOT: &str = "!";
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub enum UnaryOperator {
     Pos,
     Neg,
@@ -489,6 +1090,7 @@ impl Display for UnaryOperator {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Unary {
     pub operator: UnaryOperator,
     pub expression: InnerExpression,
@@ -515,6 +1117,7 @@ const AND: &str = "&&";
 const OR: &str = "||";
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub enum BinaryOperator {
     Add,
     Sub,
@@ -580,6 +1183,7 @@ impl Display for BinaryOperator {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Binary {
     pub operator: BinaryOperator,
     pub left: InnerExpression,
@@ -592,7 +1196,72 @@ impl InnerSpan for Binary {
     }
 }
 
+impl Binary {
+    /// Creates a left-associative chain of binary expressions from `first` and a sequence of
+    /// `(operator, operand)` pairs, e.g. `a + b - c` becomes `(a + b) - c`. The span of each
+    /// intermediate expression extends from the start of its left operand to the end of its right
+    /// operand. Both parsers build chained operators with this function, so they produce
+    /// identical models regardless of how their grammars nest the operators. Returns `first` if
+    /// `rest` is empty.
+    pub fn fold<I>(first: Anchor<Expression>, rest: I) -> Anchor<Expression>
+    where
+        I: IntoIterator<Item = (BinaryOperator, Anchor<Expression>)>,
+    {
+        rest.into_iter().fold(first, |left, (operator, right)| {
+            let span = Span::from_range(&left.span, &right.span);
+            Anchor::new(
+                Expression::Binary(Binary {
+                    operator,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                }),
+                span,
+            )
+        })
+    }
+
+    /// Splits this expression into its first operand and the `(operator, operand)` pairs that
+    /// follow it, in source order. Nested binary expressions whose operators have the same
+    /// precedence as this expression's operator are flattened into the chain, regardless of
+    /// whether they are nested on the left or the right. Grouped (parenthesized) expressions are
+    /// never flattened. `Binary::fold(first, rest)` rebuilds the canonical form of the chain.
+    pub fn into_chain(
+        self,
+    ) -> (
+        Anchor<Expression>,
+        Vec<(BinaryOperator, Anchor<Expression>)>,
+    ) {
+        fn flatten(
+            operand: Anchor<Expression>,
+            operator: Option<BinaryOperator>,
+            precedence: u8,
+            chain: &mut Vec<(Option<BinaryOperator>, Anchor<Expression>)>,
+        ) {
+            match operand.element {
+                Expression::Binary(b) if b.operator.precedence() == precedence => {
+                    flatten(*b.left, operator, precedence, chain);
+                    flatten(*b.right, Some(b.operator), precedence, chain);
+                }
+                element => chain.push((operator, Anchor::new(element, operand.span))),
+            }
+        }
+
+        let precedence = self.operator.precedence();
+        let mut chain = Vec::new();
+        flatten(*self.left, None, precedence, &mut chain);
+        flatten(*self.right, Some(self.operator), precedence, &mut chain);
+        // the first operand is the only one that is not preceded by an operator
+        let mut operands = chain.into_iter();
+        let (_, first) = operands.next().unwrap();
+        let rest = operands
+            .map(|(operator, operand)| (operator.unwrap(), operand))
+            .collect();
+        (first, rest)
+    }
+}
+
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Apply {
     pub name: Anchor<String>,
     pub arguments: Vec<Anchor<Expression>>,
@@ -606,12 +1275,14 @@ impl InnerSpan for Apply {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub enum AccessOperation {
     Index(Expression),
     Field(String),
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Access {
     pub collection: InnerExpression,
     pub accesses: Vec<Anchor<AccessOperation>>,
@@ -625,6 +1296,7 @@ impl InnerSpan for Access {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Ternary {
     pub condition: InnerExpression,
     pub true_branch: InnerExpression,
@@ -643,6 +1315,7 @@ impl InnerSpan for Ternary {
 pub type InnerExpression = Box<Anchor<Expression>>;
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub enum Expression {
     None,
     Boolean(bool),
@@ -681,76 +1354,571 @@ impl InnerSpan for Expression {
     }
 }
 
-pub type InnerType = Box<Anchor<Type>>;
-
-#[derive(Debug, PartialEq)]
-pub enum Type {
-    Boolean,
-    Int,
-    Float,
-    String,
-    File,
-    Array { item: InnerType, non_empty: bool },
-    Map { key: InnerType, value: InnerType },
-    Pair { left: InnerType, right: InnerType },
-    Object,
-    User(String),
-    Optional(InnerType),
-}
-
-#[derive(Debug, PartialEq)]
-pub struct UnboundDeclaration {
-    pub type_: Anchor<Type>,
-    pub name: Anchor<String>,
-}
+/// Operator precedence levels, from lowest to highest.
+const PRECEDENCE_TERNARY: u8 = 1;
+const PRECEDENCE_UNARY: u8 = 8;
+const PRECEDENCE_POSTFIX: u8 = 9;
+const PRECEDENCE_PRIMARY: u8 = 10;
 
-impl InnerSpan for UnboundDeclaration {
-    fn get_inner_span(&self) -> Option<Span> {
-        Some(Span::from_range(&self.type_.span, &self.name.span))
+impl BinaryOperator {
+    /// Returns the precedence of this operator. Operators with higher precedence bind more
+    /// tightly. All binary operators are left-associative.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Self::Or => 2,
+            Self::And => 3,
+            Self::Eq | Self::Neq => 4,
+            Self::Gt | Self::Lt | Self::Gte | Self::Lte => 5,
+            Self::Add | Self::Sub => 6,
+            Self::Mul | Self::Div | Self::Mod => 7,
+        }
     }
 }
 
-#[derive(Debug, PartialEq)]
-pub struct BoundDeclaration {
-    pub type_: Anchor<Type>,
-    pub name: Anchor<String>,
-    pub expression: Anchor<Expression>,
-}
-
-impl InnerSpan for BoundDeclaration {
-    fn get_inner_span(&self) -> Option<Span> {
-        Some(Span::from_range(&self.type_.span, &self.expression.span))
+impl Expression {
+    /// Returns the precedence of this expression, which determines whether it must be enclosed in
+    /// parentheses when it is the operand of another expression. Groups have the precedence of
+    /// the expression they enclose.
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Ternary(_) => PRECEDENCE_TERNARY,
+            Self::Binary(b) => b.operator.precedence(),
+            Self::Unary(_) => PRECEDENCE_UNARY,
+            Self::Int(i) if i.value < 0 => PRECEDENCE_UNARY,
+            Self::Float(f) if f.value.is_sign_negative() => PRECEDENCE_UNARY,
+            Self::Apply(_) | Self::Access(_) => PRECEDENCE_POSTFIX,
+            Self::Group(g) => g.precedence(),
+            _ => PRECEDENCE_PRIMARY,
+        }
     }
-}
 
-/// Enumeration of sources from which a WDL document may be loaded.
-#[derive(Clone, Debug, PartialEq)]
-pub enum DocumentSource {
-    File(PathBuf),
-    Uri(String),
-    Unknown,
-}
+    /// Writes this expression, enclosed in parentheses if its precedence is lower than
+    /// `min_precedence`. Groups are written without parentheses unless they are required.
+    fn fmt_operand(&self, f: &mut Formatter<'_>, min_precedence: u8) -> FmtResult {
+        if let Self::Group(g) = self {
+            return g.fmt_operand(f, min_precedence);
+        }
+        if self.precedence() < min_precedence {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
 
-impl Default for DocumentSource {
-    fn default() -> Self {
-        Self::Unknown
+    /// Returns the WDL source code for this expression. This is equivalent to `to_string()`.
+    pub fn to_wdl_source(&self) -> String {
+        self.to_string()
     }
-}
 
-impl Display for DocumentSource {
-    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    /// Returns the expression enclosed by any parentheses around this expression.
+    pub fn ungroup(&self) -> &Expression {
         match self {
-            DocumentSource::File(path) => write!(f, "{}", path.display()),
-            DocumentSource::Uri(uri) => write!(f, "{}", uri),
-            DocumentSource::Unknown => write!(f, "<unknown>"),
+            Self::Group(group) => group.ungroup(),
+            _ => self,
         }
     }
-}
 
-#[derive(Clone, Debug, PartialEq)]
+    /// Returns the name of this expression if it is an identifier.
+    pub fn as_identifier(&self) -> Option<&str> {
+        match self {
+            Self::Identifier(name) => Some(name.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Returns the arguments of this expression if it is a call of the function `name`.
+    pub fn as_apply(&self, name: &str) -> Option<&[Anchor<Expression>]> {
+        match self {
+            Self::Apply(apply) if apply.name.as_str() == name => Some(&apply.arguments),
+            _ => None,
+        }
+    }
+
+    /// Returns the operands of this expression if it is a binary operation with `operator`.
+    pub fn as_binary(
+        &self,
+        operator: BinaryOperator,
+    ) -> Option<(&Anchor<Expression>, &Anchor<Expression>)> {
+        match self {
+            Self::Binary(binary) if binary.operator == operator => {
+                Some((&binary.left, &binary.right))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn fmt_list<T: Display>(f: &mut Formatter<'_>, items: &[Anchor<T>]) -> FmtResult {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}", item.deref())?;
+    }
+    Ok(())
+}
+
+impl Display for MapEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}: {}", self.key.deref(), self.value.deref())
+    }
+}
+
+impl Display for ObjectField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}: {}", self.name.as_str(), self.expression.deref())
+    }
+}
+
+/// Writes expressions as valid WDL source code, with the minimum parentheses required by operator
+/// precedence.
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::None => f.write_str("None"),
+            Self::Boolean(b) => write!(f, "{}", b),
+            Self::Int(i) => write!(f, "{}", i),
+            Self::Float(x) => write!(f, "{}", x),
+            Self::String(s) => write!(f, "{}", s),
+            Self::Array(a) => {
+                f.write_char('[')?;
+                fmt_list(f, &a.elements)?;
+                f.write_char(']')
+            }
+            Self::Map(m) => {
+                f.write_char('{')?;
+                fmt_list(f, &m.entries)?;
+                f.write_char('}')
+            }
+            Self::Pair(p) => write!(
+                f,
+                "({}, {})",
+                p.left.deref().deref(),
+                p.right.deref().deref()
+            ),
+            Self::Object(o) => {
+                if o.fields.is_empty() {
+                    write!(f, "{} {{}}", o.type_name.as_str())
+                } else {
+                    write!(f, "{} {{ ", o.type_name.as_str())?;
+                    fmt_list(f, &o.fields)?;
+                    f.write_str(" }")
+                }
+            }
+            Self::Unary(u) => {
+                write!(f, "{}", u.operator)?;
+                // nested unary operators (and negative literals) are parenthesized to avoid
+                // emitting e.g. `--x`
+                u.expression.fmt_operand(f, PRECEDENCE_POSTFIX)
+            }
+            Self::Binary(b) => {
+                let precedence = b.operator.precedence();
+                b.left.fmt_operand(f, precedence)?;
+                write!(f, " {} ", b.operator)?;
+                b.right.fmt_operand(f, precedence + 1)
+            }
+            Self::Apply(a) => {
+                write!(f, "{}(", a.name.as_str())?;
+                fmt_list(f, &a.arguments)?;
+                f.write_char(')')
+            }
+            Self::Access(a) => {
+                a.collection.fmt_operand(f, PRECEDENCE_POSTFIX)?;
+                for access in a.accesses.iter() {
+                    match access.deref() {
+                        AccessOperation::Index(i) => write!(f, "[{}]", i)?,
+                        AccessOperation::Field(name) => write!(f, ".{}", name)?,
+                    }
+                }
+                Ok(())
+            }
+            Self::Ternary(t) => write!(
+                f,
+                "if {} then {} else {}",
+                t.condition.deref().deref(),
+                t.true_branch.deref().deref(),
+                t.false_branch.deref().deref()
+            ),
+            Self::Group(g) => write!(f, "{}", g.deref().deref()),
+            Self::Identifier(name) => f.write_str(name),
+        }
+    }
+}
+
+pub type InnerType = Box<Anchor<Type>>;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Type {
+    Boolean,
+    Int,
+    Float,
+    String,
+    File,
+    Array { item: InnerType, non_empty: bool },
+    Map { key: InnerType, value: InnerType },
+    Pair { left: InnerType, right: InnerType },
+    Object,
+    User(String),
+    Optional(InnerType),
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Boolean => f.write_str("Boolean"),
+            Self::Int => f.write_str("Int"),
+            Self::Float => f.write_str("Float"),
+            Self::String => f.write_str("String"),
+            Self::File => f.write_str("File"),
+            Self::Array { item, non_empty } => {
+                write!(f, "Array[{}]", item.deref().deref())?;
+                if *non_empty {
+                    f.write_char('+')?;
+                }
+                Ok(())
+            }
+            Self::Map { key, value } => {
+                write!(f, "Map[{}, {}]", key.deref().deref(), value.deref().deref())
+            }
+            Self::Pair { left, right } => {
+                write!(
+                    f,
+                    "Pair[{}, {}]",
+                    left.deref().deref(),
+                    right.deref().deref()
+                )
+            }
+            Self::Object => f.write_str("Object"),
+            Self::User(name) => f.write_str(name),
+            Self::Optional(inner) => write!(f, "{}?", inner.deref().deref()),
+        }
+    }
+}
+
+/// Parses a type from its WDL source representation, e.g. `Map[String, Array[File?]+]`. The
+/// spans of the resulting type and its inner types are relative to the parsed string, which must
+/// not contain any line breaks.
+impl FromStr for Type {
+    type Err = Report<ModelError>;
+
+    fn from_str(s: &str) -> Result<Self, ModelError> {
+        let mut parser = TypeParser { text: s, offset: 0 };
+        let type_ = parser.parse_type()?;
+        parser.skip_whitespace();
+        ensure!(parser.offset == s.len(), ModelError::Type(s.to_owned()));
+        Ok(type_.into_element())
+    }
+}
+
+/// Simple recursive-descent parser for types.
+struct TypeParser<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+impl<'a> TypeParser<'a> {
+    fn error(&self) -> Report<ModelError> {
+        Report::from(ModelError::Type(self.text.to_owned()))
+            .attach_printable(format!("at offset {}", self.offset))
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.offset..];
+        self.offset += rest.len() - rest.trim_start().len();
+    }
+
+    fn consume(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.text[self.offset..].starts_with(c) {
+            self.offset += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ModelError> {
+        if self.consume(c) {
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn identifier(&mut self) -> Result<&'a str, ModelError> {
+        self.skip_whitespace();
+        let rest = &self.text[self.offset..];
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        if len == 0 || !rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            return Err(self.error());
+        }
+        self.offset += len;
+        Ok(&rest[..len])
+    }
+
+    fn span(&self, start: usize) -> Span {
+        Span::from_components(0, start, start, 0, self.offset, self.offset)
+    }
+
+    fn parse_type(&mut self) -> Result<Anchor<Type>, ModelError> {
+        self.skip_whitespace();
+        let start = self.offset;
+        let name = self.identifier()?;
+        let mut type_ = match name {
+            "Boolean" => Type::Boolean,
+            "Int" => Type::Int,
+            "Float" => Type::Float,
+            "String" => Type::String,
+            "File" => Type::File,
+            "Object" => Type::Object,
+            "Array" => {
+                self.expect('[')?;
+                let item = Box::new(self.parse_type()?);
+                self.expect(']')?;
+                Type::Array {
+                    item,
+                    non_empty: self.consume('+'),
+                }
+            }
+            "Map" | "Pair" => {
+                self.expect('[')?;
+                let first = Box::new(self.parse_type()?);
+                self.expect(',')?;
+                let second = Box::new(self.parse_type()?);
+                self.expect(']')?;
+                if name == "Map" {
+                    Type::Map {
+                        key: first,
+                        value: second,
+                    }
+                } else {
+                    Type::Pair {
+                        left: first,
+                        right: second,
+                    }
+                }
+            }
+            _ => Type::User(name.to_owned()),
+        };
+        let inner_span = self.span(start);
+        if self.consume('?') {
+            type_ = Type::Optional(Box::new(Anchor::new(type_, inner_span)));
+        }
+        Ok(Anchor::new(type_, self.span(start)))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct UnboundDeclaration {
+    pub type_: Anchor<Type>,
+    pub name: Anchor<String>,
+}
+
+impl InnerSpan for UnboundDeclaration {
+    fn get_inner_span(&self) -> Option<Span> {
+        Some(Span::from_range(&self.type_.span, &self.name.span))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct BoundDeclaration {
+    pub type_: Anchor<Type>,
+    pub name: Anchor<String>,
+    pub expression: Anchor<Expression>,
+}
+
+impl InnerSpan for BoundDeclaration {
+    fn get_inner_span(&self) -> Option<Span> {
+        Some(Span::from_range(&self.type_.span, &self.expression.span))
+    }
+}
+
+/// Enumeration of sources from which a WDL document may be loaded.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum DocumentSource {
+    File(PathBuf),
+    Uri(String),
+    Unknown,
+}
+
+impl Default for DocumentSource {
+    fn default() -> Self {
+        Self::Unknown
+    }
+}
+
+impl Display for DocumentSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            DocumentSource::File(path) => write!(f, "{}", path.display()),
+            DocumentSource::Uri(uri) => write!(f, "{}", uri),
+            DocumentSource::Unknown => write!(f, "<unknown>"),
+        }
+    }
+}
+
+/// The SHA-256 digest of the text from which a document was parsed. Displays as a lowercase hex
+/// string.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SourceDigest([u8; 32]);
+
+impl SourceDigest {
+    /// Returns the digest of `text`.
+    pub fn of<T: AsRef<[u8]>>(text: T) -> Self {
+        Self(Sha256::digest(text.as_ref()).into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl Display for SourceDigest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        for byte in self.0.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for SourceDigest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "SourceDigest({})", self)
+    }
+}
+
+/// The byte offsets at which the lines of a text start, for converting between offsets and
+/// lines without scanning the text.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+    len: usize,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            line_starts,
+            len: text.len(),
+        }
+    }
+
+    /// Returns the number of lines. A text that ends with a newline has an empty last line.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Returns the (zero-based) line that contains `offset`, or `None` if `offset` is past the
+    /// end of the text.
+    pub fn line_of(&self, offset: usize) -> Option<usize> {
+        (offset <= self.len).then(|| self.line_starts.partition_point(|start| *start <= offset) - 1)
+    }
+
+    /// Returns the range of offsets of `line`, excluding its line ending.
+    pub fn line_range(&self, line: usize) -> Option<Range<usize>> {
+        let start = *self.line_starts.get(line)?;
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .map(|next| next - 1)
+            .unwrap_or(self.len);
+        Some(start..end)
+    }
+}
+
+/// The text from which a document was parsed, together with its `LineIndex`. Cloning is cheap.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SourceText {
+    text: Arc<str>,
+    lines: Arc<LineIndex>,
+}
+
+impl SourceText {
+    pub fn new<T: Into<Arc<str>>>(text: T) -> Self {
+        let text = text.into();
+        let lines = Arc::new(LineIndex::new(&text));
+        Self { text, lines }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn line_index(&self) -> &LineIndex {
+        &self.lines
+    }
+
+    /// Returns the text of `span`, or `None` if the span is not within the text.
+    pub fn slice(&self, span: &Span) -> Option<&str> {
+        self.text.get(span.start.offset..span.end.offset)
+    }
+
+    /// Returns the text of `line`, excluding its line ending.
+    pub fn line(&self, line: usize) -> Option<&str> {
+        let text = &self.text[self.lines.line_range(line)?];
+        Some(text.strip_suffix('\r').unwrap_or(text))
+    }
+
+    /// Returns the position of `offset`, or `None` if it is not at a character boundary of the
+    /// text.
+    pub fn position(&self, offset: usize) -> Option<Position> {
+        let line = self.lines.line_of(offset)?;
+        let line_start = self.lines.line_starts[line];
+        let column = self.text.get(line_start..offset)?.chars().count();
+        Some(Position::new(line, column, offset))
+    }
+}
+
+impl Debug for SourceText {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "SourceText({} bytes, {} lines)",
+            self.text.len(),
+            self.lines.line_count()
+        )
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for SourceDigest {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// A WDL version. Versions are ordered from earliest to latest.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub enum VersionIdentifier {
     V1_0,
     V1_1,
+    /// The unreleased version of WDL that is under development, which is assumed to support all
+    /// of the features of the latest release.
+    Development,
+}
+
+impl Display for VersionIdentifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::V1_0 => write!(f, "1.0"),
+            Self::V1_1 => write!(f, "1.1"),
+            Self::Development => write!(f, "development"),
+        }
+    }
 }
 
 impl FromStr for VersionIdentifier {
@@ -760,41 +1928,107 @@ impl FromStr for VersionIdentifier {
         match s {
             "1.0" => Ok(VersionIdentifier::V1_0),
             "1.1" => Ok(VersionIdentifier::V1_1),
+            "development" => Ok(VersionIdentifier::Development),
             _ => bail!(ModelError::Version(s.to_owned())),
         }
     }
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Version {
     pub identifier: Anchor<VersionIdentifier>,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub enum Namespace {
     Explicit(Anchor<String>),
     Implicit(String),
 }
 
 impl Namespace {
+    /// Infers the implicit namespace of an import from its URI: the name of the last segment of
+    /// the URI's path, without the `.wdl` extension. Query strings, fragments, and trailing
+    /// slashes are ignored, and percent-encoded characters are decoded.
     pub fn from_uri<S: AsRef<str>>(uri: S) -> Self {
-        let s = uri.as_ref();
-        let ns = match Regex::new(r".*/(.+?)(?:\.wdl)?").unwrap().captures(s) {
-            Some(cap) => cap[1].to_owned(),
-            None if s.ends_with(".wdl") => s[0..s.len() - 4].to_owned(),
-            None => s.to_owned(),
-        };
-        Self::Implicit(ns.to_owned())
+        let segment = last_path_segment(uri.as_ref());
+        let name = segment.strip_suffix(".wdl").unwrap_or(&segment);
+        Self::Implicit(name.to_owned())
+    }
+
+    /// Returns the identifier by which the namespace is referenced, whether explicit or implicit.
+    pub fn identifier(&self) -> &str {
+        match self {
+            Self::Explicit(name) => name.as_str(),
+            Self::Implicit(name) => name.as_str(),
+        }
+    }
+}
+
+/// Returns the decoded last non-empty segment of the path of `uri`.
+#[cfg(feature = "url")]
+fn last_path_segment(uri: &str) -> String {
+    match url::Url::parse(uri) {
+        Ok(url) if !url.cannot_be_a_base() => url
+            .path_segments()
+            .and_then(|segments| segments.rev().find(|segment| !segment.is_empty()))
+            .map(percent_decode)
+            .unwrap_or_else(|| url.host_str().unwrap_or_default().to_owned()),
+        // relative URIs cannot be parsed without a base
+        _ => last_path_segment_lexical(uri),
+    }
+}
+
+#[cfg(not(feature = "url"))]
+fn last_path_segment(uri: &str) -> String {
+    last_path_segment_lexical(uri)
+}
+
+/// Returns the decoded last non-empty segment of the path of `uri`, without parsing it as a URL.
+fn last_path_segment_lexical(uri: &str) -> String {
+    let end = uri.find(['?', '#']).unwrap_or(uri.len());
+    let path = uri[..end].trim_end_matches('/');
+    let segment = match path.rfind('/') {
+        Some(i) => &path[i + 1..],
+        None => path,
+    };
+    percent_decode(segment)
+}
+
+/// Decodes percent-encoded bytes in `s`. Invalid escapes are left as-is, and invalid UTF-8 is
+/// replaced with the replacement character.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(byte) = s
+                .get(i + 1..i + 3)
+                .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
     }
+    String::from_utf8_lossy(&decoded).into_owned()
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Alias {
     pub from: Anchor<String>,
     pub to: Anchor<String>,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Import {
     pub uri: Anchor<String>,
     pub namespace: Namespace,
@@ -802,17 +2036,50 @@ pub struct Import {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Struct {
     pub name: Anchor<String>,
     pub fields: Vec<Anchor<UnboundDeclaration>>,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub enum InputDeclaration {
     Bound(BoundDeclaration),
     Unbound(UnboundDeclaration),
 }
 
+impl InputDeclaration {
+    pub fn name(&self) -> &Anchor<String> {
+        match self {
+            Self::Bound(decl) => &decl.name,
+            Self::Unbound(decl) => &decl.name,
+        }
+    }
+
+    pub fn type_(&self) -> &Anchor<Type> {
+        match self {
+            Self::Bound(decl) => &decl.type_,
+            Self::Unbound(decl) => &decl.type_,
+        }
+    }
+
+    /// Returns the default value of this input if it is a constant, i.e. an expression that does
+    /// not reference any other declaration and only uses literals, operators, and the pure
+    /// standard library functions that `eval::evaluate` supports. The value is coerced to the
+    /// declared type of the input, e.g. `Float x = 1` has the default value `1.0`. Returns `None`
+    /// if the input is unbound or its default is dynamic.
+    pub fn default_value(&self) -> Option<WdlValue> {
+        match self {
+            Self::Bound(decl) => eval::evaluate(&decl.expression, &Environment::new())
+                .ok()?
+                .coerce(&decl.type_)
+                .ok(),
+            Self::Unbound(_) => None,
+        }
+    }
+}
+
 impl InnerSpan for InputDeclaration {
     fn get_inner_span(&self) -> Option<Span> {
         match self {
@@ -823,43 +2090,65 @@ impl InnerSpan for InputDeclaration {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Input {
     pub declarations: Vec<Anchor<InputDeclaration>>,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Output {
     pub declarations: Vec<Anchor<BoundDeclaration>>,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub enum MetaStringPart {
     Content(String),
     Escape(String),
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct MetaString {
     pub parts: Vec<Anchor<MetaStringPart>>,
 }
 
+impl MetaString {
+    /// Returns the text of this string with escape sequences resolved. Invalid escape sequences
+    /// are left as-is.
+    pub fn text(&self) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part.deref() {
+                MetaStringPart::Content(s) => s.clone(),
+                MetaStringPart::Escape(s) => unescape(s).unwrap_or_else(|| s.clone()),
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct MetaArray {
     pub elements: Vec<Anchor<MetaValue>>,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct MetaObjectField {
     pub name: Anchor<String>,
     pub value: Anchor<MetaValue>,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct MetaObject {
     pub fields: Vec<Anchor<MetaObjectField>>,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub enum MetaValue {
     Null,
     Boolean(bool),
@@ -870,28 +2159,176 @@ pub enum MetaValue {
     Object(MetaObject),
 }
 
+impl MetaValue {
+    /// Returns the text of this value if it is a scalar (i.e. not null, an array, or an object).
+    pub fn text(&self) -> Option<String> {
+        match self {
+            Self::Boolean(b) => Some(b.to_string()),
+            Self::Int(i) => Some(i.value.to_string()),
+            Self::Float(f) => Some(f.value.to_string()),
+            Self::String(s) => Some(s.text()),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the field `name` if this value is an object with such a field.
+    pub fn get_field(&self, name: &str) -> Option<&Anchor<MetaValue>> {
+        match self {
+            Self::Object(o) => o
+                .fields
+                .iter()
+                .find(|field| field.name.as_str() == name)
+                .map(|field| &field.value),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct MetaAttribute {
     pub name: Anchor<String>,
     pub value: Anchor<MetaValue>,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Meta {
     pub attributes: Vec<Anchor<MetaAttribute>>,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct ParameterMeta {
     pub attributes: Vec<Anchor<MetaAttribute>>,
 }
 
+/// Documentation for a task or workflow parameter, extracted from its `parameter_meta` entry.
+/// The entry may either be a string, which is taken to be the description, or an object with any
+/// of the fields `description`, `help`, `label`, and `choices`.
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ParameterDescription {
+    pub name: String,
+    pub description: Option<String>,
+    pub help: Option<String>,
+    pub label: Option<String>,
+    /// The allowed values of the parameter, or an empty `Vec` if they are not specified.
+    pub choices: Vec<String>,
+}
+
+impl ParameterDescription {
+    fn from_attributes(attributes: &[Anchor<MetaAttribute>], name: &str) -> Option<Self> {
+        let attribute = attributes
+            .iter()
+            .find(|attribute| attribute.name.as_str() == name)?;
+        let mut desc = Self {
+            name: name.to_owned(),
+            ..Default::default()
+        };
+        match attribute.value.deref() {
+            MetaValue::String(s) => desc.description = Some(s.text()),
+            value @ MetaValue::Object(_) => {
+                let text = |field| value.get_field(field).and_then(|v| v.text());
+                desc.description = text("description");
+                desc.help = text("help");
+                desc.label = text("label");
+                if let Some(choices) = value.get_field("choices") {
+                    if let MetaValue::Array(a) = choices.deref() {
+                        desc.choices = a.elements.iter().filter_map(|e| e.text()).collect();
+                    }
+                }
+            }
+            _ => (),
+        }
+        Some(desc)
+    }
+}
+
+/// The delimiters of a `command` section, which determine the placeholder syntax that is legal
+/// within it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum CommandStyle {
+    /// `command { ... }`, within which both `~{...}` and `${...}` are placeholders.
+    Braces,
+    /// `command <<< ... >>>`, within which only `~{...}` is a placeholder and `${...}` is passed
+    /// to the shell unchanged.
+    Heredoc,
+}
+
+impl CommandStyle {
+    /// Returns the opening and closing delimiters of a command with this style.
+    pub fn delimiters(&self) -> (&'static str, &'static str) {
+        match self {
+            Self::Braces => ("{", "}"),
+            Self::Heredoc => ("<<<", ">>>"),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Command {
+    pub style: CommandStyle,
     pub parts: Vec<Anchor<StringPart>>,
 }
 
+impl Command {
+    /// Returns the name and span of each `${name}` within the content of this command, where
+    /// `name` is an identifier, excluding any whose `$` is escaped. These are placeholders in a
+    /// command with braces, but are passed to the shell unchanged in a heredoc command.
+    pub fn dollar_identifiers(&self) -> Vec<(&str, Span)> {
+        let mut identifiers = Vec::new();
+        for part in self.parts.iter() {
+            let content = match part.deref() {
+                StringPart::Content(content) => content.as_str(),
+                _ => continue,
+            };
+            for (i, _) in content.match_indices("${") {
+                if content[..i].ends_with('\\') {
+                    continue;
+                }
+                let rest = &content[i + 2..];
+                let len = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                let name = &rest[..len];
+                let is_identifier = name.starts_with(|c: char| c.is_ascii_alphabetic());
+                if !is_identifier || !rest[len..].starts_with('}') {
+                    continue;
+                }
+                let position = |offset: usize| {
+                    let before = &content[..offset];
+                    let start = &part.span.start;
+                    match before.rfind('\n') {
+                        Some(newline) => Position::new(
+                            start.line + before.matches('\n').count(),
+                            before[newline + 1..].chars().count(),
+                            start.offset + offset,
+                        ),
+                        None => Position::new(
+                            start.line,
+                            start.column + before.chars().count(),
+                            start.offset + offset,
+                        ),
+                    }
+                };
+                identifiers.push((name, Span::new(position(i), position(i + len + 3))));
+            }
+        }
+        identifiers
+    }
+
+    /// Returns the text of this command with each placeholder replaced by a marker of the form
+    /// `__wdl_placeholder_N__`, for analysis by shell linters.
+    pub fn shell_template(&self) -> ShellTemplate {
+        ShellTemplate::new(self, |i, _| ShellTemplate::default_marker(i))
+    }
+}
+
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct RuntimeAttribute {
     pub name: Anchor<String>,
     pub expression: Anchor<Expression>,
@@ -904,11 +2341,201 @@ impl InnerSpan for RuntimeAttribute {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Runtime {
     pub attributes: Vec<Anchor<RuntimeAttribute>>,
 }
 
+/// A runtime attribute key. The keys defined by the WDL 1.1 spec are recognized along with the
+/// hints that are conventionally used by engines such as Cromwell (e.g. `preemptible`), and any
+/// other key is `Other`. `docker` is an alias for `container`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RuntimeKey {
+    Container,
+    Cpu,
+    Memory,
+    Gpu,
+    Disks,
+    MaxRetries,
+    ReturnCodes,
+    Preemptible,
+    GpuType,
+    GpuCount,
+    Zones,
+    BootDiskSizeGb,
+    Other(String),
+}
+
+impl RuntimeKey {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "container" | "docker" => Self::Container,
+            "cpu" => Self::Cpu,
+            "memory" => Self::Memory,
+            "gpu" => Self::Gpu,
+            "disks" => Self::Disks,
+            "maxRetries" => Self::MaxRetries,
+            "returnCodes" => Self::ReturnCodes,
+            "preemptible" => Self::Preemptible,
+            "gpuType" => Self::GpuType,
+            "gpuCount" => Self::GpuCount,
+            "zones" => Self::Zones,
+            "bootDiskSizeGb" => Self::BootDiskSizeGb,
+            other => Self::Other(other.to_owned()),
+        }
+    }
+
+    /// Returns the canonical name of this key.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Container => "container",
+            Self::Cpu => "cpu",
+            Self::Memory => "memory",
+            Self::Gpu => "gpu",
+            Self::Disks => "disks",
+            Self::MaxRetries => "maxRetries",
+            Self::ReturnCodes => "returnCodes",
+            Self::Preemptible => "preemptible",
+            Self::GpuType => "gpuType",
+            Self::GpuCount => "gpuCount",
+            Self::Zones => "zones",
+            Self::BootDiskSizeGb => "bootDiskSizeGb",
+            Self::Other(name) => name.as_str(),
+        }
+    }
+
+    /// Returns whether this key is one of the reserved keys of the WDL 1.1 spec, as opposed to an
+    /// engine-specific hint.
+    pub fn is_reserved(&self) -> bool {
+        matches!(
+            self,
+            Self::Container
+                | Self::Cpu
+                | Self::Memory
+                | Self::Gpu
+                | Self::Disks
+                | Self::MaxRetries
+                | Self::ReturnCodes
+        )
+    }
+}
+
+impl Display for RuntimeKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Typed accessors for runtime attributes. An accessor returns `None` if the attribute is missing,
+/// if its value is not a constant (see `InputDeclaration::default_value`), or if its value does
+/// not have the expected type, so callers can fall back to their own defaults.
+impl Runtime {
+    /// Returns the attribute with key `key`. If the key is given more than once (e.g. as both
+    /// `container` and `docker`), the first attribute is returned.
+    pub fn get(&self, key: &RuntimeKey) -> Option<&Anchor<RuntimeAttribute>> {
+        self.attributes
+            .iter()
+            .find(|attribute| &RuntimeKey::from_name(attribute.name.as_str()) == key)
+    }
+
+    /// Returns the value of the attribute with key `key` if it is a constant.
+    pub fn value(&self, key: &RuntimeKey) -> Option<WdlValue> {
+        self.value_in(key, &Environment::new())
+    }
+
+    /// Returns the value of the attribute with key `key` evaluated in `env`, e.g. with the task's
+    /// inputs bound to their values.
+    pub fn value_in(&self, key: &RuntimeKey, env: &Environment) -> Option<WdlValue> {
+        eval::evaluate(&self.get(key)?.expression, env).ok()
+    }
+
+    /// Returns the attributes whose keys are not recognized.
+    pub fn unknown(&self) -> impl Iterator<Item = &Anchor<RuntimeAttribute>> {
+        self.attributes.iter().filter(|attribute| {
+            matches!(
+                RuntimeKey::from_name(attribute.name.as_str()),
+                RuntimeKey::Other(_)
+            )
+        })
+    }
+
+    /// Returns the container image(s) in which the task may run, in order of preference. The
+    /// value may be either a single image or an array of images.
+    pub fn container(&self) -> Option<Vec<String>> {
+        match self.value(&RuntimeKey::Container)? {
+            WdlValue::String(image) => Some(vec![image]),
+            WdlValue::Array(images) => images
+                .into_iter()
+                .map(|image| match image {
+                    WdlValue::String(image) => Some(image),
+                    _ => None,
+                })
+                .collect(),
+            _ => None,
+        }
+    }
+
+    pub fn cpu(&self) -> Option<f64> {
+        self.float(&RuntimeKey::Cpu)
+    }
+
+    /// Returns the amount of memory, which may be given as a number of bytes or as a string
+    /// with units, e.g. `"8 GB"`.
+    pub fn memory(&self) -> Option<Quantity> {
+        units::memory(&self.value(&RuntimeKey::Memory)?)
+    }
+
+    /// Returns the disks, which may be given as a size in GiB or as a string of comma-separated
+    /// disk specifications, e.g. `"local-disk 50 HDD"`.
+    pub fn disks(&self) -> Option<Vec<Disk>> {
+        units::disks(&self.value(&RuntimeKey::Disks)?)
+    }
+
+    pub fn gpu(&self) -> Option<bool> {
+        match self.value(&RuntimeKey::Gpu)? {
+            WdlValue::Boolean(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn max_retries(&self) -> Option<i64> {
+        self.int(&RuntimeKey::MaxRetries)
+    }
+
+    /// Returns the number of times the task may be run on a preemptible instance.
+    pub fn preemptible(&self) -> Option<i64> {
+        self.int(&RuntimeKey::Preemptible)
+    }
+
+    pub fn gpu_type(&self) -> Option<String> {
+        match self.value(&RuntimeKey::GpuType)? {
+            WdlValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn gpu_count(&self) -> Option<i64> {
+        self.int(&RuntimeKey::GpuCount)
+    }
+
+    fn int(&self, key: &RuntimeKey) -> Option<i64> {
+        match self.value(key)? {
+            WdlValue::Int(i) => Some(i),
+            _ => None,
+        }
+    }
+
+    fn float(&self, key: &RuntimeKey) -> Option<f64> {
+        match self.value(key)? {
+            WdlValue::Int(i) => Some(i as f64),
+            WdlValue::Float(f) => Some(f),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub enum TaskElement {
     Input(Input),
     Output(Output),
@@ -934,14 +2561,70 @@ impl TaskElement {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Task {
     pub name: Anchor<String>,
     pub body: Vec<Anchor<TaskElement>>,
 }
 
 impl Task {
+    /// Returns this task's `input` section, if any.
+    pub fn inputs(&self) -> Option<&Input> {
+        self.body.iter().find_map(|element| match element.deref() {
+            TaskElement::Input(input) => Some(input),
+            _ => None,
+        })
+    }
+
+    /// Returns this task's `output` section, if any.
+    pub fn outputs(&self) -> Option<&Output> {
+        self.body.iter().find_map(|element| match element.deref() {
+            TaskElement::Output(output) => Some(output),
+            _ => None,
+        })
+    }
+
+    /// Returns this task's `command` section, if any.
+    pub fn command(&self) -> Option<&Command> {
+        self.body.iter().find_map(|element| match element.deref() {
+            TaskElement::Command(command) => Some(command),
+            _ => None,
+        })
+    }
+
+    /// Returns this task's `runtime` section, if any.
+    pub fn runtime(&self) -> Option<&Runtime> {
+        self.body.iter().find_map(|element| match element.deref() {
+            TaskElement::Runtime(runtime) => Some(runtime),
+            _ => None,
+        })
+    }
+
+    /// Returns the documentation for the parameter `name` from this task's `parameter_meta`
+    /// section, or `None` if there is no entry for `name`.
+    pub fn parameter_description(&self, name: &str) -> Option<ParameterDescription> {
+        self.body.iter().find_map(|element| match element.deref() {
+            TaskElement::ParameterMeta(meta) => {
+                ParameterDescription::from_attributes(&meta.attributes, name)
+            }
+            _ => None,
+        })
+    }
+
     pub fn validate(&self) -> Result<(), ModelError> {
+        self.check(&ValidationOptions::default(), &mut Vec::new())
+    }
+
+    fn check(
+        &self,
+        options: &ValidationOptions,
+        warnings: &mut Vec<Diagnostic>,
+    ) -> Result<(), ModelError> {
         let mut seen = HashSet::with_capacity(6);
+        let has_parameter_meta = self
+            .body
+            .iter()
+            .any(|element| matches!(element.deref(), TaskElement::ParameterMeta(_)));
         for element in self.body.iter() {
             let must_be_unique = match element.deref() {
                 TaskElement::Input(_)
@@ -954,26 +2637,70 @@ impl Task {
             };
             if must_be_unique {
                 let kind = element.kind();
-                ensure!(
-                    !seen.contains(kind),
-                    Report::from(ModelError::TaskRepeatedElement {
-                        task: self.name.clone(),
-                        kind: kind.to_owned()
-                    })
-                    .attach_printable(element.span.clone())
-                );
+                if seen.contains(kind) {
+                    options.repeated_sections.violation(
+                        ModelError::TaskRepeatedElement {
+                            task: (*self.name).clone(),
+                            kind: kind.to_owned(),
+                        },
+                        &element.span,
+                        vec![fix::repeated_section(
+                            kind,
+                            &element.span,
+                            has_parameter_meta,
+                        )],
+                        warnings,
+                    )?;
+                }
                 seen.insert(kind);
             }
         }
-        ensure!(
-            seen.contains("command"),
-            ModelError::TaskMissingCommand(self.name.clone())
-        );
+        if !seen.contains("command") {
+            options.missing_command.violation(
+                ModelError::TaskMissingCommand((*self.name).clone()),
+                &self.name.span,
+                Vec::new(),
+                warnings,
+            )?;
+        }
+        if let Some(command) = self
+            .command()
+            .filter(|command| command.style == CommandStyle::Heredoc)
+        {
+            let declared: HashSet<&str> = self
+                .inputs()
+                .into_iter()
+                .flat_map(|input| input.declarations.iter())
+                .map(|decl| decl.name().as_str())
+                .chain(
+                    self.body
+                        .iter()
+                        .filter_map(|element| match element.deref() {
+                            TaskElement::Declaration(decl) => Some(decl.name.as_str()),
+                            _ => None,
+                        }),
+                )
+                .collect();
+            for (name, span) in command.dollar_identifiers() {
+                if declared.contains(name) {
+                    options.heredoc_placeholders.violation(
+                        ModelError::HeredocDollarPlaceholder {
+                            task: (*self.name).clone(),
+                            name: name.to_owned(),
+                        },
+                        &span,
+                        vec![fix::tilde_placeholder(&span)],
+                        warnings,
+                    )?;
+                }
+            }
+        }
         Ok(())
     }
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct QualifiedIdentifier {
     pub parts: Vec<Anchor<String>>,
 }
@@ -985,6 +2712,7 @@ impl InnerSpan for QualifiedIdentifier {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct CallInput {
     pub name: Anchor<String>,
     pub expression: Option<Anchor<Expression>>,
@@ -1004,6 +2732,7 @@ impl InnerSpan for CallInput {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Call {
     pub target: Anchor<QualifiedIdentifier>,
     pub alias: Option<Anchor<String>>,
@@ -1029,6 +2758,7 @@ impl InnerSpan for Call {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Scatter {
     pub name: Anchor<String>,
     pub expression: Anchor<Expression>,
@@ -1036,12 +2766,14 @@ pub struct Scatter {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Conditional {
     pub expression: Anchor<Expression>,
     pub body: Vec<Anchor<WorkflowNestedElement>>,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub enum WorkflowNestedElement {
     Declaration(BoundDeclaration),
     Call(Call),
@@ -1050,6 +2782,7 @@ pub enum WorkflowNestedElement {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub enum WorkflowElement {
     Input(Input),
     Output(Output),
@@ -1077,14 +2810,130 @@ impl WorkflowElement {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Workflow {
     pub name: Anchor<String>,
     pub body: Vec<Anchor<WorkflowElement>>,
 }
 
+/// A declaration, call, scatter, or conditional in the body of a workflow, which may be nested
+/// within scatters and conditionals.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WorkflowBodyElement<'a> {
+    Declaration(&'a BoundDeclaration),
+    Call(&'a Call),
+    Scatter(&'a Scatter),
+    Conditional(&'a Conditional),
+}
+
+impl<'a> WorkflowBodyElement<'a> {
+    fn from_nested(element: &'a WorkflowNestedElement) -> Self {
+        match element {
+            WorkflowNestedElement::Declaration(decl) => Self::Declaration(decl),
+            WorkflowNestedElement::Call(call) => Self::Call(call),
+            WorkflowNestedElement::Scatter(scatter) => Self::Scatter(scatter),
+            WorkflowNestedElement::Conditional(conditional) => Self::Conditional(conditional),
+        }
+    }
+}
+
 impl Workflow {
+    /// Returns this workflow's `input` section, if any.
+    pub fn inputs(&self) -> Option<&Input> {
+        self.body.iter().find_map(|element| match element.deref() {
+            WorkflowElement::Input(input) => Some(input),
+            _ => None,
+        })
+    }
+
+    /// Returns this workflow's `output` section, if any.
+    pub fn outputs(&self) -> Option<&Output> {
+        self.body.iter().find_map(|element| match element.deref() {
+            WorkflowElement::Output(output) => Some(output),
+            _ => None,
+        })
+    }
+
+    /// Returns the declarations, calls, scatters, and conditionals of this workflow and their
+    /// spans, including those nested within scatters and conditionals, in source order (i.e. a
+    /// scatter or conditional precedes the elements of its body).
+    pub fn body_nested_iter(&self) -> impl Iterator<Item = (WorkflowBodyElement<'_>, &Span)> {
+        fn visit<'a>(
+            body: &'a [Anchor<WorkflowNestedElement>],
+            elements: &mut Vec<(WorkflowBodyElement<'a>, &'a Span)>,
+        ) {
+            for element in body {
+                elements.push((WorkflowBodyElement::from_nested(element), &element.span));
+                match element.deref() {
+                    WorkflowNestedElement::Scatter(Scatter { body, .. })
+                    | WorkflowNestedElement::Conditional(Conditional { body, .. }) => {
+                        visit(body, elements)
+                    }
+                    _ => (),
+                }
+            }
+        }
+        let mut elements = Vec::new();
+        for element in self.body.iter() {
+            let body = match element.deref() {
+                WorkflowElement::Declaration(decl) => {
+                    elements.push((WorkflowBodyElement::Declaration(decl), &element.span));
+                    continue;
+                }
+                WorkflowElement::Call(call) => {
+                    elements.push((WorkflowBodyElement::Call(call), &element.span));
+                    continue;
+                }
+                WorkflowElement::Scatter(scatter) => {
+                    elements.push((WorkflowBodyElement::Scatter(scatter), &element.span));
+                    &scatter.body
+                }
+                WorkflowElement::Conditional(conditional) => {
+                    elements.push((WorkflowBodyElement::Conditional(conditional), &element.span));
+                    &conditional.body
+                }
+                _ => continue,
+            };
+            visit(body, &mut elements)
+        }
+        elements.into_iter()
+    }
+
+    /// Returns the calls of this workflow, including those nested within scatters and
+    /// conditionals, in source order.
+    pub fn calls(&self) -> impl Iterator<Item = &Call> {
+        self.body_nested_iter()
+            .filter_map(|(element, _)| match element {
+                WorkflowBodyElement::Call(call) => Some(call),
+                _ => None,
+            })
+    }
+
+    /// Returns the documentation for the parameter `name` from this workflow's `parameter_meta`
+    /// section, or `None` if there is no entry for `name`.
+    pub fn parameter_description(&self, name: &str) -> Option<ParameterDescription> {
+        self.body.iter().find_map(|element| match element.deref() {
+            WorkflowElement::ParameterMeta(meta) => {
+                ParameterDescription::from_attributes(&meta.attributes, name)
+            }
+            _ => None,
+        })
+    }
+
     pub fn validate(&self) -> Result<(), ModelError> {
+        self.check(&ValidationOptions::default(), &mut Vec::new())
+    }
+
+    fn check(
+        &self,
+        options: &ValidationOptions,
+        warnings: &mut Vec<Diagnostic>,
+    ) -> Result<(), ModelError> {
         let mut seen = HashSet::with_capacity(4);
+        let has_parameter_meta = self
+            .body
+            .iter()
+            .any(|element| matches!(element.deref(), WorkflowElement::ParameterMeta(_)));
         for element in self.body.iter() {
             match element.deref() {
                 WorkflowElement::Input(_)
@@ -1092,14 +2941,21 @@ impl Workflow {
                 | WorkflowElement::Meta(_)
                 | WorkflowElement::ParameterMeta(_) => {
                     let kind = element.kind();
-                    ensure!(
-                        !seen.contains(&kind),
-                        Report::from(ModelError::WorkflowRepeatedElement {
-                            workflow: self.name.clone(),
-                            kind: kind.to_owned()
-                        })
-                        .attach_printable(element.span.clone())
-                    );
+                    if seen.contains(&kind) {
+                        options.repeated_sections.violation(
+                            ModelError::WorkflowRepeatedElement {
+                                workflow: (*self.name).clone(),
+                                kind: kind.to_owned(),
+                            },
+                            &element.span,
+                            vec![fix::repeated_section(
+                                kind,
+                                &element.span,
+                                has_parameter_meta,
+                            )],
+                            warnings,
+                        )?;
+                    }
                     seen.insert(kind);
                 }
                 _ => (),
@@ -1110,6 +2966,7 @@ impl Workflow {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub enum DocumentElement {
     Import(Import),
     Struct(Struct),
@@ -1117,44 +2974,339 @@ pub enum DocumentElement {
     Workflow(Workflow),
 }
 
+/// A parsed WDL document.
+///
+/// Each node of the model (i.e. each `Anchor`) has a `NodeId`, which identifies it only within
+/// this document: a node that is cloned is renumbered, so the clone has no IDs in common with the
+/// original, and parsing the same text again produces nodes with different IDs. Nodes are looked
+/// up by ID with a `NodeIndex` (see `Document::index`).
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Document {
     pub source: DocumentSource,
     pub version: Anchor<Version>,
+    /// The elements of the document, in source order.
     pub body: Vec<Anchor<DocumentElement>>,
     pub comments: Comments,
+    /// The digest of the text from which the document was parsed.
+    pub digest: SourceDigest,
+    /// The text from which the document was parsed, if the parser was configured to retain it
+    /// (see `ParseOptions::with_retained_text`) or it was attached with `attach_text`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub text: Option<SourceText>,
+}
+
+// A `Document` does not borrow from the text it was parsed from and has no interior
+// mutability, so it can be cached and shared between threads.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+    assert_send_sync::<Document>();
+};
+
+/// The names of built-in types, which cannot be used as names.
+const TYPE_NAMES: [&str; 9] = [
+    "Array", "Boolean", "File", "Float", "Int", "Map", "Object", "Pair", "String",
+];
+
+/// The keywords that are reserved in all WDL 1.x versions, other than the names of types.
+const RESERVED_KEYWORDS: [&str; 26] = [
+    "alias",
+    "as",
+    "call",
+    "command",
+    "else",
+    "false",
+    "if",
+    "import",
+    "in",
+    "input",
+    "left",
+    "meta",
+    "None",
+    "null",
+    "object",
+    "output",
+    "parameter_meta",
+    "right",
+    "runtime",
+    "scatter",
+    "struct",
+    "task",
+    "then",
+    "true",
+    "version",
+    "workflow",
+];
+
+/// Returns the reason that `name` is not a valid name in a document of the given version, if it
+/// is not. Keywords that were introduced in later versions (e.g. `after` in 1.1) are only
+/// reserved in those versions.
+fn invalid_name_reason(name: &str, version: &VersionIdentifier) -> Option<&'static str> {
+    let mut chars = name.chars();
+    let is_identifier = matches!(chars.next(), Some(c) if c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let development = *version == VersionIdentifier::Development;
+    if !is_identifier {
+        Some("not a valid identifier")
+    } else if TYPE_NAMES.contains(&name) || (development && name == "Directory") {
+        Some("the name of a built-in type")
+    } else if RESERVED_KEYWORDS.contains(&name)
+        || (*version >= VersionIdentifier::V1_1 && name == "after")
+        || (development && matches!(name, "env" | "hints" | "requirements"))
+    {
+        Some("a reserved keyword")
+    } else {
+        None
+    }
 }
 
 impl Document {
+    /// Returns the SHA-256 digest of the text from which this document was parsed, which
+    /// identifies the exact source bytes that the model corresponds to (e.g. for caching or
+    /// provenance).
+    pub fn source_digest(&self) -> &SourceDigest {
+        &self.digest
+    }
+
+    /// Returns whether this document was parsed from `text`.
+    pub fn matches_source<T: AsRef<[u8]>>(&self, text: T) -> bool {
+        self.digest == SourceDigest::of(text)
+    }
+
+    /// Retains `text` as the text of this document, if the document was parsed from it. Returns
+    /// whether the text was retained.
+    pub fn attach_text(&mut self, text: &str) -> bool {
+        let matches = self.matches_source(text);
+        if matches {
+            self.text = Some(SourceText::new(text));
+        }
+        matches
+    }
+
+    /// Returns the text from which this document was parsed, if it was retained.
+    pub fn source_text(&self) -> Option<&SourceText> {
+        self.text.as_ref()
+    }
+
+    /// Returns the text of `span`, or `None` if the text of this document was not retained or
+    /// the span is not within it.
+    pub fn text_for(&self, span: &Span) -> Option<SourceFragment> {
+        self.text
+            .as_ref()?
+            .slice(span)
+            .map(|text| SourceFragment(text.to_owned()))
+    }
+
+    /// Returns the names that are defined in this document and the kind of element that defines
+    /// each, in document order.
+    fn defined_names(&self) -> Vec<(&'static str, &Anchor<String>)> {
+        let mut names = Vec::new();
+        for element in self.body_iter() {
+            match element {
+                DocumentElement::Import(import) => {
+                    if let Namespace::Explicit(namespace) = &import.namespace {
+                        names.push(("namespace", namespace))
+                    }
+                    for alias in import.aliases.iter() {
+                        names.push(("struct", &alias.to))
+                    }
+                }
+                DocumentElement::Struct(struct_) => {
+                    names.push(("struct", &struct_.name));
+                    for field in struct_.fields.iter() {
+                        names.push(("field", &field.name))
+                    }
+                }
+                DocumentElement::Task(task) => {
+                    names.push(("task", &task.name));
+                    for decl in task.inputs().iter().flat_map(|i| i.declarations.iter()) {
+                        names.push(("input", decl.name()))
+                    }
+                    for element in task.body.iter() {
+                        if let TaskElement::Declaration(decl) = element.deref() {
+                            names.push(("declaration", &decl.name))
+                        }
+                    }
+                    for decl in task.outputs().iter().flat_map(|o| o.declarations.iter()) {
+                        names.push(("output", &decl.name))
+                    }
+                }
+                DocumentElement::Workflow(workflow) => {
+                    names.push(("workflow", &workflow.name));
+                    for decl in workflow.inputs().iter().flat_map(|i| i.declarations.iter()) {
+                        names.push(("input", decl.name()))
+                    }
+                    for (element, _) in workflow.body_nested_iter() {
+                        match element {
+                            WorkflowBodyElement::Declaration(decl) => {
+                                names.push(("declaration", &decl.name))
+                            }
+                            WorkflowBodyElement::Call(call) => {
+                                if let Some(alias) = &call.alias {
+                                    names.push(("call", alias))
+                                }
+                            }
+                            WorkflowBodyElement::Scatter(scatter) => {
+                                names.push(("scatter variable", &scatter.name))
+                            }
+                            WorkflowBodyElement::Conditional(_) => (),
+                        }
+                    }
+                    for decl in workflow
+                        .outputs()
+                        .iter()
+                        .flat_map(|o| o.declarations.iter())
+                    {
+                        names.push(("output", &decl.name))
+                    }
+                }
+            }
+        }
+        names
+    }
+
+    /// Validates this document with the default (mostly strict) options.
     pub fn validate(&self) -> Result<(), ModelError> {
+        self.validate_with(&ValidationOptions::default())
+            .map(|_| ())
+    }
+
+    /// Validates this document, enforcing each rule at the level given by `options`. Returns an
+    /// error for the first violation of a rule at level `Error`, otherwise the warnings for the
+    /// violations of rules at level `Warning`.
+    pub fn validate_with(
+        &self,
+        options: &ValidationOptions,
+    ) -> Result<Vec<Diagnostic>, ModelError> {
+        let mut warnings = Vec::new();
+        if *self.version.identifier == VersionIdentifier::Development {
+            options.development_version.violation(
+                ModelError::VersionDevelopment,
+                &self.version.identifier.span,
+                Vec::new(),
+                &mut warnings,
+            )?;
+        }
+        if options.invalid_names != ValidationLevel::Ignore {
+            for (kind, name) in self.defined_names() {
+                if let Some(reason) = invalid_name_reason(name, &self.version.identifier) {
+                    options.invalid_names.violation(
+                        ModelError::InvalidName {
+                            kind: kind.to_owned(),
+                            name: (**name).clone(),
+                            reason: reason.to_owned(),
+                        },
+                        &name.span,
+                        Vec::new(),
+                        &mut warnings,
+                    )?;
+                }
+            }
+        }
+        for comment in self.comments.dropped() {
+            options.dropped_comments.violation(
+                ModelError::CommentRepeatedLine(comment.span.start.line),
+                &comment.span,
+                Vec::new(),
+                &mut warnings,
+            )?;
+        }
         let mut element_count = 0;
-        let mut workflow_count = 0;
+        let mut workflows = Vec::new();
         for element in self.body.iter() {
             match element.deref() {
                 DocumentElement::Task(task) => {
-                    task.validate().attach_printable(element.span.clone())?;
+                    task.check(options, &mut warnings)
+                        .attach_printable(element.span.clone())?;
                     element_count += 1;
                 }
                 DocumentElement::Workflow(workflow) => {
-                    workflow.validate().attach_printable(element.span.clone())?;
+                    workflow
+                        .check(options, &mut warnings)
+                        .attach_printable(element.span.clone())?;
                     element_count += 1;
-                    workflow_count += 1;
+                    workflows.push((workflow, &element.span));
                 }
-                DocumentElement::Struct(_) => {
+                // library documents may contain only imports and/or structs
+                DocumentElement::Import(_) | DocumentElement::Struct(_) => {
                     element_count += 1;
                 }
-                _ => (),
             }
         }
-        ensure!(element_count > 0, ModelError::DocumentIncomplete);
-        ensure!(workflow_count <= 1, ModelError::DocumentMultipleWorkflows);
-        Ok(())
+        if element_count == 0 {
+            options.incomplete_document.violation(
+                ModelError::DocumentIncomplete,
+                &self.version.span,
+                Vec::new(),
+                &mut warnings,
+            )?;
+        }
+        if workflows.len() > 1 {
+            // suggest removing each workflow but the first
+            let fixes = workflows[1..]
+                .iter()
+                .map(|(workflow, span)| {
+                    Fix::new(
+                        format!("Remove workflow {}", workflow.name.as_str()),
+                        vec![TextEdit::delete((*span).clone())],
+                    )
+                })
+                .collect();
+            options.multiple_workflows.violation(
+                ModelError::DocumentMultipleWorkflows,
+                workflows[1].1,
+                fixes,
+                &mut warnings,
+            )?;
+        }
+        if options.version_features != ValidationLevel::Ignore {
+            let version = self.version.identifier.deref();
+            for requirement in analysis::version_requirements(self) {
+                let min_version = requirement.min_version();
+                if min_version > *version {
+                    let fix = Fix::new(
+                        format!("Change version to {}", min_version),
+                        vec![TextEdit::replace(
+                            self.version.identifier.span.clone(),
+                            min_version.to_string(),
+                        )],
+                    );
+                    options.version_features.violation(
+                        ModelError::VersionFeature {
+                            feature: requirement.feature.to_string(),
+                            version: min_version.to_string(),
+                        },
+                        &requirement.span,
+                        vec![fix],
+                        &mut warnings,
+                    )?;
+                }
+            }
+        }
+        Ok(warnings)
     }
 
     pub fn body_iter(&self) -> impl Iterator<Item = &DocumentElement> {
         self.body.iter().map(|e| (*e).deref())
     }
 
+    /// Returns each identifier referenced by the expressions of this document's tasks and
+    /// workflows, classified by the kind of element to which it refers. See
+    /// `analysis::identifiers`.
+    pub fn identifiers(&self) -> Vec<analysis::Identifier> {
+        analysis::identifiers(self)
+    }
+
+    /// Returns the elements of this document sorted by the start of their spans. The body of a
+    /// parsed document is already in source order; this is for documents that are constructed
+    /// or modified programmatically, so that output derived from them (e.g. snapshots) does not
+    /// depend on the order in which elements were added.
+    pub fn elements_sorted_by_span(&self) -> Vec<&Anchor<DocumentElement>> {
+        let mut elements: Vec<&Anchor<DocumentElement>> = self.body.iter().collect();
+        elements.sort_by_key(|element| (element.span.start.offset, element.span.end.offset));
+        elements
+    }
+
     /// Returns this Document's Workflow if it contains one, or its Task if it contains exactly
     /// one, otherwise None.
     pub fn get_primary_element(&self) -> Option<&DocumentElement> {
@@ -1417,7 +3569,7 @@ pub mod tests {
                                                 Span::from_components(20, 12, 317, 20, 13, 318)
                                             ),
                                             expression: Anchor::new(
-                                                Expression::Int(Integer::Decimal(0)),
+                                                Expression::Int(Integer::decimal(0)),
                                                 Span::from_components(20, 16, 321, 20, 17, 322)
                                             ),
                                         }),
@@ -1461,7 +3613,7 @@ pub mod tests {
                                             Span::from_components(24, 14, 365, 24, 15, 366)
                                         )),
                                         right: Box::new(Anchor::new(
-                                            Expression::Float(Float::Decimal(1.0)),
+                                            Expression::Float(Float::decimal(1.0)),
                                             Span::from_components(24, 18, 369, 24, 21, 372)
                                         )),
                                     }),
@@ -1658,7 +3810,7 @@ pub mod tests {
                                                 Span::from_components(34, 12, 609, 34, 13, 610)
                                             ),
                                             expression: Some(Anchor::new(
-                                                Expression::Int(Integer::Decimal(1)),
+                                                Expression::Int(Integer::decimal(1)),
                                                 Span::from_components(34, 16, 613, 34, 17, 614)
                                             ))
                                         },
@@ -1687,11 +3839,11 @@ pub mod tests {
                                     Expression::Binary(Binary {
                                         operator: BinaryOperator::Gt,
                                         left: Box::new(Anchor::new(
-                                            Expression::Int(Integer::Decimal(1)),
+                                            Expression::Int(Integer::decimal(1)),
                                             Span::from_components(38, 8, 653, 38, 9, 654)
                                         )),
                                         right: Box::new(Anchor::new(
-                                            Expression::Int(Integer::Decimal(2)),
+                                            Expression::Int(Integer::decimal(2)),
                                             Span::from_components(38, 12, 657, 38, 13, 658)
                                         )),
                                     }),
@@ -1875,7 +4027,7 @@ pub mod tests {
                                                 Span::from_components(56, 8, 1004, 56, 12, 1008)
                                             ),
                                             value: Anchor::new(
-                                                MetaValue::Int(Integer::Decimal(10)),
+                                                MetaValue::Int(Integer::decimal(10)),
                                                 Span::from_components(56, 14, 1010, 56, 16, 1012)
                                             ),
                                         },
@@ -1891,19 +4043,19 @@ pub mod tests {
                                                 MetaValue::Array(MetaArray {
                                                     elements: vec![
                                                         Anchor::new(
-                                                            MetaValue::Int(Integer::Decimal(1)),
+                                                            MetaValue::Int(Integer::decimal(1)),
                                                             Span::from_components(
                                                                 57, 18, 1031, 57, 19, 1032
                                                             )
                                                         ),
                                                         Anchor::new(
-                                                            MetaValue::Int(Integer::Decimal(2)),
+                                                            MetaValue::Int(Integer::decimal(2)),
                                                             Span::from_components(
                                                                 57, 21, 1034, 57, 22, 1035
                                                             )
                                                         ),
                                                         Anchor::new(
-                                                            MetaValue::Int(Integer::Decimal(3)),
+                                                            MetaValue::Int(Integer::decimal(3)),
                                                             Span::from_components(
                                                                 57, 24, 1037, 57, 25, 1038
                                                             )
@@ -1934,7 +4086,7 @@ pub mod tests {
                                                                 ),
                                                                 value: Anchor::new(
                                                                     MetaValue::Float(
-                                                                        Float::Decimal(1.0)
+                                                                        Float::decimal(1.0)
                                                                     ),
                                                                     Span::from_components(
                                                                         59, 15, 1075, 59, 18, 1078
@@ -1955,7 +4107,7 @@ pub mod tests {
                                                                 ),
                                                                 value: Anchor::new(
                                                                     MetaValue::Int(
-                                                                        Integer::Decimal(-1)
+                                                                        Integer::decimal(-1)
                                                                     ),
                                                                     Span::from_components(
                                                                         60, 15, 1095, 60, 17, 1097
@@ -2057,15 +4209,17 @@ pub mod tests {
                         ),
                         Anchor::new(
                             TaskElement::Command(Command {
+                                style: CommandStyle::Heredoc,
                                 parts: vec![
                                     Anchor::new(
                                         StringPart::Content("\n    echo ".to_owned()),
                                         Span::from_components(73, 15, 1247, 74, 9, 1257)
                                     ),
                                     Anchor::new(
-                                        StringPart::Placeholder(Expression::Identifier(
-                                            "file".to_owned()
-                                        )),
+                                        StringPart::Placeholder(
+                                            Expression::Identifier("file".to_owned()),
+                                            PlaceholderStyle::Tilde
+                                        ),
                                         Span::from_components(74, 9, 1257, 74, 16, 1264)
                                     ),
                                     Anchor::new(
@@ -2157,4 +4311,877 @@ pub mod tests {
             )
         );
     }
+
+    #[test]
+    fn test_cooked_string() {
+        let part = |part: StringPart, start: usize, end: usize| {
+            Anchor::new(part, Span::from_components(0, start, start, 0, end, end))
+        };
+        let s = StringLiteral {
+            parts: vec![
+                part(StringPart::Content("a".to_owned()), 1, 2),
+                part(StringPart::Escape("\\t".to_owned()), 2, 4),
+                part(StringPart::Escape("\\u00e9".to_owned()), 4, 10),
+                part(StringPart::Escape("\\101".to_owned()), 10, 14),
+                part(StringPart::Escape("\\u{1F600}".to_owned()), 14, 23),
+            ],
+        };
+        assert!(s.is_constant());
+        assert_eq!(s.cooked().unwrap(), "a\té\u{41}\u{1F600}");
+        let s = StringLiteral {
+            parts: vec![
+                part(StringPart::Content("a".to_owned()), 1, 2),
+                part(StringPart::Escape("\\q".to_owned()), 2, 4),
+            ],
+        };
+        let err = s.cooked().unwrap_err();
+        assert!(matches!(err.current_context(), ModelError::Escape(e) if e == "\\q"));
+        assert_eq!(
+            err.downcast_ref::<Span>(),
+            Some(&Span::from_components(0, 2, 2, 0, 4, 4))
+        );
+    }
+
+    #[test]
+    fn test_type_round_trip() {
+        for s in [
+            "Boolean",
+            "Int",
+            "Float",
+            "String",
+            "File",
+            "Object",
+            "MyStruct?",
+            "Array[File?]+",
+            "Map[String, Int]",
+            "Pair[Int, Array[String]+?]",
+            "Map[String, Pair[File, File?]]?",
+        ] {
+            let type_: Type = s.parse().unwrap();
+            assert_eq!(type_.to_string(), s);
+            assert_eq!(type_.to_string().parse::<Type>().unwrap(), type_);
+        }
+        let type_: Type = " Map [ String ,Array[ Int ]+ ] ? ".parse().unwrap();
+        assert_eq!(type_.to_string(), "Map[String, Array[Int]+]?");
+        for s in [
+            "",
+            "Array[Int",
+            "Int+",
+            "Map[String]",
+            "Pair[Int, Int, Int]",
+            "Int??",
+        ] {
+            assert!(
+                matches!(s.parse::<Type>().unwrap_err().current_context(), ModelError::Type(t) if t == s),
+                "{}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn test_binary_chain() {
+        let ident = |name: &str, start: usize| {
+            Anchor::new(
+                Expression::Identifier(name.to_owned()),
+                Span::from_components(0, start, start, 0, start + 1, start + 1),
+            )
+        };
+        // a right-nested chain `a - (b + c)` without a group, as produced by a right-associative
+        // grammar for `a - b + c`
+        let right = Binary::fold(ident("b", 4), vec![(BinaryOperator::Add, ident("c", 8))]);
+        let binary = Binary {
+            operator: BinaryOperator::Sub,
+            left: Box::new(ident("a", 0)),
+            right: Box::new(right),
+        };
+        let (first, rest) = binary.into_chain();
+        assert_eq!(first, ident("a", 0));
+        assert_eq!(
+            rest,
+            vec![
+                (BinaryOperator::Sub, ident("b", 4)),
+                (BinaryOperator::Add, ident("c", 8))
+            ]
+        );
+        let normalized = Binary::fold(first, rest);
+        assert_eq!(normalized.span, Span::from_components(0, 0, 0, 0, 9, 9));
+        assert_eq!(normalized.deref().to_string(), "a - b + c");
+        match normalized.deref() {
+            Expression::Binary(b) => {
+                assert_eq!(b.operator, BinaryOperator::Add);
+                assert_eq!(b.left.span, Span::from_components(0, 0, 0, 0, 5, 5));
+            }
+            _ => panic!("expected binary expression"),
+        }
+        // operators with different precedence are not flattened
+        let binary = Binary {
+            operator: BinaryOperator::Add,
+            left: Box::new(ident("a", 0)),
+            right: Box::new(Binary::fold(
+                ident("b", 4),
+                vec![(BinaryOperator::Mul, ident("c", 8))],
+            )),
+        };
+        let (_, rest) = binary.into_chain();
+        assert_eq!(rest.len(), 1);
+    }
+
+    #[cfg(feature = "pest")]
+    #[test]
+    fn test_validation_options() {
+        use crate::parsers::{PestParser, WdlParser};
+
+        let text = r#"version 1.1
+
+task t {
+    input {}
+    input {}
+}
+
+workflow a {}
+
+workflow b {}
+"#;
+        assert!(PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .is_err());
+        let doc = PestParser::new()
+            .with_validation(ValidationOptions::permissive())
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let codes = |options: &ValidationOptions| -> Vec<String> {
+            doc.validate_with(options)
+                .unwrap()
+                .into_iter()
+                .map(|warning| warning.code)
+                .collect()
+        };
+        assert_eq!(
+            codes(&ValidationOptions::permissive()),
+            vec![
+                "task_repeated_element",
+                "task_missing_command",
+                "document_multiple_workflows"
+            ]
+        );
+        let options = ValidationOptions {
+            repeated_sections: ValidationLevel::Ignore,
+            missing_command: ValidationLevel::Ignore,
+            ..ValidationOptions::permissive()
+        };
+        assert_eq!(codes(&options), vec!["document_multiple_workflows"]);
+        let options = ValidationOptions {
+            missing_command: ValidationLevel::Ignore,
+            ..ValidationOptions::strict()
+        };
+        let report = doc.validate_with(&options).unwrap_err();
+        assert!(matches!(
+            report.current_context(),
+            ModelError::TaskRepeatedElement { .. }
+        ));
+    }
+
+    #[cfg(feature = "pest")]
+    #[test]
+    fn test_library_documents() {
+        use crate::parsers::{PestParser, WdlParser};
+
+        let mut parser = PestParser::new();
+        let structs = r#"version 1.1
+
+struct Sample {
+    String name
+}
+"#;
+        let doc = parser.parse_text(structs, DocumentSource::Unknown).unwrap();
+        assert!(doc.get_primary_element().is_none());
+        let imports = r#"version 1.1
+
+import "structs.wdl" as structs
+import "tasks.wdl"
+"#;
+        let doc = parser.parse_text(imports, DocumentSource::Unknown).unwrap();
+        assert_eq!(doc.body.len(), 2);
+        let report = parser
+            .parse_text("version 1.1\n", DocumentSource::Unknown)
+            .unwrap_err();
+        assert!(report.frames().any(|frame| matches!(
+            frame.downcast_ref::<ModelError>(),
+            Some(ModelError::DocumentIncomplete)
+        )));
+    }
+
+    #[cfg(feature = "pest")]
+    #[test]
+    fn test_accessors() {
+        use crate::parsers::{PestParser, WdlParser};
+
+        let text = r#"version 1.1
+task t {
+    input {
+        Int x
+    }
+    command <<< echo ~{x} >>>
+    output {
+        Int y = x
+    }
+}
+workflow w {
+    input {
+        Array[Int] xs
+    }
+    call t as first { input: x = 1 }
+    scatter (x in xs) {
+        if (x > 1) {
+            call t { input: x }
+        }
+        Int z = x
+    }
+    output {
+        Int y = first.y
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let (task, workflow) = match (doc.body_iter().next(), doc.body_iter().nth(1)) {
+            (Some(DocumentElement::Task(t)), Some(DocumentElement::Workflow(w))) => (t, w),
+            _ => panic!("expected a task and a workflow"),
+        };
+        assert_eq!(task.inputs().unwrap().declarations[0].name().as_str(), "x");
+        assert_eq!(task.outputs().unwrap().declarations[0].name.as_str(), "y");
+        assert_eq!(task.command().unwrap().parts.len(), 3);
+        assert!(task.runtime().is_none());
+        assert_eq!(workflow.inputs().unwrap().declarations.len(), 1);
+        assert_eq!(workflow.outputs().unwrap().declarations.len(), 1);
+        let kinds: Vec<(&str, usize)> = workflow
+            .body_nested_iter()
+            .map(|(element, span)| {
+                let kind = match element {
+                    WorkflowBodyElement::Declaration(_) => "declaration",
+                    WorkflowBodyElement::Call(_) => "call",
+                    WorkflowBodyElement::Scatter(_) => "scatter",
+                    WorkflowBodyElement::Conditional(_) => "conditional",
+                };
+                (kind, span.start.line)
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("call", 14),
+                ("scatter", 15),
+                ("conditional", 16),
+                ("call", 17),
+                ("declaration", 19)
+            ]
+        );
+        let calls: Vec<&str> = workflow.calls().map(crate::fqn::call_name).collect();
+        assert_eq!(calls, vec!["first", "t"]);
+    }
+
+    #[cfg(feature = "pest")]
+    #[test]
+    fn test_source_order() {
+        use crate::parsers::{PestParser, WdlParser};
+
+        let text = r#"version 1.1
+# b
+struct B { Int x }
+task a {
+    command <<< >>>
+}  # a
+# w
+workflow w {}
+"#;
+        let mut doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let kinds: Vec<&str> = doc
+            .body_iter()
+            .map(|element| match element {
+                DocumentElement::Import(_) => "import",
+                DocumentElement::Struct(_) => "struct",
+                DocumentElement::Task(_) => "task",
+                DocumentElement::Workflow(_) => "workflow",
+            })
+            .collect();
+        assert_eq!(kinds, vec!["struct", "task", "workflow"]);
+        let comments: Vec<&str> = doc.comments.values().map(|c| c.as_str()).collect();
+        assert_eq!(comments, vec!["# b", "# a", "# w"]);
+        doc.body.reverse();
+        let lines: Vec<usize> = doc
+            .elements_sorted_by_span()
+            .iter()
+            .map(|element| element.span.start.line)
+            .collect();
+        assert_eq!(lines, vec![2, 3, 7]);
+    }
+
+    #[cfg(feature = "pest")]
+    #[test]
+    fn test_development_version() {
+        use crate::parsers::{PestParser, WdlParser};
+
+        let text = "version development\n\ntask a {\n    command <<< >>>\n}\n";
+        let report = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap_err();
+        assert!(report.frames().any(|frame| matches!(
+            frame.downcast_ref::<ModelError>(),
+            Some(ModelError::VersionDevelopment)
+        )));
+        assert!(PestParser::new()
+            .with_validation(ValidationOptions::permissive())
+            .parse_text(text, DocumentSource::Unknown)
+            .is_err());
+        let doc = PestParser::new()
+            .with_validation(
+                ValidationOptions::strict().with_development_version(ValidationLevel::Ignore),
+            )
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        assert_eq!(*doc.version.identifier, VersionIdentifier::Development);
+        assert_eq!(doc.version.identifier.deref().to_string(), "development");
+        assert!(VersionIdentifier::Development > VersionIdentifier::V1_1);
+        assert!(doc.validate_with(&ValidationOptions::permissive()).is_err());
+        let warnings = doc
+            .validate_with(
+                &ValidationOptions::permissive().with_development_version(ValidationLevel::Warning),
+            )
+            .unwrap();
+        assert_eq!(warnings[0].code, "version_development");
+    }
+
+    #[cfg(feature = "pest")]
+    #[test]
+    fn test_heredoc_placeholders() {
+        use crate::{
+            fix::apply_fixes,
+            parsers::{PestParser, WdlParser},
+        };
+
+        let text = r#"version 1.1
+
+task t {
+    input {
+        String name
+    }
+    String greeting = "hello"
+    command <<<
+        for f in *; do echo ${f} \${name}; done
+        echo ${greeting} ${name}
+    >>>
+}
+"#;
+        let report = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap_err();
+        assert!(report.frames().any(|frame| matches!(
+            frame.downcast_ref::<ModelError>(),
+            Some(ModelError::HeredocDollarPlaceholder { name, .. }) if name == "greeting"
+        )));
+        let doc = PestParser::new()
+            .with_validation(ValidationOptions::permissive())
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let task = match doc.body_iter().next().unwrap() {
+            DocumentElement::Task(task) => task,
+            _ => unreachable!(),
+        };
+        let command = task.command().unwrap();
+        assert_eq!(command.style, CommandStyle::Heredoc);
+        let identifiers: Vec<(&str, &str)> = command
+            .dollar_identifiers()
+            .into_iter()
+            .map(|(name, span)| (name, &text[span.start.offset..span.end.offset]))
+            .collect();
+        assert_eq!(
+            identifiers,
+            vec![
+                ("f", "${f}"),
+                ("greeting", "${greeting}"),
+                ("name", "${name}")
+            ]
+        );
+        let warnings = doc.validate_with(&ValidationOptions::permissive()).unwrap();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].code, "heredoc_dollar_placeholder");
+        assert_eq!(warnings[0].span.start.line, 9);
+        let fixed = apply_fixes(text, warnings.iter().flat_map(|w| w.fixes.iter())).unwrap();
+        assert!(fixed.contains("echo ~{greeting} ~{name}"));
+        assert!(fixed.contains("\\${name}"));
+
+        // in a command with braces, `${name}` is a placeholder
+        let braces = text
+            .replace(" \\${name}", "")
+            .replace("<<<", "{")
+            .replace(">>>", "}");
+        let doc = PestParser::new()
+            .parse_text(&braces, DocumentSource::Unknown)
+            .unwrap();
+        match doc.body_iter().next().unwrap() {
+            DocumentElement::Task(task) => {
+                assert_eq!(task.command().unwrap().style, CommandStyle::Braces)
+            }
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn test_comments_in_span() {
+        let comment = |line: usize| {
+            Anchor::new(
+                format!("# {}", line),
+                Span::from_components(line, 0, line * 10, line, 3, line * 10 + 3),
+            )
+        };
+        let mut comments = Comments::default();
+        for line in [0, 2, 4, 6] {
+            comments.try_insert(line, comment(line)).unwrap();
+        }
+        let span = Span::from_components(2, 4, 24, 4, 1, 41);
+        let lines: Vec<usize> = comments.in_span(&span).map(|(line, _)| *line).collect();
+        assert_eq!(lines, vec![2, 4]);
+
+        assert!(comments.check_dropped().is_ok());
+        assert!(comments.try_insert(4, comment(4)).is_err());
+        comments.push_dropped(comment(4));
+        assert_eq!(comments.dropped(), &[comment(4)]);
+        assert_eq!(comments.values().count(), 4);
+        let error = comments.check_dropped().unwrap_err();
+        assert!(matches!(
+            error.current_context(),
+            ModelError::CommentRepeatedLine(4)
+        ));
+    }
+
+    #[test]
+    fn test_span_operations() {
+        let span = |start: usize, end: usize| Span::from_components(0, start, start, 0, end, end);
+        let position = |offset: usize| Position::new(0, offset, offset);
+        let outer = span(2, 8);
+        assert_eq!(outer.len(), 6);
+        assert!(span(3, 3).is_empty());
+        assert!(outer.contains(&position(2)));
+        assert!(outer.contains(&position(7)));
+        assert!(!outer.contains(&position(8)));
+        assert!(!outer.contains(&position(1)));
+        assert!(outer.contains_span(&outer));
+        assert!(outer.contains_span(&span(3, 5)));
+        assert!(outer.contains_span(&span(8, 8)));
+        assert!(!outer.contains_span(&span(7, 9)));
+        assert!(outer.intersects(&span(7, 9)));
+        assert!(!outer.intersects(&span(8, 9)));
+        assert!(!outer.intersects(&span(0, 2)));
+        assert!(!outer.intersects(&span(4, 4)));
+        assert_eq!(outer.union(&span(0, 1)), span(0, 8));
+        assert_eq!(outer.union(&span(3, 4)), outer);
+
+        // spans are ordered by start and then end, consistently with containment
+        let mut spans = vec![span(3, 5), span(2, 8), span(2, 4), span(0, 1)];
+        spans.sort();
+        assert_eq!(spans, vec![span(0, 1), span(2, 4), span(2, 8), span(3, 5)]);
+        for (i, a) in spans.iter().enumerate() {
+            for b in spans[i + 1..].iter() {
+                assert!(a <= b && a.start <= b.start);
+                assert_eq!(a.contains_span(b) && b.contains_span(a), a == b);
+                assert_eq!(a.union(b), b.union(a));
+            }
+        }
+        assert_eq!(position(4).cmp(&Position::new(1, 0, 4)), Ordering::Equal);
+    }
+
+    #[cfg(feature = "pest")]
+    #[test]
+    fn test_invalid_names() {
+        use crate::parsers::{PestParser, WdlParser};
+
+        let text = r#"version 1.0
+
+task t {
+    command <<< >>>
+}
+
+workflow wf {
+    input {
+        Int left
+    }
+    call t as right
+}
+"#;
+        let report = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap_err();
+        assert!(report.frames().any(|frame| matches!(
+            frame.downcast_ref::<ModelError>(),
+            Some(ModelError::InvalidName { name, .. }) if name == "left"
+        )));
+        let doc = PestParser::new()
+            .with_validation(ValidationOptions::permissive())
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let warnings: Vec<(String, usize)> = doc
+            .validate_with(&ValidationOptions::permissive())
+            .unwrap()
+            .into_iter()
+            .map(|w| (w.message, w.span.start.line))
+            .collect();
+        assert_eq!(
+            warnings,
+            vec![
+                ("Invalid input name left: a reserved keyword".to_owned(), 8),
+                ("Invalid call name right: a reserved keyword".to_owned(), 10),
+            ]
+        );
+
+        let v1_1 = VersionIdentifier::V1_1;
+        assert_eq!(invalid_name_reason("sample_1", &v1_1), None);
+        assert_eq!(
+            invalid_name_reason("_x", &v1_1),
+            Some("not a valid identifier")
+        );
+        assert_eq!(
+            invalid_name_reason("File", &v1_1),
+            Some("the name of a built-in type")
+        );
+        assert_eq!(
+            invalid_name_reason("after", &v1_1),
+            Some("a reserved keyword")
+        );
+        assert_eq!(invalid_name_reason("after", &VersionIdentifier::V1_0), None);
+        assert_eq!(invalid_name_reason("hints", &v1_1), None);
+        assert_eq!(
+            invalid_name_reason("hints", &VersionIdentifier::Development),
+            Some("a reserved keyword")
+        );
+    }
+
+    #[test]
+    fn test_namespace_from_uri() {
+        for (uri, expected) in [
+            ("tasks.wdl", "tasks"),
+            ("lib/tasks.wdl", "tasks"),
+            ("../lib/v1.2/tasks.wdl", "tasks"),
+            ("lib/tasks/", "tasks"),
+            ("file:///a/b/tasks.wdl", "tasks"),
+            ("https://example.com/x/tasks.wdl?ref=main&y=1", "tasks"),
+            ("https://example.com/x/tasks.wdl#L10", "tasks"),
+            ("https://example.com/x/my%20tasks.wdl", "my tasks"),
+            ("https://example.com/x/tasks%2", "tasks%2"),
+        ] {
+            let namespace = Namespace::from_uri(uri);
+            assert_eq!(namespace.identifier(), expected, "{}", uri);
+            assert_eq!(namespace, Namespace::Implicit(expected.to_owned()));
+        }
+    }
+
+    #[test]
+    fn test_literal_raw() {
+        let i = Integer::from_str("0777").unwrap();
+        assert_eq!((i.value, i.radix), (511, Radix::Octal));
+        assert_eq!(i.to_string(), "0777");
+        assert_eq!(i.negate().to_string(), "-0777");
+        assert_eq!(Integer::from_str("0x1f").unwrap().to_string(), "0x1f");
+        assert_eq!(Integer::hex(-31).to_string(), "-0x1F");
+        let f = Float::from_str("1e-3").unwrap();
+        assert_eq!((f.value, f.notation), (0.001, Notation::Scientific));
+        assert_eq!(f.to_string(), "1e-3");
+        assert_eq!(f.negate().negate().to_string(), "1e-3");
+        assert_eq!(Float::decimal(2.0).to_string(), "2.0");
+    }
+
+    #[cfg(feature = "pest")]
+    #[test]
+    fn test_runtime_accessors() {
+        use crate::{
+            parsers::{PestParser, WdlParser},
+            units::Unit,
+        };
+
+        let text = r#"version 1.1
+
+task t {
+    input {
+        Int n
+    }
+    command <<< >>>
+    runtime {
+        docker: ["ubuntu:22.04", "debian:11"]
+        cpu: 2
+        memory: "~{n} GB"
+        disks: "local-disk 50 HDD"
+        maxRetries: 3
+        preemptible: "2"
+        gpuType: "nvidia-tesla-t4"
+        noAddress: true
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let runtime = match doc.body_iter().next() {
+            Some(DocumentElement::Task(task)) => task.runtime().unwrap(),
+            _ => panic!("expected a task"),
+        };
+        assert_eq!(
+            runtime.container(),
+            Some(vec!["ubuntu:22.04".to_owned(), "debian:11".to_owned()])
+        );
+        assert_eq!(runtime.cpu(), Some(2.0));
+        assert_eq!(
+            runtime.disks().unwrap()[0].size,
+            Quantity::new(50.0, Unit::GiB)
+        );
+        assert_eq!(runtime.max_retries(), Some(3));
+        assert_eq!(runtime.gpu_type(), Some("nvidia-tesla-t4".to_owned()));
+        // dynamic, wrongly typed, and missing values
+        assert_eq!(runtime.memory(), None);
+        assert_eq!(runtime.preemptible(), None);
+        assert_eq!(runtime.gpu(), None);
+        let unknown: Vec<&str> = runtime
+            .unknown()
+            .map(|attribute| attribute.name.as_str())
+            .collect();
+        assert_eq!(unknown, vec!["noAddress"]);
+        assert_eq!(
+            runtime.value(&RuntimeKey::from_name("noAddress")),
+            Some(WdlValue::Boolean(true))
+        );
+    }
+
+    #[cfg(feature = "pest")]
+    #[test]
+    fn test_default_value() {
+        use crate::parsers::{PestParser, WdlParser};
+
+        let text = r#"version 1.1
+
+task t {
+    input {
+        Int a = 1 + 2 * 3
+        Float b = 1
+        Array[String] c = ["x", "y"]
+        Map[String, Int] d = {"x": 1}
+        Int? e = None
+        Int f = a + 1
+        Array[String] g = read_lines("x.txt")
+        String h
+    }
+    command <<< >>>
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let task = match doc.body_iter().next() {
+            Some(DocumentElement::Task(task)) => task,
+            _ => panic!("expected a task"),
+        };
+        let defaults: Vec<Option<WdlValue>> = task
+            .body
+            .iter()
+            .filter_map(|element| match element.deref() {
+                TaskElement::Input(input) => Some(input),
+                _ => None,
+            })
+            .flat_map(|input| input.declarations.iter())
+            .map(|decl| decl.default_value())
+            .collect();
+        assert_eq!(
+            defaults,
+            vec![
+                Some(WdlValue::Int(7)),
+                Some(WdlValue::Float(1.0)),
+                Some(WdlValue::Array(vec![
+                    WdlValue::String("x".to_owned()),
+                    WdlValue::String("y".to_owned())
+                ])),
+                Some(WdlValue::Map(vec![(
+                    WdlValue::String("x".to_owned()),
+                    WdlValue::Int(1)
+                )])),
+                Some(WdlValue::None),
+                None,
+                None,
+                None,
+            ]
+        );
+    }
+
+    #[cfg(feature = "pest")]
+    #[test]
+    fn test_parameter_description() {
+        use crate::parsers::{PestParser, WdlParser};
+
+        let mut parser = PestParser::new();
+        let text = r#"
+version 1.1
+
+task t {
+    input {
+        String mode
+        File reads
+    }
+    command <<< >>>
+    parameter_meta {
+        reads: "input reads"
+        mode: {
+            description: "alignment mode",
+            help: "use local for short reads",
+            choices: ["local", "global"]
+        }
+    }
+}
+"#;
+        let doc = parser.parse_text(text, DocumentSource::Unknown).unwrap();
+        let task = match doc.body_iter().next() {
+            Some(DocumentElement::Task(task)) => task,
+            _ => panic!("expected task"),
+        };
+        assert_eq!(
+            task.parameter_description("reads"),
+            Some(ParameterDescription {
+                name: "reads".to_owned(),
+                description: Some("input reads".to_owned()),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            task.parameter_description("mode"),
+            Some(ParameterDescription {
+                name: "mode".to_owned(),
+                description: Some("alignment mode".to_owned()),
+                help: Some("use local for short reads".to_owned()),
+                label: None,
+                choices: vec!["local".to_owned(), "global".to_owned()],
+            })
+        );
+        assert_eq!(task.parameter_description("other"), None);
+    }
+
+    #[cfg(feature = "pest")]
+    #[test]
+    fn test_comment_kinds() {
+        use crate::parsers::{PestParser, WdlParser};
+
+        let mut parser = PestParser::new();
+        let text = r#"version 1.1
+# about t
+  # more about t
+task t {
+    input {
+        Int x = 1 # trailing
+    }
+    command <<< >>>
+}
+"#;
+        let doc = parser.parse_text(text, DocumentSource::Unknown).unwrap();
+        assert_eq!(doc.comments.kind(1), Some(CommentKind::FullLine));
+        assert_eq!(doc.comments.kind(2), Some(CommentKind::FullLine));
+        assert_eq!(doc.comments.kind(5), Some(CommentKind::Trailing));
+        assert_eq!(doc.comments.kind(3), None);
+        assert_eq!(doc.comments.trailing(1), None);
+        assert_eq!(doc.comments.trailing(5).unwrap().as_str(), "# trailing");
+        let leading: Vec<&str> = doc.comments.leading(3).iter().map(|c| c.as_str()).collect();
+        assert_eq!(leading, vec!["# about t", "# more about t"]);
+        assert!(doc.comments.leading(6).is_empty());
+    }
+
+    #[cfg(feature = "pest")]
+    #[test]
+    fn test_expression_display() {
+        use crate::parsers::{PestParser, WdlParser};
+
+        let mut parser = PestParser::new();
+        let doc = parser
+            .parse_file(
+                PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                    .join("resources")
+                    .join("test")
+                    .join("expressions.wdl"),
+            )
+            .unwrap();
+        let workflow = match doc.body_iter().next() {
+            Some(DocumentElement::Workflow(workflow)) => workflow,
+            _ => panic!("expected workflow"),
+        };
+        let rendered: Vec<(String, String)> = workflow
+            .body
+            .iter()
+            .filter_map(|element| match element.deref() {
+                WorkflowElement::Declaration(decl) => {
+                    Some(((*decl.name).clone(), decl.expression.to_wdl_source()))
+                }
+                _ => None,
+            })
+            .collect();
+        let get = |name: &str| {
+            rendered
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, e)| e.as_str())
+                .unwrap()
+        };
+        assert_eq!(get("i4"), "0xFFFF");
+        assert_eq!(get("i5"), "0777");
+        assert_eq!(get("f1"), "1e5");
+        assert_eq!(get("m1"), "{1: true, 2: false}");
+        assert_eq!(get("obj1"), "object { a: true, b: 1, c: -1.0 }");
+        assert_eq!(get("bin2"), "1 + 2 * 3");
+        assert_eq!(get("bin3"), "-(1 + 2) * 3");
+        assert_eq!(get("bool4"), "!(true || false)");
+        assert_eq!(get("k"), "select_first([a2[0]])");
+        assert_eq!(get("x"), "if 1 > 2 then 0 else 1");
+        // rendering the parsed rendering produces the same text
+        let text = rendered
+            .iter()
+            .map(|(name, expr)| format!("    Int {} = {}\n", name, expr))
+            .collect::<String>();
+        let doc = parser
+            .parse_text(
+                format!("version 1.1\nworkflow Test {{\n{}}}\n", text),
+                DocumentSource::Unknown,
+            )
+            .unwrap();
+        let reparsed = match doc.body_iter().next() {
+            Some(DocumentElement::Workflow(workflow)) => workflow,
+            _ => panic!("expected workflow"),
+        };
+        for (element, (_, expected)) in reparsed.body.iter().zip(rendered.iter()) {
+            match element.deref() {
+                WorkflowElement::Declaration(decl) => {
+                    assert_eq!(&decl.expression.to_wdl_source(), expected)
+                }
+                _ => panic!("expected declaration"),
+            }
+        }
+    }
+
+    #[cfg(feature = "pest")]
+    #[test]
+    fn test_dropped_comments() {
+        use crate::parsers::{PestParser, WdlParser};
+
+        let text = "version 1.1\n\n# a task\ntask a {\n    command <<< >>>\n}\n";
+        let mut document = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        assert!(document.comments.dropped().is_empty());
+        let comment = document.comments.get(2).unwrap();
+        let dropped = Anchor::new((**comment).clone(), comment.span.clone());
+        document.comments.push_dropped(dropped);
+        assert!(document
+            .validate_with(&ValidationOptions::strict())
+            .is_err());
+        let warnings = document
+            .validate_with(&ValidationOptions::default())
+            .unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, "comment_repeated_line");
+        assert_eq!(warnings[0].span.start.line, 2);
+    }
 }
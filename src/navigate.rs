@@ -0,0 +1,440 @@
+//! Navigation from references to the elements that they refer to, e.g. for "go to definition"
+//! in an editor. References to elements of other documents are resolved through the imports of
+//! a `Workspace`.
+use crate::{
+    analysis::{resolve_scopes, walk_expression},
+    model::{
+        Anchor, Call, Document, DocumentElement, DocumentSource, Expression, Import,
+        InputDeclaration, Position, Span, StringPart, TaskElement, Type, WorkflowElement,
+        WorkflowNestedElement,
+    },
+    workspace::Workspace,
+};
+use std::ops::Deref;
+
+/// A span within a document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    pub source: DocumentSource,
+    pub span: Span,
+}
+
+impl Location {
+    fn new(document: &Document, span: &Span) -> Self {
+        Self {
+            source: document.source.clone(),
+            span: span.clone(),
+        }
+    }
+}
+
+/// Returns the location of the definition of the element referenced at `position` in `document`,
+/// which may be in another document of `workspace`. Positions are compared by line and column.
+///
+/// The following references are resolved:
+/// * Call targets, to the name of the task or workflow. For namespaced targets (e.g.
+///   `lib.align`), the namespace is resolved to the import that declares it.
+/// * User-defined types and struct literals, to the name of the struct, following import
+///   aliases (`alias Foo as Bar`).
+/// * Import URIs, to the version statement of the imported document.
+/// * Identifiers within expressions, to the declaration, call, or scatter variable that defines
+///   them.
+pub fn definition_at(
+    workspace: &Workspace,
+    document: &Document,
+    position: &Position,
+) -> Option<Location> {
+    let target = find_target(document, position)?;
+    match target {
+        Target::Call(call, index) => resolve_call(workspace, document, call, index),
+        Target::Struct(name) => find_struct(workspace, document, name, &mut Vec::new()),
+        Target::ImportedStruct(import, name) => {
+            let imported = workspace.resolve_import(document, import)?;
+            find_struct(workspace, imported, name, &mut Vec::new())
+        }
+        Target::Import(import) => workspace
+            .resolve_import(document, import)
+            .map(|imported| Location::new(imported, &imported.version.span)),
+        Target::Identifier => {
+            let scopes = resolve_scopes(document);
+            let reference = scopes
+                .references
+                .iter()
+                .find(|reference| contains(&reference.span, position))?;
+            reference
+                .symbol
+                .as_ref()
+                .map(|symbol| Location::new(document, &symbol.span))
+        }
+    }
+}
+
+/// An element that refers to another element.
+enum Target<'a> {
+    /// The call and the index of the part of its target.
+    Call(&'a Call, usize),
+    Struct(&'a str),
+    /// The name of a struct in the document imported by an import.
+    ImportedStruct(&'a Import, &'a str),
+    Import(&'a Import),
+    Identifier,
+}
+
+fn contains(span: &Span, position: &Position) -> bool {
+    let start = (span.start.line, span.start.column);
+    let end = (span.end.line, span.end.column);
+    let position = (position.line, position.column);
+    start <= position && position < end
+}
+
+fn find_target<'a>(document: &'a Document, position: &Position) -> Option<Target<'a>> {
+    let mut finder = Finder {
+        position,
+        target: None,
+    };
+    let element = document
+        .body
+        .iter()
+        .find(|element| contains(&element.span, position))?;
+    match element.deref() {
+        DocumentElement::Import(import) => {
+            if contains(&import.uri.span, position) {
+                return Some(Target::Import(import));
+            }
+            for alias in import.aliases.iter() {
+                if contains(&alias.from.span, position) || contains(&alias.to.span, position) {
+                    return Some(Target::ImportedStruct(import, alias.from.as_str()));
+                }
+            }
+        }
+        DocumentElement::Struct(struct_) => {
+            for field in struct_.fields.iter() {
+                finder.type_(&field.type_);
+            }
+        }
+        DocumentElement::Task(task) => {
+            for element in task.body.iter() {
+                match element.deref() {
+                    TaskElement::Input(input) => {
+                        for decl in input.declarations.iter() {
+                            finder.input_declaration(decl)
+                        }
+                    }
+                    TaskElement::Declaration(decl) => {
+                        finder.type_(&decl.type_);
+                        finder.expression(&decl.expression);
+                    }
+                    TaskElement::Output(output) => {
+                        for decl in output.declarations.iter() {
+                            finder.type_(&decl.type_);
+                            finder.expression(&decl.expression);
+                        }
+                    }
+                    TaskElement::Command(command) => {
+                        for part in command.parts.iter() {
+                            if let StringPart::Placeholder(expression, _) = part.deref() {
+                                finder.unanchored_expression(expression, &part.span)
+                            }
+                        }
+                    }
+                    TaskElement::Runtime(runtime) => {
+                        for attribute in runtime.attributes.iter() {
+                            finder.expression(&attribute.expression)
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+        DocumentElement::Workflow(workflow) => {
+            for element in workflow.body.iter() {
+                match element.deref() {
+                    WorkflowElement::Input(input) => {
+                        for decl in input.declarations.iter() {
+                            finder.input_declaration(decl)
+                        }
+                    }
+                    WorkflowElement::Output(output) => {
+                        for decl in output.declarations.iter() {
+                            finder.type_(&decl.type_);
+                            finder.expression(&decl.expression);
+                        }
+                    }
+                    WorkflowElement::Declaration(decl) => {
+                        finder.type_(&decl.type_);
+                        finder.expression(&decl.expression);
+                    }
+                    WorkflowElement::Call(call) => finder.call(call),
+                    WorkflowElement::Scatter(scatter) => {
+                        finder.expression(&scatter.expression);
+                        finder.nested(&scatter.body);
+                    }
+                    WorkflowElement::Conditional(conditional) => {
+                        finder.expression(&conditional.expression);
+                        finder.nested(&conditional.body);
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+    finder.target
+}
+
+struct Finder<'a, 'p> {
+    position: &'p Position,
+    target: Option<Target<'a>>,
+}
+
+impl<'a, 'p> Finder<'a, 'p> {
+    fn type_(&mut self, type_: &'a Anchor<Type>) {
+        if self.target.is_some() || !contains(&type_.span, self.position) {
+            return;
+        }
+        match type_.deref() {
+            Type::User(name) => self.target = Some(Target::Struct(name)),
+            Type::Array { item, .. } => self.type_(item),
+            Type::Map { key, value } => {
+                self.type_(key);
+                self.type_(value);
+            }
+            Type::Pair { left, right } => {
+                self.type_(left);
+                self.type_(right);
+            }
+            Type::Optional(inner) => self.type_(inner),
+            _ => (),
+        }
+    }
+
+    fn input_declaration(&mut self, decl: &'a InputDeclaration) {
+        self.type_(decl.type_());
+        if let InputDeclaration::Bound(decl) = decl {
+            self.expression(&decl.expression);
+        }
+    }
+
+    fn expression(&mut self, expression: &'a Anchor<Expression>) {
+        self.unanchored_expression(expression.deref(), &expression.span)
+    }
+
+    fn unanchored_expression(&mut self, expression: &'a Expression, span: &'a Span) {
+        if self.target.is_some() || !contains(span, self.position) {
+            return;
+        }
+        let position = self.position;
+        let mut target = None;
+        walk_expression(expression, span, &mut |e, span| match e {
+            Expression::Identifier(_) if contains(span, position) => {
+                target = Some(Target::Identifier)
+            }
+            Expression::Object(object) if contains(&object.type_name.span, position) => {
+                target = Some(Target::Struct(object.type_name.as_str()))
+            }
+            _ => (),
+        });
+        self.target = target;
+    }
+
+    fn call(&mut self, call: &'a Call) {
+        if self.target.is_some() {
+            return;
+        }
+        if let Some(index) = call
+            .target
+            .parts
+            .iter()
+            .position(|part| contains(&part.span, self.position))
+        {
+            self.target = Some(Target::Call(call, index));
+            return;
+        }
+        for input in call.inputs.iter().flatten() {
+            match &input.expression {
+                Some(expression) => self.expression(expression),
+                None if contains(&input.name.span, self.position) => {
+                    self.target = Some(Target::Identifier)
+                }
+                None => (),
+            }
+        }
+    }
+
+    fn nested(&mut self, body: &'a [Anchor<WorkflowNestedElement>]) {
+        for element in body.iter() {
+            match element.deref() {
+                WorkflowNestedElement::Declaration(decl) => {
+                    self.type_(&decl.type_);
+                    self.expression(&decl.expression);
+                }
+                WorkflowNestedElement::Call(call) => self.call(call),
+                WorkflowNestedElement::Scatter(scatter) => {
+                    self.expression(&scatter.expression);
+                    self.nested(&scatter.body);
+                }
+                WorkflowNestedElement::Conditional(conditional) => {
+                    self.expression(&conditional.expression);
+                    self.nested(&conditional.body);
+                }
+            }
+        }
+    }
+}
+
+/// Resolves part `index` of the target of `call`. All parts but the last are namespaces.
+fn resolve_call(
+    workspace: &Workspace,
+    document: &Document,
+    call: &Call,
+    index: usize,
+) -> Option<Location> {
+    let parts = &call.target.parts;
+    let mut current = document;
+    for (i, part) in parts.iter().enumerate() {
+        if i == parts.len() - 1 {
+            return current.body_iter().find_map(|element| match element {
+                DocumentElement::Task(task) if task.name.as_str() == part.as_str() => {
+                    Some(Location::new(current, &task.name.span))
+                }
+                DocumentElement::Workflow(workflow) if workflow.name.as_str() == part.as_str() => {
+                    Some(Location::new(current, &workflow.name.span))
+                }
+                _ => None,
+            });
+        }
+        let (import, imported) = workspace.resolve_namespace(current, part.as_str())?;
+        if i == index {
+            return Some(Location::new(current, &import.uri.span));
+        }
+        current = imported?;
+    }
+    None
+}
+
+/// Finds the struct named `name` in `document` or, recursively, in the documents it imports.
+/// `visited` contains the sources of the documents that have already been searched, to guard
+/// against import cycles.
+pub(crate) fn find_struct(
+    workspace: &Workspace,
+    document: &Document,
+    name: &str,
+    visited: &mut Vec<DocumentSource>,
+) -> Option<Location> {
+    if visited.contains(&document.source) {
+        return None;
+    }
+    visited.push(document.source.clone());
+    for element in document.body_iter() {
+        if let DocumentElement::Struct(struct_) = element {
+            if struct_.name.as_str() == name {
+                return Some(Location::new(document, &struct_.name.span));
+            }
+        }
+    }
+    for element in document.body_iter() {
+        if let DocumentElement::Import(import) = element {
+            let imported = match workspace.resolve_import(document, import) {
+                Some(imported) => imported,
+                None => continue,
+            };
+            // structs may be renamed by aliases, in which case they are not visible by their
+            // original names
+            let aliased = import
+                .aliases
+                .iter()
+                .find(|alias| alias.to.as_str() == name);
+            let renamed = import
+                .aliases
+                .iter()
+                .any(|alias| alias.from.as_str() == name);
+            let location = match aliased {
+                Some(alias) => find_struct(workspace, imported, alias.from.as_str(), visited),
+                None if !renamed => find_struct(workspace, imported, name, visited),
+                None => None,
+            };
+            if location.is_some() {
+                return location;
+            }
+        }
+    }
+    None
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::parsers::{PestParser, WdlParser};
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    fn parse(path: &str, text: &str) -> Document {
+        PestParser::new()
+            .parse_text(text, DocumentSource::File(PathBuf::from(path)))
+            .unwrap()
+    }
+
+    /// Returns the position of the first character of the `n`th occurrence of `pattern`.
+    fn position_of(text: &str, pattern: &str, n: usize) -> Position {
+        let offset = text.match_indices(pattern).nth(n).unwrap().0;
+        let line = text[..offset].matches('\n').count();
+        let column = offset - text[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        Position::new(line, column, offset)
+    }
+
+    #[test]
+    fn test_definition_at() {
+        let lib = r#"version 1.1
+
+struct Sample {
+    String name
+}
+
+task align {
+    input {
+        Sample s
+    }
+    command <<< >>>
+}
+"#;
+        let main = r#"version 1.1
+
+import "lib/tasks.wdl" as lib alias Sample as Specimen
+
+workflow wf {
+    input {
+        Specimen sample
+    }
+    call lib.align { input: s = sample }
+}
+"#;
+        let mut workspace = Workspace::new();
+        workspace.insert(parse("/w/lib/tasks.wdl", lib));
+        workspace.insert(parse("/w/main.wdl", main));
+        let main_doc = workspace
+            .get(&DocumentSource::File(PathBuf::from("/w/main.wdl")))
+            .unwrap();
+        let lib_source = DocumentSource::File(PathBuf::from("/w/lib/tasks.wdl"));
+        let main_source = main_doc.source.clone();
+        let definition = |pattern: &str, n: usize| {
+            definition_at(&workspace, main_doc, &position_of(main, pattern, n))
+                .map(|location| (location.source, location.span.start.line))
+        };
+        // call target, through the namespace
+        assert_eq!(definition("align", 0), Some((lib_source.clone(), 6)));
+        // namespace
+        assert_eq!(definition("lib.align", 0), Some((main_source.clone(), 2)));
+        // aliased struct type
+        assert_eq!(
+            definition("Specimen sample", 0),
+            Some((lib_source.clone(), 2))
+        );
+        // import alias
+        assert_eq!(definition("Sample", 0), Some((lib_source.clone(), 2)));
+        // import uri
+        assert_eq!(definition("lib/tasks", 0), Some((lib_source, 0)));
+        // identifier
+        assert_eq!(definition("sample }", 0), Some((main_source, 6)));
+        // nothing to resolve
+        assert_eq!(definition("workflow", 0), None);
+    }
+}
@@ -1,12 +1,30 @@
+#[cfg(feature = "pest")]
 mod pest;
+#[cfg(any(feature = "pest", feature = "tree-sitter"))]
+mod scan;
+#[cfg(any(feature = "pest", feature = "tree-sitter"))]
+mod syntax_error;
+mod syntax_tree;
+#[cfg(feature = "tree-sitter")]
 mod tree_sitter;
 
+#[cfg(feature = "pest")]
 pub use crate::parsers::pest::PestParser;
+#[cfg(feature = "tree-sitter")]
 pub use crate::parsers::tree_sitter::TreeSitterParser;
+#[cfg(any(feature = "pest", feature = "tree-sitter"))]
+pub use syntax_error::{SyntaxError, PLACEHOLDER_IN_TYPE, PLACEHOLDER_IN_VERSION, SYNTAX_ERROR};
+pub use syntax_tree::{ParseResult, SyntaxNode, SyntaxTree};
 
-use crate::model::{Document, DocumentSource};
+use crate::{
+    diagnostics::Diagnostic,
+    edit::TextEdit,
+    fix::Fix,
+    model::{Document, DocumentSource, Span},
+    sourcemap::position_at,
+};
 use error_stack::{IntoReport, Result, ResultExt};
-use std::{fs, path::Path};
+use std::{fs, path::Path, time::Duration};
 use thiserror::Error;
 
 /// Syntax errors that may be returned when creating model elements.
@@ -16,12 +34,234 @@ pub enum WdlParserError {
     Internal,
     #[error("error reading WDL document from {0}")]
     IO(DocumentSource),
+    #[error("WDL document from {0} is not valid UTF-8")]
+    Encoding(DocumentSource),
     #[error("error parsing WDL document from {0}")]
     Syntax(DocumentSource),
+    #[error("WDL document from {0} is missing a version statement")]
+    MissingVersion(DocumentSource),
     #[error("error building WDL model from {0}")]
     Model(DocumentSource),
+    #[error("WDL document from {0} is larger than the limit of {1} bytes")]
+    TooLarge(DocumentSource, usize),
+    #[error("parsing WDL document from {0} timed out")]
+    Timeout(DocumentSource),
+    #[error("WDL document from {0} exceeds the maximum nesting depth of {1}")]
+    TooDeep(DocumentSource, usize),
 }
 
+/// The default maximum nesting depth of brackets in a document.
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 128;
+
+/// Limits on the resources that are used to parse a document, e.g. for services that parse
+/// untrusted documents. By default only the nesting depth is limited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// The maximum time to spend parsing a document, including building and validating its
+    /// model. The tree-sitter parser is interrupted when the timeout expires; the pest parser
+    /// cannot be interrupted while it matches the grammar, so the timeout is checked after
+    /// matching and after building the model.
+    pub timeout: Option<Duration>,
+    /// The maximum size of a document, in bytes.
+    pub max_input_size: Option<usize>,
+    /// The maximum depth to which parentheses, brackets, braces, and placeholders may be nested.
+    /// Both the parsers and the construction of the model are recursive, so deeply nested
+    /// expressions would otherwise overflow the stack. The default is
+    /// `DEFAULT_MAX_NESTING_DEPTH`.
+    pub max_nesting_depth: Option<usize>,
+    /// Whether to retain the text of a document on the `Document`, so that the text of any span
+    /// can later be retrieved with `Document::text_for` (e.g. to render diagnostics or to apply
+    /// refactorings) without reading the document again. Not retained by default.
+    pub retain_text: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            max_input_size: None,
+            max_nesting_depth: Some(DEFAULT_MAX_NESTING_DEPTH),
+            retain_text: false,
+        }
+    }
+}
+
+impl ParseOptions {
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_max_input_size(mut self, size: usize) -> Self {
+        self.max_input_size = Some(size);
+        self
+    }
+
+    /// Sets the maximum nesting depth, or removes the limit if `depth` is `None`.
+    pub fn with_max_nesting_depth(mut self, depth: Option<usize>) -> Self {
+        self.max_nesting_depth = depth;
+        self
+    }
+
+    pub fn with_retained_text(mut self, retain: bool) -> Self {
+        self.retain_text = retain;
+        self
+    }
+
+    /// Returns an error if `text` exceeds the maximum input size or nesting depth, otherwise the
+    /// deadline by which parsing must finish, if any. An error for the nesting depth has a
+    /// `Diagnostic` attachment at the bracket that exceeds the limit.
+    #[cfg(any(feature = "pest", feature = "tree-sitter"))]
+    pub(crate) fn start(
+        &self,
+        text: &str,
+        source: &DocumentSource,
+    ) -> Result<Deadline, WdlParserError> {
+        if let Some(limit) = self.max_input_size {
+            error_stack::ensure!(
+                text.len() <= limit,
+                WdlParserError::TooLarge(source.clone(), limit)
+            );
+        }
+        if let Some(limit) = self.max_nesting_depth {
+            if let Some(offset) = nesting_limit_offset(text, limit) {
+                let span = Span {
+                    start: position_at(text, offset),
+                    end: position_at(text, offset + 1),
+                };
+                return Err(error_stack::Report::new(WdlParserError::TooDeep(
+                    source.clone(),
+                    limit,
+                ))
+                .attach(Diagnostic::error(
+                    "nesting_too_deep",
+                    format!("nesting exceeds the maximum depth of {}", limit),
+                    span,
+                )));
+            }
+        }
+        Ok(Deadline(
+            self.timeout
+                .map(|timeout| std::time::Instant::now() + timeout),
+        ))
+    }
+}
+
+/// The time by which parsing a document must finish.
+#[cfg(any(feature = "pest", feature = "tree-sitter"))]
+pub(crate) struct Deadline(Option<std::time::Instant>);
+
+#[cfg(any(feature = "pest", feature = "tree-sitter"))]
+impl Deadline {
+    /// Returns the time remaining before the deadline, or `None` if there is no deadline.
+    pub(crate) fn remaining(&self) -> Option<Duration> {
+        self.0
+            .map(|deadline| deadline.saturating_duration_since(std::time::Instant::now()))
+    }
+
+    /// Returns an error if the deadline has passed.
+    pub(crate) fn check(&self, source: &DocumentSource) -> Result<(), WdlParserError> {
+        error_stack::ensure!(
+            self.remaining() != Some(Duration::ZERO),
+            WdlParserError::Timeout(source.clone())
+        );
+        Ok(())
+    }
+}
+
+/// Returns the offset of the first bracket in `text` at which the nesting depth exceeds `limit`,
+/// or `None` if it does not. Parentheses, brackets, braces, and placeholders are counted, except
+/// within comments and outside of placeholders in string literals and commands (see
+/// `scan::scan`). The text is not parsed, so unbalanced brackets are tolerated.
+#[cfg(any(feature = "pest", feature = "tree-sitter"))]
+fn nesting_limit_offset(text: &str, limit: usize) -> Option<usize> {
+    let mut depth = 0;
+    scan::scan(text, |bracket, offset| {
+        if bracket == scan::Bracket::Close {
+            depth -= 1;
+        } else {
+            depth += 1;
+            if depth > limit {
+                return std::ops::ControlFlow::Break(offset);
+            }
+        }
+        std::ops::ControlFlow::Continue(())
+    })
+}
+
+/// Returns a diagnostic if `text` does not begin with a version statement (ignoring whitespace
+/// and comments), which is the case for draft-2 documents. The diagnostic has a fix that adds
+/// `version 1.0`, although a draft-2 document will generally require other changes as well.
+pub fn missing_version(text: &str) -> Option<Diagnostic> {
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if !trimmed.is_empty() && !trimmed.starts_with('#') {
+            let start = offset + line.len() - trimmed.len();
+            let token = trimmed
+                .split(|c: char| c.is_whitespace() || c == '{')
+                .next()
+                .unwrap();
+            if token == "version" {
+                return None;
+            }
+            let span = Span {
+                start: position_at(text, start),
+                end: position_at(text, start + token.len()),
+            };
+            let fix = Fix::new(
+                "Add version 1.0",
+                vec![TextEdit::insert(
+                    position_at(text, offset),
+                    "version 1.0\n\n",
+                )],
+            );
+            return Some(
+                Diagnostic::error(
+                    "missing_version",
+                    "missing version statement - is this a draft-2 document?",
+                    span,
+                )
+                .with_fix(fix),
+            );
+        }
+        offset += line.len();
+    }
+    let end = position_at(text, text.len());
+    Some(Diagnostic::error(
+        "missing_version",
+        "missing version statement",
+        Span {
+            start: end.clone(),
+            end,
+        },
+    ))
+}
+
+/// Returns an error with the `missing_version` diagnostic attached if `text` does not begin with
+/// a version statement, so that the parsers can report it instead of a syntax error.
+#[cfg(any(feature = "pest", feature = "tree-sitter"))]
+pub(crate) fn check_version(text: &str, source: &DocumentSource) -> Result<(), WdlParserError> {
+    match missing_version(text) {
+        Some(diagnostic) => Err(error_stack::Report::new(WdlParserError::MissingVersion(
+            source.clone(),
+        ))
+        .attach(diagnostic)),
+        None => Ok(()),
+    }
+}
+
+// Parsers share state (e.g. comments) between the nodes of a parse tree with `Rc`s, but that
+// state does not outlive a call to a parse method, so parsers can be moved between threads.
+const _: fn() = || {
+    #[allow(dead_code)]
+    fn assert_send<T: Send>() {}
+    #[cfg(feature = "pest")]
+    assert_send::<PestParser>();
+    #[cfg(feature = "tree-sitter")]
+    assert_send::<TreeSitterParser>();
+};
+
 pub trait WdlParser {
     fn parse_text<Text: AsRef<str>>(
         &mut self,
@@ -29,12 +269,326 @@ pub trait WdlParser {
         source: DocumentSource,
     ) -> Result<Document, WdlParserError>;
 
+    /// Parses `text` like `parse_text`, and also returns the syntax tree from which the model
+    /// was built.
+    fn parse_text_with_syntax<Text: AsRef<str>>(
+        &mut self,
+        text: Text,
+        source: DocumentSource,
+    ) -> Result<ParseResult, WdlParserError>;
+
+    /// Parses a document from `bytes`, which must be UTF-8, taking ownership of them to avoid a
+    /// copy. The returned `Document` does not borrow from or retain the text, so it can be
+    /// stored in a long-lived cache once the text is dropped.
+    fn parse_bytes(
+        &mut self,
+        bytes: Vec<u8>,
+        source: DocumentSource,
+    ) -> Result<Document, WdlParserError> {
+        let text = String::from_utf8(bytes)
+            .into_report()
+            .change_context(WdlParserError::Encoding(source.clone()))?;
+        self.parse_text(text, source)
+    }
+
     fn parse_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Document, WdlParserError> {
         let path = path.as_ref().to_owned();
         let source = DocumentSource::File(path.clone());
-        let text = fs::read_to_string(&path)
+        let bytes = fs::read(&path)
             .into_report()
             .change_context(WdlParserError::IO(source.clone()))?;
-        self.parse_text(text, source)
+        self.parse_bytes(bytes, source)
+    }
+}
+
+#[cfg(all(test, feature = "pest", feature = "tree-sitter"))]
+mod tests {
+    use super::*;
+    use crate::testing::assert_parsers_agree;
+
+    #[test]
+    fn test_binary_chains() {
+        let text = r#"
+version 1.1
+
+workflow chains {
+    Int a = 1 + 2 + 3
+    Int b = 1 - 2 + 3 * 4 / 5 % 6
+    Boolean c = true || false && true || false
+    Boolean d = 1 < 2 == 3 > 4 != false
+    Int e = 1 - (2 - 3) - 4
+}
+"#;
+        assert_parsers_agree(text);
+    }
+
+    #[test]
+    fn test_command_style() {
+        use crate::model::{CommandStyle, DocumentElement};
+
+        let style = |doc: &Document| match doc.body_iter().next().unwrap() {
+            DocumentElement::Task(task) => task.command().unwrap().style,
+            _ => unreachable!(),
+        };
+        for (command, expected) in [
+            ("command <<< echo ~{x} >>>", CommandStyle::Heredoc),
+            (
+                "command <<<\n        echo ~{x} ${HOME}\n    >>>",
+                CommandStyle::Heredoc,
+            ),
+            ("command { echo ~{x} ${x} }", CommandStyle::Braces),
+            ("command {}", CommandStyle::Braces),
+        ] {
+            let text = format!(
+                "version 1.1\n\ntask t {{\n    input {{\n        Int x\n    }}\n    {}\n}}\n",
+                command
+            );
+            let pest = PestParser::new()
+                .parse_text(&text, DocumentSource::Unknown)
+                .unwrap();
+            let tree_sitter = TreeSitterParser::new()
+                .unwrap()
+                .parse_text(&text, DocumentSource::Unknown)
+                .unwrap();
+            assert_eq!((style(&pest), style(&tree_sitter)), (expected, expected));
+            assert_eq!(
+                pest.body_iter().next().unwrap(),
+                tree_sitter.body_iter().next().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_nesting_limit_offset() {
+        let text = "Int x = [(1)] # (((\nString s = \"((~{[a]}\"\ncommand <<< ( ${x} ~{y} >>>";
+        assert_eq!(nesting_limit_offset(text, 2), None);
+        assert_eq!(nesting_limit_offset(text, 1), Some(9));
+        let text = "String s = \"~{[a]}\"";
+        assert_eq!(nesting_limit_offset(text, 1), Some(14));
+        assert_eq!(nesting_limit_offset("((1)) ((1))", 2), None);
+        assert_eq!(nesting_limit_offset(")))((1)", 1), Some(4));
+        // the shell script of a brace command is not scanned as WDL, so its quotes and comments
+        // do not hide the braces that close the command and the enclosing blocks
+        let task = "task t {\n  command {\n    echo ${#a[@]} don't # ${x}\n  }\n}\n";
+        let text = task.repeat(200);
+        assert_eq!(nesting_limit_offset(&text, 4), None);
+        assert_eq!(
+            nesting_limit_offset(&text, 3),
+            Some(task.find('[').unwrap())
+        );
+        // a brace command ends at its first `}`, as in the grammar, even if the shell script
+        // quotes it or has braces of its own, and the quote that follows it only starts a string
+        // that ends with the line, so nesting in the rest of the document is still found
+        for task in [
+            "task t {\n  command {\n    echo '}'\n  }\n}\n",
+            "task t {\n  command {\n    echo {\n  }\n}\n",
+        ] {
+            let workflow = "workflow w {\n  Int x = ((1))\n}\n";
+            let text = format!("{}{}", task.repeat(200), workflow);
+            assert_eq!(nesting_limit_offset(&text, 3), None);
+            assert_eq!(
+                nesting_limit_offset(&text, 2),
+                Some(text.len() - workflow.len() + workflow.find("(1").unwrap())
+            );
+        }
+    }
+
+    /// Asserts that a parser created by `parser` applies the `ParseOptions` it is given.
+    fn assert_parse_options<P: WdlParser>(parser: impl Fn(ParseOptions) -> P) {
+        let text = "version 1.1\n\ntask a {\n    command <<< >>>\n}\n";
+        let options = ParseOptions::default().with_max_input_size(text.len());
+        assert!(parser(options)
+            .parse_text(text, DocumentSource::Unknown)
+            .is_ok());
+        let report = parser(options.with_max_input_size(text.len() - 1))
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap_err();
+        assert!(matches!(
+            report.current_context(),
+            WdlParserError::TooLarge(_, limit) if *limit == text.len() - 1
+        ));
+        let report = parser(options.with_timeout(Duration::ZERO))
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap_err();
+        assert!(matches!(
+            report.current_context(),
+            WdlParserError::Timeout(_)
+        ));
+        let text = format!(
+            "version 1.1\n\nworkflow w {{\n    Int x = {}1{}\n}}\n",
+            "(".repeat(200),
+            ")".repeat(200)
+        );
+        let report = parser(ParseOptions::default())
+            .parse_text(&text, DocumentSource::Unknown)
+            .unwrap_err();
+        assert!(matches!(
+            report.current_context(),
+            WdlParserError::TooDeep(_, DEFAULT_MAX_NESTING_DEPTH)
+        ));
+        let diagnostic = report.downcast_ref::<Diagnostic>().unwrap();
+        assert_eq!(diagnostic.span.start.line, 3);
+        // the brace of the workflow is the first level
+        assert_eq!(
+            diagnostic.span.start.column,
+            12 + DEFAULT_MAX_NESTING_DEPTH - 1
+        );
+        let text = "version 1.1\n\nworkflow w {\n    Int x = ((1))\n}\n";
+        assert!(
+            parser(ParseOptions::default().with_max_nesting_depth(Some(3)))
+                .parse_text(text, DocumentSource::Unknown)
+                .is_ok()
+        );
+        assert!(
+            parser(ParseOptions::default().with_max_nesting_depth(Some(2)))
+                .parse_text(text, DocumentSource::Unknown)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_options() {
+        assert_parse_options(|options| PestParser::new().with_parse_options(options));
+        assert_parse_options(|options| {
+            TreeSitterParser::new().unwrap().with_parse_options(options)
+        });
+    }
+
+    fn assert_parse_bytes<P: WdlParser>(mut parser: P) {
+        let text = "version 1.1\n\ntask a {\n    command <<< >>>\n}\n";
+        let document = parser
+            .parse_bytes(text.as_bytes().to_vec(), DocumentSource::Unknown)
+            .unwrap();
+        assert_eq!(document.body.len(), 1);
+        let report = parser
+            .parse_bytes(vec![b'v', 0xff], DocumentSource::Unknown)
+            .unwrap_err();
+        assert!(matches!(
+            report.current_context(),
+            WdlParserError::Encoding(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_bytes() {
+        assert_parse_bytes(PestParser::new());
+        assert_parse_bytes(TreeSitterParser::new().unwrap());
+    }
+
+    fn assert_source_digest<P: WdlParser>(mut parser: P) {
+        let text = "version 1.1\n\ntask a {\n    command <<< >>>\n}\n";
+        let document = parser.parse_text(text, DocumentSource::Unknown).unwrap();
+        assert_eq!(
+            document.source_digest().to_string(),
+            "1777fe39e08fd9e949eee1fef422bf23f8e87f924738f3e1a0918a509577efe3"
+        );
+        assert!(document.matches_source(text));
+        assert!(!document.matches_source(text.replace("a {", "b {")));
+        let from_bytes = parser
+            .parse_bytes(text.as_bytes().to_vec(), DocumentSource::Unknown)
+            .unwrap();
+        assert_eq!(from_bytes.source_digest(), document.source_digest());
+    }
+
+    #[test]
+    fn test_source_digest() {
+        assert_source_digest(PestParser::new());
+        assert_source_digest(TreeSitterParser::new().unwrap());
+    }
+
+    fn assert_retained_text<P: WdlParser>(parser: impl Fn(ParseOptions) -> P) {
+        use crate::model::{DocumentElement, Position};
+
+        let text = "version 1.1\n\ntask a {\n    command <<< >>>\n}\n";
+        let mut document = parser(ParseOptions::default())
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        assert!(document.source_text().is_none());
+        assert!(!document.attach_text("version 1.1\n"));
+        assert!(document.attach_text(text));
+
+        let document = parser(ParseOptions::default().with_retained_text(true))
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let task = match document.body_iter().next() {
+            Some(DocumentElement::Task(task)) => task,
+            other => panic!("expected task, got {:?}", other),
+        };
+        assert_eq!(
+            document
+                .text_for(&task.name.span)
+                .map(|text| text.to_string()),
+            Some("a".to_owned())
+        );
+        let source = document.source_text().unwrap();
+        assert_eq!(source.line_index().line_count(), 6);
+        assert_eq!(source.line(3), Some("    command <<< >>>"));
+        assert_eq!(source.position(17), Some(Position::new(2, 4, 17)));
+        assert_eq!(
+            source.position(task.name.span.start.offset),
+            Some(task.name.span.start.clone())
+        );
+        assert_eq!(source.position(text.len() + 1), None);
+    }
+
+    #[test]
+    fn test_retained_text() {
+        assert_retained_text(|options| PestParser::new().with_parse_options(options));
+        assert_retained_text(|options| {
+            TreeSitterParser::new().unwrap().with_parse_options(options)
+        });
+    }
+
+    #[test]
+    fn test_missing_version() {
+        let text = "# a draft-2 task\n\ntask a {\n    command {}\n}\n";
+        let diagnostic = missing_version(text).unwrap();
+        assert_eq!(diagnostic.code, "missing_version");
+        assert_eq!(diagnostic.span, Span::from_components(2, 0, 18, 2, 4, 22));
+        assert_eq!(
+            diagnostic.fixes[0].edits[0],
+            TextEdit::insert(crate::model::Position::new(2, 0, 18), "version 1.0\n\n")
+        );
+        assert!(missing_version("  # header\nversion 1.1\n").is_none());
+        assert!(missing_version("").is_some());
+        let report = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap_err();
+        assert!(matches!(
+            report.current_context(),
+            WdlParserError::MissingVersion(_)
+        ));
+        assert_eq!(
+            report.downcast_ref::<Diagnostic>().unwrap().code,
+            "missing_version"
+        );
+        let report = TreeSitterParser::new()
+            .unwrap()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap_err();
+        assert!(report.downcast_ref::<Diagnostic>().is_some());
+    }
+
+    #[test]
+    fn test_misplaced_placeholder() {
+        let text = "version 1.1\n\nstruct S {\n    Map[String, ~{value_type}] m\n}\n";
+        let report = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap_err();
+        assert!(matches!(
+            report.current_context(),
+            WdlParserError::Syntax(_)
+        ));
+        let diagnostic = report.downcast_ref::<Diagnostic>().unwrap();
+        assert_eq!(diagnostic.code, PLACEHOLDER_IN_TYPE);
+        assert_eq!(diagnostic.span, Span::from_components(3, 16, 40, 3, 29, 53));
+        let report = TreeSitterParser::new()
+            .unwrap()
+            .parse_text("version ${version}\n", DocumentSource::Unknown)
+            .unwrap_err();
+        assert_eq!(
+            report.downcast_ref::<Diagnostic>().unwrap().code,
+            PLACEHOLDER_IN_VERSION
+        );
     }
 }
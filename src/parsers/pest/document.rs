@@ -1,7 +1,7 @@
 use crate::{
     model::{
         Alias, Anchor, Document, DocumentElement, DocumentSource, Import, ModelError, Namespace,
-        Struct, Version,
+        SourceDigest, Struct, Version,
     },
     parsers::pest::{node::PestNode, Rule},
 };
@@ -111,6 +111,8 @@ impl<'a> TryFrom<PestNode<'a>> for Document {
             version,
             body,
             comments: comments.take(),
+            digest: SourceDigest::default(),
+            text: None,
         })
     }
 }
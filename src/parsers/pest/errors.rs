@@ -0,0 +1,215 @@
+use crate::parsers::{
+    pest::Rule,
+    syntax_error::{alternatives, describe_token, enclosing_block, token_span, SyntaxError},
+};
+use pest::error::{Error as PestError, ErrorVariant, InputLocation};
+
+/// Returns a description of what `rule` matches, for use in "expected ..." messages, or `None`
+/// if the rule should not be mentioned.
+fn describe(rule: Rule) -> Option<String> {
+    let description = match rule {
+        Rule::COMMENT => return None,
+        Rule::EOI => "the end of the document",
+        Rule::identifier => "a name",
+        Rule::namespace => "a namespace",
+        Rule::import => "an import",
+        Rule::structdef => "a struct",
+        Rule::task => "a task",
+        Rule::workflow => "a workflow",
+        Rule::typedef
+        | Rule::primitive_type
+        | Rule::array_type
+        | Rule::non_empty_array_type
+        | Rule::map_type
+        | Rule::pair_type
+        | Rule::user_type => "a type",
+        Rule::optional => "`?`",
+        Rule::non_empty => "`+`",
+        Rule::bound_declaration | Rule::unbound_declaration => "a declaration",
+        Rule::input => "an input section",
+        Rule::output => "an output section",
+        Rule::command => "a command section",
+        Rule::meta => "a meta section",
+        Rule::parameter_meta => "a parameter_meta section",
+        Rule::runtime => "a runtime section",
+        Rule::call => "a call",
+        Rule::call_alias => "`as`",
+        Rule::call_inputs => "call inputs",
+        Rule::scatter => "a scatter",
+        Rule::conditional => "a conditional",
+        Rule::meta_object | Rule::meta_array | Rule::meta_number => "a metadata value",
+        Rule::dquote_string
+        | Rule::squote_string
+        | Rule::simple_dquote_string
+        | Rule::simple_squote_string => "a string",
+        Rule::dquote_literal
+        | Rule::squote_literal
+        | Rule::simple_dquote_literal
+        | Rule::simple_squote_literal
+        | Rule::dquote_escape_sequence
+        | Rule::squote_escape_sequence
+        | Rule::simple_dquote_escape_sequence
+        | Rule::simple_squote_escape_sequence => "the rest of the string",
+        Rule::command_heredoc_literal
+        | Rule::command_heredoc_escape_sequence
+        | Rule::single_line_command_block_literal
+        | Rule::multi_line_command_block_literal
+        | Rule::command_block_escape_sequence => "the rest of the command",
+        Rule::tilde_placeholder | Rule::dollar_placeholder => "a placeholder",
+        Rule::expression
+        | Rule::ternary
+        | Rule::disjunction
+        | Rule::conjunction
+        | Rule::equality
+        | Rule::comparison
+        | Rule::unary
+        | Rule::access
+        | Rule::group
+        | Rule::apply
+        | Rule::array
+        | Rule::map
+        | Rule::pair
+        | Rule::object
+        | Rule::boolean
+        | Rule::none
+        | Rule::null
+        | Rule::float
+        | Rule::dec_int
+        | Rule::hex_int
+        | Rule::oct_int => "an expression",
+        Rule::or
+        | Rule::and
+        | Rule::eq
+        | Rule::neq
+        | Rule::lt
+        | Rule::lte
+        | Rule::gt
+        | Rule::gte
+        | Rule::add
+        | Rule::sub
+        | Rule::mul
+        | Rule::div
+        | Rule::rem => "an operator",
+        Rule::index => "an index",
+        Rule::field => "a member access",
+        other => return Some(format!("{:?}", other).replace('_', " ")),
+    };
+    Some(description.to_owned())
+}
+
+/// Whether an error at which one of `rules` is expected may instead be the end of the enclosing
+/// block, i.e. whether the rules are the start of an element of a block.
+fn starts_element(rules: &[Rule]) -> bool {
+    rules.iter().any(|rule| {
+        matches!(
+            rule,
+            Rule::typedef
+                | Rule::primitive_type
+                | Rule::user_type
+                | Rule::bound_declaration
+                | Rule::unbound_declaration
+                | Rule::input
+                | Rule::output
+                | Rule::command
+                | Rule::meta
+                | Rule::parameter_meta
+                | Rule::runtime
+                | Rule::call
+                | Rule::scatter
+                | Rule::conditional
+        )
+    })
+}
+
+/// Translates an error of the pest grammar into a `SyntaxError` for `text`, with a message that
+/// describes the expected syntax rather than the names of the grammar rules.
+pub(crate) fn syntax_error(error: &PestError<Rule>, text: &str) -> SyntaxError {
+    let offset = match error.location {
+        InputLocation::Pos(pos) => pos,
+        InputLocation::Span((start, _)) => start,
+    };
+    let span = token_span(text, offset);
+    let found = describe_token(text, &span);
+    let message = match &error.variant {
+        ErrorVariant::CustomError { message } => message.clone(),
+        ErrorVariant::ParsingError {
+            positives,
+            negatives,
+        } => {
+            let mut expected: Vec<String> = Vec::new();
+            for description in positives.iter().filter_map(|rule| describe(*rule)) {
+                if !expected.contains(&description) {
+                    expected.push(description)
+                }
+            }
+            // a string is an expression
+            if expected.iter().any(|e| e == "an expression") {
+                expected.retain(|e| e != "a string")
+            }
+            // the end of the document is the least likely alternative
+            let eoi = describe(Rule::EOI).unwrap();
+            if let Some(i) = expected.iter().position(|e| *e == eoi) {
+                let eoi = expected.remove(i);
+                expected.push(eoi)
+            }
+            let block = enclosing_block(text, offset);
+            match block {
+                Some(block) if starts_element(positives) => {
+                    expected.push(format!("`}}` to close {}", block))
+                }
+                _ => (),
+            }
+            let unexpected: Vec<String> = negatives.iter().filter_map(|r| describe(*r)).collect();
+            match (expected.is_empty(), block) {
+                (false, _) => format!("expected {}, found {}", alternatives(&expected), found),
+                (true, _) if !unexpected.is_empty() => {
+                    format!("unexpected {}", alternatives(&unexpected))
+                }
+                (true, Some(block)) => format!("unexpected {} in {}", found, block),
+                (true, None) => format!("unexpected {}", found),
+            }
+        }
+    };
+    SyntaxError::new(text, message, span)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        diagnostics::Diagnostic,
+        model::DocumentSource,
+        parsers::{PestParser, SyntaxError, WdlParser, WdlParserError},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_syntax_errors() {
+        let message = |text: &str| -> String {
+            let report = PestParser::new()
+                .parse_text(text, DocumentSource::Unknown)
+                .unwrap_err();
+            assert!(matches!(
+                report.current_context(),
+                WdlParserError::Syntax(_)
+            ));
+            assert_eq!(
+                report.downcast_ref::<Diagnostic>().unwrap().code,
+                "syntax_error"
+            );
+            let errors = SyntaxError::from_report(&report);
+            assert_eq!(errors.len(), 1);
+            errors[0].to_string()
+        };
+        assert_eq!(
+            message("version 1.1\n\ntask a {\n    input {\n        Int = 1\n    }\n}\n"),
+            "5:13: expected a name or `?`, found `=`\n            Int = 1\n                ^"
+        );
+        assert_eq!(
+            message("version 1.1\n\nworkflow w {\n    Int x = 1 +\n"),
+            "5:1: expected an expression, found the end of the document\n    \n    ^"
+        );
+        let message = message("version 1.1\n\nfoo\n");
+        assert!(message.starts_with("3:1: expected an import"));
+        assert!(message.contains(", or the end of the document, found `foo`"));
+    }
+}
@@ -1,8 +1,8 @@
 use crate::{
     model::{
         Access, AccessOperation, Anchor, Apply, ArrayLiteral, Binary, BinaryOperator, Expression,
-        InnerSpan, MapEntry, MapLiteral, ModelError, ObjectField, ObjectLiteral, PairLiteral, Span,
-        StringLiteral, StringPart, Ternary, Unary, UnaryOperator,
+        InnerSpan, MapEntry, MapLiteral, ModelError, ObjectField, ObjectLiteral, PairLiteral,
+        PlaceholderStyle, Span, StringLiteral, StringPart, Ternary, Unary, UnaryOperator,
     },
     parsers::pest::{
         node::{PestNode, PestNodes},
@@ -26,9 +26,14 @@ impl<'a> TryFrom<PestNode<'a>> for StringPart {
             | Rule::dquote_escape_sequence
             | Rule::command_block_escape_sequence
             | Rule::command_heredoc_escape_sequence => Self::Escape(node.try_into()?),
-            Rule::tilde_placeholder | Rule::dollar_placeholder => {
-                Self::Placeholder(Expression::try_from(node.one_inner()?)?)
-            }
+            Rule::tilde_placeholder => Self::Placeholder(
+                Expression::try_from(node.one_inner()?)?,
+                PlaceholderStyle::Tilde,
+            ),
+            Rule::dollar_placeholder => Self::Placeholder(
+                Expression::try_from(node.one_inner()?)?,
+                PlaceholderStyle::Dollar,
+            ),
             _ => bail!(ModelError::parser(format!(
                 "Invalid string part {:?}",
                 node
@@ -115,10 +120,8 @@ impl<'a> TryFrom<PestNode<'a>> for ObjectLiteral {
 
     fn try_from(node: PestNode<'a>) -> Result<Self, ModelError> {
         let mut inner = node.into_inner();
-        Ok(Self {
-            type_name: inner.next_node().try_into()?,
-            fields: inner.collect_anchors()?,
-        })
+        let type_name = inner.next_node().try_into()?;
+        Ok(Self::new(type_name, inner.collect_anchors()?))
     }
 }
 
@@ -224,22 +227,14 @@ fn try_into_unary<'a>(first: PestNode<'a>, second: PestNode<'a>) -> Result<Expre
 fn try_into_binary<'a>(
     first: PestNode<'a>,
     mut rest: PestNodes<'a>,
-) -> Result<Expression, ModelError> {
-    let operator = rest.next_node()?.try_into()?;
-    let mut bin = Binary {
-        operator,
-        left: Box::new(try_into_expression_anchor(first)?),
-        right: Box::new(try_into_expression_anchor(rest.next_node()?)?),
-    };
+) -> Result<Anchor<Expression>, ModelError> {
+    let first = try_into_expression_anchor(first)?;
+    let mut operands = Vec::new();
     while let Some(node) = rest.next() {
-        let span = Span::from_range(&bin.left.span, &bin.right.span);
-        bin = Binary {
-            operator: node?.try_into()?,
-            left: Box::new(Anchor::new(Expression::Binary(bin), span)),
-            right: Box::new(try_into_expression_anchor(rest.next_node()?)?),
-        }
+        let operator: BinaryOperator = node?.try_into()?;
+        operands.push((operator, try_into_expression_anchor(rest.next_node()?)?));
     }
-    Ok(Expression::Binary(bin))
+    Ok(Binary::fold(first, operands))
 }
 
 fn try_into_access_operation_anchor<'a>(
@@ -304,9 +299,7 @@ pub fn try_into_expression_anchor<'a>(
             let mut inner = node.into_inner();
             let first = inner.next_node()?;
             if inner.has_next() {
-                let binary = try_into_binary(first, inner)?;
-                let span = binary.get_inner_span().unwrap();
-                Ok(Anchor::new(binary, span))
+                try_into_binary(first, inner)
             } else {
                 try_into_expression_anchor(first)
             }
@@ -353,7 +346,7 @@ impl<'a> TryFrom<PestNode<'a>> for Expression {
                 let mut inner = node.into_inner();
                 let first = inner.next_node()?;
                 if inner.has_next() {
-                    try_into_binary(first, inner)?
+                    try_into_binary(first, inner)?.into_element()
                 } else {
                     first.try_into()?
                 }
@@ -1,5 +1,6 @@
 mod declarations;
 mod document;
+mod errors;
 mod expressions;
 mod meta;
 mod node;
@@ -8,20 +9,108 @@ mod task;
 mod workflow;
 
 use crate::{
-    model::{Comments, Document, DocumentSource, Position, Span},
-    parsers::{pest::node::PestNode, WdlParser, WdlParserError},
+    model::{
+        Comments, Document, DocumentSource, Position, SourceDigest, SourceText, Span,
+        ValidationOptions,
+    },
+    parsers::{
+        check_version,
+        pest::node::PestNode,
+        syntax_error::misplaced_placeholder,
+        syntax_tree::{span_fn, tokenize},
+        ParseOptions, ParseResult, SyntaxNode, SyntaxTree, WdlParser, WdlParserError,
+    },
+    trace,
+};
+use error_stack::{Report, Result, ResultExt};
+use pest::{
+    error::{Error as PestError, InputLocation, LineColLocation},
+    iterators::Pair,
 };
-use error_stack::{IntoReport, Result, ResultExt};
-use pest::error::{Error as PestError, InputLocation, LineColLocation};
 use pest_wdl_1 as wdl;
 use pest_wdl_1::Rule;
 use std::{cell::RefCell, rc::Rc};
 
-pub struct PestParser;
+pub struct PestParser {
+    validation: ValidationOptions,
+    options: ParseOptions,
+}
 
 impl PestParser {
     pub fn new() -> Self {
-        PestParser {}
+        PestParser {
+            validation: ValidationOptions::default(),
+            options: ParseOptions::default(),
+        }
+    }
+
+    /// Sets the limits on the resources that are used to parse a document.
+    pub fn with_parse_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sets the options with which parsed documents are validated. Warnings are not returned by
+    /// the parser; use `Document::validate_with` to get them.
+    pub fn with_validation(mut self, options: ValidationOptions) -> Self {
+        self.validation = options;
+        self
+    }
+}
+
+impl PestParser {
+    fn parse(
+        &self,
+        text: &str,
+        source: DocumentSource,
+        with_syntax: bool,
+    ) -> Result<(Document, Option<SyntaxTree>), WdlParserError> {
+        trace::document(&source.clone(), text.len(), || {
+            self.parse_phases(text, source, with_syntax)
+        })
+    }
+
+    fn parse_phases(
+        &self,
+        text: &str,
+        source: DocumentSource,
+        with_syntax: bool,
+    ) -> Result<(Document, Option<SyntaxTree>), WdlParserError> {
+        let deadline = self.options.start(text, &source)?;
+        check_version(text, &source)?;
+        let root_pair = match trace::phase("grammar", || wdl::parse_document(text)) {
+            Ok(pair) => pair,
+            Err(error) => {
+                let syntax_error = misplaced_placeholder(text)
+                    .unwrap_or_else(|| errors::syntax_error(&error, text));
+                return Err(Report::new(error)
+                    .change_context(WdlParserError::Syntax(source))
+                    .attach(syntax_error.to_diagnostic())
+                    .attach_printable(syntax_error));
+            }
+        };
+        deadline.check(&source)?;
+        let syntax = with_syntax.then(|| {
+            trace::phase("syntax", || {
+                let root = syntax_node(root_pair.clone(), text, &span_fn(text));
+                SyntaxTree::new(text.to_owned(), root)
+            })
+        });
+        let root_node = PestNode::new(root_pair, Rc::new(RefCell::new(Comments::default())));
+        let mut doc = trace::phase("model", || Document::try_from(root_node))
+            .change_context(WdlParserError::Model(source.clone()))?;
+        deadline.check(&source)?;
+        doc.source = source.clone();
+        doc.digest = SourceDigest::of(text);
+        if self.options.retain_text {
+            doc.text = Some(SourceText::new(text));
+        }
+        trace::count("elements", doc.body.len());
+        trace::count("comments", doc.comments.values().count());
+        let warnings = trace::phase("validation", || doc.validate_with(&self.validation))
+            .change_context(WdlParserError::Model(source))?;
+        trace::count("warnings", warnings.len());
+        Ok((doc, syntax))
     }
 }
 
@@ -31,18 +120,46 @@ impl WdlParser for PestParser {
         text: Text,
         source: DocumentSource,
     ) -> Result<Document, WdlParserError> {
-        let text: &str = text.as_ref();
-        let root_pair = wdl::parse_document(text)
-            .into_report()
-            .change_context(WdlParserError::Syntax(source.clone()))?;
-        let root_node = PestNode::new(root_pair, Rc::new(RefCell::new(Comments::default())));
-        let mut doc: Document = root_node
-            .try_into()
-            .change_context(WdlParserError::Model(source.clone()))?;
-        doc.source = source.clone();
-        doc.validate()
-            .change_context(WdlParserError::Model(source))?;
-        Ok(doc)
+        Ok(self.parse(text.as_ref(), source, false)?.0)
+    }
+
+    fn parse_text_with_syntax<Text: AsRef<str>>(
+        &mut self,
+        text: Text,
+        source: DocumentSource,
+    ) -> Result<ParseResult, WdlParserError> {
+        let (document, syntax) = self.parse(text.as_ref(), source, true)?;
+        Ok(ParseResult {
+            document,
+            syntax: syntax.unwrap(),
+        })
+    }
+}
+
+/// Converts a pest pair to a syntax node. Pest does not produce pairs for literals (such as
+/// keywords) or whitespace, so the text between the inner pairs of `pair` is split into tokens.
+fn syntax_node<F: Fn(usize, usize) -> Span>(
+    pair: Pair<'_, Rule>,
+    text: &str,
+    span_of: &F,
+) -> SyntaxNode {
+    let (start, end) = (pair.as_span().start(), pair.as_span().end());
+    let mut children = Vec::new();
+    let mut inner = pair.clone().into_inner().peekable();
+    if inner.peek().is_some() {
+        let mut offset = start;
+        for child in inner {
+            children.extend(tokenize(text, offset, child.as_span().start(), span_of));
+            offset = child.as_span().end();
+            children.push(syntax_node(child, text, span_of));
+        }
+        children.extend(tokenize(text, offset, end, span_of));
+    }
+    SyntaxNode {
+        kind: format!("{:?}", pair.as_rule()),
+        span: span_of(start, end),
+        token: false,
+        children,
     }
 }
 
@@ -1,5 +1,5 @@
 use crate::{
-    model::{Anchor, Comments, InnerSpan, ModelError, SourceFragment, Span},
+    model::{Anchor, CommentKind, Comments, InnerSpan, ModelError, SourceFragment, Span},
     parsers::pest::Rule,
 };
 use error_stack::{report, Report, Result};
@@ -134,14 +134,31 @@ impl<'a> PestNodes<'a> {
         }
     }
 
+    /// Returns the comment of a `COMMENT` pair, the line on which it starts, and its kind.
+    fn comment(pair: &Pair<'a, Rule>) -> (usize, Anchor<String>, CommentKind) {
+        let start = pair.as_span().start_pos();
+        // the text of the line before the comment
+        let prefix: String = start
+            .line_of()
+            .chars()
+            .take(start.line_col().1 - 1)
+            .collect();
+        let span: Span = (&pair.as_span()).into();
+        (
+            span.start.line,
+            Anchor::new(pair.as_str().to_owned(), span),
+            CommentKind::from_prefix(&prefix),
+        )
+    }
+
     fn get_next_pair(&mut self) -> Result<Option<Pair<'a, Rule>>, ModelError> {
         while let Some(pair) = self.pairs.next() {
             match pair.as_rule() {
                 Rule::COMMENT => {
-                    let span: Span = (&pair.as_span()).into();
-                    let mut comments = self.comments.borrow_mut();
-                    comments
-                        .try_insert(span.start.line, Anchor::new(pair.as_str().to_owned(), span))?;
+                    let (line, comment, kind) = Self::comment(&pair);
+                    self.comments
+                        .borrow_mut()
+                        .try_insert_with_kind(line, comment, kind)?;
                 }
                 Rule::EOI => continue,
                 _ => return Ok(Some(pair)),
@@ -200,13 +217,49 @@ impl<'a> Iterator for PestNodes<'a> {
 
 impl<'a> Drop for PestNodes<'a> {
     fn drop(&mut self) {
-        // drains the iterator to ensure all comment pairs are added to `self.comments`
-        loop {
-            match self.get_next_pair() {
-                Ok(Some(_)) => (),
-                Ok(None) => break,
-                Err(_) => break, // TODO: log this
+        // drains the iterator to ensure all comment pairs are added to `self.comments`; errors
+        // cannot be returned from `drop`, so comments that cannot be added are recorded as
+        // dropped (see `Comments::dropped`)
+        for pair in self.pairs.by_ref() {
+            if pair.as_rule() == Rule::COMMENT {
+                let (line, comment, kind) = Self::comment(&pair);
+                let mut comments = self.comments.borrow_mut();
+                if comments
+                    .try_insert_with_kind(line, comment.clone(), kind)
+                    .is_err()
+                {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(line, "dropped comment that could not be added to the model");
+                    comments.push_dropped(comment)
+                }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pest_wdl_1 as wdl;
+
+    #[test]
+    fn test_drain_dropped_comments() {
+        let text = "version 1.1\n\n# a task\ntask a {\n    command <<< >>>\n}\n";
+        let root = wdl::parse_document(text).unwrap();
+        let comments = Rc::new(RefCell::new(Comments::default()));
+        // dropping the unconsumed nodes adds their comments
+        drop(PestNode::new(root.clone(), comments.clone()).into_inner());
+        assert!(comments.borrow().get(2).is_some());
+        assert!(comments.borrow().dropped().is_empty());
+        // a bug that visits the same pairs twice adds the same comments twice, which cannot be
+        // reported from `drop`, so the comments are recorded as dropped
+        drop(PestNode::new(root, comments.clone()).into_inner());
+        let comments = comments.borrow();
+        let dropped: Vec<(usize, &str)> = comments
+            .dropped()
+            .iter()
+            .map(|comment| (comment.span.start.line, comment.as_str()))
+            .collect();
+        assert_eq!(dropped, vec![(2, "# a task")]);
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    model::{Command, ModelError, Runtime, RuntimeAttribute, Task, TaskElement},
+    model::{Command, CommandStyle, ModelError, Runtime, RuntimeAttribute, Task, TaskElement},
     parsers::pest::{expressions, node::PestNode, Rule},
 };
 use error_stack::{bail, Report, Result};
@@ -9,8 +9,14 @@ impl<'a> TryFrom<PestNode<'a>> for Command {
     type Error = Report<ModelError>;
 
     fn try_from(node: PestNode<'a>) -> Result<Self, ModelError> {
+        let inner = node.one_inner()?;
+        let style = match inner.as_rule() {
+            Rule::command_heredoc => CommandStyle::Heredoc,
+            _ => CommandStyle::Braces,
+        };
         Ok(Self {
-            parts: node.one_inner()?.into_inner().collect_anchors()?,
+            style,
+            parts: inner.into_inner().collect_anchors()?,
         })
     }
 }
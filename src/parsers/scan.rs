@@ -0,0 +1,189 @@
+//! A scanner for the brackets of a document that does not parse it, for the checks that are made
+//! before parsing (e.g. of the nesting depth) or after parsing fails.
+use std::ops::ControlFlow;
+
+/// A bracket found by `scan`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Bracket {
+    /// `(`, `[`, or `{`, including the brace that opens a brace command (`command {`).
+    Open,
+    /// A placeholder (`~{` or `${`) within a string or command.
+    Placeholder,
+    /// A placeholder outside of strings and commands, where placeholders are not allowed.
+    MisplacedPlaceholder,
+    /// `)`, `]`, or `}` that closes one of the above.
+    Close,
+}
+
+enum Frame {
+    /// A bracket, within which expressions may be nested.
+    Bracket,
+    /// A placeholder within a string or command. Its expression cannot contain comments, so `#`
+    /// is not the start of a comment within it, e.g. in `${#array[@]}` in a brace command.
+    Placeholder,
+    /// A string literal, which cannot span lines.
+    String(u8),
+    Heredoc,
+    /// A brace command, which ends at the first `}` that is not escaped or within a placeholder.
+    Command,
+}
+
+/// Calls `f` with each bracket in `text` and its offset, until `f` returns `Break`, whose value
+/// is returned. Brackets within comments, and within strings and commands outside of their
+/// placeholders, are skipped. As in the grammar, a brace command ends at its first `}` (even one
+/// that the shell script quotes), and a string literal at the end of its line, so that the end of
+/// a command or an unterminated string is found where the parser finds it. The text is not
+/// parsed, so closing brackets that do not match an open bracket are ignored.
+pub(crate) fn scan<B>(
+    text: &str,
+    mut f: impl FnMut(Bracket, usize) -> ControlFlow<B>,
+) -> Option<B> {
+    let bytes = text.as_bytes();
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        let is_placeholder = rest.starts_with(b"~{") || rest.starts_with(b"${");
+        let mut bracket = None;
+        match stack.last_mut() {
+            Some(Frame::String(quote)) => match rest[0] {
+                b'\\' => i += 1,
+                b if b == *quote || b == b'\n' => {
+                    stack.pop();
+                }
+                _ if is_placeholder => {
+                    stack.push(Frame::Placeholder);
+                    bracket = Some((Bracket::Placeholder, i));
+                    i += 1
+                }
+                _ => (),
+            },
+            Some(Frame::Heredoc) if rest.starts_with(b">>>") => {
+                stack.pop();
+                i += 2
+            }
+            Some(Frame::Heredoc) | Some(Frame::Command) if is_placeholder => {
+                stack.push(Frame::Placeholder);
+                bracket = Some((Bracket::Placeholder, i));
+                i += 1
+            }
+            Some(Frame::Heredoc) => (),
+            Some(Frame::Command) => match rest[0] {
+                b'\\' => i += 1,
+                b'}' => {
+                    stack.pop();
+                    bracket = Some((Bracket::Close, i));
+                }
+                _ => (),
+            },
+            _ => match rest[0] {
+                _ if is_placeholder => {
+                    stack.push(Frame::Bracket);
+                    bracket = Some((Bracket::MisplacedPlaceholder, i));
+                    i += 1
+                }
+                b'#' if !stack
+                    .iter()
+                    .any(|frame| matches!(frame, Frame::Placeholder)) =>
+                {
+                    i += rest.iter().position(|b| *b == b'\n').unwrap_or(rest.len());
+                    continue;
+                }
+                b'"' | b'\'' => stack.push(Frame::String(rest[0])),
+                b'<' if rest.starts_with(b"<<<") => {
+                    stack.push(Frame::Heredoc);
+                    i += 2
+                }
+                b'(' | b'[' | b'{' => {
+                    stack.push(Frame::Bracket);
+                    bracket = Some((Bracket::Open, i));
+                }
+                b')' | b']' | b'}' => {
+                    if matches!(stack.last(), Some(Frame::Bracket | Frame::Placeholder)) {
+                        stack.pop();
+                        bracket = Some((Bracket::Close, i));
+                    }
+                }
+                b'c' if rest.starts_with(b"command")
+                    && (i == 0
+                        || !(bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_')) =>
+                {
+                    // a brace command, i.e. `command {`, but not `command <<<`
+                    let next = text[i + "command".len()..].trim_start();
+                    if next.starts_with('{') {
+                        i = text.len() - next.len();
+                        stack.push(Frame::Command);
+                        bracket = Some((Bracket::Open, i));
+                    }
+                }
+                _ => (),
+            },
+        }
+        if let Some((bracket, offset)) = bracket {
+            if let ControlFlow::Break(value) = f(bracket, offset) {
+                return Some(value);
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn brackets(text: &str) -> Vec<(Bracket, &str)> {
+        let mut brackets = Vec::new();
+        scan::<()>(text, |bracket, offset| {
+            brackets.push((bracket, &text[offset..offset + 1]));
+            ControlFlow::Continue(())
+        });
+        brackets
+    }
+
+    #[test]
+    fn test_scan() {
+        use Bracket::*;
+        let text = "Int x = [(1)] # (\nString s = '(~{a})'\ncommand <<< ( ${x} >>>\n\
+                    command {\n  echo ${#arr[@]} don't # (\n  if x; then { ~{y}; }; fi\n}\n~{z}";
+        assert_eq!(
+            brackets(text),
+            vec![
+                (Open, "["),
+                (Open, "("),
+                (Close, ")"),
+                (Close, "]"),
+                (Placeholder, "~"),
+                (Close, "}"),
+                (Placeholder, "$"),
+                (Close, "}"),
+                (Open, "{"),
+                (Placeholder, "$"),
+                (Open, "["),
+                (Close, "]"),
+                (Close, "}"),
+                (Placeholder, "~"),
+                (Close, "}"),
+                (Close, "}"),
+                (MisplacedPlaceholder, "~"),
+                (Close, "}"),
+            ]
+        );
+        // the command ends at the quoted brace, and the string that the quote after it starts
+        // ends with the line
+        let text = "command { echo '}' }\n[~{x}]";
+        assert_eq!(
+            brackets(text),
+            vec![
+                (Open, "{"),
+                (Close, "}"),
+                (Open, "["),
+                (MisplacedPlaceholder, "~"),
+                (Close, "}"),
+                (Close, "]"),
+            ]
+        );
+    }
+}
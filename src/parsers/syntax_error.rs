@@ -0,0 +1,343 @@
+//! Syntax errors with messages that describe what was expected in terms of the WDL syntax rather
+//! than the rules of a parser's grammar, e.g. "expected a type or `}` to close the input section,
+//! found `=`".
+use crate::{
+    diagnostics::Diagnostic,
+    model::Span,
+    parsers::scan::{scan, Bracket},
+    sourcemap::position_at,
+};
+use error_stack::Report;
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    ops::ControlFlow,
+};
+
+/// The code of the diagnostics of syntax errors, except for the more specific ones below.
+pub const SYNTAX_ERROR: &str = "syntax_error";
+/// The code of the diagnostic of a placeholder within a version statement, e.g.
+/// `version ~{v}`.
+pub const PLACEHOLDER_IN_VERSION: &str = "placeholder_in_version";
+/// The code of the diagnostic of a placeholder within the type of a declaration, e.g.
+/// `Array[~{t}] xs`.
+pub const PLACEHOLDER_IN_TYPE: &str = "placeholder_in_type";
+
+/// A syntax error in a document. Parsers attach `SyntaxError`s, and the equivalent
+/// `Diagnostic`s, to the `WdlParserError::Syntax` reports that they return.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxError {
+    /// The code of the equivalent diagnostic, i.e. `SYNTAX_ERROR` or a more specific code.
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+    /// The text of the line on which the error starts, without its line ending.
+    pub line: String,
+}
+
+impl SyntaxError {
+    pub(crate) fn new<M: Into<String>>(text: &str, message: M, span: Span) -> Self {
+        let line = text
+            .lines()
+            .nth(span.start.line)
+            .unwrap_or_default()
+            .to_owned();
+        Self {
+            code: SYNTAX_ERROR,
+            message: message.into(),
+            span,
+            line,
+        }
+    }
+
+    pub(crate) fn with_code(mut self, code: &'static str) -> Self {
+        self.code = code;
+        self
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic::error(self.code, self.message.clone(), self.span.clone())
+    }
+
+    /// Returns the syntax errors that are attached to `report`, in document order.
+    pub fn from_report<C>(report: &Report<C>) -> Vec<&SyntaxError> {
+        let mut errors: Vec<&SyntaxError> = report
+            .frames()
+            .filter_map(|frame| frame.downcast_ref::<SyntaxError>())
+            .collect();
+        errors.sort_by(|a, b| a.span.cmp(&b.span));
+        errors
+    }
+}
+
+/// Displays the error with its (one-based) line and column, followed by the line on which it
+/// starts with the start of the error marked.
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        writeln!(
+            f,
+            "{}:{}: {}",
+            self.span.start.line + 1,
+            self.span.start.column + 1,
+            self.message
+        )?;
+        writeln!(f, "    {}", self.line)?;
+        write!(f, "    {:>1$}", "^", self.span.start.column + 1)
+    }
+}
+
+/// Returns the span of the token at `offset`, which is a single character unless it is part of
+/// a name or number. The span is empty at the end of the text.
+pub(crate) fn token_span(text: &str, offset: usize) -> Span {
+    let rest = &text[offset..];
+    let len = match rest.chars().next() {
+        None => 0,
+        Some(c) if c.is_ascii_alphanumeric() || c == '_' => rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len()),
+        Some(c) => c.len_utf8(),
+    };
+    Span {
+        start: position_at(text, offset),
+        end: position_at(text, offset + len),
+    }
+}
+
+/// Returns a description of the token at `span`, e.g. "`=`" or "the end of the document".
+pub(crate) fn describe_token(text: &str, span: &Span) -> String {
+    match &text[span.start.offset..span.end.offset] {
+        "" => "the end of the document".to_owned(),
+        "\n" | "\r" => "the end of the line".to_owned(),
+        token => format!("`{}`", token),
+    }
+}
+
+/// Joins `items` into a list of alternatives, e.g. "a, b, or c".
+#[cfg(feature = "pest")]
+pub(crate) fn alternatives(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [item] => item.clone(),
+        [first, second] => format!("{} or {}", first, second),
+        [init @ .., last] => format!("{}, or {}", init.join(", "), last),
+    }
+}
+
+/// Returns a description of the innermost block that encloses `offset`, e.g. "the input
+/// section", or `None` if `offset` is not within a block or the block is a literal (e.g. a map).
+/// Brackets within comments and string literals are ignored; the text is not parsed, so this is
+/// a best effort for documents that contain syntax errors.
+pub(crate) fn enclosing_block(text: &str, offset: usize) -> Option<&'static str> {
+    let bytes = text.as_bytes();
+    let mut braces = Vec::new();
+    let mut quote = None;
+    let mut i = 0;
+    while i < offset.min(bytes.len()) {
+        match (quote, bytes[i]) {
+            (Some(_), b'\\') => i += 1,
+            (Some(q), b) if b == q => quote = None,
+            (Some(_), _) => (),
+            (None, b'#') => {
+                i += bytes[i..]
+                    .iter()
+                    .position(|b| *b == b'\n')
+                    .unwrap_or(bytes.len() - i);
+                continue;
+            }
+            (None, b'"') | (None, b'\'') => quote = Some(bytes[i]),
+            (None, b'<') if bytes[i..].starts_with(b"<<<") => quote = Some(b'>'),
+            (None, b'{') => braces.push(i),
+            (None, b'}') => {
+                braces.pop();
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+    let brace = *braces.last()?;
+    // the first word of the statement that opens the block
+    let statement_start = text[..brace]
+        .rfind(['\n', '{', '}'])
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let keyword = text[statement_start..brace]
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .find(|word| !word.is_empty())?;
+    Some(match keyword {
+        "input" => "the input section",
+        "output" => "the output section",
+        "command" => "the command section",
+        "meta" => "the meta section",
+        "parameter_meta" => "the parameter_meta section",
+        "runtime" => "the runtime section",
+        "requirements" => "the requirements section",
+        "hints" => "the hints section",
+        "task" => "the task",
+        "workflow" => "the workflow",
+        "struct" => "the struct",
+        "scatter" => "the scatter",
+        "if" => "the conditional",
+        "call" => "the call inputs",
+        _ => return None,
+    })
+}
+
+/// Words that begin statements other than declarations.
+const STATEMENT_KEYWORDS: &[&str] = &[
+    "version",
+    "import",
+    "struct",
+    "task",
+    "workflow",
+    "input",
+    "output",
+    "command",
+    "meta",
+    "parameter_meta",
+    "runtime",
+    "requirements",
+    "hints",
+    "call",
+    "scatter",
+    "if",
+];
+
+/// Returns the offset of the `}` that closes the brace at `open`, or `None` if it is not closed
+/// on the same line.
+fn closing_brace(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 1 => return Some(open + i),
+            '}' => depth -= 1,
+            '\n' => return None,
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Returns whether the placeholder at `start..end` of `line` is within the type of a declaration,
+/// e.g. `Array[~{t}] xs` or `~{t} x = 1`: the text before it is the start of a type, and it is
+/// followed by the rest of the type and the name of the declaration.
+fn is_in_type(line: &str, start: usize, end: usize) -> bool {
+    let is_type_char = |c: char| c.is_ascii_alphanumeric() || "_[], ?+".contains(c);
+    let before = line[..start].trim_start();
+    let after = line[end..].split('=').next().unwrap_or_default().trim_end();
+    let first_word = before
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .next()
+        .unwrap_or_default();
+    let words = format!("{}T{}", before, after);
+    before.chars().all(is_type_char)
+        && after.chars().all(is_type_char)
+        && !STATEMENT_KEYWORDS.contains(&first_word)
+        && words.split_whitespace().count() >= 2
+        && words
+            .split_whitespace()
+            .last()
+            .is_some_and(|name| name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+}
+
+/// Returns a syntax error for the first placeholder (`~{...}` or `${...}`) in `text` that is
+/// within a version statement or the type of a declaration, where placeholders are not allowed,
+/// e.g. because the document was copied from a template. Placeholders within string literals,
+/// commands, and comments are ignored. The text is not parsed, so this is a best effort for
+/// documents that fail to parse, for which it gives a more helpful message than the grammar.
+pub(crate) fn misplaced_placeholder(text: &str) -> Option<SyntaxError> {
+    scan(text, |bracket, i| {
+        if bracket != Bracket::MisplacedPlaceholder {
+            return ControlFlow::Continue(());
+        }
+        let line_start = text[..i].rfind('\n').map_or(0, |n| n + 1);
+        let line_end = text[i..].find('\n').map_or(text.len(), |n| i + n);
+        let line = &text[line_start..line_end];
+        let end = closing_brace(text, i + 1).map_or(i + 2, |end| end + 1);
+        let span = Span {
+            start: position_at(text, i),
+            end: position_at(text, end),
+        };
+        if line.trim_start().starts_with("version") {
+            return ControlFlow::Break(
+                SyntaxError::new(
+                    text,
+                    "placeholders are not allowed in the version statement; the version must be \
+                     written literally, e.g. `version 1.1`",
+                    span,
+                )
+                .with_code(PLACEHOLDER_IN_VERSION),
+            );
+        }
+        if is_in_type(line, i - line_start, end - line_start) {
+            return ControlFlow::Break(
+                SyntaxError::new(
+                    text,
+                    "placeholders are not allowed in types; types must be written literally, \
+                     e.g. `Array[String]`",
+                    span,
+                )
+                .with_code(PLACEHOLDER_IN_TYPE),
+            );
+        }
+        ControlFlow::Continue(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_enclosing_block() {
+        let text = "task a {\n  input {\n    String s = \"{\" # {\n    Int i = {\"a\": 1\n  }\n  x";
+        let at = |pattern: &str| text.find(pattern).unwrap();
+        assert_eq!(
+            enclosing_block(text, at("String")),
+            Some("the input section")
+        );
+        assert_eq!(enclosing_block(text, at("1")), None);
+        assert_eq!(enclosing_block(text, at("x")), Some("the input section"));
+        assert_eq!(enclosing_block(text, at("input")), Some("the task"));
+        assert_eq!(enclosing_block(text, 0), None);
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        let text = "version 1.1\n\ntask a {\n  Int = 1\n}\n";
+        let span = token_span(text, text.find('=').unwrap());
+        assert_eq!(describe_token(text, &span), "`=`");
+        assert_eq!(
+            describe_token(text, &token_span(text, text.len())),
+            "the end of the document"
+        );
+        let error = SyntaxError::new(text, "expected a name", span);
+        assert_eq!(error.line, "  Int = 1");
+        assert_eq!(
+            error.to_string(),
+            "4:7: expected a name\n      Int = 1\n          ^"
+        );
+        assert_eq!(error.to_diagnostic().code, "syntax_error");
+    }
+
+    #[test]
+    fn test_misplaced_placeholder() {
+        let error = misplaced_placeholder("version ~{wdl_version}\n").unwrap();
+        assert_eq!(error.code, PLACEHOLDER_IN_VERSION);
+        assert_eq!((error.span.start.offset, error.span.end.offset), (8, 22));
+        let text =
+            "version 1.1\n\ntask a {\n  input {\n    Array[~{t}]+ xs\n    ${t} y = 1\n  }\n}\n";
+        let error = misplaced_placeholder(text).unwrap();
+        assert_eq!(error.code, PLACEHOLDER_IN_TYPE);
+        assert_eq!(
+            &text[error.span.start.offset..error.span.end.offset],
+            "~{t}"
+        );
+        assert_eq!(error.to_diagnostic().code, PLACEHOLDER_IN_TYPE);
+        // placeholders in strings, commands, and comments, and in other positions, are ignored
+        let text = "version 1.1\n\ntask a {\n  String s = \"~{x}\" # ~{y} z\n  command {\n    \
+                    if [ 1 ]; then { echo ${s} ~{s}; }; fi\n  }\n  command <<< ~{s} >>>\n  \
+                    Int i = ~{x}\n}\n";
+        assert_eq!(misplaced_placeholder(text), None);
+    }
+}
@@ -0,0 +1,235 @@
+use crate::model::{Document, Span};
+
+/// A node of a `SyntaxTree`: either a node of the parser's grammar, or a token (a leaf for a
+/// keyword, operator, punctuation, or whitespace).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxNode {
+    /// The kind of the node. For nodes of the grammar this is the name of the pest rule or
+    /// tree-sitter node kind, which differ between parsers; for tokens it is the token's text,
+    /// which does not.
+    pub kind: String,
+    pub span: Span,
+    /// Whether this node is a token.
+    pub token: bool,
+    pub children: Vec<SyntaxNode>,
+}
+
+impl SyntaxNode {
+    /// Returns an iterator over this node and its descendants, in pre-order.
+    pub fn descendants(&self) -> impl Iterator<Item = &SyntaxNode> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children.iter().rev());
+            Some(node)
+        })
+    }
+
+    /// Returns an iterator over the tokens within this node, other than whitespace, in order.
+    pub fn tokens(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.descendants()
+            .filter(|node| node.token && !node.is_whitespace())
+    }
+
+    /// Returns an iterator over the leaves of this node (i.e. the descendants that have no
+    /// children), in order. The leaves of a node cover all of its text.
+    pub fn leaves(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.descendants().filter(|node| node.children.is_empty())
+    }
+
+    pub fn is_whitespace(&self) -> bool {
+        self.token && self.kind.trim().is_empty()
+    }
+}
+
+/// The concrete syntax tree of a document, which includes details that are not captured by the
+/// model, such as the positions of keywords and punctuation. The tree is lossless: the text of
+/// the document is the concatenation of the text of its leaves, including whitespace and comments.
+/// The structure of the tree (other than its tokens) is specific to the parser that produced it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxTree {
+    text: String,
+    root: SyntaxNode,
+}
+
+impl SyntaxTree {
+    pub fn new(text: String, root: SyntaxNode) -> Self {
+        Self { text, root }
+    }
+
+    /// Returns the text of the document.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn root(&self) -> &SyntaxNode {
+        &self.root
+    }
+
+    /// Returns the text of `node`, which must be a node of this tree.
+    pub fn node_text(&self, node: &SyntaxNode) -> &str {
+        &self.text[node.span.start.offset..node.span.end.offset]
+    }
+
+    /// Returns the text of the document reconstructed from the leaves of the tree, which is
+    /// identical to `text()`.
+    pub fn leaf_text(&self) -> String {
+        self.root
+            .leaves()
+            .map(|leaf| self.node_text(leaf))
+            .collect()
+    }
+
+    /// Returns the smallest node that contains `offset`.
+    pub fn node_at(&self, offset: usize) -> Option<&SyntaxNode> {
+        let contains =
+            |node: &&SyntaxNode| node.span.start.offset <= offset && offset < node.span.end.offset;
+        let mut node = Some(&self.root).filter(contains)?;
+        while let Some(child) = node.children.iter().find(contains) {
+            node = child
+        }
+        Some(node)
+    }
+}
+
+/// The result of parsing a document: the model and the syntax tree from which it was built.
+#[derive(Debug, PartialEq)]
+pub struct ParseResult {
+    pub document: Document,
+    pub syntax: SyntaxTree,
+}
+
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+    assert_send_sync::<ParseResult>();
+};
+
+/// Returns a function that returns the span of a byte range of `text`.
+#[cfg(any(feature = "pest", feature = "tree-sitter"))]
+pub(crate) fn span_fn(text: &str) -> impl Fn(usize, usize) -> Span + '_ {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    move |start, end| {
+        let position = |offset: usize| {
+            let line = line_starts.partition_point(|start| *start <= offset) - 1;
+            let column = text[line_starts[line]..offset].chars().count();
+            crate::model::Position::new(line, column, offset)
+        };
+        Span {
+            start: position(start),
+            end: position(end),
+        }
+    }
+}
+
+/// Multi-character operators and delimiters, which are treated as single tokens.
+#[cfg(any(feature = "pest", feature = "tree-sitter"))]
+const OPERATORS: [&str; 10] = ["<<<", ">>>", "==", "!=", "<=", ">=", "&&", "||", "~{", "${"];
+
+/// Splits the text between the children of a node into tokens, for parsers whose trees omit
+/// literals (i.e. pest) or whitespace. `span_of` returns the span of a byte range of the
+/// document. Runs of whitespace, words (runs of alphanumeric characters and underscores), and
+/// operators are single tokens; any other character is a token by itself.
+#[cfg(any(feature = "pest", feature = "tree-sitter"))]
+pub(crate) fn tokenize<F: Fn(usize, usize) -> Span>(
+    text: &str,
+    start: usize,
+    end: usize,
+    span_of: &F,
+) -> Vec<SyntaxNode> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut tokens = Vec::new();
+    let mut offset = start;
+    while offset < end {
+        let rest = &text[offset..end];
+        let c = rest.chars().next().unwrap();
+        let len = if c.is_whitespace() {
+            rest.find(|c: char| !c.is_whitespace())
+                .unwrap_or(rest.len())
+        } else if is_word(c) {
+            rest.find(|c| !is_word(c)).unwrap_or(rest.len())
+        } else {
+            OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(*op))
+                .map(|op| op.len())
+                .unwrap_or_else(|| c.len_utf8())
+        };
+        tokens.push(SyntaxNode {
+            kind: rest[..len].to_owned(),
+            span: span_of(offset, offset + len),
+            token: true,
+            children: Vec::new(),
+        });
+        offset += len;
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        model::DocumentSource,
+        parsers::{WdlParser, WdlParserError},
+    };
+    use error_stack::Result;
+    use pretty_assertions::assert_eq;
+    use std::{fs, path::PathBuf};
+
+    fn comprehensive_path() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources")
+            .join("test")
+            .join("comprehensive.wdl")
+    }
+
+    #[cfg(feature = "pest")]
+    #[test]
+    fn test_pest() -> Result<(), WdlParserError> {
+        use crate::parsers::PestParser;
+
+        let text = "version 1.1\n\n# a task\ntask a {\n    command <<< echo >>>\n}\n";
+        let result = PestParser::new().parse_text_with_syntax(text, DocumentSource::Unknown)?;
+        assert_eq!(result.document.body.len(), 1);
+        let syntax = result.syntax;
+        assert_eq!(syntax.node_text(syntax.node_at(23).unwrap()), "task");
+        let keywords: Vec<(&str, usize)> = syntax
+            .root()
+            .tokens()
+            .filter(|token| ["version", "task", "command", "{"].contains(&token.kind.as_str()))
+            .map(|token| (token.kind.as_str(), token.span.start.offset))
+            .collect();
+        assert_eq!(
+            keywords,
+            vec![("version", 0), ("task", 22), ("{", 29), ("command", 35)]
+        );
+        assert_eq!(syntax.leaf_text(), text);
+        let text = fs::read_to_string(comprehensive_path()).unwrap();
+        let result = PestParser::new().parse_text_with_syntax(&text, DocumentSource::Unknown)?;
+        assert_eq!(result.syntax.leaf_text(), text);
+        Ok(())
+    }
+
+    #[cfg(feature = "tree-sitter")]
+    #[test]
+    fn test_tree_sitter() -> Result<(), WdlParserError> {
+        use crate::parsers::TreeSitterParser;
+
+        // the whitespace and comments before the first token and after the last are included
+        let text = "\n# a header\nversion 1.1\n\ntask a {\n    command <<< echo >>>\n}\n\n";
+        let result =
+            TreeSitterParser::new()?.parse_text_with_syntax(text, DocumentSource::Unknown)?;
+        assert_eq!(result.document.body.len(), 1);
+        let syntax = result.syntax;
+        assert_eq!(syntax.root().span.start.offset, 0);
+        assert_eq!(syntax.root().span.end.offset, text.len());
+        assert_eq!(syntax.node_text(syntax.node_at(25).unwrap()), "task");
+        assert_eq!(syntax.leaf_text(), text);
+        let text = fs::read_to_string(comprehensive_path()).unwrap();
+        let result =
+            TreeSitterParser::new()?.parse_text_with_syntax(&text, DocumentSource::Unknown)?;
+        assert_eq!(result.syntax.leaf_text(), text);
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{
     model::{
         Alias, Anchor, Document, DocumentElement, DocumentSource, Import, ModelError, Namespace,
-        Struct, Version,
+        SourceDigest, Struct, Version,
     },
     parsers::tree_sitter::{
         node::{BlockDelim, BlockEnds, TSNode},
@@ -128,13 +128,13 @@ impl<'a> TryFrom<TSNode<'a>> for Document {
             .next_field(fields::BODY)?
             .into_children()
             .collect_anchors()?;
-        let doc = Self {
+        Ok(Self {
             source: DocumentSource::default(),
             version,
             body,
             comments: comments.take(),
-        };
-        doc.validate()?;
-        Ok(doc)
+            digest: SourceDigest::default(),
+            text: None,
+        })
     }
 }
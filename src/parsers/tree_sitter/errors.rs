@@ -0,0 +1,86 @@
+use crate::{
+    model::Span,
+    parsers::syntax_error::{describe_token, enclosing_block, token_span, SyntaxError},
+};
+use tree_sitter as ts;
+
+/// Prefixes `description` with an indefinite article.
+fn with_article(description: &str) -> String {
+    match description.chars().next() {
+        Some('a' | 'e' | 'i' | 'o' | 'u') => format!("an {}", description),
+        _ => format!("a {}", description),
+    }
+}
+
+/// Returns the message for a node of kind `kind` that is missing at `offset` of `text`.
+fn missing_message(text: &str, offset: usize, kind: &str, named: bool) -> String {
+    let expected = if named {
+        with_article(&kind.replace('_', " "))
+    } else {
+        format!("`{}`", kind)
+    };
+    match enclosing_block(text, offset) {
+        Some(block) if kind == "}" => format!("expected `}}` to close {}", block),
+        _ => format!(
+            "expected {}, found {}",
+            expected,
+            describe_token(text, &token_span(text, offset))
+        ),
+    }
+}
+
+/// Returns the message for text at `offset` of `text` that could not be parsed.
+fn unexpected_message(text: &str, offset: usize) -> String {
+    let found = describe_token(text, &token_span(text, offset));
+    match enclosing_block(text, offset) {
+        Some(block) => format!("unexpected {} in {}", found, block),
+        None => format!("unexpected {}", found),
+    }
+}
+
+/// Returns a `SyntaxError` for each `ERROR` and `MISSING` node of the tree rooted at `root`, in
+/// document order. Tree-sitter does not report which nodes were expected where text could not be
+/// parsed, so the messages of `ERROR` nodes only describe the unexpected text.
+pub(crate) fn syntax_errors(root: ts::Node<'_>, text: &str) -> Vec<SyntaxError> {
+    let mut errors = Vec::new();
+    let mut cursor = root.walk();
+    let mut pending = vec![root];
+    while let Some(node) = pending.pop() {
+        let span: Span = (&node).into();
+        if node.is_missing() {
+            let message = missing_message(text, node.start_byte(), node.kind(), node.is_named());
+            errors.push(SyntaxError::new(text, message, span))
+        } else if node.is_error() {
+            let message = unexpected_message(text, node.start_byte());
+            errors.push(SyntaxError::new(text, message, span))
+        } else if node.has_error() {
+            let children: Vec<ts::Node<'_>> = node.children(&mut cursor).collect();
+            pending.extend(children.into_iter().rev())
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_messages() {
+        let text = "version 1.1\n\ntask a {\n    input {\n        Int i = 1 1\n";
+        assert_eq!(
+            missing_message(text, text.len(), "}", false),
+            "expected `}` to close the input section"
+        );
+        assert_eq!(
+            missing_message(text, 0, "identifier", true),
+            "expected an identifier, found `version`"
+        );
+        assert_eq!(
+            unexpected_message(text, text.rfind('1').unwrap()),
+            "unexpected `1` in the input section"
+        );
+        assert_eq!(unexpected_message("x", 0), "unexpected `x`");
+    }
+}
@@ -1,8 +1,8 @@
 use crate::{
     model::{
         Access, AccessOperation, Anchor, Apply, ArrayLiteral, Binary, BinaryOperator, Expression,
-        MapEntry, MapLiteral, ModelError, ObjectField, ObjectLiteral, PairLiteral, StringLiteral,
-        StringPart, Ternary, Unary, UnaryOperator,
+        MapEntry, MapLiteral, ModelError, ObjectField, ObjectLiteral, PairLiteral,
+        PlaceholderStyle, StringLiteral, StringPart, Ternary, Unary, UnaryOperator,
     },
     parsers::tree_sitter::{
         node::{BlockDelim, BlockEnds, TSNode},
@@ -21,8 +21,11 @@ impl<'a> TryFrom<TSNode<'a>> for StringPart {
             rules::ESCAPE_SEQUENCE => Self::Escape(node.try_into()?),
             rules::PLACEHOLDER => {
                 let mut children = node.into_children();
-                let _ = children.next_node()?.try_as_str()?;
-                Self::Placeholder(children.next_field(fields::EXPRESSION)?.try_into()?)
+                let style = match children.next_node()?.try_as_str()? {
+                    "${" => PlaceholderStyle::Dollar,
+                    _ => PlaceholderStyle::Tilde,
+                };
+                Self::Placeholder(children.next_field(fields::EXPRESSION)?.try_into()?, style)
             }
             _ => bail!(ModelError::parser(format!(
                 "Invalid string part {:?}",
@@ -121,7 +124,7 @@ impl<'a> TryFrom<TSNode<'a>> for ObjectLiteral {
             .next_field(fields::FIELDS)?
             .into_block(BlockEnds::Braces, BlockDelim::Comma)
             .collect_anchors()?;
-        Ok(Self { type_name, fields })
+        Ok(Self::new(type_name, fields))
     }
 }
 
@@ -273,7 +276,10 @@ impl<'a> TryFrom<TSNode<'a>> for Expression {
             rules::BINARY_OPERATOR
             | rules::AND_OPERATOR
             | rules::OR_OPERATOR
-            | rules::COMPARISON_OPERATOR => Self::Binary(node.try_into()?),
+            | rules::COMPARISON_OPERATOR => {
+                let (first, rest) = Binary::try_from(node)?.into_chain();
+                Binary::fold(first, rest).into_element()
+            }
             rules::APPLY_EXPRESSION => Self::Apply(node.try_into()?),
             rules::INDEX_EXPRESSION | rules::FIELD_EXPRESSION => Self::Access(node.try_into()?),
             rules::TERNARY_EXPRESSION => Self::Ternary(node.try_into()?),
@@ -1,5 +1,6 @@
 mod declarations;
 mod document;
+mod errors;
 mod expressions;
 mod meta;
 mod node;
@@ -9,49 +10,212 @@ mod task;
 mod workflow;
 
 use crate::{
-    model::{Comments, Document, DocumentSource, Position, Span},
-    parsers::{tree_sitter::node::TSNode, WdlParser, WdlParserError},
+    model::{
+        Comments, Document, DocumentSource, Position, SourceDigest, SourceText, Span,
+        ValidationOptions,
+    },
+    parsers::{
+        check_version,
+        syntax_error::misplaced_placeholder,
+        syntax_tree::{span_fn, tokenize},
+        tree_sitter::node::TSNode,
+        ParseOptions, ParseResult, SyntaxError, SyntaxNode, SyntaxTree, WdlParser, WdlParserError,
+    },
+    trace,
 };
-use error_stack::{IntoReport, Result, ResultExt};
+use error_stack::{bail, IntoReport, Report, Result, ResultExt};
 use std::{cell::RefCell, rc::Rc};
 use tree_sitter as ts;
 use tree_sitter_wdl_1;
 
-pub struct TreeSitterParser(ts::Parser);
+pub struct TreeSitterParser {
+    parser: ts::Parser,
+    validation: ValidationOptions,
+    options: ParseOptions,
+}
 
 impl TreeSitterParser {
     pub fn new() -> Result<Self, WdlParserError> {
-        Ok(Self(
-            tree_sitter_wdl_1::parser()
+        Ok(Self {
+            parser: tree_sitter_wdl_1::parser()
                 .into_report()
                 .change_context(WdlParserError::Internal)?,
-        ))
+            validation: ValidationOptions::default(),
+            options: ParseOptions::default(),
+        })
     }
-}
 
-impl WdlParser for TreeSitterParser {
-    fn parse_text<Text: AsRef<str>>(
+    /// Sets the limits on the resources that are used to parse a document.
+    pub fn with_parse_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sets the options with which parsed documents are validated. Warnings are not returned by
+    /// the parser; use `Document::validate_with` to get them.
+    pub fn with_validation(mut self, options: ValidationOptions) -> Self {
+        self.validation = options;
+        self
+    }
+    /// Returns all syntax errors in `text`, in document order, or an empty `Vec` if there are
+    /// none. Unlike the `WdlParser` methods, this recovers from errors rather than stopping at
+    /// the first one, and does not build the document model. The parse options are not applied.
+    pub fn syntax_errors<Text: AsRef<str>>(
         &mut self,
         text: Text,
-        source: DocumentSource,
-    ) -> Result<Document, WdlParserError> {
+    ) -> Result<Vec<SyntaxError>, WdlParserError> {
         let text = text.as_ref();
-        let tree = self
-            .0
-            .parse(text, None)
-            .ok_or(WdlParserError::Syntax(source.clone()))?;
+        self.parser.set_timeout_micros(0);
+        match self.parser.parse(text, None) {
+            Some(tree) => Ok(errors::syntax_errors(tree.root_node(), text)),
+            None => bail!(WdlParserError::Syntax(DocumentSource::Unknown)),
+        }
+    }
+}
+
+impl TreeSitterParser {
+    fn parse(
+        &mut self,
+        text: &str,
+        source: DocumentSource,
+        with_syntax: bool,
+    ) -> Result<(Document, Option<SyntaxTree>), WdlParserError> {
+        trace::document(&source.clone(), text.len(), || {
+            self.parse_phases(text, source, with_syntax)
+        })
+    }
+
+    fn parse_phases(
+        &mut self,
+        text: &str,
+        source: DocumentSource,
+        with_syntax: bool,
+    ) -> Result<(Document, Option<SyntaxTree>), WdlParserError> {
+        let deadline = self.options.start(text, &source)?;
+        check_version(text, &source)?;
+        // a timeout of 0 disables the timeout, so the remaining time is rounded up
+        let timeout = deadline
+            .remaining()
+            .map_or(0, |remaining| remaining.as_micros().max(1) as u64);
+        self.parser.set_timeout_micros(timeout);
+        let tree = match trace::phase("grammar", || self.parser.parse(text, None)) {
+            Some(tree) => tree,
+            None => {
+                // the parser must be reset before it is reused after a timeout
+                self.parser.reset();
+                if timeout > 0 {
+                    bail!(WdlParserError::Timeout(source))
+                }
+                let report = Report::new(WdlParserError::Syntax(source));
+                return Err(match misplaced_placeholder(text) {
+                    Some(error) => report.attach(error.to_diagnostic()).attach_printable(error),
+                    None => report,
+                });
+            }
+        };
+        let mut errors = errors::syntax_errors(tree.root_node(), text);
+        if !errors.is_empty() {
+            if let Some(error) = misplaced_placeholder(text) {
+                errors = vec![error]
+            }
+            bail!(errors.into_iter().fold(
+                Report::new(WdlParserError::Syntax(source)),
+                |report, error| report.attach(error.to_diagnostic()).attach_printable(error)
+            ))
+        }
+        let syntax = with_syntax.then(|| {
+            trace::phase("syntax", || {
+                // the root node starts at the first token, so it is extended to cover any
+                // whitespace before it (and after the last token)
+                let root = syntax_node_in(tree.root_node(), 0, text.len(), text, &span_fn(text));
+                SyntaxTree::new(text.to_owned(), root)
+            })
+        });
         let root = TSNode::from_cursor(
             Rc::new(RefCell::new(tree.walk())),
             text.as_bytes(),
             Rc::new(RefCell::new(Comments::default())),
         );
-        let mut doc: Document = root
-            .try_into()
+        let mut doc = trace::phase("model", || Document::try_from(root))
             .change_context(WdlParserError::Model(source.clone()))?;
+        deadline.check(&source)?;
         doc.source = source.clone();
-        doc.validate()
+        doc.digest = SourceDigest::of(text);
+        if self.options.retain_text {
+            doc.text = Some(SourceText::new(text));
+        }
+        trace::count("elements", doc.body.len());
+        trace::count("comments", doc.comments.values().count());
+        let warnings = trace::phase("validation", || doc.validate_with(&self.validation))
             .change_context(WdlParserError::Model(source))?;
-        Ok(doc)
+        trace::count("warnings", warnings.len());
+        Ok((doc, syntax))
+    }
+}
+
+impl WdlParser for TreeSitterParser {
+    fn parse_text<Text: AsRef<str>>(
+        &mut self,
+        text: Text,
+        source: DocumentSource,
+    ) -> Result<Document, WdlParserError> {
+        Ok(self.parse(text.as_ref(), source, false)?.0)
+    }
+
+    fn parse_text_with_syntax<Text: AsRef<str>>(
+        &mut self,
+        text: Text,
+        source: DocumentSource,
+    ) -> Result<ParseResult, WdlParserError> {
+        let (document, syntax) = self.parse(text.as_ref(), source, true)?;
+        Ok(ParseResult {
+            document,
+            syntax: syntax.unwrap(),
+        })
+    }
+}
+
+/// Converts a tree-sitter node to a syntax node. Anonymous nodes (i.e. literals) are tokens.
+/// Tree-sitter does not produce nodes for whitespace, so the text between the children of `node`
+/// is split into tokens.
+fn syntax_node<F: Fn(usize, usize) -> Span>(
+    node: ts::Node<'_>,
+    text: &str,
+    span_of: &F,
+) -> SyntaxNode {
+    syntax_node_in(node, node.start_byte(), node.end_byte(), text, span_of)
+}
+
+/// Converts a tree-sitter node to a syntax node that spans the byte range `start..end`, which
+/// contains the node.
+fn syntax_node_in<F: Fn(usize, usize) -> Span>(
+    node: ts::Node<'_>,
+    start: usize,
+    end: usize,
+    text: &str,
+    span_of: &F,
+) -> SyntaxNode {
+    let mut cursor = node.walk();
+    let mut children = Vec::new();
+    if node.child_count() > 0 {
+        let mut offset = start;
+        for child in node.children(&mut cursor) {
+            children.extend(tokenize(text, offset, child.start_byte(), span_of));
+            offset = child.end_byte();
+            children.push(syntax_node(child, text, span_of));
+        }
+        children.extend(tokenize(text, offset, end, span_of));
+    }
+    let span = if (start, end) == (node.start_byte(), node.end_byte()) {
+        (&node).into()
+    } else {
+        span_of(start, end)
+    };
+    SyntaxNode {
+        kind: node.kind().to_owned(),
+        span,
+        token: !node.is_named(),
+        children,
     }
 }
 
@@ -78,8 +242,8 @@ impl<'a> From<&ts::Node<'a>> for Span {
 #[cfg(test)]
 mod tests {
     use crate::{
-        model::tests,
-        parsers::{tree_sitter::TreeSitterParser, WdlParser, WdlParserError},
+        model::{tests, DocumentSource},
+        parsers::{tree_sitter::TreeSitterParser, SyntaxError, WdlParser, WdlParserError},
     };
     use error_stack::Result;
     use std::path::PathBuf;
@@ -99,4 +263,31 @@ mod tests {
         tests::test_comprehensive(doc);
         Ok(())
     }
+
+    #[test]
+    fn test_syntax_errors() -> Result<(), WdlParserError> {
+        // two separate errors, one in each task
+        let text = "version 1.1\n\ntask a {\n    Int x = @\n    command <<< echo >>>\n}\n\n\
+            task b {\n    Int y = 1 @\n    command <<< echo >>>\n}\n";
+        let lines = |errors: &[&SyntaxError]| -> Vec<usize> {
+            let mut lines: Vec<usize> = errors.iter().map(|error| error.span.start.line).collect();
+            lines.dedup();
+            lines
+        };
+        let mut parser = TreeSitterParser::new()?;
+        let errors = parser.syntax_errors(text)?;
+        assert_eq!(lines(&errors.iter().collect::<Vec<_>>()), vec![3, 8]);
+        let report = parser
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap_err();
+        assert!(matches!(
+            report.current_context(),
+            WdlParserError::Syntax(_)
+        ));
+        assert_eq!(lines(&SyntaxError::from_report(&report)), vec![3, 8]);
+        assert!(parser
+            .syntax_errors("version 1.1\n\ntask a {\n}\n")?
+            .is_empty());
+        Ok(())
+    }
 }
@@ -1,5 +1,5 @@
 use crate::{
-    model::{Anchor, Comments, ModelError, SourceFragment, Span},
+    model::{Anchor, CommentKind, Comments, ModelError, SourceFragment, Span},
     parsers::tree_sitter::syntax::rules,
 };
 use error_stack::{bail, report, IntoReport, Report, Result, ResultExt};
@@ -18,17 +18,37 @@ fn node_as_str<'a>(node: ts::Node<'a>, text: &'a [u8]) -> Result<&'a str, ModelE
 }
 
 /// Adds a comment node to `comments`.
+/// Adds the comment `node` to `comments`. If the comment cannot be added and `record_dropped` is
+/// `true`, it is recorded as dropped (see `Comments::dropped`) rather than returning an error.
 fn add_comment<'a, C: DerefMut<Target = Comments>>(
     node: ts::Node<'a>,
     text: &'a [u8],
     mut comments: C,
+    record_dropped: bool,
 ) -> Result<(), ModelError> {
     let element = node_as_str(node, text)?;
     let comment = Anchor::new(element.to_owned(), (&node).into());
-    comments
-        .deref_mut()
-        .try_insert(node.start_position().row, comment)?;
-    Ok(())
+    // the text of the line before the comment
+    let line_start = text[..node.start_byte()]
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let prefix = String::from_utf8_lossy(&text[line_start..node.start_byte()]);
+    let line = node.start_position().row;
+    match comments.deref_mut().try_insert_with_kind(
+        line,
+        comment.clone(),
+        CommentKind::from_prefix(&prefix),
+    ) {
+        Err(_) if record_dropped => {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(line, "dropped comment that could not be added to the model");
+            comments.push_dropped(comment);
+            Ok(())
+        }
+        result => result,
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -257,6 +277,7 @@ trait TSCursorExt<'a> {
         &mut self,
         text: &'a [u8],
         comments: Rc<RefCell<Comments>>,
+        record_dropped: bool,
     ) -> Result<Option<(ts::Node<'a>, Option<&'a str>)>, ModelError>;
 }
 
@@ -265,13 +286,14 @@ impl<'a> TSCursorExt<'a> for ts::TreeCursor<'a> {
         &mut self,
         text: &'a [u8],
         comments: Rc<RefCell<Comments>>,
+        record_dropped: bool,
     ) -> Result<Option<(ts::Node<'a>, Option<&'a str>)>, ModelError> {
         loop {
             let node = self.node();
             if node.is_error() || node.is_missing() {
                 bail!(ModelError::parser(format!("Parser error {:?}", node)));
             } else if node.kind() == rules::COMMENT {
-                add_comment(node, text, (*comments).borrow_mut())?;
+                add_comment(node, text, (*comments).borrow_mut(), record_dropped)?;
                 if !self.goto_next_sibling() {
                     return Ok(None);
                 }
@@ -310,6 +332,8 @@ pub struct TSNodeIterator<'a> {
     delim: BlockDelim,
     text: &'a [u8],
     comments: Rc<RefCell<Comments>>,
+    /// Whether the iterator is being drained because it was dropped.
+    dropping: bool,
 }
 
 impl<'a> TSNodeIterator<'a> {
@@ -332,6 +356,7 @@ impl<'a> TSNodeIterator<'a> {
             delim,
             text,
             comments,
+            dropping: false,
         }
     }
 
@@ -354,7 +379,7 @@ impl<'a> TSNodeIterator<'a> {
                 }
                 State::Open => {
                     let mut cursor = (*self.cursor).borrow_mut();
-                    match cursor.get_next(self.text, self.comments.clone())? {
+                    match cursor.get_next(self.text, self.comments.clone(), self.dropping)? {
                         Some((node, _)) => {
                             match BlockEnds::get_open(node.kind()) {
                                 BlockEnds::SingleQuotes if self.ends == BlockEnds::Quotes => {
@@ -388,7 +413,7 @@ impl<'a> TSNodeIterator<'a> {
                     let mut cursor = (*self.cursor).borrow_mut();
                     let has_ends = self.ends != BlockEnds::None;
                     let (node, field_name) =
-                        match cursor.get_next(self.text, self.comments.clone())? {
+                        match cursor.get_next(self.text, self.comments.clone(), self.dropping)? {
                             Some((node, _))
                                 if has_ends && self.ends == BlockEnds::get_close(node.kind()) =>
                             {
@@ -447,10 +472,11 @@ impl<'a> TSNodeIterator<'a> {
                     }
                 }
                 State::Exhaust => {
-                    match (*self.cursor)
-                        .borrow_mut()
-                        .get_next(self.text, self.comments.clone())?
-                    {
+                    match (*self.cursor).borrow_mut().get_next(
+                        self.text,
+                        self.comments.clone(),
+                        self.dropping,
+                    )? {
                         Some((node, _)) => bail!(ModelError::parser(format!(
                             "Expected iterator to be exhausted but found {:?}",
                             node
@@ -625,6 +651,14 @@ impl<'a> Iterator for TSNodeIterator<'a> {
 
 impl<'a> Drop for TSNodeIterator<'a> {
     fn drop(&mut self) {
-        self.drain(false).unwrap();
+        // drains the iterator to ensure all comment nodes are added to `self.comments`; errors
+        // cannot be returned from `drop`, so comments that cannot be added are recorded as
+        // dropped (see `Comments::dropped`), and any other error (e.g. a syntax error in a node
+        // that was not converted) ends the drain
+        self.dropping = true;
+        if let Err(_error) = self.drain(false) {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(error = ?_error, "error while draining dropped node iterator");
+        }
     }
 }
@@ -1,5 +1,5 @@
 use crate::{
-    model::{Command, ModelError, Runtime, RuntimeAttribute, Task, TaskElement},
+    model::{Command, CommandStyle, ModelError, Runtime, RuntimeAttribute, Task, TaskElement},
     parsers::tree_sitter::{
         node::{BlockDelim, BlockEnds, TSNode},
         syntax::{fields, keywords, rules, symbols},
@@ -14,13 +14,16 @@ impl<'a> TryFrom<TSNode<'a>> for Command {
     fn try_from(node: TSNode<'a>) -> Result<Self, ModelError> {
         let mut children = node.into_children();
         children.skip_terminal(keywords::COMMAND)?;
-        let _ = children.next_node()?.try_as_str()?;
+        let style = match children.next_node()?.try_as_str()? {
+            "<<<" => CommandStyle::Heredoc,
+            _ => CommandStyle::Braces,
+        };
         let parts = if let Some(parts) = children.get_next_field(fields::PARTS)? {
             parts.into_children().collect_anchors()?
         } else {
             Vec::new()
         };
-        Ok(Self { parts })
+        Ok(Self { style, parts })
     }
 }
 
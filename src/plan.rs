@@ -0,0 +1,625 @@
+//! Engine-agnostic execution plans. A `Plan` lists the task invocations (steps) of a workflow in
+//! an order that respects the dependencies between them, along with the values of their inputs
+//! and the number of times each step is invoked, to the extent that these can be determined
+//! statically from the workflow inputs. This is intended for lightweight runners and dry-run
+//! tooling; it does not attempt to evaluate anything that requires executing a task.
+use crate::{
+    analysis::referenced_identifiers,
+    eval::{evaluate, Environment},
+    fqn::{self, FqnKind, FullyQualifiedName},
+    model::{
+        Anchor, BoundDeclaration, Call, CallInput, Document, DocumentElement, Expression,
+        InputDeclaration, Span, TaskElement, Type, Workflow, WorkflowElement,
+        WorkflowNestedElement,
+    },
+    values::WdlValue,
+};
+use error_stack::{Result, ResultExt};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Deref,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum PlanError {
+    #[error("Document does not contain a workflow")]
+    MissingWorkflow,
+    #[error("Invalid value for input {0}")]
+    Input(String),
+}
+
+/// A scatter or conditional block that encloses a step.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlannedBlock {
+    Scatter {
+        name: FullyQualifiedName,
+        variable: String,
+        /// The number of elements in the scatter collection, if it can be determined statically.
+        width: Option<usize>,
+    },
+    Conditional {
+        name: FullyQualifiedName,
+        /// The value of the condition, if it can be determined statically.
+        condition: Option<bool>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlannedInput {
+    /// The input value was determined statically.
+    Value(WdlValue),
+    /// The input value depends on values that are only known at runtime (e.g. the outputs of
+    /// other calls or scatter variables). Contains the referenced identifiers.
+    Deferred(Vec<String>),
+    /// The input is required but no value was provided.
+    Missing,
+}
+
+/// A single call in a workflow.
+#[derive(Debug, PartialEq)]
+pub struct Step {
+    /// The fully-qualified name of the call.
+    pub name: FullyQualifiedName,
+    /// The call target, e.g. `lib.task`.
+    pub target: String,
+    /// The enclosing blocks, from outermost to innermost.
+    pub blocks: Vec<PlannedBlock>,
+    /// The call inputs, in declaration order. For calls to tasks that are defined in the same
+    /// document, this includes all of the task's inputs; otherwise it includes only the inputs
+    /// that are specified in the call.
+    pub inputs: Vec<(String, PlannedInput)>,
+    /// The names of the calls on which this step depends.
+    pub dependencies: Vec<String>,
+}
+
+impl Step {
+    /// Returns the name by which the step's call is referenced in the workflow.
+    pub fn call_name(&self) -> &str {
+        self.name.local_name()
+    }
+
+    /// Returns the number of times this step will be invoked: the product of the widths of its
+    /// enclosing scatters, or zero if any of its enclosing conditions is false. Returns `None` if
+    /// this cannot be determined statically or does not fit in a `usize`.
+    pub fn invocations(&self) -> Option<usize> {
+        let mut invocations: Option<usize> = Some(1);
+        for block in self.blocks.iter() {
+            match block {
+                PlannedBlock::Scatter { width: Some(w), .. } => {
+                    invocations = invocations.and_then(|i| i.checked_mul(*w))
+                }
+                PlannedBlock::Conditional {
+                    condition: Some(false),
+                    ..
+                } => return Some(0),
+                PlannedBlock::Conditional {
+                    condition: Some(true),
+                    ..
+                } => (),
+                _ => invocations = None,
+            }
+        }
+        invocations
+    }
+
+    /// Returns the names of any required inputs for which no value was provided.
+    pub fn missing_inputs(&self) -> Vec<&str> {
+        self.inputs
+            .iter()
+            .filter(|(_, input)| *input == PlannedInput::Missing)
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Plan {
+    pub workflow: String,
+    /// The steps of the plan, ordered such that each step comes after all of its dependencies.
+    /// Independent steps are ordered as they appear in the workflow.
+    pub steps: Vec<Anchor<Step>>,
+}
+
+/// Generates a plan for the workflow in `document`. `inputs` may be keyed by either unqualified
+/// workflow input names (e.g. `x`) or fully-qualified names (e.g. `wf.x`); inputs to calls may be
+/// specified as `wf.call.input`. Input values are coerced to their declared types.
+pub fn plan(document: &Document, inputs: &HashMap<String, WdlValue>) -> Result<Plan, PlanError> {
+    let workflow = document
+        .body_iter()
+        .find_map(|element| match element {
+            DocumentElement::Workflow(workflow) => Some(workflow),
+            _ => None,
+        })
+        .ok_or(PlanError::MissingWorkflow)?;
+    let mut planner = Planner {
+        document,
+        workflow,
+        inputs,
+        env: Environment::new(),
+        nodes: HashMap::new(),
+        scatters: 0,
+        conditionals: 0,
+        blocks: Vec::new(),
+        steps: Vec::new(),
+    };
+    planner.collect_nodes(workflow);
+    let root = FullyQualifiedName::workflow(workflow.name.as_str());
+    for element in workflow.body.iter() {
+        match element.deref() {
+            WorkflowElement::Input(input) => {
+                for decl in input.declarations.iter() {
+                    planner.add_workflow_input(decl)?;
+                }
+            }
+            WorkflowElement::Declaration(decl) => planner.add_declaration(decl),
+            WorkflowElement::Call(call) => planner.add_call(&root, call, &element.span),
+            WorkflowElement::Scatter(scatter) => planner.add_block(
+                &root,
+                Some(scatter.name.as_str()),
+                &scatter.expression,
+                &scatter.body,
+            ),
+            WorkflowElement::Conditional(conditional) => {
+                planner.add_block(&root, None, &conditional.expression, &conditional.body)
+            }
+            _ => (),
+        }
+    }
+    Ok(Plan {
+        workflow: (*workflow.name).clone(),
+        steps: sort_steps(planner.steps),
+    })
+}
+
+/// Orders `steps` topologically, breaking ties by the original order.
+fn sort_steps(mut steps: Vec<Anchor<Step>>) -> Vec<Anchor<Step>> {
+    let mut sorted = Vec::with_capacity(steps.len());
+    let mut done: HashSet<String> = HashSet::new();
+    let names: HashSet<String> = steps.iter().map(|s| s.call_name().to_owned()).collect();
+    while !steps.is_empty() {
+        let next = steps
+            .iter()
+            .position(|step| {
+                step.dependencies
+                    .iter()
+                    .all(|d| done.contains(d) || !names.contains(d))
+            })
+            // a dependency cycle - fall back to source order
+            .unwrap_or(0);
+        let step = steps.remove(next);
+        done.insert(step.call_name().to_owned());
+        sorted.push(step);
+    }
+    sorted
+}
+
+/// A named element of the workflow that may be referenced by other elements.
+struct Node {
+    references: Vec<String>,
+    is_call: bool,
+}
+
+struct Planner<'a> {
+    document: &'a Document,
+    workflow: &'a Workflow,
+    inputs: &'a HashMap<String, WdlValue>,
+    env: Environment,
+    nodes: HashMap<String, Node>,
+    scatters: usize,
+    conditionals: usize,
+    blocks: Vec<PlannedBlock>,
+    steps: Vec<Anchor<Step>>,
+}
+
+fn references(expression: &Anchor<Expression>) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for identifier in referenced_identifiers(expression) {
+        if !names.iter().any(|name| name == identifier.as_str()) {
+            names.push((*identifier).clone());
+        }
+    }
+    names
+}
+
+impl<'a> Planner<'a> {
+    fn collect_nodes(&mut self, workflow: &Workflow) {
+        for element in workflow.body.iter() {
+            match element.deref() {
+                WorkflowElement::Declaration(decl) => self.add_decl_node(decl, &[]),
+                WorkflowElement::Call(call) => self.add_call_node(call, &[]),
+                WorkflowElement::Scatter(scatter) => {
+                    let block_refs = references(&scatter.expression);
+                    self.nodes.insert(
+                        (*scatter.name).clone(),
+                        Node {
+                            references: block_refs.clone(),
+                            is_call: false,
+                        },
+                    );
+                    self.collect_nested_nodes(&scatter.body, &block_refs)
+                }
+                WorkflowElement::Conditional(conditional) => {
+                    let block_refs = references(&conditional.expression);
+                    self.collect_nested_nodes(&conditional.body, &block_refs)
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn collect_nested_nodes(
+        &mut self,
+        body: &[Anchor<WorkflowNestedElement>],
+        block_refs: &[String],
+    ) {
+        for element in body.iter() {
+            match element.deref() {
+                WorkflowNestedElement::Declaration(decl) => self.add_decl_node(decl, block_refs),
+                WorkflowNestedElement::Call(call) => self.add_call_node(call, block_refs),
+                WorkflowNestedElement::Scatter(scatter) => {
+                    let mut refs = block_refs.to_vec();
+                    refs.extend(references(&scatter.expression));
+                    self.nodes.insert(
+                        (*scatter.name).clone(),
+                        Node {
+                            references: refs.clone(),
+                            is_call: false,
+                        },
+                    );
+                    self.collect_nested_nodes(&scatter.body, &refs)
+                }
+                WorkflowNestedElement::Conditional(conditional) => {
+                    let mut refs = block_refs.to_vec();
+                    refs.extend(references(&conditional.expression));
+                    self.collect_nested_nodes(&conditional.body, &refs)
+                }
+            }
+        }
+    }
+
+    fn add_decl_node(&mut self, decl: &BoundDeclaration, block_refs: &[String]) {
+        let mut refs = block_refs.to_vec();
+        refs.extend(references(&decl.expression));
+        self.nodes.insert(
+            (*decl.name).clone(),
+            Node {
+                references: refs,
+                is_call: false,
+            },
+        );
+    }
+
+    fn add_call_node(&mut self, call: &Call, block_refs: &[String]) {
+        let mut refs = block_refs.to_vec();
+        if let Some(inputs) = &call.inputs {
+            for input in inputs.iter() {
+                match &input.expression {
+                    Some(expression) => refs.extend(references(expression)),
+                    None => refs.push((*input.name).clone()),
+                }
+            }
+        }
+        self.nodes.insert(
+            fqn::call_name(call).to_owned(),
+            Node {
+                references: refs,
+                is_call: true,
+            },
+        );
+    }
+
+    /// Returns the names of the calls on which the element with the given references depends,
+    /// either directly or via declarations.
+    fn call_dependencies(&self, refs: &[String]) -> Vec<String> {
+        let mut dependencies = Vec::new();
+        let mut seen = HashSet::new();
+        let mut stack: Vec<&String> = refs.iter().rev().collect();
+        while let Some(name) = stack.pop() {
+            if !seen.insert(name) {
+                continue;
+            }
+            match self.nodes.get(name) {
+                Some(node) if node.is_call => dependencies.push(name.clone()),
+                Some(node) => stack.extend(node.references.iter().rev()),
+                None => (),
+            }
+        }
+        dependencies
+    }
+
+    fn lookup_input(&self, parts: &[&str]) -> Option<&WdlValue> {
+        let qualified = format!("{}.{}", self.workflow.name.as_str(), parts.join("."));
+        self.inputs
+            .get(&qualified)
+            .or_else(|| self.inputs.get(&parts.join(".")))
+    }
+
+    fn add_workflow_input(&mut self, decl: &InputDeclaration) -> Result<(), PlanError> {
+        let (name, type_) = (decl.name().as_str(), decl.type_());
+        if let Some(value) = self.lookup_input(&[name]) {
+            let value = value
+                .clone()
+                .coerce(type_)
+                .change_context(PlanError::Input(name.to_owned()))?;
+            self.env.bind(name, value);
+        } else {
+            match decl {
+                InputDeclaration::Bound(d) => {
+                    if let Ok(value) = evaluate(&d.expression, &self.env) {
+                        self.env.bind(name, value)
+                    }
+                }
+                InputDeclaration::Unbound(_) if matches!(type_.deref(), Type::Optional(_)) => {
+                    self.env.bind(name, WdlValue::None)
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn add_declaration(&mut self, decl: &BoundDeclaration) {
+        // declarations within blocks are not bound since they may have different values in each
+        // scatter shard, or may not be evaluated at all
+        if self.blocks.is_empty() {
+            if let Ok(value) = evaluate(&decl.expression, &self.env) {
+                self.env.bind(decl.name.as_str(), value);
+            }
+        }
+    }
+
+    fn resolve(&self, expression: &Anchor<Expression>, env: &Environment) -> PlannedInput {
+        match evaluate(expression, env) {
+            Ok(value) => PlannedInput::Value(value),
+            Err(_) => PlannedInput::Deferred(references(expression)),
+        }
+    }
+
+    fn add_call(&mut self, parent: &FullyQualifiedName, call: &Call, span: &Span) {
+        let call_name = fqn::call_name(call);
+        let call_inputs: Vec<&Anchor<CallInput>> = match &call.inputs {
+            Some(inputs) => inputs.iter().collect(),
+            None => Vec::new(),
+        };
+        let resolve_call_input = |input: &CallInput| match &input.expression {
+            Some(expression) => self.resolve(expression, &self.env),
+            None => match self.env.get(input.name.as_str()) {
+                Some(value) => PlannedInput::Value(value.clone()),
+                None => PlannedInput::Deferred(vec![(*input.name).clone()]),
+            },
+        };
+        let mut inputs = Vec::new();
+        if let Some(task) = fqn::find_local_task(self.document, call) {
+            let mut task_env = Environment::new();
+            for element in task.body.iter() {
+                if let TaskElement::Input(input) = element.deref() {
+                    for decl in input.declarations.iter() {
+                        let (name, type_) = (decl.name().as_str(), decl.type_());
+                        let planned = if let Some(input) =
+                            call_inputs.iter().find(|i| i.name.as_str() == name)
+                        {
+                            resolve_call_input(input)
+                        } else if let Some(value) = self.lookup_input(&[call_name, name]) {
+                            match value.clone().coerce(type_) {
+                                Ok(value) => PlannedInput::Value(value),
+                                Err(_) => PlannedInput::Missing,
+                            }
+                        } else {
+                            match decl.deref() {
+                                InputDeclaration::Bound(d) => {
+                                    self.resolve(&d.expression, &task_env)
+                                }
+                                InputDeclaration::Unbound(_)
+                                    if matches!(type_.deref(), Type::Optional(_)) =>
+                                {
+                                    PlannedInput::Value(WdlValue::None)
+                                }
+                                InputDeclaration::Unbound(_) => PlannedInput::Missing,
+                            }
+                        };
+                        if let PlannedInput::Value(value) = &planned {
+                            task_env.bind(name, value.clone());
+                        }
+                        inputs.push((name.to_owned(), planned));
+                    }
+                }
+            }
+        } else {
+            for input in call_inputs.iter() {
+                inputs.push(((*input.name).clone(), resolve_call_input(input)));
+            }
+        }
+        let dependencies = match self.nodes.get(call_name) {
+            Some(node) => self.call_dependencies(&node.references),
+            None => Vec::new(),
+        };
+        let step = Step {
+            name: parent.child(FqnKind::Call, call_name),
+            target: call
+                .target
+                .parts
+                .iter()
+                .map(|part| part.as_str())
+                .collect::<Vec<_>>()
+                .join("."),
+            blocks: self.blocks.clone(),
+            inputs,
+            dependencies,
+        };
+        self.steps.push(Anchor::new(step, span.clone()));
+    }
+
+    fn add_block(
+        &mut self,
+        parent: &FullyQualifiedName,
+        scatter_variable: Option<&str>,
+        expression: &Anchor<Expression>,
+        body: &[Anchor<WorkflowNestedElement>],
+    ) {
+        // only top-level values are bound in the environment, so any expression that references
+        // a value that is local to an enclosing block fails to evaluate
+        let value = evaluate(expression, &self.env).ok();
+        let block = match scatter_variable {
+            Some(variable) => {
+                self.scatters += 1;
+                PlannedBlock::Scatter {
+                    name: parent.child(FqnKind::Scatter, fqn::scatter_name(self.scatters - 1)),
+                    variable: variable.to_owned(),
+                    width: match value {
+                        Some(WdlValue::Array(values)) => Some(values.len()),
+                        _ => None,
+                    },
+                }
+            }
+            None => {
+                self.conditionals += 1;
+                PlannedBlock::Conditional {
+                    name: parent.child(
+                        FqnKind::Conditional,
+                        fqn::conditional_name(self.conditionals - 1),
+                    ),
+                    condition: match value {
+                        Some(WdlValue::Boolean(b)) => Some(b),
+                        _ => None,
+                    },
+                }
+            }
+        };
+        let block_fqn = match &block {
+            PlannedBlock::Scatter { name, .. } | PlannedBlock::Conditional { name, .. } => {
+                name.clone()
+            }
+        };
+        self.blocks.push(block);
+        for element in body.iter() {
+            match element.deref() {
+                WorkflowNestedElement::Declaration(decl) => self.add_declaration(decl),
+                WorkflowNestedElement::Call(call) => self.add_call(&block_fqn, call, &element.span),
+                WorkflowNestedElement::Scatter(scatter) => self.add_block(
+                    &block_fqn,
+                    Some(scatter.name.as_str()),
+                    &scatter.expression,
+                    &scatter.body,
+                ),
+                WorkflowNestedElement::Conditional(conditional) => {
+                    self.add_block(&block_fqn, None, &conditional.expression, &conditional.body)
+                }
+            }
+        }
+        self.blocks.pop();
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_plan() {
+        let text = r#"
+version 1.1
+
+task t {
+    input {
+        Int a
+        Int b = a + 1
+        String? c
+    }
+    command <<< >>>
+    output {
+        Int y = a
+    }
+}
+
+workflow wf {
+    input {
+        Array[Int] xs
+        Boolean go = true
+    }
+    scatter (x in xs) {
+        if (go) {
+            call t as second {
+                input: a = first.y
+            }
+        }
+    }
+    call t as first {
+        input: a = length(xs)
+    }
+    call lib.align
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let mut inputs = HashMap::new();
+        inputs.insert(
+            "wf.xs".to_owned(),
+            WdlValue::Array(vec![WdlValue::Int(1), WdlValue::Int(2)]),
+        );
+        let plan = plan(&doc, &inputs).unwrap();
+        let steps: Vec<(String, Option<usize>, Vec<String>)> = plan
+            .steps
+            .iter()
+            .map(|step| {
+                (
+                    step.name.to_string(),
+                    step.invocations(),
+                    step.dependencies.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                ("wf.first".to_owned(), Some(1), vec![]),
+                (
+                    "wf.scatter_0.conditional_0.second".to_owned(),
+                    Some(2),
+                    vec!["first".to_owned()]
+                ),
+                ("wf.align".to_owned(), Some(1), vec![]),
+            ]
+        );
+        assert_eq!(
+            plan.steps[0].inputs,
+            vec![
+                ("a".to_owned(), PlannedInput::Value(WdlValue::Int(2))),
+                ("b".to_owned(), PlannedInput::Value(WdlValue::Int(3))),
+                ("c".to_owned(), PlannedInput::Value(WdlValue::None)),
+            ]
+        );
+        assert_eq!(
+            plan.steps[1].inputs[1],
+            ("b".to_owned(), PlannedInput::Deferred(vec!["a".to_owned()]))
+        );
+        assert_eq!(plan.steps[2].target, "lib.align");
+    }
+
+    #[test]
+    fn test_invocations_overflow() {
+        let root = FullyQualifiedName::workflow("wf");
+        let step = |scatters: usize| Step {
+            name: root.child(FqnKind::Call, "t"),
+            target: "t".to_owned(),
+            blocks: (0..scatters)
+                .map(|i| PlannedBlock::Scatter {
+                    name: root.child(FqnKind::Scatter, fqn::scatter_name(i)),
+                    variable: "i".to_owned(),
+                    width: Some(1_000_000),
+                })
+                .collect(),
+            inputs: Vec::new(),
+            dependencies: Vec::new(),
+        };
+        assert_eq!(step(3).invocations(), Some(1_000_000_000_000_000_000));
+        // 10^24 invocations do not fit in a usize
+        assert_eq!(step(4).invocations(), None);
+    }
+}
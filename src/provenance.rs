@@ -0,0 +1,379 @@
+//! Export of a structured description of a workflow for archiving and publication, as the
+//! metadata of an [RO-Crate](https://www.researchobject.org/ro-crate/) that follows the
+//! [Workflow RO-Crate](https://w3id.org/workflowhub/workflow-ro-crate/1.0) profile.
+//!
+//! The metadata is a JSON-LD document whose `@graph` contains:
+//!
+//! * The metadata descriptor (`ro-crate-metadata.json`) and the root dataset (`./`).
+//! * The main workflow document, typed as a `ComputationalWorkflow`, with its inputs and outputs
+//!   as `FormalParameter`s and its calls as `HowToStep`s.
+//! * Each document that is imported (directly or transitively) by the main document and that is
+//!   in the workspace.
+//! * Each container image that is used by the tasks of these documents, as a `ContainerImage`.
+//! * The WDL language, as a `ComputerLanguage`.
+//!
+//! Every document has a `sha256` property with the digest of its source text. Documents that
+//! were loaded from files are identified by their paths relative to the directory of the main
+//! document, so the metadata can be written alongside the documents as `ro-crate-metadata.json`.
+use crate::{
+    analysis::{container_inventory, ImageReference},
+    fqn,
+    model::{Document, DocumentElement, DocumentSource, InputDeclaration, Type, Workflow},
+    workspace::Workspace,
+};
+use error_stack::{bail, Result};
+use serde_json::{json, Value as JsonValue};
+use std::{collections::BTreeSet, ops::Deref, path::Path};
+use thiserror::Error;
+
+pub const RO_CRATE_CONTEXT: &str = "https://w3id.org/ro/crate/1.1/context";
+pub const RO_CRATE_PROFILE: &str = "https://w3id.org/ro/crate/1.1";
+pub const WORKFLOW_PROFILE: &str = "https://w3id.org/workflowhub/workflow-ro-crate/1.0";
+const FORMAL_PARAMETER_PROFILE: &str =
+    "https://bioschemas.org/profiles/FormalParameter/1.0-RELEASE";
+const DOCKER_IMAGE: &str = "https://w3id.org/ro/terms/workflow-run#DockerImage";
+const WDL_ID: &str = "#wdl";
+
+#[derive(Error, Debug)]
+pub enum ProvenanceError {
+    #[error("document {0} does not contain a workflow")]
+    MissingWorkflow(DocumentSource),
+}
+
+/// Returns the `@id` of the entity for `document`. Files within `root` are identified by their
+/// relative paths, and documents with unknown sources by their digests.
+fn document_id(document: &Document, root: Option<&Path>) -> String {
+    match &document.source {
+        DocumentSource::File(path) => root
+            .and_then(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned(),
+        DocumentSource::Uri(uri) => uri.clone(),
+        DocumentSource::Unknown => format!("#{}", document.source_digest()),
+    }
+}
+
+/// Returns the documents that are imported by `document`, directly or transitively, and that
+/// are in `workspace`, in breadth-first order. Each document is returned once.
+fn imported_documents<'a>(workspace: &'a Workspace, document: &'a Document) -> Vec<&'a Document> {
+    let mut seen = BTreeSet::from([&document.source]);
+    let mut documents = vec![document];
+    let mut next = 0;
+    while next < documents.len() {
+        let current = documents[next];
+        next += 1;
+        for element in current.body_iter() {
+            if let DocumentElement::Import(import) = element {
+                if let Some(imported) = workspace.resolve_import(current, import) {
+                    if seen.insert(&imported.source) {
+                        documents.push(imported)
+                    }
+                }
+            }
+        }
+    }
+    documents.split_off(1)
+}
+
+/// Returns the schema.org type that best describes values of `type_`, which is used as the
+/// `additionalType` of a `FormalParameter`.
+fn additional_type(type_: &Type) -> &'static str {
+    match type_ {
+        Type::Optional(inner) => additional_type(inner),
+        Type::Boolean => "Boolean",
+        Type::Int => "Integer",
+        Type::Float => "Float",
+        Type::String => "Text",
+        Type::File => "File",
+        Type::Array { .. } => "Collection",
+        Type::Map { .. } | Type::Pair { .. } | Type::Object | Type::User(_) => "PropertyValue",
+    }
+}
+
+fn formal_parameter(
+    workflow: &Workflow,
+    id: String,
+    name: &str,
+    type_: &Type,
+    required: bool,
+) -> JsonValue {
+    let mut parameter = json!({
+        "@id": id,
+        "@type": "FormalParameter",
+        "conformsTo": { "@id": FORMAL_PARAMETER_PROFILE },
+        "name": name,
+        "additionalType": additional_type(type_),
+        "encodingFormat": type_.to_string(),
+        "valueRequired": required,
+    });
+    if let Some(description) = workflow
+        .parameter_description(name)
+        .and_then(|desc| desc.description)
+    {
+        parameter["description"] = description.into();
+    }
+    parameter
+}
+
+fn container_image(reference: &ImageReference) -> JsonValue {
+    let mut image = json!({
+        "@id": format!("#container/{}", reference),
+        "@type": "ContainerImage",
+        "additionalType": { "@id": DOCKER_IMAGE },
+        "registry": reference.registry,
+        "name": reference.repository,
+    });
+    if let Some(tag) = &reference.tag {
+        image["tag"] = tag.as_str().into();
+    }
+    if let Some(hex) = reference
+        .digest
+        .as_deref()
+        .and_then(|digest| digest.strip_prefix("sha256:"))
+    {
+        image["sha256"] = hex.into();
+    }
+    image
+}
+
+/// Returns the RO-Crate metadata (i.e. the contents of `ro-crate-metadata.json`) that describes
+/// the workflow in `document`. Imports of `document` are resolved in `workspace`; imported
+/// documents that are not in the workspace are omitted.
+pub fn ro_crate(workspace: &Workspace, document: &Document) -> Result<JsonValue, ProvenanceError> {
+    let workflow = match document.body_iter().find_map(|element| match element {
+        DocumentElement::Workflow(workflow) => Some(workflow),
+        _ => None,
+    }) {
+        Some(workflow) => workflow,
+        None => bail!(ProvenanceError::MissingWorkflow(document.source.clone())),
+    };
+    let root = match &document.source {
+        DocumentSource::File(path) => path.parent(),
+        _ => None,
+    };
+    let main_id = document_id(document, root);
+    let imports = imported_documents(workspace, document);
+    let mut graph = Vec::new();
+
+    let mut inputs = Vec::new();
+    for decl in workflow
+        .inputs()
+        .iter()
+        .flat_map(|input| &input.declarations)
+    {
+        let name = decl.name().as_str();
+        let required = matches!(decl.deref(), InputDeclaration::Unbound(_))
+            && !matches!(decl.type_().deref(), Type::Optional(_));
+        let id = format!("#{}/inputs/{}", workflow.name.as_str(), name);
+        graph.push(formal_parameter(
+            workflow,
+            id.clone(),
+            name,
+            decl.type_(),
+            required,
+        ));
+        inputs.push(json!({ "@id": id }));
+    }
+    let mut outputs = Vec::new();
+    for decl in workflow
+        .outputs()
+        .iter()
+        .flat_map(|output| &output.declarations)
+    {
+        let name = decl.name.as_str();
+        let id = format!("#{}/outputs/{}", workflow.name.as_str(), name);
+        graph.push(formal_parameter(
+            workflow,
+            id.clone(),
+            name,
+            &decl.type_,
+            true,
+        ));
+        outputs.push(json!({ "@id": id }));
+    }
+    let mut steps = Vec::new();
+    for (position, call) in workflow.calls().enumerate() {
+        let name = fqn::call_name(call);
+        let id = format!("#{}/steps/{}", workflow.name.as_str(), name);
+        let target: Vec<&str> = call.target.parts.iter().map(|part| part.as_str()).collect();
+        let step = json!({
+            "@id": id,
+            "@type": "HowToStep",
+            "name": name,
+            "position": position,
+            "identifier": target.join("."),
+        });
+        graph.push(step);
+        steps.push(json!({ "@id": id }));
+    }
+    // distinct container images of the tasks of all documents, sorted by reference
+    let mut references: Vec<ImageReference> = std::iter::once(document)
+        .chain(imports.iter().copied())
+        .flat_map(|document| {
+            container_inventory(document)
+                .references()
+                .into_iter()
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    references.sort_by_key(|reference| reference.to_string());
+    references.dedup();
+    let containers: Vec<JsonValue> = references
+        .iter()
+        .map(|reference| json!({ "@id": format!("#container/{}", reference) }))
+        .collect();
+    graph.extend(references.iter().map(container_image));
+
+    let import_ids: Vec<String> = imports
+        .iter()
+        .map(|imported| document_id(imported, root))
+        .collect();
+    for (imported, id) in imports.iter().zip(import_ids.iter()) {
+        graph.push(json!({
+            "@id": id,
+            "@type": ["File", "SoftwareSourceCode"],
+            "programmingLanguage": { "@id": WDL_ID },
+            "sha256": imported.source_digest().to_string(),
+        }));
+    }
+    let parts: Vec<JsonValue> = std::iter::once(&main_id)
+        .chain(import_ids.iter())
+        .map(|id| json!({ "@id": id }))
+        .collect();
+    let mut main = json!({
+        "@id": main_id,
+        "@type": ["File", "SoftwareSourceCode", "ComputationalWorkflow"],
+        "name": workflow.name.as_str(),
+        "programmingLanguage": { "@id": WDL_ID },
+        "sha256": document.source_digest().to_string(),
+        "input": inputs,
+        "output": outputs,
+        "step": steps,
+        "softwareRequirements": containers,
+    });
+    if !import_ids.is_empty() {
+        main["hasPart"] = parts[1..].to_vec().into();
+    }
+    graph.push(main);
+    graph.push(json!({
+        "@id": WDL_ID,
+        "@type": "ComputerLanguage",
+        "name": "Workflow Description Language",
+        "alternateName": "WDL",
+        "identifier": { "@id": "https://openwdl.org/" },
+        "url": { "@id": "https://openwdl.org/" },
+        "version": document.version.identifier.to_string(),
+    }));
+    let mut entities = vec![
+        json!({
+            "@id": "ro-crate-metadata.json",
+            "@type": "CreativeWork",
+            "conformsTo": [{ "@id": RO_CRATE_PROFILE }, { "@id": WORKFLOW_PROFILE }],
+            "about": { "@id": "./" },
+        }),
+        json!({
+            "@id": "./",
+            "@type": "Dataset",
+            "conformsTo": { "@id": WORKFLOW_PROFILE },
+            "mainEntity": { "@id": main_id },
+            "hasPart": parts,
+        }),
+    ];
+    entities.extend(graph);
+    Ok(json!({
+        "@context": RO_CRATE_CONTEXT,
+        "@graph": entities,
+    }))
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::SourceDigest,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_ro_crate() {
+        let tasks = r#"version 1.1
+
+task align {
+    input {
+        File reads
+    }
+    command <<< >>>
+    output {
+        File bam = "out.bam"
+    }
+    runtime {
+        container: "ubuntu:22.04"
+    }
+}
+"#;
+        let main = r#"version 1.1
+
+import "lib/tasks.wdl" as lib
+
+workflow main {
+    input {
+        File reads
+        Int? threads
+    }
+    call lib.align { input: reads }
+    call lib.align as again { input: reads }
+    output {
+        File bam = align.bam
+    }
+    parameter_meta {
+        reads: "the reads to align"
+    }
+}
+"#;
+        let mut parser = PestParser::new();
+        let mut workspace = Workspace::new();
+        for (path, text) in [("/w/lib/tasks.wdl", tasks), ("/w/main.wdl", main)] {
+            workspace.insert(
+                parser
+                    .parse_text(text, DocumentSource::File(PathBuf::from(path)))
+                    .unwrap(),
+            );
+        }
+        let document = workspace
+            .get(&DocumentSource::File(PathBuf::from("/w/main.wdl")))
+            .unwrap();
+        let crate_ = ro_crate(&workspace, document).unwrap();
+        let graph = crate_["@graph"].as_array().unwrap();
+        let entity = |id: &str| graph.iter().find(|entity| entity["@id"] == id).unwrap();
+        assert_eq!(crate_["@context"], RO_CRATE_CONTEXT);
+        assert_eq!(entity("./")["mainEntity"]["@id"], "main.wdl");
+        let workflow = entity("main.wdl");
+        assert_eq!(workflow["sha256"], SourceDigest::of(main).to_string());
+        assert_eq!(workflow["hasPart"], json!([{ "@id": "lib/tasks.wdl" }]));
+        assert_eq!(
+            workflow["step"],
+            json!([{ "@id": "#main/steps/align" }, { "@id": "#main/steps/again" }])
+        );
+        let reads = entity("#main/inputs/reads");
+        assert_eq!(reads["additionalType"], "File");
+        assert_eq!(reads["valueRequired"], true);
+        assert_eq!(reads["description"], "the reads to align");
+        assert_eq!(entity("#main/inputs/threads")["valueRequired"], false);
+        assert_eq!(entity("#main/outputs/bam")["encodingFormat"], "File");
+        let container = entity("#container/docker.io/library/ubuntu:22.04");
+        assert_eq!(container["name"], "library/ubuntu");
+        assert_eq!(container["tag"], "22.04");
+        assert_eq!(
+            entity("lib/tasks.wdl")["sha256"],
+            SourceDigest::of(tasks).to_string()
+        );
+
+        let text = "version 1.1\n\ntask t {\n    command <<< >>>\n}\n";
+        let document = parser.parse_text(text, DocumentSource::Unknown).unwrap();
+        assert!(ro_crate(&Workspace::new(), &document).is_err());
+    }
+}
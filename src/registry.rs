@@ -0,0 +1,350 @@
+//! A client for fetching workflows from [Dockstore](https://dockstore.org) via its GA4GH Tool
+//! Registry Service (TRS) API, e.g. to lint published workflows.
+//!
+//! Workflows are identified by their TRS IDs (e.g. `#workflow/github.com/org/repo/name`) and
+//! version names (e.g. a branch or tag). The documents of a workflow are added to a `Workspace`
+//! with `Uri` sources of the form `<base>/tools/<id>/versions/<version>/WDL/descriptor/<path>`,
+//! so that relative imports resolve to the URIs from which the imported documents are fetched.
+use crate::{
+    model::{DocumentElement, DocumentSource},
+    parsers::WdlParser,
+    workspace::Workspace,
+};
+use error_stack::{bail, IntoReport, Result, ResultExt};
+use serde_json::Value as JsonValue;
+use std::fmt::Write;
+use thiserror::Error;
+
+/// The base URL of the TRS API of Dockstore.
+pub const DOCKSTORE_TRS_URL: &str = "https://dockstore.org/api/ga4gh/trs/v2";
+
+#[derive(Error, Debug)]
+pub enum RegistryError {
+    #[error("error requesting {0}")]
+    Request(String),
+    #[error("invalid response from {0}")]
+    Response(String),
+    #[error("{0} version {1} has no primary WDL descriptor")]
+    MissingDescriptor(String, String),
+    #[error("error parsing document {0}")]
+    Parse(DocumentSource),
+}
+
+/// The type of a file of a tool version, as listed by the registry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FileType {
+    PrimaryDescriptor,
+    SecondaryDescriptor,
+    TestFile,
+    Containerfile,
+    Other(String),
+}
+
+impl FileType {
+    fn new(file_type: &str) -> Self {
+        match file_type {
+            "PRIMARY_DESCRIPTOR" => Self::PrimaryDescriptor,
+            "SECONDARY_DESCRIPTOR" => Self::SecondaryDescriptor,
+            "TEST_FILE" => Self::TestFile,
+            "CONTAINERFILE" => Self::Containerfile,
+            other => Self::Other(other.to_owned()),
+        }
+    }
+}
+
+/// A file of a tool version.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ToolFile {
+    /// The path of the file, relative to the primary descriptor.
+    pub path: String,
+    pub file_type: FileType,
+}
+
+/// The metadata of a version of a workflow in the registry.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VersionMetadata {
+    /// The TRS ID of the workflow.
+    pub id: String,
+    /// The name of the version.
+    pub version: String,
+    /// The URL of the version's page in the registry.
+    pub url: Option<String>,
+    pub authors: Vec<String>,
+    /// Whether the version has been verified by the registry.
+    pub verified: bool,
+    /// The container images that the registry lists for the version.
+    pub images: Vec<String>,
+    /// The files of the version.
+    pub files: Vec<ToolFile>,
+}
+
+impl VersionMetadata {
+    /// Returns the path of the primary descriptor.
+    pub fn primary_path(&self) -> Option<&str> {
+        self.files
+            .iter()
+            .find(|file| file.file_type == FileType::PrimaryDescriptor)
+            .map(|file| file.path.as_str())
+    }
+}
+
+/// A workflow that was fetched from the registry.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegistryWorkflow {
+    /// The source of the primary descriptor in the workspace.
+    pub source: DocumentSource,
+    pub metadata: VersionMetadata,
+}
+
+/// Percent-encodes all characters of `s` other than unreserved characters, so that it can be
+/// used as a single path segment (e.g. a TRS ID, which contains `#` and `/`).
+fn encode_segment(s: &str) -> String {
+    let mut encoded = String::new();
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char)
+        } else {
+            write!(encoded, "%{:02X}", byte).unwrap()
+        }
+    }
+    encoded
+}
+
+/// Returns the URL of version `version` of the tool `id`.
+fn version_url(base_url: &str, id: &str, version: &str) -> String {
+    format!(
+        "{}/tools/{}/versions/{}",
+        base_url.trim_end_matches('/'),
+        encode_segment(id),
+        encode_segment(version)
+    )
+}
+
+fn text_field(value: &JsonValue, field: &str) -> Option<String> {
+    value.get(field)?.as_str().map(|s| s.to_owned())
+}
+
+/// Parses the response to a request for the files of a tool version.
+fn parse_files(files: &JsonValue) -> Option<Vec<ToolFile>> {
+    files
+        .as_array()?
+        .iter()
+        .map(|file| {
+            Some(ToolFile {
+                path: text_field(file, "path")?,
+                file_type: FileType::new(file.get("file_type")?.as_str()?),
+            })
+        })
+        .collect()
+}
+
+/// Parses the response to a request for a tool version.
+fn parse_version(id: &str, version: &str, value: &JsonValue) -> VersionMetadata {
+    let strings = |field| -> Vec<String> {
+        value
+            .get(field)
+            .and_then(|values| values.as_array())
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|value| value.as_str().map(|s| s.to_owned()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let images = value
+        .get("images")
+        .and_then(|images| images.as_array())
+        .map(|images| {
+            images
+                .iter()
+                .filter_map(|image| text_field(image, "image_name"))
+                .collect()
+        })
+        .unwrap_or_default();
+    VersionMetadata {
+        id: id.to_owned(),
+        version: version.to_owned(),
+        url: text_field(value, "url"),
+        authors: strings("author"),
+        verified: value
+            .get("verified")
+            .and_then(|verified| verified.as_bool())
+            .unwrap_or(false),
+        images,
+        files: Vec::new(),
+    }
+}
+
+/// A client for the TRS API of Dockstore (or of another registry that implements the API).
+#[derive(Clone, Debug)]
+pub struct DockstoreClient {
+    base_url: String,
+    agent: ureq::Agent,
+}
+
+impl Default for DockstoreClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DockstoreClient {
+    pub fn new() -> Self {
+        Self::with_base_url(DOCKSTORE_TRS_URL)
+    }
+
+    /// Creates a client for the TRS API at `base_url`.
+    pub fn with_base_url<U: Into<String>>(base_url: U) -> Self {
+        Self {
+            base_url: base_url.into(),
+            agent: ureq::Agent::new(),
+        }
+    }
+
+    fn get(&self, url: &str) -> Result<String, RegistryError> {
+        self.agent
+            .get(url)
+            .set("Accept", "application/json")
+            .call()
+            .into_report()
+            .change_context_lazy(|| RegistryError::Request(url.to_owned()))?
+            .into_string()
+            .into_report()
+            .change_context_lazy(|| RegistryError::Response(url.to_owned()))
+    }
+
+    fn get_json(&self, url: &str) -> Result<JsonValue, RegistryError> {
+        serde_json::from_str(&self.get(url)?)
+            .into_report()
+            .change_context_lazy(|| RegistryError::Response(url.to_owned()))
+    }
+
+    /// Returns the metadata of version `version` of the workflow with TRS ID `id`, including the
+    /// list of its WDL files.
+    pub fn version_metadata(
+        &self,
+        id: &str,
+        version: &str,
+    ) -> Result<VersionMetadata, RegistryError> {
+        let url = version_url(&self.base_url, id, version);
+        let mut metadata = parse_version(id, version, &self.get_json(&url)?);
+        let files_url = format!("{}/WDL/files", url);
+        metadata.files = match parse_files(&self.get_json(&files_url)?) {
+            Some(files) => files,
+            None => bail!(RegistryError::Response(files_url)),
+        };
+        Ok(metadata)
+    }
+
+    /// Returns the text of the document at `source`. Documents in the registry are fetched via
+    /// the TRS descriptor endpoint; other URIs (i.e. imports of remote documents) are fetched
+    /// directly.
+    fn fetch_text(&self, source: &DocumentSource) -> Result<String, RegistryError> {
+        let url = match source {
+            DocumentSource::Uri(url) => url,
+            _ => bail!(RegistryError::Request(source.to_string())),
+        };
+        if url.starts_with(self.base_url.trim_end_matches('/')) {
+            match text_field(&self.get_json(url)?, "content") {
+                Some(content) => Ok(content),
+                None => bail!(RegistryError::Response(url.clone())),
+            }
+        } else {
+            self.get(url)
+        }
+    }
+
+    /// Fetches version `version` of the workflow with TRS ID `id` and, recursively, the
+    /// documents that it imports, parses them with `parser`, and adds them to `workspace`.
+    /// Documents that are already in the workspace are not fetched again. Imports of local files
+    /// (i.e. `file://` URIs or absolute paths) are not fetched.
+    pub fn fetch<P: WdlParser>(
+        &self,
+        parser: &mut P,
+        workspace: &mut Workspace,
+        id: &str,
+        version: &str,
+    ) -> Result<RegistryWorkflow, RegistryError> {
+        let metadata = self.version_metadata(id, version)?;
+        let primary = match metadata.primary_path() {
+            Some(path) => path.trim_start_matches('/'),
+            None => bail!(RegistryError::MissingDescriptor(
+                id.to_owned(),
+                version.to_owned()
+            )),
+        };
+        let source = DocumentSource::Uri(format!(
+            "{}/WDL/descriptor/{}",
+            version_url(&self.base_url, id, version),
+            primary
+        ));
+        let mut pending = vec![source.clone()];
+        while let Some(next) = pending.pop() {
+            if workspace.contains(&next) || !matches!(next, DocumentSource::Uri(_)) {
+                continue;
+            }
+            let text = self.fetch_text(&next)?;
+            let document = parser
+                .parse_text(&text, next.clone())
+                .change_context_lazy(|| RegistryError::Parse(next.clone()))?;
+            for element in document.body_iter() {
+                if let DocumentElement::Import(import) = element {
+                    pending.push(workspace.resolve_source(&next, import.uri.as_str()));
+                }
+            }
+            workspace.insert(document);
+        }
+        Ok(RegistryWorkflow { source, metadata })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    #[test]
+    fn test_version_url() {
+        assert_eq!(
+            version_url(
+                DOCKSTORE_TRS_URL,
+                "#workflow/github.com/org/repo/align",
+                "v1.0"
+            ),
+            "https://dockstore.org/api/ga4gh/trs/v2/tools/\
+             %23workflow%2Fgithub.com%2Forg%2Frepo%2Falign/versions/v1.0"
+        );
+    }
+
+    #[test]
+    fn test_parse_metadata() {
+        let version = json!({
+            "author": ["Jane Doe"],
+            "url": "https://dockstore.org/workflows/github.com/org/repo/align:v1.0",
+            "verified": true,
+            "images": [{ "image_name": "ubuntu:22.04" }],
+        });
+        let mut metadata = parse_version("#workflow/x", "v1.0", &version);
+        metadata.files = parse_files(&json!([
+            { "path": "/lib/tasks.wdl", "file_type": "SECONDARY_DESCRIPTOR" },
+            { "path": "/main.wdl", "file_type": "PRIMARY_DESCRIPTOR" },
+        ]))
+        .unwrap();
+        assert_eq!(metadata.authors, vec!["Jane Doe".to_owned()]);
+        assert!(metadata.verified);
+        assert_eq!(metadata.images, vec!["ubuntu:22.04".to_owned()]);
+        assert_eq!(metadata.primary_path(), Some("/main.wdl"));
+        assert_eq!(
+            Workspace::import_source(
+                &DocumentSource::Uri(format!(
+                    "{}/WDL/descriptor/main.wdl",
+                    version_url("b", "x", "v")
+                )),
+                "lib/tasks.wdl"
+            ),
+            DocumentSource::Uri("b/tools/x/versions/v/WDL/descriptor/lib/tasks.wdl".to_owned())
+        );
+    }
+}
@@ -0,0 +1,449 @@
+//! Generation of the skeletons of new tasks and workflows from specifications, e.g. for a
+//! `wdl new task` command. Types and expressions are given as WDL source text and are checked by
+//! parsing the generated document.
+use crate::{
+    model::{Document, DocumentSource, VersionIdentifier},
+    parsers::WdlParser,
+};
+use error_stack::{bail, Result, ResultExt};
+use std::fmt::Write;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ScaffoldError {
+    #[error("invalid identifier {0}")]
+    InvalidName(String),
+    #[error("output {0} has no expression")]
+    MissingExpression(String),
+    #[error("the generated document is not valid WDL")]
+    Parse,
+}
+
+/// An input or output of a task or workflow.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Parameter {
+    pub name: String,
+    /// The WDL type, e.g. `Array[File]`.
+    pub type_: String,
+    /// The default value of an input or the expression of an output, e.g. `"~{sample}.bam"`.
+    pub expression: Option<String>,
+    /// The description that is added to the `parameter_meta` section.
+    pub description: Option<String>,
+}
+
+impl Parameter {
+    pub fn new<N: Into<String>, T: Into<String>>(name: N, type_: T) -> Self {
+        Self {
+            name: name.into(),
+            type_: type_.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_expression<S: Into<String>>(mut self, expression: S) -> Self {
+        self.expression = Some(expression.into());
+        self
+    }
+
+    pub fn with_description<S: Into<String>>(mut self, description: S) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TaskSpec {
+    pub name: String,
+    pub description: Option<String>,
+    pub inputs: Vec<Parameter>,
+    pub outputs: Vec<Parameter>,
+    /// The container image in which the task runs, e.g. `ubuntu:22.04`.
+    pub container: Option<String>,
+    /// The command template. Inputs are referenced with placeholders, e.g. `~{sample}`. The
+    /// common indentation of the lines of the template is removed.
+    pub command: String,
+}
+
+/// A call of a workflow skeleton.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CallSpec {
+    /// The name of the called task.
+    pub task: String,
+    pub alias: Option<String>,
+    /// The names and expressions of the inputs of the call. If empty and the called task is one
+    /// of the tasks of the workflow specification, each input of the task that has the same name
+    /// as an input of the workflow is set to that input.
+    pub inputs: Vec<(String, String)>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WorkflowSpec {
+    pub name: String,
+    pub description: Option<String>,
+    pub inputs: Vec<Parameter>,
+    pub outputs: Vec<Parameter>,
+    pub calls: Vec<CallSpec>,
+    /// Tasks that are generated in the same document as the workflow.
+    pub tasks: Vec<TaskSpec>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScaffoldOptions {
+    pub version: VersionIdentifier,
+    /// The number of spaces per level of indentation.
+    pub indent_width: usize,
+}
+
+impl Default for ScaffoldOptions {
+    fn default() -> Self {
+        Self {
+            version: VersionIdentifier::V1_1,
+            indent_width: 4,
+        }
+    }
+}
+
+fn check_name(name: &str) -> Result<(), ScaffoldError> {
+    let mut chars = name.chars();
+    let valid = matches!(chars.next(), Some(c) if c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        bail!(ScaffoldError::InvalidName(name.to_owned()))
+    }
+    Ok(())
+}
+
+/// Returns `s` as a WDL string literal.
+fn quote(s: &str) -> String {
+    format!(
+        "\"{}\"",
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+struct Renderer<'a> {
+    options: &'a ScaffoldOptions,
+    text: String,
+}
+
+impl<'a> Renderer<'a> {
+    fn new(options: &'a ScaffoldOptions) -> Self {
+        Self {
+            options,
+            text: format!("version {}\n", options.version),
+        }
+    }
+
+    fn line(&mut self, level: usize, line: &str) {
+        if line.is_empty() {
+            self.text.push('\n')
+        } else {
+            let indent = " ".repeat(level * self.options.indent_width);
+            writeln!(self.text, "{}{}", indent, line).unwrap()
+        }
+    }
+
+    fn inputs(&mut self, inputs: &[Parameter]) -> Result<(), ScaffoldError> {
+        if inputs.is_empty() {
+            return Ok(());
+        }
+        self.line(1, "input {");
+        for input in inputs {
+            check_name(&input.name)?;
+            match &input.expression {
+                Some(default) => {
+                    self.line(2, &format!("{} {} = {}", input.type_, input.name, default))
+                }
+                None => self.line(2, &format!("{} {}", input.type_, input.name)),
+            }
+        }
+        self.line(1, "}");
+        Ok(())
+    }
+
+    fn outputs(&mut self, outputs: &[Parameter]) -> Result<(), ScaffoldError> {
+        if outputs.is_empty() {
+            return Ok(());
+        }
+        self.line(1, "output {");
+        for output in outputs {
+            check_name(&output.name)?;
+            let expression = output
+                .expression
+                .as_ref()
+                .ok_or_else(|| ScaffoldError::MissingExpression(output.name.clone()))?;
+            self.line(
+                2,
+                &format!("{} {} = {}", output.type_, output.name, expression),
+            )
+        }
+        self.line(1, "}");
+        Ok(())
+    }
+
+    fn meta(&mut self, description: &Option<String>, parameters: &[&[Parameter]]) {
+        if let Some(description) = description {
+            self.line(1, "meta {");
+            self.line(2, &format!("description: {}", quote(description)));
+            self.line(1, "}");
+        }
+        let described: Vec<&Parameter> = parameters
+            .iter()
+            .flat_map(|parameters| parameters.iter())
+            .filter(|parameter| parameter.description.is_some())
+            .collect();
+        if !described.is_empty() {
+            self.line(1, "parameter_meta {");
+            for parameter in described {
+                let description = parameter.description.as_ref().unwrap();
+                self.line(2, &format!("{}: {}", parameter.name, quote(description)));
+            }
+            self.line(1, "}");
+        }
+    }
+
+    fn task(&mut self, spec: &TaskSpec) -> Result<(), ScaffoldError> {
+        check_name(&spec.name)?;
+        self.line(0, "");
+        self.line(0, &format!("task {} {{", spec.name));
+        self.inputs(&spec.inputs)?;
+        self.line(1, "command <<<");
+        let lines: Vec<&str> = spec.command.lines().collect();
+        let indent = lines
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.len() - line.trim_start().len())
+            .min()
+            .unwrap_or(0);
+        let mut lines: Vec<&str> = lines
+            .iter()
+            .map(|line| line.get(indent..).unwrap_or("").trim_end())
+            .skip_while(|line| line.is_empty())
+            .collect();
+        while lines.last() == Some(&"") {
+            lines.pop();
+        }
+        for line in lines {
+            self.line(2, line)
+        }
+        self.line(1, ">>>");
+        self.outputs(&spec.outputs)?;
+        if let Some(container) = &spec.container {
+            let key = match self.options.version {
+                VersionIdentifier::V1_0 => "docker",
+                VersionIdentifier::V1_1 | VersionIdentifier::Development => "container",
+            };
+            self.line(1, "runtime {");
+            self.line(2, &format!("{}: {}", key, quote(container)));
+            self.line(1, "}");
+        }
+        self.meta(&spec.description, &[&spec.inputs, &spec.outputs]);
+        self.line(0, "}");
+        Ok(())
+    }
+
+    fn workflow(&mut self, spec: &WorkflowSpec) -> Result<(), ScaffoldError> {
+        check_name(&spec.name)?;
+        self.line(0, "");
+        self.line(0, &format!("workflow {} {{", spec.name));
+        self.inputs(&spec.inputs)?;
+        for call in spec.calls.iter() {
+            check_name(&call.task)?;
+            let mut line = format!("call {}", call.task);
+            if let Some(alias) = &call.alias {
+                check_name(alias)?;
+                write!(line, " as {}", alias).unwrap();
+            }
+            let mut inputs: Vec<String> = Vec::new();
+            for (name, expression) in call.inputs.iter() {
+                check_name(name)?;
+                inputs.push(format!("{} = {}", name, expression))
+            }
+            if call.inputs.is_empty() {
+                let task = spec.tasks.iter().find(|task| task.name == call.task);
+                for input in task.iter().flat_map(|task| task.inputs.iter()) {
+                    if spec.inputs.iter().any(|i| i.name == input.name) {
+                        inputs.push(match self.options.version {
+                            VersionIdentifier::V1_0 => format!("{} = {}", input.name, input.name),
+                            VersionIdentifier::V1_1 | VersionIdentifier::Development => {
+                                input.name.clone()
+                            }
+                        })
+                    }
+                }
+            }
+            if !inputs.is_empty() {
+                write!(line, " {{ input: {} }}", inputs.join(", ")).unwrap();
+            }
+            self.line(1, &line)
+        }
+        self.outputs(&spec.outputs)?;
+        self.meta(&spec.description, &[&spec.inputs, &spec.outputs]);
+        self.line(0, "}");
+        for task in spec.tasks.iter() {
+            self.task(task)?
+        }
+        Ok(())
+    }
+}
+
+/// Returns the text of a document that contains the task specified by `spec`.
+pub fn render_task(spec: &TaskSpec, options: &ScaffoldOptions) -> Result<String, ScaffoldError> {
+    let mut renderer = Renderer::new(options);
+    renderer.task(spec)?;
+    Ok(renderer.text)
+}
+
+/// Returns the text of a document that contains the workflow specified by `spec` and its tasks.
+pub fn render_workflow(
+    spec: &WorkflowSpec,
+    options: &ScaffoldOptions,
+) -> Result<String, ScaffoldError> {
+    let mut renderer = Renderer::new(options);
+    renderer.workflow(spec)?;
+    Ok(renderer.text)
+}
+
+/// Generates a document that contains the task specified by `spec`, and parses it with `parser`.
+/// Returns the text and the model of the document.
+pub fn task<P: WdlParser>(
+    parser: &mut P,
+    spec: &TaskSpec,
+    options: &ScaffoldOptions,
+) -> Result<(String, Document), ScaffoldError> {
+    let text = render_task(spec, options)?;
+    let document = parser
+        .parse_text(&text, DocumentSource::Unknown)
+        .change_context(ScaffoldError::Parse)?;
+    Ok((text, document))
+}
+
+/// Generates a document that contains the workflow specified by `spec` and its tasks, and parses
+/// it with `parser`. Returns the text and the model of the document.
+pub fn workflow<P: WdlParser>(
+    parser: &mut P,
+    spec: &WorkflowSpec,
+    options: &ScaffoldOptions,
+) -> Result<(String, Document), ScaffoldError> {
+    let text = render_workflow(spec, options)?;
+    let document = parser
+        .parse_text(&text, DocumentSource::Unknown)
+        .change_context(ScaffoldError::Parse)?;
+    Ok((text, document))
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::parsers::PestParser;
+    use pretty_assertions::assert_eq;
+
+    fn align() -> TaskSpec {
+        TaskSpec {
+            name: "align".to_owned(),
+            description: Some("Aligns \"reads\".".to_owned()),
+            inputs: vec![
+                Parameter::new("reads", "File").with_description("the reads"),
+                Parameter::new("threads", "Int").with_expression("4"),
+            ],
+            outputs: vec![Parameter::new("bam", "File").with_expression("\"out.bam\"")],
+            container: Some("biocontainers/bwa:0.7.17".to_owned()),
+            command: r#"
+                bwa mem -t ~{threads} ref.fa ~{reads} \
+                  > out.bam
+            "#
+            .to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_task() {
+        let mut parser = PestParser::new();
+        let (text, document) = task(&mut parser, &align(), &ScaffoldOptions::default()).unwrap();
+        assert_eq!(
+            text,
+            r#"version 1.1
+
+task align {
+    input {
+        File reads
+        Int threads = 4
+    }
+    command <<<
+        bwa mem -t ~{threads} ref.fa ~{reads} \
+          > out.bam
+    >>>
+    output {
+        File bam = "out.bam"
+    }
+    runtime {
+        container: "biocontainers/bwa:0.7.17"
+    }
+    meta {
+        description: "Aligns \"reads\"."
+    }
+    parameter_meta {
+        reads: "the reads"
+    }
+}
+"#
+        );
+        assert_eq!(document.body.len(), 1);
+
+        let invalid = TaskSpec {
+            name: "1align".to_owned(),
+            ..align()
+        };
+        assert!(matches!(
+            render_task(&invalid, &ScaffoldOptions::default())
+                .unwrap_err()
+                .current_context(),
+            ScaffoldError::InvalidName(_)
+        ));
+        let invalid = TaskSpec {
+            inputs: vec![Parameter::new("reads", "Fil e")],
+            ..align()
+        };
+        assert!(task(&mut parser, &invalid, &ScaffoldOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_workflow() {
+        let spec = WorkflowSpec {
+            name: "main".to_owned(),
+            inputs: vec![Parameter::new("reads", "File")],
+            outputs: vec![Parameter::new("bam", "File").with_expression("align.bam")],
+            calls: vec![CallSpec {
+                task: "align".to_owned(),
+                ..Default::default()
+            }],
+            tasks: vec![align()],
+            ..Default::default()
+        };
+        let options = ScaffoldOptions {
+            version: VersionIdentifier::V1_0,
+            indent_width: 2,
+        };
+        let (text, document) = workflow(&mut PestParser::new(), &spec, &options).unwrap();
+        assert!(text.starts_with(
+            r#"version 1.0
+
+workflow main {
+  input {
+    File reads
+  }
+  call align { input: reads = reads }
+  output {
+    File bam = align.bam
+  }
+}
+
+task align {
+"#
+        ));
+        assert!(text.contains("    docker: \"biocontainers/bwa:0.7.17\"\n"));
+        assert_eq!(document.body.len(), 2);
+    }
+}
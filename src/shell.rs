@@ -0,0 +1,162 @@
+//! The text of task commands with their placeholders replaced by markers, for analysis by shell
+//! linters. Offsets in the text can be mapped back to positions in the source document.
+use crate::model::{Command, Expression, Position, Span, StringPart};
+use std::ops::{Deref, Range};
+
+/// A part of a `ShellTemplate` that corresponds to a part of the command.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TemplateSegment {
+    /// The byte range of the segment in the template text.
+    pub range: Range<usize>,
+    /// The span of the corresponding command part in the source document.
+    pub span: Span,
+    /// The index of the placeholder that the segment replaces, or `None` if the segment is
+    /// literal text of the command.
+    pub placeholder: Option<usize>,
+}
+
+/// The text of a command in which each placeholder has been replaced by a marker.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShellTemplate {
+    pub text: String,
+    pub segments: Vec<TemplateSegment>,
+}
+
+impl ShellTemplate {
+    /// Builds a template for `command`, replacing the placeholder with index `i` and expression
+    /// `e` by `marker(i, e)`.
+    pub fn new<F: FnMut(usize, &Expression) -> String>(command: &Command, mut marker: F) -> Self {
+        let mut text = String::new();
+        let mut segments = Vec::new();
+        let mut placeholders = 0;
+        for part in command.parts.iter() {
+            let start = text.len();
+            let placeholder = match part.deref() {
+                StringPart::Content(s) | StringPart::Escape(s) => {
+                    text.push_str(s);
+                    None
+                }
+                StringPart::Placeholder(expression, _) => {
+                    text.push_str(&marker(placeholders, expression));
+                    placeholders += 1;
+                    Some(placeholders - 1)
+                }
+            };
+            segments.push(TemplateSegment {
+                range: start..text.len(),
+                span: part.span.clone(),
+                placeholder,
+            })
+        }
+        Self { text, segments }
+    }
+
+    /// Returns the marker used by `Command::shell_template` for the placeholder with index `i`.
+    pub fn default_marker(i: usize) -> String {
+        format!("__wdl_placeholder_{}__", i)
+    }
+
+    /// Returns the segment that contains the byte at `offset` of the template text.
+    pub fn segment(&self, offset: usize) -> Option<&TemplateSegment> {
+        self.segments
+            .iter()
+            .find(|segment| segment.range.contains(&offset))
+    }
+
+    /// Returns the position in the source document that corresponds to `offset` in the template
+    /// text. Offsets within a placeholder marker map to the start of the placeholder. An offset
+    /// at the end of the text maps to the end of the last segment.
+    pub fn position(&self, offset: usize) -> Option<Position> {
+        if offset == self.text.len() {
+            return self.segments.last().map(|segment| segment.span.end.clone());
+        }
+        let segment = self.segment(offset)?;
+        if segment.placeholder.is_some() {
+            return Some(segment.span.start.clone());
+        }
+        let mut position = segment.span.start.clone();
+        for c in self.text[segment.range.start..offset].chars() {
+            if c == '\n' {
+                position.line += 1;
+                position.column = 0;
+            } else {
+                position.column += 1;
+            }
+            position.offset += c.len_utf8();
+        }
+        Some(position)
+    }
+
+    /// Returns the span in the source document that corresponds to `range` in the template text.
+    pub fn span(&self, range: Range<usize>) -> Option<Span> {
+        let start = self.position(range.start)?;
+        let end = if range.end > range.start {
+            // the end of a range that ends in a placeholder is the end of the placeholder
+            match self.segment(range.end - 1) {
+                Some(segment) if segment.placeholder.is_some() => segment.span.end.clone(),
+                _ => self.position(range.end)?,
+            }
+        } else {
+            start.clone()
+        };
+        Some(Span { start, end })
+    }
+
+    /// Returns the segments that replace placeholders.
+    pub fn placeholders(&self) -> impl Iterator<Item = &TemplateSegment> {
+        self.segments
+            .iter()
+            .filter(|segment| segment.placeholder.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Anchor, CommandStyle, PlaceholderStyle};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_shell_template() {
+        // echo ~{x}\n  cd ~{y}
+        let command = Command {
+            style: CommandStyle::Heredoc,
+            parts: vec![
+                Anchor::new(
+                    StringPart::Content("echo ".to_owned()),
+                    Span::from_components(1, 4, 20, 1, 9, 25),
+                ),
+                Anchor::new(
+                    StringPart::Placeholder(
+                        Expression::Identifier("x".to_owned()),
+                        PlaceholderStyle::Tilde,
+                    ),
+                    Span::from_components(1, 9, 25, 1, 13, 29),
+                ),
+                Anchor::new(
+                    StringPart::Content("\n  cd ".to_owned()),
+                    Span::from_components(1, 13, 29, 2, 5, 35),
+                ),
+                Anchor::new(
+                    StringPart::Placeholder(
+                        Expression::Identifier("y".to_owned()),
+                        PlaceholderStyle::Tilde,
+                    ),
+                    Span::from_components(2, 5, 35, 2, 9, 39),
+                ),
+            ],
+        };
+        let template = command.shell_template();
+        assert_eq!(
+            template.text,
+            "echo __wdl_placeholder_0__\n  cd __wdl_placeholder_1__"
+        );
+        let cd = template.text.find("cd").unwrap();
+        assert_eq!(template.position(cd), Some(Position::new(2, 2, 32)));
+        assert_eq!(
+            template.span(cd..template.text.len()),
+            Some(Span::from_components(2, 2, 32, 2, 9, 39))
+        );
+        assert_eq!(template.placeholders().count(), 2);
+    }
+}
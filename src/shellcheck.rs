@@ -0,0 +1,244 @@
+//! Runs [ShellCheck](https://www.shellcheck.net) on the commands of tasks. Each command is checked
+//! as a bash script with its placeholders replaced by dummy values, and the positions of the
+//! problems that ShellCheck reports are mapped back to the source document.
+use crate::{
+    diagnostics::{Diagnostic, Severity},
+    model::{Command, Document, DocumentElement, TaskElement},
+    shell::ShellTemplate,
+};
+use error_stack::{bail, IntoReport, Result, ResultExt};
+use serde_json::Value as JsonValue;
+use std::{
+    io::Write,
+    ops::Deref,
+    path::PathBuf,
+    process::{Command as Process, Stdio},
+};
+use thiserror::Error;
+
+/// The width of a tab in the columns that ShellCheck reports.
+const TAB_WIDTH: usize = 8;
+
+#[derive(Error, Debug)]
+pub enum ShellCheckError {
+    #[error("Failed to run shellcheck")]
+    Run,
+    #[error("shellcheck failed: {0}")]
+    Failed(String),
+    #[error("Invalid shellcheck output")]
+    Output,
+}
+
+/// Runs ShellCheck on task commands. The diagnostics that are returned have codes of the form
+/// `SC2086`, i.e. the codes of the ShellCheck checks.
+#[derive(Debug)]
+pub struct ShellCheck {
+    program: PathBuf,
+    excluded: Vec<u32>,
+}
+
+impl Default for ShellCheck {
+    fn default() -> Self {
+        Self {
+            program: PathBuf::from("shellcheck"),
+            excluded: Vec::new(),
+        }
+    }
+}
+
+impl ShellCheck {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the path of the `shellcheck` executable, which by default is found on the `PATH`.
+    pub fn with_program<P: Into<PathBuf>>(mut self, program: P) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    /// Excludes the check with code `SC<code>`.
+    pub fn exclude(mut self, code: u32) -> Self {
+        self.excluded.push(code);
+        self
+    }
+
+    /// Checks the commands of all tasks in `document`.
+    pub fn check_document(&self, document: &Document) -> Result<Vec<Diagnostic>, ShellCheckError> {
+        let mut diagnostics = Vec::new();
+        for element in document.body_iter() {
+            if let DocumentElement::Task(task) = element {
+                for element in task.body.iter() {
+                    if let TaskElement::Command(command) = element.deref() {
+                        diagnostics.extend(self.check_command(command)?)
+                    }
+                }
+            }
+        }
+        Ok(diagnostics)
+    }
+
+    pub fn check_command(&self, command: &Command) -> Result<Vec<Diagnostic>, ShellCheckError> {
+        let template = ShellTemplate::new(command, |i, _| dummy_value(i));
+        let mut process = Process::new(&self.program);
+        process.args(["--shell=bash", "--format=json1"]);
+        if !self.excluded.is_empty() {
+            let excluded: Vec<String> = self.excluded.iter().map(|code| code.to_string()).collect();
+            process.arg(format!("--exclude={}", excluded.join(",")));
+        }
+        let mut child = process
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .into_report()
+            .change_context(ShellCheckError::Run)?;
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(template.text.as_bytes())
+            .into_report()
+            .change_context(ShellCheckError::Run)?;
+        let output = child
+            .wait_with_output()
+            .into_report()
+            .change_context(ShellCheckError::Run)?;
+        // shellcheck exits with status 1 if it finds any problems
+        if !matches!(output.status.code(), Some(0) | Some(1)) {
+            bail!(ShellCheckError::Failed(
+                String::from_utf8_lossy(&output.stderr).trim().to_owned()
+            ))
+        }
+        diagnostics_from_json(&template, &String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+/// Returns the dummy value that replaces the placeholder with index `i`. Dummy values are plain
+/// words, so ShellCheck treats them like the values of the placeholders after instantiation.
+fn dummy_value(i: usize) -> String {
+    format!("WDL_PLACEHOLDER_{}", i)
+}
+
+/// Converts ShellCheck's JSON output for `template` into diagnostics.
+fn diagnostics_from_json(
+    template: &ShellTemplate,
+    json: &str,
+) -> Result<Vec<Diagnostic>, ShellCheckError> {
+    let output: JsonValue = serde_json::from_str(json)
+        .into_report()
+        .change_context(ShellCheckError::Output)?;
+    let comments = output
+        .get("comments")
+        .and_then(|comments| comments.as_array())
+        .ok_or(ShellCheckError::Output)?;
+    let mut diagnostics = Vec::new();
+    for comment in comments {
+        let field = |name: &str| {
+            comment
+                .get(name)
+                .and_then(|value| value.as_u64())
+                .map(|value| value as usize)
+                .ok_or(ShellCheckError::Output)
+        };
+        let start = offset(&template.text, field("line")?, field("column")?);
+        let end = offset(&template.text, field("endLine")?, field("endColumn")?);
+        let span = match start
+            .zip(end)
+            .and_then(|(start, end)| template.span(start..end))
+        {
+            Some(span) => span,
+            None => continue,
+        };
+        let severity = match comment.get("level").and_then(|level| level.as_str()) {
+            Some("error") => Severity::Error,
+            Some("warning") => Severity::Warning,
+            _ => Severity::Note,
+        };
+        let message = comment
+            .get("message")
+            .and_then(|message| message.as_str())
+            .unwrap_or_default();
+        diagnostics.push(Diagnostic::new(
+            severity,
+            format!("SC{}", field("code")?),
+            message,
+            span,
+        ))
+    }
+    Ok(diagnostics)
+}
+
+/// Returns the byte offset in `text` of the one-based `line` and `column` reported by ShellCheck,
+/// in which tabs are expanded to `TAB_WIDTH` columns.
+fn offset(text: &str, line: usize, column: usize) -> Option<usize> {
+    let mut line_start = 0;
+    for _ in 1..line {
+        line_start += text[line_start..].find('\n')? + 1;
+    }
+    let mut width = 1;
+    for (i, c) in text[line_start..].char_indices() {
+        if width >= column || c == '\n' {
+            return Some(line_start + i);
+        }
+        width = match c {
+            '\t' => ((width - 1) / TAB_WIDTH + 1) * TAB_WIDTH + 1,
+            _ => width + 1,
+        };
+    }
+    Some(text.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Anchor, CommandStyle, Expression, PlaceholderStyle, Span, StringPart};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_offset() {
+        let text = "a\n\tb c\n";
+        assert_eq!(offset(text, 1, 1), Some(0));
+        assert_eq!(offset(text, 2, 9), Some(3));
+        assert_eq!(offset(text, 2, 11), Some(5));
+        assert_eq!(offset(text, 3, 1), Some(7));
+    }
+
+    #[test]
+    fn test_diagnostics_from_json() {
+        // cd ~{dir}\n
+        let command = Command {
+            style: CommandStyle::Heredoc,
+            parts: vec![
+                Anchor::new(
+                    StringPart::Content("cd ".to_owned()),
+                    Span::from_components(3, 4, 40, 3, 7, 43),
+                ),
+                Anchor::new(
+                    StringPart::Placeholder(
+                        Expression::Identifier("dir".to_owned()),
+                        PlaceholderStyle::Tilde,
+                    ),
+                    Span::from_components(3, 7, 43, 3, 13, 49),
+                ),
+                Anchor::new(
+                    StringPart::Content("\n".to_owned()),
+                    Span::from_components(3, 13, 49, 4, 0, 50),
+                ),
+            ],
+        };
+        let template = ShellTemplate::new(&command, |i, _| dummy_value(i));
+        let json = r#"{"comments": [{"file": "-", "line": 1, "endLine": 1, "column": 1,
+            "endColumn": 21, "level": "warning", "code": 2164,
+            "message": "Use 'cd ... || exit' in case cd fails."}]}"#;
+        let diagnostics = diagnostics_from_json(&template, json).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "SC2164");
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(
+            diagnostics[0].span,
+            Span::from_components(3, 4, 40, 3, 13, 49)
+        );
+    }
+}
@@ -0,0 +1,274 @@
+//! Source maps, which map spans of generated text, such as the output of a transformation or of a
+//! set of edits, back to spans of the documents from which the text was derived, so that
+//! diagnostics about the generated text can be reported at their original locations.
+use crate::{
+    diagnostics::Diagnostic,
+    edit::{apply_edits, EditError, TextEdit},
+    model::{DocumentSource, Position, Span},
+};
+use error_stack::Result;
+use std::ops::Range;
+
+/// A range of generated text that was derived from a span of a source document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mapping {
+    /// The byte range of the generated text.
+    pub range: Range<usize>,
+    pub source: DocumentSource,
+    /// The span of the source document.
+    pub span: Span,
+    /// Whether the generated text is an unchanged copy of the text of `span`. Positions within a
+    /// verbatim mapping are mapped exactly, while positions within any other mapping are mapped
+    /// to the whole of `span`.
+    pub verbatim: bool,
+}
+
+/// A set of mappings from ranges of generated text to spans of source documents. Mappings may be
+/// nested, e.g. a verbatim mapping within a mapping of a whole element, in which case a span of
+/// the generated text is mapped with the smallest mapping that contains it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    mappings: Vec<Mapping>,
+}
+
+/// Returns the position of `offset` in `text`.
+pub(crate) fn position_at(text: &str, offset: usize) -> Position {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    Position::new(
+        before.matches('\n').count(),
+        before[line_start..].chars().count(),
+        offset,
+    )
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a mapping from `range` of the generated text to `span` of the document `source`.
+    /// Empty ranges are ignored.
+    pub fn add(&mut self, range: Range<usize>, source: DocumentSource, span: Span, verbatim: bool) {
+        if !range.is_empty() {
+            self.mappings.push(Mapping {
+                range,
+                source,
+                span,
+                verbatim,
+            })
+        }
+    }
+
+    /// Adds a verbatim mapping for the text of `original` in `range`, where `original` is the
+    /// text of the document `source`, that was copied to offset `at` of the generated text.
+    pub fn add_verbatim(
+        &mut self,
+        at: usize,
+        source: DocumentSource,
+        original: &str,
+        range: Range<usize>,
+    ) {
+        let span = Span {
+            start: position_at(original, range.start),
+            end: position_at(original, range.end),
+        };
+        self.add(at..at + range.len(), source, span, true)
+    }
+
+    pub fn mappings(&self) -> &[Mapping] {
+        &self.mappings
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    /// Adds the mappings of `other`, a source map of text that was inserted at offset `at` of
+    /// the generated text.
+    pub fn append(&mut self, other: SourceMap, at: usize) {
+        self.mappings
+            .extend(other.mappings.into_iter().map(|mapping| Mapping {
+                range: mapping.range.start + at..mapping.range.end + at,
+                ..mapping
+            }))
+    }
+
+    /// Applies `edits` to `text`, the text of the document `source`, and returns the edited text
+    /// and a source map from it to `text`. The text that is not edited is mapped verbatim, and
+    /// the text of each edit is mapped to the span that it replaces.
+    pub fn from_edits(
+        text: &str,
+        source: &DocumentSource,
+        edits: &[TextEdit],
+    ) -> Result<(String, Self), EditError> {
+        Self::from_edits_in(text, 0..text.len(), source, edits)
+    }
+
+    /// Like `from_edits`, but returns only the edited text of `range` of `text`. The spans of
+    /// `edits` refer to `text` and must be within `range`.
+    pub(crate) fn from_edits_in(
+        text: &str,
+        range: Range<usize>,
+        source: &DocumentSource,
+        edits: &[TextEdit],
+    ) -> Result<(String, Self), EditError> {
+        let mut edits: Vec<TextEdit> = edits.to_vec();
+        edits.sort_by_key(|edit| (edit.span.start.offset, edit.span.end.offset));
+        let rebased: Vec<TextEdit> = edits
+            .iter()
+            .cloned()
+            .map(|mut edit| {
+                edit.span.start.offset -= range.start;
+                edit.span.end.offset -= range.start;
+                edit
+            })
+            .collect();
+        let edited = apply_edits(&text[range.clone()], &rebased)?;
+        let mut source_map = Self::new();
+        // the offsets in `text` and in `edited` of the end of the previous edit
+        let (mut offset, mut generated) = (range.start, 0);
+        for edit in edits {
+            let start = edit.span.start.offset;
+            source_map.add_verbatim(generated, source.clone(), text, offset..start);
+            generated += start - offset;
+            source_map.add(
+                generated..generated + edit.text.len(),
+                source.clone(),
+                edit.span.clone(),
+                false,
+            );
+            generated += edit.text.len();
+            offset = edit.span.end.offset;
+        }
+        source_map.add_verbatim(generated, source.clone(), text, offset..range.end);
+        Ok((edited, source_map))
+    }
+
+    /// Returns the source document and span from which `span` of `generated`, the generated
+    /// text, was derived. If `span` is not contained by a single mapping, it is mapped with the
+    /// mapping that contains its start. Returns `None` if no mapping contains the start of
+    /// `span`, i.e. if it is in text that was not derived from any document.
+    pub fn original(&self, generated: &str, span: &Span) -> Option<(&DocumentSource, Span)> {
+        let (start, end) = (span.start.offset, span.end.offset);
+        let contains = |mapping: &&Mapping| {
+            mapping.range.start <= start && end <= mapping.range.end && start < mapping.range.end
+        };
+        if let Some(mapping) = self
+            .mappings
+            .iter()
+            .filter(contains)
+            .min_by_key(|mapping| mapping.range.len())
+        {
+            if !mapping.verbatim {
+                return Some((&mapping.source, mapping.span.clone()));
+            }
+            let position = |offset: usize| {
+                let mut position = mapping.span.start.clone();
+                for c in generated[mapping.range.start..offset].chars() {
+                    if c == '\n' {
+                        position.line += 1;
+                        position.column = 0;
+                    } else {
+                        position.column += 1;
+                    }
+                    position.offset += c.len_utf8();
+                }
+                position
+            };
+            return Some((
+                &mapping.source,
+                Span {
+                    start: position(start),
+                    end: position(end),
+                },
+            ));
+        }
+        self.mappings
+            .iter()
+            .filter(|mapping| mapping.range.contains(&start))
+            .min_by_key(|mapping| mapping.range.len())
+            .map(|mapping| (&mapping.source, mapping.span.clone()))
+    }
+
+    /// Returns `diagnostic`, which was raised against `generated`, with its span mapped to the
+    /// source document from which it was derived, and that document. Related spans that are not
+    /// derived from the same document are removed, as are the fixes of the diagnostic, which
+    /// apply to the generated text. Returns `None` if the span of the diagnostic cannot be
+    /// mapped.
+    pub fn remap(
+        &self,
+        generated: &str,
+        diagnostic: &Diagnostic,
+    ) -> Option<(DocumentSource, Diagnostic)> {
+        let (source, span) = self.original(generated, &diagnostic.span)?;
+        let mut remapped = Diagnostic {
+            span,
+            related: Vec::new(),
+            fixes: Vec::new(),
+            ..diagnostic.clone()
+        };
+        for related in diagnostic.related.iter() {
+            if let Some((related_source, span)) = self.original(generated, &related.span) {
+                if related_source == source {
+                    remapped = remapped.with_related(span, related.message.clone())
+                }
+            }
+        }
+        Some((source.clone(), remapped))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_source_map() {
+        let source = DocumentSource::File(PathBuf::from("/w/main.wdl"));
+        let text = "version 1.1\ntask a {\n    command <<< >>>\n}\n";
+        let edits = vec![
+            TextEdit::replace(Span::from_components(1, 5, 17, 1, 6, 18), "align"),
+            TextEdit::insert(Position::new(1, 0, 12), "# generated\n"),
+        ];
+        let (edited, source_map) = SourceMap::from_edits(text, &source, &edits).unwrap();
+        assert_eq!(
+            edited,
+            "version 1.1\n# generated\ntask align {\n    command <<< >>>\n}\n"
+        );
+        let span_of = |pattern: &str| {
+            let start = edited.find(pattern).unwrap();
+            Span {
+                start: position_at(&edited, start),
+                end: position_at(&edited, start + pattern.len()),
+            }
+        };
+
+        // verbatim text is mapped exactly
+        let (mapped_source, span) = source_map.original(&edited, &span_of("command")).unwrap();
+        assert_eq!(mapped_source, &source);
+        assert_eq!(span, Span::from_components(2, 4, 25, 2, 11, 32));
+        assert_eq!(&text[span.start.offset..span.end.offset], "command");
+
+        // edited text is mapped to the span it replaced
+        let (_, span) = source_map.original(&edited, &span_of("align")).unwrap();
+        assert_eq!(span, Span::from_components(1, 5, 17, 1, 6, 18));
+        let (_, span) = source_map.original(&edited, &span_of("generated")).unwrap();
+        assert_eq!(span, Span::from_components(1, 0, 12, 1, 0, 12));
+
+        let diagnostic = Diagnostic::warning("test", "task name", span_of("align"))
+            .with_related(span_of("command"), "command");
+        let (mapped_source, remapped) = source_map.remap(&edited, &diagnostic).unwrap();
+        assert_eq!(mapped_source, source);
+        assert_eq!(remapped.span, Span::from_components(1, 5, 17, 1, 6, 18));
+        assert_eq!(
+            remapped.related[0].span,
+            Span::from_components(2, 4, 25, 2, 11, 32)
+        );
+        assert!(SourceMap::new()
+            .original(&edited, &span_of("align"))
+            .is_none());
+    }
+}
@@ -0,0 +1,53 @@
+//! The WDL documents that are used to test this crate, so that downstream crates can test against
+//! the same corpus (e.g. with `testing::assert_model_snapshot`). Requires the `testdata` feature.
+use crate::{
+    model::{Document, DocumentSource},
+    parsers::{WdlParser, WdlParserError},
+};
+use error_stack::Result;
+use std::path::PathBuf;
+
+/// A bundled test document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TestDocument {
+    /// The name of the document, which is its file name without the `.wdl` extension.
+    pub name: &'static str,
+    pub text: &'static str,
+}
+
+impl TestDocument {
+    /// Returns the path of the document within the source of this crate.
+    pub fn path(&self) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("resources")
+            .join("test")
+            .join(format!("{}.wdl", self.name))
+    }
+
+    /// Parses the document with `parser`. The source of the model is the path of the document,
+    /// but the text is not read from it.
+    pub fn parse<P: WdlParser>(&self, parser: &mut P) -> Result<Document, WdlParserError> {
+        parser.parse_text(self.text, DocumentSource::File(self.path()))
+    }
+}
+
+/// A WDL 1.1 document with imports, structs, a workflow, and a task, which covers most of the
+/// syntax of document, workflow, and task elements.
+pub const COMPREHENSIVE: TestDocument = TestDocument {
+    name: "comprehensive",
+    text: include_str!("../resources/test/comprehensive.wdl"),
+};
+
+/// A WDL 1.1 workflow whose declarations cover each kind of literal and expression.
+pub const EXPRESSIONS: TestDocument = TestDocument {
+    name: "expressions",
+    text: include_str!("../resources/test/expressions.wdl"),
+};
+
+/// All of the bundled test documents.
+pub const ALL: &[TestDocument] = &[COMPREHENSIVE, EXPRESSIONS];
+
+/// Returns the bundled test document named `name`, if any.
+pub fn get(name: &str) -> Option<&'static TestDocument> {
+    ALL.iter().find(|document| document.name == name)
+}
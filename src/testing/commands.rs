@@ -0,0 +1,393 @@
+//! Rendering of task commands from input fixtures and comparison with golden files, so that the
+//! construction of commands can be tested without running an engine.
+use crate::{
+    analysis,
+    eval::{self, Environment},
+    model::{Command, Expression, InputDeclaration, Span, StringPart, Task, TaskElement, Type},
+    values::WdlValue,
+};
+use error_stack::{bail, IntoReport, Report, Result, ResultExt};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use std::{
+    fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// If this environment variable is set, `assert_golden_commands` updates golden files rather
+/// than comparing against them.
+pub const UPDATE_GOLDEN_VAR: &str = "WDL_UPDATE_GOLDEN";
+
+#[derive(Error, Debug)]
+pub enum CommandTestError {
+    #[error("Invalid fixtures: {0}")]
+    Fixtures(String),
+    #[error("YAML fixtures require the yaml feature")]
+    Yaml,
+    #[error("Missing value for required input {0}")]
+    MissingInput(String),
+    #[error("Invalid value for input {0}")]
+    Input(String),
+    #[error("Error evaluating {0}")]
+    Eval(String),
+    #[error("Task {0} does not have a command")]
+    MissingCommand(String),
+    #[error("IO error accessing {0}")]
+    IO(PathBuf),
+}
+
+/// A named set of input values for a task.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandFixture {
+    pub name: String,
+    pub inputs: JsonMap<String, JsonValue>,
+}
+
+/// Parses fixtures from a JSON object that maps fixture names to objects of input values, e.g.
+/// `{"paired": {"reads": ["a.fq", "b.fq"]}}`.
+pub fn fixtures_from_json(text: &str) -> Result<Vec<CommandFixture>, CommandTestError> {
+    let json: JsonValue = serde_json::from_str(text)
+        .into_report()
+        .change_context_lazy(|| CommandTestError::Fixtures("invalid JSON".to_owned()))?;
+    fixtures_from_value(json)
+}
+
+/// Parses fixtures from YAML with the same structure as for `fixtures_from_json`.
+#[cfg(feature = "yaml")]
+pub fn fixtures_from_yaml(text: &str) -> Result<Vec<CommandFixture>, CommandTestError> {
+    let json: JsonValue = serde_yaml::from_str(text)
+        .into_report()
+        .change_context_lazy(|| CommandTestError::Fixtures("invalid YAML".to_owned()))?;
+    fixtures_from_value(json)
+}
+
+fn fixtures_from_value(json: JsonValue) -> Result<Vec<CommandFixture>, CommandTestError> {
+    let fixtures = match json {
+        JsonValue::Object(fixtures) => fixtures,
+        _ => bail!(CommandTestError::Fixtures(
+            "expected an object of fixtures".to_owned()
+        )),
+    };
+    fixtures
+        .into_iter()
+        .map(|(name, inputs)| match inputs {
+            JsonValue::Object(inputs) => Ok(CommandFixture { name, inputs }),
+            _ => bail!(CommandTestError::Fixtures(format!(
+                "the inputs of fixture {} are not an object",
+                name
+            ))),
+        })
+        .collect()
+}
+
+/// Reads fixtures from the file at `path`, which is parsed as YAML if its extension is `yaml` or
+/// `yml` and otherwise as JSON.
+pub fn read_fixtures<P: AsRef<Path>>(path: P) -> Result<Vec<CommandFixture>, CommandTestError> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)
+        .into_report()
+        .change_context_lazy(|| CommandTestError::IO(path.to_owned()))?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        #[cfg(feature = "yaml")]
+        Some("yaml") | Some("yml") => fixtures_from_yaml(&text),
+        #[cfg(not(feature = "yaml"))]
+        Some("yaml") | Some("yml") => bail!(CommandTestError::Yaml),
+        _ => fixtures_from_json(&text),
+    }
+}
+
+/// Renders the command of `task` with its inputs bound to the values in `inputs`, which are
+/// coerced to the declared types of the inputs. Inputs that are missing from `inputs` take their
+/// default values, or are undefined if they are optional. Common leading whitespace is removed
+/// from the lines of the command, as are a leading line and trailing whitespace that consist only
+/// of whitespace.
+pub fn render_command(
+    task: &Task,
+    inputs: &JsonMap<String, JsonValue>,
+) -> Result<String, CommandTestError> {
+    let mut env = Environment::new();
+    let mut command = None;
+    for element in task.body.iter() {
+        match element.deref() {
+            TaskElement::Input(input) => {
+                for decl in input.declarations.iter() {
+                    let name = decl.name().as_str();
+                    let value = match (inputs.get(name), decl.deref()) {
+                        (Some(json), decl) => WdlValue::from_json_typed(json, decl.type_())
+                            .change_context_lazy(|| CommandTestError::Input(name.to_owned()))?,
+                        (None, InputDeclaration::Bound(decl)) => {
+                            evaluate(&decl.expression, &env, name)?
+                                .coerce(&decl.type_)
+                                .change_context_lazy(|| CommandTestError::Input(name.to_owned()))?
+                        }
+                        (None, InputDeclaration::Unbound(decl)) => match decl.type_.deref() {
+                            Type::Optional(_) => WdlValue::None,
+                            _ => bail!(CommandTestError::MissingInput(name.to_owned())),
+                        },
+                    };
+                    env.bind(name, value)
+                }
+            }
+            TaskElement::Declaration(decl) => {
+                let value = evaluate(&decl.expression, &env, decl.name.as_str())?;
+                env.bind(decl.name.as_str(), value)
+            }
+            TaskElement::Command(c) => command = Some(c),
+            _ => (),
+        }
+    }
+    let command = command.ok_or_else(|| {
+        Report::new(CommandTestError::MissingCommand(
+            task.name.as_str().to_owned(),
+        ))
+    })?;
+    render(command, &env)
+}
+
+fn evaluate(
+    expression: &Expression,
+    env: &Environment,
+    name: &str,
+) -> Result<WdlValue, CommandTestError> {
+    eval::evaluate(expression, env).change_context_lazy(|| CommandTestError::Eval(name.to_owned()))
+}
+
+/// Returns the number of leading whitespace characters that are common to all lines of `command`
+/// that contain anything other than whitespace. Placeholders count as non-whitespace.
+fn common_indent(command: &Command) -> usize {
+    let mut indent: Option<usize> = None;
+    let mut current = Some(0);
+    let mut first_line = true;
+    for part in command.parts.iter() {
+        match part.deref() {
+            StringPart::Content(s) | StringPart::Escape(s) => {
+                for c in s.chars() {
+                    match (c, current) {
+                        ('\n', _) => {
+                            current = Some(0);
+                            first_line = false;
+                        }
+                        (' ' | '\t', Some(n)) => current = Some(n + 1),
+                        (_, Some(n)) => {
+                            if !first_line {
+                                indent = Some(indent.map_or(n, |indent| indent.min(n)));
+                            }
+                            current = None
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            StringPart::Placeholder(_, _) => {
+                if let (Some(n), false) = (current, first_line) {
+                    indent = Some(indent.map_or(n, |indent| indent.min(n)));
+                }
+                current = None
+            }
+        }
+    }
+    indent.unwrap_or(0)
+}
+
+fn render(command: &Command, env: &Environment) -> Result<String, CommandTestError> {
+    let indent = common_indent(command);
+    let mut text = String::new();
+    // the number of indentation characters that remain to be removed from the current line
+    let mut strip = 0;
+    for part in command.parts.iter() {
+        match part.deref() {
+            StringPart::Content(s) | StringPart::Escape(s) => {
+                for c in s.chars() {
+                    if c == '\n' {
+                        strip = indent;
+                    } else if strip > 0 && (c == ' ' || c == '\t') {
+                        strip -= 1;
+                        continue;
+                    } else {
+                        strip = 0;
+                    }
+                    text.push(c)
+                }
+            }
+            StringPart::Placeholder(expression, _) => {
+                strip = 0;
+                match eval::evaluate(expression, env) {
+                    Ok(WdlValue::None) => (),
+                    Ok(value) => text.push_str(&value.to_string()),
+                    // as specified by WDL, a placeholder whose evaluation fails due to an
+                    // undefined value is replaced by the empty string
+                    Err(_) if references_undefined(expression, &part.span, env) => (),
+                    Err(err) => {
+                        return Err(
+                            err.change_context(CommandTestError::Eval(expression.to_string()))
+                        )
+                    }
+                }
+            }
+        }
+    }
+    // remove the first line if it is blank, and any trailing whitespace
+    if let Some((first, rest)) = text.split_once('\n') {
+        if first.trim().is_empty() {
+            text = rest.to_owned();
+        }
+    }
+    let len = text.trim_end().len();
+    text.truncate(len);
+    Ok(text)
+}
+
+/// Returns whether `expression` refers to a declaration whose value is `None`.
+fn references_undefined(expression: &Expression, span: &Span, env: &Environment) -> bool {
+    let mut undefined = false;
+    analysis::walk_expression(expression, span, &mut |e, _| {
+        if let Expression::Identifier(name) = e {
+            undefined |= env.get(name) == Some(&WdlValue::None)
+        }
+    });
+    undefined
+}
+
+/// A rendered command that differs from its golden file.
+#[derive(Debug, PartialEq)]
+pub struct GoldenMismatch {
+    pub fixture: String,
+    pub path: PathBuf,
+    /// The contents of the golden file, or `None` if it does not exist.
+    pub expected: Option<String>,
+    pub actual: String,
+}
+
+/// Renders the command of `task` for each of `fixtures` and compares it with the golden file
+/// `<golden_dir>/<task>/<fixture>.sh`. If `update` is true, golden files are written rather than
+/// compared. Returns the mismatches.
+pub fn check_golden_commands<P: AsRef<Path>>(
+    task: &Task,
+    fixtures: &[CommandFixture],
+    golden_dir: P,
+    update: bool,
+) -> Result<Vec<GoldenMismatch>, CommandTestError> {
+    let dir = golden_dir.as_ref().join(task.name.as_str());
+    let mut mismatches = Vec::new();
+    for fixture in fixtures {
+        let actual = render_command(task, &fixture.inputs)?;
+        let path = dir.join(format!("{}.sh", fixture.name));
+        if update {
+            fs::create_dir_all(&dir)
+                .and_then(|_| fs::write(&path, format!("{}\n", actual)))
+                .into_report()
+                .change_context_lazy(|| CommandTestError::IO(path.clone()))?;
+            continue;
+        }
+        let expected = fs::read_to_string(&path).ok();
+        if expected.as_deref().map(|expected| expected.trim_end()) != Some(actual.as_str()) {
+            mismatches.push(GoldenMismatch {
+                fixture: fixture.name.clone(),
+                path,
+                expected,
+                actual,
+            })
+        }
+    }
+    Ok(mismatches)
+}
+
+/// Checks the commands of `task` against golden files as with `check_golden_commands`, and panics
+/// if any differ. Golden files are updated instead if the `WDL_UPDATE_GOLDEN` environment
+/// variable is set.
+pub fn assert_golden_commands<P: AsRef<Path>>(
+    task: &Task,
+    fixtures: &[CommandFixture],
+    golden_dir: P,
+) {
+    let update = std::env::var_os(UPDATE_GOLDEN_VAR).is_some();
+    let mismatches = match check_golden_commands(task, fixtures, golden_dir, update) {
+        Ok(mismatches) => mismatches,
+        Err(err) => panic!("{:?}", err),
+    };
+    if let Some(mismatch) = mismatches.first() {
+        panic!(
+            "command of task {} for fixture {} does not match {}\nexpected:\n{}\nactual:\n{}\n\
+             (set {} to update golden files)",
+            task.name.as_str(),
+            mismatch.fixture,
+            mismatch.path.display(),
+            mismatch.expected.as_deref().unwrap_or("<missing>"),
+            mismatch.actual,
+            UPDATE_GOLDEN_VAR
+        )
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::{DocumentElement, DocumentSource},
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    const TEXT: &str = r#"version 1.1
+
+task align {
+    input {
+        File reads
+        String? read_group
+        Int threads = 4
+    }
+    String prefix = basename(reads, ".fq")
+    command <<<
+        bwa mem \
+            -t ~{threads} ~{"-R " + read_group} \
+            ref.fa ~{reads} > ~{prefix}.sam
+    >>>
+}
+"#;
+
+    #[test]
+    fn test_render_command() {
+        let doc = PestParser::new()
+            .parse_text(TEXT, DocumentSource::Unknown)
+            .unwrap();
+        let task = match doc.body_iter().next() {
+            Some(DocumentElement::Task(task)) => task,
+            _ => panic!("expected a task"),
+        };
+        let fixtures = fixtures_from_json(
+            r#"{
+                "default": {"reads": "s1.fq"},
+                "read_group": {"reads": "s2.fq", "read_group": "RG1", "threads": 8}
+            }"#,
+        )
+        .unwrap();
+        let rendered: Vec<String> = fixtures
+            .iter()
+            .map(|fixture| render_command(task, &fixture.inputs).unwrap())
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "bwa mem \\\n    -t 4  \\\n    ref.fa s1.fq > s1.sam",
+                "bwa mem \\\n    -t 8 -R RG1 \\\n    ref.fa s2.fq > s2.sam",
+            ]
+        );
+        assert!(matches!(
+            render_command(task, &JsonMap::new())
+                .unwrap_err()
+                .current_context(),
+            CommandTestError::MissingInput(_)
+        ));
+        let dir = std::env::temp_dir().join(format!("wdl-golden-{}", std::process::id()));
+        assert_eq!(
+            check_golden_commands(task, &fixtures, &dir, false)
+                .unwrap()
+                .len(),
+            2
+        );
+        check_golden_commands(task, &fixtures, &dir, true).unwrap();
+        assert_golden_commands(task, &fixtures, &dir);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,22 @@
+//! Utilities for testing WDL documents and the parsers.
+mod commands;
+#[cfg(all(feature = "pest", feature = "tree-sitter"))]
+mod parsers;
+#[cfg(feature = "testdata")]
+mod snapshots;
+
+#[cfg(feature = "yaml")]
+pub use commands::fixtures_from_yaml;
+pub use commands::{
+    assert_golden_commands, check_golden_commands, fixtures_from_json, read_fixtures,
+    render_command, CommandFixture, CommandTestError, GoldenMismatch, UPDATE_GOLDEN_VAR,
+};
+#[cfg(all(feature = "pest", feature = "tree-sitter"))]
+pub use parsers::{
+    assert_parsers_agree, compare_parsers, first_divergence, parse_with_both, Divergence,
+};
+#[cfg(feature = "testdata")]
+pub use snapshots::{
+    assert_model_snapshot, assert_model_snapshot_at, check_model_snapshot, model_snapshot,
+    SnapshotError, SnapshotMismatch, SNAPSHOT_DIR,
+};
@@ -0,0 +1,232 @@
+//! Utilities for verifying that the pest and tree-sitter parsers produce identical models.
+//!
+//! Models are compared structurally, using their `Debug` representations. When the models differ,
+//! the first divergence is reported along with its path within the `Document` (e.g.
+//! `body[0].element.name.element`) and the span of the innermost model element that contains it.
+use crate::{
+    model::{Document, DocumentSource, Span},
+    parsers::{PestParser, TreeSitterParser, WdlParser, WdlParserError},
+};
+use error_stack::Result;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// The first point at which two models differ.
+#[derive(Debug, PartialEq)]
+pub struct Divergence {
+    /// Path to the divergent value within the `Document`.
+    pub path: String,
+    /// `Debug` representation of the value in the model produced by the pest parser.
+    pub pest: String,
+    /// `Debug` representation of the value in the model produced by the tree-sitter parser.
+    pub tree_sitter: String,
+    /// Span of the innermost element containing the value in the pest model.
+    pub pest_span: Option<Span>,
+    /// Span of the innermost element containing the value in the tree-sitter model.
+    pub tree_sitter_span: Option<Span>,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let fmt_span = |span: &Option<Span>| {
+            span.as_ref()
+                .map(|span| format!(" ({})", span))
+                .unwrap_or_default()
+        };
+        writeln!(f, "models differ at {}", self.path)?;
+        writeln!(
+            f,
+            "  pest:        {}{}",
+            self.pest,
+            fmt_span(&self.pest_span)
+        )?;
+        write!(
+            f,
+            "  tree-sitter: {}{}",
+            self.tree_sitter,
+            fmt_span(&self.tree_sitter_span)
+        )
+    }
+}
+
+/// Parses `text` with both parsers and returns the pest and tree-sitter models, respectively.
+pub fn parse_with_both<Text: AsRef<str>>(
+    text: Text,
+) -> Result<(Document, Document), WdlParserError> {
+    let text = text.as_ref();
+    let pest = PestParser::new().parse_text(text, DocumentSource::Unknown)?;
+    let tree_sitter = TreeSitterParser::new()?.parse_text(text, DocumentSource::Unknown)?;
+    Ok((pest, tree_sitter))
+}
+
+/// Parses `text` with both parsers and returns the first divergence between the models, or
+/// `None` if they are identical.
+pub fn compare_parsers<Text: AsRef<str>>(text: Text) -> Result<Option<Divergence>, WdlParserError> {
+    let (pest, tree_sitter) = parse_with_both(text)?;
+    Ok(first_divergence(&pest, &tree_sitter))
+}
+
+/// Parses `text` with both parsers and panics if either parser fails or if the models differ.
+pub fn assert_parsers_agree<Text: AsRef<str>>(text: Text) {
+    match compare_parsers(text) {
+        Ok(None) => (),
+        Ok(Some(divergence)) => panic!("{}", divergence),
+        Err(err) => panic!("{:?}", err),
+    }
+}
+
+/// Returns the first divergence between the `pest` and `tree_sitter` models, or `None` if they
+/// are identical.
+pub fn first_divergence(pest: &Document, tree_sitter: &Document) -> Option<Divergence> {
+    let pest_debug = format!("{:#?}", pest);
+    let tree_sitter_debug = format!("{:#?}", tree_sitter);
+    let pest_lines: Vec<&str> = pest_debug.lines().collect();
+    let tree_sitter_lines: Vec<&str> = tree_sitter_debug.lines().collect();
+    // open structs, tuples, and lists enclosing the current line
+    let mut frames: Vec<Frame> = Vec::new();
+    for i in 0..pest_lines.len().max(tree_sitter_lines.len()) {
+        let pest_line = pest_lines.get(i).copied();
+        let tree_sitter_line = tree_sitter_lines.get(i).copied();
+        // lines are identical up to `i`, so the structure can be inferred from either model
+        let line = pest_line.or(tree_sitter_line).unwrap();
+        let content = line.trim().trim_end_matches(',');
+        if pest_line == tree_sitter_line && content.starts_with(['}', ']', ')']) {
+            frames.pop();
+            continue;
+        }
+        let segment = match content.split_once(": ") {
+            Some((key, _)) if key.chars().all(|c| c.is_alphanumeric() || c == '_') => {
+                format!(".{}", key)
+            }
+            _ => match frames.last_mut() {
+                Some(frame) if frame.list => {
+                    frame.index += 1;
+                    format!("[{}]", frame.index - 1)
+                }
+                _ => String::new(),
+            },
+        };
+        if pest_line != tree_sitter_line {
+            let mut path: String = frames
+                .iter()
+                .map(|frame| frame.segment.as_str())
+                .chain([segment.as_str()])
+                .collect();
+            if path.starts_with('.') {
+                path.remove(0);
+            }
+            // the innermost `element` or `span` of an `Anchor`, including the current line
+            let anchor_line = frames
+                .iter()
+                .map(|frame| (frame.segment.as_str(), frame.line))
+                .chain([(segment.as_str(), i)])
+                .rev()
+                .find(|(segment, _)| *segment == ".element" || *segment == ".span")
+                .map(|(_, line)| line);
+            let fmt_line = |line: Option<&str>| {
+                line.map(|line| line.trim().trim_end_matches(',').to_owned())
+                    .unwrap_or_else(|| "<none>".to_owned())
+            };
+            return Some(Divergence {
+                path,
+                pest: fmt_line(pest_line),
+                tree_sitter: fmt_line(tree_sitter_line),
+                pest_span: anchor_line.and_then(|line| anchor_span(&pest_lines, line)),
+                tree_sitter_span: anchor_line
+                    .and_then(|line| anchor_span(&tree_sitter_lines, line)),
+            });
+        }
+        if content.ends_with(['{', '[', '(']) {
+            frames.push(Frame {
+                segment,
+                list: content.ends_with('['),
+                index: 0,
+                line: i,
+            })
+        }
+    }
+    None
+}
+
+struct Frame {
+    segment: String,
+    list: bool,
+    index: usize,
+    line: usize,
+}
+
+/// Finds the span of the `Anchor` whose `element` or `span` field starts on line `start` of the
+/// `Debug` representation `lines`. The span is a sibling of the element, with the same
+/// indentation.
+fn anchor_span(lines: &[&str], start: usize) -> Option<Span> {
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let anchor_indent = indent(lines.get(start)?);
+    let span_line = (start..lines.len()).find(|i| {
+        indent(lines[*i]) == anchor_indent && lines[*i].trim_start().starts_with("span: ")
+    })?;
+    // the span's start and end positions, each of which has a line, column, and offset
+    let coordinates: Vec<usize> = lines[span_line..]
+        .iter()
+        .filter_map(|line| {
+            let (key, value) = line.trim().trim_end_matches(',').split_once(": ")?;
+            match key {
+                "line" | "column" | "offset" => value.parse().ok(),
+                _ => None,
+            }
+        })
+        .take(6)
+        .collect();
+    match coordinates[..] {
+        [start_line, start_column, start_offset, end_line, end_column, end_offset] => {
+            Some(Span::from_components(
+                start_line,
+                start_column,
+                start_offset,
+                end_line,
+                end_column,
+                end_offset,
+            ))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn parse(text: &str) -> Document {
+        PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_first_divergence() {
+        let doc = parse("version 1.1\n\nworkflow foo {\n    Int x = 1 + 2\n}\n");
+        assert_eq!(first_divergence(&doc, &doc), None);
+        let other = parse("version 1.1\n\nworkflow foo {\n    Int x = 1 + 3\n}\n");
+        let divergence = first_divergence(&doc, &other).unwrap();
+        assert_eq!(
+            divergence.path,
+            "body[0].element.body[0].element.expression.element.right.element.value"
+        );
+        assert_eq!(
+            (divergence.pest.as_str(), divergence.tree_sitter.as_str()),
+            ("value: 2", "value: 3")
+        );
+        assert_eq!(
+            divergence.pest_span,
+            Some(Span::from_components(3, 16, 44, 3, 17, 45))
+        );
+        assert_eq!(divergence.pest_span, divergence.tree_sitter_span);
+        let other = parse("version 1.1\n\nworkflow foo {\n    Int x = 1  + 2\n}\n");
+        let divergence = first_divergence(&doc, &other).unwrap();
+        assert_eq!(
+            divergence.to_string(),
+            "models differ at body[0].element.body[0].element.expression.element.right.span.start.column
+  pest:        column: 16 (3:16..3:17)
+  tree-sitter: column: 17 (3:17..3:18)"
+        );
+    }
+}
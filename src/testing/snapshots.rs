@@ -0,0 +1,210 @@
+//! Snapshot tests of document models: the model of a document is rendered as text and compared
+//! with a snapshot file, which is written (or rewritten) when `WDL_UPDATE_GOLDEN` is set.
+use crate::{
+    model::{Document, DocumentSource},
+    testing::UPDATE_GOLDEN_VAR,
+};
+use error_stack::{IntoReport, Result, ResultExt};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// The directory, relative to the manifest directory of the crate under test, in which
+/// `assert_model_snapshot` stores snapshots.
+pub const SNAPSHOT_DIR: &str = "snapshots";
+
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("IO error accessing {0}")]
+    IO(PathBuf),
+}
+
+/// Renders the model of `document` as it is stored in snapshots, i.e. the `Debug` representations
+/// of its version, body, and comments. The source and digest of the document are omitted, so the
+/// snapshot does not depend on where the document is located.
+pub fn model_snapshot(document: &Document) -> String {
+    format!(
+        "version: {:#?}\nbody: {:#?}\ncomments: {:#?}\n",
+        document.version, document.body, document.comments
+    )
+}
+
+/// A model that differs from its snapshot.
+#[derive(Debug, PartialEq)]
+pub struct SnapshotMismatch {
+    pub path: PathBuf,
+    /// The contents of the snapshot file, or `None` if it does not exist.
+    pub expected: Option<String>,
+    pub actual: String,
+}
+
+impl SnapshotMismatch {
+    /// Returns the (1-based) number of the first line that differs, and the expected and actual
+    /// lines, which are empty if the snapshot is missing or either side has fewer lines.
+    pub fn first_difference(&self) -> (usize, &str, &str) {
+        let expected = self.expected.as_deref().unwrap_or_default();
+        let mut expected_lines = expected.lines();
+        let mut actual_lines = self.actual.lines();
+        let mut line = 1;
+        loop {
+            match (expected_lines.next(), actual_lines.next()) {
+                (Some(expected), Some(actual)) if expected == actual => line += 1,
+                (expected, actual) => {
+                    return (
+                        line,
+                        expected.unwrap_or_default(),
+                        actual.unwrap_or_default(),
+                    )
+                }
+            }
+        }
+    }
+}
+
+/// Compares the model of `document` with the snapshot at `path`. If `update` is true, the
+/// snapshot is written rather than compared. Returns the mismatch, if any.
+pub fn check_model_snapshot<P: AsRef<Path>>(
+    document: &Document,
+    path: P,
+    update: bool,
+) -> Result<Option<SnapshotMismatch>, SnapshotError> {
+    let path = path.as_ref();
+    let actual = model_snapshot(document);
+    if update {
+        path.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(path, &actual))
+            .into_report()
+            .change_context_lazy(|| SnapshotError::IO(path.to_owned()))?;
+        return Ok(None);
+    }
+    let expected = fs::read_to_string(path).ok();
+    if expected.as_deref() == Some(actual.as_str()) {
+        Ok(None)
+    } else {
+        Ok(Some(SnapshotMismatch {
+            path: path.to_owned(),
+            expected,
+            actual,
+        }))
+    }
+}
+
+/// Returns the path of the snapshot named `name` in `SNAPSHOT_DIR`, within the manifest directory
+/// of the crate under test (or the current directory when not run by cargo).
+fn snapshot_path(name: &str) -> PathBuf {
+    std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .join(SNAPSHOT_DIR)
+        .join(format!("{}.snap", name))
+}
+
+/// Checks the model of `document` against the snapshot at `path` as with `check_model_snapshot`,
+/// and panics if it differs. The snapshot is updated instead if the `WDL_UPDATE_GOLDEN`
+/// environment variable is set.
+pub fn assert_model_snapshot_at<P: AsRef<Path>>(document: &Document, path: P) {
+    let update = std::env::var_os(UPDATE_GOLDEN_VAR).is_some();
+    let mismatch = match check_model_snapshot(document, path, update) {
+        Ok(mismatch) => mismatch,
+        Err(err) => panic!("{:?}", err),
+    };
+    if let Some(mismatch) = mismatch {
+        let (line, expected, actual) = mismatch.first_difference();
+        panic!(
+            "model of {} does not match {}{}\nfirst difference at line {}:\nexpected: {}\n\
+             actual:   {}\n(set {} to update snapshots)",
+            document.source,
+            mismatch.path.display(),
+            if mismatch.expected.is_none() {
+                " (missing)"
+            } else {
+                ""
+            },
+            line,
+            expected,
+            actual,
+            UPDATE_GOLDEN_VAR
+        )
+    }
+}
+
+/// Checks the model of `document` against the snapshot `<SNAPSHOT_DIR>/<name>.snap`, where the
+/// name is the file stem of the source of the document, e.g. `snapshots/comprehensive.snap` for
+/// a document parsed from `comprehensive.wdl`. Panics if the source of the document is unknown;
+/// use `assert_model_snapshot_at` for such documents.
+pub fn assert_model_snapshot(document: &Document) {
+    let name = match &document.source {
+        DocumentSource::File(path) => path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned()),
+        DocumentSource::Uri(uri) => uri
+            .rsplit('/')
+            .next()
+            .map(|name| name.trim_end_matches(".wdl").to_owned()),
+        DocumentSource::Unknown => None,
+    };
+    match name {
+        Some(name) if !name.is_empty() => assert_model_snapshot_at(document, snapshot_path(&name)),
+        _ => panic!(
+            "cannot name the snapshot of a document from {}; use assert_model_snapshot_at",
+            document.source
+        ),
+    }
+}
+
+#[cfg(all(test, feature = "pest", feature = "testdata"))]
+mod tests {
+    use super::*;
+    use crate::{
+        parsers::{PestParser, WdlParser},
+        testdata,
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_model_snapshots() {
+        let mut parser = PestParser::new();
+        // the models of the bundled test documents match the snapshots in the repository
+        for document in testdata::ALL {
+            let doc = document.parse(&mut parser).unwrap();
+            assert_model_snapshot_at(
+                &doc,
+                PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                    .join("resources")
+                    .join("test")
+                    .join("snapshots")
+                    .join(format!("{}.snap", document.name)),
+            );
+        }
+
+        let dir = std::env::temp_dir().join(format!("wdl-snapshots-{}", std::process::id()));
+        let path = dir.join("example.snap");
+        let text = "version 1.1\n\nworkflow w {\n    Int i = 1\n}\n";
+        let doc = parser.parse_text(text, DocumentSource::Unknown).unwrap();
+        let mismatch = check_model_snapshot(&doc, &path, false).unwrap().unwrap();
+        assert_eq!(mismatch.expected, None);
+        check_model_snapshot(&doc, &path, true).unwrap();
+        assert_eq!(check_model_snapshot(&doc, &path, false).unwrap(), None);
+        // the source does not affect the snapshot
+        let moved = parser
+            .parse_text(
+                text,
+                DocumentSource::Uri("https://example.com/w.wdl".to_owned()),
+            )
+            .unwrap();
+        assert_eq!(check_model_snapshot(&moved, &path, false).unwrap(), None);
+        let changed = parser
+            .parse_text(text.replace("= 1", "= 2"), DocumentSource::Unknown)
+            .unwrap();
+        let mismatch = check_model_snapshot(&changed, &path, false)
+            .unwrap()
+            .unwrap();
+        let (_, expected, actual) = mismatch.first_difference();
+        assert!(expected.contains("value: 1"));
+        assert!(actual.contains("value: 2"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
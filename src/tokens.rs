@@ -0,0 +1,400 @@
+//! Lightweight tokenizer for syntax highlighting. Unlike the parsers, the tokenizer does not build
+//! a model, never fails, and can be used on incomplete or invalid documents. Tokens are produced
+//! lazily, in source order, and whitespace is skipped.
+//!
+//! String literals are split around their placeholders, e.g. `"a ~{b} c"` is tokenized as
+//! `"a ` (string), `~{` (punctuation), `b` (identifier), `}` (punctuation), and ` c"` (string).
+//! The contents of command sections are tokenized in the same way.
+use crate::model::{Position, Span};
+
+/// Reserved words, including the names of primitive and compound types.
+pub const KEYWORDS: &[&str] = &[
+    "Array",
+    "Boolean",
+    "File",
+    "Float",
+    "Int",
+    "Map",
+    "None",
+    "Object",
+    "Pair",
+    "String",
+    "alias",
+    "as",
+    "call",
+    "command",
+    "else",
+    "false",
+    "if",
+    "import",
+    "in",
+    "input",
+    "left",
+    "meta",
+    "null",
+    "object",
+    "output",
+    "parameter_meta",
+    "right",
+    "runtime",
+    "scatter",
+    "struct",
+    "task",
+    "then",
+    "true",
+    "version",
+    "workflow",
+];
+
+/// Operators, longest first so that e.g. `<=` is not tokenized as `<` followed by `=`.
+const OPERATORS: &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||", "<", ">", "+", "-", "*", "/", "%", "!", "=", "?",
+];
+
+const PUNCTUATION: &[char] = &['{', '}', '[', ']', '(', ')', ',', ':', '.'];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Keyword,
+    Identifier,
+    /// An integer or floating point number.
+    Number,
+    /// A string literal, or the part of a string literal before, between, or after placeholders.
+    String,
+    /// The contents of a command section, excluding placeholders.
+    Command,
+    Comment,
+    Operator,
+    /// Brackets, separators, and the delimiters of placeholders and command sections.
+    Punctuation,
+    /// A character that cannot start any token.
+    Unknown,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub span: Span,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Mode {
+    /// Regular WDL syntax. `braces` is the number of unclosed braces within a placeholder.
+    Normal { braces: usize },
+    /// Inside a string literal delimited by `quote`.
+    String { quote: char },
+    /// Inside a command section. `braces` is the number of unclosed braces within the command.
+    Command { heredoc: bool, braces: usize },
+}
+
+/// Iterator over the tokens of a document, returned by `tokenize`.
+pub struct Tokens<'a> {
+    text: &'a str,
+    position: Position,
+    modes: Vec<Mode>,
+    /// Whether the previous token was the `command` keyword.
+    after_command: bool,
+}
+
+/// Returns an iterator over the tokens in `text`.
+pub fn tokenize(text: &str) -> Tokens<'_> {
+    Tokens {
+        text,
+        position: Position::new(0, 0, 0),
+        modes: vec![Mode::Normal { braces: 0 }],
+        after_command: false,
+    }
+}
+
+fn is_placeholder_start(s: &str, heredoc: bool) -> bool {
+    s.starts_with("~{") || (!heredoc && s.starts_with("${"))
+}
+
+impl<'a> Tokens<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.position.offset..]
+    }
+
+    fn advance(&mut self, len: usize) {
+        for c in self.rest()[..len].chars() {
+            if c == '\n' {
+                self.position.line += 1;
+                self.position.column = 0;
+            } else {
+                self.position.column += 1;
+            }
+        }
+        self.position.offset += len;
+    }
+
+    /// Creates a token from the next `len` bytes of the text.
+    fn token(&mut self, kind: TokenKind, len: usize) -> Token<'a> {
+        let start = self.position.clone();
+        let text = &self.rest()[..len];
+        self.advance(len);
+        Token {
+            kind,
+            text,
+            span: Span {
+                start,
+                end: self.position.clone(),
+            },
+        }
+    }
+
+    fn set_braces(&mut self, n: usize) {
+        match self.modes.last_mut() {
+            Some(Mode::Normal { braces }) | Some(Mode::Command { braces, .. }) => *braces = n,
+            _ => (),
+        }
+    }
+
+    fn next_normal(&mut self, braces: usize) -> Option<Token<'a>> {
+        let rest = self.rest();
+        let c = rest.chars().next()?;
+        let after_command = std::mem::replace(&mut self.after_command, false);
+        let token = if c == '#' {
+            let len = rest.find('\n').unwrap_or(rest.len());
+            self.token(TokenKind::Comment, len)
+        } else if c == '"' || c == '\'' {
+            self.modes.push(Mode::String { quote: c });
+            self.next_string(c, c.len_utf8())?
+        } else if c.is_ascii_alphabetic() {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..len];
+            if KEYWORDS.contains(&word) {
+                self.after_command = word == "command";
+                self.token(TokenKind::Keyword, len)
+            } else {
+                self.token(TokenKind::Identifier, len)
+            }
+        } else if c.is_ascii_digit()
+            || (c == '.' && rest[1..].starts_with(|c: char| c.is_ascii_digit()))
+        {
+            let len = if rest.starts_with("0x") || rest.starts_with("0X") {
+                2 + rest[2..]
+                    .find(|c: char| !c.is_ascii_hexdigit())
+                    .unwrap_or(rest.len() - 2)
+            } else {
+                let mut len = 0;
+                let mut prev = ' ';
+                for c in rest.chars() {
+                    let valid = c.is_ascii_digit()
+                        || c == '.'
+                        || c == 'e'
+                        || c == 'E'
+                        || ((c == '+' || c == '-') && (prev == 'e' || prev == 'E'));
+                    if !valid {
+                        break;
+                    }
+                    len += 1;
+                    prev = c;
+                }
+                len
+            };
+            self.token(TokenKind::Number, len)
+        } else if after_command && rest.starts_with("<<<") {
+            self.modes.push(Mode::Command {
+                heredoc: true,
+                braces: 0,
+            });
+            self.token(TokenKind::Punctuation, 3)
+        } else if c == '{' {
+            if after_command {
+                self.modes.push(Mode::Command {
+                    heredoc: false,
+                    braces: 0,
+                });
+            } else {
+                self.set_braces(braces + 1);
+            }
+            self.token(TokenKind::Punctuation, 1)
+        } else if c == '}' {
+            if braces > 0 {
+                self.set_braces(braces - 1);
+            } else if self.modes.len() > 1 {
+                // end of a placeholder
+                self.modes.pop();
+            }
+            self.token(TokenKind::Punctuation, 1)
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+            self.token(TokenKind::Operator, op.len())
+        } else if PUNCTUATION.contains(&c) {
+            self.token(TokenKind::Punctuation, 1)
+        } else {
+            self.token(TokenKind::Unknown, c.len_utf8())
+        };
+        Some(token)
+    }
+
+    /// Returns the next part of a string literal, starting `skip` bytes after the current
+    /// position.
+    fn next_string(&mut self, quote: char, skip: usize) -> Option<Token<'a>> {
+        let rest = self.rest();
+        if skip == 0 && is_placeholder_start(rest, false) {
+            self.modes.push(Mode::Normal { braces: 0 });
+            return Some(self.token(TokenKind::Punctuation, 2));
+        }
+        let mut chars = rest[skip..].char_indices();
+        let mut len = rest.len();
+        while let Some((i, c)) = chars.next() {
+            if c == '\\' {
+                chars.next();
+            } else if c == quote {
+                self.modes.pop();
+                len = skip + i + c.len_utf8();
+                break;
+            } else if is_placeholder_start(&rest[skip + i..], false) {
+                len = skip + i;
+                break;
+            }
+        }
+        Some(self.token(TokenKind::String, len))
+    }
+
+    /// Returns the next part of a command section.
+    fn next_command(&mut self, heredoc: bool, braces: usize) -> Option<Token<'a>> {
+        let rest = self.rest();
+        if heredoc && rest.starts_with(">>>") {
+            self.modes.pop();
+            return Some(self.token(TokenKind::Punctuation, 3));
+        }
+        if !heredoc && braces == 0 && rest.starts_with('}') {
+            self.modes.pop();
+            return Some(self.token(TokenKind::Punctuation, 1));
+        }
+        if is_placeholder_start(rest, heredoc) {
+            self.modes.push(Mode::Normal { braces: 0 });
+            return Some(self.token(TokenKind::Punctuation, 2));
+        }
+        let mut braces = braces;
+        let mut len = rest.len();
+        for (i, c) in rest.char_indices() {
+            let s = &rest[i..];
+            if (heredoc && s.starts_with(">>>")) || is_placeholder_start(s, heredoc) {
+                len = i;
+                break;
+            }
+            if !heredoc {
+                if c == '{' {
+                    braces += 1;
+                } else if c == '}' {
+                    if braces == 0 {
+                        len = i;
+                        break;
+                    }
+                    braces -= 1;
+                }
+            }
+        }
+        self.set_braces(braces);
+        Some(self.token(TokenKind::Command, len))
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest().is_empty() {
+            return None;
+        }
+        match *self.modes.last()? {
+            Mode::Normal { braces } => {
+                let rest = self.rest();
+                let trimmed = rest.trim_start();
+                self.advance(rest.len() - trimmed.len());
+                self.next_normal(braces)
+            }
+            Mode::String { quote } => self.next_string(quote, 0),
+            Mode::Command { heredoc, braces } => self.next_command(heredoc, braces),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn kinds(text: &str) -> Vec<(TokenKind, &str)> {
+        tokenize(text)
+            .map(|token| (token.kind, token.text))
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("version 1.1\n# comment\nInt? x = 0x1F + 2.5e-3 <= y"),
+            vec![
+                (Keyword, "version"),
+                (Number, "1.1"),
+                (Comment, "# comment"),
+                (Keyword, "Int"),
+                (Operator, "?"),
+                (Identifier, "x"),
+                (Operator, "="),
+                (Number, "0x1F"),
+                (Operator, "+"),
+                (Number, "2.5e-3"),
+                (Operator, "<="),
+                (Identifier, "y"),
+            ]
+        );
+        assert_eq!(
+            kinds(r#"String s = "a \"~{b + {"k": 1}["k"]} c""#),
+            vec![
+                (Keyword, "String"),
+                (Identifier, "s"),
+                (Operator, "="),
+                (String, r#""a \""#),
+                (Punctuation, "~{"),
+                (Identifier, "b"),
+                (Operator, "+"),
+                (Punctuation, "{"),
+                (String, r#""k""#),
+                (Punctuation, ":"),
+                (Number, "1"),
+                (Punctuation, "}"),
+                (Punctuation, "["),
+                (String, r#""k""#),
+                (Punctuation, "]"),
+                (Punctuation, "}"),
+                (String, r#" c""#),
+            ]
+        );
+        assert_eq!(
+            kinds("command <<<\n  echo ${HOME} ~{x}\n>>>\ncommand { f() { :; } ${y} }"),
+            vec![
+                (Keyword, "command"),
+                (Punctuation, "<<<"),
+                (Command, "\n  echo ${HOME} "),
+                (Punctuation, "~{"),
+                (Identifier, "x"),
+                (Punctuation, "}"),
+                (Command, "\n"),
+                (Punctuation, ">>>"),
+                (Keyword, "command"),
+                (Punctuation, "{"),
+                (Command, " f() { :; } "),
+                (Punctuation, "${"),
+                (Identifier, "y"),
+                (Punctuation, "}"),
+                (Command, " "),
+                (Punctuation, "}"),
+            ]
+        );
+        assert_eq!(
+            kinds("\"unterminated ~{"),
+            vec![(String, "\"unterminated "), (Punctuation, "~{")]
+        );
+        let tokens: Vec<Token> = tokenize("a\n  \"é\" b").collect();
+        assert_eq!(tokens[1].span, Span::from_components(1, 2, 4, 1, 5, 8));
+        assert_eq!(tokens[2].span, Span::from_components(1, 6, 9, 1, 7, 10));
+    }
+}
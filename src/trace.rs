@@ -0,0 +1,49 @@
+//! Instrumentation of the phases of parsing and analysis, for profiling slow parses of large
+//! documents. When the `tracing` feature is enabled, each phase (e.g. `grammar`, `model`,
+//! `validation`, or a lint) runs in a `phase` span at the debug level, and an event with the
+//! duration of the phase in microseconds is emitted when it ends. The phases of parsing a
+//! document are nested in a `document` span. Without the feature, these functions only call the
+//! wrapped closures.
+#[cfg(any(feature = "pest", feature = "tree-sitter"))]
+use crate::model::DocumentSource;
+
+/// Runs `f` in a span for the document `source`, which has `bytes` bytes of text.
+#[cfg(any(feature = "pest", feature = "tree-sitter"))]
+pub(crate) fn document<T, F: FnOnce() -> T>(source: &DocumentSource, bytes: usize, f: F) -> T {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("document", source = %source, bytes).entered();
+    #[cfg(not(feature = "tracing"))]
+    let _ = (source, bytes);
+    timed(f)
+}
+
+/// Runs `f` in a span for the phase `name`.
+pub(crate) fn phase<T, F: FnOnce() -> T>(name: &str, f: F) -> T {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("phase", name).entered();
+    #[cfg(not(feature = "tracing"))]
+    let _ = name;
+    timed(f)
+}
+
+fn timed<T, F: FnOnce() -> T>(f: F) -> T {
+    #[cfg(feature = "tracing")]
+    {
+        let start = std::time::Instant::now();
+        let result = f();
+        tracing::debug!(elapsed_us = start.elapsed().as_micros() as u64, "finished");
+        result
+    }
+    #[cfg(not(feature = "tracing"))]
+    f()
+}
+
+/// Emits an event with the number of items of kind `what` (e.g. the elements of a document) that
+/// were produced by the current phase.
+#[cfg(any(feature = "pest", feature = "tree-sitter"))]
+pub(crate) fn count(what: &str, count: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(what, count);
+    #[cfg(not(feature = "tracing"))]
+    let _ = (what, count);
+}
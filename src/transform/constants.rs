@@ -0,0 +1,572 @@
+use crate::{
+    analysis::{resolve_scopes, walk_expression, Scopes},
+    edit::{apply_edits, TextEdit},
+    eval::{evaluate, Environment},
+    model::{
+        BoundDeclaration, Document, DocumentElement, Expression, ObjectKind, Span, StringPart,
+        TaskElement, VersionIdentifier, WorkflowElement,
+    },
+    tree::{walk, Node},
+    values::WdlValue,
+};
+use error_stack::{Result, ResultExt};
+use std::{collections::BTreeMap, ops::Deref};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum PropagateError {
+    #[error("error rewriting document {0}")]
+    Edit(String),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PropagateOptions {
+    /// Whether expressions that only depend on constants (after propagation) are replaced by
+    /// their values, e.g. `n * 2` becomes `6` if `n` is the constant `3`.
+    pub simplify: bool,
+}
+
+/// The maximum length of an array created by a call of `range` that is replaced by an array
+/// literal when simplifying. Longer ranges are kept as calls, so that the text does not grow by
+/// the length of the range.
+pub const MAX_EXPANDED_RANGE: usize = 100;
+
+/// A private declaration of a task or workflow whose value is a constant.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Constant {
+    pub name: String,
+    /// The span of the name of the declaration.
+    pub span: Span,
+    pub value: WdlValue,
+}
+
+/// The result of propagating the constants of a document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Propagated {
+    pub text: String,
+    /// The edits that were applied to the source text to produce `text`.
+    pub edits: Vec<TextEdit>,
+    /// The constant declarations, ordered by position.
+    pub constants: Vec<Constant>,
+}
+
+/// Returns `s` as a WDL string literal.
+fn string_literal(s: &str) -> String {
+    let mut literal = String::with_capacity(s.len() + 2);
+    literal.push('"');
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => literal.push_str("\\\\"),
+            '"' => literal.push_str("\\\""),
+            '\n' => literal.push_str("\\n"),
+            '\t' => literal.push_str("\\t"),
+            '~' | '$' if chars.peek() == Some(&'{') => {
+                literal.push('\\');
+                literal.push(c)
+            }
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// Returns the WDL source of a literal with the value `value`, or `None` if it cannot be written
+/// as a literal (i.e. it is a non-finite float). Struct values are written as struct literals if
+/// `struct_literals` is set (WDL 1.1 and later) and as object literals otherwise.
+fn literal(value: &WdlValue, struct_literals: bool) -> Option<String> {
+    let list = |values: &mut dyn Iterator<Item = Option<String>>| {
+        values
+            .collect::<Option<Vec<String>>>()
+            .map(|v| v.join(", "))
+    };
+    let members = |members: &[(String, WdlValue)]| {
+        list(&mut members.iter().map(|(name, value)| {
+            literal(value, struct_literals).map(|value| format!("{}: {}", name, value))
+        }))
+    };
+    let literal = match value {
+        WdlValue::None => "None".to_owned(),
+        WdlValue::Boolean(b) => b.to_string(),
+        WdlValue::Int(i) => i.to_string(),
+        WdlValue::Float(f) if f.is_finite() => format!("{:?}", f),
+        WdlValue::Float(_) => return None,
+        WdlValue::String(s) | WdlValue::File(s) => string_literal(s),
+        WdlValue::Array(values) => format!(
+            "[{}]",
+            list(&mut values.iter().map(|v| literal(v, struct_literals)))?
+        ),
+        WdlValue::Map(entries) => format!(
+            "{{{}}}",
+            list(&mut entries.iter().map(|(key, value)| {
+                Some(format!(
+                    "{}: {}",
+                    literal(key, struct_literals)?,
+                    literal(value, struct_literals)?
+                ))
+            }))?
+        ),
+        WdlValue::Pair(left, right) => format!(
+            "({}, {})",
+            literal(left, struct_literals)?,
+            literal(right, struct_literals)?
+        ),
+        WdlValue::Struct { name, members: m } if struct_literals => {
+            format!("{} {{ {} }}", name, members(m)?)
+        }
+        WdlValue::Object(m) | WdlValue::Struct { members: m, .. } => {
+            format!("object {{ {} }}", members(m)?)
+        }
+    };
+    Some(literal)
+}
+
+/// Returns `true` if `expression` is a literal, i.e. it does not have any identifiers, operators,
+/// function calls, or placeholders.
+fn is_literal(expression: &Expression, span: &Span) -> bool {
+    let mut literal = true;
+    walk_expression(expression, span, &mut |e, _| match e {
+        Expression::None
+        | Expression::Boolean(_)
+        | Expression::Int(_)
+        | Expression::Float(_)
+        | Expression::Array(_)
+        | Expression::Map(_)
+        | Expression::Pair(_) => (),
+        Expression::String(s) => literal &= s.is_constant(),
+        Expression::Object(o) => literal &= o.kind == ObjectKind::Struct,
+        _ => literal = false,
+    });
+    literal
+}
+
+/// Returns the spans of the names of the call inputs of `document` that do not have expressions,
+/// i.e. that are references to the declarations of the same name.
+pub(super) fn implicit_inputs(document: &Document) -> Vec<Span> {
+    // `call x { input: y }` is shorthand for `call x { input: y = y }`
+    let mut spans = Vec::new();
+    walk(document, &mut |node, _| {
+        if let Node::CallInput(input) = node {
+            if input.expression.is_none() {
+                spans.push(input.name.span.clone())
+            }
+        }
+    });
+    spans
+}
+
+/// Returns the edit that replaces the reference to `name` at `span` of `text` with `replacement`.
+/// The text of the reference is either the name itself, a placeholder that only contains the
+/// name, or the name of a call input in `implicit_inputs`, which is given an expression. Returns
+/// `None` for any other reference, e.g. an identifier in an index operation, whose span is that of
+/// the enclosing expression.
+pub(super) fn replace_reference(
+    text: &str,
+    span: &Span,
+    name: &str,
+    replacement: &str,
+    implicit_inputs: &[Span],
+) -> Option<TextEdit> {
+    let source = &text[span.start.offset..span.end.offset];
+    let text = if implicit_inputs.contains(span) {
+        format!("{} = {}", name, replacement)
+    } else if source == name {
+        replacement.to_owned()
+    } else {
+        replace_placeholder(source, replacement)
+            .filter(|_| source[2..source.len() - 1].trim() == name)?
+    };
+    Some(TextEdit::replace(span.clone(), text))
+}
+
+/// Returns `source`, the text of a placeholder, with its expression replaced by `literal`.
+fn replace_placeholder(source: &str, literal: &str) -> Option<String> {
+    if (source.starts_with("~{") || source.starts_with("${")) && source.ends_with('}') {
+        Some(format!("{}{{{}}}", &source[..1], literal))
+    } else {
+        None
+    }
+}
+
+struct Propagator<'a> {
+    text: &'a str,
+    scopes: Scopes,
+    struct_literals: bool,
+    /// The values of the constant declarations, by the spans of their names.
+    constants: BTreeMap<Span, (&'a str, WdlValue)>,
+}
+
+impl<'a> Propagator<'a> {
+    /// Returns an environment in which the identifiers of `expression` are bound to the values of
+    /// the constants to which they refer, or `None` if any of them does not refer to a constant.
+    fn environment(&self, expression: &Expression, span: &Span) -> Option<Environment> {
+        let mut identifiers = Vec::new();
+        walk_expression(expression, span, &mut |e, span| {
+            if let Expression::Identifier(name) = e {
+                identifiers.push((name, span))
+            }
+        });
+        let mut env = Environment::new();
+        for (name, span) in identifiers {
+            let symbol = self
+                .scopes
+                .references
+                .iter()
+                .find(|reference| reference.span == *span && reference.name == *name)?
+                .symbol
+                .as_ref()?;
+            let (_, value) = self.constants.get(&symbol.span)?;
+            env.bind(name.as_str(), value.clone())
+        }
+        Some(env)
+    }
+
+    /// Returns the value of `expression`, if it only depends on constants.
+    fn value(&self, expression: &Expression, span: &Span) -> Option<WdlValue> {
+        let env = self.environment(expression, span)?;
+        evaluate(expression, &env).ok()
+    }
+
+    /// Returns the WDL source of the value of `expression`, if it only depends on constants.
+    fn evaluate(&self, expression: &Expression, span: &Span) -> Option<String> {
+        literal(&self.value(expression, span)?, self.struct_literals)
+    }
+
+    /// Determines which of `declarations` are constants. A declaration may depend on constants
+    /// that are declared after it, so this is repeated until no more constants are found.
+    fn find_constants(&mut self, declarations: &[&'a BoundDeclaration]) {
+        loop {
+            let mut found = false;
+            for decl in declarations.iter() {
+                if self.constants.contains_key(&decl.name.span) {
+                    continue;
+                }
+                let value = self
+                    .environment(&decl.expression, &decl.expression.span)
+                    .and_then(|env| evaluate(&decl.expression, &env).ok())
+                    .and_then(|value| value.coerce(&decl.type_).ok())
+                    .filter(|value| literal(value, self.struct_literals).is_some());
+                if let Some(value) = value {
+                    self.constants
+                        .insert(decl.name.span.clone(), (decl.name.as_str(), value));
+                    found = true;
+                }
+            }
+            if !found {
+                break;
+            }
+        }
+    }
+
+    /// Returns the edits that replace the maximal non-literal expressions that only depend on
+    /// constants with their values, other than calls of `range` that create more than
+    /// `MAX_EXPANDED_RANGE` elements.
+    fn simplifications(&self, document: &'a Document) -> Vec<TextEdit> {
+        let mut candidates = Vec::new();
+        walk(document, &mut |node, _| match node {
+            Node::Expression(expression) => {
+                if matches!(expression.deref(), Expression::Identifier(_))
+                    || is_literal(expression, &expression.span)
+                {
+                    return;
+                }
+                let value = self.value(expression, &expression.span);
+                if let (Expression::Apply(apply), Some(WdlValue::Array(values))) =
+                    (expression.deref(), &value)
+                {
+                    if apply.name.as_str() == "range" && values.len() > MAX_EXPANDED_RANGE {
+                        return;
+                    }
+                }
+                if let Some(literal) = value.and_then(|value| literal(&value, self.struct_literals))
+                {
+                    candidates.push(TextEdit::replace(expression.span.clone(), literal))
+                }
+            }
+            Node::StringPart(part) => {
+                if let StringPart::Placeholder(expression, _) = part.deref() {
+                    if is_literal(expression, &part.span) {
+                        return;
+                    }
+                    let source = &self.text[part.span.start.offset..part.span.end.offset];
+                    if let Some(text) = self
+                        .evaluate(expression, &part.span)
+                        .and_then(|literal| replace_placeholder(source, &literal))
+                    {
+                        candidates.push(TextEdit::replace(part.span.clone(), text))
+                    }
+                }
+            }
+            _ => (),
+        });
+        // outer expressions first, so that the expressions they contain are skipped
+        candidates.sort_by(|a, b| {
+            (a.span.start.offset, b.span.end.offset).cmp(&(b.span.start.offset, a.span.end.offset))
+        });
+        let mut edits: Vec<TextEdit> = Vec::new();
+        for candidate in candidates {
+            if !edits
+                .iter()
+                .any(|edit| edit.span.contains_span(&candidate.span))
+            {
+                edits.push(candidate)
+            }
+        }
+        edits
+    }
+
+    /// Returns the edits that replace the references to constants with their values, except for
+    /// those within `simplified`.
+    fn substitutions(&self, document: &Document, simplified: &[TextEdit]) -> Vec<TextEdit> {
+        let implicit_inputs = implicit_inputs(document);
+        let mut edits = Vec::new();
+        for reference in self.scopes.references.iter() {
+            let (name, value) = match reference
+                .symbol
+                .as_ref()
+                .and_then(|symbol| self.constants.get(&symbol.span))
+            {
+                Some(constant) => constant,
+                None => continue,
+            };
+            if simplified
+                .iter()
+                .any(|edit| edit.span.contains_span(&reference.span))
+            {
+                continue;
+            }
+            let literal = match literal(value, self.struct_literals) {
+                Some(literal) => literal,
+                None => continue,
+            };
+            edits.extend(replace_reference(
+                self.text,
+                &reference.span,
+                name,
+                &literal,
+                &implicit_inputs,
+            ))
+        }
+        edits
+    }
+}
+
+/// Propagates the constants of `document`, whose source is `text`, into the expressions that
+/// reference them. A constant is a private declaration of a task or workflow (i.e. not an input,
+/// which may be overridden, or an output) that is not nested in a scatter or conditional, and
+/// whose expression can be evaluated by `eval::evaluate` after the constants it references are
+/// propagated, e.g. `Int n = 3` or `String name = "~{prefix}.txt"` where `prefix` is a constant.
+///
+/// Each reference to a constant is replaced by a literal of its value, and the declarations of
+/// constants are kept. If `options.simplify` is set, expressions that only depend on constants
+/// are also replaced by their values, e.g. `scatter (i in range(n))` becomes
+/// `scatter (i in [0, 1, 2])`, unless the range has more than `MAX_EXPANDED_RANGE` elements.
+pub fn propagate_constants_with(
+    document: &Document,
+    text: &str,
+    options: &PropagateOptions,
+) -> Result<Propagated, PropagateError> {
+    let mut propagator = Propagator {
+        text,
+        scopes: resolve_scopes(document),
+        struct_literals: *document.version.identifier >= VersionIdentifier::V1_1,
+        constants: BTreeMap::new(),
+    };
+    for element in document.body_iter() {
+        let declarations: Vec<&BoundDeclaration> = match element {
+            DocumentElement::Task(task) => task
+                .body
+                .iter()
+                .filter_map(|element| match element.deref() {
+                    TaskElement::Declaration(decl) => Some(decl),
+                    _ => None,
+                })
+                .collect(),
+            DocumentElement::Workflow(workflow) => workflow
+                .body
+                .iter()
+                .filter_map(|element| match element.deref() {
+                    WorkflowElement::Declaration(decl) => Some(decl),
+                    _ => None,
+                })
+                .collect(),
+            _ => continue,
+        };
+        propagator.find_constants(&declarations)
+    }
+    let mut edits = if options.simplify {
+        propagator.simplifications(document)
+    } else {
+        Vec::new()
+    };
+    let substitutions = propagator.substitutions(document, &edits);
+    edits.extend(substitutions);
+    edits.sort_by(|a, b| a.span.cmp(&b.span));
+    let text = apply_edits(text, &edits)
+        .change_context(PropagateError::Edit(document.source.to_string()))?;
+    let constants = propagator
+        .constants
+        .into_iter()
+        .map(|(span, (name, value))| Constant {
+            name: name.to_owned(),
+            span,
+            value,
+        })
+        .collect();
+    Ok(Propagated {
+        text,
+        edits,
+        constants,
+    })
+}
+
+/// Propagates the constants of `document` with the default options (i.e. without simplification)
+/// and returns the rewritten text. See `propagate_constants_with`.
+pub fn propagate_constants(document: &Document, text: &str) -> Result<String, PropagateError> {
+    propagate_constants_with(document, text, &PropagateOptions::default())
+        .map(|propagated| propagated.text)
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    const TEXT: &str = r#"version 1.1
+
+task t {
+    input {
+        Int n
+        String label = "x"
+    }
+    String prefix = "out"
+    command <<< echo ~{prefix} ~{label} >>>
+    output {
+        File result = "~{prefix}.txt"
+    }
+}
+
+workflow w {
+    input {
+        Int base = 1
+    }
+    Int n = width * 2
+    Int width = 3
+    Int offset = base + n
+    scatter (i in range(n)) {
+        Int n2 = n
+        call t { input: n }
+    }
+}
+"#;
+
+    #[test]
+    fn test_propagate_constants() {
+        let doc = PestParser::new()
+            .parse_text(TEXT, DocumentSource::Unknown)
+            .unwrap();
+        let propagated =
+            propagate_constants_with(&doc, TEXT, &PropagateOptions::default()).unwrap();
+        let constants: Vec<(&str, &WdlValue)> = propagated
+            .constants
+            .iter()
+            .map(|constant| (constant.name.as_str(), &constant.value))
+            .collect();
+        assert_eq!(
+            constants,
+            vec![
+                ("prefix", &WdlValue::String("out".to_owned())),
+                ("n", &WdlValue::Int(6)),
+                ("width", &WdlValue::Int(3)),
+            ]
+        );
+        assert_eq!(
+            propagated.text,
+            TEXT.replace("echo ~{prefix}", "echo ~{\"out\"}")
+                .replace("\"~{prefix}.txt\"", "\"~{\"out\"}.txt\"")
+                .replace("width * 2", "3 * 2")
+                .replace("base + n", "base + 6")
+                .replace("range(n)", "range(6)")
+                .replace("n2 = n", "n2 = 6")
+                .replace("input: n }", "input: n = 6 }")
+        );
+
+        let simplified = propagate_constants_with(&doc, TEXT, &PropagateOptions { simplify: true })
+            .unwrap()
+            .text;
+        assert_eq!(
+            simplified,
+            TEXT.replace("echo ~{prefix}", "echo ~{\"out\"}")
+                .replace("\"~{prefix}.txt\"", "\"out.txt\"")
+                .replace("width * 2", "6")
+                .replace("base + n", "base + 6")
+                .replace("range(n)", "[0, 1, 2, 3, 4, 5]")
+                .replace("n2 = n", "n2 = 6")
+                .replace("input: n }", "input: n = 6 }")
+        );
+    }
+
+    #[test]
+    fn test_simplify_range() {
+        let text = r#"version 1.1
+
+workflow w {
+    Int n = 100
+    Array[Int] small = range(2)
+    Array[Int] large = range(n + 1)
+    scatter (i in range(n)) {
+        Int j = i
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let simplified = propagate_constants_with(&doc, text, &PropagateOptions { simplify: true })
+            .unwrap()
+            .text;
+        let expanded = (0..MAX_EXPANDED_RANGE)
+            .map(|i| i.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        assert_eq!(
+            simplified,
+            text.replace("range(2)", "[0, 1]")
+                .replace("range(n + 1)", "range(101)")
+                .replace("range(n)", &format!("[{}]", expanded))
+        );
+    }
+
+    #[test]
+    fn test_literal() {
+        assert_eq!(
+            literal(&WdlValue::String("a\"~{b}\n".to_owned()), true).unwrap(),
+            r#""a\"\~{b}\n""#
+        );
+        assert_eq!(literal(&WdlValue::Float(1.0), true).unwrap(), "1.0");
+        assert_eq!(literal(&WdlValue::Float(f64::NAN), true), None);
+        let value = WdlValue::Struct {
+            name: "P".to_owned(),
+            members: vec![(
+                "m".to_owned(),
+                WdlValue::Map(vec![(
+                    WdlValue::String("k".to_owned()),
+                    WdlValue::Pair(Box::new(WdlValue::Int(1)), Box::new(WdlValue::None)),
+                )]),
+            )],
+        };
+        assert_eq!(
+            literal(&value, true).unwrap(),
+            "P { m: {\"k\": (1, None)} }"
+        );
+        assert_eq!(
+            literal(&value, false).unwrap(),
+            "object { m: {\"k\": (1, None)} }"
+        );
+    }
+}
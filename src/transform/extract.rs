@@ -0,0 +1,410 @@
+use crate::{
+    edit::{apply_edits, TextEdit},
+    model::{
+        Document, DocumentElement, DocumentSource, Expression, Namespace, ObjectKind, Position,
+        Span, Type, WorkflowElement, WorkflowNestedElement,
+    },
+    parsers::WdlParser,
+    sourcemap::position_at,
+    transform::rename::fresh_name,
+    tree::{walk, Node},
+    workspace::{namespace_name, Workspace},
+};
+use error_stack::{bail, Result, ResultExt};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Deref,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ExtractError {
+    #[error("the source text of document {0} is not available")]
+    MissingText(DocumentSource),
+    #[error("there is no task named {0}")]
+    UnknownTask(String),
+    #[error("struct {0} cannot be extracted because it is imported")]
+    ImportedStruct(String),
+    #[error("error rewriting document {0}")]
+    Edit(DocumentSource),
+    #[error("error parsing rewritten document {0}")]
+    Parse(DocumentSource),
+}
+
+/// The result of extracting tasks from a document into a new document.
+#[derive(Debug)]
+pub struct Extracted {
+    /// The namespace under which the original document imports the new document.
+    pub namespace: String,
+    /// The edits that were applied to the source text of the original document.
+    pub edits: Vec<TextEdit>,
+    /// The rewritten text of the original document.
+    pub text: String,
+    /// The rewritten original document.
+    pub document: Document,
+    /// The text of the new document.
+    pub extracted_text: String,
+    /// The new document, whose source is `new_uri` resolved relative to the original document.
+    pub extracted: Document,
+}
+
+/// Returns the names of the structs that are referenced by the types and struct literals within
+/// `span` of `document`.
+fn referenced_structs<'a>(document: &'a Document, span: &Span) -> HashSet<&'a str> {
+    let mut names = HashSet::new();
+    walk(document, &mut |node, _| match node {
+        Node::Type(type_) if span.contains_span(&type_.span) => {
+            if let Type::User(name) = type_.deref() {
+                names.insert(name.as_str());
+            }
+        }
+        Node::Expression(expression) if span.contains_span(&expression.span) => {
+            if let Expression::Object(object) = expression.deref() {
+                if object.kind == ObjectKind::Struct {
+                    names.insert(object.type_name.as_str());
+                }
+            }
+        }
+        _ => (),
+    });
+    names
+}
+
+/// Moves the tasks of `document` named `task_names` into a new document that is imported by the
+/// original document from `new_uri`, e.g. to split a document that has grown too large. The
+/// source text of `document` must be available (see `Document::source_text`).
+///
+/// The original document imports the new document under a namespace derived from `new_uri`
+/// (which is made unique among the namespaces of its imports), and calls to the extracted tasks
+/// are rewritten to refer to the namespace, e.g. `call align` becomes `call tasks.align`. Call
+/// names are unchanged, so references to the outputs of calls do not need to be rewritten.
+///
+/// The structs that are referenced by the extracted tasks are moved into the new document as
+/// well if they are not referenced by the rest of the original document, and are otherwise
+/// copied and imported under an alias (e.g. `alias Sample as tasks_Sample`), so that the names
+/// of the original document do not conflict with the names it imports. Returns an error if an
+/// extracted task references a struct that the original document imports.
+///
+/// Comments that precede an extracted element are moved with it. Both documents are parsed with
+/// `parser`.
+pub fn extract_tasks<P: WdlParser>(
+    parser: &mut P,
+    document: &Document,
+    task_names: &[&str],
+    new_uri: &str,
+) -> Result<Extracted, ExtractError> {
+    let text = match document.source_text() {
+        Some(text) => text.as_str(),
+        None => bail!(ExtractError::MissingText(document.source.clone())),
+    };
+    for name in task_names {
+        let exists = document.body_iter().any(
+            |element| matches!(element, DocumentElement::Task(task) if task.name.as_str() == *name),
+        );
+        if !exists {
+            bail!(ExtractError::UnknownTask((*name).to_owned()))
+        }
+    }
+
+    // the end of each element, excluding any trailing whitespace that its span includes
+    let ends: Vec<Position> = document
+        .body
+        .iter()
+        .map(|element| {
+            let span = &element.span;
+            let trimmed = text[span.start.offset..span.end.offset].trim_end();
+            position_at(text, span.start.offset + trimmed.len())
+        })
+        .collect();
+    // the span of each element, including the comments and whitespace that precede it
+    let mut previous_end = document.version.span.end.clone();
+    let mut extents = Vec::new();
+    for end in ends.iter() {
+        extents.push(Span {
+            start: previous_end.clone(),
+            end: end.clone(),
+        });
+        previous_end = end.clone();
+    }
+    let chunk = |i: usize| text[extents[i].start.offset..extents[i].end.offset].trim_start();
+
+    let structs: HashMap<&str, usize> = document
+        .body
+        .iter()
+        .enumerate()
+        .filter_map(|(i, element)| match element.deref() {
+            DocumentElement::Struct(struct_) => Some((struct_.name.as_str(), i)),
+            _ => None,
+        })
+        .collect();
+    // returns the structs that are referenced by the elements `roots`, directly or by the fields
+    // of other structs
+    let closure = |roots: Vec<usize>| -> HashSet<&str> {
+        let mut referenced = HashSet::new();
+        let mut pending = roots;
+        while let Some(i) = pending.pop() {
+            for name in referenced_structs(document, &document.body[i].span) {
+                if referenced.insert(name) {
+                    pending.extend(structs.get(name))
+                }
+            }
+        }
+        referenced
+    };
+    let is_extracted = |element: &DocumentElement| match element {
+        DocumentElement::Task(task) => task_names.contains(&task.name.as_str()),
+        _ => false,
+    };
+    let (extracted, remaining): (Vec<usize>, Vec<usize>) = (0..document.body.len())
+        .filter(|i| !matches!(document.body[*i].deref(), DocumentElement::Struct(_)))
+        .partition(|i| is_extracted(&document.body[*i]));
+    let needed = closure(extracted.clone());
+    let kept = closure(remaining);
+    if let Some(name) = needed.iter().find(|name| !structs.contains_key(*name)) {
+        bail!(ExtractError::ImportedStruct((*name).to_string()))
+    }
+
+    let implicit = Namespace::from_uri(new_uri).identifier().to_owned();
+    let namespaces: HashSet<&str> = document
+        .body_iter()
+        .filter_map(|element| match element {
+            DocumentElement::Import(import) => Some(namespace_name(import)),
+            _ => None,
+        })
+        .collect();
+    let namespace = fresh_name(
+        &implicit.replace(|c: char| !c.is_ascii_alphanumeric() && c != '_', "_"),
+        |name| namespaces.contains(name),
+    );
+
+    let version = &document.version.span;
+    let mut extracted_text = text[version.start.offset..version.end.offset].to_owned();
+    let mut moved = Vec::new();
+    let mut aliases = Vec::new();
+    for (i, element) in document.body.iter().enumerate() {
+        match element.deref() {
+            DocumentElement::Struct(struct_) if needed.contains(struct_.name.as_str()) => {
+                let name = struct_.name.as_str();
+                if kept.contains(name) {
+                    aliases.push(format!(" alias {} as {}_{}", name, namespace, name))
+                } else {
+                    moved.push(i)
+                }
+            }
+            task if is_extracted(task) => moved.push(i),
+            _ => continue,
+        }
+        extracted_text.push_str("\n\n");
+        extracted_text.push_str(chunk(i));
+    }
+    extracted_text.push('\n');
+
+    let mut import = format!("import \"{}\"", new_uri);
+    if namespace != implicit {
+        import.push_str(&format!(" as {}", namespace))
+    }
+    import.extend(aliases);
+    // the import is added after the last import, or else after the version statement
+    let position = document
+        .body_iter()
+        .zip(ends.iter())
+        .filter(|(element, _)| matches!(element, DocumentElement::Import(_)))
+        .last()
+        .map(|(_, end)| end.clone())
+        .unwrap_or_else(|| version.end.clone());
+    let separator = if position == version.end {
+        "\n\n"
+    } else {
+        "\n"
+    };
+    let mut edits = Vec::new();
+    let mut inserted = false;
+    for i in moved {
+        // an element that directly follows the position of the import is replaced by it
+        if extents[i].start == position {
+            edits.push(TextEdit::replace(
+                extents[i].clone(),
+                format!("{}{}", separator, import),
+            ));
+            inserted = true;
+        } else {
+            edits.push(TextEdit::delete(extents[i].clone()))
+        }
+    }
+    if !inserted {
+        edits.push(TextEdit::insert(
+            position,
+            format!("{}{}", separator, import),
+        ))
+    }
+    walk(document, &mut |node, _| {
+        let call = match node {
+            Node::WorkflowElement(element) => match element.deref() {
+                WorkflowElement::Call(call) => call,
+                _ => return,
+            },
+            Node::WorkflowNestedElement(element) => match element.deref() {
+                WorkflowNestedElement::Call(call) => call,
+                _ => return,
+            },
+            _ => return,
+        };
+        if let [name] = call.target.parts.as_slice() {
+            if task_names.contains(&name.as_str()) {
+                edits.push(TextEdit::replace(
+                    name.span.clone(),
+                    format!("{}.{}", namespace, name.as_str()),
+                ))
+            }
+        }
+    });
+    edits.sort_by(|a, b| a.span.cmp(&b.span));
+    let rewritten =
+        apply_edits(text, &edits).change_context(ExtractError::Edit(document.source.clone()))?;
+
+    let source = Workspace::import_source(&document.source, new_uri);
+    let rewritten_document = parser
+        .parse_text(&rewritten, document.source.clone())
+        .change_context(ExtractError::Parse(document.source.clone()))?;
+    let extracted = parser
+        .parse_text(&extracted_text, source.clone())
+        .change_context(ExtractError::Parse(source))?;
+    Ok(Extracted {
+        namespace,
+        edits,
+        text: rewritten,
+        document: rewritten_document,
+        extracted_text,
+        extracted,
+    })
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::parsers::{ParseOptions, PestParser};
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    const TEXT: &str = r#"version 1.1
+
+import "lib/tasks.wdl"
+
+struct Sample {
+    String id
+}
+
+struct Index {
+    Array[File] files
+}
+
+# Aligns a sample.
+task align {
+    input {
+        Sample sample
+        Index index
+    }
+    command <<< align >>>
+}
+
+task report {
+    input {
+        Sample sample
+    }
+    command <<< report >>>
+}
+
+workflow w {
+    input {
+        Sample sample
+    }
+    scatter (i in range(2)) {
+        call align { input: sample }
+    }
+    call report { input: sample }
+}
+"#;
+
+    #[test]
+    fn test_extract_tasks() {
+        let mut parser =
+            PestParser::new().with_parse_options(ParseOptions::default().with_retained_text(true));
+        let doc = parser
+            .parse_text(TEXT, DocumentSource::File(PathBuf::from("wf/main.wdl")))
+            .unwrap();
+        let extracted = extract_tasks(&mut parser, &doc, &["align"], "tasks.wdl").unwrap();
+        assert_eq!(extracted.namespace, "tasks_2");
+        assert_eq!(
+            extracted.extracted_text,
+            r#"version 1.1
+
+struct Sample {
+    String id
+}
+
+struct Index {
+    Array[File] files
+}
+
+# Aligns a sample.
+task align {
+    input {
+        Sample sample
+        Index index
+    }
+    command <<< align >>>
+}
+"#
+        );
+        assert_eq!(
+            extracted.text,
+            r#"version 1.1
+
+import "lib/tasks.wdl"
+import "tasks.wdl" as tasks_2 alias Sample as tasks_2_Sample
+
+struct Sample {
+    String id
+}
+
+task report {
+    input {
+        Sample sample
+    }
+    command <<< report >>>
+}
+
+workflow w {
+    input {
+        Sample sample
+    }
+    scatter (i in range(2)) {
+        call tasks_2.align { input: sample }
+    }
+    call report { input: sample }
+}
+"#
+        );
+        assert_eq!(
+            extracted.extracted.source,
+            DocumentSource::File(PathBuf::from("wf/tasks.wdl"))
+        );
+        assert_eq!(extracted.document.body.len(), 5);
+
+        let extracted = extract_tasks(&mut parser, &doc, &["report"], "report.wdl").unwrap();
+        assert_eq!(extracted.namespace, "report");
+        assert!(extracted
+            .text
+            .contains("import \"report.wdl\" alias Sample as report_Sample\n"));
+        assert!(extracted
+            .text
+            .contains("call report.report { input: sample }"));
+
+        assert!(matches!(
+            extract_tasks(&mut parser, &doc, &["missing"], "x.wdl")
+                .unwrap_err()
+                .current_context(),
+            ExtractError::UnknownTask(name) if name == "missing"
+        ));
+    }
+}
@@ -0,0 +1,985 @@
+use crate::{
+    edit::TextEdit,
+    fqn::call_name,
+    model::{
+        AccessOperation, Anchor, Call, Document, DocumentElement, DocumentSource, Expression,
+        InputDeclaration, Span, Type, Workflow, WorkflowElement, WorkflowNestedElement,
+    },
+    navigate::find_struct,
+    sourcemap::SourceMap,
+    tree::{walk, Node},
+    workspace::Workspace,
+};
+use error_stack::{bail, Report, Result, ResultExt};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Deref,
+    path::Path,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum FlattenError {
+    #[error("document {0} is not in the workspace")]
+    MissingDocument(DocumentSource),
+    #[error("the source text of document {0} is not available")]
+    MissingText(DocumentSource),
+    #[error("call to {0} cannot be inlined because it is not a task or workflow in an imported document")]
+    UnsupportedCall(String),
+    #[error("call {call} cannot be inlined because it does not provide required input {input}")]
+    MissingInput { call: String, input: String },
+    #[error("error rewriting document {0}")]
+    Edit(DocumentSource),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FlattenOptions {
+    /// Whether calls to the workflows of imported documents are inlined. If `false`, such calls
+    /// result in an error.
+    pub inline_workflows: bool,
+}
+
+/// A flattened document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Flattened {
+    pub text: String,
+    /// The mappings from `text` to the source documents. Each element that was copied from a
+    /// source document is mapped to the element as a whole, and the parts of it that were not
+    /// changed (e.g. by renaming) are mapped verbatim.
+    pub source_map: SourceMap,
+}
+
+/// Returns the documents imported by `source` and, recursively, by the documents it imports,
+/// such that each document comes after the documents it imports.
+fn imported_documents<'a>(
+    workspace: &'a Workspace,
+    source: &DocumentSource,
+    visited: &mut HashSet<DocumentSource>,
+    documents: &mut Vec<&'a Document>,
+) -> Result<(), FlattenError> {
+    let document = workspace
+        .get(source)
+        .ok_or_else(|| FlattenError::MissingDocument(source.clone()))?;
+    for element in document.body_iter() {
+        if let DocumentElement::Import(import) = element {
+            let imported = workspace.resolve_source(source, import.uri.as_str());
+            if visited.insert(imported.clone()) {
+                imported_documents(workspace, &imported, visited, documents)?;
+                documents.push(workspace.get(&imported).unwrap())
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the file name of `source` without its extension, with any characters that are not
+/// valid in identifiers replaced by `_`.
+pub(super) fn file_stem(source: &DocumentSource) -> String {
+    let stem = match source {
+        DocumentSource::File(path) => path.file_stem(),
+        DocumentSource::Uri(uri) => Path::new(uri.as_str()).file_stem(),
+        DocumentSource::Unknown => None,
+    };
+    stem.map(|stem| {
+        stem.to_string_lossy()
+            .replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+    })
+    .unwrap_or_else(|| "imported".to_owned())
+}
+
+/// A workflow whose elements are being copied, i.e. the workflow of the entrypoint document or a
+/// workflow that is inlined. Elements outside of any workflow use a context with no locals.
+struct Context<'a> {
+    document: &'a Document,
+    text: &'a str,
+    /// The prefix that is added to the names of `locals`.
+    prefix: String,
+    /// The names of the inputs, declarations, outputs, non-inlined calls, and scatter variables
+    /// of the workflow.
+    locals: HashSet<&'a str>,
+    /// The names of the calls of the workflow that are inlined.
+    inlined: HashSet<&'a str>,
+}
+
+impl<'a> Context<'a> {
+    /// Returns the name of the local `name` in the flattened document.
+    fn local(&self, name: &str) -> String {
+        if self.locals.contains(name) {
+            format!("{}{}", self.prefix, name)
+        } else {
+            name.to_owned()
+        }
+    }
+}
+
+struct Flattener<'a> {
+    workspace: &'a Workspace,
+    texts: &'a HashMap<DocumentSource, String>,
+    options: &'a FlattenOptions,
+    /// The name in the flattened document of each struct and task, by document, kind, and name.
+    names: HashMap<(DocumentSource, &'static str, String), String>,
+}
+
+impl<'a> Flattener<'a> {
+    fn text(&self, source: &DocumentSource) -> Result<&'a str, FlattenError> {
+        self.texts
+            .get(source)
+            .map(|text| text.as_str())
+            .ok_or_else(|| Report::new(FlattenError::MissingText(source.clone())))
+    }
+
+    /// Names the structs and tasks of `documents`, which are given in order of priority. Each
+    /// element keeps its name unless the name is already taken, in which case it is prefixed with
+    /// the file name of its document. A struct with the same name and definition as a struct that
+    /// is already named is given the same name, so that it is only inserted once.
+    fn assign_names(&mut self, documents: &[&'a Document]) -> Result<(), FlattenError> {
+        // the original name and definition of each name that is taken
+        let mut taken: HashMap<(&'static str, String), (&str, &str)> = HashMap::new();
+        for document in documents.iter() {
+            let text = self.text(&document.source)?;
+            for element in document.body.iter() {
+                let (kind, name) = match element.deref() {
+                    DocumentElement::Struct(struct_) => ("struct", struct_.name.as_str()),
+                    DocumentElement::Task(task) => ("task", task.name.as_str()),
+                    _ => continue,
+                };
+                let definition = &text[element.span.start.offset..element.span.end.offset];
+                let identical = taken.iter().find(|((k, _), (n, d))| {
+                    kind == "struct" && *k == kind && *n == name && *d == definition
+                });
+                let assigned = match identical {
+                    Some(((_, existing), _)) => existing.clone(),
+                    None => {
+                        let mut candidate = name.to_owned();
+                        let mut suffix = 1;
+                        while taken.contains_key(&(kind, candidate.clone())) {
+                            candidate = match suffix {
+                                1 => format!("{}_{}", file_stem(&document.source), name),
+                                _ => format!("{}_{}_{}", file_stem(&document.source), name, suffix),
+                            };
+                            suffix += 1;
+                        }
+                        taken.insert((kind, candidate.clone()), (name, definition));
+                        candidate
+                    }
+                };
+                self.names
+                    .insert((document.source.clone(), kind, name.to_owned()), assigned);
+            }
+        }
+        Ok(())
+    }
+
+    fn name(&self, source: &DocumentSource, kind: &'static str, name: &str) -> Option<&str> {
+        self.names
+            .get(&(source.clone(), kind, name.to_owned()))
+            .map(|name| name.as_str())
+    }
+
+    /// Returns the name in the flattened document of the struct that `name` refers to in
+    /// `document`, which may be an import alias.
+    fn struct_name(&self, document: &Document, name: &str) -> Option<&str> {
+        let location = find_struct(self.workspace, document, name, &mut Vec::new())?;
+        let defining = self.workspace.get(&location.source).unwrap_or(document);
+        defining.body_iter().find_map(|element| match element {
+            DocumentElement::Struct(struct_) if struct_.name.span == location.span => {
+                self.name(&location.source, "struct", struct_.name.as_str())
+            }
+            _ => None,
+        })
+    }
+
+    /// Returns the imported workflow called by `call` in `document`, and the document that
+    /// contains it, if the call is to be inlined.
+    fn inlined_workflow(
+        &self,
+        document: &'a Document,
+        call: &Call,
+    ) -> Option<(&'a Document, &'a Workflow)> {
+        if !self.options.inline_workflows || call.target.parts.len() != 2 {
+            return None;
+        }
+        let (_, imported) = self
+            .workspace
+            .resolve_namespace(document, call.target.parts[0].as_str())?;
+        imported?.body_iter().find_map(|element| match element {
+            DocumentElement::Workflow(workflow)
+                if workflow.name.as_str() == call.target.parts[1].as_str() =>
+            {
+                Some((imported.unwrap(), workflow))
+            }
+            _ => None,
+        })
+    }
+
+    fn context(
+        &self,
+        document: &'a Document,
+        workflow: Option<&'a Workflow>,
+        prefix: String,
+    ) -> Result<Context<'a>, FlattenError> {
+        let mut context = Context {
+            document,
+            text: self.text(&document.source)?,
+            prefix,
+            locals: HashSet::new(),
+            inlined: HashSet::new(),
+        };
+        let add_call = |context: &mut Context<'a>, call: &'a Call| {
+            if self.inlined_workflow(document, call).is_some() {
+                context.inlined.insert(call_name(call));
+            } else {
+                context.locals.insert(call_name(call));
+            }
+        };
+        let mut nested: Vec<&'a WorkflowNestedElement> = Vec::new();
+        for element in workflow.iter().flat_map(|workflow| workflow.body.iter()) {
+            match element.deref() {
+                WorkflowElement::Input(input) => context
+                    .locals
+                    .extend(input.declarations.iter().map(|decl| decl.name().as_str())),
+                WorkflowElement::Output(output) => context
+                    .locals
+                    .extend(output.declarations.iter().map(|decl| decl.name.as_str())),
+                WorkflowElement::Declaration(decl) => {
+                    context.locals.insert(decl.name.as_str());
+                }
+                WorkflowElement::Call(call) => add_call(&mut context, call),
+                WorkflowElement::Scatter(scatter) => {
+                    context.locals.insert(scatter.name.as_str());
+                    nested.extend(scatter.body.iter().map(|element| element.deref()))
+                }
+                WorkflowElement::Conditional(conditional) => {
+                    nested.extend(conditional.body.iter().map(|element| element.deref()))
+                }
+                WorkflowElement::Meta(_) | WorkflowElement::ParameterMeta(_) => (),
+            }
+        }
+        while let Some(element) = nested.pop() {
+            match element {
+                WorkflowNestedElement::Declaration(decl) => {
+                    context.locals.insert(decl.name.as_str());
+                }
+                WorkflowNestedElement::Call(call) => add_call(&mut context, call),
+                WorkflowNestedElement::Scatter(scatter) => {
+                    context.locals.insert(scatter.name.as_str());
+                    nested.extend(scatter.body.iter().map(|element| element.deref()))
+                }
+                WorkflowNestedElement::Conditional(conditional) => {
+                    nested.extend(conditional.body.iter().map(|element| element.deref()))
+                }
+            }
+        }
+        Ok(context)
+    }
+
+    /// Returns the text of `span` of the document of `context` as it appears in the flattened
+    /// document.
+    fn render(&self, context: &Context<'a>, span: &Span) -> Result<String, FlattenError> {
+        self.render_mapped(context, span).map(|(text, _)| text)
+    }
+
+    /// Returns the text of `span` of the document of `context` as it appears in the flattened
+    /// document, and the mappings from the text to `span`.
+    fn render_mapped(
+        &self,
+        context: &Context<'a>,
+        span: &Span,
+    ) -> Result<(String, SourceMap), FlattenError> {
+        let range = span.start.offset..span.end.offset;
+        let (text, mut source_map) = SourceMap::from_edits_in(
+            context.text,
+            range.clone(),
+            &context.document.source,
+            &self.edits(context, span)?,
+        )
+        .change_context_lazy(|| FlattenError::Edit(context.document.source.clone()))?;
+        let verbatim = text == context.text[range];
+        source_map.add(
+            0..text.len(),
+            context.document.source.clone(),
+            span.clone(),
+            verbatim,
+        );
+        Ok((text, source_map))
+    }
+
+    /// Returns the edits that rename the elements and references within `span` of the document of
+    /// `context`, and that replace the inlined calls within `span`.
+    fn edits(&self, context: &Context<'a>, span: &Span) -> Result<Vec<TextEdit>, FlattenError> {
+        let document = context.document;
+        let mut nodes = Vec::new();
+        walk(document, &mut |node, _| {
+            if span.contains_span(node.span()) {
+                nodes.push(node)
+            }
+        });
+        fn rename(edits: &mut Vec<TextEdit>, name: &Anchor<String>, to: &str) {
+            if name.as_str() != to {
+                edits.push(TextEdit::replace(name.span.clone(), to))
+            }
+        }
+        let mut edits = Vec::new();
+        // the spans whose nodes have already been replaced
+        let mut replaced: Vec<&Span> = Vec::new();
+        for node in nodes {
+            if replaced
+                .iter()
+                .any(|outer| outer.contains_span(node.span()))
+            {
+                continue;
+            }
+            match node {
+                Node::DocumentElement(element) => match element.deref() {
+                    DocumentElement::Struct(struct_) => {
+                        if let Some(to) =
+                            self.name(&document.source, "struct", struct_.name.as_str())
+                        {
+                            rename(&mut edits, &struct_.name, to)
+                        }
+                    }
+                    DocumentElement::Task(task) => {
+                        if let Some(to) = self.name(&document.source, "task", task.name.as_str()) {
+                            rename(&mut edits, &task.name, to)
+                        }
+                    }
+                    _ => (),
+                },
+                Node::WorkflowElement(element) => match element.deref() {
+                    WorkflowElement::Declaration(decl) => {
+                        rename(&mut edits, &decl.name, &context.local(&decl.name))
+                    }
+                    WorkflowElement::Call(call) => {
+                        match self.inline(context, call, &element.span)? {
+                            Some(text) => {
+                                edits.push(TextEdit::replace(element.span.clone(), text));
+                                replaced.push(&element.span);
+                            }
+                            None => self.rename_call(context, call, &mut edits)?,
+                        }
+                    }
+                    WorkflowElement::Scatter(scatter) => {
+                        rename(&mut edits, &scatter.name, &context.local(&scatter.name))
+                    }
+                    _ => (),
+                },
+                Node::WorkflowNestedElement(element) => match element.deref() {
+                    WorkflowNestedElement::Declaration(decl) => {
+                        rename(&mut edits, &decl.name, &context.local(&decl.name))
+                    }
+                    WorkflowNestedElement::Call(call) => {
+                        match self.inline(context, call, &element.span)? {
+                            Some(text) => {
+                                edits.push(TextEdit::replace(element.span.clone(), text));
+                                replaced.push(&element.span);
+                            }
+                            None => self.rename_call(context, call, &mut edits)?,
+                        }
+                    }
+                    WorkflowNestedElement::Scatter(scatter) => {
+                        rename(&mut edits, &scatter.name, &context.local(&scatter.name))
+                    }
+                    _ => (),
+                },
+                Node::InputDeclaration(decl) => {
+                    rename(&mut edits, decl.name(), &context.local(decl.name()))
+                }
+                Node::BoundDeclaration(decl) => {
+                    rename(&mut edits, &decl.name, &context.local(&decl.name))
+                }
+                Node::CallInput(input) if input.expression.is_none() => {
+                    // the shorthand `name` becomes `name = <renamed local>`
+                    let to = context.local(&input.name);
+                    if to != *input.name {
+                        edits.push(TextEdit::insert(
+                            input.name.span.end.clone(),
+                            format!(" = {}", to),
+                        ))
+                    }
+                }
+                Node::Type(type_) => {
+                    if let Type::User(name) = type_.deref() {
+                        if let Some(to) = self.struct_name(document, name) {
+                            if to != name {
+                                edits.push(TextEdit::replace(type_.span.clone(), to))
+                            }
+                        }
+                    }
+                }
+                Node::Expression(expression) => match expression.deref() {
+                    Expression::Identifier(name) => {
+                        let to = context.local(name);
+                        if to != *name {
+                            edits.push(TextEdit::replace(expression.span.clone(), to))
+                        }
+                    }
+                    Expression::Object(object) => {
+                        if let Some(to) = self.struct_name(document, &object.type_name) {
+                            rename(&mut edits, &object.type_name, to)
+                        }
+                    }
+                    Expression::Access(access) => {
+                        // `call.output` of an inlined call becomes the name of the output's
+                        // declaration
+                        let first = access.accesses.first();
+                        if let (Expression::Identifier(call), Some(first)) =
+                            (access.collection.deref().deref(), first)
+                        {
+                            if let AccessOperation::Field(output) = first.deref() {
+                                if context.inlined.contains(call.as_str()) {
+                                    let mut span = access.collection.span.clone();
+                                    span.end = first.span.end.clone();
+                                    edits.push(TextEdit::replace(
+                                        span,
+                                        format!("{}{}_{}", context.prefix, call, output),
+                                    ));
+                                    replaced.push(&access.collection.span);
+                                    replaced.push(&first.span);
+                                }
+                            }
+                        }
+                    }
+                    _ => (),
+                },
+                _ => (),
+            }
+        }
+        Ok(edits)
+    }
+
+    /// Adds the edits that rewrite `call`, which is not inlined, to `edits`: the target becomes the
+    /// name of the task in the flattened document, and the call keeps its (local) name, so that
+    /// references to its outputs still resolve. If the name of the task differs from the name of
+    /// the call, the call is aliased, e.g. `call a.t` becomes `call a_t as t`.
+    fn rename_call(
+        &self,
+        context: &Context,
+        call: &Call,
+        edits: &mut Vec<TextEdit>,
+    ) -> Result<(), FlattenError> {
+        let target = &call.target;
+        let task = target.parts.last().unwrap().as_str();
+        let to_task = match target.parts.len() {
+            1 => self.name(&context.document.source, "task", task),
+            2 => self
+                .workspace
+                .resolve_namespace(context.document, target.parts[0].as_str())
+                .and_then(|(_, imported)| imported)
+                .and_then(|imported| self.name(&imported.source, "task", task)),
+            _ => None,
+        };
+        let to_task = match to_task {
+            Some(to_task) => to_task,
+            None => {
+                let parts: Vec<&str> = target.parts.iter().map(|part| part.as_str()).collect();
+                bail!(FlattenError::UnsupportedCall(parts.join(".")))
+            }
+        };
+        let name = call_name(call);
+        let to = context.local(name);
+        match &call.alias {
+            Some(alias) => {
+                if target.parts.len() > 1 || to_task != task {
+                    edits.push(TextEdit::replace(target.span.clone(), to_task))
+                }
+                if to != name {
+                    edits.push(TextEdit::replace(alias.span.clone(), to))
+                }
+            }
+            None => {
+                let alias = if to_task != to {
+                    format!("{} as {}", to_task, to)
+                } else {
+                    to_task.to_owned()
+                };
+                if target.parts.len() > 1 || alias != task {
+                    edits.push(TextEdit::replace(target.span.clone(), alias))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the text that replaces `call`, whose element has the given span, if it is a call
+    /// to an imported workflow that is inlined.
+    fn inline(
+        &self,
+        context: &Context<'a>,
+        call: &'a Call,
+        span: &Span,
+    ) -> Result<Option<String>, FlattenError> {
+        let (document, workflow) = match self.inlined_workflow(context.document, call) {
+            Some(inlined) => inlined,
+            None => return Ok(None),
+        };
+        let name = call_name(call);
+        let inner = self.context(
+            document,
+            Some(workflow),
+            format!("{}{}_", context.prefix, name),
+        )?;
+        let mut lines = Vec::new();
+        let mut outputs = Vec::new();
+        for element in workflow.body.iter() {
+            match element.deref() {
+                WorkflowElement::Input(input) => {
+                    for decl in input.declarations.iter() {
+                        let input_name = decl.name().as_str();
+                        let provided = call
+                            .inputs
+                            .iter()
+                            .flatten()
+                            .find(|input| input.name.as_str() == input_name);
+                        let value = match (provided, decl.deref()) {
+                            (Some(input), _) => match &input.expression {
+                                Some(expression) => self.render(context, &expression.span)?,
+                                None => context.local(input_name),
+                            },
+                            (None, InputDeclaration::Bound(bound)) => {
+                                self.render(&inner, &bound.expression.span)?
+                            }
+                            (None, InputDeclaration::Unbound(unbound))
+                                if matches!(unbound.type_.deref(), Type::Optional(_)) =>
+                            {
+                                "None".to_owned()
+                            }
+                            (None, InputDeclaration::Unbound(_)) => {
+                                bail!(FlattenError::MissingInput {
+                                    call: name.to_owned(),
+                                    input: input_name.to_owned(),
+                                })
+                            }
+                        };
+                        lines.push(format!(
+                            "{} {} = {}",
+                            self.render(&inner, &decl.type_().span)?,
+                            inner.local(input_name),
+                            value
+                        ))
+                    }
+                }
+                WorkflowElement::Output(output) => {
+                    for decl in output.declarations.iter() {
+                        outputs.push(self.render(&inner, &decl.span)?)
+                    }
+                }
+                WorkflowElement::Meta(_) | WorkflowElement::ParameterMeta(_) => (),
+                _ => lines.push(self.render(&inner, &element.span)?),
+            }
+        }
+        lines.extend(outputs);
+        let separator = format!("\n{}", " ".repeat(span.start.column));
+        Ok(Some(lines.join(&separator)))
+    }
+}
+
+/// Flattens the document `entry` of `workspace` and the documents that it imports into a single
+/// document with no imports, e.g. for engines that do not support imports. `texts` must contain
+/// the source text of each of the documents.
+///
+/// The structs and tasks of the imported documents are inserted after the version statement of
+/// `entry`, the imports of `entry` are removed, and call targets and struct types are rewritten
+/// to refer to the inserted elements. Structs and tasks whose names are already taken (by
+/// `entry`, which has priority, or by a document that was inserted earlier) are renamed by
+/// prefixing the file name of their document, e.g. the task `align` of `lib/bwa.wdl` becomes
+/// `bwa_align`. Structs that are defined identically in more than one document are inserted once.
+///
+/// If `options.inline_workflows` is set, each call to a workflow of an imported document is
+/// replaced by the body of that workflow, in which each name is prefixed with the name of the
+/// call: the inputs of the workflow become declarations that are bound to the inputs of the call
+/// (or to their defaults), and its outputs become declarations to which references to the
+/// outputs of the call are rewritten, e.g. `sub.out` becomes `sub_out`. Otherwise, calls to
+/// imported workflows result in an error.
+pub fn flatten_with(
+    workspace: &Workspace,
+    texts: &HashMap<DocumentSource, String>,
+    entry: &DocumentSource,
+    options: &FlattenOptions,
+) -> Result<Flattened, FlattenError> {
+    let document = workspace
+        .get(entry)
+        .ok_or_else(|| FlattenError::MissingDocument(entry.clone()))?;
+    let mut visited = HashSet::from([entry.clone()]);
+    let mut imported = Vec::new();
+    imported_documents(workspace, entry, &mut visited, &mut imported)?;
+    let mut flattener = Flattener {
+        workspace,
+        texts,
+        options,
+        names: HashMap::new(),
+    };
+    let prioritized: Vec<&Document> = std::iter::once(document)
+        .chain(imported.iter().copied())
+        .collect();
+    flattener.assign_names(&prioritized)?;
+    let text = flattener.text(entry)?;
+
+    let version_end = document.version.span.end.offset;
+    let mut output = text[..version_end].to_owned();
+    let mut source_map = SourceMap::new();
+    source_map.add_verbatim(0, entry.clone(), text, 0..version_end);
+    let mut inserted = HashSet::new();
+    for doc in imported.iter() {
+        let context = flattener.context(doc, None, String::new())?;
+        for element in doc.body.iter() {
+            let key = match element.deref() {
+                DocumentElement::Struct(struct_) => (
+                    "struct",
+                    flattener.name(&doc.source, "struct", struct_.name.as_str()),
+                ),
+                DocumentElement::Task(task) => (
+                    "task",
+                    flattener.name(&doc.source, "task", task.name.as_str()),
+                ),
+                _ => continue,
+            };
+            if !inserted.insert(key) {
+                continue;
+            }
+            output.push_str("\n\n");
+            let (rendered, rendered_map) = flattener.render_mapped(&context, &element.span)?;
+            source_map.append(rendered_map, output.len());
+            output.push_str(&rendered);
+        }
+    }
+
+    let mut offset = version_end;
+    for element in document.body.iter() {
+        let span = &element.span;
+        source_map.add_verbatim(output.len(), entry.clone(), text, offset..span.start.offset);
+        output.push_str(&text[offset..span.start.offset]);
+        offset = span.end.offset;
+        let workflow = match element.deref() {
+            DocumentElement::Import(_) => {
+                // also remove the line break that follows the import
+                if text[offset..].starts_with('\n') {
+                    offset += 1;
+                }
+                continue;
+            }
+            DocumentElement::Workflow(workflow) => Some(workflow),
+            _ => None,
+        };
+        let context = flattener.context(document, workflow, String::new())?;
+        let (rendered, rendered_map) = flattener.render_mapped(&context, span)?;
+        source_map.append(rendered_map, output.len());
+        output.push_str(&rendered);
+    }
+    source_map.add_verbatim(output.len(), entry.clone(), text, offset..text.len());
+    output.push_str(&text[offset..]);
+    Ok(Flattened {
+        text: output,
+        source_map,
+    })
+}
+
+/// Flattens the document `entry` of `workspace` with the default options and returns the
+/// flattened text. See `flatten_with`.
+pub fn flatten(
+    workspace: &Workspace,
+    texts: &HashMap<DocumentSource, String>,
+    entry: &DocumentSource,
+) -> Result<String, FlattenError> {
+    flatten_with(workspace, texts, entry, &FlattenOptions::default())
+        .map(|flattened| flattened.text)
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        parsers::{PestParser, WdlParser},
+        sourcemap::position_at,
+    };
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    fn load(files: &[(&str, &str)]) -> (Workspace, HashMap<DocumentSource, String>) {
+        let mut parser = PestParser::new();
+        let mut workspace = Workspace::new();
+        let mut texts = HashMap::new();
+        for (path, text) in files {
+            let source = DocumentSource::File(PathBuf::from(path));
+            workspace.insert(parser.parse_text(text, source.clone()).unwrap());
+            texts.insert(source, text.to_string());
+        }
+        (workspace, texts)
+    }
+
+    #[test]
+    fn test_flatten() {
+        let main = r#"version 1.1
+
+import "lib/align.wdl" as align
+import "common.wdl"
+
+workflow main {
+    call align.bwa { input: reads = Reads { fastq: "r.fq" } }
+    call local
+}
+
+task local {
+    command <<< >>>
+}
+"#;
+        let align = r#"version 1.1
+
+import "../common.wdl"
+
+task bwa {
+    input {
+        Reads reads
+    }
+    command <<< bwa mem >>>
+}
+"#;
+        let common = r#"version 1.1
+
+struct Reads {
+    File fastq
+}
+"#;
+        let (workspace, texts) = load(&[
+            ("/w/main.wdl", main),
+            ("/w/lib/align.wdl", align),
+            ("/w/common.wdl", common),
+        ]);
+        let flattened = flatten(
+            &workspace,
+            &texts,
+            &DocumentSource::File(PathBuf::from("/w/main.wdl")),
+        )
+        .unwrap();
+        assert_eq!(
+            flattened,
+            r#"version 1.1
+
+struct Reads {
+    File fastq
+}
+
+task bwa {
+    input {
+        Reads reads
+    }
+    command <<< bwa mem >>>
+}
+
+workflow main {
+    call bwa { input: reads = Reads { fastq: "r.fq" } }
+    call local
+}
+
+task local {
+    command <<< >>>
+}
+"#
+        );
+        let reparsed = PestParser::new()
+            .parse_text(&flattened, DocumentSource::Unknown)
+            .unwrap();
+        assert_eq!(reparsed.body.len(), 4);
+
+        // the imported task is renamed to avoid a collision with the local task, and the call
+        // keeps its name
+        let main = r#"version 1.1
+import "a.wdl"
+workflow w {
+    call a.t
+    call t as u
+    output {
+        String out = t.out
+    }
+}
+task t { command <<< >>> }
+"#;
+        let (workspace, texts) = load(&[
+            ("/w/main.wdl", main),
+            (
+                "/w/a.wdl",
+                "version 1.1\ntask t {\n    command <<< echo >>>\n    output { String out = \"\" }\n}\n",
+            ),
+        ]);
+        let flattened = flatten(
+            &workspace,
+            &texts,
+            &DocumentSource::File(PathBuf::from("/w/main.wdl")),
+        )
+        .unwrap();
+        assert_eq!(
+            flattened,
+            r#"version 1.1
+
+task a_t {
+    command <<< echo >>>
+    output { String out = "" }
+}
+workflow w {
+    call a_t as t
+    call t as u
+    output {
+        String out = t.out
+    }
+}
+task t { command <<< >>> }
+"#
+        );
+        let reparsed = PestParser::new()
+            .parse_text(&flattened, DocumentSource::Unknown)
+            .unwrap();
+        let workflow = reparsed
+            .body_iter()
+            .find_map(|element| match element {
+                DocumentElement::Workflow(workflow) => Some(workflow),
+                _ => None,
+            })
+            .unwrap();
+        let calls: Vec<&str> = workflow
+            .body
+            .iter()
+            .filter_map(|element| match element.deref() {
+                WorkflowElement::Call(call) => Some(call_name(call)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(calls, vec!["t", "u"]);
+    }
+
+    #[test]
+    fn test_flatten_inline_workflows() {
+        let main = r#"version 1.1
+
+import "sub.wdl"
+
+struct Sample {
+    String id
+}
+
+workflow main {
+    input {
+        Array[String] ids
+    }
+    scatter (id in ids) {
+        call sub.process { input: id }
+    }
+    output {
+        Array[File] results = process.result
+    }
+}
+
+task run {
+    command <<< >>>
+}
+"#;
+        let sub = r#"version 1.1
+
+struct Sample {
+    String name
+}
+
+workflow process {
+    input {
+        String id
+        Int threads = 2
+        File? reference
+    }
+    Sample sample = Sample { name: id }
+    call run { input: name = sample.name, threads }
+    output {
+        File result = run.out
+    }
+}
+
+task run {
+    input {
+        String name
+        Int threads
+    }
+    command <<< >>>
+    output {
+        File out = "out.txt"
+    }
+}
+"#;
+        let (workspace, texts) = load(&[("/w/main.wdl", main), ("/w/sub.wdl", sub)]);
+        let entry = DocumentSource::File(PathBuf::from("/w/main.wdl"));
+        let err = flatten(&workspace, &texts, &entry).unwrap_err();
+        assert!(matches!(
+            err.current_context(),
+            FlattenError::UnsupportedCall(target) if target == "sub.process"
+        ));
+
+        let options = FlattenOptions {
+            inline_workflows: true,
+        };
+        let flattened = flatten_with(&workspace, &texts, &entry, &options).unwrap();
+        assert_eq!(
+            flattened.text,
+            r#"version 1.1
+
+struct sub_Sample {
+    String name
+}
+
+task sub_run {
+    input {
+        String name
+        Int threads
+    }
+    command <<< >>>
+    output {
+        File out = "out.txt"
+    }
+}
+
+struct Sample {
+    String id
+}
+
+workflow main {
+    input {
+        Array[String] ids
+    }
+    scatter (id in ids) {
+        String process_id = id
+        Int process_threads = 2
+        File? process_reference = None
+        sub_Sample process_sample = sub_Sample { name: process_id }
+        call sub_run as process_run { input: name = process_sample.name, threads = process_threads }
+        File process_result = process_run.out
+    }
+    output {
+        Array[File] results = process_result
+    }
+}
+
+task run {
+    command <<< >>>
+}
+"#
+        );
+        PestParser::new()
+            .parse_text(&flattened.text, DocumentSource::Unknown)
+            .unwrap();
+        // renamed and inlined text is mapped to the element it replaces, other text is mapped
+        // exactly
+        let original = |pattern: &str| {
+            let start = flattened.text.find(pattern).unwrap();
+            let span = Span {
+                start: position_at(&flattened.text, start),
+                end: position_at(&flattened.text, start + pattern.len()),
+            };
+            let (source, span) = flattened
+                .source_map
+                .original(&flattened.text, &span)
+                .unwrap();
+            (source.clone(), span.start.line, span.start.column)
+        };
+        let sub = DocumentSource::File(PathBuf::from("/w/sub.wdl"));
+        assert_eq!(original("sub_run"), (sub.clone(), 19, 5));
+        assert_eq!(original("command"), (sub, 24, 4));
+        assert_eq!(original("File process_result"), (entry.clone(), 13, 8));
+        assert_eq!(original("results"), (entry.clone(), 16, 20));
+        assert_eq!(original("version"), (entry, 0, 0));
+    }
+}
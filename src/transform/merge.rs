@@ -0,0 +1,404 @@
+use crate::{
+    edit::TextEdit,
+    model::{Document, DocumentElement, DocumentSource, Expression, ObjectKind, Span, Type},
+    sourcemap::SourceMap,
+    transform::{flatten::file_stem, rename::fresh_name},
+    tree::{walk, Node},
+};
+use error_stack::{bail, Result, ResultExt};
+use std::{collections::HashMap, ops::Deref};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum MergeError {
+    #[error("there are no documents to merge")]
+    NoDocuments,
+    #[error("document {0} has a different version than the first document")]
+    VersionMismatch(DocumentSource),
+    #[error("the source text of document {0} is not available")]
+    MissingText(DocumentSource),
+    #[error("{kind} {name} of document {document} conflicts with a {kind} of an earlier document")]
+    Conflict {
+        kind: &'static str,
+        name: String,
+        document: DocumentSource,
+    },
+    #[error("error rewriting document {0}")]
+    Edit(DocumentSource),
+}
+
+/// How to handle a struct or task whose name is already taken by a different struct or task of an
+/// earlier document. Structs and tasks with the same name and the same definition are always
+/// merged into one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConflictResolution {
+    /// Rename the struct or task by prefixing the file name of its document, e.g. the task
+    /// `align` of `lib/bwa.wdl` becomes `bwa_align`.
+    #[default]
+    Rename,
+    /// Keep the struct or task of the earlier document and omit the later one. References to an
+    /// omitted struct refer to the kept struct.
+    KeepFirst,
+    /// Return a `MergeError::Conflict` error.
+    Error,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MergeOptions {
+    pub conflicts: ConflictResolution,
+}
+
+/// A struct or task that was renamed or omitted when merging documents.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MergedElement {
+    pub source: DocumentSource,
+    /// `struct` or `task`.
+    pub kind: &'static str,
+    pub name: String,
+    /// The name of the element in the merged document, which is the name of the element that
+    /// replaces it if it was omitted.
+    pub merged_name: String,
+}
+
+/// A merged document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Merged {
+    pub text: String,
+    /// The mappings from `text` to the source documents.
+    pub source_map: SourceMap,
+    /// The structs and tasks that were renamed, in the order in which they were merged.
+    pub renamed: Vec<MergedElement>,
+    /// The structs and tasks that were omitted because they were identical to, or conflicted
+    /// with (if the conflict resolution is `KeepFirst`), an element of an earlier document.
+    pub omitted: Vec<MergedElement>,
+}
+
+struct Merger<'a> {
+    /// The name in the merged document of each struct and task, by document, kind, and name.
+    names: HashMap<(&'a DocumentSource, &'static str, &'a str), String>,
+    /// The first document that defines each struct.
+    structs: HashMap<&'a str, &'a DocumentSource>,
+}
+
+impl<'a> Merger<'a> {
+    /// Returns the name in the merged document of the struct `name` that is referenced by
+    /// `document`: the struct of `document` if it defines one with that name, otherwise the
+    /// struct of the first document that does.
+    fn struct_name(&self, document: &'a Document, name: &'a str) -> Option<&str> {
+        self.names
+            .get(&(&document.source, "struct", name))
+            .or_else(|| {
+                let source = self.structs.get(name)?;
+                self.names.get(&(*source, "struct", name))
+            })
+            .map(|name| name.as_str())
+    }
+
+    /// Returns the edits that rename the structs and tasks within `element` of `document`, and
+    /// the references to structs.
+    fn edits(&self, document: &'a Document, element: &DocumentElement) -> Vec<TextEdit> {
+        let mut edits = Vec::new();
+        let mut rename = |name: &str, to: Option<&str>, span: &Span| {
+            if let Some(to) = to.filter(|to| *to != name) {
+                edits.push(TextEdit::replace(span.clone(), to))
+            }
+        };
+        match element {
+            DocumentElement::Struct(struct_) => rename(
+                struct_.name.as_str(),
+                self.struct_name(document, struct_.name.as_str()),
+                &struct_.name.span,
+            ),
+            DocumentElement::Task(task) => rename(
+                task.name.as_str(),
+                self.names
+                    .get(&(&document.source, "task", task.name.as_str()))
+                    .map(|name| name.as_str()),
+                &task.name.span,
+            ),
+            _ => (),
+        }
+        walk(document, &mut |node, _| match node {
+            Node::Type(type_) => {
+                if let Type::User(name) = type_.deref() {
+                    rename(name, self.struct_name(document, name), &type_.span)
+                }
+            }
+            Node::Expression(expression) => {
+                if let Expression::Object(object) = expression.deref() {
+                    if object.kind == ObjectKind::Struct {
+                        let name = object.type_name.as_str();
+                        rename(
+                            name,
+                            self.struct_name(document, name),
+                            &object.type_name.span,
+                        )
+                    }
+                }
+            }
+            _ => (),
+        });
+        edits
+    }
+}
+
+/// Merges the structs and tasks of `documents` into a single document, e.g. to deliver a library
+/// of tasks that is maintained as several documents as a single file. The documents must have
+/// the same version and their source text must be available (see `Document::source_text`).
+///
+/// The structs and tasks are copied in the order of the documents and in the order in which they
+/// are defined in each document. Workflows and imports are omitted, and references to structs
+/// that were imported refer to the merged struct with the same name, if any. Structs and tasks
+/// whose names are already taken by an earlier document are merged into one if they have the
+/// same definition, and otherwise are handled according to `options.conflicts`.
+pub fn merge(documents: &[&Document], options: &MergeOptions) -> Result<Merged, MergeError> {
+    let first = match documents.first() {
+        Some(first) => first,
+        None => bail!(MergeError::NoDocuments),
+    };
+    let mut texts = Vec::new();
+    for document in documents.iter() {
+        if *document.version.identifier != *first.version.identifier {
+            bail!(MergeError::VersionMismatch(document.source.clone()))
+        }
+        match document.source_text() {
+            Some(text) => texts.push(text.as_str()),
+            None => bail!(MergeError::MissingText(document.source.clone())),
+        }
+    }
+
+    let mut merger = Merger {
+        names: HashMap::new(),
+        structs: HashMap::new(),
+    };
+    // the definition of each name that is taken, by kind and name
+    let mut taken: HashMap<(&'static str, String), &str> = HashMap::new();
+    let mut renamed = Vec::new();
+    let mut omitted = Vec::new();
+    let mut merged = Vec::new();
+    for (document, text) in documents.iter().zip(texts.iter()) {
+        for element in document.body.iter() {
+            let (kind, name) = match element.deref() {
+                DocumentElement::Struct(struct_) => ("struct", struct_.name.as_str()),
+                DocumentElement::Task(task) => ("task", task.name.as_str()),
+                _ => continue,
+            };
+            if kind == "struct" {
+                merger.structs.entry(name).or_insert(&document.source);
+            }
+            let definition = &text[element.span.start.offset..element.span.end.offset];
+            let merged_element = |merged_name: &str| MergedElement {
+                source: document.source.clone(),
+                kind,
+                name: name.to_owned(),
+                merged_name: merged_name.to_owned(),
+            };
+            let assigned = match taken.get(&(kind, name.to_owned())) {
+                None => {
+                    merged.push((*document, *text, element));
+                    name.to_owned()
+                }
+                Some(existing) if *existing == definition => {
+                    omitted.push(merged_element(name));
+                    name.to_owned()
+                }
+                Some(_) => match options.conflicts {
+                    ConflictResolution::Rename => {
+                        let assigned =
+                            fresh_name(&format!("{}_{}", file_stem(&document.source), name), |n| {
+                                taken.contains_key(&(kind, n.to_owned()))
+                            });
+                        renamed.push(merged_element(&assigned));
+                        merged.push((*document, *text, element));
+                        assigned
+                    }
+                    ConflictResolution::KeepFirst => {
+                        omitted.push(merged_element(name));
+                        name.to_owned()
+                    }
+                    ConflictResolution::Error => bail!(MergeError::Conflict {
+                        kind,
+                        name: name.to_owned(),
+                        document: document.source.clone(),
+                    }),
+                },
+            };
+            taken.entry((kind, assigned.clone())).or_insert(definition);
+            merger
+                .names
+                .insert((&document.source, kind, name), assigned);
+        }
+    }
+
+    let version = &first.version.span;
+    let mut text = texts[0][version.start.offset..version.end.offset].to_owned();
+    let mut source_map = SourceMap::new();
+    source_map.add_verbatim(
+        0,
+        first.source.clone(),
+        texts[0],
+        version.start.offset..version.end.offset,
+    );
+    for (document, document_text, element) in merged {
+        let span = &element.span;
+        let edits: Vec<TextEdit> = merger
+            .edits(document, element)
+            .into_iter()
+            .filter(|edit| span.contains_span(&edit.span))
+            .collect();
+        let (rendered, rendered_map) = SourceMap::from_edits_in(
+            document_text,
+            span.start.offset..span.end.offset,
+            &document.source,
+            &edits,
+        )
+        .change_context_lazy(|| MergeError::Edit(document.source.clone()))?;
+        text.push_str("\n\n");
+        source_map.append(rendered_map, text.len());
+        text.push_str(&rendered);
+    }
+    text.push('\n');
+    Ok(Merged {
+        text,
+        source_map,
+        renamed,
+        omitted,
+    })
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::parsers::{ParseOptions, PestParser, WdlParser};
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    fn parse(path: &str, text: &str) -> Document {
+        PestParser::new()
+            .with_parse_options(ParseOptions::default().with_retained_text(true))
+            .parse_text(text, DocumentSource::File(PathBuf::from(path)))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_merge() {
+        let bwa = parse(
+            "lib/bwa.wdl",
+            r#"version 1.1
+
+struct Sample {
+    String id
+}
+
+task align {
+    input {
+        Sample sample
+    }
+    command <<< bwa >>>
+}
+"#,
+        );
+        let bowtie = parse(
+            "lib/bowtie.wdl",
+            r#"version 1.1
+
+import "bwa.wdl"
+
+struct Sample {
+    String id
+}
+
+struct Index {
+    Array[File] files
+}
+
+task align {
+    input {
+        Sample sample
+        Index index
+    }
+    command <<< bowtie >>>
+}
+
+workflow w {
+    call align
+}
+"#,
+        );
+        let merged = merge(&[&bwa, &bowtie], &MergeOptions::default()).unwrap();
+        assert_eq!(
+            merged.text,
+            r#"version 1.1
+
+struct Sample {
+    String id
+}
+
+task align {
+    input {
+        Sample sample
+    }
+    command <<< bwa >>>
+}
+
+struct Index {
+    Array[File] files
+}
+
+task bowtie_align {
+    input {
+        Sample sample
+        Index index
+    }
+    command <<< bowtie >>>
+}
+"#
+        );
+        assert_eq!(
+            merged.renamed,
+            vec![MergedElement {
+                source: bowtie.source.clone(),
+                kind: "task",
+                name: "align".to_owned(),
+                merged_name: "bowtie_align".to_owned(),
+            }]
+        );
+        assert_eq!(merged.omitted.len(), 1);
+        assert_eq!(merged.omitted[0].name, "Sample");
+
+        let kept = merge(
+            &[&bwa, &bowtie],
+            &MergeOptions {
+                conflicts: ConflictResolution::KeepFirst,
+            },
+        )
+        .unwrap();
+        assert!(!kept.text.contains("bowtie"));
+        assert_eq!(kept.omitted.len(), 2);
+
+        let error = merge(
+            &[&bwa, &bowtie],
+            &MergeOptions {
+                conflicts: ConflictResolution::Error,
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error.current_context(),
+            MergeError::Conflict { kind: "task", name, .. } if name == "align"
+        ));
+
+        let unretained = PestParser::new()
+            .parse_text(
+                "version 1.1\n\ntask t {\n    command <<< >>>\n}\n",
+                DocumentSource::Unknown,
+            )
+            .unwrap();
+        assert!(matches!(
+            merge(&[&bwa, &unretained], &MergeOptions::default())
+                .unwrap_err()
+                .current_context(),
+            MergeError::MissingText(_)
+        ));
+    }
+}
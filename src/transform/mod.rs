@@ -0,0 +1,15 @@
+//! Transformations that produce new WDL source text from one or more documents.
+mod constants;
+mod extract;
+mod flatten;
+mod merge;
+mod rename;
+
+pub use constants::{
+    propagate_constants, propagate_constants_with, Constant, PropagateError, PropagateOptions,
+    Propagated, MAX_EXPANDED_RANGE,
+};
+pub use extract::{extract_tasks, ExtractError, Extracted};
+pub use flatten::{flatten, flatten_with, FlattenError, FlattenOptions, Flattened};
+pub use merge::{merge, ConflictResolution, MergeError, MergeOptions, Merged, MergedElement};
+pub use rename::{alpha_rename, fresh_name, rename_symbols, RenameError, Renamed};
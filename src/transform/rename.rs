@@ -0,0 +1,358 @@
+use crate::{
+    analysis::{resolve_scopes, Symbol, SymbolKind},
+    edit::{apply_edits, TextEdit},
+    model::{Call, Document, DocumentElement, Span, WorkflowElement, WorkflowNestedElement},
+    transform::constants::{implicit_inputs, replace_reference},
+    tree::{walk, Node},
+};
+use error_stack::{bail, Result, ResultExt};
+use std::{collections::HashSet, ops::Deref};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum RenameError {
+    #[error("there is no task or workflow named {0}")]
+    UnknownScope(String),
+    #[error("more than one element of {scope} would be named {name}")]
+    Collision { scope: String, name: String },
+    #[error("error rewriting document {0}")]
+    Edit(String),
+}
+
+/// The result of renaming the symbols of a task or workflow.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Renamed {
+    pub text: String,
+    /// The edits that were applied to the source text to produce `text`.
+    pub edits: Vec<TextEdit>,
+    /// Each symbol that was renamed and its new name, ordered by the position of the symbol.
+    pub renames: Vec<(Symbol, String)>,
+}
+
+impl Renamed {
+    /// Returns the new name of the symbol named `name`, if it was renamed.
+    pub fn renamed(&self, name: &str) -> Option<&str> {
+        self.renames
+            .iter()
+            .find(|(symbol, _)| symbol.name == name)
+            .map(|(_, to)| to.as_str())
+    }
+}
+
+/// Returns `name` if `is_taken` returns `false` for it, otherwise the first of `name_2`,
+/// `name_3`, ... for which it returns `false`.
+pub fn fresh_name<F: Fn(&str) -> bool>(name: &str, is_taken: F) -> String {
+    if !is_taken(name) {
+        return name.to_owned();
+    }
+    (2..)
+        .map(|i| format!("{}_{}", name, i))
+        .find(|candidate| !is_taken(candidate))
+        .unwrap()
+}
+
+/// Returns the span of the task or workflow named `scope`.
+fn scope_span<'a>(document: &'a Document, scope: &str) -> Result<&'a Span, RenameError> {
+    for element in document.body.iter() {
+        let name = match &**element {
+            DocumentElement::Task(task) => &task.name,
+            DocumentElement::Workflow(workflow) => &workflow.name,
+            _ => continue,
+        };
+        if name.as_str() == scope {
+            return Ok(&element.span);
+        }
+    }
+    bail!(RenameError::UnknownScope(scope.to_owned()))
+}
+
+/// Renames the symbols (i.e. inputs, declarations, outputs, calls, and scatter variables) of the
+/// task or workflow named `scope` of `document`, whose source is `text`, to the names returned by
+/// `rename`, or leaves them unchanged if it returns `None`. Each reference to a renamed symbol is
+/// rewritten. A call without an alias is renamed by adding an alias, and an input of a call
+/// without an expression (`input: x`) that refers to a renamed symbol is given one (`x = y`).
+///
+/// Renaming inputs and outputs changes the interface of the task or workflow, so callers (and
+/// inputs files) must be updated accordingly. Returns an error if a renamed symbol would have the
+/// same name as another symbol that is defined where it is, i.e. anywhere in the scope, or within
+/// the scatter of a scatter variable (so the variables of sibling scatters may have the same
+/// name).
+pub fn rename_symbols<F: FnMut(&Symbol) -> Option<String>>(
+    document: &Document,
+    text: &str,
+    scope: &str,
+    mut rename: F,
+) -> Result<Renamed, RenameError> {
+    let span = scope_span(document, scope)?;
+    let scopes = resolve_scopes(document);
+    let symbols: Vec<&Symbol> = scopes
+        .symbols
+        .iter()
+        .filter(|symbol| span.contains_span(&symbol.span))
+        .collect();
+    let renames: Vec<(Symbol, String)> = symbols
+        .iter()
+        .filter_map(|symbol| {
+            rename(symbol)
+                .filter(|to| *to != symbol.name)
+                .map(|to| ((*symbol).clone(), to))
+        })
+        .collect();
+
+    let mut calls: Vec<&Call> = Vec::new();
+    // the span of the name of each scatter variable, and the span of its scatter
+    let mut scatters: Vec<(&Span, &Span)> = Vec::new();
+    walk(document, &mut |node, _| match node {
+        Node::WorkflowElement(element) => match element.deref() {
+            WorkflowElement::Call(call) => calls.push(call),
+            WorkflowElement::Scatter(scatter) => scatters.push((&scatter.name.span, &element.span)),
+            _ => (),
+        },
+        Node::WorkflowNestedElement(element) => match element.deref() {
+            WorkflowNestedElement::Call(call) => calls.push(call),
+            WorkflowNestedElement::Scatter(scatter) => {
+                scatters.push((&scatter.name.span, &element.span))
+            }
+            _ => (),
+        },
+        _ => (),
+    });
+    // the span within which a symbol is defined
+    let defined_in = |symbol: &Symbol| -> &Span {
+        scatters
+            .iter()
+            .find(|(name, _)| **name == symbol.span)
+            .map_or(span, |(_, scatter)| *scatter)
+    };
+    let named: Vec<(&Symbol, &String, bool)> = symbols
+        .iter()
+        .map(
+            |symbol| match renames.iter().find(|(renamed, _)| renamed == *symbol) {
+                Some((_, to)) => (*symbol, to, true),
+                None => (*symbol, &symbol.name, false),
+            },
+        )
+        .collect();
+    for (i, (symbol, name, renamed)) in named.iter().enumerate() {
+        let collision = named[..i].iter().any(|(other, other_name, other_renamed)| {
+            let (a, b) = (defined_in(symbol), defined_in(other));
+            name == other_name
+                && (*renamed || *other_renamed)
+                && (a.contains_span(b) || b.contains_span(a))
+        });
+        if collision {
+            bail!(RenameError::Collision {
+                scope: scope.to_owned(),
+                name: (*name).clone(),
+            })
+        }
+    }
+
+    let implicit_inputs = implicit_inputs(document);
+    let mut edits = Vec::new();
+    for (symbol, to) in renames.iter() {
+        // a call without an alias is named by the last part of its target
+        let unaliased = calls.iter().find(|call| {
+            symbol.kind == SymbolKind::Call
+                && call.alias.is_none()
+                && call.target.parts.last().map(|part| &part.span) == Some(&symbol.span)
+        });
+        match unaliased {
+            Some(call) => edits.push(TextEdit::insert(
+                call.target.span.end.clone(),
+                format!(" as {}", to),
+            )),
+            None => edits.push(TextEdit::replace(symbol.span.clone(), to)),
+        }
+        for reference in scopes.references_to(symbol) {
+            edits.extend(replace_reference(
+                text,
+                &reference.span,
+                &symbol.name,
+                to,
+                &implicit_inputs,
+            ))
+        }
+    }
+    edits.sort_by(|a, b| a.span.cmp(&b.span));
+    let text =
+        apply_edits(text, &edits).change_context(RenameError::Edit(document.source.to_string()))?;
+    Ok(Renamed {
+        text,
+        edits,
+        renames,
+    })
+}
+
+/// Renames the private declarations, calls, and scatter variables of the task or workflow named
+/// `scope` whose names are in `avoid` to fresh names, i.e. names that are neither in `avoid` nor
+/// used by any other symbol of the scope, e.g. before inserting the body of the scope into
+/// another workflow that defines the names in `avoid`. Inputs and outputs are not renamed. See
+/// `rename_symbols`.
+pub fn alpha_rename(
+    document: &Document,
+    text: &str,
+    scope: &str,
+    avoid: &HashSet<String>,
+) -> Result<Renamed, RenameError> {
+    let span = scope_span(document, scope)?;
+    let mut taken: HashSet<String> = avoid.clone();
+    taken.extend(
+        resolve_scopes(document)
+            .symbols
+            .into_iter()
+            .filter(|symbol| span.contains_span(&symbol.span))
+            .map(|symbol| symbol.name),
+    );
+    rename_symbols(document, text, scope, |symbol| {
+        let renamed = matches!(
+            symbol.kind,
+            SymbolKind::Declaration | SymbolKind::Call | SymbolKind::ScatterVariable
+        ) && avoid.contains(&symbol.name);
+        if !renamed {
+            return None;
+        }
+        let to = fresh_name(&symbol.name, |name| taken.contains(name));
+        taken.insert(to.clone());
+        Some(to)
+    })
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    const TEXT: &str = r#"version 1.1
+
+task t {
+    input {
+        Int n
+    }
+    String x = "a"
+    command <<< echo ~{x} ~{n} >>>
+    output {
+        String out = x
+    }
+}
+
+workflow w {
+    input {
+        Int n
+    }
+    Int x = n + 1
+    Int x_2 = x
+    scatter (i in range(x)) {
+        call t { input: n }
+    }
+    output {
+        Array[String] outs = t.out
+    }
+}
+"#;
+
+    #[test]
+    fn test_alpha_rename() {
+        let doc = PestParser::new()
+            .parse_text(TEXT, DocumentSource::Unknown)
+            .unwrap();
+        let avoid: HashSet<String> = ["x", "t", "i", "n"]
+            .into_iter()
+            .map(|s| s.to_owned())
+            .collect();
+        let renamed = alpha_rename(&doc, TEXT, "w", &avoid).unwrap();
+        assert_eq!(renamed.renamed("x"), Some("x_3"));
+        assert_eq!(renamed.renamed("n"), None);
+        assert_eq!(
+            renamed.text,
+            TEXT.replace("Int x = n + 1", "Int x_3 = n + 1")
+                .replace("Int x_2 = x", "Int x_2 = x_3")
+                .replace("scatter (i in range(x))", "scatter (i_2 in range(x_3))")
+                .replace("call t {", "call t as t_2 {")
+                .replace("= t.out", "= t_2.out")
+        );
+
+        let renamed = alpha_rename(&doc, TEXT, "t", &avoid).unwrap();
+        assert_eq!(
+            renamed.text,
+            TEXT.replacen("String x = \"a\"", "String x_2 = \"a\"", 1)
+                .replacen("echo ~{x}", "echo ~{x_2}", 1)
+                .replacen("out = x\n", "out = x_2\n", 1)
+        );
+
+        let renamed = rename_symbols(&doc, TEXT, "w", |symbol| match symbol.kind {
+            SymbolKind::Input => Some("count".to_owned()),
+            _ => None,
+        })
+        .unwrap();
+        let (task, workflow) = TEXT.split_at(TEXT.find("workflow w").unwrap());
+        assert_eq!(
+            renamed.text,
+            task.to_owned()
+                + &workflow
+                    .replace("Int n\n", "Int count\n")
+                    .replace("Int x = n + 1", "Int x = count + 1")
+                    .replace("input: n }", "input: n = count }")
+        );
+
+        let error = rename_symbols(&doc, TEXT, "w", |symbol| match symbol.kind {
+            SymbolKind::Declaration => Some("y".to_owned()),
+            _ => None,
+        })
+        .unwrap_err();
+        assert_eq!(
+            error.current_context().to_string(),
+            "more than one element of w would be named y"
+        );
+        assert!(alpha_rename(&doc, TEXT, "missing", &avoid).is_err());
+
+        // the variables of sibling scatters may have the same name, but not the variables of
+        // nested scatters
+        let text = r#"version 1.1
+
+workflow w {
+    input {
+        Array[Int] a
+    }
+    scatter (i in a) {
+        Int x = i
+    }
+    scatter (j in a) {
+        scatter (k in a) {
+            Int y = j + k
+        }
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let rename_to = |from: &'static str, to: &'static str| {
+            rename_symbols(&doc, text, "w", move |symbol| {
+                (symbol.name == from).then(|| to.to_owned())
+            })
+        };
+        let renamed = rename_to("j", "i").unwrap();
+        assert_eq!(
+            renamed.text,
+            text.replace("(j in a)", "(i in a)")
+                .replace("j + k", "i + k")
+        );
+        assert!(rename_to("k", "j").is_err());
+        assert!(rename_to("k", "x").is_err());
+        assert!(rename_to("x", "a").is_err());
+        assert!(rename_symbols(
+            &PestParser::new()
+                .parse_text(&renamed.text, DocumentSource::Unknown)
+                .unwrap(),
+            &renamed.text,
+            "w",
+            |_| None
+        )
+        .is_ok());
+    }
+}
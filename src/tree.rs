@@ -0,0 +1,637 @@
+//! Generic traversal of the nodes of a document, i.e. of all its `Anchor`s, and lookup of nodes
+//! by `NodeId`.
+use crate::model::{
+    AccessOperation, Alias, Anchor, BoundDeclaration, Call, CallInput, Document, DocumentElement,
+    Expression, InputDeclaration, MapEntry, MetaAttribute, MetaObjectField, MetaStringPart,
+    MetaValue, Namespace, NodeId, ObjectField, QualifiedIdentifier, RuntimeAttribute, Span,
+    StringPart, Task, TaskElement, Type, UnboundDeclaration, Version, VersionIdentifier, Workflow,
+    WorkflowElement, WorkflowNestedElement,
+};
+use std::{collections::HashMap, ops::Deref};
+
+/// A reference to a node of a document.
+#[derive(Clone, Copy, Debug)]
+pub enum Node<'a> {
+    Version(&'a Anchor<Version>),
+    VersionIdentifier(&'a Anchor<VersionIdentifier>),
+    DocumentElement(&'a Anchor<DocumentElement>),
+    Alias(&'a Anchor<Alias>),
+    /// A name, e.g. of a declaration, task, or struct, or an import URI.
+    Name(&'a Anchor<String>),
+    Type(&'a Anchor<Type>),
+    UnboundDeclaration(&'a Anchor<UnboundDeclaration>),
+    BoundDeclaration(&'a Anchor<BoundDeclaration>),
+    InputDeclaration(&'a Anchor<InputDeclaration>),
+    TaskElement(&'a Anchor<TaskElement>),
+    WorkflowElement(&'a Anchor<WorkflowElement>),
+    WorkflowNestedElement(&'a Anchor<WorkflowNestedElement>),
+    QualifiedIdentifier(&'a Anchor<QualifiedIdentifier>),
+    CallInput(&'a Anchor<CallInput>),
+    Expression(&'a Anchor<Expression>),
+    StringPart(&'a Anchor<StringPart>),
+    MapEntry(&'a Anchor<MapEntry>),
+    ObjectField(&'a Anchor<ObjectField>),
+    AccessOperation(&'a Anchor<AccessOperation>),
+    RuntimeAttribute(&'a Anchor<RuntimeAttribute>),
+    MetaAttribute(&'a Anchor<MetaAttribute>),
+    MetaValue(&'a Anchor<MetaValue>),
+    MetaObjectField(&'a Anchor<MetaObjectField>),
+    MetaStringPart(&'a Anchor<MetaStringPart>),
+}
+
+macro_rules! node_field {
+    ($node:expr, $anchor:ident => $e:expr) => {
+        match $node {
+            Node::Version($anchor) => $e,
+            Node::VersionIdentifier($anchor) => $e,
+            Node::DocumentElement($anchor) => $e,
+            Node::Alias($anchor) => $e,
+            Node::Name($anchor) => $e,
+            Node::Type($anchor) => $e,
+            Node::UnboundDeclaration($anchor) => $e,
+            Node::BoundDeclaration($anchor) => $e,
+            Node::InputDeclaration($anchor) => $e,
+            Node::TaskElement($anchor) => $e,
+            Node::WorkflowElement($anchor) => $e,
+            Node::WorkflowNestedElement($anchor) => $e,
+            Node::QualifiedIdentifier($anchor) => $e,
+            Node::CallInput($anchor) => $e,
+            Node::Expression($anchor) => $e,
+            Node::StringPart($anchor) => $e,
+            Node::MapEntry($anchor) => $e,
+            Node::ObjectField($anchor) => $e,
+            Node::AccessOperation($anchor) => $e,
+            Node::RuntimeAttribute($anchor) => $e,
+            Node::MetaAttribute($anchor) => $e,
+            Node::MetaValue($anchor) => $e,
+            Node::MetaObjectField($anchor) => $e,
+            Node::MetaStringPart($anchor) => $e,
+        }
+    };
+}
+
+impl<'a> Node<'a> {
+    pub fn id(&self) -> NodeId {
+        node_field!(self, anchor => anchor.id())
+    }
+
+    pub fn span(&self) -> &'a Span {
+        node_field!(*self, anchor => &anchor.span)
+    }
+}
+
+struct Walker<'a, 'v> {
+    visit: &'v mut dyn FnMut(Node<'a>, Option<NodeId>),
+}
+
+impl<'a, 'v> Walker<'a, 'v> {
+    fn node(&mut self, node: Node<'a>, parent: Option<NodeId>) -> Option<NodeId> {
+        (self.visit)(node, parent);
+        Some(node.id())
+    }
+
+    fn name(&mut self, name: &'a Anchor<String>, parent: Option<NodeId>) {
+        self.node(Node::Name(name), parent);
+    }
+
+    fn type_(&mut self, type_: &'a Anchor<Type>, parent: Option<NodeId>) {
+        let id = self.node(Node::Type(type_), parent);
+        match type_.deref() {
+            Type::Array { item, .. } => self.type_(item, id),
+            Type::Map { key, value } => {
+                self.type_(key, id);
+                self.type_(value, id)
+            }
+            Type::Pair { left, right } => {
+                self.type_(left, id);
+                self.type_(right, id)
+            }
+            Type::Optional(inner) => self.type_(inner, id),
+            _ => (),
+        }
+    }
+
+    fn unbound(&mut self, decl: &'a UnboundDeclaration, parent: Option<NodeId>) {
+        self.type_(&decl.type_, parent);
+        self.name(&decl.name, parent);
+    }
+
+    fn bound(&mut self, decl: &'a BoundDeclaration, parent: Option<NodeId>) {
+        self.type_(&decl.type_, parent);
+        self.name(&decl.name, parent);
+        self.expression(&decl.expression, parent);
+    }
+
+    fn string_parts(&mut self, parts: &'a [Anchor<StringPart>], parent: Option<NodeId>) {
+        for part in parts.iter() {
+            let id = self.node(Node::StringPart(part), parent);
+            if let StringPart::Placeholder(expression, _) = part.deref() {
+                self.expression_children(expression, id)
+            }
+        }
+    }
+
+    fn expression(&mut self, expression: &'a Anchor<Expression>, parent: Option<NodeId>) {
+        let id = self.node(Node::Expression(expression), parent);
+        self.expression_children(expression, id)
+    }
+
+    fn expression_children(&mut self, expression: &'a Expression, id: Option<NodeId>) {
+        match expression {
+            Expression::String(s) => self.string_parts(&s.parts, id),
+            Expression::Array(array) => {
+                for element in array.elements.iter() {
+                    self.expression(element, id)
+                }
+            }
+            Expression::Map(map) => {
+                for entry in map.entries.iter() {
+                    let entry_id = self.node(Node::MapEntry(entry), id);
+                    self.expression(&entry.key, entry_id);
+                    self.expression(&entry.value, entry_id)
+                }
+            }
+            Expression::Pair(pair) => {
+                self.expression(&pair.left, id);
+                self.expression(&pair.right, id)
+            }
+            Expression::Object(object) => {
+                self.name(&object.type_name, id);
+                for field in object.fields.iter() {
+                    let field_id = self.node(Node::ObjectField(field), id);
+                    self.name(&field.name, field_id);
+                    self.expression(&field.expression, field_id)
+                }
+            }
+            Expression::Unary(unary) => self.expression(&unary.expression, id),
+            Expression::Binary(binary) => {
+                self.expression(&binary.left, id);
+                self.expression(&binary.right, id)
+            }
+            Expression::Apply(apply) => {
+                self.name(&apply.name, id);
+                for argument in apply.arguments.iter() {
+                    self.expression(argument, id)
+                }
+            }
+            Expression::Access(access) => {
+                self.expression(&access.collection, id);
+                for operation in access.accesses.iter() {
+                    let operation_id = self.node(Node::AccessOperation(operation), id);
+                    if let AccessOperation::Index(index) = operation.deref() {
+                        self.expression_children(index, operation_id)
+                    }
+                }
+            }
+            Expression::Ternary(ternary) => {
+                self.expression(&ternary.condition, id);
+                self.expression(&ternary.true_branch, id);
+                self.expression(&ternary.false_branch, id)
+            }
+            Expression::Group(inner) => self.expression(inner, id),
+            _ => (),
+        }
+    }
+
+    fn meta_value(&mut self, value: &'a Anchor<MetaValue>, parent: Option<NodeId>) {
+        let id = self.node(Node::MetaValue(value), parent);
+        match value.deref() {
+            MetaValue::String(s) => {
+                for part in s.parts.iter() {
+                    self.node(Node::MetaStringPart(part), id);
+                }
+            }
+            MetaValue::Array(array) => {
+                for element in array.elements.iter() {
+                    self.meta_value(element, id)
+                }
+            }
+            MetaValue::Object(object) => {
+                for field in object.fields.iter() {
+                    let field_id = self.node(Node::MetaObjectField(field), id);
+                    self.name(&field.name, field_id);
+                    self.meta_value(&field.value, field_id)
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn meta_attributes(&mut self, attributes: &'a [Anchor<MetaAttribute>], parent: Option<NodeId>) {
+        for attribute in attributes.iter() {
+            let id = self.node(Node::MetaAttribute(attribute), parent);
+            self.name(&attribute.name, id);
+            self.meta_value(&attribute.value, id)
+        }
+    }
+
+    fn input_declarations(
+        &mut self,
+        declarations: &'a [Anchor<InputDeclaration>],
+        parent: Option<NodeId>,
+    ) {
+        for decl in declarations.iter() {
+            let id = self.node(Node::InputDeclaration(decl), parent);
+            match decl.deref() {
+                InputDeclaration::Bound(bound) => self.bound(bound, id),
+                InputDeclaration::Unbound(unbound) => self.unbound(unbound, id),
+            }
+        }
+    }
+
+    fn output_declarations(
+        &mut self,
+        declarations: &'a [Anchor<BoundDeclaration>],
+        parent: Option<NodeId>,
+    ) {
+        for decl in declarations.iter() {
+            let id = self.node(Node::BoundDeclaration(decl), parent);
+            self.bound(decl, id)
+        }
+    }
+
+    fn call(&mut self, call: &'a Call, id: Option<NodeId>) {
+        let target_id = self.node(Node::QualifiedIdentifier(&call.target), id);
+        for part in call.target.parts.iter() {
+            self.name(part, target_id)
+        }
+        if let Some(alias) = &call.alias {
+            self.name(alias, id)
+        }
+        for input in call.inputs.iter().flatten() {
+            let input_id = self.node(Node::CallInput(input), id);
+            self.name(&input.name, input_id);
+            if let Some(expression) = &input.expression {
+                self.expression(expression, input_id)
+            }
+        }
+    }
+
+    fn nested(&mut self, body: &'a [Anchor<WorkflowNestedElement>], parent: Option<NodeId>) {
+        for element in body.iter() {
+            let id = self.node(Node::WorkflowNestedElement(element), parent);
+            match element.deref() {
+                WorkflowNestedElement::Declaration(decl) => self.bound(decl, id),
+                WorkflowNestedElement::Call(call) => self.call(call, id),
+                WorkflowNestedElement::Scatter(scatter) => {
+                    self.name(&scatter.name, id);
+                    self.expression(&scatter.expression, id);
+                    self.nested(&scatter.body, id)
+                }
+                WorkflowNestedElement::Conditional(conditional) => {
+                    self.expression(&conditional.expression, id);
+                    self.nested(&conditional.body, id)
+                }
+            }
+        }
+    }
+
+    fn document(&mut self, document: &'a Document) {
+        let version_id = self.node(Node::Version(&document.version), None);
+        self.node(
+            Node::VersionIdentifier(&document.version.identifier),
+            version_id,
+        );
+        for element in document.body.iter() {
+            let id = self.node(Node::DocumentElement(element), None);
+            match element.deref() {
+                DocumentElement::Import(import) => {
+                    self.name(&import.uri, id);
+                    if let Namespace::Explicit(namespace) = &import.namespace {
+                        self.name(namespace, id)
+                    }
+                    for alias in import.aliases.iter() {
+                        let alias_id = self.node(Node::Alias(alias), id);
+                        self.name(&alias.from, alias_id);
+                        self.name(&alias.to, alias_id)
+                    }
+                }
+                DocumentElement::Struct(struct_) => {
+                    self.name(&struct_.name, id);
+                    for field in struct_.fields.iter() {
+                        let field_id = self.node(Node::UnboundDeclaration(field), id);
+                        self.unbound(field, field_id)
+                    }
+                }
+                DocumentElement::Task(task) => {
+                    self.name(&task.name, id);
+                    for element in task.body.iter() {
+                        let element_id = self.node(Node::TaskElement(element), id);
+                        match element.deref() {
+                            TaskElement::Input(input) => {
+                                self.input_declarations(&input.declarations, element_id)
+                            }
+                            TaskElement::Output(output) => {
+                                self.output_declarations(&output.declarations, element_id)
+                            }
+                            TaskElement::Declaration(decl) => self.bound(decl, element_id),
+                            TaskElement::Command(command) => {
+                                self.string_parts(&command.parts, element_id)
+                            }
+                            TaskElement::Runtime(runtime) => {
+                                for attribute in runtime.attributes.iter() {
+                                    let attribute_id =
+                                        self.node(Node::RuntimeAttribute(attribute), element_id);
+                                    self.name(&attribute.name, attribute_id);
+                                    self.expression(&attribute.expression, attribute_id)
+                                }
+                            }
+                            TaskElement::Meta(meta) => {
+                                self.meta_attributes(&meta.attributes, element_id)
+                            }
+                            TaskElement::ParameterMeta(meta) => {
+                                self.meta_attributes(&meta.attributes, element_id)
+                            }
+                        }
+                    }
+                }
+                DocumentElement::Workflow(workflow) => {
+                    self.name(&workflow.name, id);
+                    for element in workflow.body.iter() {
+                        let element_id = self.node(Node::WorkflowElement(element), id);
+                        match element.deref() {
+                            WorkflowElement::Input(input) => {
+                                self.input_declarations(&input.declarations, element_id)
+                            }
+                            WorkflowElement::Output(output) => {
+                                self.output_declarations(&output.declarations, element_id)
+                            }
+                            WorkflowElement::Declaration(decl) => self.bound(decl, element_id),
+                            WorkflowElement::Call(call) => self.call(call, element_id),
+                            WorkflowElement::Scatter(scatter) => {
+                                self.name(&scatter.name, element_id);
+                                self.expression(&scatter.expression, element_id);
+                                self.nested(&scatter.body, element_id)
+                            }
+                            WorkflowElement::Conditional(conditional) => {
+                                self.expression(&conditional.expression, element_id);
+                                self.nested(&conditional.body, element_id)
+                            }
+                            WorkflowElement::Meta(meta) | WorkflowElement::ParameterMeta(meta) => {
+                                self.meta_attributes(&meta.attributes, element_id)
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Calls `visit` with each node of `document` and the ID of its parent node (or `None` for the
+/// version statement and the top-level elements of the document), in source order. Each node is
+/// visited before its children.
+///
+/// Elements that are not anchored themselves, such as the expression of a placeholder, are not
+/// visited, but their children are visited as children of the closest anchored node.
+pub fn walk<'a>(document: &'a Document, visit: &mut dyn FnMut(Node<'a>, Option<NodeId>)) {
+    Walker { visit }.document(document)
+}
+
+impl Document {
+    /// Returns all the nodes of this document, in the order in which they are visited by `walk`.
+    pub fn nodes(&self) -> Vec<Node<'_>> {
+        let mut nodes = Vec::new();
+        walk(self, &mut |node, _| nodes.push(node));
+        nodes
+    }
+
+    /// Returns an index of the nodes of this document by ID. Building the index walks the
+    /// document, so it should be built once and used for all lookups.
+    pub fn index(&self) -> NodeIndex<'_> {
+        NodeIndex::new(self)
+    }
+
+    /// Returns the parent relationships of the nodes of this document.
+    pub fn parents(&self) -> Parents<'_> {
+        Parents::new(self)
+    }
+}
+
+/// The nodes of a document by ID, for looking up nodes in constant time.
+#[derive(Debug)]
+pub struct NodeIndex<'a> {
+    nodes: HashMap<NodeId, Node<'a>>,
+}
+
+impl<'a> NodeIndex<'a> {
+    pub fn new(document: &'a Document) -> Self {
+        let mut nodes = HashMap::new();
+        walk(document, &mut |node, _| {
+            nodes.insert(node.id(), node);
+        });
+        Self { nodes }
+    }
+
+    /// Returns the node with the given ID, if it is a node of the document.
+    pub fn node(&self, id: NodeId) -> Option<Node<'a>> {
+        self.nodes.get(&id).copied()
+    }
+
+    /// Returns the number of nodes of the document.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+/// The parent of each node of a document, for walking upward from any node, e.g. to find the
+/// task that contains an expression.
+#[derive(Debug)]
+pub struct Parents<'a> {
+    index: NodeIndex<'a>,
+    parents: HashMap<NodeId, NodeId>,
+}
+
+impl<'a> Parents<'a> {
+    pub fn new(document: &'a Document) -> Self {
+        let mut nodes = HashMap::new();
+        let mut parents = HashMap::new();
+        walk(document, &mut |node, parent| {
+            nodes.insert(node.id(), node);
+            if let Some(parent) = parent {
+                parents.insert(node.id(), parent);
+            }
+        });
+        Self {
+            index: NodeIndex { nodes },
+            parents,
+        }
+    }
+
+    /// Returns the index of the nodes of the document.
+    pub fn index(&self) -> &NodeIndex<'a> {
+        &self.index
+    }
+
+    /// Returns the node with the given ID, if it is a node of the document.
+    pub fn node(&self, id: NodeId) -> Option<Node<'a>> {
+        self.index.node(id)
+    }
+
+    /// Returns the parent of the node with the given ID, or `None` if the node is a top-level
+    /// node or is not a node of the document.
+    pub fn parent(&self, id: NodeId) -> Option<Node<'a>> {
+        self.parents.get(&id).and_then(|parent| self.node(*parent))
+    }
+
+    /// Returns the ancestors of the node with the given ID, starting with its parent and ending
+    /// with a top-level node.
+    pub fn ancestors(&self, id: NodeId) -> Vec<Node<'a>> {
+        let mut ancestors = Vec::new();
+        let mut current = id;
+        while let Some(parent) = self.parent(current) {
+            current = parent.id();
+            ancestors.push(parent);
+        }
+        ancestors
+    }
+
+    /// Returns the top-level element (import, struct, task, or workflow) that contains the node
+    /// with the given ID, or the node itself if it is a top-level element.
+    pub fn document_element(&self, id: NodeId) -> Option<&'a Anchor<DocumentElement>> {
+        std::iter::once(self.node(id)?)
+            .chain(self.ancestors(id))
+            .find_map(|node| match node {
+                Node::DocumentElement(element) => Some(element),
+                _ => None,
+            })
+    }
+
+    /// Returns the task that contains the node with the given ID, if any.
+    pub fn task(&self, id: NodeId) -> Option<&'a Task> {
+        match self.document_element(id)?.deref() {
+            DocumentElement::Task(task) => Some(task),
+            _ => None,
+        }
+    }
+
+    /// Returns the workflow that contains the node with the given ID, if any.
+    pub fn workflow(&self, id: NodeId) -> Option<&'a Workflow> {
+        match self.document_element(id)?.deref() {
+            DocumentElement::Workflow(workflow) => Some(workflow),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::{
+        model::DocumentSource,
+        parsers::{PestParser, WdlParser},
+    };
+    use pretty_assertions::assert_eq;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_node_ids() {
+        let text = r#"version 1.1
+
+task t {
+    input {
+        Array[String] names
+    }
+    command <<< echo ~{sep(" ", names)} >>>
+    output {
+        Map[String, Int] counts = {"a": length(names)}
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let nodes = doc.nodes();
+        let ids: HashSet<NodeId> = nodes.iter().map(|node| node.id()).collect();
+        assert_eq!(ids.len(), nodes.len());
+        let identifiers: Vec<&Node> = nodes
+            .iter()
+            .filter(|node| matches!(node, Node::Expression(e) if matches!(***e, Expression::Identifier(_))))
+            .collect();
+        assert_eq!(identifiers.len(), 2);
+        let id = identifiers[1].id();
+        let index = doc.index();
+        assert_eq!(index.len(), nodes.len());
+        match index.node(id) {
+            Some(Node::Expression(expression)) => {
+                let span = &expression.span;
+                assert_eq!(&text[span.start.offset..span.end.offset], "names");
+                assert_eq!(span.start.line, 8);
+            }
+            other => panic!("unexpected node {:?}", other),
+        }
+        let reparsed = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        assert_eq!(reparsed, doc);
+        assert!(reparsed.index().node(id).is_none());
+        // a clone of a subtree has new IDs
+        let map: &Anchor<Type> = nodes
+            .iter()
+            .find_map(|node| match node {
+                Node::Type(type_) if matches!(***type_, Type::Map { .. }) => Some(*type_),
+                _ => None,
+            })
+            .unwrap();
+        let cloned = map.clone();
+        assert_eq!(&cloned, map);
+        match cloned.deref() {
+            Type::Map { key, value } => assert!([cloned.id(), key.id(), value.id()]
+                .iter()
+                .all(|id| index.node(*id).is_none())),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_parents() {
+        let text = r#"version 1.1
+
+task t {
+    command <<< >>>
+}
+
+workflow wf {
+    scatter (i in range(3)) {
+        if (i > 0) {
+            Int x = i + 1
+        }
+    }
+}
+"#;
+        let doc = PestParser::new()
+            .parse_text(text, DocumentSource::Unknown)
+            .unwrap();
+        let parents = doc.parents();
+        let plus = doc
+            .nodes()
+            .into_iter()
+            .find(|node| matches!(node, Node::Expression(e) if matches!(***e, Expression::Binary(_)) && e.span.start.line == 9))
+            .unwrap();
+        let kinds: Vec<&str> = parents
+            .ancestors(plus.id())
+            .iter()
+            .map(|node| match node {
+                Node::WorkflowNestedElement(element) => match ***element {
+                    WorkflowNestedElement::Declaration(_) => "declaration",
+                    WorkflowNestedElement::Conditional(_) => "conditional",
+                    _ => "other nested",
+                },
+                Node::WorkflowElement(element) => element.kind(),
+                Node::DocumentElement(_) => "document element",
+                _ => "other",
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec!["declaration", "conditional", "scatter", "document element"]
+        );
+        assert_eq!(parents.workflow(plus.id()).unwrap().name.as_str(), "wf");
+        assert!(parents.task(plus.id()).is_none());
+        assert!(parents.parent(doc.version.id()).is_none());
+    }
+}
@@ -0,0 +1,288 @@
+//! Parsing of the memory and disk sizes that are given as strings in runtime sections, e.g.
+//! `"8 GB"` or `"local-disk 50 HDD"`, into structured quantities.
+use crate::values::WdlValue;
+use error_stack::{bail, ensure, Report, Result};
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    str::FromStr,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum UnitsError {
+    #[error("Invalid quantity {0}")]
+    Quantity(String),
+    #[error("Unknown unit {0}")]
+    Unit(String),
+    #[error("Invalid disk specification {0}")]
+    Disk(String),
+}
+
+/// A unit of storage size. Decimal units are powers of 1000 and binary units are powers of 1024.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Unit {
+    B,
+    KB,
+    MB,
+    GB,
+    TB,
+    KiB,
+    MiB,
+    GiB,
+    TiB,
+}
+
+impl Unit {
+    /// Returns the number of bytes in one of this unit.
+    pub fn bytes(&self) -> f64 {
+        match self {
+            Self::B => 1.0,
+            Self::KB => 1e3,
+            Self::MB => 1e6,
+            Self::GB => 1e9,
+            Self::TB => 1e12,
+            Self::KiB => 1024.0,
+            Self::MiB => 1024.0 * 1024.0,
+            Self::GiB => 1024.0 * 1024.0 * 1024.0,
+            Self::TiB => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        }
+    }
+}
+
+/// Parses a unit suffix as defined by the WDL spec. The single-letter (`G`) and two-letter binary
+/// (`Gi`) abbreviations are accepted, and suffixes are case-insensitive since engines commonly
+/// accept e.g. `"8 gb"`.
+impl FromStr for Unit {
+    type Err = Report<UnitsError>;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let unit = match s.to_ascii_lowercase().as_str() {
+            "b" => Self::B,
+            "k" | "kb" => Self::KB,
+            "m" | "mb" => Self::MB,
+            "g" | "gb" => Self::GB,
+            "t" | "tb" => Self::TB,
+            "ki" | "kib" => Self::KiB,
+            "mi" | "mib" => Self::MiB,
+            "gi" | "gib" => Self::GiB,
+            "ti" | "tib" => Self::TiB,
+            _ => bail!(UnitsError::Unit(s.to_owned())),
+        };
+        Ok(unit)
+    }
+}
+
+impl Display for Unit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            Self::B => "B",
+            Self::KB => "KB",
+            Self::MB => "MB",
+            Self::GB => "GB",
+            Self::TB => "TB",
+            Self::KiB => "KiB",
+            Self::MiB => "MiB",
+            Self::GiB => "GiB",
+            Self::TiB => "TiB",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A storage size, e.g. an amount of memory or the size of a disk.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    pub unit: Unit,
+}
+
+impl Quantity {
+    pub fn new(value: f64, unit: Unit) -> Self {
+        Self { value, unit }
+    }
+
+    pub fn bytes(&self) -> f64 {
+        self.value * self.unit.bytes()
+    }
+
+    /// Returns the size of this quantity in `unit`.
+    pub fn to(&self, unit: Unit) -> f64 {
+        self.bytes() / unit.bytes()
+    }
+
+    /// Parses a quantity, using `default` as the unit if `s` is a bare number.
+    pub fn parse_with_default(s: &str, default: Unit) -> Result<Self, UnitsError> {
+        let s = s.trim();
+        let split = s
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        let value: f64 = number
+            .parse()
+            .map_err(|_| Report::new(UnitsError::Quantity(s.to_owned())))?;
+        ensure!(value.is_finite(), UnitsError::Quantity(s.to_owned()));
+        let unit = match unit.trim() {
+            "" => default,
+            unit => unit.parse()?,
+        };
+        Ok(Self { value, unit })
+    }
+}
+
+/// Parses a quantity such as `"8 GB"`, `"8GB"`, or `"1.5 GiB"`. A bare number is a number of
+/// bytes, as for the `memory` runtime attribute.
+impl FromStr for Quantity {
+    type Err = Report<UnitsError>;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::parse_with_default(s, Unit::B)
+    }
+}
+
+impl Display for Quantity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{} {}", self.value, self.unit)
+    }
+}
+
+/// The type of a disk, as used in the `disks` runtime attribute by Cromwell and other engines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DiskType {
+    Hdd,
+    Ssd,
+    Local,
+}
+
+impl FromStr for DiskType {
+    type Err = Report<UnitsError>;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "HDD" => Ok(Self::Hdd),
+            "SSD" => Ok(Self::Ssd),
+            "LOCAL" => Ok(Self::Local),
+            _ => bail!(UnitsError::Disk(s.to_owned())),
+        }
+    }
+}
+
+/// A disk requirement, e.g. `"local-disk 50 HDD"` or `"/mnt/data 100 GiB"`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Disk {
+    /// The mount point of the disk; `local-disk` denotes the task's working disk. `None` if the
+    /// specification only gives a size.
+    pub mount_point: Option<String>,
+    pub size: Quantity,
+    pub disk_type: Option<DiskType>,
+}
+
+/// Parses a disk specification of the form `[<mount-point>] <size> [<unit>] [<type>]`. A size
+/// without a unit is in GiB, as specified by WDL 1.1.
+impl FromStr for Disk {
+    type Err = Report<UnitsError>;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut tokens: Vec<&str> = s.split_whitespace().collect();
+        ensure!(!tokens.is_empty(), UnitsError::Disk(s.to_owned()));
+        let mount_point = if tokens[0].starts_with(|c: char| c.is_ascii_digit()) {
+            None
+        } else {
+            Some(tokens.remove(0).to_owned())
+        };
+        let disk_type = match tokens.last() {
+            Some(token) if tokens.len() > 1 => match token.parse::<DiskType>() {
+                Ok(disk_type) => {
+                    tokens.pop();
+                    Some(disk_type)
+                }
+                Err(_) => None,
+            },
+            _ => None,
+        };
+        ensure!(
+            !tokens.is_empty() && tokens.len() <= 2,
+            UnitsError::Disk(s.to_owned())
+        );
+        let size = Quantity::parse_with_default(&tokens.join(" "), Unit::GiB)
+            .map_err(|report| report.change_context(UnitsError::Disk(s.to_owned())))?;
+        Ok(Self {
+            mount_point,
+            size,
+            disk_type,
+        })
+    }
+}
+
+/// Parses the value of a `disks` runtime attribute, which may specify multiple disks separated by
+/// commas.
+pub fn parse_disks(s: &str) -> Result<Vec<Disk>, UnitsError> {
+    s.split(',').map(|disk| disk.parse()).collect()
+}
+
+/// Returns the amount of memory given by the value of a `memory` runtime attribute, which may be
+/// a number of bytes or a string with units.
+pub fn memory(value: &WdlValue) -> Option<Quantity> {
+    match value {
+        WdlValue::Int(bytes) => Some(Quantity::new(*bytes as f64, Unit::B)),
+        WdlValue::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Returns the disks given by the value of a `disks` runtime attribute, which may be a size in
+/// GiB or a string of disk specifications.
+pub fn disks(value: &WdlValue) -> Option<Vec<Disk>> {
+    match value {
+        WdlValue::Int(size) => Some(vec![Disk {
+            mount_point: None,
+            size: Quantity::new(*size as f64, Unit::GiB),
+            disk_type: None,
+        }]),
+        WdlValue::String(s) => parse_disks(s).ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_quantity() {
+        let q: Quantity = "8 GB".parse().unwrap();
+        assert_eq!(q, Quantity::new(8.0, Unit::GB));
+        assert_eq!(q.bytes(), 8e9);
+        let q: Quantity = "1.5Gi".parse().unwrap();
+        assert_eq!(q.to(Unit::MiB), 1536.0);
+        assert_eq!("1024".parse::<Quantity>().unwrap().to(Unit::KiB), 1.0);
+        assert!("8 GX".parse::<Quantity>().is_err());
+        assert!("GB".parse::<Quantity>().is_err());
+    }
+
+    #[test]
+    fn test_disks() {
+        assert_eq!(
+            parse_disks("local-disk 50 HDD, /mnt/data 100 GB SSD, 10").unwrap(),
+            vec![
+                Disk {
+                    mount_point: Some("local-disk".to_owned()),
+                    size: Quantity::new(50.0, Unit::GiB),
+                    disk_type: Some(DiskType::Hdd),
+                },
+                Disk {
+                    mount_point: Some("/mnt/data".to_owned()),
+                    size: Quantity::new(100.0, Unit::GB),
+                    disk_type: Some(DiskType::Ssd),
+                },
+                Disk {
+                    mount_point: None,
+                    size: Quantity::new(10.0, Unit::GiB),
+                    disk_type: None,
+                },
+            ]
+        );
+        assert!("local-disk".parse::<Disk>().is_err());
+        assert!("local-disk 50 HDD extra".parse::<Disk>().is_err());
+    }
+}
@@ -0,0 +1,341 @@
+//! Runtime values of WDL expressions, inputs and outputs, along with conversion to and from JSON
+//! and the coercion rules defined in the WDL spec.
+use crate::model::Type;
+use error_stack::{bail, Report, Result, ResultExt};
+use serde_json::{Map as JsonMap, Number, Value as JsonValue};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ValueError {
+    #[error("Cannot coerce value of type {from} to type {to}")]
+    Coercion { from: String, to: String },
+    #[error("Non-empty array type {0} cannot be empty")]
+    EmptyArray(String),
+    #[error("Cannot convert map with non-primitive key {0} to JSON")]
+    JsonKey(String),
+    #[error("Cannot convert floating point value {0} to JSON")]
+    JsonFloat(f64),
+    #[error("Invalid map key {key} for type {type_}")]
+    MapKey { key: String, type_: String },
+    #[error("Invalid member {member} for type {type_}")]
+    Member { member: String, type_: String },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WdlValue {
+    None,
+    Boolean(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    File(String),
+    Array(Vec<WdlValue>),
+    /// Map entries, in insertion order.
+    Map(Vec<(WdlValue, WdlValue)>),
+    Pair(Box<WdlValue>, Box<WdlValue>),
+    /// Object members, in insertion order.
+    Object(Vec<(String, WdlValue)>),
+    /// An instance of a user-defined struct type.
+    Struct {
+        name: String,
+        members: Vec<(String, WdlValue)>,
+    },
+}
+
+impl WdlValue {
+    /// Returns the name of the kind of this value, e.g. `Int` or `Array`.
+    pub fn kind(&self) -> &str {
+        match self {
+            Self::None => "None",
+            Self::Boolean(_) => "Boolean",
+            Self::Int(_) => "Int",
+            Self::Float(_) => "Float",
+            Self::String(_) => "String",
+            Self::File(_) => "File",
+            Self::Array(_) => "Array",
+            Self::Map(_) => "Map",
+            Self::Pair(_, _) => "Pair",
+            Self::Object(_) => "Object",
+            Self::Struct { name, .. } => name.as_str(),
+        }
+    }
+
+    /// Returns `true` if this is a primitive (Boolean, Int, Float, String, or File) value.
+    pub fn is_primitive(&self) -> bool {
+        matches!(
+            self,
+            Self::Boolean(_) | Self::Int(_) | Self::Float(_) | Self::String(_) | Self::File(_)
+        )
+    }
+
+    /// Converts a JSON value to a WDL value without any type information: `null` becomes `None`,
+    /// numbers become `Int` if they are integers and `Float` otherwise, strings become `String`,
+    /// and JSON objects become `Object`. Use `from_json_typed` to convert JSON with a known WDL
+    /// type (e.g. workflow inputs).
+    pub fn from_json(json: &JsonValue) -> Self {
+        match json {
+            JsonValue::Null => Self::None,
+            JsonValue::Bool(b) => Self::Boolean(*b),
+            JsonValue::Number(n) => match n.as_i64() {
+                Some(i) => Self::Int(i),
+                None => Self::Float(n.as_f64().unwrap_or(f64::NAN)),
+            },
+            JsonValue::String(s) => Self::String(s.clone()),
+            JsonValue::Array(a) => Self::Array(a.iter().map(Self::from_json).collect()),
+            JsonValue::Object(o) => Self::Object(
+                o.iter()
+                    .map(|(key, value)| (key.clone(), Self::from_json(value)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Converts a JSON value to a WDL value of type `type_`. JSON objects may be converted to
+    /// `Map`s, `Pair`s (with `left` and `right` members), `Object`s, or structs.
+    pub fn from_json_typed(json: &JsonValue, type_: &Type) -> Result<Self, ValueError> {
+        Self::from_json(json).coerce(type_)
+    }
+
+    /// Converts this value to JSON. Map keys must be primitive values, which are converted to
+    /// strings. Pairs are converted to objects with `left` and `right` members.
+    pub fn to_json(&self) -> Result<JsonValue, ValueError> {
+        let json = match self {
+            Self::None => JsonValue::Null,
+            Self::Boolean(b) => JsonValue::Bool(*b),
+            Self::Int(i) => JsonValue::Number((*i).into()),
+            Self::Float(f) => match Number::from_f64(*f) {
+                Some(n) => JsonValue::Number(n),
+                None => bail!(ValueError::JsonFloat(*f)),
+            },
+            Self::String(s) | Self::File(s) => JsonValue::String(s.clone()),
+            Self::Array(a) => JsonValue::Array(
+                a.iter()
+                    .map(|value| value.to_json())
+                    .collect::<Result<Vec<_>, ValueError>>()?,
+            ),
+            Self::Map(entries) => {
+                let mut map = JsonMap::new();
+                for (key, value) in entries {
+                    if !key.is_primitive() {
+                        bail!(ValueError::JsonKey(key.to_string()))
+                    }
+                    map.insert(key.to_string(), value.to_json()?);
+                }
+                JsonValue::Object(map)
+            }
+            Self::Pair(left, right) => {
+                let mut map = JsonMap::new();
+                map.insert("left".to_owned(), left.to_json()?);
+                map.insert("right".to_owned(), right.to_json()?);
+                JsonValue::Object(map)
+            }
+            Self::Object(members) | Self::Struct { members, .. } => {
+                let mut map = JsonMap::new();
+                for (name, value) in members {
+                    map.insert(name.clone(), value.to_json()?);
+                }
+                JsonValue::Object(map)
+            }
+        };
+        Ok(json)
+    }
+
+    /// Coerces this value to type `type_` according to the coercion rules in the WDL spec:
+    ///
+    /// * Any value may be coerced to its own type.
+    /// * `Int` may be coerced to `Float`.
+    /// * `String` may be coerced to `File` and vice-versa.
+    /// * `T` may be coerced to `T?`, and `None` may be coerced to any optional type.
+    /// * Compound values are coerced element-wise, e.g. `Array[Int]` to `Array[Float]`.
+    /// * `Object`s and `Map`s with `String` keys may be coerced to `Map`s and structs. Since
+    ///   struct definitions are not available here, struct members are not validated.
+    /// * `Object`s with `left` and `right` members may be coerced to `Pair`s.
+    pub fn coerce(self, type_: &Type) -> Result<Self, ValueError> {
+        let value = match (self, type_) {
+            (Self::None, Type::Optional(_)) => Self::None,
+            (value, Type::Optional(inner)) => value.coerce(inner)?,
+            (value @ Self::Boolean(_), Type::Boolean) => value,
+            (value @ Self::Int(_), Type::Int) => value,
+            (Self::Int(i), Type::Float) => Self::Float(i as f64),
+            (value @ Self::Float(_), Type::Float) => value,
+            (Self::String(s), Type::String) | (Self::File(s), Type::String) => Self::String(s),
+            (Self::String(s), Type::File) | (Self::File(s), Type::File) => Self::File(s),
+            (Self::Array(values), Type::Array { item, non_empty }) => {
+                if *non_empty && values.is_empty() {
+                    bail!(ValueError::EmptyArray(type_.to_string()))
+                }
+                Self::Array(
+                    values
+                        .into_iter()
+                        .map(|value| value.coerce(item))
+                        .collect::<Result<Vec<_>, ValueError>>()?,
+                )
+            }
+            (Self::Map(entries), Type::Map { key, value }) => Self::Map(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| Ok((k.coerce(key)?, v.coerce(value)?)))
+                    .collect::<Result<Vec<_>, ValueError>>()?,
+            ),
+            (Self::Object(members), Type::Map { key, value }) => Self::Map(
+                members
+                    .into_iter()
+                    .map(|(k, v)| {
+                        let coerced_key = Self::String(k.clone()).coerce(key).change_context(
+                            ValueError::MapKey {
+                                key: k,
+                                type_: type_.to_string(),
+                            },
+                        )?;
+                        Ok((coerced_key, v.coerce(value)?))
+                    })
+                    .collect::<Result<Vec<_>, ValueError>>()?,
+            ),
+            (Self::Pair(l, r), Type::Pair { left, right }) => {
+                Self::Pair(Box::new(l.coerce(left)?), Box::new(r.coerce(right)?))
+            }
+            (Self::Object(members), Type::Pair { left, right }) => {
+                let mut l = None;
+                let mut r = None;
+                for (name, value) in members {
+                    match name.as_str() {
+                        "left" => l = Some(value.coerce(left)?),
+                        "right" => r = Some(value.coerce(right)?),
+                        _ => bail!(ValueError::Member {
+                            member: name,
+                            type_: type_.to_string()
+                        }),
+                    }
+                }
+                match (l, r) {
+                    (Some(l), Some(r)) => Self::Pair(Box::new(l), Box::new(r)),
+                    _ => bail!(ValueError::Coercion {
+                        from: "Object".to_owned(),
+                        to: type_.to_string(),
+                    }),
+                }
+            }
+            (value @ Self::Object(_), Type::Object) => value,
+            (Self::Object(members), Type::User(name))
+            | (Self::Struct { members, .. }, Type::User(name)) => Self::Struct {
+                name: name.clone(),
+                members,
+            },
+            (Self::Map(entries), Type::User(name)) => Self::Struct {
+                name: name.clone(),
+                members: entries
+                    .into_iter()
+                    .map(|(key, value)| match key {
+                        Self::String(key) => Ok((key, value)),
+                        key => Err(Report::new(ValueError::MapKey {
+                            key: key.to_string(),
+                            type_: name.clone(),
+                        })),
+                    })
+                    .collect::<Result<Vec<_>, ValueError>>()?,
+            },
+            (value, _) => bail!(ValueError::Coercion {
+                from: value.kind().to_owned(),
+                to: type_.to_string(),
+            }),
+        };
+        Ok(value)
+    }
+}
+
+impl Display for WdlValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::None => write!(f, "None"),
+            Self::Boolean(b) => write!(f, "{}", b),
+            Self::Int(i) => write!(f, "{}", i),
+            Self::Float(x) => write!(f, "{}", x),
+            Self::String(s) | Self::File(s) => write!(f, "{}", s),
+            Self::Array(values) => {
+                let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                write!(f, "[{}]", values.join(", "))
+            }
+            Self::Map(entries) => {
+                let entries: Vec<String> = entries
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", k, v))
+                    .collect();
+                write!(f, "{{{}}}", entries.join(", "))
+            }
+            Self::Pair(left, right) => write!(f, "({}, {})", left, right),
+            Self::Object(members) | Self::Struct { members, .. } => {
+                let members: Vec<String> = members
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", k, v))
+                    .collect();
+                write!(f, "{{{}}}", members.join(", "))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Anchor, Span};
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    fn anchor(type_: Type) -> Box<Anchor<Type>> {
+        Box::new(Anchor::new(type_, Span::from_components(0, 0, 0, 0, 0, 0)))
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let json = json!({"a": [1, 2.5, "x", null, true]});
+        let value = WdlValue::from_json(&json);
+        assert_eq!(
+            value,
+            WdlValue::Object(vec![(
+                "a".to_owned(),
+                WdlValue::Array(vec![
+                    WdlValue::Int(1),
+                    WdlValue::Float(2.5),
+                    WdlValue::String("x".to_owned()),
+                    WdlValue::None,
+                    WdlValue::Boolean(true),
+                ])
+            )])
+        );
+        assert_eq!(value.to_json().unwrap(), json);
+    }
+
+    #[test]
+    fn test_coerce() {
+        let map_type = Type::Map {
+            key: anchor(Type::String),
+            value: anchor(Type::Array {
+                item: anchor(Type::Float),
+                non_empty: true,
+            }),
+        };
+        let value = WdlValue::from_json_typed(&json!({"a": [1, 2.5]}), &map_type).unwrap();
+        assert_eq!(
+            value,
+            WdlValue::Map(vec![(
+                WdlValue::String("a".to_owned()),
+                WdlValue::Array(vec![WdlValue::Float(1.0), WdlValue::Float(2.5)])
+            )])
+        );
+        assert!(WdlValue::from_json_typed(&json!({"a": []}), &map_type).is_err());
+        let pair_type = Type::Pair {
+            left: anchor(Type::Int),
+            right: anchor(Type::Optional(anchor(Type::File))),
+        };
+        assert_eq!(
+            WdlValue::from_json_typed(&json!({"left": 1, "right": "f.txt"}), &pair_type).unwrap(),
+            WdlValue::Pair(
+                Box::new(WdlValue::Int(1)),
+                Box::new(WdlValue::File("f.txt".to_owned()))
+            )
+        );
+        assert!(WdlValue::Float(1.5).coerce(&Type::Int).is_err());
+        assert!(WdlValue::None.coerce(&Type::Int).is_err());
+    }
+}
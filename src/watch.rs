@@ -0,0 +1,239 @@
+//! Revalidation of the documents in a directory as they change, e.g. for a language server or a
+//! `check --watch` command. A `Watcher` monitors a directory for changes to `.wdl` files, updates
+//! the text of each changed document in an incremental `Database`, and reports the diagnostics
+//! of the changed documents. Since results are memoized by the database, only the documents that
+//! changed are re-parsed and re-checked.
+use crate::{
+    diagnostics::Diagnostic, incremental::Database, model::DocumentSource, parsers::WdlParser,
+};
+use error_stack::{bail, IntoReport, Result, ResultExt};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::{
+    collections::BTreeSet,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Receiver, RecvTimeoutError},
+        Arc,
+    },
+    time::Duration,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum WatchError {
+    #[error("error watching directory {0}")]
+    Watch(PathBuf),
+    #[error("error reading directory {0}")]
+    Read(PathBuf),
+    #[error("the watcher stopped")]
+    Disconnected,
+}
+
+/// The result of revalidating a document.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WatchEvent {
+    /// The document was added or changed, and has the given diagnostics.
+    Diagnostics {
+        source: DocumentSource,
+        diagnostics: Arc<Vec<Diagnostic>>,
+    },
+    /// The document was added or changed, and cannot be parsed.
+    ParseError {
+        source: DocumentSource,
+        message: String,
+    },
+    /// The document was removed.
+    Removed(DocumentSource),
+}
+
+/// Returns whether `path` is a WDL document.
+fn is_wdl(path: &Path) -> bool {
+    path.extension() == Some(OsStr::new("wdl"))
+}
+
+/// Returns the `.wdl` files in `dir` and its subdirectories.
+fn wdl_files(dir: &Path) -> Result<Vec<PathBuf>, WatchError> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_owned()];
+    while let Some(dir) = pending.pop() {
+        let entries = fs::read_dir(&dir)
+            .into_report()
+            .change_context_lazy(|| WatchError::Read(dir.clone()))?;
+        for entry in entries {
+            let path = entry
+                .into_report()
+                .change_context_lazy(|| WatchError::Read(dir.clone()))?
+                .path();
+            if path.is_dir() {
+                pending.push(path)
+            } else if is_wdl(&path) {
+                files.push(path)
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Monitors a directory and revalidates the documents in it as they change.
+pub struct Watcher<P: WdlParser> {
+    db: Database<P>,
+    root: PathBuf,
+    // the watcher stops when it is dropped
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+}
+
+impl<P: WdlParser> Watcher<P> {
+    /// Starts watching the directory `root` and its subdirectories. The documents that are
+    /// already in the directory are loaded by `initial_events`. Diagnostics are computed with the
+    /// linter and validation options of `db`.
+    pub fn new<R: Into<PathBuf>>(db: Database<P>, root: R) -> Result<Self, WatchError> {
+        let root = root.into();
+        let (sender, events) = channel();
+        let mut watcher = notify::recommended_watcher(sender)
+            .into_report()
+            .change_context_lazy(|| WatchError::Watch(root.clone()))?;
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .into_report()
+            .change_context_lazy(|| WatchError::Watch(root.clone()))?;
+        Ok(Self {
+            db,
+            root,
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the database that holds the current text of the documents.
+    pub fn database(&self) -> &Database<P> {
+        &self.db
+    }
+
+    /// Loads the documents that are in the directory and returns their events, in path order.
+    pub fn initial_events(&mut self) -> Result<Vec<WatchEvent>, WatchError> {
+        let files = wdl_files(&self.root)?;
+        Ok(self.update(files))
+    }
+
+    /// Updates the documents at `paths` from the file system (i.e. sets their text, or removes
+    /// them if they no longer exist) and returns the events of the documents that changed, in
+    /// path order. Paths that are not `.wdl` files are ignored.
+    pub fn update<I: IntoIterator<Item = PathBuf>>(&mut self, paths: I) -> Vec<WatchEvent> {
+        let paths: BTreeSet<PathBuf> = paths.into_iter().filter(|path| is_wdl(path)).collect();
+        let mut events = Vec::new();
+        for path in paths {
+            let source = DocumentSource::File(path.clone());
+            let revision = self.db.revision();
+            match fs::read_to_string(&path) {
+                Ok(text) => {
+                    if self.db.set_text(source.clone(), text) == revision {
+                        continue;
+                    }
+                    events.push(match self.db.parse(&source).as_ref() {
+                        Ok(_) => WatchEvent::Diagnostics {
+                            diagnostics: self.db.diagnostics(&source),
+                            source,
+                        },
+                        Err(report) => WatchEvent::ParseError {
+                            source,
+                            message: report.to_string(),
+                        },
+                    })
+                }
+                Err(_) => {
+                    if self.db.remove(&source) != revision {
+                        events.push(WatchEvent::Removed(source))
+                    }
+                }
+            }
+        }
+        events
+    }
+
+    /// Waits up to `timeout` for changes to the directory and returns the events of the
+    /// documents that changed. Changes that are reported at the same time are handled as a
+    /// single batch. Returns an empty `Vec` if there were no changes to documents.
+    pub fn poll(&mut self, timeout: Duration) -> Result<Vec<WatchEvent>, WatchError> {
+        let mut paths = Vec::new();
+        let first = match self.events.recv_timeout(timeout) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => return Ok(Vec::new()),
+            Err(RecvTimeoutError::Disconnected) => bail!(WatchError::Disconnected),
+        };
+        // errors of the underlying watcher are not fatal; changes may have been missed
+        for event in std::iter::once(first)
+            .chain(self.events.try_iter())
+            .flatten()
+        {
+            paths.extend(event.paths)
+        }
+        Ok(self.update(paths))
+    }
+
+    /// Reports the events of the documents that are already in the directory to `callback`, and
+    /// then the events of documents as they change, until the watcher stops.
+    pub fn run<F: FnMut(WatchEvent)>(&mut self, mut callback: F) -> Result<(), WatchError> {
+        self.initial_events()?.into_iter().for_each(&mut callback);
+        loop {
+            self.poll(Duration::from_secs(1))?
+                .into_iter()
+                .for_each(&mut callback)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "pest"))]
+mod tests {
+    use super::*;
+    use crate::parsers::PestParser;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_update() {
+        let dir = std::env::temp_dir().join(format!("wdl-watch-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        let a = dir.join("a.wdl");
+        let b = dir.join("lib/b.wdl");
+        fs::write(&a, "version 1.1\n\nworkflow a {}\n").unwrap();
+        fs::write(&b, "version 1.1\n\nworkflow b {}\n").unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+        let mut watcher = Watcher::new(Database::new(PestParser::new()), &dir).unwrap();
+        let sources = |events: &[WatchEvent]| -> Vec<DocumentSource> {
+            events
+                .iter()
+                .map(|event| match event {
+                    WatchEvent::Diagnostics { source, .. }
+                    | WatchEvent::ParseError { source, .. }
+                    | WatchEvent::Removed(source) => source.clone(),
+                })
+                .collect()
+        };
+        let events = watcher.initial_events().unwrap();
+        assert_eq!(
+            sources(&events),
+            vec![
+                DocumentSource::File(a.clone()),
+                DocumentSource::File(b.clone())
+            ]
+        );
+        assert!(matches!(events[0], WatchEvent::Diagnostics { .. }));
+
+        // unchanged documents are not reported
+        fs::write(&a, "version 1.1\n\nworkflow a {\n").unwrap();
+        fs::remove_file(&b).unwrap();
+        let events = watcher.update(vec![a.clone(), b.clone(), dir.join("notes.txt")]);
+        assert!(matches!(events[0], WatchEvent::ParseError { .. }));
+        assert_eq!(events[1], WatchEvent::Removed(DocumentSource::File(b)));
+        assert_eq!(events.len(), 2);
+        assert!(watcher.update(vec![a]).is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,493 @@
+//! A workspace is a set of documents that may import one another. Imports are resolved to the
+//! documents in the workspace by their URIs, relative to the importing document by default. The
+//! resolution of URIs can be configured with `ResolverOptions`, e.g. for offline environments.
+use crate::{
+    model::{Document, DocumentElement, DocumentSource, Import, Span},
+    parsers::{WdlParser, WdlParserError},
+};
+use error_stack::Result;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashSet},
+    fmt::{Display, Formatter, Result as FmtResult},
+    ops::Deref,
+    path::{Component, Path, PathBuf},
+};
+
+/// An import statement that is part of an import cycle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CycleImport {
+    /// The source of the importing document.
+    pub source: DocumentSource,
+    /// The URI of the import.
+    pub uri: String,
+    /// The span of the import statement.
+    pub span: Span,
+}
+
+/// A chain of imports that leads from a document back to itself, either directly or through
+/// other documents.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportCycle {
+    /// The imports of the cycle, in order. The first import is in the document from which the
+    /// cycle was found, and the last import imports that document.
+    pub imports: Vec<CycleImport>,
+}
+
+impl Display for ImportCycle {
+    /// Formats the cycle as the chain of importing documents, e.g. `a.wdl -> b.wdl -> a.wdl`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        for import in self.imports.iter() {
+            write!(f, "{} -> ", import.source)?;
+        }
+        match self.imports.first() {
+            Some(import) => write!(f, "{}", import.source),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A rule that rewrites import URIs that start with `prefix` by replacing the prefix with
+/// `replacement`, e.g. to map `https://raw.githubusercontent.com/org/repo/` to a local mirror.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UriRewrite {
+    pub prefix: String,
+    pub replacement: String,
+}
+
+impl UriRewrite {
+    pub fn new<P: Into<String>, R: Into<String>>(prefix: P, replacement: R) -> Self {
+        Self {
+            prefix: prefix.into(),
+            replacement: replacement.into(),
+        }
+    }
+}
+
+/// Options that control how import URIs are resolved to document sources.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolverOptions {
+    /// Whether relative paths are resolved relative to the directory of the importing document.
+    /// If `false`, they are resolved relative to the current directory. Defaults to `true`.
+    pub relative_to_importer: bool,
+    /// Directories that are searched, in order, for relative paths that do not resolve to a
+    /// document in the workspace or to an existing file.
+    pub search_paths: Vec<PathBuf>,
+    /// Rules that are applied to URIs before they are resolved. Only the first matching rule is
+    /// applied.
+    pub rewrites: Vec<UriRewrite>,
+}
+
+impl Default for ResolverOptions {
+    fn default() -> Self {
+        Self {
+            relative_to_importer: true,
+            search_paths: Vec::new(),
+            rewrites: Vec::new(),
+        }
+    }
+}
+
+impl ResolverOptions {
+    pub fn with_search_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.search_paths.push(path.into());
+        self
+    }
+
+    pub fn with_rewrite<P: Into<String>, R: Into<String>>(
+        mut self,
+        prefix: P,
+        replacement: R,
+    ) -> Self {
+        self.rewrites.push(UriRewrite::new(prefix, replacement));
+        self
+    }
+
+    /// Applies the first rewrite rule that matches `uri`, if any.
+    pub fn rewrite<'a>(&self, uri: &'a str) -> Cow<'a, str> {
+        self.rewrites
+            .iter()
+            .find_map(|rule| {
+                uri.strip_prefix(rule.prefix.as_str())
+                    .map(|rest| Cow::Owned(format!("{}{}", rule.replacement, rest)))
+            })
+            .unwrap_or(Cow::Borrowed(uri))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Workspace {
+    documents: BTreeMap<DocumentSource, Document>,
+    options: ResolverOptions,
+}
+
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Workspace>();
+};
+
+impl Workspace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: ResolverOptions) -> Self {
+        Self {
+            documents: BTreeMap::new(),
+            options,
+        }
+    }
+
+    pub fn options(&self) -> &ResolverOptions {
+        &self.options
+    }
+
+    /// Adds `document` to the workspace, replacing any existing document with the same source.
+    pub fn insert(&mut self, document: Document) {
+        self.documents.insert(document.source.clone(), document);
+    }
+
+    pub fn get(&self, source: &DocumentSource) -> Option<&Document> {
+        self.documents.get(source)
+    }
+
+    pub fn contains(&self, source: &DocumentSource) -> bool {
+        self.documents.contains_key(source)
+    }
+
+    /// Returns an iterator over the documents in the workspace, ordered by source, so that the
+    /// order does not depend on the order in which they were added.
+    pub fn documents(&self) -> impl Iterator<Item = &Document> {
+        self.documents.values()
+    }
+
+    /// Returns the source of the document imported from `uri` by a document with source `from`.
+    /// URIs with a scheme (e.g. `https://`) are used as-is, except that `file://` URIs are
+    /// converted to paths. Relative paths are resolved relative to the directory of `from`.
+    pub fn import_source(from: &DocumentSource, uri: &str) -> DocumentSource {
+        if let Some(path) = uri.strip_prefix("file://") {
+            return DocumentSource::File(normalize(Path::new(path)));
+        }
+        if uri.contains("://") {
+            return DocumentSource::Uri(uri.to_owned());
+        }
+        if Path::new(uri).is_absolute() {
+            return DocumentSource::File(normalize(Path::new(uri)));
+        }
+        match from {
+            DocumentSource::File(path) => {
+                let dir = path.parent().unwrap_or_else(|| Path::new(""));
+                DocumentSource::File(normalize(&dir.join(uri)))
+            }
+            DocumentSource::Uri(base) => match base.rfind('/') {
+                Some(i) => DocumentSource::Uri(format!("{}/{}", &base[..i], uri)),
+                None => DocumentSource::Uri(uri.to_owned()),
+            },
+            DocumentSource::Unknown => DocumentSource::File(normalize(Path::new(uri))),
+        }
+    }
+
+    /// Returns the source of the document imported from `uri` by a document with source `from`,
+    /// using the resolver options of this workspace. The URI is first rewritten, then resolved as
+    /// by `import_source` (or relative to the current directory if the options do not resolve
+    /// relative to the importer). If the result is a file that is neither in the workspace nor
+    /// exists, and the URI is a relative path, the search paths are tried in order; if none of
+    /// them contains the file, the unsearched result is returned.
+    pub fn resolve_source(&self, from: &DocumentSource, uri: &str) -> DocumentSource {
+        let uri = self.options.rewrite(uri);
+        let uri = uri.as_ref();
+        let is_relative_path =
+            !uri.contains("://") && !uri.starts_with("file:") && Path::new(uri).is_relative();
+        let source = if is_relative_path && !self.options.relative_to_importer {
+            DocumentSource::File(normalize(Path::new(uri)))
+        } else {
+            Self::import_source(from, uri)
+        };
+        if !is_relative_path || self.exists(&source) {
+            return source;
+        }
+        self.options
+            .search_paths
+            .iter()
+            .map(|dir| DocumentSource::File(normalize(&dir.join(uri))))
+            .find(|candidate| self.exists(candidate))
+            .unwrap_or(source)
+    }
+
+    /// Returns whether `source` is in the workspace or is an existing file.
+    fn exists(&self, source: &DocumentSource) -> bool {
+        self.contains(source) || matches!(source, DocumentSource::File(path) if path.is_file())
+    }
+
+    /// Returns the document imported by `import` in `document`, if it is in the workspace.
+    pub fn resolve_import(&self, document: &Document, import: &Import) -> Option<&Document> {
+        self.get(&self.resolve_source(&document.source, import.uri.as_str()))
+    }
+
+    /// Returns the import in `document` whose namespace is `namespace`, and the imported document
+    /// if it is in the workspace.
+    pub fn resolve_namespace<'a>(
+        &'a self,
+        document: &'a Document,
+        namespace: &str,
+    ) -> Option<(&'a Import, Option<&'a Document>)> {
+        document.body_iter().find_map(|element| match element {
+            DocumentElement::Import(import) if namespace_name(import) == namespace => {
+                Some((import, self.resolve_import(document, import)))
+            }
+            _ => None,
+        })
+    }
+
+    /// Parses the file at `path` and, recursively, all the local files that it imports, and adds
+    /// them to the workspace. Documents that are already in the workspace are not parsed again.
+    /// Imports of remote URIs are not loaded. Returns the source of the document at `path`.
+    pub fn load<P: WdlParser, F: AsRef<Path>>(
+        &mut self,
+        parser: &mut P,
+        path: F,
+    ) -> Result<DocumentSource, WdlParserError> {
+        let source = DocumentSource::File(normalize(path.as_ref()));
+        let mut pending = vec![source.clone()];
+        while let Some(next) = pending.pop() {
+            let path = match &next {
+                DocumentSource::File(path) if !self.contains(&next) => path,
+                _ => continue,
+            };
+            let document = parser.parse_file(path)?;
+            for element in document.body_iter() {
+                if let DocumentElement::Import(import) = element {
+                    pending.push(self.resolve_source(&next, import.uri.as_str()));
+                }
+            }
+            self.insert(document);
+        }
+        Ok(source)
+    }
+
+    /// Returns the import cycles that include the document with the given source, with at most
+    /// one cycle for each of its imports. Imports of documents that are not in the workspace are
+    /// ignored.
+    pub fn import_cycles(&self, source: &DocumentSource) -> Vec<ImportCycle> {
+        let document = match self.get(source) {
+            Some(document) => document,
+            None => return Vec::new(),
+        };
+        let mut cycles = Vec::new();
+        for (import, span) in imports(document) {
+            let mut chain = vec![CycleImport {
+                source: source.clone(),
+                uri: import.uri.as_str().to_owned(),
+                span: span.clone(),
+            }];
+            let mut visited = HashSet::new();
+            let target = self.resolve_source(source, import.uri.as_str());
+            if self.find_cycle(source, &target, &mut chain, &mut visited) {
+                cycles.push(ImportCycle { imports: chain })
+            }
+        }
+        cycles
+    }
+
+    /// Searches depth-first for a chain of imports from `current` to `start`, appending the
+    /// imports of the chain to `chain`. Each document is searched at most once.
+    fn find_cycle(
+        &self,
+        start: &DocumentSource,
+        current: &DocumentSource,
+        chain: &mut Vec<CycleImport>,
+        visited: &mut HashSet<DocumentSource>,
+    ) -> bool {
+        if current == start {
+            return true;
+        }
+        if !visited.insert(current.clone()) {
+            return false;
+        }
+        let document = match self.get(current) {
+            Some(document) => document,
+            None => return false,
+        };
+        for (import, span) in imports(document) {
+            chain.push(CycleImport {
+                source: current.clone(),
+                uri: import.uri.as_str().to_owned(),
+                span: span.clone(),
+            });
+            let target = self.resolve_source(current, import.uri.as_str());
+            if self.find_cycle(start, &target, chain, visited) {
+                return true;
+            }
+            chain.pop();
+        }
+        false
+    }
+}
+
+/// Returns the imports of `document` and the spans of the import statements.
+fn imports(document: &Document) -> impl Iterator<Item = (&Import, &Span)> {
+    document
+        .body
+        .iter()
+        .filter_map(|element| match element.deref() {
+            DocumentElement::Import(import) => Some((import, &element.span)),
+            _ => None,
+        })
+}
+
+/// Returns the name of the namespace of `import`, which is either explicit (`as <name>`) or
+/// derived from its URI.
+pub fn namespace_name(import: &Import) -> &str {
+    import.namespace.identifier()
+}
+
+/// Lexically normalizes `path` by removing `.` components and resolving `..` components. Unlike
+/// `fs::canonicalize`, does not access the file system or resolve symbolic links.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(component)
+                }
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    #[test]
+    fn test_import_source() {
+        let from = DocumentSource::File(PathBuf::from("/a/b/main.wdl"));
+        assert_eq!(
+            Workspace::import_source(&from, "lib/tasks.wdl"),
+            DocumentSource::File(PathBuf::from("/a/b/lib/tasks.wdl"))
+        );
+        assert_eq!(
+            Workspace::import_source(&from, "../c/./tasks.wdl"),
+            DocumentSource::File(PathBuf::from("/a/c/tasks.wdl"))
+        );
+        assert_eq!(
+            Workspace::import_source(&from, "https://example.com/x/tasks.wdl"),
+            DocumentSource::Uri("https://example.com/x/tasks.wdl".to_owned())
+        );
+        let from = DocumentSource::Uri("https://example.com/x/main.wdl".to_owned());
+        assert_eq!(
+            Workspace::import_source(&from, "tasks.wdl"),
+            DocumentSource::Uri("https://example.com/x/tasks.wdl".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_resolver_options() {
+        let root = std::env::temp_dir().join(format!("wdl-resolver-{}", std::process::id()));
+        let shared = root.join("shared");
+        fs::create_dir_all(shared.join("lib")).unwrap();
+        fs::write(shared.join("lib").join("common.wdl"), "version 1.1\n").unwrap();
+        let options = ResolverOptions::default()
+            .with_search_path(root.join("vendor"))
+            .with_search_path(&shared)
+            .with_rewrite(
+                "https://raw.githubusercontent.com/org/repo/main/",
+                "/mirror/repo/",
+            );
+        assert_eq!(
+            options.rewrite("https://raw.githubusercontent.com/org/repo/main/tasks.wdl"),
+            "/mirror/repo/tasks.wdl"
+        );
+        assert_eq!(options.rewrite("tasks.wdl"), "tasks.wdl");
+        let workspace = Workspace::with_options(options);
+        let from = DocumentSource::File(PathBuf::from("/w/main.wdl"));
+        assert_eq!(
+            workspace.resolve_source(
+                &from,
+                "https://raw.githubusercontent.com/org/repo/main/tasks.wdl"
+            ),
+            DocumentSource::File(PathBuf::from("/mirror/repo/tasks.wdl"))
+        );
+        assert_eq!(
+            workspace.resolve_source(&from, "lib/common.wdl"),
+            DocumentSource::File(shared.join("lib").join("common.wdl"))
+        );
+        // not found anywhere, so resolved relative to the importer
+        assert_eq!(
+            workspace.resolve_source(&from, "lib/other.wdl"),
+            DocumentSource::File(PathBuf::from("/w/lib/other.wdl"))
+        );
+        let workspace = Workspace::with_options(ResolverOptions {
+            relative_to_importer: false,
+            ..Default::default()
+        });
+        assert_eq!(
+            workspace.resolve_source(&from, "lib/other.wdl"),
+            DocumentSource::File(PathBuf::from("lib/other.wdl"))
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(feature = "pest")]
+    #[test]
+    fn test_import_cycles() {
+        use crate::parsers::PestParser;
+
+        let mut parser = PestParser::new();
+        let mut workspace = Workspace::new();
+        for (path, text) in [
+            (
+                "/w/a.wdl",
+                "version 1.1\nimport \"b.wdl\"\nimport \"c.wdl\"\n",
+            ),
+            ("/w/b.wdl", "version 1.1\nimport \"lib/d.wdl\"\n"),
+            ("/w/c.wdl", "version 1.1\nstruct C { Int x }\n"),
+            ("/w/lib/d.wdl", "version 1.1\nimport \"../a.wdl\"\n"),
+            ("/w/e.wdl", "version 1.1\nimport \"e.wdl\"\n"),
+        ] {
+            workspace.insert(
+                parser
+                    .parse_text(text, DocumentSource::File(PathBuf::from(path)))
+                    .unwrap(),
+            );
+        }
+        let sources: Vec<String> = workspace
+            .documents()
+            .map(|document| document.source.to_string())
+            .collect();
+        assert_eq!(
+            sources,
+            vec![
+                "/w/a.wdl",
+                "/w/b.wdl",
+                "/w/c.wdl",
+                "/w/e.wdl",
+                "/w/lib/d.wdl"
+            ]
+        );
+        let cycles = workspace.import_cycles(&DocumentSource::File(PathBuf::from("/w/a.wdl")));
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(
+            cycles[0].to_string(),
+            "/w/a.wdl -> /w/b.wdl -> /w/lib/d.wdl -> /w/a.wdl"
+        );
+        let uris: Vec<&str> = cycles[0]
+            .imports
+            .iter()
+            .map(|import| import.uri.as_str())
+            .collect();
+        assert_eq!(uris, vec!["b.wdl", "lib/d.wdl", "../a.wdl"]);
+        assert_eq!(cycles[0].imports[0].span.start.line, 1);
+        let cycles = workspace.import_cycles(&DocumentSource::File(PathBuf::from("/w/e.wdl")));
+        assert_eq!(cycles[0].to_string(), "/w/e.wdl -> /w/e.wdl");
+        assert!(workspace
+            .import_cycles(&DocumentSource::File(PathBuf::from("/w/c.wdl")))
+            .is_empty());
+    }
+}